@@ -0,0 +1,50 @@
+use std::sync::Arc;
+use std::sync::atomic::{AtomicBool, Ordering};
+
+use crate::error::{Result, Error, ErrorKind};
+
+
+/// Error message for cancelled operation.
+const OPERATION_CANCELLED: &str = "Operation was cancelled";
+
+
+/// Token, that allows to cancel a long-running operation.
+/// 
+/// Clones of a token share the same state, so one clone can be
+/// passed to an operation, and another one can be used to cancel
+/// it from another thread. Operations check the token only at safe
+/// points, i.e. where stopping leaves no partial state behind, 
+/// therefore cancellation is not immediate.
+#[derive(Clone, Default, Debug)]
+pub struct CancellationToken {
+    /// Cancellation flag shared between clones
+    cancelled: Arc<AtomicBool>,
+}
+
+
+impl CancellationToken {
+    /// Creates a token, that is not cancelled.
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Requests cancellation of all operations, that use this token.
+    pub fn cancel(&self) {
+        self.cancelled
+            .store(true, Ordering::SeqCst);
+    }
+
+    /// Checks if cancellation was requested.
+    pub fn is_cancelled(&self) -> bool {
+        self.cancelled
+            .load(Ordering::SeqCst)
+    }
+
+    /// Returns an error if cancellation was requested.
+    pub fn check(&self) -> Result<()> {
+        match self.is_cancelled() {
+            true => Err(Error::from_message(OPERATION_CANCELLED).with_kind(ErrorKind::Cancelled)),
+            false => Ok(())
+        }
+    }
+}
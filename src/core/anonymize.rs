@@ -0,0 +1,225 @@
+use std::collections::HashMap;
+use std::collections::hash_map::RandomState;
+use std::hash::BuildHasher;
+
+use crate::storage::{Account, Category, Transaction, Plan, Loan, Holding, PricePoint, Person, CategoryGroup, Id};
+use crate::storage::{CustomFields, CustomValue};
+use super::changelog::Changelog;
+use super::invariants::LiveItems;
+
+
+/// Replaces sensitive values of items with meaningless ones
+/// keeping structure of a budget intact.
+///
+/// Texts are replaced with keyed hashes, hence equal texts remain
+/// equal within one export, but cannot be recovered or matched
+/// against other exports. Identifiers, timestamps and kinds of
+/// items are kept as is.
+pub(crate) struct Anonymizer {
+    /// Random key of text hashes
+    key: RandomState,
+
+    /// Factor to multiply amounts by (if any)
+    amount_scale: Option<f64>,
+}
+
+
+impl Anonymizer {
+    /// Creates an anonymizer with a fresh random key.
+    ///
+    /// * `amount_scale` - factor to multiply amounts by (`None` keeps them)
+    pub(crate) fn new(amount_scale: Option<f64>) -> Self {
+        Anonymizer {
+            key: RandomState::new(),
+            amount_scale
+        }
+    }
+
+    /// Builds a changelog, that adds anonymized copies of all items.
+    ///
+    /// * `items` - live items of a budget
+    pub(crate) fn anonymize(&self, items: LiveItems) -> Changelog {
+        let transactions: Vec<Transaction> = items.transactions
+            .into_iter()
+            .map(|transaction| self.transaction(transaction))
+            .collect();
+
+        //
+        // Balances are recalculated from scaled amounts instead of
+        // being scaled themselves, otherwise rounding breaks them
+        //
+
+        let mut sums: HashMap<Id, isize> = HashMap::new();
+        for transaction in &transactions {
+            *sums.entry(transaction.account_id).or_default() += transaction.amount;
+        }
+
+        let mut changelog = Changelog::new();
+        changelog.accounts.added = items.accounts
+            .into_iter()
+            .map(|account| self.account(account, &sums))
+            .collect();
+
+        changelog.categories.added = items.categories
+            .into_iter()
+            .map(|category| self.category(category))
+            .collect();
+
+        changelog.transactions.added = transactions;
+
+        changelog.plans.added = items.plans
+            .into_iter()
+            .map(|plan| self.plan(plan))
+            .collect();
+
+        changelog.loans.added = items.loans
+            .into_iter()
+            .map(|loan| self.loan(loan))
+            .collect();
+
+        changelog.holdings.added = items.holdings
+            .into_iter()
+            .map(|holding| self.holding(holding))
+            .collect();
+
+        changelog.prices.added = items.prices
+            .into_iter()
+            .map(|price| self.price(price))
+            .collect();
+
+        changelog.persons.added = items.persons
+            .into_iter()
+            .map(|person| self.person(person))
+            .collect();
+
+        changelog.category_groups.added = items.category_groups
+            .into_iter()
+            .map(|group| self.category_group(group))
+            .collect();
+
+        changelog
+    }
+}
+
+
+impl Anonymizer {
+    fn account(&self, account: Account, sums: &HashMap<Id, isize>) -> Account {
+        let initial_balance = self.amount(account.initial_balance);
+        let transactions_sum = account.id
+            .and_then(|id| sums.get(&id).copied())
+            .unwrap_or_default();
+
+        Account {
+            name: self.text(&account.name),
+            balance: initial_balance + transactions_sum,
+            initial_balance,
+            note: self.text(&account.note),
+            custom_fields: self.custom_fields(&account.custom_fields),
+            ..account
+        }
+    }
+
+    fn category(&self, category: Category) -> Category {
+        Category {
+            name: self.text(&category.name),
+            note: self.text(&category.note),
+            custom_fields: self.custom_fields(&category.custom_fields),
+            ..category
+        }
+    }
+
+    fn transaction(&self, transaction: Transaction) -> Transaction {
+        Transaction {
+            description: self.text(&transaction.description),
+            amount: self.amount(transaction.amount),
+            note: self.text(&transaction.note),
+            custom_fields: self.custom_fields(&transaction.custom_fields),
+            ..transaction
+        }
+    }
+
+    fn plan(&self, plan: Plan) -> Plan {
+        Plan {
+            name: self.text(&plan.name),
+            amount_limit: self.amount(plan.amount_limit),
+            note: self.text(&plan.note),
+            custom_fields: self.custom_fields(&plan.custom_fields),
+            ..plan
+        }
+    }
+
+    fn loan(&self, loan: Loan) -> Loan {
+        Loan {
+            name: self.text(&loan.name),
+            principal: self.amount(loan.principal),
+            note: self.text(&loan.note),
+            custom_fields: self.custom_fields(&loan.custom_fields),
+            ..loan
+        }
+    }
+
+    fn holding(&self, holding: Holding) -> Holding {
+        Holding {
+            symbol: self.text(&holding.symbol),
+            note: self.text(&holding.note),
+            custom_fields: self.custom_fields(&holding.custom_fields),
+            ..holding
+        }
+    }
+
+    fn price(&self, price: PricePoint) -> PricePoint {
+        PricePoint {
+            symbol: self.text(&price.symbol),
+            price: self.amount(price.price),
+            ..price
+        }
+    }
+
+    fn person(&self, person: Person) -> Person {
+        Person {
+            name: self.text(&person.name),
+            ..person
+        }
+    }
+
+    fn category_group(&self, group: CategoryGroup) -> CategoryGroup {
+        CategoryGroup {
+            name: self.text(&group.name),
+            ..group
+        }
+    }
+
+    fn custom_fields(&self, fields: &CustomFields) -> CustomFields {
+        fields
+            .iter()
+            .map(|(key, value)| {
+                let value = match value {
+                    CustomValue::Text(text) => CustomValue::Text(self.text(text)),
+                    CustomValue::Integer(integer) => CustomValue::Integer(self.amount(*integer)),
+                    other => other.clone()
+                };
+
+                (self.text(key), value)
+            })
+            .collect()
+    }
+
+    fn text(&self, text: &str) -> String {
+        //
+        // Empty texts are kept empty to preserve the shape of data
+        //
+
+        if text.is_empty() {
+            return String::new();
+        }
+
+        format!("{:016x}", self.key.hash_one(text))
+    }
+
+    fn amount(&self, amount: isize) -> isize {
+        match self.amount_scale {
+            Some(scale) => (amount as f64 * scale).round() as isize,
+            None => amount
+        }
+    }
+}
@@ -0,0 +1,255 @@
+use std::future::Future;
+use std::pin::Pin;
+use std::sync::{Arc, Mutex, PoisonError, mpsc};
+use std::task::{Context as TaskContext, Poll, Waker};
+use std::thread::JoinHandle;
+
+use crate::cancel::CancellationToken;
+use crate::progress::Progress;
+use crate::crypto::CryptoEngine;
+use crate::error::{Result, Error, ErrorKind};
+use crate::storage::DataStorage;
+use crate::sync::SyncEngine;
+use super::budget::Budget;
+use super::{WORKER_STOPPED, WORKER_PANICKED};
+
+
+/// Job executed by budget's worker.
+type Job<Ce, Se, St> = Box<dyn FnOnce(&Budget<Ce, Se, St>) + Send>;
+
+
+/// Shared state of a pending job.
+struct Slot<R> {
+    /// Result of the job, if it has been completed
+    result: Option<Result<R>>,
+
+    /// Waker of a task, that awaits the result
+    waker: Option<Waker>,
+}
+
+
+impl<R> Slot<R> {
+    fn complete(slot: &Mutex<Self>, result: Result<R>) {
+        let mut slot = slot.lock()
+            .unwrap_or_else(PoisonError::into_inner);
+
+        slot.result = Some(result);
+
+        if let Some(waker) = slot.waker.take() {
+            waker.wake();
+        }
+    }
+}
+
+
+/// Future, that resolves to a result of a job executed by [`AsyncBudget`].
+///
+/// It does not depend on any particular async runtime.
+pub struct BudgetFuture<R> {
+    /// State shared with the worker
+    slot: Arc<Mutex<Slot<R>>>,
+}
+
+
+impl<R> BudgetFuture<R> {
+    fn new() -> Self {
+        BudgetFuture {
+            slot: Arc::new(Mutex::new(Slot { result: None, waker: None }))
+        }
+    }
+
+    fn ready(result: Result<R>) -> Self {
+        let future = Self::new();
+        Slot::complete(&future.slot, result);
+
+        future
+    }
+}
+
+
+impl<R> Future for BudgetFuture<R> {
+    type Output = Result<R>;
+
+    fn poll(self: Pin<&mut Self>, cx: &mut TaskContext<'_>) -> Poll<Self::Output> {
+        let mut slot = self.slot
+            .lock()
+            .unwrap_or_else(PoisonError::into_inner);
+
+        match slot.result.take() {
+            Some(result) => Poll::Ready(result),
+            None => {
+                slot.waker = Some(cx.waker().clone());
+                Poll::Pending
+            }
+        }
+    }
+}
+
+
+/// Asynchronous wrapper around [`Budget`].
+///
+/// Budget is owned by a dedicated worker thread, that executes
+/// submitted jobs one by one. Long operations (synchronization,
+/// encryption of many items) therefore do not block a caller, e.g.
+/// GUI event loop. Returned futures can be awaited in any runtime.
+///
+/// Budget is created inside the worker, hence its components are
+/// not required to be [`Send`].
+pub struct AsyncBudget<Ce, Se, St>
+where
+    Ce: CryptoEngine + 'static,
+    Se: SyncEngine + 'static,
+    St: DataStorage + 'static
+{
+    /// Queue of jobs for the worker
+    jobs: Option<mpsc::Sender<Job<Ce, Se, St>>>,
+
+    /// Worker thread handle
+    worker: Option<JoinHandle<()>>,
+}
+
+
+impl<Ce, Se, St> AsyncBudget<Ce, Se, St>
+where
+    Ce: CryptoEngine + 'static,
+    Se: SyncEngine + 'static,
+    St: DataStorage + 'static
+{
+    /// Starts a worker and opens a budget in it.
+    ///
+    /// * `open` - function, that opens a budget (e.g. [`super::DefaultBudget::open`])
+    pub async fn open<F>(open: F) -> Result<Self>
+    where
+        F: FnOnce() -> Result<Budget<Ce, Se, St>> + Send + 'static
+    {
+        let (jobs, queue) = mpsc::channel::<Job<Ce, Se, St>>();
+        let opened = BudgetFuture::new();
+        let opened_slot = opened.slot.clone();
+
+        let worker = std::thread::spawn(move || {
+            let budget = match Self::catch_panic(open) {
+                Ok(budget) => {
+                    Slot::complete(&opened_slot, Ok(()));
+                    budget
+                },
+                Err(error) => {
+                    Slot::complete(&opened_slot, Err(error));
+                    return;
+                }
+            };
+
+            //
+            // Jobs are processed until the wrapper is dropped
+            //
+
+            for job in queue {
+                job(&budget);
+            }
+        });
+
+        let budget = AsyncBudget {
+            jobs: Some(jobs),
+            worker: Some(worker)
+        };
+
+        opened.await?;
+        Ok(budget)
+    }
+
+    /// Executes a job with the budget in the worker.
+    ///
+    /// Jobs are executed in order of submission.
+    ///
+    /// * `job` - function to execute
+    pub fn run<F, R>(&self, job: F) -> BudgetFuture<R>
+    where
+        F: FnOnce(&Budget<Ce, Se, St>) -> Result<R> + Send + 'static,
+        R: Send + 'static
+    {
+        let future = BudgetFuture::new();
+        let slot = future.slot.clone();
+
+        let job: Job<Ce, Se, St> = Box::new(move |budget| {
+            Slot::complete(&slot, Self::catch_panic(|| job(budget)));
+        });
+
+        let sent = self.jobs
+            .as_ref()
+            .map(|jobs| jobs.send(job).is_ok())
+            .unwrap_or(false);
+
+        match sent {
+            true => future,
+            false => BudgetFuture::ready(Err(Error::from_message(WORKER_STOPPED).with_kind(ErrorKind::Other)))
+        }
+    }
+
+    /// Performs synchronization with remote instances
+    /// (see [`Budget::perform_sync`]).
+    ///
+    /// * `auth` - authentication information for synchronization
+    pub fn perform_sync(&self, auth: Vec<u8>) -> BudgetFuture<()> {
+        self.run(move |budget| budget.perform_sync(&auth))
+    }
+
+    /// Performs synchronization with remote instances, that can be
+    /// cancelled (see [`Budget::perform_sync_cancellable`]).
+    ///
+    /// * `auth` - authentication information for synchronization
+    /// * `cancel` - token to cancel synchronization with
+    pub fn perform_sync_cancellable(&self, auth: Vec<u8>, cancel: CancellationToken) -> BudgetFuture<()> {
+        self.run(move |budget| budget.perform_sync_cancellable(&auth, &cancel))
+    }
+
+    /// Performs synchronization with remote instances, that can be
+    /// cancelled and reports its progress (see [`Budget::perform_sync_with`]).
+    ///
+    /// Progress is reported from the worker thread.
+    ///
+    /// * `auth` - authentication information for synchronization
+    /// * `cancel` - token to cancel synchronization with
+    /// * `progress` - receiver of progress reports
+    pub fn perform_sync_with<P>(&self, auth: Vec<u8>, cancel: CancellationToken, progress: P) -> BudgetFuture<()>
+    where
+        P: Progress + Send + 'static
+    {
+        self.run(move |budget| budget.perform_sync_with(&auth, &cancel, &progress))
+    }
+}
+
+
+impl<Ce, Se, St> AsyncBudget<Ce, Se, St>
+where
+    Ce: CryptoEngine + 'static,
+    Se: SyncEngine + 'static,
+    St: DataStorage + 'static
+{
+    fn catch_panic<F, R>(func: F) -> Result<R>
+    where
+        F: FnOnce() -> Result<R>
+    {
+        std::panic::catch_unwind(std::panic::AssertUnwindSafe(func))
+            .unwrap_or_else(|_| Err(Error::from_message(WORKER_PANICKED).with_kind(ErrorKind::Other)))
+    }
+}
+
+
+impl<Ce, Se, St> Drop for AsyncBudget<Ce, Se, St>
+where
+    Ce: CryptoEngine + 'static,
+    Se: SyncEngine + 'static,
+    St: DataStorage + 'static
+{
+    fn drop(&mut self) {
+        //
+        // Closing the queue stops the worker after all submitted
+        // jobs are completed
+        //
+
+        drop(self.jobs.take());
+
+        if let Some(worker) = self.worker.take() {
+            let _ = worker.join();
+        }
+    }
+}
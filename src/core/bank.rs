@@ -0,0 +1,64 @@
+use crate::datetime::Timestamp;
+use crate::error::Result;
+
+
+/// Account in a bank as it is reported by a [`BankProvider`].
+#[derive(Clone)]
+pub struct BankAccount {
+    /// Identifier of the account in the bank
+    pub id: String,
+
+    /// Name of the account (e.g. product name or masked number)
+    pub name: String,
+
+    /// Current balance of the account (if the bank reports it)
+    pub balance: Option<isize>,
+}
+
+
+/// Transaction as it is reported by a [`BankProvider`].
+#[derive(Clone)]
+pub struct BankTransaction {
+    /// Identifier of the transaction in the bank, MUST be stable,
+    /// so that repeated imports do not duplicate transactions
+    pub id: String,
+
+    /// Identifier of an account in the bank
+    pub account_id: String,
+
+    /// Date of the transaction
+    pub timestamp: Timestamp,
+
+    /// Description of the transaction (e.g. payee)
+    pub description: String,
+
+    /// Amount of money affected, negative for spendings
+    pub amount: isize,
+}
+
+
+/// Connector to a bank or an aggregator of banks (e.g. PSD2 or
+/// Plaid-style APIs).
+///
+/// Connectors are implemented by external crates, the library maps
+/// fetched transactions to accounts of a budget and deduplicates them
+/// (see [`crate::core::Budget::preview_bank_import`]).
+pub trait BankProvider {
+    /// Name of the provider. It is used as a source of imported items
+    /// (see [`crate::core::external_id`]), hence it MUST NOT change.
+    fn name(&self) -> &str;
+
+    /// Authenticates in the bank.
+    ///
+    /// * `auth` - provider-specific authentication data (e.g. token)
+    fn authenticate(&mut self, auth: &[u8]) -> Result<()>;
+
+    /// Returns accounts available to the authenticated user.
+    fn accounts(&self) -> Result<Vec<BankAccount>>;
+
+    /// Returns transactions of an account dated since a given point.
+    ///
+    /// * `account_id` - identifier of an account in the bank
+    /// * `since` - point in time to return transactions since
+    fn transactions_since(&self, account_id: &str, since: Timestamp) -> Result<Vec<BankTransaction>>;
+}
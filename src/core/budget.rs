@@ -1,15 +1,41 @@
-use std::array::TryFromSliceError;
-use std::io::Write;
-
-use crate::crypto::{CryptoEngine, CryptoBuffer, Kdf};
-use crate::error::{Result, Error};
-use crate::sync::{Syncable, SyncEngine};
-use crate::datetime::{Clock, Timestamp, JANUARY_1970};
-use crate::storage::{EncryptedTransaction, EncryptedAccount, EncryptedCategory, EncryptedPlan, MetaInfo};
-use crate::storage::{DataStorage, Id, Transaction, Account, Category, Plan, CategoryType};
-use super::config::{Config, InstanceId};
-use super::changelog::Changelog;
-use super::MALFORMED_TIMESTAMP;
+use std::collections::{HashMap, HashSet};
+use std::io::{Read, Write};
+
+use parking_lot::Mutex;
+
+use crate::cancel::CancellationToken;
+use crate::trace::trace_debug;
+use crate::location::Location;
+use crate::progress::{Progress, NoProgress, Phase};
+use crate::period::{Period, TimeZonePolicy, MonthPeriod};
+use crate::reports::{CategoryBreakdown, NetWorthSeries, BudgetStatus, ForecastPoint, PeriodComparison, FlowReport};
+use crate::reports::{self, RecurringCandidate, Anomaly, Statement, CalendarDay, Alert, SpendProjection};
+use crate::reports::{BaselineExpenses, SpenderBreakdown, GroupBreakdown, ReportCache};
+use crate::crypto::{CryptoEngine, CryptoBuffer, Kdf, KeyIdentifier, RecoveryKey, Prng};
+use crate::error::{Result, Error, ErrorKind, Context};
+use crate::sync::{Syncable, SyncEngine, SyncHooks, SyncStatus};
+use crate::datetime::{Clock, Timestamp, TxnDate, JANUARY_1970, FIRST_AFTER_JANUARY_1970};
+use crate::storage::{EncryptedTransaction, TransactionSummary, EncryptedAccount, EncryptedCategory, EncryptedPlan, EncryptedLoan, MetaInfo};
+use crate::storage::{EncryptedHolding, EncryptedPricePoint, Holding, PricePoint, StorageEvent, EntityKind, EncryptedSettings};
+use crate::storage::{EncryptedPerson, Person, EncryptedCategoryGroup, CategoryGroup};
+use crate::storage::{DataStorage, Id, Transaction, Account, Category, Plan, Loan, CategoryType, TransactionKind, CustomFields};
+use crate::storage::{JournalPosition, SnapshotId, Validate, ValidationError, Merge, MergeKind, CryptoRecord, RecoveryRecord, ItemCount, IndexedField};
+use super::config::{Config, ConfigKey, InstanceId};
+use super::changelog::{Changelog, Scope};
+use super::search::{SearchResults, Searchable};
+use super::settings::{Settings, SettingsLayer, SharedSettings};
+use super::invariants::{self, Invariant, Violation, LiveItems};
+use super::anonymize::Anonymizer;
+use super::external;
+use super::receipt;
+use super::import::{ImportPreview, ProposedTransaction, ImportStatus, PayeeHistory, CategoryPrediction};
+use super::categorize::Categorizer;
+use super::lazy::LazyTransaction;
+use super::export::{ExportFormat, TransactionWriter};
+use super::bank::BankProvider;
+use super::geo::{self, GeoLocation, BoundingBox};
+use super::{MALFORMED_TIMESTAMP, ENGINE_MISMATCH, CRYPTO_MISMATCH, KEY_MISMATCH, INVARIANT_VIOLATED, SNAPSHOT_IS_ACTIVE, INVALID_ITEM, INCOMPATIBLE_MERGE, CORRUPTED_REMOTE_CHANGELOG};
+use super::{MISSING_RECOVERY_KEY, MALFORMED_EMERGENCY_EXPORT, WRONG_RECOVERY_KEY, UNKNOWN_CATEGORY_GROUP};
 
 
 /// Name of income transfer category.
@@ -24,6 +50,54 @@ const TRANSFER_OUTCOME_CAT_NAME: &str = "Transfer (outcome)";
 /// Name of outcome transfer transaction.
 const TRANSFER_OUTCOME_DESCRIPTION: &str = "Transfer (outcome) -->";
 
+/// Name of income balance adjustment category.
+const ADJUSTMENT_INCOME_CAT_NAME: &str = "Balance adjustment (income)";
+
+/// Name of outcome balance adjustment category.
+const ADJUSTMENT_OUTCOME_CAT_NAME: &str = "Balance adjustment (outcome)";
+
+/// Name of opening balance transaction.
+const OPENING_BALANCE_DESCRIPTION: &str = "Opening balance";
+
+/// Name of balance adjustment transaction.
+const ADJUSTMENT_DESCRIPTION: &str = "Balance adjustment";
+
+/// Prefix of loan principal payment transaction name.
+const LOAN_PRINCIPAL_DESCRIPTION: &str = "Loan principal";
+
+/// Prefix of loan interest payment transaction name.
+const LOAN_INTEREST_DESCRIPTION: &str = "Loan interest";
+
+/// Interval, after which snapshot of full state in synchronization
+/// repository is refreshed.
+const STATE_SNAPSHOT_INTERVAL: chrono::Duration = chrono::Duration::days(7);
+
+/// Size of header of a diff: timestamp and instance identifier.
+const DIFF_HEADER_SIZE: usize = std::mem::size_of::<i64>() + 16;
+
+/// Range of dates, that transactions are exported by: it bounds
+/// memory, that is used by exports.
+const EXPORT_WINDOW: chrono::Duration = chrono::Duration::days(31);
+
+/// Magic bytes, that emergency exports start with.
+const EMERGENCY_MAGIC: &[u8] = b"bdgt-emergency-v1";
+
+/// Size of salt, that export key is derived from recovery key with.
+const RECOVERY_SALT_SIZE: usize = 16;
+
+/// Size of the smallest encrypted changelog: nonce and tag of cipher.
+const MIN_EMERGENCY_CIPHERTEXT_SIZE: usize = 12 + 16;
+
+/// Number of complete months, that forecasts are based on.
+const FORECAST_HISTORY_MONTHS: usize = 3;
+
+/// Number of previous periods, that spending projections are based on.
+const PROJECTION_HISTORY_PERIODS: usize = 3;
+
+/// Version of the local index of transactions. Index is rebuilt,
+/// if it was built with an older version.
+const INDEX_VERSION: u32 = 2;
+
 
 /// Budget manager.
 pub struct Budget<Ce, Se, St>
@@ -46,6 +120,12 @@ where
 
     /// Key used to encrypt and decrypt sensitive data.
     key: Ce::Key,
+
+    /// Hooks, that run around synchronization.
+    hooks: SyncHooks,
+
+    /// On-disk cache of reports (if enabled).
+    report_cache: Mutex<Option<ReportCache>>,
 }
 
 
@@ -61,15 +141,49 @@ where
     /// * `storage` - storage used to store data
     /// * `config` - app's configuration
     pub fn new(crypto_engine: Ce, sync_engine: Se, storage: St, config: Config<Ce>) -> Result<Self> {
+        //
+        // Key can be looked up only by the engine it was created for
+        //
+
+        if let Some(engine) = config.engine() {
+            if engine != crypto_engine.engine() {
+                return Err(Error::from_message_with_extra(ENGINE_MISMATCH,
+                    format!("expected: {}, actual: {}", engine, crypto_engine.engine())).with_kind(ErrorKind::Config));
+            }
+        }
+
+        //
+        // Data protected by one engine is garbage for another one,
+        // so the engine is recorded on the first open and checked later
+        //
+
+        let actual = CryptoRecord {
+            engine: crypto_engine.engine().to_owned(),
+            algorithm: crypto_engine.algorithm().to_owned()
+        };
+
+        match storage.crypto_record()? {
+            Some(stored) if stored != actual => {
+                return Err(Error::from_message_with_extra(CRYPTO_MISMATCH,
+                    format!("stored: {}, actual: {}", stored, actual)).with_kind(ErrorKind::CryptoFailure));
+            },
+            Some(_) => (),
+            None => storage.set_crypto_record(&actual)?
+        }
+
         let key = crypto_engine
             .lookup_key(config.key_id())?;
 
+        Self::check_key_pin(&crypto_engine, &storage, &key, config.key_id())?;
+
         Ok(Budget { 
             crypto_engine: crypto_engine, 
             sync_engine: sync_engine,
             storage: storage,
             config: config,
             key: key,
+            hooks: SyncHooks::new(),
+            report_cache: Mutex::new(None),
         })
     }
 
@@ -85,6 +199,24 @@ where
             .version()
     }
 
+    /// Algorithm, that protects data (see [`CryptoEngine::algorithm`]).
+    pub fn algorithm(&self) -> &str {
+        self.crypto_engine
+            .algorithm()
+    }
+
+    /// Underlying cryptographic engine.
+    #[cfg(feature = "native")]
+    pub(crate) fn crypto_engine(&self) -> &Ce {
+        &self.crypto_engine
+    }
+
+    /// Key used to encrypt and decrypt sensitive data.
+    #[cfg(feature = "native")]
+    pub(crate) fn key(&self) -> &Ce::Key {
+        &self.key
+    }
+
     /// Encryption key identifier.
     pub fn key_id(&self) -> &Ce::KeyId {
         self.config
@@ -97,6 +229,240 @@ where
             .instance_id()
     }
 
+    /// Instance configuration.
+    pub fn config(&self) -> &Config<Ce> {
+        &self.config
+    }
+
+    /// Effective settings: defaults overridden by shared settings, 
+    /// that are overridden by local ones.
+    pub fn settings(&self) -> Result<Settings> {
+        Ok(Settings::from_layers(self.config.instance_name(), 
+            &self.shared_settings()?, self.config.local_settings()))
+    }
+
+    /// Settings shared between all synchronized instances.
+    pub fn shared_settings(&self) -> Result<SettingsLayer> {
+        Ok(self.stored_shared_settings()?
+            .map(|settings| settings.layer)
+            .unwrap_or_default())
+    }
+
+    /// Replace settings shared between all synchronized instances.
+    /// 
+    /// * `layer` - new shared settings
+    pub fn set_shared_settings(&self, layer: &SettingsLayer) -> Result<()> {
+        layer.validate()?;
+
+        let mut meta_info = MetaInfo::new(None, Some(Clock::now()), None);
+        meta_info.set_origin_if_absent(self.instance_id());
+
+        let settings = SharedSettings { 
+            layer: layer.clone(), 
+            meta_info 
+        };
+
+        self.storage
+            .set_settings(self.encrypt_settings(&settings)?)
+    }
+
+    /// Add a person within a household, that may be set as a spender
+    /// of transactions (see [`Transaction::spender_id`]).
+    /// 
+    /// Persons are synchronized between all instances like any other
+    /// item. Returns identifier of the added person.
+    /// 
+    /// * `name` - name of the person
+    pub fn add_person(&self, name: &str) -> Result<Id> {
+        let id = uuid::Uuid::new_v4().into_bytes();
+        let person = Person {
+            id: Some(id),
+            name: name.to_owned(),
+            meta_info: MetaInfo::new(Some(Clock::now()), None, None)
+        };
+
+        Self::ensure_valid(&person)?;
+        self.insert_person(&person)?;
+
+        Ok(id)
+    }
+
+    /// Rename a person within a household.
+    /// 
+    /// * `person_id` - identifier of the person
+    /// * `name` - new name of the person
+    pub fn rename_person(&self, person_id: Id, name: &str) -> Result<()> {
+        let stored = self.person(person_id)?;
+        let person = Person {
+            name: name.to_owned(),
+            meta_info: MetaInfo { changed_timestamp: None, ..stored.meta_info },
+            ..stored
+        };
+
+        Self::ensure_valid(&person)?;
+        self.replace_person(&person)
+    }
+
+    /// Remove a person within a household.
+    /// 
+    /// Transactions of the person are not changed, but they are
+    /// reported as ones without a known spender.
+    /// 
+    /// * `person_id` - identifier of the person
+    pub fn remove_person(&self, person_id: Id) -> Result<()> {
+        self.person(person_id)?;
+        self.storage.remove_person(person_id, Clock::now())
+    }
+
+    /// Return person with a given identifier.
+    /// 
+    /// * `person_id` - identifier to return record for
+    pub fn person(&self, person_id: Id) -> Result<Person> {
+        self.decrypt_person(&self.storage.person(person_id)?)
+    }
+
+    /// Return all persons within a household sorted by creation timestamp.
+    pub fn persons(&self) -> Result<Vec<Person>> {
+        self.decrypt_persons(&self.storage.persons()?)
+    }
+
+    /// Add a group of categories for reporting (e.g. needs, wants
+    /// or savings). Categories are added into the group with
+    /// [`Budget::set_category_group`].
+    /// 
+    /// Groups are synchronized between all instances like any other
+    /// item. Returns identifier of the added group.
+    /// 
+    /// * `name` - name of the group
+    pub fn add_category_group(&self, name: &str) -> Result<Id> {
+        let id = uuid::Uuid::new_v4().into_bytes();
+        let group = CategoryGroup {
+            id: Some(id),
+            name: name.to_owned(),
+            categories: Vec::new(),
+            meta_info: MetaInfo::new(Some(Clock::now()), None, None)
+        };
+
+        Self::ensure_valid(&group)?;
+        self.insert_category_group(&group)?;
+
+        Ok(id)
+    }
+
+    /// Rename a group of categories.
+    /// 
+    /// * `group_id` - identifier of the group
+    /// * `name` - new name of the group
+    pub fn rename_category_group(&self, group_id: Id, name: &str) -> Result<()> {
+        let stored = self.category_group(group_id)?;
+        let group = CategoryGroup {
+            name: name.to_owned(),
+            meta_info: MetaInfo { changed_timestamp: None, ..stored.meta_info },
+            ..stored
+        };
+
+        Self::ensure_valid(&group)?;
+        self.replace_category_group(&group)
+    }
+
+    /// Remove a group of categories. Categories themselves are
+    /// not changed, they just become ungrouped.
+    /// 
+    /// * `group_id` - identifier of the group
+    pub fn remove_category_group(&self, group_id: Id) -> Result<()> {
+        self.category_group(group_id)?;
+        self.storage.remove_category_group(group_id, Clock::now())
+    }
+
+    /// Return group of categories with a given identifier.
+    /// 
+    /// * `group_id` - identifier to return record for
+    pub fn category_group(&self, group_id: Id) -> Result<CategoryGroup> {
+        let group = self.storage
+            .category_group(group_id)
+            .map_err(|error| match error.kind() {
+                ErrorKind::NotFound => Error::from_message(UNKNOWN_CATEGORY_GROUP).with_kind(ErrorKind::NotFound),
+                _ => error
+            })?;
+
+        self.decrypt_category_group(&group)
+    }
+
+    /// Return all groups of categories sorted by creation timestamp.
+    pub fn category_groups(&self) -> Result<Vec<CategoryGroup>> {
+        self.decrypt_category_groups(&self.storage.category_groups()?)
+    }
+
+    /// Move a category into a group or out of any group.
+    /// 
+    /// A category belongs to at most one group, hence it is
+    /// removed from its previous group (if any). Only groups,
+    /// that are actually changed, are updated.
+    /// 
+    /// * `category_id` - identifier of the category
+    /// * `group_id` - identifier of the group (`None` ungroups the category)
+    pub fn set_category_group(&self, category_id: Id, group_id: Option<Id>) -> Result<()> {
+        //
+        // Make sure, that the category and the group exist
+        //
+
+        self.category(category_id)?;
+
+        if let Some(group_id) = group_id {
+            self.category_group(group_id)?;
+        }
+
+        self.atomically(|| {
+            for mut group in self.category_groups()? {
+                let is_target = group.id == group_id;
+                let is_member = group.categories.contains(&category_id);
+
+                if is_target == is_member {
+                    continue;
+                }
+
+                match is_target {
+                    true => group.categories.push(category_id),
+                    false => group.categories.retain(|category| *category != category_id)
+                }
+
+                group.meta_info.changed_timestamp = None;
+                self.replace_category_group(&group)?;
+            }
+
+            Ok(())
+        })
+    }
+
+    /// Re-reads configuration if it was changed on disk.
+    /// 
+    /// If key identifier was changed, then the new key is looked up.
+    /// The key must be the one, that the budget is pinned to, otherwise
+    /// the current key remains in use and an error is returned.
+    /// Returns a list of changed configuration entries.
+    pub fn reload_config(&mut self) -> Result<Vec<ConfigKey>> {
+        let changes = self.config
+            .watch()?;
+
+        if changes.contains(&ConfigKey::KeyId) {
+            let key = self.crypto_engine
+                .lookup_key(self.config.key_id())?;
+
+            Self::check_key_pin(&self.crypto_engine, &self.storage, &key, self.config.key_id())?;
+            self.key = key;
+        }
+
+        Ok(changes)
+    }
+
+    /// Decrypted sensitive configuration value.
+    /// 
+    /// * `name` - name of the value
+    pub fn secret(&self, name: &str) -> Result<Option<CryptoBuffer>> {
+        self.config
+            .secret(&self.crypto_engine, &self.key, name)
+    }
+
     /// Initializes budget instance for the first time.
     pub fn initialize(&self) -> Result<()> {
         //
@@ -108,6 +474,8 @@ where
             id: Some(St::TRANSFER_INCOME_ID), 
             name: TRANSFER_INCOME_CAT_NAME.to_owned(), 
             category_type: CategoryType::Income,
+            note: String::new(),
+            custom_fields: CustomFields::new(),
             meta_info: MetaInfo::new(Some(*JANUARY_1970), None, None)
         })?;
 
@@ -115,44 +483,91 @@ where
             id: Some(St::TRANSFER_OUTCOME_ID), 
             name: TRANSFER_OUTCOME_CAT_NAME.to_owned(),
             category_type: CategoryType::Outcome,
+            note: String::new(),
+            custom_fields: CustomFields::new(),
             meta_info: MetaInfo::new(Some(*JANUARY_1970), None, None)
-        })
-    }
+        })?;
+
+        self.add_category(&Category { 
+            id: Some(St::ADJUSTMENT_INCOME_ID), 
+            name: ADJUSTMENT_INCOME_CAT_NAME.to_owned(),
+            category_type: CategoryType::Income,
+            note: String::new(),
+            custom_fields: CustomFields::new(),
+            meta_info: MetaInfo::new(Some(*JANUARY_1970), None, None)
+        })?;
+
+        self.add_category(&Category { 
+            id: Some(St::ADJUSTMENT_OUTCOME_ID), 
+            name: ADJUSTMENT_OUTCOME_CAT_NAME.to_owned(),
+            category_type: CategoryType::Outcome,
+            note: String::new(),
+            custom_fields: CustomFields::new(),
+            meta_info: MetaInfo::new(Some(*JANUARY_1970), None, None)
+        })?;
 
-    /// Add a new transaction.
-    /// 
-    /// * `transaction` - transaction data
-    pub fn add_transaction(&self, transaction: &Transaction) -> Result<()> {
         //
-        // Amount is considered to have a proper sign,
-        // so I just add it to a corresponding account's
-        // balance.
-        // Change timestamp for account should not be 
-        // modified in this case, so I don't modify it 
-        // in account instance.
+        // New budgets have no transactions to convert
         //
 
-        let mut decrypted_account = self.decrypt_account(
-            &self.storage.account(transaction.account_id)?)?;
+        self.storage.set_canonical_dates()
+    }
 
-        decrypted_account.balance += transaction.amount;
+    /// Converts timestamps of existing transactions into canonical
+    /// transaction dates (see [`TxnDate`]).
+    /// 
+    /// Older budgets store moments of transactions, that are shifted 
+    /// between days in different time zones. They are converted into
+    /// wall-clock dates as seen in the time zone, that transactions were
+    /// made in. Conversion is local to the instance, hence each instance
+    /// converts its own data. Budget is converted only once, hence it is 
+    /// safe to call this function on each start. Returns number of 
+    /// converted transactions.
+    /// 
+    /// * `policy` - time zone, that transactions were made in
+    pub fn migrate_transaction_dates(&self, policy: TimeZonePolicy) -> Result<usize> {
+        if self.storage.canonical_dates()? {
+            return Ok(0);
+        }
 
         //
-        // Well... It would be better to use DB's transactions here,
-        // but it is more complicated though. 
-        // If transaction will not be added, account will not be modified.
-        // If account update fails, one can just remove bad transaction
-        // with `emergency` flag set to `true`.
-        // Hence there is a way to restore consistency.
+        // Conversion is not idempotent, so it is performed atomically
+        // within a snapshot, otherwise an interrupted conversion would
+        // shift some dates twice
         //
 
-        let mut transaction = self.encrypt_transaction(transaction)?;
-        transaction.meta_info.set_origin_if_absent(self.instance_id());
+        self.atomically(|| self.convert_transaction_dates(policy))
+    }
 
-        self.storage.add_transaction(transaction)?;
-        self.storage.update_account(self.encrypt_account(&decrypted_account)?)?;
+    /// Add a new transaction.
+    /// 
+    /// * `transaction` - transaction data
+    pub fn add_transaction(&self, transaction: &Transaction) -> Result<()> {
+        Self::ensure_valid(transaction)?;
+        self.insert_transaction(transaction)
+    }
 
-        Ok(())
+    /// Update a transaction in place, so that its identifier is kept.
+    /// 
+    /// Balances of affected accounts are corrected. Change timestamp
+    /// is set to current time if absent.
+    /// 
+    /// * `transaction` - transaction data with updated values
+    pub fn update_transaction(&self, transaction: &Transaction) -> Result<()> {
+        Self::ensure_valid(transaction)?;
+        self.replace_transaction(transaction)
+    }
+
+    /// Attach a receipt to a stored transaction or replace its receipt.
+    /// 
+    /// * `transaction` - identifier of a transaction
+    /// * `text` - text of a receipt (`None` removes it)
+    pub fn set_receipt(&self, transaction: Id, text: Option<&str>) -> Result<()> {
+        let mut transaction = self.decrypt_transaction(&self.storage.transaction(transaction)?)?;
+        receipt::set_receipt_text(&mut transaction.custom_fields, text);
+        transaction.meta_info.changed_timestamp = None;
+
+        self.update_transaction(&transaction)
     }
 
     /// Add transfer transactions.
@@ -178,6 +593,10 @@ where
             account_id: to_account,
             category_id: St::TRANSFER_INCOME_ID,
             amount: amount,
+            kind: TransactionKind::Regular,
+            note: String::new(),
+            custom_fields: CustomFields::new(),
+            spender_id: None,
             meta_info: MetaInfo::new(Some(now), None, None)
         })?;
 
@@ -188,45 +607,121 @@ where
             account_id: from_account,
             category_id: St::TRANSFER_OUTCOME_ID,
             amount: -amount,
+            kind: TransactionKind::Regular,
+            note: String::new(),
+            custom_fields: CustomFields::new(),
+            spender_id: None,
             meta_info: MetaInfo::new(Some(now), None, None)
         })?;
 
         Ok(())
     }
 
+    /// Set opening balance of an account.
+    /// 
+    /// Opening balance is a special transaction, that is taken into account
+    /// in balances, but not in incomes and spendings. If the account already
+    /// has an opening balance, it is replaced with the new one.
+    /// 
+    /// * `account` - account to set opening balance for
+    /// * `amount` - opening balance value
+    /// * `timestamp` - date of the opening balance
+    pub fn set_opening_balance(&self, account: Id, amount: isize, timestamp: Timestamp) -> Result<()> {
+        //
+        // Only one opening balance can exist for an account,
+        // so I remove previous ones first
+        //
+
+        //
+        // Previous opening balance may be in a locked period, and
+        // then it must be kept along with balance of the account
+        //
+
+        self.atomically(|| {
+            let now = Clock::now();
+            let previous = self.transactions_of(account)?
+                .into_iter()
+                .filter(|transaction| transaction.kind == TransactionKind::OpeningBalance);
+
+            for transaction in previous {
+                self.remove_transaction(transaction.id.unwrap(), false, now)?;
+            }
+
+            self.add_balance_transaction(account, amount, timestamp, 
+                TransactionKind::OpeningBalance, OPENING_BALANCE_DESCRIPTION)
+        })
+    }
+
+    /// Adjust account balance to match the observed one.
+    /// 
+    /// Adds an adjustment transaction with the difference between observed
+    /// balance and balance as of the date of the adjustment, so that a 
+    /// backdated reconciliation does not count later transactions. 
+    /// Adjustments are taken into account in balances, but not in incomes
+    /// and spendings. Nothing is added if balances match.
+    /// 
+    /// * `account` - account to adjust balance of
+    /// * `observed_balance` - actual balance of the account at `timestamp` (e.g. from bank statement)
+    /// * `timestamp` - date of the adjustment
+    pub fn adjust_balance(&self, account: Id, observed_balance: isize, timestamp: Timestamp) -> Result<()> {
+        let later: isize = self.transactions_of(account)?
+            .iter()
+            .filter(|transaction| transaction.timestamp > timestamp)
+            .map(|transaction| transaction.amount)
+            .sum();
+
+        let difference = observed_balance - (self.account(account)?.balance - later);
+        if 0 == difference {
+            return Ok(());
+        }
+
+        self.add_balance_transaction(account, difference, timestamp, 
+            TransactionKind::Adjustment, ADJUSTMENT_DESCRIPTION)
+    }
+
     /// Remove transaction.
     /// 
     /// * `transaction` - identifier of a transaction to remove
     /// * `emergency` - if `true`, then the linked account will not be updated
     /// * `removal_timestame` - this value will be written as removal timestamp
     pub fn remove_transaction(&self, transaction: Id, emergency: bool, removal_timestamp: Timestamp) -> Result<()> {
-        if !emergency {
-            //
-            // Here is the same story: it would be probably better to use
-            // DB's transactions, but it is not the way here.
-            // If account is not updated, transaction will not be added.
-            // If transaction is not removed, but account is updated yet,
-            // one can remove transaction with `emergency` flag set.
-            // Hence there is a way to restore consistency.
-            //
+        if emergency {
+            return self.storage.remove_transaction(transaction, removal_timestamp);
+        }
 
-            let decrypted_transaction = self.decrypt_transaction(
-                &self.storage.transaction(transaction)?)?;
+        let decrypted_transaction = self.decrypt_transaction(
+            &self.storage.transaction(transaction)?)?;
 
-            let mut decrypted_account = self.decrypt_account(
-                &self.storage.account(decrypted_transaction.account_id)?)?;
+        //
+        // Here is the same story: it would be probably better to use
+        // DB's transactions, but it is not the way here.
+        // Transaction is removed first, so that a refused removal
+        // (e.g. in a locked period) leaves the account intact.
+        // If account is not updated, but transaction is removed yet,
+        // the balance can be fixed with an adjustment.
+        // Hence there is a way to restore consistency.
+        //
 
-            //
-            // Again, amount in transaction is considered to have a proper sign,
-            // hence I just subtract it from account's balance
-            //
+        self.storage.remove_transaction(transaction, removal_timestamp)?;
 
-            decrypted_account.balance -= decrypted_transaction.amount;
+        let mut decrypted_account = self.decrypt_account(
+            &self.storage.account(decrypted_transaction.account_id)?)?;
 
-            self.storage.update_account(self.encrypt_account(&decrypted_account)?)?;
-        }
+        //
+        // Again, amount in transaction is considered to have a proper sign,
+        // hence I just subtract it from account's balance
+        //
 
-        self.storage.remove_transaction(transaction, removal_timestamp)
+        decrypted_account.balance -= decrypted_transaction.amount;
+
+        self.storage.update_account(self.encrypt_account(&decrypted_account)?)
+    }
+
+    /// Return transaction with a given identifier.
+    /// 
+    /// * `transaction` - identifier to return record for
+    pub fn transaction(&self, transaction: Id) -> Result<Transaction> {
+        self.decrypt_transaction(&self.storage.transaction(transaction)?)
     }
 
     // Return all transactions.
@@ -234,6 +729,19 @@ where
         self.decrypt_transactions(&self.storage.transactions()?)
     }
 
+    /// Return all transactions sorted by timestamp in descending order.
+    /// Their sensitive fields are decrypted on first access, that makes
+    /// listing cheap, if only some fields are shown.
+    pub fn lazy_transactions(&self) -> Result<Vec<LazyTransaction<'_, Ce, Se, St>>> {
+        let transactions = self.storage
+            .transactions()?
+            .into_iter()
+            .map(|transaction| LazyTransaction::new(self, transaction))
+            .collect();
+
+        Ok(transactions)
+    }
+
     /// Return all transactions between a given time points (including start 
     /// of the interval and excluding the end) sorted by timestamp in 
     /// descending order.
@@ -246,6 +754,59 @@ where
         self.decrypt_transactions(&self.storage.transactions_between(start_timestamp, end_timestamp)?) 
     }
 
+    /// Return all transactions within a period sorted by timestamp 
+    /// in descending order.
+    /// 
+    /// * `period` - period to return transactions of
+    pub fn transactions_in<P: Period>(&self, period: &P) -> Result<Vec<Transaction>> {
+        self.transactions_between(period.start(), period.end())
+    }
+
+    /// Return summaries of all transactions between a given time points 
+    /// (including start of the interval and excluding the end) sorted by 
+    /// timestamp in descending order.
+    /// 
+    /// Descriptions, notes and custom fields are neither fetched nor
+    /// decrypted, so it is much cheaper for list views, that show
+    /// dates, amounts and categories only.
+    /// 
+    /// * `start_timestamp` - point in time to start from
+    /// * `end_timestamp` - point in time to end before
+    pub fn transaction_summaries_between(&self, start_timestamp: Timestamp, end_timestamp: Timestamp) -> Result<Vec<TransactionSummary>> {
+        let summaries = self.storage
+            .transaction_summaries_between(start_timestamp, end_timestamp)?;
+
+        let amounts: Vec<_> = summaries
+            .iter()
+            .map(|summary| summary.amount.as_slice())
+            .collect();
+
+        let decrypted_amounts = self.crypto_engine
+            .decrypt_batch(&self.key, &amounts)?;
+
+        summaries
+            .iter()
+            .zip(decrypted_amounts)
+            .map(|(summary, amount)| Ok(TransactionSummary {
+                id: summary.id,
+                timestamp: summary.timestamp,
+                account_id: summary.account_id,
+                category_id: summary.category_id,
+                amount: Self::isize_from_buffer(amount)?,
+                kind: summary.kind
+            }))
+            .collect()
+    }
+
+    /// Return summaries of all transactions within a period sorted 
+    /// by timestamp in descending order (see 
+    /// [`Budget::transaction_summaries_between`]).
+    /// 
+    /// * `period` - period to return transactions of
+    pub fn transaction_summaries_in<P: Period>(&self, period: &P) -> Result<Vec<TransactionSummary>> {
+        self.transaction_summaries_between(period.start(), period.end())
+    }
+
     /// Return all transactions bound with a given account sorted by timestamp 
     /// in descending order.
     /// 
@@ -292,39 +853,112 @@ where
         self.decrypt_transactions(&self.storage.transactions_with_between(category, start_timestamp, end_timestamp)?) 
     }
 
-    /// Add a new account.
+    /// Return all transactions with exactly given description sorted
+    /// by timestamp in descending order.
     /// 
-    /// * `account` - account data
-    pub fn add_account(&self, account: &Account) -> Result<()> {
-        let mut account = self.encrypt_account(account)?;
-        account.meta_info.set_origin_if_absent(self.instance_id());
+    /// Storage finds them directly if exact matching of descriptions
+    /// is enabled (see [`Budget::set_exact_match_descriptions`]),
+    /// otherwise all transactions are decrypted and compared.
+    /// 
+    /// * `description` - description to look for
+    pub fn transactions_described(&self, description: &str) -> Result<Vec<Transaction>> {
+        if self.storage.deterministic_descriptions()? {
+            let encrypted_description = self.crypto_engine
+                .encrypt_deterministic(&self.key, description.as_bytes())?;
 
-        self.storage.add_account(account)
+            return self.decrypt_transactions(&self.storage.transactions_described(encrypted_description.as_bytes())?);
+        }
+
+        let mut transactions = self.transactions()?;
+        transactions.retain(|transaction| transaction.description == description);
+
+        Ok(transactions)
     }
 
-    /// Remove an account if possible (or forced).
+    /// Return transactions made within an area sorted by timestamp
+    /// in descending order (see [`super::set_location`]).
     /// 
-    /// If account has transaction and `force` is false, then this function fails.
+    /// Locations are encrypted, hence only locations from the local
+    /// index are decrypted, and then matching transactions are fetched.
     /// 
-    /// * `account` - identifier of an account to remove
-    /// * `force` - if true, then account is deleted anyway with all of its transactions
-    /// * `removal_timestame` - this value will be written as removal timestamp
-    pub fn remove_account(&self, account: Id, force: bool, removal_timestamp: Timestamp) -> Result<()> {
-        if force {
-            //
-            // Forced removal is requested, hence I need to remove
-            // all linked transactions first
-            //
-
-            for transaction in self.storage.transactions_of(account)? {
-                self.storage.remove_transaction(transaction.id.unwrap(), removal_timestamp)?;
+    /// * `area` - area to return transactions made within
+    pub fn transactions_within(&self, area: &BoundingBox) -> Result<Vec<Transaction>> {
+        self.ensure_indexed()?;
+
+        let mut transactions = Vec::new();
+        for (id, location) in self.storage.indexed_values(IndexedField::Location)? {
+            if area.contains(&self.decrypt_location(&location)?) {
+                transactions.push(self.decrypt_transaction(&self.storage.transaction(id)?)?);
             }
         }
 
-        self.storage.remove_account(account, removal_timestamp)
+        transactions.sort_by_key(|transaction| std::cmp::Reverse(transaction.timestamp));
+
+        Ok(transactions)
     }
 
-    /// Return account with a given identifier.
+    /// Check if exact matching of transaction descriptions is enabled
+    /// (see [`Budget::set_exact_match_descriptions`]).
+    pub fn exact_match_descriptions(&self) -> Result<bool> {
+        self.storage
+            .deterministic_descriptions()
+    }
+
+    /// Enables or disables exact matching of transaction descriptions.
+    /// 
+    /// When enabled, descriptions are encrypted deterministically, so
+    /// storage finds transactions by description without decryption
+    /// (see [`Budget::transactions_described`]).
+    /// 
+    /// Trade-off: equal descriptions are stored as equal ciphertexts,
+    /// hence anyone with access to storage learns, which transactions
+    /// share a description and how often it occurs, though not the
+    /// description itself. Other fields are not affected.
+    /// 
+    /// The mode is local to the instance, synchronized data does not
+    /// depend on it. Existing descriptions are re-encrypted atomically.
+    /// Returns number of re-encrypted transactions.
+    /// 
+    /// * `enabled` - `true` to enable exact matching
+    pub fn set_exact_match_descriptions(&self, enabled: bool) -> Result<usize> {
+        if self.storage.deterministic_descriptions()? == enabled {
+            return Ok(0);
+        }
+
+        self.atomically(|| self.reencrypt_descriptions(enabled))
+    }
+
+    /// Add a new account.
+    /// 
+    /// * `account` - account data
+    pub fn add_account(&self, account: &Account) -> Result<()> {
+        Self::ensure_valid(account)?;
+        self.insert_account(account)
+    }
+
+    /// Remove an account if possible (or forced).
+    /// 
+    /// If account has transaction and `force` is false, then this function fails.
+    /// 
+    /// * `account` - identifier of an account to remove
+    /// * `force` - if true, then account is deleted anyway with all of its transactions
+    /// * `removal_timestame` - this value will be written as removal timestamp
+    pub fn remove_account(&self, account: Id, force: bool, removal_timestamp: Timestamp) -> Result<()> {
+        if force {
+            //
+            // Forced removal is requested, hence I need to remove
+            // all linked transactions first
+            //
+
+            for transaction in self.storage.transactions_of(account)? {
+                self.storage.remove_transaction(transaction.id.unwrap(), removal_timestamp)?;
+            }
+        }
+
+        self.storage.remove_account(account, removal_timestamp)
+    }
+
+    /// Return account with a given identifier.
     /// 
     /// * `account` - identifier to return record for
     pub fn account(&self, account: Id) -> Result<Account> {
@@ -336,14 +970,43 @@ where
         self.decrypt_accounts(&self.storage.accounts()?)
     }
 
+    /// Merge an account into another one.
+    /// 
+    /// Transactions, loans and holdings of the merged account are moved
+    /// to the kept one, then the merged account is removed. Initial 
+    /// balance of the merged account is moved as an adjustment. Either 
+    /// all changes are made or none of them. The merge is synchronized.
+    /// 
+    /// * `keep` - identifier of an account to keep
+    /// * `remove` - identifier of an account to merge into the kept one
+    pub fn merge_accounts(&self, keep: Id, remove: Id) -> Result<()> {
+        let kept = self.account(keep)?;
+        let removed = self.account(remove)?;
+
+        Self::ensure_mergeable(keep, remove, true)?;
+
+        self.atomically(|| {
+            let now = Clock::now();
+
+            self.storage.redirect_references(remove, keep)?;
+            self.move_balance(kept, &removed)?;
+
+            if 0 != removed.initial_balance {
+                self.add_balance_transaction(keep, removed.initial_balance, now,
+                    TransactionKind::Adjustment, ADJUSTMENT_DESCRIPTION)?;
+            }
+
+            self.storage.remove_account(remove, now)?;
+            self.record_merge(MergeKind::Account, keep, remove, now)
+        })
+    }
+
     /// Add a new category.
     /// 
     /// * `category` - category data
     pub fn add_category(&self, category: &Category) -> Result<()> {
-        let mut category = self.encrypt_category(category)?;
-        category.meta_info.set_origin_if_absent(self.instance_id());
-
-        self.storage.add_category(category)
+        Self::ensure_valid(category)?;
+        self.insert_category(category)
     }
 
     /// Remove category if possible.
@@ -377,14 +1040,35 @@ where
         self.decrypt_categories(&self.storage.categories_of(category_type)?)
     }
 
+    /// Merge a category into another one.
+    /// 
+    /// Transactions, plans and loans of the merged category are moved
+    /// to the kept one, then the merged category is removed. Either all
+    /// changes are made or none of them. The merge is synchronized.
+    /// 
+    /// * `keep` - identifier of a category to keep
+    /// * `remove` - identifier of a category to merge into the kept one
+    pub fn merge_categories(&self, keep: Id, remove: Id) -> Result<()> {
+        let kept = self.category(keep)?;
+        let removed = self.category(remove)?;
+
+        Self::ensure_mergeable(keep, remove, kept.category_type == removed.category_type)?;
+
+        self.atomically(|| {
+            let now = Clock::now();
+
+            self.storage.redirect_references(remove, keep)?;
+            self.storage.remove_category(remove, now)?;
+            self.record_merge(MergeKind::Category, keep, remove, now)
+        })
+    }
+
     /// Add a new plan.
     /// 
     /// * `plan` - plan data
     pub fn add_plan(&self, plan: &Plan) -> Result<()> {
-        let mut plan = self.encrypt_plan(plan)?;
-        plan.meta_info.set_origin_if_absent(self.instance_id());
-        
-        self.storage.add_plan(plan)
+        Self::ensure_valid(plan)?;
+        self.insert_plan(plan)
     }
 
     /// Remove plan.
@@ -414,128 +1098,2132 @@ where
         self.decrypt_plans(&self.storage.plans_for(category)?)
     }
 
-    /// Delete permanently all previously removed items.
+    /// Return amount of money spent (or earned) within a period
+    /// in a plan's category. Only regular transactions are counted.
     /// 
-    /// Actually `remove_*` functions can perform no removal, e.g.
-    /// just mark items as removed. This function therefore permanently
-    /// deletes such marked items.
-    pub fn clean_removed(&self) -> Result<()> {
-        self.storage.clean_removed()
+    /// * `plan` - identifier of a plan
+    /// * `period` - period to sum transactions of
+    pub fn plan_spending<P: Period>(&self, plan: Id, period: &P) -> Result<isize> {
+        let plan = self.plan(plan)?;
+        let total: isize = self.transactions_with_between(plan.category_id, period.start(), period.end())?
+            .iter()
+            .filter(|transaction| transaction.kind.is_regular())
+            .map(|transaction| transaction.amount)
+            .sum();
+
+        Ok(total.abs())
     }
 
-    /// Performs synchronization with remote instances.
+    /// Add a new loan.
     /// 
-    /// * `auth` - authentication information for synchronization
-    pub fn perform_sync(&self, auth: &[u8]) -> Result<()> {
-        //
-        // Just use the synchronization engine
-        //
+    /// * `loan` - loan data
+    pub fn add_loan(&self, loan: &Loan) -> Result<()> {
+        Self::ensure_valid(loan)?;
+        self.insert_loan(loan)
+    }
 
-        let context = CryptoBuffer::from(auth);
-        self.sync_engine
-            .perform_sync(self.config.instance_id(), self, &context)?;
+    /// Remove loan.
+    /// 
+    /// Payments made for the loan are not removed.
+    /// 
+    /// * `loan` - identifier of loan to remove
+    /// * `removal_timestame` - this value will be written as removal timestamp
+    pub fn remove_loan(&self, loan: Id, removal_timestamp: Timestamp) -> Result<()> {
+        self.storage.remove_loan(loan, removal_timestamp)
+    }
+
+    /// Return loan with a given identifier.
+    /// 
+    /// * `loan` - identifier to return record for
+    pub fn loan(&self, loan: Id) -> Result<Loan> {
+        self.decrypt_loan(&self.storage.loan(loan)?)
+    }
+
+    /// Return all loans sorted by start date.
+    pub fn loans(&self) -> Result<Vec<Loan>> {
+        self.decrypt_loans(&self.storage.loans()?)
+    }
+
+    /// Return outstanding principal of a loan.
+    /// 
+    /// Computed as initial principal reduced by all transactions of the
+    /// loan's account with the loan's principal category made after
+    /// the loan issue.
+    /// 
+    /// * `loan` - identifier of a loan
+    pub fn loan_balance(&self, loan: Id) -> Result<isize> {
+        let loan = self.loan(loan)?;
+        self.outstanding_principal(&loan)
+    }
+
+    /// Record a loan payment.
+    /// 
+    /// Payment is split into interest accrued for a month on the outstanding
+    /// principal and the principal part. Each part is added as a separate 
+    /// transaction with the corresponding category of the loan.
+    /// 
+    /// * `loan` - identifier of a loan
+    /// * `amount` - total amount of the payment
+    /// * `timestamp` - date of the payment
+    pub fn record_loan_payment(&self, loan: Id, amount: isize, timestamp: Timestamp) -> Result<()> {
+        let loan = self.loan(loan)?;
+        let amount = amount.abs();
+
+        let remaining = self.outstanding_principal(&loan)?;
+        let interest = loan.monthly_interest(remaining).min(amount);
+        let principal = (amount - interest).min(remaining);
+
+        let parts = [
+            (interest, loan.interest_category_id, LOAN_INTEREST_DESCRIPTION),
+            (principal, loan.principal_category_id, LOAN_PRINCIPAL_DESCRIPTION),
+        ];
+
+        for (part, category, description) in parts {
+            if 0 == part {
+                continue;
+            }
+
+            self.add_transaction(&Transaction {
+                id: None,
+                timestamp,
+                description: format!("{}: {}", description, loan.name),
+                account_id: loan.account_id,
+                category_id: category,
+                amount: -part,
+                kind: TransactionKind::Regular,
+                note: String::new(),
+                custom_fields: CustomFields::new(),
+                spender_id: None,
+                meta_info: MetaInfo::new(Some(Clock::now()), None, None)
+            })?;
+        }
+
+        Ok(())
+    }
+
+    /// Project the date, when a loan will be paid off.
+    /// 
+    /// Returns [`None`] if payments don't cover accrued interest.
+    /// 
+    /// * `loan` - identifier of a loan
+    /// * `payment` - regular monthly payment, if [`None`], scheduled annuity payment is used
+    pub fn loan_payoff_date(&self, loan: Id, payment: Option<isize>) -> Result<Option<Timestamp>> {
+        let loan = self.loan(loan)?;
+        let remaining = self.outstanding_principal(&loan)?;
+        let payment = payment.unwrap_or(loan.monthly_payment());
+
+        Ok(loan.payoff_timestamp(remaining, payment, Clock::now()))
+    }
+
+    /// Add a new investment holding.
+    /// 
+    /// * `holding` - holding data
+    pub fn add_holding(&self, holding: &Holding) -> Result<()> {
+        Self::ensure_valid(holding)?;
+        self.insert_holding(holding)
+    }
+
+    /// Update an investment holding (e.g. after buying or selling units).
+    /// 
+    /// Change timestamp is set to current time if absent.
+    /// 
+    /// * `holding` - holding data with updated values
+    pub fn update_holding(&self, holding: &Holding) -> Result<()> {
+        Self::ensure_valid(holding)?;
+        self.replace_holding(holding)
+    }
+
+    /// Remove investment holding.
+    /// 
+    /// * `holding` - identifier of holding to remove
+    /// * `removal_timestame` - this value will be written as removal timestamp
+    pub fn remove_holding(&self, holding: Id, removal_timestamp: Timestamp) -> Result<()> {
+        self.storage.remove_holding(holding, removal_timestamp)
+    }
+
+    /// Return investment holding with a given identifier.
+    /// 
+    /// * `holding` - identifier to return record for
+    pub fn holding(&self, holding: Id) -> Result<Holding> {
+        self.decrypt_holding(&self.storage.holding(holding)?)
+    }
+
+    /// Return all investment holdings.
+    pub fn holdings(&self) -> Result<Vec<Holding>> {
+        self.decrypt_holdings(&self.storage.holdings()?)
+    }
+
+    /// Return all investment holdings of an account.
+    /// 
+    /// * `account` - account identifier to return holdings for
+    pub fn holdings_of(&self, account: Id) -> Result<Vec<Holding>> {
+        self.decrypt_holdings(&self.storage.holdings_of(account)?)
+    }
+
+    /// Add a new security price (e.g. entered manually).
+    /// 
+    /// * `price` - price data
+    pub fn add_price_point(&self, price: &PricePoint) -> Result<()> {
+        Self::ensure_valid(price)?;
+        self.insert_price_point(price)
+    }
+
+    /// Add a bunch of security prices (e.g. imported from a quotes provider).
+    /// 
+    /// * `prices` - prices data
+    pub fn add_price_points(&self, prices: &[PricePoint]) -> Result<()> {
+        for price in prices {
+            self.add_price_point(price)?;
+        }
+
+        Ok(())
+    }
+
+    /// Remove security price.
+    /// 
+    /// * `price` - identifier of price to remove
+    /// * `removal_timestame` - this value will be written as removal timestamp
+    pub fn remove_price_point(&self, price: Id, removal_timestamp: Timestamp) -> Result<()> {
+        self.storage.remove_price(price, removal_timestamp)
+    }
+
+    /// Return all security prices sorted by timestamp in descending order.
+    pub fn price_points(&self) -> Result<Vec<PricePoint>> {
+        self.decrypt_price_points(&self.storage.prices()?)
+    }
 
+    /// Return the latest known price of a security at a given time point.
+    /// 
+    /// * `symbol` - security identifier
+    /// * `timestamp` - time point to return price at
+    pub fn latest_price(&self, symbol: &str, timestamp: Timestamp) -> Result<Option<PricePoint>> {
         //
-        // Some items had been removed since the previous sync,
-        // but they were pushed to remote, and now it is not
-        // necessary to keep them locally
+        // Prices are sorted by timestamp in descending order,
+        // so the first suitable one is the latest
         //
 
-        self.clean_removed()
+        let price = self.price_points()?
+            .into_iter()
+            .find(|price| price.symbol == symbol && price.timestamp <= timestamp);
+
+        Ok(price)
     }
 
-    /// Replaces an existsing remote URL with a new one.
+    /// Return value of an investment holding at a given time point.
     /// 
-    /// * `remote` - new remote URL
-    pub fn set_remote_url(&self, remote: &str) -> Result<()> {
-        self.sync_engine
-            .change_remote(remote)
+    /// Returns [`None`] if no price of the security is known at the time point.
+    /// 
+    /// * `holding` - identifier of a holding
+    /// * `timestamp` - time point to evaluate holding at
+    pub fn holding_value(&self, holding: Id, timestamp: Timestamp) -> Result<Option<isize>> {
+        let holding = self.holding(holding)?;
+        self.evaluate_holding(&holding, timestamp)
     }
-}
 
+    /// Return total value of an account at a given time point.
+    /// 
+    /// Value consists of the account's cash balance and values of all its
+    /// investment holdings. Balance is restored from transactions made
+    /// before the time point, holdings are evaluated with prices at the
+    /// time point, but with their current quantity. Holdings without 
+    /// known price are not counted.
+    /// 
+    /// * `account` - account identifier
+    /// * `timestamp` - time point to evaluate account at
+    pub fn account_value(&self, account: Id, timestamp: Timestamp) -> Result<isize> {
+        let later: isize = self.decrypt_transactions(&self.storage.transactions_of_after(account, timestamp)?)?
+            .iter()
+            .map(|transaction| transaction.amount)
+            .sum();
 
-impl<Ce, Se, St> Syncable for Budget<Ce, Se, St> 
-where
-    Ce: CryptoEngine,
-    Se: SyncEngine,
-    St: DataStorage
-{
-    type Context = CryptoBuffer;
+        let mut value = self.account(account)?.balance - later;
+        for holding in self.holdings_of(account)? {
+            value += self.evaluate_holding(&holding, timestamp)?.unwrap_or(0);
+        }
 
-    type InstanceId = InstanceId;
+        Ok(value)
+    }
+
+    /// Return net worth at a given time point.
+    /// 
+    /// Net worth is a total value of all accounts (including investment
+    /// holdings) reduced by principal of all loans, that is outstanding
+    /// at the time point (see [`Budget::account_value`]).
+    /// 
+    /// * `timestamp` - time point to evaluate net worth at
+    pub fn net_worth(&self, timestamp: Timestamp) -> Result<isize> {
+        let mut net_worth = 0;
+        for account in self.accounts()? {
+            net_worth += self.account_value(account.id.unwrap(), timestamp)?;
+        }
+
+        for loan in self.loans()? {
+            net_worth -= self.outstanding_principal_at(&loan, timestamp)?;
+        }
+
+        Ok(net_worth)
+    }
+
+    /// Return totals of transactions per category within a period.
+    /// 
+    /// * `period` - period to aggregate transactions of
+    pub fn category_breakdown<P: Period>(&self, period: &P) -> Result<CategoryBreakdown> {
+        Ok(CategoryBreakdown::new(period.start(), period.end(), &self.categories()?,
+            &self.transactions_in(period)?, St::TRANSFER_INCOME_ID, St::TRANSFER_OUTCOME_ID))
+    }
+
+    /// Return totals of transactions per person within a household
+    /// (see [`Transaction::spender_id`]) within a period.
+    /// 
+    /// * `period` - period to aggregate transactions of
+    pub fn spender_breakdown<P: Period>(&self, period: &P) -> Result<SpenderBreakdown> {
+        Ok(SpenderBreakdown::new(period.start(), period.end(), &self.persons()?,
+            &self.transactions_in(period)?, St::TRANSFER_INCOME_ID, St::TRANSFER_OUTCOME_ID))
+    }
+
+    /// Return totals of transactions per group of categories (see
+    /// [`Budget::add_category_group`]) within a period.
+    /// 
+    /// * `period` - period to aggregate transactions of
+    pub fn group_breakdown<P: Period>(&self, period: &P) -> Result<GroupBreakdown> {
+        Ok(GroupBreakdown::new(&self.category_breakdown(period)?,
+            &self.category_groups()?))
+    }
+
+    /// Compare totals of two periods per category, e.g. this month 
+    /// vs. the last one or the same month a year ago.
+    /// 
+    /// * `period` - period to compare
+    /// * `base_period` - period to compare with
+    pub fn compare_periods<P: Period, B: Period>(&self, period: &P, base_period: &B) -> Result<PeriodComparison> {
+        Ok(PeriodComparison::new(self.category_breakdown(period)?,
+            self.category_breakdown(base_period)?))
+    }
+
+    /// Return flows of money within a period: from income categories
+    /// into accounts and from accounts into expense categories, e.g.
+    /// to render a Sankey diagram.
+    /// 
+    /// Amounts are encrypted, hence they are aggregated after decryption
+    /// in a single pass over transactions of the period.
+    /// 
+    /// * `period` - period to return flows of
+    pub fn money_flows<P: Period>(&self, period: &P) -> Result<FlowReport> {
+        Ok(FlowReport::new(period.start(), period.end(), &self.categories()?, &self.accounts()?,
+            &self.transactions_in(period)?, St::TRANSFER_INCOME_ID, St::TRANSFER_OUTCOME_ID))
+    }
+
+    /// Look for likely recurring transactions (subscriptions, bills,
+    /// salary) in history: ones with similar amounts from the same payee
+    /// at regular intervals. Each candidate has its periodicity and 
+    /// expected timestamp of the next transaction.
+    pub fn detect_recurring(&self) -> Result<Vec<RecurringCandidate>> {
+        Ok(reports::detect_recurring(&self.transactions()?, Clock::now()))
+    }
+
+    /// Return transactions within a period, that look unusual: amounts,
+    /// that are outliers for their category or payee, or payments to
+    /// a payee, that are much more frequent than usual. Each anomaly 
+    /// has reasons, so that frontends can explain warnings.
+    /// 
+    /// Transactions are compared with the whole history.
+    /// 
+    /// * `period` - period to look for unusual transactions in
+    pub fn anomalies<P: Period>(&self, period: &P) -> Result<Vec<Anomaly>> {
+        Ok(reports::detect_anomalies(period.start(), period.end(), &self.transactions()?))
+    }
+
+    /// Return statement of an account within a period: transactions
+    /// in chronological order with running balance, opening and closing
+    /// balances and totals, e.g. to render or print it.
+    /// 
+    /// * `account` - account to return statement of
+    /// * `period` - period of the statement
+    pub fn statement<P: Period>(&self, account: Id, period: &P) -> Result<Statement> {
+        Ok(Statement::new(period.start(), period.end(), &self.account(account)?,
+            &self.transactions_of(account)?, St::TRANSFER_INCOME_ID, St::TRANSFER_OUTCOME_ID))
+    }
+
+    /// Return aggregates of each day of a month: actual incomes and
+    /// expenses, expected payments (recurring transactions and loan
+    /// payments) and balance, that is projected for today and later
+    /// days, e.g. to render a calendar view.
+    /// 
+    /// * `month` - month to return calendar of
+    pub fn calendar(&self, month: &MonthPeriod) -> Result<Vec<CalendarDay>> {
+        let now = Clock::now();
+        let transactions = self.transactions()?;
+        let recurring = reports::detect_recurring(&transactions, now);
+
+        Ok(reports::calendar(month, now, &self.accounts()?, &transactions,
+            &recurring, &self.loans()?))
+    }
 
-    fn merge_and_export_changes<Ts, Li, Cl>(&self, timestamp_rw: &mut Ts, last_instance_rw: &mut Li, 
-        changelog_rw: &mut Cl, last_sync: &Timestamp, auth: &Self::Context) -> Result<()>
+    /// Return net worth at a sequence of time points.
+    /// 
+    /// Balances of accounts and principal of loans are restored from
+    /// transactions made before each time point. Holdings are evaluated
+    /// with prices at each time point, but with their current quantity.
+    /// 
+    /// * `timestamps` - time points to evaluate net worth at
+    pub fn net_worth_series<I>(&self, timestamps: I) -> Result<NetWorthSeries>
     where
-        Ts: std::io::Read + std::io::Write + std::io::Seek,
-        Li: std::io::Read + std::io::Write + std::io::Seek,
-        Cl: std::io::Read + std::io::Write + std::io::Seek
+        I: IntoIterator<Item = Timestamp>
     {
-        let mut cumulative_changelog = if Self::empty_sync_files(timestamp_rw, last_instance_rw, changelog_rw)? {
-            //
-            // Files are correct, but empty
-            // Just return empty changelog
-            //
+        let timestamps: Vec<_> = timestamps
+            .into_iter()
+            .collect();
 
-            Changelog::new()
+        let balance: isize = self.accounts()?
+            .iter()
+            .map(|account| account.balance)
+            .sum();
+
+        //
+        // All data is read at once, so that time points are
+        // evaluated without access to storage
+        //
+
+        Ok(NetWorthSeries::new(&timestamps, balance, &self.transactions()?, &self.holdings()?,
+            &self.price_points()?, &self.loans()?))
+    }
+
+    /// Return state of all plans within a period.
+    /// 
+    /// * `period` - period to sum transactions of
+    pub fn budget_status<P: Period>(&self, period: &P) -> Result<Vec<BudgetStatus>> {
+        let transactions = self.transactions_in(period)?;
+
+        Ok(self.plans()?
+            .into_iter()
+            .map(|plan| {
+                let spent: isize = transactions
+                    .iter()
+                    .filter(|transaction| transaction.category_id == plan.category_id && transaction.kind.is_regular())
+                    .map(|transaction| transaction.amount)
+                    .sum();
+
+                BudgetStatus::new(plan.id.unwrap(), plan.category_id, plan.name, plan.amount_limit, spent.abs())
+            })
+            .collect())
+    }
+
+    /// Return triggered alerts of plans within a period: plans, that
+    /// reached their alert thresholds, exceeded their limits or are
+    /// projected to exceed them by the end of the period (see
+    /// [`Budget::projected_spend`]).
+    /// 
+    /// * `period` - period to check plans within
+    pub fn alerts<P: Period>(&self, period: &P) -> Result<Vec<Alert>> {
+        let mut alerts = Vec::new();
+        for plan in self.plans()? {
+            let projection = self.projected_spend(plan.category_id, period)?;
+            alerts.extend(Alert::check(&plan, projection.spent, projection.projected));
         }
-        else {
-            //
-            // Read remote timestamp and instance identifiers to derive decryption key
-            //
 
-            let remote_timestamp = Self::read_timestamp(timestamp_rw)?;
-            let remote_instance = Self::read_instance(last_instance_rw)?;
+        Ok(alerts)
+    }
+
+    /// Project amount spent (or earned) in a category by the end of
+    /// a period by current pace and by spending patterns of several
+    /// previous periods. Only regular transactions are counted.
+    /// 
+    /// * `category` - category to project spending of
+    /// * `period` - period to project spending within
+    pub fn projected_spend<P: Period>(&self, category: Id, period: &P) -> Result<SpendProjection> {
+        let now = Clock::now();
+        let elapsed = (now.clamp(period.start(), period.end()) - period.start()).num_seconds() as f64 /
+            (period.end() - period.start()).num_seconds() as f64;
+
+        let spent_between = |start: Timestamp, end: Timestamp| -> Result<isize> {
+            let total: isize = self.transactions_with_between(category, start, end)?
+                .iter()
+                .filter(|transaction| transaction.kind.is_regular())
+                .map(|transaction| transaction.amount)
+                .sum();
+
+            Ok(total.abs())
+        };
+
+        //
+        // Periods differ in length (e.g. months), hence the same
+        // share of each previous period is taken
+        //
+
+        let mut history = Vec::with_capacity(PROJECTION_HISTORY_PERIODS);
+        let mut previous = period.previous();
+        for _ in 0..PROJECTION_HISTORY_PERIODS {
+            let length = (previous.end() - previous.start()).num_seconds() as f64;
+            let cutoff = previous.start() + chrono::Duration::seconds((length * elapsed).round() as i64);
+
+            history.push((spent_between(previous.start(), cutoff)?, spent_between(cutoff, previous.end())?));
+            previous = previous.previous();
+        }
+
+        Ok(SpendProjection::new(category, period.start(), period.end(), elapsed,
+            spent_between(period.start(), period.end())?, &history))
+    }
+
+    /// Project total balance of accounts at the end of the current 
+    /// month and several following ones.
+    /// 
+    /// Projection assumes, that average monthly income and expenses
+    /// of the last complete months persist. Only regular transactions
+    /// are taken into account.
+    /// 
+    /// * `months` - number of months to project
+    /// * `policy` - policy of mapping timestamps onto calendar months
+    pub fn forecast(&self, months: usize, policy: TimeZonePolicy) -> Result<Vec<ForecastPoint>> {
+        let now = Clock::now();
+        let current_month = MonthPeriod::containing(&now, policy);
+
+        let mut history_start = current_month;
+        for _ in 0..FORECAST_HISTORY_MONTHS {
+            history_start = history_start.previous();
+        }
+
+        let history = self.transactions_between(history_start.start(), current_month.start())?;
+        let monthly = |positive: bool| history
+            .iter()
+            .filter(|transaction| transaction.kind.is_regular() && (transaction.amount > 0) == positive)
+            .map(|transaction| transaction.amount)
+            .sum::<isize>() / FORECAST_HISTORY_MONTHS as isize;
+
+        let (income, expenses) = (monthly(true), monthly(false));
+
+        //
+        // The current month is partially elapsed, hence only
+        // its remaining part is projected
+        //
+
+        let remaining = (current_month.end() - now).num_seconds() as f64 /
+            (current_month.end() - current_month.start()).num_seconds() as f64;
+
+        let mut balance: isize = self.accounts()?
+            .iter()
+            .map(|account| account.balance)
+            .sum();
+
+        let mut points = Vec::with_capacity(months);
+        let mut month = current_month;
+        for index in 0..months {
+            let fraction = if index == 0 { remaining } else { 1.0 };
+            let month_income = (income as f64 * fraction).round() as isize;
+            let month_expenses = (expenses as f64 * fraction).round() as isize;
+
+            balance += month_income + month_expenses;
+            points.push(ForecastPoint {
+                timestamp: month.end(),
+                balance,
+                income: month_income,
+                expenses: month_expenses
+            });
+
+            month = month.next();
+        }
+
+        Ok(points)
+    }
+
+    /// Return average monthly essential spending (cost of living) over
+    /// several last complete months, e.g. to suggest size of an 
+    /// emergency fund.
+    /// 
+    /// Essential categories are configured in settings (see 
+    /// [`Settings::essential_categories`]). If they are not configured,
+    /// all expense categories except transfers count as essential.
+    /// 
+    /// * `months_back` - number of complete months to average
+    /// * `policy` - policy of mapping timestamps onto calendar months
+    pub fn baseline_expenses(&self, months_back: usize, policy: TimeZonePolicy) -> Result<BaselineExpenses> {
+        let end = MonthPeriod::containing(&Clock::now(), policy);
+
+        let mut start = end;
+        for _ in 0..months_back {
+            start = start.previous();
+        }
+
+        let essential_categories: Vec<_> = match self.settings()?.essential_categories {
+            Some(essential_categories) => self.categories()?
+                .into_iter()
+                .filter(|category| category.id.is_some_and(|id| essential_categories.contains(&id)))
+                .collect(),
+
+            None => self.categories_of(CategoryType::Outcome)?
+                .into_iter()
+                .filter(|category| category.id != Some(St::TRANSFER_OUTCOME_ID))
+                .collect()
+        };
+
+        Ok(BaselineExpenses::new(start.start(), end.start(), months_back, &essential_categories,
+            &self.transactions_between(start.start(), end.start())?))
+    }
+
+    /// Enable on-disk cache of reports in cache directory of a location 
+    /// (see [`Budget::cached_report`]).
+    /// 
+    /// * `loc` - location of the budget
+    pub fn enable_report_cache<L: Location>(&self, loc: &L) {
+        *self.report_cache.lock() = Some(ReportCache::new(loc));
+    }
+
+    /// Return a cached report or build and cache it.
+    /// 
+    /// Reports are cached on disk (if cache is enabled with 
+    /// [`Budget::enable_report_cache`]) encrypted with the budget's key.
+    /// Cached reports are valid until any change of budget data, so the
+    /// same report is not recomputed by subsequent runs of an app, e.g.
+    /// CLI invocations. Failures of the cache are not reported, the report
+    /// is built instead.
+    /// 
+    /// Query MUST identify everything the report depends on except of
+    /// budget data, e.g. its kind, period and current date for reports,
+    /// that depend on it.
+    /// 
+    /// * `query` - query, that identifies the report, e.g. `breakdown:2024-05`
+    /// * `build` - function, that builds the report
+    pub fn cached_report<T, F>(&self, query: &str, build: F) -> Result<T>
+    where
+        T: serde::Serialize + serde::de::DeserializeOwned,
+        F: FnOnce(&Self) -> Result<T>
+    {
+        //
+        // Cache is not locked while a report is built, since
+        // building may use the cache too
+        //
+
+        let cache = self.report_cache.lock().clone();
+        let Some(cache) = cache else {
+            return build(self);
+        };
+
+        let position = self.storage.journal_position()?;
+        if let Some(report) = self.read_cached_report(&cache, query, position) {
+            return Ok(report);
+        }
+
+        let report = build(self)?;
+        if self.write_cached_report(&cache, query, position, &report).is_err() {
+            trace_debug!("report is not cached");
+        }
+
+        Ok(report)
+    }
+
+    /// Remove all cached reports.
+    pub fn clear_report_cache(&self) -> Result<()> {
+        match self.report_cache.lock().as_ref() {
+            Some(cache) => cache.clear(),
+            None => Ok(())
+        }
+    }
+
+    /// Register a callback, that is invoked after each change of budget data.
+    /// 
+    /// Changes made during synchronization are reported too.
+    /// 
+    /// * `callback` - callback receiving an event with changed item description
+    pub fn on_change<F>(&self, callback: F)
+    where
+        F: Fn(&StorageEvent) + Send + Sync + 'static
+    {
+        self.storage
+            .on_change(std::sync::Arc::new(callback));
+    }
+
+    /// Make an item private to the current instance or shared again.
+    /// 
+    /// Private items are not exported to other instances during 
+    /// synchronization. Items referencing private ones (e.g. transactions
+    /// of a private account) are not exported too. Note, that items 
+    /// already synchronized with other instances are not removed from them.
+    /// 
+    /// * `item` - identifier of an item of any kind
+    /// * `private` - `true` to make the item private, `false` to share it
+    pub fn set_private(&self, item: Id, private: bool) -> Result<()> {
+        let instance = private
+            .then_some(self.instance_id().into_bytes());
+
+        self.storage.set_private_to(item, instance)
+    }
+
+    /// Checks if an item is private to the current instance.
+    /// 
+    /// * `item` - identifier of an item of any kind
+    pub fn is_private(&self, item: Id) -> Result<bool> {
+        Ok(self.private_items()?.contains(&item))
+    }
+
+    /// Add a transaction from an external source or update it, if it 
+    /// was added before.
+    /// 
+    /// Transactions are matched by their identifiers in the source, that
+    /// are stored in custom fields (see [`external_id`]), so re-running
+    /// an import does not duplicate them. Changed transaction is updated 
+    /// in place, unchanged one is left intact. Only data owned by the
+    /// source (timestamp, description and amount) is updated, category,
+    /// note and custom fields edited by a user are kept. Returns 
+    /// identifier of the stored transaction, that never changes on 
+    /// re-import.
+    /// 
+    /// * `source` - name of an external source (e.g. bank or importer name)
+    /// * `external_id` - identifier of the transaction in the source
+    /// * `transaction` - transaction data
+    pub fn upsert_transaction_by_external_id(&self, source: &str, external_id: &str, transaction: &Transaction) -> Result<Id> {
+        match self.transaction_by_external_id(source, external_id)? {
+            Some(existing) => {
+                let id = existing.id.unwrap();
+                if !external::same_imported_data(&existing, transaction) {
+                    //
+                    // Change timestamp is reset, so that it is set to
+                    // current time and the change is synchronized
+                    //
+
+                    self.update_transaction(&Transaction {
+                        timestamp: transaction.timestamp,
+                        description: transaction.description.clone(),
+                        amount: transaction.amount,
+                        meta_info: MetaInfo { changed_timestamp: None, ..existing.meta_info },
+                        ..existing
+                    })?;
+                }
+
+                Ok(id)
+            },
+            None => {
+                let mut custom_fields = transaction.custom_fields.clone();
+                external::set_external_id(&mut custom_fields, source, external_id);
+
+                let id = uuid::Uuid::new_v4().into_bytes();
+                self.add_transaction(&Transaction {
+                    id: Some(id),
+                    description: transaction.description.clone(),
+                    note: transaction.note.clone(),
+                    custom_fields,
+                    ..*transaction
+                })?;
+
+                Ok(id)
+            }
+        }
+    }
+
+    /// Add an account from an external source or update it, if it 
+    /// was added before.
+    /// 
+    /// Accounts are matched by their identifiers in the source (see 
+    /// [`Budget::upsert_transaction_by_external_id`]). Name, note and
+    /// custom fields of an existing account are updated, its balance 
+    /// is left intact. Returns identifier of the stored account.
+    /// 
+    /// * `source` - name of an external source (e.g. bank or importer name)
+    /// * `external_id` - identifier of the account in the source
+    /// * `account` - account data
+    pub fn upsert_account_by_external_id(&self, source: &str, external_id: &str, account: &Account) -> Result<Id> {
+        let mut custom_fields = account.custom_fields.clone();
+        external::set_external_id(&mut custom_fields, source, external_id);
+
+        match self.account_by_external_id(source, external_id)? {
+            Some(existing) => {
+                let updated = Account {
+                    name: account.name.clone(),
+                    note: account.note.clone(),
+                    custom_fields,
+                    ..existing
+                };
+
+                Self::ensure_valid(&updated)?;
+                self.storage.update_account(self.encrypt_account(&updated)?)?;
+
+                Ok(updated.id.unwrap())
+            },
+
+            None => {
+                let id = uuid::Uuid::new_v4().into_bytes();
+                self.add_account(&Account {
+                    id: Some(id),
+                    custom_fields,
+                    ..account.clone()
+                })?;
+
+                Ok(id)
+            }
+        }
+    }
+
+    /// Return transaction with a given identifier in an external source (if any).
+    /// 
+    /// * `source` - name of an external source
+    /// * `external_id` - identifier of the transaction in the source
+    pub fn transaction_by_external_id(&self, source: &str, external_id: &str) -> Result<Option<Transaction>> {
+        self.ensure_indexed()?;
+
+        let key = self.external_key(source, external_id)?;
+        self.storage
+            .transactions_indexed(IndexedField::ExternalId, &key)?
+            .first()
+            .map(|transaction| self.decrypt_transaction(transaction))
+            .transpose()
+    }
+
+    /// Return account with a given identifier in an external source (if any).
+    /// 
+    /// * `source` - name of an external source
+    /// * `external_id` - identifier of the account in the source
+    pub fn account_by_external_id(&self, source: &str, external_id: &str) -> Result<Option<Account>> {
+        Ok(self.accounts()?
+            .into_iter()
+            .find(|account| external::has_external_id(&account.custom_fields, source, external_id)))
+    }
+
+    /// Prepare an import of transactions from an external source
+    /// without storing anything.
+    /// 
+    /// Categories of new transactions are predicted from history of
+    /// their payees or, if a payee is new, from similar transactions
+    /// (see [`Budget::suggest_category`]). Already imported ones keep
+    /// their current categories. A caller may review and edit the
+    /// preview and then store it with [`Budget::commit_import`].
+    /// 
+    /// * `source` - name of an external source (e.g. bank or importer name)
+    /// * `transactions` - pairs of identifiers in the source and transactions
+    pub fn preview_import<I>(&self, source: &str, transactions: I) -> Result<ImportPreview>
+    where
+        I: IntoIterator<Item = (String, Transaction)>
+    {
+        let existing_transactions = self.transactions()?;
+        let history = PayeeHistory::new(&existing_transactions);
+        let categorizer = Categorizer::new(&existing_transactions);
+
+        let proposed = transactions
+            .into_iter()
+            .map(|(external_id, mut transaction)| {
+                let existing = existing_transactions
+                    .iter()
+                    .find(|existing| external::has_external_id(&existing.custom_fields, source, &external_id));
+
+                let (prediction, status) = match existing {
+                    Some(existing) => {
+                        transaction.category_id = existing.category_id;
+
+                        let is_same = external::same_imported_data(existing, &transaction);
+
+                        (None, if is_same { ImportStatus::Unchanged } else { ImportStatus::Changed })
+                    },
+
+                    None => {
+                        let prediction = history.predict(&transaction.description)
+                            .or_else(|| categorizer.suggest(&transaction.description,
+                                transaction.amount, transaction.account_id));
+                        if let Some(prediction) = &prediction {
+                            transaction.category_id = prediction.category_id;
+                        }
+
+                        (prediction, ImportStatus::New)
+                    }
+                };
+
+                ProposedTransaction {
+                    external_id,
+                    transaction,
+                    prediction,
+                    status
+                }
+            })
+            .collect();
+
+        Ok(ImportPreview {
+            source: source.to_owned(),
+            transactions: proposed
+        })
+    }
+
+    /// Prepare an import of transactions from a bank without storing
+    /// anything (see [`Budget::preview_import`]).
+    /// 
+    /// Only accounts, that are linked to bank accounts, are imported.
+    /// Accounts are linked by their identifiers in the bank, e.g. with
+    /// [`Budget::upsert_account_by_external_id`] with the provider's name
+    /// as a source. Transactions, that were imported before, are marked
+    /// as unchanged or changed, so repeated imports do not duplicate them.
+    /// 
+    /// * `provider` - authenticated bank provider
+    /// * `since` - point in time to fetch transactions since
+    /// * `fallback_category` - category of new transactions, which category cannot be predicted
+    pub fn preview_bank_import<P: BankProvider>(&self, provider: &P, since: Timestamp, fallback_category: Id) -> Result<ImportPreview> {
+        let source = provider.name();
+        let now = Clock::now();
+
+        let mut transactions = Vec::new();
+        for bank_account in provider.accounts()? {
+            let Some(account_id) = self.account_by_external_id(source, &bank_account.id)?.and_then(|account| account.id) else {
+                continue;
+            };
+
+            let fetched = provider
+                .transactions_since(&bank_account.id, since)?
+                .into_iter()
+                .map(|fetched| (fetched.id, Transaction {
+                    id: None,
+                    timestamp: fetched.timestamp,
+                    description: fetched.description,
+                    account_id,
+                    category_id: fallback_category,
+                    amount: fetched.amount,
+                    kind: TransactionKind::Regular,
+                    note: String::new(),
+                    custom_fields: CustomFields::new(),
+                    spender_id: None,
+                    meta_info: MetaInfo::new(Some(now), None, None)
+                }));
+
+            transactions.extend(fetched);
+        }
+
+        self.preview_import(source, transactions)
+    }
+
+    /// Suggest a category for a transaction by previous transactions
+    /// with similar descriptions. Returns [`None`] if there are no
+    /// such transactions.
+    /// 
+    /// Words of the description are matched against descriptions of
+    /// previous transactions in the same direction (income or expense),
+    /// transactions of the same account weigh more. No external services
+    /// are involved.
+    /// 
+    /// * `description` - description of the transaction
+    /// * `amount` - amount of the transaction
+    /// * `account` - account of the transaction
+    pub fn suggest_category(&self, description: &str, amount: isize, account: Id) -> Result<Option<CategoryPrediction>> {
+        let transactions = self.transactions()?;
+
+        Ok(Categorizer::new(&transactions)
+            .suggest(description, amount, account))
+    }
+
+    /// Store transactions of an import preview (see [`Budget::preview_import`]).
+    /// 
+    /// Transactions are stored as with [`Budget::upsert_transaction_by_external_id`]
+    /// and all at once: if one of them fails, none is stored. Returns
+    /// identifiers of stored transactions in order of the preview.
+    /// 
+    /// * `preview` - reviewed preview
+    pub fn commit_import(&self, preview: &ImportPreview) -> Result<Vec<Id>> {
+        self.atomically(|| preview.transactions
+            .iter()
+            .map(|proposed| self.upsert_transaction_by_external_id(&preview.source,
+                &proposed.external_id, &proposed.transaction))
+            .collect())
+    }
+
+    /// Search for items by text.
+    /// 
+    /// Search is case-insensitive. Names, descriptions, notes and
+    /// textual custom fields (including texts of receipts, see
+    /// [`super::set_receipt_text`]) are looked through. Since all these
+    /// values are encrypted, search requires decryption of all items.
+    /// 
+    /// * `query` - text to look for
+    pub fn search(&self, query: &str) -> Result<SearchResults> {
+        let query = query.to_lowercase();
+
+        Ok(SearchResults {
+            accounts: Self::matching(self.accounts()?, &query),
+            categories: Self::matching(self.categories()?, &query),
+            transactions: Self::matching(self.transactions()?, &query),
+            plans: Self::matching(self.plans()?, &query),
+        })
+    }
+
+    /// Delete permanently all previously removed items.
+    /// 
+    /// Actually `remove_*` functions can perform no removal, e.g.
+    /// just mark items as removed. This function therefore permanently
+    /// deletes such marked items.
+    pub fn clean_removed(&self) -> Result<()> {
+        self.storage.clean_removed()
+    }
+
+    /// Deletes permanently items removed longer ago than trash retention
+    /// period (see [`Settings::trash_retention_days`]).
+    /// 
+    /// Nothing is deleted if no retention period is set. Removals are 
+    /// synchronized before items are deleted, so other instances remove
+    /// items too and purge them according to the same shared settings.
+    /// Returns number of deleted items.
+    pub fn empty_trash(&self) -> Result<usize> {
+        let retention_days = match self.settings()?.trash_retention_days {
+            Some(retention_days) => retention_days,
+            None => return Ok(0)
+        };
+
+        let removed_before = Clock::now()
+            .checked_sub_days(chrono::Days::new(retention_days as u64));
+
+        match removed_before {
+            Some(removed_before) => self.storage.purge_removed(removed_before),
+            None => Ok(0)
+        }
+    }
+
+    /// Returns numbers of items and ranges of their dates, e.g. to show
+    /// "12345 transactions since 2019". Numbers are maintained by the
+    /// storage, so this is cheap even for large budgets.
+    pub fn counts(&self) -> Result<Vec<ItemCount>> {
+        self.storage.counts()
+    }
+
+    /// Takes a snapshot of the budget.
+    /// 
+    /// Snapshot allows to try hypothetical changes (e.g. re-budgeting)
+    /// and discard them later with [`Budget::rollback_to`] or keep them
+    /// with [`Budget::drop_snapshot`]. Synchronization is impossible 
+    /// until all snapshots are dropped. Changes made after a snapshot 
+    /// may be lost, if the budget is closed before it is dropped.
+    pub fn snapshot(&self) -> Result<SnapshotId> {
+        self.storage.snapshot()
+    }
+
+    /// Discards all changes made after a snapshot and drops it 
+    /// with all snapshots taken after it.
+    /// 
+    /// * `snapshot` - snapshot to roll back to
+    pub fn rollback_to(&self, snapshot: SnapshotId) -> Result<()> {
+        self.storage.rollback_to(snapshot)?;
+
+        //
+        // Journal positions are rolled back too, hence they
+        // may be reused for other changes later
+        //
+
+        self.clear_report_cache()
+    }
+
+    /// Drops a snapshot with all snapshots taken after it, 
+    /// changes made after them are kept.
+    /// 
+    /// * `snapshot` - snapshot to drop
+    pub fn drop_snapshot(&self, snapshot: SnapshotId) -> Result<()> {
+        self.storage.drop_snapshot(snapshot)
+    }
+
+    /// Enables or disables batching of writes, e.g. for the time of
+    /// a bulk import or edit.
+    /// 
+    /// Batched changes are committed in groups, that cuts disk
+    /// synchronization overhead. They are committed after a given
+    /// number of changes, on [`Budget::flush`] and when the budget
+    /// is closed, but may be lost on a crash.
+    /// 
+    /// * `batch_size` - number of changes per group (`None` disables batching)
+    pub fn set_write_batching(&self, batch_size: Option<usize>) -> Result<()> {
+        self.storage.set_write_batching(batch_size)
+    }
+
+    /// Commits changes, that are batched so far (see
+    /// [`Budget::set_write_batching`]).
+    pub fn flush(&self) -> Result<()> {
+        self.storage.flush()
+    }
+
+    /// Locks transactions dated before a timestamp, e.g. after a month
+    /// is reconciled. Locked transactions cannot be added or removed
+    /// unless the lock is overridden with [`Budget::override_period_lock`].
+    /// 
+    /// Lock is local to the instance: synchronized changes are merged
+    /// regardless of it.
+    /// 
+    /// * `until` - timestamp to lock transactions before
+    pub fn lock_period(&self, until: Timestamp) -> Result<()> {
+        self.storage.set_period_lock(Some(until))
+    }
+
+    /// Removes the period lock.
+    pub fn unlock_period(&self) -> Result<()> {
+        self.storage.set_period_lock(None)
+    }
+
+    /// Returns timestamp, that transactions dated before are locked (if any).
+    pub fn locked_until(&self) -> Result<Option<Timestamp>> {
+        self.storage.period_lock()
+    }
+
+    /// Runs a function, that is allowed to modify locked transactions.
+    /// 
+    /// * `f` - function to run
+    pub fn override_period_lock<R, F>(&self, f: F) -> Result<R>
+    where
+        F: FnOnce(&Self) -> Result<R>
+    {
+        let previous = self.storage.override_period_lock(true);
+        let result = f(self);
+        self.storage.override_period_lock(previous);
+
+        result
+    }
+
+    /// Checks all invariants and returns their violations.
+    /// 
+    /// Empty result means, that the budget is consistent.
+    pub fn check_invariants(&self) -> Result<Vec<Violation>> {
+        let mut violations = Vec::new();
+        for invariant in Invariant::ALL {
+            violations.append(&mut self.check_invariant(invariant)?);
+        }
+
+        Ok(violations)
+    }
+
+    /// Checks a single invariant and returns its violations.
+    /// 
+    /// * `invariant` - invariant to check
+    pub fn check_invariant(&self, invariant: Invariant) -> Result<Vec<Violation>> {
+        let items = self.live_items()?;
+
+        let violations = match invariant {
+            Invariant::AccountBalance => invariants::account_balances(&items),
+            Invariant::TransferLegs => invariants::transfer_legs(&items, St::TRANSFER_INCOME_ID, St::TRANSFER_OUTCOME_ID),
+            Invariant::ForeignKeys => invariants::foreign_keys(&items),
+            Invariant::ChangelogReplay => invariants::changelog_replay(&items, &self.journal_changes(0)?,
+                0 == self.storage.exported_position()?)
+        };
+
+        Ok(violations)
+    }
+
+    /// Checks integrity of the budget.
+    /// 
+    /// Fails with the first violated invariant (see [`Budget::check_invariants`]).
+    pub fn check_integrity(&self) -> Result<()> {
+        match self.check_invariants()?.first() {
+            Some(violation) => Err(Error::from_message_with_extra(INVARIANT_VIOLATED, violation.to_string())
+                .with_kind(ErrorKind::ConsistencyViolation)),
+            None => Ok(())
+        }
+    }
+
+    /// Writes an anonymized copy of the budget, that can be attached 
+    /// to bug reports.
+    /// 
+    /// Copy is a plain (unencrypted) changelog, that adds all items.
+    /// Names, descriptions, notes and symbols are replaced with keyed 
+    /// hashes (equal texts give equal hashes within one export), 
+    /// identifiers and timestamps are kept. Amounts can be scaled 
+    /// additionally, balances are recalculated so that the copy 
+    /// remains consistent.
+    /// 
+    /// * `writer` - destination of the copy
+    /// * `amount_scale` - factor to multiply amounts by (`None` keeps them)
+    pub fn export_anonymized<W: Write>(&self, mut writer: W, amount_scale: Option<f64>) -> Result<()> {
+        let changelog = Anonymizer::new(amount_scale)
+            .anonymize(self.live_items()?);
+
+        writer.write_all(&changelog.to_vec()?)?;
+        writer.flush()?;
+
+        Ok(())
+    }
+
+    /// Writes all transactions in chronological order, e.g. to open
+    /// them in a spreadsheet. Amounts are written in minor units,
+    /// accounts and categories are written by names.
+    /// 
+    /// Transactions are fetched, decrypted and written by ranges of
+    /// dates, so memory usage does not depend on the size of the
+    /// budget. Progress is reported after each range. Returns number
+    /// of written transactions.
+    /// 
+    /// * `writer` - destination of the export
+    /// * `format` - format of the export
+    /// * `progress` - receiver of progress reports
+    pub fn export_transactions<W: Write>(&self, writer: W, format: ExportFormat, progress: &dyn Progress) -> Result<usize> {
+        let accounts = self.accounts()?
+            .into_iter()
+            .filter_map(|account| Some((account.id?, account.name)))
+            .collect();
+
+        let categories = self.categories()?
+            .into_iter()
+            .filter_map(|category| Some((category.id?, category.name)))
+            .collect();
+
+        let mut exported = TransactionWriter::new(std::io::BufWriter::new(writer), format, accounts, categories)?;
+
+        let counts = self.storage
+            .counts()?
+            .into_iter()
+            .find(|count| count.entity == EntityKind::Transaction);
+
+        let Some(ItemCount { count: total, first: Some(first), last: Some(last), .. }) = counts else {
+            return exported.finish();
+        };
+
+        let mut start = first;
+        while start <= last {
+            let end = start + EXPORT_WINDOW;
+
+            //
+            // Storage returns the newest transactions first
+            //
+
+            for transaction in self.transactions_between(start, end)?.iter().rev() {
+                exported.write(transaction)?;
+            }
+
+            progress.report(Phase::Export, exported.written(), total);
+            start = end;
+        }
+
+        exported.finish()
+    }
+
+    /// Creates a recovery key, that protects emergency exports (see
+    /// [`Budget::export_emergency`]). The previous recovery key (if
+    /// any) is revoked.
+    /// 
+    /// The key is returned only once: it is not stored, so it MUST be
+    /// printed or saved by a user right away.
+    pub fn create_recovery_key(&self) -> Result<RecoveryKey> {
+        let recovery_key = RecoveryKey::generate()?;
+
+        let mut salt = vec![0u8; RECOVERY_SALT_SIZE];
+        Prng::new()
+            .generate(&mut salt)?;
+
+        let export_key = recovery_key.derive_key(&salt)?;
+        let encrypted_export_key = self.crypto_engine
+            .encrypt(&self.key, export_key.as_bytes())?;
+
+        self.storage.set_recovery_record(&RecoveryRecord {
+            salt,
+            export_key: encrypted_export_key.as_bytes().into()
+        })?;
+
+        Ok(recovery_key)
+    }
+
+    /// Checks, that a recovery key is the current one, e.g. that it
+    /// was printed without mistakes. Returns `false` if it is not or
+    /// if there is no recovery key at all.
+    /// 
+    /// * `recovery_key` - recovery key to check
+    pub fn verify_recovery_key(&self, recovery_key: &RecoveryKey) -> Result<bool> {
+        let Some(record) = self.storage.recovery_record()? else {
+            return Ok(false);
+        };
+
+        let export_key = self.crypto_engine
+            .decrypt(&self.key, &record.export_key)?;
+
+        Ok(recovery_key.derive_key(&record.salt)?.as_bytes() == export_key.as_bytes())
+    }
+
+    /// Revokes the recovery key: emergency exports cannot be made until
+    /// a new one is created. Exports made before remain readable with
+    /// the revoked key, hence they should be destroyed as well.
+    /// 
+    /// Returns `false` if there was no recovery key.
+    pub fn revoke_recovery_key(&self) -> Result<bool> {
+        self.storage
+            .remove_recovery_record()
+    }
+
+    /// Writes an emergency export of the budget protected by the 
+    /// recovery key (see [`Budget::create_recovery_key`]).
+    /// 
+    /// Export is a changelog, that adds all items. It can be restored
+    /// with the recovery key only (see [`Budget::restore_emergency`]),
+    /// e.g. into a budget with [`crate::crypto::PassphraseCryptoEngine`],
+    /// if the original key is lost.
+    /// 
+    /// * `writer` - destination of the export
+    pub fn export_emergency<W: Write>(&self, mut writer: W) -> Result<()> {
+        let record = self.storage
+            .recovery_record()?
+            .ok_or(Error::from_message(MISSING_RECOVERY_KEY).with_kind(ErrorKind::NotFound))?;
+
+        let export_key = self.crypto_engine
+            .decrypt(&self.key, &record.export_key)?;
+
+        let changelog = self.crypto_engine
+            .encrypt_symmetric(export_key.as_bytes(), &self.live_changelog()?.to_vec()?)?;
+
+        writer.write_all(EMERGENCY_MAGIC)?;
+        writer.write_all(&record.salt)?;
+        writer.write_all(changelog.as_bytes())?;
+        writer.flush()?;
+
+        Ok(())
+    }
+
+    /// Restores items from an emergency export (see [`Budget::export_emergency`]).
+    /// 
+    /// Budget is expected to be a fresh one made by another instance:
+    /// items, that originate from the current instance, are skipped
+    /// the same way as in synchronization.
+    /// 
+    /// * `recovery_key` - recovery key, that protects the export
+    /// * `reader` - source of the export
+    pub fn restore_emergency<R: Read>(&self, recovery_key: &RecoveryKey, mut reader: R) -> Result<()> {
+        let mut export = Vec::new();
+        reader.read_to_end(&mut export)?;
+
+        let (salt, changelog) = export
+            .strip_prefix(EMERGENCY_MAGIC)
+            .filter(|rest| rest.len() >= RECOVERY_SALT_SIZE + MIN_EMERGENCY_CIPHERTEXT_SIZE)
+            .map(|rest| rest.split_at(RECOVERY_SALT_SIZE))
+            .ok_or(Error::from_message(MALFORMED_EMERGENCY_EXPORT).with_kind(ErrorKind::Corruption))?;
+
+        let export_key = recovery_key.derive_key(salt)?;
+        let changelog = self.crypto_engine
+            .decrypt_symmetric(export_key.as_bytes(), changelog)
+            .map_err(|_| Error::from_message(WRONG_RECOVERY_KEY).with_kind(ErrorKind::CryptoFailure))?;
+
+        let changelog = Changelog::from_slice(changelog.as_bytes())?;
+
+        self.atomically(|| self.override_period_lock(
+            |budget| budget.merge_changes(&changelog, &FIRST_AFTER_JANUARY_1970)))
+    }
+
+    /// Registers a hook, that runs before each synchronization, 
+    /// e.g. to make a backup. If the hook fails, synchronization
+    /// is not performed and its error is returned.
+    /// 
+    /// * `hook` - hook to register
+    pub fn on_pre_sync<F>(&self, hook: F)
+    where
+        F: Fn() -> Result<()> + 'static
+    {
+        self.hooks
+            .add_pre_sync(Box::new(hook));
+    }
+
+    /// Registers a hook, that runs after each synchronization,
+    /// e.g. to notify a user. It receives result of synchronization.
+    /// 
+    /// * `hook` - hook to register
+    pub fn on_post_sync<F>(&self, hook: F)
+    where
+        F: Fn(&Result<()>) + 'static
+    {
+        self.hooks
+            .add_post_sync(Box::new(hook));
+    }
+
+    /// Registers executable hooks from `hooks` folder of location's 
+    /// configuration directory: `pre-sync` runs before synchronization
+    /// and prevents it if exits with an error, `post-sync` runs after
+    /// synchronization with "succeeded" or "failed" argument.
+    /// 
+    /// * `loc` - location to look hooks up in
+    pub fn add_executable_hooks<L: Location>(&self, loc: &L) {
+        self.hooks
+            .add_executables(loc);
+    }
+
+    /// Performs synchronization with remote instances.
+    /// 
+    /// * `auth` - authentication information for synchronization
+    pub fn perform_sync(&self, auth: &[u8]) -> Result<()> {
+        self.perform_sync_cancellable(auth, &CancellationToken::new())
+    }
+
+    /// Performs synchronization with remote instances, that can be
+    /// cancelled before local changes are merged.
+    /// 
+    /// * `auth` - authentication information for synchronization
+    /// * `cancel` - token to cancel synchronization with
+    pub fn perform_sync_cancellable(&self, auth: &[u8], cancel: &CancellationToken) -> Result<()> {
+        self.perform_sync_with(auth, cancel, &NoProgress)
+    }
+
+    /// Performs synchronization with remote instances, that can be
+    /// cancelled and reports its progress.
+    /// 
+    /// * `auth` - authentication information for synchronization
+    /// * `cancel` - token to cancel synchronization with
+    /// * `progress` - receiver of progress reports
+    #[cfg_attr(feature = "tracing", tracing::instrument(level = "debug", skip_all))]
+    pub fn perform_sync_with(&self, auth: &[u8], cancel: &CancellationToken, progress: &dyn Progress) -> Result<()> {
+        //
+        // Hypothetical changes must never leave this instance
+        //
+
+        if self.storage.has_snapshots() {
+            return Err(Error::from_message(SNAPSHOT_IS_ACTIVE).with_kind(ErrorKind::Locked));
+        }
+
+        self.hooks
+            .run_pre_sync()?;
+
+        let result = self.sync_with_engine(auth, cancel, progress);
+        self.hooks
+            .run_post_sync(&result);
+
+        result
+    }
+
+    /// Returns state of synchronization: associated remote, unsent and
+    /// unmerged commits, number of unexported local changes and time of 
+    /// the latest successful synchronization. Remote is not contacted.
+    pub fn sync_status(&self) -> Result<SyncStatus> {
+        self.sync_engine
+            .status(self)
+    }
+
+    /// Replaces an existsing remote URL with a new one.
+    /// 
+    /// * `remote` - new remote URL
+    pub fn set_remote_url(&self, remote: &str) -> Result<()> {
+        self.sync_engine
+            .change_remote(remote)
+    }
+}
+
+
+impl<Ce, Se, St> Budget<Ce, Se, St>
+where
+    Ce: CryptoEngine,
+    Se: SyncEngine,
+    St: DataStorage
+{
+    fn sync_with_engine(&self, auth: &[u8], cancel: &CancellationToken, progress: &dyn Progress) -> Result<()> {
+        //
+        // Just use the synchronization engine
+        //
+
+        let context = CryptoBuffer::from(auth);
+        self.sync_engine
+            .perform_sync(self.config.instance_id(), self, &context, cancel, progress)?;
+
+        //
+        // Some items had been removed since the previous sync,
+        // but they were pushed to remote, and now it is not
+        // necessary to keep them locally
+        //
+
+        self.clean_removed()
+    }
+
+    fn add_balance_transaction(&self, account: Id, amount: isize, timestamp: Timestamp, 
+        kind: TransactionKind, description: &str) -> Result<()> 
+    {
+        //
+        // Budgets created with older versions have no predefined
+        // adjustment categories, so they are created on demand
+        //
+
+        let (category, category_name, category_type) = if amount < 0 {
+            (St::ADJUSTMENT_OUTCOME_ID, ADJUSTMENT_OUTCOME_CAT_NAME, CategoryType::Outcome)
+        }
+        else {
+            (St::ADJUSTMENT_INCOME_ID, ADJUSTMENT_INCOME_CAT_NAME, CategoryType::Income)
+        };
+
+        self.ensure_predefined_category(category, category_name, category_type)?;
+
+        self.add_transaction(&Transaction {
+            id: None,
+            timestamp,
+            description: description.to_owned(),
+            account_id: account,
+            category_id: category,
+            amount,
+            kind,
+            note: String::new(),
+            custom_fields: CustomFields::new(),
+            spender_id: None,
+            meta_info: MetaInfo::new(Some(Clock::now()), None, None)
+        })
+    }
+
+    fn check_key_pin(crypto_engine: &Ce, storage: &St, key: &Ce::Key, key_id: &Ce::KeyId) -> Result<()> {
+        //
+        // The same key identifier may point to another key (e.g. on
+        // another machine or with a mistyped passphrase), that fails
+        // deep inside decryption. Hence budget is pinned to its key
+        //
+
+        let fingerprint = crypto_engine
+            .key_fingerprint(key)?;
+
+        match storage.key_fingerprint()? {
+            Some(pinned) if pinned != fingerprint => {
+                return Err(Error::from_message_with_extra(KEY_MISMATCH,
+                    format!("budget key: {}, configured key: {} ({})", pinned, key_id.as_string(), fingerprint)).with_kind(ErrorKind::Config));
+            },
+            Some(_) => (),
+            None => storage.set_key_fingerprint(&fingerprint)?
+        }
+
+        Ok(())
+    }
+
+    fn evaluate_holding(&self, holding: &Holding, timestamp: Timestamp) -> Result<Option<isize>> {
+        let value = self.latest_price(&holding.symbol, timestamp)?
+            .map(|price| (price.price as f64 * holding.quantity).round() as isize);
+
+        Ok(value)
+    }
+
+    fn outstanding_principal(&self, loan: &Loan) -> Result<isize> {
+        let paid: isize = self.transactions_with_after(loan.principal_category_id, loan.start_timestamp)?
+            .iter()
+            .filter(|transaction| transaction.account_id == loan.account_id)
+            .map(|transaction| -transaction.amount)
+            .sum();
+
+        Ok((loan.principal - paid).max(0))
+    }
+
+    fn outstanding_principal_at(&self, loan: &Loan, timestamp: Timestamp) -> Result<isize> {
+        if timestamp < loan.start_timestamp {
+            return Ok(0);
+        }
+
+        let paid: isize = self.transactions_with_between(loan.principal_category_id, loan.start_timestamp, timestamp)?
+            .iter()
+            .filter(|transaction| transaction.account_id == loan.account_id)
+            .map(|transaction| -transaction.amount)
+            .sum();
+
+        Ok((loan.principal - paid).max(0))
+    }
+
+    fn transactions_with_after(&self, category: Id, start_timestamp: Timestamp) -> Result<Vec<Transaction>> {
+        self.decrypt_transactions(&self.storage.transactions_with_after(category, start_timestamp)?)
+    }
+
+    fn insert_transaction(&self, transaction: &Transaction) -> Result<()> {
+        //
+        // Amount is considered to have a proper sign,
+        // so I just add it to a corresponding account's
+        // balance.
+        // Change timestamp for account should not be 
+        // modified in this case, so I don't modify it 
+        // in account instance.
+        //
+
+        let mut decrypted_account = self.decrypt_account(
+            &self.storage.account(transaction.account_id)?)?;
+
+        decrypted_account.balance += transaction.amount;
+
+        //
+        // Well... It would be better to use DB's transactions here,
+        // but it is more complicated though. 
+        // If transaction will not be added, account will not be modified.
+        // If account update fails, one can just remove bad transaction
+        // with `emergency` flag set to `true`.
+        // Hence there is a way to restore consistency.
+        //
+
+        //
+        // Identifier is needed to index the transaction,
+        // so it is generated here if absent
+        //
+
+        let id = transaction.id
+            .unwrap_or_else(|| uuid::Uuid::new_v4().into_bytes());
+
+        let mut encrypted_transaction = self.encrypt_transaction(transaction)?;
+        encrypted_transaction.id = Some(id);
+        encrypted_transaction.meta_info.set_origin_if_absent(self.instance_id());
+
+        self.storage.add_transaction(encrypted_transaction)?;
+        self.storage.update_account(self.encrypt_account(&decrypted_account)?)?;
+
+        self.index_transaction(id, transaction)
+    }
+
+    fn replace_transaction(&self, transaction: &Transaction) -> Result<()> {
+        let id = transaction.id
+            .ok_or(Error::from_message(INVALID_ITEM).with_kind(ErrorKind::InvalidInput))?;
+
+        let stored = self.decrypt_transaction(&self.storage.transaction(id)?)?;
+
+        //
+        // Stored amount is moved out of its account and the new one
+        // is moved into the (possibly another) account. Accounts are
+        // updated after the transaction, so if the update fails, 
+        // balances are left intact
+        //
+
+        let mut encrypted_transaction = self.encrypt_transaction(transaction)?;
+        if encrypted_transaction.meta_info.changed_timestamp.is_none() {
+            encrypted_transaction.meta_info.changed_timestamp = Some(Clock::now());
+        }
+
+        self.storage.update_transaction(encrypted_transaction)?;
+
+        let mut stored_account = self.decrypt_account(
+            &self.storage.account(stored.account_id)?)?;
+
+        stored_account.balance -= stored.amount;
+
+        if stored.account_id != transaction.account_id {
+            self.storage.update_account(self.encrypt_account(&stored_account)?)?;
+            stored_account = self.decrypt_account(
+                &self.storage.account(transaction.account_id)?)?;
+        }
+
+        stored_account.balance += transaction.amount;
+
+        self.storage.update_account(self.encrypt_account(&stored_account)?)?;
+
+        self.index_transaction(id, transaction)
+    }
+
+    fn index_transaction(&self, id: Id, transaction: &Transaction) -> Result<()> {
+        let external_keys = external::external_ids(&transaction.custom_fields)
+            .map(|(source, external_id)| self.external_key(source, external_id))
+            .collect::<Result<Vec<_>>>()?;
+
+        let locations = geo::location(&transaction.custom_fields)
+            .map(|location| self.encrypt_location(&location))
+            .into_iter()
+            .collect::<Result<Vec<_>>>()?;
+
+        self.storage
+            .set_indexed_values(id, IndexedField::ExternalId, &external_keys)?;
+
+        self.storage
+            .set_indexed_values(id, IndexedField::Location, &locations)
+    }
+
+    fn ensure_indexed(&self) -> Result<()> {
+        //
+        // Index is built lazily on first lookup: older budgets have
+        // no index, and it is rebuilt when its version changes
+        //
+
+        if INDEX_VERSION <= self.storage.index_version()? {
+            return Ok(());
+        }
+
+        for transaction in self.transactions()? {
+            self.index_transaction(transaction.id.unwrap(), &transaction)?;
+        }
+
+        self.storage
+            .set_index_version(INDEX_VERSION)
+    }
+
+    fn external_key(&self, source: &str, external_id: &str) -> Result<Vec<u8>> {
+        //
+        // Keys are deterministic, so they are looked up by equality.
+        // Length of a source name is prepended, so that different
+        // pairs of sources and identifiers never make the same key
+        //
+
+        let plaintext = CryptoBuffer::from(format!("{}:{}{}", source.len(), source, external_id).into_bytes());
+        let key = self.crypto_engine
+            .encrypt_deterministic(&self.key, plaintext.as_bytes())?;
+
+        Ok(key.as_bytes().to_vec())
+    }
+
+    fn encrypt_location(&self, location: &GeoLocation) -> Result<Vec<u8>> {
+        let coordinates = CryptoBuffer::from([location.latitude.to_le_bytes(), location.longitude.to_le_bytes()].concat());
+        let encrypted_location = self.crypto_engine
+            .encrypt(&self.key, coordinates.as_bytes())?;
+
+        Ok(encrypted_location.as_bytes().into())
+    }
+
+    fn decrypt_location(&self, data: &[u8]) -> Result<GeoLocation> {
+        let decrypted = self.crypto_engine
+            .decrypt(&self.key, data)?;
+
+        let (latitude, longitude) = decrypted
+            .as_bytes()
+            .split_at(std::mem::size_of::<f64>());
+
+        Ok(GeoLocation {
+            latitude: f64::from_le_bytes(latitude.try_into().map_err(Error::from)?),
+            longitude: f64::from_le_bytes(longitude.try_into().map_err(Error::from)?),
+            place: None
+        })
+    }
+
+    fn insert_account(&self, account: &Account) -> Result<()> {
+        let mut account = self.encrypt_account(account)?;
+        account.meta_info.set_origin_if_absent(self.instance_id());
+
+        self.storage.add_account(account)
+    }
+
+    fn insert_category(&self, category: &Category) -> Result<()> {
+        let mut category = self.encrypt_category(category)?;
+        category.meta_info.set_origin_if_absent(self.instance_id());
+
+        self.storage.add_category(category)
+    }
+
+    fn insert_plan(&self, plan: &Plan) -> Result<()> {
+        let mut plan = self.encrypt_plan(plan)?;
+        plan.meta_info.set_origin_if_absent(self.instance_id());
+        
+        self.storage.add_plan(plan)
+    }
+
+    fn insert_loan(&self, loan: &Loan) -> Result<()> {
+        let mut loan = self.encrypt_loan(loan)?;
+        loan.meta_info.set_origin_if_absent(self.instance_id());
+
+        self.storage.add_loan(loan)
+    }
+
+    fn insert_holding(&self, holding: &Holding) -> Result<()> {
+        let mut holding = self.encrypt_holding(holding)?;
+        holding.meta_info.set_origin_if_absent(self.instance_id());
+
+        self.storage.add_holding(holding)
+    }
+
+    fn insert_person(&self, person: &Person) -> Result<()> {
+        let mut person = self.encrypt_person(person)?;
+        person.meta_info.set_origin_if_absent(self.instance_id());
+
+        self.storage.add_person(person)
+    }
+
+    fn replace_person(&self, person: &Person) -> Result<()> {
+        let mut person = self.encrypt_person(person)?;
+        if person.meta_info.changed_timestamp.is_none() {
+            person.meta_info.changed_timestamp = Some(Clock::now());
+        }
+
+        self.storage.update_person(person)
+    }
+
+    fn insert_category_group(&self, group: &CategoryGroup) -> Result<()> {
+        let mut group = self.encrypt_category_group(group)?;
+        group.meta_info.set_origin_if_absent(self.instance_id());
+
+        self.storage.add_category_group(group)
+    }
+
+    fn replace_category_group(&self, group: &CategoryGroup) -> Result<()> {
+        let mut group = self.encrypt_category_group(group)?;
+        if group.meta_info.changed_timestamp.is_none() {
+            group.meta_info.changed_timestamp = Some(Clock::now());
+        }
+
+        self.storage.update_category_group(group)
+    }
+
+    fn replace_holding(&self, holding: &Holding) -> Result<()> {
+        let mut holding = self.encrypt_holding(holding)?;
+        if holding.meta_info.changed_timestamp.is_none() {
+            holding.meta_info.changed_timestamp = Some(Clock::now());
+        }
+
+        self.storage.update_holding(holding)
+    }
+
+    fn insert_price_point(&self, price: &PricePoint) -> Result<()> {
+        let mut price = self.encrypt_price_point(price)?;
+        price.meta_info.set_origin_if_absent(self.instance_id());
+
+        self.storage.add_price(price)
+    }
+
+    fn ensure_valid<T: Validate>(item: &T) -> Result<()> {
+        let errors = item.validate();
+        if errors.is_empty() {
+            return Ok(());
+        }
+
+        let details = errors
+            .iter()
+            .map(ValidationError::to_string)
+            .collect::<Vec<_>>()
+            .join("; ");
+
+        Err(Error::from_message_with_extra(INVALID_ITEM, details).with_kind(ErrorKind::InvalidInput))
+    }
+
+    fn atomically<R, F>(&self, f: F) -> Result<R>
+    where
+        F: FnOnce() -> Result<R>
+    {
+        let snapshot = self.storage.snapshot()?;
+        let result = f();
+
+        match result {
+            Ok(_) => self.storage.drop_snapshot(snapshot)?,
+            Err(_) => self.storage.rollback_to(snapshot)?
+        }
+
+        result
+    }
+
+    fn ensure_mergeable(keep: Id, remove: Id, compatible: bool) -> Result<()> {
+        if keep == remove {
+            return Err(Error::from_message_with_extra(INCOMPATIBLE_MERGE, "Item cannot be merged into itself")
+                .with_kind(ErrorKind::InvalidInput));
+        }
+
+        if !compatible {
+            return Err(Error::from_message_with_extra(INCOMPATIBLE_MERGE, "Items have different types")
+                .with_kind(ErrorKind::InvalidInput));
+        }
+
+        Ok(())
+    }
+
+    fn move_balance(&self, mut kept: Account, removed: &Account) -> Result<()> {
+        //
+        // Only amounts of transactions are moved, initial balance
+        // of the removed account is not a part of the kept one
+        //
+
+        kept.balance += removed.balance - removed.initial_balance;
+
+        self.storage.update_account(self.encrypt_account(&kept)?)
+    }
+
+    fn record_merge(&self, kind: MergeKind, kept: Id, removed: Id, timestamp: Timestamp) -> Result<()> {
+        let mut meta_info = MetaInfo::new(Some(timestamp), None, None);
+        meta_info.set_origin_if_absent(self.instance_id());
+
+        self.storage.add_merge(Merge {
+            kind,
+            kept,
+            removed,
+            meta_info
+        })
+    }
+
+    fn reencrypt_descriptions(&self, deterministic: bool) -> Result<usize> {
+        //
+        // Removed transactions are not re-encrypted: they are
+        // never queried, and decryption accepts both modes
+        //
+
+        self.storage.set_deterministic_descriptions(deterministic)?;
+
+        let transactions = self.storage.transactions()?;
+        let descriptions: Vec<_> = transactions
+            .iter()
+            .map(|transaction| transaction.description.as_slice())
+            .collect();
+
+        let decrypted_descriptions = self.decrypt_descriptions(&descriptions)?;
+        let encrypted_descriptions = match deterministic {
+            true => decrypted_descriptions
+                .iter()
+                .map(|description| self.encrypt_description(description))
+                .collect::<Result<Vec<_>>>()?,
+            false => {
+                let plaintexts: Vec<_> = decrypted_descriptions
+                    .iter()
+                    .map(String::as_bytes)
+                    .collect();
+
+                self.crypto_engine
+                    .encrypt_batch(&self.key, &plaintexts)?
+            }
+        };
+
+        for (transaction, encrypted_description) in transactions.iter().zip(encrypted_descriptions) {
+            self.storage.set_transaction_description(transaction.id.unwrap(), encrypted_description.as_bytes().into())?;
+        }
+
+        Ok(transactions.len())
+    }
+
+    fn convert_transaction_dates(&self, policy: TimeZonePolicy) -> Result<usize> {
+        let mut converted = 0;
+        for transaction in self.storage.transactions()? {
+            let canonical = TxnDate::from_instant(&transaction.timestamp, policy)
+                .to_canonical();
+
+            if canonical != transaction.timestamp {
+                self.storage.set_transaction_timestamp(transaction.id.unwrap(), canonical)?;
+                converted += 1;
+            }
+        }
+
+        self.storage.set_canonical_dates()?;
+        Ok(converted)
+    }
+
+    fn live_changelog(&self) -> Result<Changelog> {
+        let items = self.live_items()?;
+
+        //
+        // All live items are added, merges are not needed, since
+        // merged items are absent already
+        //
+
+        let mut changelog = Changelog::new();
+        changelog.accounts.added = items.accounts;
+        changelog.categories.added = items.categories;
+        changelog.category_groups.added = items.category_groups;
+        changelog.persons.added = items.persons;
+        changelog.transactions.added = items.transactions;
+        changelog.plans.added = items.plans;
+        changelog.loans.added = items.loans;
+        changelog.holdings.added = items.holdings;
+        changelog.prices.added = items.prices;
+        changelog.settings = self.stored_shared_settings()?;
+
+        Ok(changelog)
+    }
+
+    fn live_items(&self) -> Result<LiveItems> {
+        Ok(LiveItems {
+            accounts: self.accounts()?,
+            categories: self.categories()?,
+            persons: self.persons()?,
+            category_groups: self.category_groups()?,
+            transactions: self.transactions()?,
+            plans: self.plans()?,
+            loans: self.loans()?,
+            holdings: self.holdings()?,
+            prices: self.price_points()?
+        })
+    }
+
+    fn private_items(&self) -> Result<HashSet<Id>> {
+        let private = self.storage
+            .private_items(self.instance_id().into_bytes())?;
+
+        Ok(private.into_iter().collect())
+    }
+
+    fn matching<T: Searchable>(items: Vec<T>, query: &str) -> Vec<T> {
+        items
+            .into_iter()
+            .filter(|item| item.matches(query))
+            .collect()
+    }
+
+    fn ensure_predefined_category(&self, category: Id, name: &str, category_type: CategoryType) -> Result<()> {
+        let exists = self.storage
+            .categories_of(category_type)?
+            .iter()
+            .any(|existing| existing.id == Some(category));
+
+        if exists {
+            return Ok(());
+        }
+
+        self.add_category(&Category { 
+            id: Some(category), 
+            name: name.to_owned(),
+            category_type,
+            note: String::new(),
+            custom_fields: CustomFields::new(),
+            meta_info: MetaInfo::new(Some(*JANUARY_1970), None, None)
+        })
+    }
+}
+
+
+impl<Ce, Se, St> Syncable for Budget<Ce, Se, St> 
+where
+    Ce: CryptoEngine,
+    Se: SyncEngine,
+    St: DataStorage
+{
+    type Context = CryptoBuffer;
+
+    type InstanceId = InstanceId;
+
+    type Scope = Scope;
+
+    fn scopes(&self) -> Vec<Self::Scope> {
+        Scope::ALL.to_vec()
+    }
+
+    fn pending_changes(&self) -> Result<usize> {
+        Ok(self.export_local_changes(self.storage.exported_position()?)?
+            .len())
+    }
+
+    fn merge_and_export_changes<Ts, Li, Cl, Ss>(&self, timestamp_rw: &mut Ts, last_instance_rw: &mut Li, 
+        changelog_rw: &mut Cl, state_snapshot_rw: &mut Ss, last_sync: Option<&Timestamp>, auth: &Self::Context) -> Result<()>
+    where
+        Ts: std::io::Read + std::io::Write + std::io::Seek,
+        Li: std::io::Read + std::io::Write + std::io::Seek,
+        Cl: std::io::Read + std::io::Write + std::io::Seek,
+        Ss: std::io::Read + std::io::Write + std::io::Seek
+    {
+        let mut cumulative_changelog = if Self::empty_sync_files(timestamp_rw, last_instance_rw, changelog_rw)? {
+            //
+            // Files are correct, but empty
+            // Just return empty changelog
+            //
+
+            Changelog::new()
+        }
+        else {
+            let (_, remote_changelog) = self.read_changelog(timestamp_rw, last_instance_rw, changelog_rw, auth)?;
+            remote_changelog
+        };
+
+        //
+        // Merge remote and export local changes
+        // Then join them together
+        //
+
+        //
+        // Local changes are taken from the storage's journal, hence they
+        // do not depend on local clock. Changes made during merge are 
+        // skipped later, since the exported position is moved past them
+        //
+
+        let local_changelog = self.export_local_changes(self.storage.exported_position()?)?;
+
+        //
+        // Instance, that has never been synchronized, starts from the latest
+        // snapshot of full state (if any), then only changes, that are not 
+        // covered by the snapshot, are merged
+        //
+
+        let state_snapshot = match last_sync {
+            Some(last_sync) if last_sync.le(&FIRST_AFTER_JANUARY_1970) => self.read_state_snapshot(state_snapshot_rw, auth),
+            _ => None
+        };
+
+        if let Some(last_sync) = last_sync {
+            self.override_period_lock(|budget| match &state_snapshot {
+                Some((taken, snapshot)) => {
+                    budget.merge_changes(snapshot, last_sync)?;
+                    budget.merge_changes(&cumulative_changelog.after_snapshot(snapshot, taken), last_sync)
+                },
+                None => budget.merge_changes(&cumulative_changelog, last_sync)
+            })?;
+        }
+        
+        cumulative_changelog.append(local_changelog)?;
+
+        //
+        // Derive new encryption key, encrypt and write updated values
+        //
+
+        let local_timestamp = Clock::now();
+        let local_instance = self.instance_id();
+
+        Self::prepare_for_overwrite(timestamp_rw)?;
+        Self::write_timestamp(&local_timestamp, timestamp_rw)?;
+
+        Self::prepare_for_overwrite(last_instance_rw)?;
+        Self::write_instance(&local_instance, last_instance_rw)?;
+
+        let local_salt = Self::make_key_derivation_salt(&local_timestamp, &local_instance)?;
+        let encryption_key = Kdf::derive_key(auth.as_bytes(), local_salt.as_bytes(), 
+            self.crypto_engine.symmetric_key_length())?;
+
+        let cumulative_changelog = self.crypto_engine
+            .encrypt_symmetric(encryption_key.as_bytes(), &cumulative_changelog.to_vec()?)?;
+
+        Self::prepare_for_overwrite(changelog_rw)?;
+        changelog_rw.write_all(cumulative_changelog.as_bytes())?;
+
+        if Self::state_snapshot_due(state_snapshot_rw, &local_timestamp)? {
+            let state_snapshot = self.crypto_engine
+                .encrypt_symmetric(encryption_key.as_bytes(), &self.state_snapshot()?.to_vec()?)?;
+
+            Self::prepare_for_overwrite(state_snapshot_rw)?;
+            Self::write_timestamp(&local_timestamp, state_snapshot_rw)?;
+            Self::write_instance(local_instance, state_snapshot_rw)?;
+            state_snapshot_rw.write_all(&(state_snapshot.as_bytes().len() as u64).to_le_bytes())?;
+            state_snapshot_rw.write_all(state_snapshot.as_bytes())?;
+        }
+
+        self.storage
+            .set_exported_position(self.storage.journal_position()?)?;
+
+        Ok(())
+    }
+
+    fn export_diff<W: std::io::Write>(&self, diff_w: &mut W, auth: &Self::Context) -> Result<bool> {
+        let local_changelog = self.export_local_changes(self.storage.exported_position()?)?;
+        if 0 == local_changelog.len() {
+            return Ok(false);
+        }
+
+        //
+        // Diff is laid out as synchronization files: timestamp and
+        // instance, that the key is derived from, then changelog
+        //
+
+        let local_timestamp = Clock::now();
+        let local_instance = self.instance_id();
+
+        let local_salt = Self::make_key_derivation_salt(&local_timestamp, local_instance)?;
+        let encryption_key = Kdf::derive_key(auth.as_bytes(), local_salt.as_bytes(), 
+            self.crypto_engine.symmetric_key_length())?;
+
+        let local_changelog = self.crypto_engine
+            .encrypt_symmetric(encryption_key.as_bytes(), &local_changelog.to_vec()?)?;
 
-            let remote_salt = Self::make_key_derivation_salt(&remote_timestamp, &remote_instance)?;
-            let decryption_key = Kdf::derive_key(auth.as_bytes(), remote_salt.as_bytes(), 
-                self.crypto_engine.symmetric_key_length())?;
+        Self::write_timestamp(&local_timestamp, diff_w)?;
+        Self::write_instance(local_instance, diff_w)?;
+        diff_w.write_all(local_changelog.as_bytes())?;
 
-            //
-            // Read and decrypt changelog
-            //
+        Ok(true)
+    }
 
-            let mut remote_changelog = Vec::new();
-            changelog_rw.read_to_end(&mut remote_changelog)?;
+    fn merge_diff<R: std::io::Read>(&self, diff_r: &mut R, auth: &Self::Context) -> Result<()> {
+        let mut diff = Vec::new();
+        diff_r.read_to_end(&mut diff)?;
 
-            let remote_changelog = self.crypto_engine
-                .decrypt_symmetric(decryption_key.as_bytes(), &remote_changelog)?;
+        if diff.len() < DIFF_HEADER_SIZE {
+            return Err(Error::from_message(CORRUPTED_REMOTE_CHANGELOG).with_kind(ErrorKind::Corruption));
+        }
 
-            Changelog::from_slice(remote_changelog.as_bytes())?
-        };
+        let (mut timestamp, rest) = diff.split_at(std::mem::size_of::<i64>());
+        let (mut instance, mut changelog) = rest.split_at(DIFF_HEADER_SIZE - timestamp.len());
 
-        //
-        // Merge remote and export local changes
-        // Then join them together
-        //
+        let (_, changelog) = self.read_changelog(&mut timestamp, &mut instance, &mut changelog, auth)?;
 
-        let local_changelog = self.export_local_changes(last_sync)?;
-        self.merge_changes(&cumulative_changelog, last_sync)?;
-        
-        cumulative_changelog.append(local_changelog)?;
+        self.override_period_lock(|budget| budget.merge_changes(&changelog, &FIRST_AFTER_JANUARY_1970))?;
 
         //
-        // Derive new encryption key, encrypt and write updated values
+        // Merged changes are not local ones, hence they are skipped
+        // by the next export the same way as in changelog merging
         //
 
-        let local_timestamp = Clock::now();
-        let local_instance = self.instance_id();
+        self.storage
+            .set_exported_position(self.storage.journal_position()?)
+    }
 
-        Self::prepare_for_overwrite(timestamp_rw)?;
-        Self::write_timestamp(&local_timestamp, timestamp_rw)?;
+    fn join_changelog<Ts, Li, Cl>(&self, timestamp_r: &mut Ts, last_instance_r: &mut Li, changelog_rw: &mut Cl,
+        joined: (&[u8], &[u8], &[u8]), auth: &Self::Context) -> Result<()>
+    where
+        Ts: std::io::Read + std::io::Seek,
+        Li: std::io::Read + std::io::Seek,
+        Cl: std::io::Read + std::io::Write + std::io::Seek
+    {
+        timestamp_r.rewind()?;
+        last_instance_r.rewind()?;
+        changelog_rw.rewind()?;
 
-        Self::prepare_for_overwrite(last_instance_rw)?;
-        Self::write_instance(&local_instance, last_instance_rw)?;
+        let (key, mut changelog) = self.read_changelog(timestamp_r, last_instance_r, changelog_rw, auth)?;
 
-        let local_salt = Self::make_key_derivation_salt(&local_timestamp, &local_instance)?;
-        let encryption_key = Kdf::derive_key(auth.as_bytes(), local_salt.as_bytes(), 
-            self.crypto_engine.symmetric_key_length())?;
+        let (mut joined_timestamp, mut joined_instance, mut joined_changelog) = joined;
+        let (_, joined_changelog) = self.read_changelog(&mut joined_timestamp, &mut joined_instance, 
+            &mut joined_changelog, auth)?;
 
-        let cumulative_changelog = self.crypto_engine
-            .encrypt_symmetric(encryption_key.as_bytes(), &cumulative_changelog.to_vec()?)?;
+        //
+        // Both changelogs are cumulative, so their union contains
+        // changes of both histories. Local data has all of them
+        // already, hence nothing is merged
+        //
+
+        changelog.append(joined_changelog)?;
+
+        let changelog = self.crypto_engine
+            .encrypt_symmetric(key.as_bytes(), &changelog.to_vec()?)?;
 
         Self::prepare_for_overwrite(changelog_rw)?;
-        changelog_rw.write_all(cumulative_changelog.as_bytes())?;
+        changelog_rw.write_all(changelog.as_bytes())?;
 
         Ok(())
     }
@@ -547,6 +3235,46 @@ where
     Se: SyncEngine,
     St: DataStorage
 {
+    fn read_changelog<Ts, Li, Cl>(&self, timestamp_r: &mut Ts, last_instance_r: &mut Li, changelog_r: &mut Cl,
+        auth: &CryptoBuffer) -> Result<(CryptoBuffer, Changelog)>
+    where
+        Ts: std::io::Read,
+        Li: std::io::Read,
+        Cl: std::io::Read
+    {
+        //
+        // Read remote timestamp and instance identifiers to derive decryption key
+        //
+
+        let remote_timestamp = Self::read_timestamp(timestamp_r)?;
+        let remote_instance = Self::read_instance(last_instance_r)?;
+
+        let remote_salt = Self::make_key_derivation_salt(&remote_timestamp, &remote_instance)?;
+        let decryption_key = Kdf::derive_key(auth.as_bytes(), remote_salt.as_bytes(), 
+            self.crypto_engine.symmetric_key_length())?;
+
+        //
+        // Read and decrypt changelog
+        //
+
+        let mut remote_changelog = Vec::new();
+        changelog_r.read_to_end(&mut remote_changelog)?;
+
+        let remote_changelog = self.crypto_engine
+            .decrypt_symmetric(decryption_key.as_bytes(), &remote_changelog)?;
+
+        //
+        // Changelog is authenticated by encryption, so errors here mean,
+        // that the writing instance produced a damaged one
+        //
+
+        let remote_changelog = Changelog::from_slice(remote_changelog.as_bytes())
+            .map_err(|error| Error::from_message_with_extra(CORRUPTED_REMOTE_CHANGELOG, 
+                format!("instance: {}", remote_instance)).with_kind(ErrorKind::Corruption).with_source(error))?;
+
+        Ok((decryption_key, remote_changelog))
+    }
+
     fn empty_sync_files<Ts, Li, Cl>(timestamp: &mut Ts, last_instance: &mut Li, changelog: &mut Cl) -> Result<bool>
     where
         Ts: std::io::Seek,
@@ -572,7 +3300,7 @@ where
         match (timestamp_size, last_instance_size, changelog_size) {
             (0, 0, 0) => return Ok(true),
             (1.., 1.., _) => return Ok(false),
-            _ => return Err(Error::from_message("msg"))
+            _ => return Err(Error::from_message("msg").with_kind(ErrorKind::Corruption))
         };
     }
 
@@ -584,7 +3312,7 @@ where
         };
 
         Timestamp::from_timestamp(seconds, 0)
-            .ok_or(Error::from_message(MALFORMED_TIMESTAMP))
+            .ok_or(Error::from_message(MALFORMED_TIMESTAMP).with_kind(ErrorKind::Corruption))
     }
 
     fn write_timestamp<W: std::io::Write>(timestamp: &Timestamp, timestamp_writer: &mut W) -> Result<()> {
@@ -623,43 +3351,192 @@ where
         Ok(CryptoBuffer::from(salt))
     }
 
-    fn export_local_changes(&self, last_sync: &Timestamp) -> Result<Changelog> {
-        let mut local_changelog = Changelog::new();
+    fn read_state_snapshot<R>(&self, state_snapshot: &mut R, auth: &CryptoBuffer) -> Option<(Timestamp, Changelog)>
+    where
+        R: std::io::Read + std::io::Seek
+    {
+        //
+        // Snapshot only speeds up the first synchronization, so if it 
+        // cannot be used, the whole changelog is replayed instead
+        //
+
+        let read = |state_snapshot: &mut R| -> Result<Option<(Timestamp, Changelog)>> {
+            let size = state_snapshot.seek(std::io::SeekFrom::End(0))?;
+            state_snapshot.rewind()?;
+
+            if 0 == size {
+                return Ok(None);
+            }
+
+            let taken = Self::read_timestamp(state_snapshot)?;
+            let instance = Self::read_instance(state_snapshot)?;
+
+            let mut length = [0; std::mem::size_of::<u64>()];
+            state_snapshot.read_exact(&mut length)?;
+
+            let mut encrypted = Vec::new();
+            state_snapshot
+                .take(u64::from_le_bytes(length))
+                .read_to_end(&mut encrypted)?;
+
+            let salt = Self::make_key_derivation_salt(&taken, &instance)?;
+            let decryption_key = Kdf::derive_key(auth.as_bytes(), salt.as_bytes(), 
+                self.crypto_engine.symmetric_key_length())?;
+
+            let snapshot = self.crypto_engine
+                .decrypt_symmetric(decryption_key.as_bytes(), &encrypted)?;
+
+            Ok(Some((taken, Changelog::from_slice(snapshot.as_bytes())?)))
+        };
+
+        read(state_snapshot)
+            .inspect_err(|_| { trace_debug!("snapshot of full state is unusable, replaying changelog"); })
+            .ok()
+            .flatten()
+    }
+
+    fn state_snapshot_due<S>(state_snapshot: &mut S, now: &Timestamp) -> Result<bool> 
+    where
+        S: std::io::Read + std::io::Seek
+    {
+        let size = state_snapshot.seek(std::io::SeekFrom::End(0))?;
+        state_snapshot.rewind()?;
+
+        if 0 == size {
+            return Ok(true);
+        }
 
         //
-        // I don't filter out "foreign" items, because it is assumed, that
-        // there are none of them since this instance has not been synced
-        // during the interval (last_sync, now]
+        // Unreadable snapshot is replaced with a new one
+        //
+
+        Ok(Self::read_timestamp(state_snapshot)
+            .map_or(true, |taken| taken + STATE_SNAPSHOT_INTERVAL <= *now))
+    }
+
+    fn state_snapshot(&self) -> Result<Changelog> {
+        let mut snapshot = self.live_changelog()?;
+        snapshot.remove_private(&self.private_items()?);
+
+        Ok(snapshot)
+    }
+
+    #[cfg_attr(feature = "tracing", tracing::instrument(level = "debug", skip_all))]
+    fn export_local_changes(&self, base: JournalPosition) -> Result<Changelog> {
+        let mut local_changelog = self.journal_changes(base)?;
+
         //
+        // Private items never leave this instance
+        //
+
+        local_changelog.remove_private(&self.private_items()?);
 
-        local_changelog.accounts.added = self.accounts_added_since(*last_sync)?;
-        local_changelog.accounts.changed = self.accounts_changed_since(*last_sync)?;
-        local_changelog.accounts.removed = self.accounts_removed_since(*last_sync)?;
+        Ok(local_changelog)
+    }
+
+    fn journal_changes(&self, base: JournalPosition) -> Result<Changelog> {
+        let mut local_changelog = Changelog::new();
 
-        local_changelog.categories.added = self.categories_added_since(*last_sync)?;
-        local_changelog.categories.changed = self.categories_changed_since(*last_sync)?;
-        local_changelog.categories.removed = self.categories_removed_since(*last_sync)?;
+        for scope in self.scopes() {
+            local_changelog.append(self.scope_changes_since(scope, base)
+                .with_context(|| format!("exporting {}", scope))?)?;
+        }
 
-        local_changelog.plans.added = self.plans_added_since(*last_sync)?;
-        local_changelog.plans.changed = self.plans_changed_since(*last_sync)?;
-        local_changelog.plans.removed = self.plans_removed_since(*last_sync)?;
+        local_changelog.settings = self.storage
+            .settings_changed_since(base)?
+            .map(|settings| self.decrypt_settings(&settings))
+            .transpose()?;
 
-        local_changelog.transactions.added = self.transactions_added_since(*last_sync)?;
-        local_changelog.transactions.changed = self.transactions_changed_since(*last_sync)?;
-        local_changelog.transactions.removed = self.transactions_removed_since(*last_sync)?;
+        local_changelog.merges = self.storage.merges_since(base)?;
 
         Ok(local_changelog)
     }
 
+    fn scope_changes_since(&self, scope: Scope, base: JournalPosition) -> Result<Changelog> {
+        let mut changelog = Changelog::new();
+
+        //
+        // I don't filter out "foreign" items, because it is assumed, that
+        // there are none of them since merged changes are never exported
+        //
+
+        match scope {
+            Scope::Accounts => {
+                changelog.accounts.added = self.accounts_added_since(base)?;
+                changelog.accounts.changed = self.accounts_changed_since(base)?;
+                changelog.accounts.removed = self.accounts_removed_since(base)?;
+            },
+            Scope::Categories => {
+                changelog.categories.added = self.categories_added_since(base)?;
+                changelog.categories.changed = self.categories_changed_since(base)?;
+                changelog.categories.removed = self.categories_removed_since(base)?;
+            },
+            Scope::CategoryGroups => {
+                changelog.category_groups.added = self.category_groups_added_since(base)?;
+                changelog.category_groups.changed = self.category_groups_changed_since(base)?;
+                changelog.category_groups.removed = self.category_groups_removed_since(base)?;
+            },
+            Scope::Persons => {
+                changelog.persons.added = self.persons_added_since(base)?;
+                changelog.persons.changed = self.persons_changed_since(base)?;
+                changelog.persons.removed = self.persons_removed_since(base)?;
+            },
+            Scope::Plans => {
+                changelog.plans.added = self.plans_added_since(base)?;
+                changelog.plans.changed = self.plans_changed_since(base)?;
+                changelog.plans.removed = self.plans_removed_since(base)?;
+            },
+            Scope::Transactions => {
+                changelog.transactions.added = self.transactions_added_since(base)?;
+                changelog.transactions.changed = self.transactions_changed_since(base)?;
+                changelog.transactions.removed = self.transactions_removed_since(base)?;
+            },
+            Scope::Loans => {
+                changelog.loans.added = self.loans_added_since(base)?;
+                changelog.loans.changed = self.loans_changed_since(base)?;
+                changelog.loans.removed = self.loans_removed_since(base)?;
+            },
+            Scope::Holdings => {
+                changelog.holdings.added = self.holdings_added_since(base)?;
+                changelog.holdings.changed = self.holdings_changed_since(base)?;
+                changelog.holdings.removed = self.holdings_removed_since(base)?;
+            },
+            Scope::Prices => {
+                changelog.prices.added = self.prices_added_since(base)?;
+                changelog.prices.changed = self.prices_changed_since(base)?;
+                changelog.prices.removed = self.prices_removed_since(base)?;
+            }
+        }
+
+        Ok(changelog)
+    }
+
+    #[cfg_attr(feature = "tracing", tracing::instrument(level = "debug", skip_all))]
     fn merge_changes(&self, changelog: &Changelog, last_sync: &Timestamp) -> Result<()> {
         //
         // First, added items are processed in the following order:
         //  1. Accounts
         //  2. Categories
-        //  3. Plans
-        //  4. Transactions
+        //  3. Groups of categories
+        //  4. Persons
+        //  5. Plans
+        //  6. Transactions
+        //  7. Loans
+        //  8. Holdings
+        //  9. Prices
+        //
+        // Items are not validated here: they were validated by instances,
+        // that made them, and a rejected item would break synchronization
         //
 
+        // Items may reference ones merged into others meanwhile, hence
+        // transactions are repointed before being added (balances of
+        // accounts depend on them), other items are repointed later
+        //
+
+        let merges = self.known_merges(changelog)?;
+        let redirects = Self::merge_redirects(&merges);
+
         self.merge_step(&changelog.accounts.added,
             |account| {
                 account.meta_info.added_timestamp.unwrap().ge(last_sync) &&
@@ -674,7 +3551,7 @@ where
                 let mut account = account.clone();
                 account.balance = account.initial_balance;
 
-                self.add_account(&account)
+                self.insert_account(&account)
             }
         )?;
 
@@ -683,7 +3560,23 @@ where
                 category.meta_info.added_timestamp.unwrap().ge(last_sync) &&
                 category.meta_info.origin.unwrap() != self.instance_id().into_bytes()
             },
-            |category| { self.add_category(category) }
+            |category| { self.insert_category(category) }
+        )?;
+
+        self.merge_step(&changelog.category_groups.added,
+            |group| {
+                group.meta_info.added_timestamp.unwrap().ge(last_sync) &&
+                group.meta_info.origin.unwrap() != self.instance_id().into_bytes()
+            },
+            |group| { self.insert_category_group(group) }
+        )?;
+
+        self.merge_step(&changelog.persons.added,
+            |person| {
+                person.meta_info.added_timestamp.unwrap().ge(last_sync) &&
+                person.meta_info.origin.unwrap() != self.instance_id().into_bytes()
+            },
+            |person| { self.insert_person(person) }
         )?;
 
         self.merge_step(&changelog.plans.added,
@@ -691,7 +3584,7 @@ where
                 plan.meta_info.added_timestamp.unwrap().ge(last_sync) &&
                 plan.meta_info.origin.unwrap() != self.instance_id().into_bytes()
             }, 
-            |plan| { self.add_plan(plan) }
+            |plan| { self.insert_plan(plan) }
         )?;
 
         self.merge_step(&changelog.transactions.added,
@@ -699,62 +3592,329 @@ where
                 transaction.meta_info.added_timestamp.unwrap().ge(last_sync) &&
                 transaction.meta_info.origin.unwrap() != self.instance_id().into_bytes()
             },
-            |transaction| { self.add_transaction(transaction) }
+            |transaction| { self.insert_transaction(&Self::redirect_transaction(transaction, &redirects)) }
+        )?;
+
+        self.merge_step(&changelog.loans.added,
+            |loan| {
+                loan.meta_info.added_timestamp.unwrap().ge(last_sync) &&
+                loan.meta_info.origin.unwrap() != self.instance_id().into_bytes()
+            },
+            |loan| { self.insert_loan(loan) }
+        )?;
+
+        self.merge_step(&changelog.holdings.added,
+            |holding| {
+                holding.meta_info.added_timestamp.unwrap().ge(last_sync) &&
+                holding.meta_info.origin.unwrap() != self.instance_id().into_bytes()
+            },
+            |holding| { self.insert_holding(holding) }
+        )?;
+
+        self.merge_step(&changelog.prices.added,
+            |price| {
+                price.meta_info.added_timestamp.unwrap().ge(last_sync) &&
+                price.meta_info.origin.unwrap() != self.instance_id().into_bytes()
+            },
+            |price| { self.insert_price_point(price) }
+        )?;
+
+        //
+        // Then, changed items are processed in the reverse order
+        //
+
+        // Holdings, transactions, persons and groups of categories are the
+        // only items, that can be changed for now. Updates are idempotent,
+        // so changes are applied regardless of origin
+        //
+
+        self.merge_step(&changelog.holdings.changed,
+            |holding| {
+                holding.meta_info.changed_timestamp.unwrap().ge(last_sync)
+            },
+            |holding| { self.replace_holding(holding) }
+        )?;
+
+        self.merge_step(&changelog.transactions.changed,
+            |transaction| {
+                transaction.meta_info.changed_timestamp.unwrap().ge(last_sync)
+            },
+            |transaction| { self.merge_transaction_change(&Self::redirect_transaction(transaction, &redirects)) }
+        )?;
+
+        self.merge_step(&changelog.persons.changed,
+            |person| {
+                person.meta_info.changed_timestamp.unwrap().ge(last_sync)
+            },
+            |person| { self.merge_person_change(person) }
+        )?;
+
+        self.merge_step(&changelog.category_groups.changed,
+            |group| {
+                group.meta_info.changed_timestamp.unwrap().ge(last_sync)
+            },
+            |group| { self.merge_category_group_change(group) }
+        )?;
+
+        //
+        // Then merges are replayed, so merged items have no references
+        // left when they are removed. Replaying is idempotent: balances
+        // are moved only while merged accounts exist
+        //
+
+        for merge in &merges {
+            self.replay_merge(merge)?;
+        }
+
+        //
+        // Finally, removed items are processed in the reverse order too
+        //
+
+        self.merge_step(&changelog.prices.removed,
+            |price| {
+                price.meta_info.removed_timestamp.unwrap().ge(last_sync) &&
+                price.meta_info.origin.unwrap() != self.instance_id().into_bytes()
+            },
+            |price| {
+                self.remove_price_point(price.id.unwrap(), price.meta_info.removed_timestamp.unwrap())
+            }
+        )?;
+
+        self.merge_step(&changelog.holdings.removed,
+            |holding| {
+                holding.meta_info.removed_timestamp.unwrap().ge(last_sync) &&
+                holding.meta_info.origin.unwrap() != self.instance_id().into_bytes()
+            },
+            |holding| {
+                self.remove_holding(holding.id.unwrap(), holding.meta_info.removed_timestamp.unwrap())
+            }
+        )?;
+
+        self.merge_step(&changelog.loans.removed,
+            |loan| {
+                loan.meta_info.removed_timestamp.unwrap().ge(last_sync) &&
+                loan.meta_info.origin.unwrap() != self.instance_id().into_bytes()
+            },
+            |loan| {
+                self.remove_loan(loan.id.unwrap(), loan.meta_info.removed_timestamp.unwrap())
+            }
+        )?;
+
+        self.merge_step(&changelog.transactions.removed,
+            |transaction| {
+                transaction.meta_info.removed_timestamp.unwrap().ge(last_sync) &&
+                transaction.meta_info.origin.unwrap() != self.instance_id().into_bytes()
+            },
+            |transaction| {
+                self.remove_transaction(transaction.id.unwrap(), false,
+                    transaction.meta_info.removed_timestamp.unwrap())
+            }
+        )?;
+
+        self.merge_step(&changelog.plans.removed,
+            |plan| {
+                plan.meta_info.removed_timestamp.unwrap().ge(last_sync) &&
+                plan.meta_info.origin.unwrap() != self.instance_id().into_bytes()
+            },
+            |plan| {
+                self.remove_plan(plan.id.unwrap(), plan.meta_info.removed_timestamp.unwrap())
+            }
+        )?;
+
+        self.merge_step(&changelog.persons.removed,
+            |person| {
+                person.meta_info.removed_timestamp.unwrap().ge(last_sync) &&
+                person.meta_info.origin.unwrap() != self.instance_id().into_bytes()
+            },
+            |person| {
+                self.storage.remove_person(person.id.unwrap(), person.meta_info.removed_timestamp.unwrap())
+            }
+        )?;
+
+        self.merge_step(&changelog.category_groups.removed,
+            |group| {
+                group.meta_info.removed_timestamp.unwrap().ge(last_sync) &&
+                group.meta_info.origin.unwrap() != self.instance_id().into_bytes()
+            },
+            |group| {
+                self.storage.remove_category_group(group.id.unwrap(), group.meta_info.removed_timestamp.unwrap())
+            }
+        )?;
+
+        self.merge_step(&changelog.categories.removed,
+            |category| {
+                category.meta_info.removed_timestamp.unwrap().ge(last_sync) &&
+                category.meta_info.origin.unwrap() != self.instance_id().into_bytes()
+            },
+            |category| {
+                self.remove_category(category.id.unwrap(), category.meta_info.removed_timestamp.unwrap())
+            }
+        )?;
+
+        self.merge_step(&changelog.accounts.removed,
+            |account| {
+                account.meta_info.removed_timestamp.unwrap().ge(last_sync) &&
+                account.meta_info.origin.unwrap() != self.instance_id().into_bytes()
+            },
+            |account| {
+                self.remove_account(account.id.unwrap(), false,
+                    account.meta_info.removed_timestamp.unwrap())
+            }
         )?;
 
         //
-        // Then, changed items are processed in the reverse order
+        // And finally shared settings are merged: the latest ones win
+        //
+
+        if let Some(remote_settings) = &changelog.settings {
+            let remote_timestamp = remote_settings.meta_info.changed_timestamp.unwrap();
+
+            let is_newer = match self.stored_shared_settings()? {
+                Some(local_settings) => local_settings.meta_info.changed_timestamp.unwrap() < remote_timestamp,
+                None => true
+            };
+
+            if remote_timestamp.ge(last_sync) && is_newer &&
+                remote_settings.meta_info.origin.unwrap() != self.instance_id().into_bytes() 
+            {
+                self.storage
+                    .set_settings(self.encrypt_settings(remote_settings)?)?;
+            }
+        }
+
+        Ok(())
+    }
+
+    fn known_merges(&self, changelog: &Changelog) -> Result<Vec<Merge>> {
+        //
+        // Local merges, that are not exported yet, are not in the 
+        // changelog, but remote items may reference merged ones too
+        //
+
+        let mut merges = changelog.merges.clone();
+        merges.extend(self.storage.merges_since(self.storage.exported_position()?)?);
+
+        Ok(merges)
+    }
+
+    fn merge_redirects(merges: &[Merge]) -> HashMap<Id, Id> {
+        let direct: HashMap<Id, Id> = merges
+            .iter()
+            .map(|merge| (merge.removed, merge.kept))
+            .collect();
+
+        //
+        // Items can be merged several times in a row, so redirects
+        // are followed to the end. Concurrent merges can form a cycle,
+        // hence number of steps is limited.
+        //
+
+        direct
+            .keys()
+            .map(|removed| {
+                let mut target = *removed;
+                for _ in 0..direct.len() {
+                    match direct.get(&target) {
+                        Some(kept) => target = *kept,
+                        None => break
+                    }
+                }
+
+                (*removed, target)
+            })
+            .collect()
+    }
+
+    fn redirect_transaction(transaction: &Transaction, redirects: &HashMap<Id, Id>) -> Transaction {
+        let redirect = |id: Id| redirects.get(&id).copied().unwrap_or(id);
+
+        Transaction {
+            id: transaction.id,
+            timestamp: transaction.timestamp,
+            description: transaction.description.clone(),
+            account_id: redirect(transaction.account_id),
+            category_id: redirect(transaction.category_id),
+            amount: transaction.amount,
+            kind: transaction.kind,
+            note: transaction.note.clone(),
+            custom_fields: transaction.custom_fields.clone(),
+            spender_id: transaction.spender_id,
+            meta_info: transaction.meta_info
+        }
+    }
+
+    fn merge_transaction_change(&self, transaction: &Transaction) -> Result<()> {
+        //
+        // Balances are corrected relative to the stored transaction,
+        // hence replaying a change does not move money twice. Changes
+        // of transactions removed meanwhile are dropped, and the latest
+        // change wins
+        //
+
+        let stored = match self.storage.transaction(transaction.id.unwrap()) {
+            Ok(stored) => stored,
+            Err(error) if ErrorKind::NotFound == error.kind() => return Ok(()),
+            Err(error) => return Err(error)
+        };
+
+        if stored.meta_info.changed_timestamp > transaction.meta_info.changed_timestamp {
+            return Ok(());
+        }
+
+        self.replace_transaction(transaction)
+    }
+
+    fn merge_person_change(&self, person: &Person) -> Result<()> {
+        //
+        // Persons removed meanwhile stay removed, and the latest
+        // rename wins
         //
 
-        // For now, no changes can be made, therefore, nothing is processed
+        let stored = match self.storage.person(person.id.unwrap()) {
+            Ok(stored) => stored,
+            Err(error) if ErrorKind::NotFound == error.kind() => return Ok(()),
+            Err(error) => return Err(error)
+        };
+
+        if stored.meta_info.changed_timestamp > person.meta_info.changed_timestamp {
+            return Ok(());
+        }
+
+        self.replace_person(person)
+    }
 
+    fn merge_category_group_change(&self, group: &CategoryGroup) -> Result<()> {
         //
-        // Finally, removed items are processed in the reverse order too
+        // Groups removed meanwhile stay removed, and the latest
+        // change wins
         //
 
-        self.merge_step(&changelog.transactions.removed,
-            |transaction| {
-                transaction.meta_info.removed_timestamp.unwrap().ge(last_sync) &&
-                transaction.meta_info.origin.unwrap() != self.instance_id().into_bytes()
-            },
-            |transaction| {
-                self.remove_transaction(transaction.id.unwrap(), false,
-                    transaction.meta_info.removed_timestamp.unwrap())
-            }
-        )?;
+        let stored = match self.storage.category_group(group.id.unwrap()) {
+            Ok(stored) => stored,
+            Err(error) if ErrorKind::NotFound == error.kind() => return Ok(()),
+            Err(error) => return Err(error)
+        };
 
-        self.merge_step(&changelog.plans.removed,
-            |plan| {
-                plan.meta_info.removed_timestamp.unwrap().ge(last_sync) &&
-                plan.meta_info.origin.unwrap() != self.instance_id().into_bytes()
-            },
-            |plan| {
-                self.remove_plan(plan.id.unwrap(), plan.meta_info.removed_timestamp.unwrap())
-            }
-        )?;
+        if stored.meta_info.changed_timestamp > group.meta_info.changed_timestamp {
+            return Ok(());
+        }
 
-        self.merge_step(&changelog.categories.removed,
-            |category| {
-                category.meta_info.removed_timestamp.unwrap().ge(last_sync) &&
-                category.meta_info.origin.unwrap() != self.instance_id().into_bytes()
-            },
-            |category| {
-                self.remove_category(category.id.unwrap(), category.meta_info.removed_timestamp.unwrap())
-            }
-        )?;
+        self.replace_category_group(group)
+    }
 
-        self.merge_step(&changelog.accounts.removed,
-            |account| {
-                account.meta_info.removed_timestamp.unwrap().ge(last_sync) &&
-                account.meta_info.origin.unwrap() != self.instance_id().into_bytes()
-            },
-            |account| {
-                self.remove_account(account.id.unwrap(), false,
-                    account.meta_info.removed_timestamp.unwrap())
+    fn replay_merge(&self, merge: &Merge) -> Result<()> {
+        if MergeKind::Account == merge.kind {
+            let accounts = self.accounts()?;
+            let find = |id: Id| accounts
+                .iter()
+                .find(|account| account.id == Some(id));
+
+            if let (Some(kept), Some(removed)) = (find(merge.kept), find(merge.removed)) {
+                self.move_balance(kept.clone(), removed)?;
             }
-        )?;
+        }
 
-        Ok(())
+        self.storage.redirect_references(merge.removed, merge.kept)
     }
 
     fn merge_step<T, I, F, Mo>(&self, items: I, filter: F, merge_operation: Mo) -> Result<()>
@@ -778,53 +3938,113 @@ where
     Se: SyncEngine,
     St: DataStorage
 {
-    fn transactions_added_since(&self, base: Timestamp) -> Result<Vec<Transaction>> {
+    fn transactions_added_since(&self, base: JournalPosition) -> Result<Vec<Transaction>> {
         self.decrypt_transactions(&self.storage.transactions_added_since(base)?)
     }
 
-    fn transactions_changed_since(&self, base: Timestamp) -> Result<Vec<Transaction>> {
+    fn transactions_changed_since(&self, base: JournalPosition) -> Result<Vec<Transaction>> {
         self.decrypt_transactions(&self.storage.transactions_changed_since(base)?)
     }
 
-    fn transactions_removed_since(&self, base: Timestamp) -> Result<Vec<Transaction>> {
+    fn transactions_removed_since(&self, base: JournalPosition) -> Result<Vec<Transaction>> {
         self.decrypt_transactions(&self.storage.transactions_removed_since(base)?)
     }
 
-    fn accounts_added_since(&self, base: Timestamp) -> Result<Vec<Account>> {
+    fn accounts_added_since(&self, base: JournalPosition) -> Result<Vec<Account>> {
         self.decrypt_accounts(&self.storage.accounts_added_since(base)?)
     }
 
-    fn accounts_changed_since(&self, base: Timestamp) -> Result<Vec<Account>> {
+    fn accounts_changed_since(&self, base: JournalPosition) -> Result<Vec<Account>> {
         self.decrypt_accounts(&self.storage.accounts_changed_since(base)?)
     }
 
-    fn accounts_removed_since(&self, base: Timestamp) -> Result<Vec<Account>> {
+    fn accounts_removed_since(&self, base: JournalPosition) -> Result<Vec<Account>> {
         self.decrypt_accounts(&self.storage.accounts_removed_since(base)?)
     }
 
-    fn categories_added_since(&self, base: Timestamp) -> Result<Vec<Category>> {
+    fn categories_added_since(&self, base: JournalPosition) -> Result<Vec<Category>> {
         self.decrypt_categories(&self.storage.categories_added_since(base)?)
     }
 
-    fn categories_changed_since(&self, base: Timestamp) -> Result<Vec<Category>> {
+    fn categories_changed_since(&self, base: JournalPosition) -> Result<Vec<Category>> {
         self.decrypt_categories(&self.storage.categories_changed_since(base)?)
     }
 
-    fn categories_removed_since(&self, base: Timestamp) -> Result<Vec<Category>> {
+    fn categories_removed_since(&self, base: JournalPosition) -> Result<Vec<Category>> {
         self.decrypt_categories(&self.storage.categories_removed_since(base)?)
     }
 
-    fn plans_added_since(&self, base: Timestamp) -> Result<Vec<Plan>> {
+    fn persons_added_since(&self, base: JournalPosition) -> Result<Vec<Person>> {
+        self.decrypt_persons(&self.storage.persons_added_since(base)?)
+    }
+
+    fn persons_changed_since(&self, base: JournalPosition) -> Result<Vec<Person>> {
+        self.decrypt_persons(&self.storage.persons_changed_since(base)?)
+    }
+
+    fn persons_removed_since(&self, base: JournalPosition) -> Result<Vec<Person>> {
+        self.decrypt_persons(&self.storage.persons_removed_since(base)?)
+    }
+
+    fn category_groups_added_since(&self, base: JournalPosition) -> Result<Vec<CategoryGroup>> {
+        self.decrypt_category_groups(&self.storage.category_groups_added_since(base)?)
+    }
+
+    fn category_groups_changed_since(&self, base: JournalPosition) -> Result<Vec<CategoryGroup>> {
+        self.decrypt_category_groups(&self.storage.category_groups_changed_since(base)?)
+    }
+
+    fn category_groups_removed_since(&self, base: JournalPosition) -> Result<Vec<CategoryGroup>> {
+        self.decrypt_category_groups(&self.storage.category_groups_removed_since(base)?)
+    }
+
+    fn plans_added_since(&self, base: JournalPosition) -> Result<Vec<Plan>> {
         self.decrypt_plans(&self.storage.plans_added_since(base)?)
     }
 
-    fn plans_changed_since(&self, base: Timestamp) -> Result<Vec<Plan>> {
+    fn plans_changed_since(&self, base: JournalPosition) -> Result<Vec<Plan>> {
         self.decrypt_plans(&self.storage.plans_changed_since(base)?)
     }
 
-    fn plans_removed_since(&self, base: Timestamp) -> Result<Vec<Plan>> {
+    fn plans_removed_since(&self, base: JournalPosition) -> Result<Vec<Plan>> {
         self.decrypt_plans(&self.storage.plans_removed_since(base)?)
     }
+
+    fn loans_added_since(&self, base: JournalPosition) -> Result<Vec<Loan>> {
+        self.decrypt_loans(&self.storage.loans_added_since(base)?)
+    }
+
+    fn loans_changed_since(&self, base: JournalPosition) -> Result<Vec<Loan>> {
+        self.decrypt_loans(&self.storage.loans_changed_since(base)?)
+    }
+
+    fn loans_removed_since(&self, base: JournalPosition) -> Result<Vec<Loan>> {
+        self.decrypt_loans(&self.storage.loans_removed_since(base)?)
+    }
+
+    fn holdings_added_since(&self, base: JournalPosition) -> Result<Vec<Holding>> {
+        self.decrypt_holdings(&self.storage.holdings_added_since(base)?)
+    }
+
+    fn holdings_changed_since(&self, base: JournalPosition) -> Result<Vec<Holding>> {
+        self.decrypt_holdings(&self.storage.holdings_changed_since(base)?)
+    }
+
+    fn holdings_removed_since(&self, base: JournalPosition) -> Result<Vec<Holding>> {
+        self.decrypt_holdings(&self.storage.holdings_removed_since(base)?)
+    }
+
+    fn prices_added_since(&self, base: JournalPosition) -> Result<Vec<PricePoint>> {
+        self.decrypt_price_points(&self.storage.prices_added_since(base)?)
+    }
+
+    fn prices_changed_since(&self, base: JournalPosition) -> Result<Vec<PricePoint>> {
+        self.decrypt_price_points(&self.storage.prices_changed_since(base)?)
+    }
+
+    fn prices_removed_since(&self, base: JournalPosition) -> Result<Vec<PricePoint>> {
+        self.decrypt_price_points(&self.storage.prices_removed_since(base)?)
+    }
 }
 
 
@@ -834,19 +4054,84 @@ where
     Se: SyncEngine,
     St: DataStorage
 {
+    fn read_cached_report<T: serde::de::DeserializeOwned>(&self, cache: &ReportCache, query: &str, position: JournalPosition) -> Option<T> {
+        //
+        // Entries of other keys or versions of data are just misses,
+        // they are overwritten afterwards
+        //
+
+        let entry = self.crypto_engine
+            .decrypt(&self.key, &cache.read(query).ok()??)
+            .ok()?;
+
+        let (header, report) = entry
+            .as_bytes()
+            .split_at_checked(std::mem::size_of::<JournalPosition>())?;
+
+        match JournalPosition::from_le_bytes(header.try_into().ok()?) == position {
+            true => flexbuffers::from_slice(report).ok(),
+            false => None
+        }
+    }
+
+    fn write_cached_report<T: serde::Serialize>(&self, cache: &ReportCache, query: &str, position: JournalPosition, report: &T) -> Result<()> {
+        let mut entry = CryptoBuffer::from(&position.to_le_bytes()[..]);
+        entry.extend_from_slice(CryptoBuffer::from(flexbuffers::to_vec(report)?).as_bytes());
+
+        let encrypted = self.crypto_engine
+            .encrypt(&self.key, entry.as_bytes())?;
+
+        cache.write(query, encrypted.as_bytes())
+    }
+
     fn encrypt_string(&self, data: &String) -> Result<CryptoBuffer> {
         self.crypto_engine
             .encrypt(&self.key, data.as_bytes())
     }
 
     fn decrypt_string(&self, data: &[u8]) -> Result<String> {
-        let decrypted = self.crypto_engine
-            .decrypt(&self.key, data)?;
+        self.crypto_engine
+            .decrypt(&self.key, data)?
+            .into_string_checked()
+    }
+
+    fn encrypt_description(&self, description: &String) -> Result<CryptoBuffer> {
+        match self.storage.deterministic_descriptions()? {
+            true => self.crypto_engine.encrypt_deterministic(&self.key, description.as_bytes()),
+            false => self.encrypt_string(description)
+        }
+    }
+
+    pub(super) fn decrypt_description(&self, data: &[u8]) -> Result<String> {
+        //
+        // Ciphertexts are authenticated, so a description encrypted
+        // in another mode just fails to decrypt. Both modes are tried,
+        // since removed transactions are not re-encrypted
+        //
+
+        self.decrypt_string(data)
+            .or_else(|error| self.crypto_engine
+                .decrypt_deterministic(&self.key, data)
+                .and_then(CryptoBuffer::into_string_checked)
+                .map_err(|_| error))
+    }
+
+    fn decrypt_descriptions(&self, data: &[&[u8]]) -> Result<Vec<String>> {
+        //
+        // Batch fails as a whole, if some descriptions are encrypted
+        // in another mode. They are decrypted one by one then
+        //
 
-        Ok(
-            String::from_utf8_lossy(decrypted.as_bytes())
-                .to_string()
-        )
+        self.crypto_engine
+            .decrypt_batch(&self.key, data)
+            .and_then(|decrypted| decrypted
+                .into_iter()
+                .map(CryptoBuffer::into_string_checked)
+                .collect())
+            .or_else(|_| data
+                .iter()
+                .map(|description| self.decrypt_description(description))
+                .collect())
     }
 
     fn encrypt_isize(&self, data: &isize) -> Result<CryptoBuffer> {
@@ -854,20 +4139,110 @@ where
             .encrypt(&self.key, &data.to_le_bytes())
     }
 
-    fn decrypt_isize(&self, data: &[u8]) -> Result<isize> {
+    pub(super) fn decrypt_isize(&self, data: &[u8]) -> Result<isize> {
         let decrypted = self.crypto_engine
             .decrypt(&self.key, data)?;
 
+        Self::isize_from_buffer(decrypted)
+    }
+
+    fn isize_from_buffer(decrypted: CryptoBuffer) -> Result<isize> {
         let bytes = decrypted
             .as_bytes()
             .try_into()
-            .map_err(|e: TryFromSliceError| Error::from_message(e.to_string()))?;
+            .map_err(Error::from)?;
 
         Ok(isize::from_le_bytes(bytes))
     }
 
+    fn encrypt_f64(&self, data: &f64) -> Result<CryptoBuffer> {
+        self.crypto_engine
+            .encrypt(&self.key, &data.to_le_bytes())
+    }
+
+    fn decrypt_f64(&self, data: &[u8]) -> Result<f64> {
+        let decrypted = self.crypto_engine
+            .decrypt(&self.key, data)?;
+
+        let bytes = decrypted
+            .as_bytes()
+            .try_into()
+            .map_err(Error::from)?;
+
+        Ok(f64::from_le_bytes(bytes))
+    }
+
+    fn encrypt_note(&self, note: &String) -> Result<Option<Vec<u8>>> {
+        if note.is_empty() {
+            return Ok(None);
+        }
+
+        let encrypted_note = self.encrypt_string(note)?;
+        Ok(Some(encrypted_note.as_bytes().into()))
+    }
+
+    pub(super) fn decrypt_note(&self, data: &Option<Vec<u8>>) -> Result<String> {
+        match data {
+            Some(data) => self.decrypt_string(data),
+            None => Ok(String::new())
+        }
+    }
+
+    fn stored_shared_settings(&self) -> Result<Option<SharedSettings>> {
+        self.storage
+            .settings()?
+            .map(|settings| self.decrypt_settings(&settings))
+            .transpose()
+    }
+
+    fn encrypt_settings(&self, settings: &SharedSettings) -> Result<EncryptedSettings> {
+        let serialized = CryptoBuffer::from(flexbuffers::to_vec(&settings.layer)?);
+        let encrypted_data = self.crypto_engine
+            .encrypt(&self.key, serialized.as_bytes())?;
+
+        Ok(EncryptedSettings {
+            data: encrypted_data.as_bytes().into(),
+            meta_info: settings.meta_info
+        })
+    }
+
+    fn decrypt_settings(&self, settings: &EncryptedSettings) -> Result<SharedSettings> {
+        let decrypted = self.crypto_engine
+            .decrypt(&self.key, &settings.data)?;
+
+        Ok(SharedSettings {
+            layer: flexbuffers::from_slice(decrypted.as_bytes())?,
+            meta_info: settings.meta_info
+        })
+    }
+
+    fn encrypt_custom_fields(&self, custom_fields: &CustomFields) -> Result<Option<Vec<u8>>> {
+        if custom_fields.is_empty() {
+            return Ok(None);
+        }
+
+        let serialized = CryptoBuffer::from(flexbuffers::to_vec(custom_fields)?);
+        let encrypted_custom_fields = self.crypto_engine
+            .encrypt(&self.key, serialized.as_bytes())?;
+
+        Ok(Some(encrypted_custom_fields.as_bytes().into()))
+    }
+
+    pub(super) fn decrypt_custom_fields(&self, data: &Option<Vec<u8>>) -> Result<CustomFields> {
+        let data = match data {
+            Some(data) => data,
+            None => return Ok(CustomFields::new())
+        };
+
+        let decrypted = self.crypto_engine
+            .decrypt(&self.key, data)?;
+
+        flexbuffers::from_slice(decrypted.as_bytes())
+            .map_err(Error::from)
+    }
+
     fn encrypt_transaction(&self, transaction: &Transaction) -> Result<EncryptedTransaction> {
-        let encrypted_description = self.encrypt_string(&transaction.description)?;
+        let encrypted_description = self.encrypt_description(&transaction.description)?;
         let encrypted_amount = self.encrypt_isize(&transaction.amount)?;
 
         Ok(EncryptedTransaction {
@@ -877,29 +4252,74 @@ where
             account_id: transaction.account_id,
             category_id: transaction.category_id,
             amount: encrypted_amount.as_bytes().into(),
+            kind: transaction.kind,
+            note: self.encrypt_note(&transaction.note)?,
+            custom_fields: self.encrypt_custom_fields(&transaction.custom_fields)?,
+            spender_id: transaction.spender_id,
             meta_info: transaction.meta_info
         })
     }
 
     fn decrypt_transaction(&self, encrypted_transaction: &EncryptedTransaction) -> Result<Transaction> {
-        let decrypted_description = self.decrypt_string(&encrypted_transaction.description)?;
+        let decrypted_description = self.decrypt_description(&encrypted_transaction.description)?;
         let decrypted_amount = self.decrypt_isize(&encrypted_transaction.amount)?;
 
+        self.complete_transaction(encrypted_transaction, decrypted_description, decrypted_amount)
+    }
+
+    fn complete_transaction(&self, encrypted_transaction: &EncryptedTransaction, description: String, amount: isize) -> Result<Transaction> {
         Ok(Transaction {
             id: encrypted_transaction.id,
             timestamp: encrypted_transaction.timestamp,
-            description: decrypted_description,
+            description,
             account_id: encrypted_transaction.account_id,
             category_id: encrypted_transaction.category_id,
-            amount: decrypted_amount,
+            amount,
+            kind: encrypted_transaction.kind,
+            note: self.decrypt_note(&encrypted_transaction.note)?,
+            custom_fields: self.decrypt_custom_fields(&encrypted_transaction.custom_fields)?,
+            spender_id: encrypted_transaction.spender_id,
             meta_info: encrypted_transaction.meta_info
         })
     }
 
     fn decrypt_transactions(&self, encrypted_transactions: &Vec<EncryptedTransaction>) -> Result<Vec<Transaction>> {
+        //
+        // Batch decryption is much cheaper, but it does not tell,
+        // which item is broken. Hence if it fails, transactions are
+        // decrypted one by one to report a proper error
+        //
+
+        self.decrypt_transactions_batch(encrypted_transactions)
+            .or_else(|_| encrypted_transactions
+                .iter()
+                .map(|transaction| self.decrypt_transaction(transaction)
+                    .with_context(|| Self::item_context("transaction", transaction.id)))
+                .collect())
+    }
+
+    fn decrypt_transactions_batch(&self, encrypted_transactions: &[EncryptedTransaction]) -> Result<Vec<Transaction>> {
+        let descriptions: Vec<_> = encrypted_transactions
+            .iter()
+            .map(|transaction| transaction.description.as_slice())
+            .collect();
+
+        let amounts: Vec<_> = encrypted_transactions
+            .iter()
+            .map(|transaction| transaction.amount.as_slice())
+            .collect();
+
+        let decrypted_descriptions = self.decrypt_descriptions(&descriptions)?;
+        let decrypted_amounts = self.crypto_engine
+            .decrypt_batch(&self.key, &amounts)?;
+
         encrypted_transactions
             .iter()
-            .map(|transaction| self.decrypt_transaction(transaction))
+            .zip(decrypted_descriptions)
+            .zip(decrypted_amounts)
+            .map(|((transaction, description), amount)| {
+                self.complete_transaction(transaction, description, Self::isize_from_buffer(amount)?)
+            })
             .collect()
     }
 
@@ -913,6 +4333,8 @@ where
             name: encrypted_name.as_bytes().into(), 
             balance: encrypted_balance.as_bytes().into(),
             initial_balance: encrypted_initial_balance.as_bytes().into(),
+            note: self.encrypt_note(&account.note)?,
+            custom_fields: self.encrypt_custom_fields(&account.custom_fields)?,
             meta_info: account.meta_info
         })
     }
@@ -927,6 +4349,8 @@ where
             name: decrypted_name, 
             balance: decrypted_balance,
             initial_balance: decrypted_initial_balance,
+            note: self.decrypt_note(&encrypted_account.note)?,
+            custom_fields: self.decrypt_custom_fields(&encrypted_account.custom_fields)?,
             meta_info: encrypted_account.meta_info
         })
     }
@@ -934,7 +4358,8 @@ where
     fn decrypt_accounts(&self, encrypted_accounts: &Vec<EncryptedAccount>) -> Result<Vec<Account>> {
         encrypted_accounts
             .iter()
-            .map(|account| self.decrypt_account(account))
+            .map(|account| self.decrypt_account(account)
+                .with_context(|| Self::item_context("account", account.id)))
             .collect()
     }
 
@@ -945,6 +4370,8 @@ where
             id: category.id,
             name: encrypted_name.as_bytes().into(),
             category_type: category.category_type,
+            note: self.encrypt_note(&category.note)?,
+            custom_fields: self.encrypt_custom_fields(&category.custom_fields)?,
             meta_info: category.meta_info
         })
     }
@@ -956,6 +4383,8 @@ where
             id: encrypted_category.id,
             name: decrypted_category, 
             category_type: encrypted_category.category_type,
+            note: self.decrypt_note(&encrypted_category.note)?,
+            custom_fields: self.decrypt_custom_fields(&encrypted_category.custom_fields)?,
             meta_info: encrypted_category.meta_info
         })
     }
@@ -963,7 +4392,62 @@ where
     fn decrypt_categories(&self, encrypted_categories: &Vec<EncryptedCategory>) -> Result<Vec<Category>> {
         encrypted_categories
             .iter()
-            .map(|category| self.decrypt_category(category))
+            .map(|category| self.decrypt_category(category)
+                .with_context(|| Self::item_context("category", category.id)))
+            .collect()
+    }
+
+    fn encrypt_person(&self, person: &Person) -> Result<EncryptedPerson> {
+        let encrypted_name = self.encrypt_string(&person.name)?;
+
+        Ok(EncryptedPerson {
+            id: person.id,
+            name: encrypted_name.as_bytes().into(),
+            meta_info: person.meta_info
+        })
+    }
+
+    fn decrypt_person(&self, encrypted_person: &EncryptedPerson) -> Result<Person> {
+        Ok(Person {
+            id: encrypted_person.id,
+            name: self.decrypt_string(&encrypted_person.name)?,
+            meta_info: encrypted_person.meta_info
+        })
+    }
+
+    fn decrypt_persons(&self, encrypted_persons: &[EncryptedPerson]) -> Result<Vec<Person>> {
+        encrypted_persons
+            .iter()
+            .map(|person| self.decrypt_person(person)
+                .with_context(|| Self::item_context("person", person.id)))
+            .collect()
+    }
+
+    fn encrypt_category_group(&self, group: &CategoryGroup) -> Result<EncryptedCategoryGroup> {
+        let encrypted_name = self.encrypt_string(&group.name)?;
+
+        Ok(EncryptedCategoryGroup {
+            id: group.id,
+            name: encrypted_name.as_bytes().into(),
+            categories: group.categories.clone(),
+            meta_info: group.meta_info
+        })
+    }
+
+    fn decrypt_category_group(&self, encrypted_group: &EncryptedCategoryGroup) -> Result<CategoryGroup> {
+        Ok(CategoryGroup {
+            id: encrypted_group.id,
+            name: self.decrypt_string(&encrypted_group.name)?,
+            categories: encrypted_group.categories.clone(),
+            meta_info: encrypted_group.meta_info
+        })
+    }
+
+    fn decrypt_category_groups(&self, encrypted_groups: &[EncryptedCategoryGroup]) -> Result<Vec<CategoryGroup>> {
+        encrypted_groups
+            .iter()
+            .map(|group| self.decrypt_category_group(group)
+                .with_context(|| Self::item_context("category group", group.id)))
             .collect()
     }
 
@@ -976,6 +4460,11 @@ where
             category_id: plan.category_id, 
             name: encrypted_name.as_bytes().into(), 
             amount_limit: encrypted_amount_limit.as_bytes().into(),
+            alert_threshold: plan.alert_threshold
+                .map(|alert_threshold| self.encrypt_isize(&alert_threshold).map(|encrypted| encrypted.as_bytes().into()))
+                .transpose()?,
+            note: self.encrypt_note(&plan.note)?,
+            custom_fields: self.encrypt_custom_fields(&plan.custom_fields)?,
             meta_info: plan.meta_info
         })
     }
@@ -989,6 +4478,12 @@ where
             category_id: encrypted_plan.category_id, 
             name: decrypted_name, 
             amount_limit: decrypted_amount_limit,
+            alert_threshold: encrypted_plan.alert_threshold
+                .as_ref()
+                .map(|alert_threshold| self.decrypt_isize(alert_threshold))
+                .transpose()?,
+            note: self.decrypt_note(&encrypted_plan.note)?,
+            custom_fields: self.decrypt_custom_fields(&encrypted_plan.custom_fields)?,
             meta_info: encrypted_plan.meta_info
         })
     }
@@ -996,7 +4491,256 @@ where
     fn decrypt_plans(&self, encrypted_plans: &Vec<EncryptedPlan>) -> Result<Vec<Plan>> {
         encrypted_plans
             .iter()
-            .map(|plan| self.decrypt_plan(plan))
+            .map(|plan| self.decrypt_plan(plan)
+                .with_context(|| Self::item_context("plan", plan.id)))
+            .collect()
+    }
+
+    fn encrypt_loan(&self, loan: &Loan) -> Result<EncryptedLoan> {
+        let encrypted_name = self.encrypt_string(&loan.name)?;
+        let encrypted_principal = self.encrypt_isize(&loan.principal)?;
+        let encrypted_interest_rate = self.encrypt_isize(&loan.interest_rate)?;
+
+        Ok(EncryptedLoan {
+            id: loan.id,
+            name: encrypted_name.as_bytes().into(),
+            account_id: loan.account_id,
+            principal_category_id: loan.principal_category_id,
+            interest_category_id: loan.interest_category_id,
+            principal: encrypted_principal.as_bytes().into(),
+            interest_rate: encrypted_interest_rate.as_bytes().into(),
+            term: loan.term,
+            start_timestamp: loan.start_timestamp,
+            note: self.encrypt_note(&loan.note)?,
+            custom_fields: self.encrypt_custom_fields(&loan.custom_fields)?,
+            meta_info: loan.meta_info
+        })
+    }
+
+    fn decrypt_loan(&self, encrypted_loan: &EncryptedLoan) -> Result<Loan> {
+        Ok(Loan {
+            id: encrypted_loan.id,
+            name: self.decrypt_string(&encrypted_loan.name)?,
+            account_id: encrypted_loan.account_id,
+            principal_category_id: encrypted_loan.principal_category_id,
+            interest_category_id: encrypted_loan.interest_category_id,
+            principal: self.decrypt_isize(&encrypted_loan.principal)?,
+            interest_rate: self.decrypt_isize(&encrypted_loan.interest_rate)?,
+            term: encrypted_loan.term,
+            start_timestamp: encrypted_loan.start_timestamp,
+            note: self.decrypt_note(&encrypted_loan.note)?,
+            custom_fields: self.decrypt_custom_fields(&encrypted_loan.custom_fields)?,
+            meta_info: encrypted_loan.meta_info
+        })
+    }
+
+    fn decrypt_loans(&self, encrypted_loans: &Vec<EncryptedLoan>) -> Result<Vec<Loan>> {
+        encrypted_loans
+            .iter()
+            .map(|loan| self.decrypt_loan(loan)
+                .with_context(|| Self::item_context("loan", loan.id)))
+            .collect()
+    }
+
+    fn encrypt_holding(&self, holding: &Holding) -> Result<EncryptedHolding> {
+        let encrypted_symbol = self.encrypt_string(&holding.symbol)?;
+        let encrypted_quantity = self.encrypt_f64(&holding.quantity)?;
+
+        Ok(EncryptedHolding {
+            id: holding.id,
+            account_id: holding.account_id,
+            symbol: encrypted_symbol.as_bytes().into(),
+            quantity: encrypted_quantity.as_bytes().into(),
+            note: self.encrypt_note(&holding.note)?,
+            custom_fields: self.encrypt_custom_fields(&holding.custom_fields)?,
+            meta_info: holding.meta_info
+        })
+    }
+
+    fn decrypt_holding(&self, encrypted_holding: &EncryptedHolding) -> Result<Holding> {
+        Ok(Holding {
+            id: encrypted_holding.id,
+            account_id: encrypted_holding.account_id,
+            symbol: self.decrypt_string(&encrypted_holding.symbol)?,
+            quantity: self.decrypt_f64(&encrypted_holding.quantity)?,
+            note: self.decrypt_note(&encrypted_holding.note)?,
+            custom_fields: self.decrypt_custom_fields(&encrypted_holding.custom_fields)?,
+            meta_info: encrypted_holding.meta_info
+        })
+    }
+
+    fn decrypt_holdings(&self, encrypted_holdings: &Vec<EncryptedHolding>) -> Result<Vec<Holding>> {
+        encrypted_holdings
+            .iter()
+            .map(|holding| self.decrypt_holding(holding)
+                .with_context(|| Self::item_context("holding", holding.id)))
+            .collect()
+    }
+
+    fn encrypt_price_point(&self, price: &PricePoint) -> Result<EncryptedPricePoint> {
+        let encrypted_symbol = self.encrypt_string(&price.symbol)?;
+        let encrypted_price = self.encrypt_isize(&price.price)?;
+
+        Ok(EncryptedPricePoint {
+            id: price.id,
+            symbol: encrypted_symbol.as_bytes().into(),
+            timestamp: price.timestamp,
+            price: encrypted_price.as_bytes().into(),
+            meta_info: price.meta_info
+        })
+    }
+
+    fn decrypt_price_point(&self, encrypted_price: &EncryptedPricePoint) -> Result<PricePoint> {
+        Ok(PricePoint {
+            id: encrypted_price.id,
+            symbol: self.decrypt_string(&encrypted_price.symbol)?,
+            timestamp: encrypted_price.timestamp,
+            price: self.decrypt_isize(&encrypted_price.price)?,
+            meta_info: encrypted_price.meta_info
+        })
+    }
+
+    fn decrypt_price_points(&self, encrypted_prices: &[EncryptedPricePoint]) -> Result<Vec<PricePoint>> {
+        encrypted_prices
+            .iter()
+            .map(|price| self.decrypt_price_point(price)
+                .with_context(|| Self::item_context("price point", price.id)))
             .collect()
     }
+
+    fn item_context(entity: &str, id: Option<Id>) -> String {
+        match id {
+            Some(id) => {
+                let id: String = id
+                    .iter()
+                    .map(|byte| format!("{:02x}", byte))
+                    .collect();
+
+                format!("decrypting {} {}", entity, id)
+            }
+            None => format!("decrypting {}", entity)
+        }
+    }
+}
+
+
+#[cfg(test)]
+mod tests {
+    use crate::datetime::Clock;
+    use crate::storage::{Transaction, TransactionKind, CustomFields, MetaInfo};
+    use crate::testing::{memory_budget, BudgetGenerator, GeneratedBudget, MemoryBudget};
+    use super::super::{receipt_text, set_receipt_text};
+
+    fn generated_budget() -> (MemoryBudget, GeneratedBudget) {
+        let budget = memory_budget(1).unwrap();
+        let generated = BudgetGenerator::new(1)
+            .accounts(1)
+            .months(1)
+            .generate(&budget)
+            .unwrap();
+
+        (budget, generated)
+    }
+
+    #[test]
+    fn removal_in_locked_period_keeps_balance() {
+        let (budget, generated) = generated_budget();
+        let account = generated.accounts[0];
+        budget.lock_period(Clock::now()).unwrap();
+
+        let balance = budget.account(account).unwrap().balance;
+        let transaction = budget.transactions_of(account).unwrap()
+            .into_iter()
+            .find(|transaction| transaction.kind == TransactionKind::Regular)
+            .unwrap();
+
+        assert!(budget.remove_transaction(transaction.id.unwrap(), false, Clock::now()).is_err());
+        assert_eq!(budget.account(account).unwrap().balance, balance);
+        assert!(budget.transaction(transaction.id.unwrap()).is_ok());
+    }
+
+    #[test]
+    fn opening_balance_in_locked_period_keeps_balance() {
+        let (budget, generated) = generated_budget();
+        let account = generated.accounts[0];
+        budget.lock_period(Clock::now()).unwrap();
+
+        let balance = budget.account(account).unwrap().balance;
+
+        assert!(budget.set_opening_balance(account, 1, Clock::now()).is_err());
+        assert_eq!(budget.account(account).unwrap().balance, balance);
+    }
+
+    #[test]
+    fn backdated_adjustment_ignores_later_transactions() {
+        let (budget, generated) = generated_budget();
+        let account = generated.accounts[0];
+        let adjusted_at = Clock::now();
+
+        let balance = budget.account(account).unwrap().balance;
+        budget.add_transaction(&Transaction {
+            id: None,
+            timestamp: adjusted_at + chrono::Days::new(1),
+            description: "Bakery".to_owned(),
+            account_id: account,
+            category_id: generated.outcome_categories[0],
+            amount: -100,
+            kind: TransactionKind::Regular,
+            note: String::new(),
+            custom_fields: CustomFields::new(),
+            spender_id: None,
+            meta_info: MetaInfo::new(Some(Clock::now()), None, None)
+        }).unwrap();
+
+        budget.adjust_balance(account, balance + 50, adjusted_at).unwrap();
+
+        let adjustment = budget.transactions_of(account).unwrap()
+            .into_iter()
+            .find(|transaction| transaction.kind == TransactionKind::Adjustment)
+            .unwrap();
+
+        assert_eq!(adjustment.amount, 50);
+        assert_eq!(budget.account(account).unwrap().balance, balance - 50);
+    }
+
+    #[test]
+    fn reimport_keeps_user_edits() {
+        let (budget, generated) = generated_budget();
+        let account = generated.accounts[0];
+        let imported = Transaction {
+            id: None,
+            timestamp: Clock::now(),
+            description: "Bakery".to_owned(),
+            account_id: account,
+            category_id: generated.outcome_categories[0],
+            amount: -100,
+            kind: TransactionKind::Regular,
+            note: String::new(),
+            custom_fields: CustomFields::new(),
+            spender_id: None,
+            meta_info: MetaInfo::new(Some(Clock::now()), None, None)
+        };
+
+        let id = budget.upsert_transaction_by_external_id("bank", "1", &imported).unwrap();
+
+        let mut edited = budget.transaction(id).unwrap();
+        edited.category_id = generated.outcome_categories[1];
+        edited.note = "Birthday cake".to_owned();
+        set_receipt_text(&mut edited.custom_fields, Some("Cake 1.00"));
+        budget.update_transaction(&edited).unwrap();
+
+        let balance = budget.account(account).unwrap().balance;
+        let reimported = budget.upsert_transaction_by_external_id("bank", "1", &Transaction {
+            amount: -250,
+            ..imported
+        }).unwrap();
+
+        let stored = budget.transaction(id).unwrap();
+        assert_eq!(reimported, id);
+        assert_eq!(stored.amount, -250);
+        assert_eq!(stored.category_id, generated.outcome_categories[1]);
+        assert_eq!(stored.note, "Birthday cake");
+        assert_eq!(receipt_text(&stored.custom_fields), Some("Cake 1.00"));
+        assert_eq!(budget.account(account).unwrap().balance, balance - 150);
+    }
 }
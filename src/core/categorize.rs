@@ -0,0 +1,153 @@
+use std::collections::{HashMap, HashSet};
+
+use crate::storage::{Transaction, Id};
+use super::import::{CategoryPrediction, PredictionBasis};
+
+
+/// Weight of transactions of the same account: people tend to pay
+/// for the same things from the same account.
+const SAME_ACCOUNT_WEIGHT: f64 = 2.0;
+
+/// Minimal length of a token, shorter ones carry no meaning.
+const MIN_TOKEN_LENGTH: usize = 2;
+
+
+/// Lightweight categorizer, that matches words of descriptions
+/// against previous transactions.
+///
+/// Each word of a description votes for categories of previous
+/// transactions, that contain it, in proportion to how often the
+/// word occurs in each category. Only transactions of the same
+/// direction (income or expense) are considered, and transactions
+/// of the same account weigh more.
+pub(crate) struct Categorizer {
+    /// Previous transactions prepared for matching
+    samples: Vec<Sample>,
+}
+
+
+/// Previous transaction prepared for matching.
+struct Sample {
+    /// Words of description
+    tokens: HashSet<String>,
+
+    /// Category of the transaction
+    category_id: Id,
+
+    /// Account of the transaction
+    account_id: Id,
+
+    /// If the transaction is an income
+    is_income: bool,
+}
+
+
+impl Categorizer {
+    /// Prepares previous transactions for matching.
+    ///
+    /// * `transactions` - previous transactions
+    pub(crate) fn new(transactions: &[Transaction]) -> Self {
+        let samples = transactions
+            .iter()
+            .map(|transaction| Sample {
+                tokens: tokenize(&transaction.description),
+                category_id: transaction.category_id,
+                account_id: transaction.account_id,
+                is_income: transaction.amount > 0
+            })
+            .filter(|sample| !sample.tokens.is_empty())
+            .collect();
+
+        Categorizer { samples }
+    }
+
+    /// Suggests a category for a transaction. Returns [`None`] if
+    /// no previous transaction has common words with it.
+    ///
+    /// * `description` - description of the transaction
+    /// * `amount` - amount of the transaction (only sign matters)
+    /// * `account_id` - account of the transaction
+    pub(crate) fn suggest(&self, description: &str, amount: isize, account_id: Id) -> Option<CategoryPrediction> {
+        let tokens = tokenize(description);
+        if tokens.is_empty() {
+            return None;
+        }
+
+        let mut votes: HashMap<&str, HashMap<Id, f64>> = HashMap::new();
+        let mut supporters: HashMap<Id, usize> = HashMap::new();
+
+        let samples = self.samples
+            .iter()
+            .filter(|sample| sample.is_income == (amount > 0));
+
+        for sample in samples {
+            let weight = match sample.account_id == account_id {
+                true => SAME_ACCOUNT_WEIGHT,
+                false => 1.0
+            };
+
+            let mut is_supporter = false;
+            for token in tokens.iter().filter(|token| sample.tokens.contains(*token)) {
+                *votes.entry(token.as_str())
+                    .or_default()
+                    .entry(sample.category_id)
+                    .or_default() += weight;
+
+                is_supporter = true;
+            }
+
+            if is_supporter {
+                *supporters.entry(sample.category_id).or_default() += 1;
+            }
+        }
+
+        //
+        // Each word gives one vote split between categories, words
+        // unseen before give nothing, so partial matches score less
+        //
+
+        let mut scores: HashMap<Id, f64> = HashMap::new();
+        for categories in votes.values() {
+            let total: f64 = categories
+                .values()
+                .sum();
+
+            for (category_id, weight) in categories {
+                *scores.entry(*category_id).or_default() += weight / total;
+            }
+        }
+
+        let (category_id, score) = scores
+            .into_iter()
+            .max_by(|(lhs_id, lhs_score), (rhs_id, rhs_score)| lhs_score.total_cmp(rhs_score)
+                .then_with(|| rhs_id.cmp(lhs_id)))?;
+
+        //
+        // Scarce evidence is discounted the same way as in payee
+        // history: a single similar transaction gives a half
+        //
+
+        let support = supporters[&category_id] as f64;
+
+        Some(CategoryPrediction {
+            category_id,
+            score: score / tokens.len() as f64 * support / (support + 1.0),
+            basis: PredictionBasis::SimilarTransactions
+        })
+    }
+}
+
+
+fn tokenize(description: &str) -> HashSet<String> {
+    //
+    // Numbers are mostly dates, card numbers and receipt
+    // identifiers, that differ every time, so they are skipped
+    //
+
+    description
+        .split(|symbol: char| !symbol.is_alphanumeric())
+        .filter(|token| token.chars().count() >= MIN_TOKEN_LENGTH)
+        .filter(|token| !token.chars().all(|symbol| symbol.is_numeric()))
+        .map(str::to_lowercase)
+        .collect()
+}
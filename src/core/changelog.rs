@@ -1,19 +1,204 @@
-use serde::{Serialize, Deserialize};
+use std::collections::{HashSet, HashMap};
 
-use crate::error::{Result, Error};
-use crate::storage::{Transaction, Account, Category, Plan};
+use serde::{Serialize, Deserialize, Deserializer};
+use sha2::{Sha256, Digest};
+
+use crate::error::{Result, Error, ErrorKind};
+use crate::datetime::Timestamp;
+use crate::storage::{Transaction, Account, Category, Plan, Loan, Holding, PricePoint, Person, CategoryGroup, Merge, MetaInfo, Id, PrimaryId};
+use super::settings::SharedSettings;
+use super::{MALFORMED_CHANGELOG, UNSUPPORTED_CHANGELOG_VERSION};
+
+
+/// Marker of chunked changelog representation.
+/// 
+/// Legacy changelogs are plain flexbuffers, that never start with it.
+const CHUNKED_MAGIC: &[u8; 4] = b"BDCL";
+
+/// Version of chunked changelog format written by the library.
+/// 
+/// It is increased on every change of the format. Changes, that
+/// older versions can read (e.g. new fields or kinds of items),
+/// do not change the minimal version of a reader.
+const CHANGELOG_VERSION: u32 = 1;
+
+/// Minimal version of a reader, that understands changelogs
+/// written by the library.
+const MIN_READER_VERSION: u32 = 1;
+
+/// Maximal size of a serialized segment before compression. Larger
+/// segments are split unless they contain a single item.
+const MAX_SEGMENT_SIZE: usize = 1024 * 1024;
+
+/// Level of zstd compression of segments.
+const COMPRESSION_LEVEL: i32 = 9;
+
+
+/// Kind of entities, that are synchronized via changelog.
+#[derive(Clone, Copy, PartialEq, Eq, Debug)]
+pub enum Scope {
+    /// Accounts
+    Accounts,
+
+    /// Categories
+    Categories,
+
+    /// Plans
+    Plans,
+
+    /// Transactions
+    Transactions,
+
+    /// Loans
+    Loans,
+
+    /// Investment holdings
+    Holdings,
+
+    /// Security prices
+    Prices,
+
+    /// Persons within a household
+    Persons,
+
+    /// Groups of categories
+    CategoryGroups,
+}
+
+
+impl Scope {
+    /// All kinds of entities in order of merging of added ones:
+    /// referenced entities precede ones, that reference them.
+    pub(crate) const ALL: [Scope; 9] = [
+        Scope::Accounts,
+        Scope::Categories,
+        Scope::CategoryGroups,
+        Scope::Persons,
+        Scope::Plans,
+        Scope::Transactions,
+        Scope::Loans,
+        Scope::Holdings,
+        Scope::Prices,
+    ];
+}
+
+
+impl std::fmt::Display for Scope {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        let name = match self {
+            Scope::Accounts => "accounts",
+            Scope::Categories => "categories",
+            Scope::Plans => "plans",
+            Scope::Transactions => "transactions",
+            Scope::Loans => "loans",
+            Scope::Holdings => "holdings",
+            Scope::Prices => "prices",
+            Scope::Persons => "persons",
+            Scope::CategoryGroups => "category groups"
+        };
+
+        write!(f, "{}", name)
+    }
+}
+
+
+/// Trait for items, that can be present in a changelog.
+pub(crate) trait ChangelogItem {
+    /// Identifier of the item.
+    fn id(&self) -> PrimaryId;
+
+    /// Identifiers of items referenced by the item.
+    fn references(&self) -> Vec<Id>;
+
+    /// Meta information of the item.
+    fn meta_info(&self) -> &MetaInfo;
+}
+
+
+impl ChangelogItem for Account {
+    fn id(&self) -> PrimaryId { self.id }
+    fn references(&self) -> Vec<Id> { Vec::new() }
+    fn meta_info(&self) -> &MetaInfo { &self.meta_info }
+}
+
+
+impl ChangelogItem for Category {
+    fn id(&self) -> PrimaryId { self.id }
+    fn references(&self) -> Vec<Id> { Vec::new() }
+    fn meta_info(&self) -> &MetaInfo { &self.meta_info }
+}
+
+
+impl ChangelogItem for Transaction {
+    fn id(&self) -> PrimaryId { self.id }
+    fn references(&self) -> Vec<Id> { vec![self.account_id, self.category_id] }
+    fn meta_info(&self) -> &MetaInfo { &self.meta_info }
+}
+
+
+impl ChangelogItem for Plan {
+    fn id(&self) -> PrimaryId { self.id }
+    fn references(&self) -> Vec<Id> { vec![self.category_id] }
+    fn meta_info(&self) -> &MetaInfo { &self.meta_info }
+}
+
+
+impl ChangelogItem for Loan {
+    fn id(&self) -> PrimaryId { self.id }
+    fn references(&self) -> Vec<Id> { vec![self.account_id, self.principal_category_id, self.interest_category_id] }
+    fn meta_info(&self) -> &MetaInfo { &self.meta_info }
+}
+
+
+impl ChangelogItem for Holding {
+    fn id(&self) -> PrimaryId { self.id }
+    fn references(&self) -> Vec<Id> { vec![self.account_id] }
+    fn meta_info(&self) -> &MetaInfo { &self.meta_info }
+}
+
+
+impl ChangelogItem for PricePoint {
+    fn id(&self) -> PrimaryId { self.id }
+    fn references(&self) -> Vec<Id> { Vec::new() }
+    fn meta_info(&self) -> &MetaInfo { &self.meta_info }
+}
+
+
+impl ChangelogItem for Person {
+    fn id(&self) -> PrimaryId { self.id }
+    fn references(&self) -> Vec<Id> { Vec::new() }
+    fn meta_info(&self) -> &MetaInfo { &self.meta_info }
+}
+
+
+impl ChangelogItem for CategoryGroup {
+    fn id(&self) -> PrimaryId { self.id }
+
+    // Categories may be removed or kept private, while a group still
+    // lists them, hence they are not strict references
+    fn references(&self) -> Vec<Id> { Vec::new() }
+
+    fn meta_info(&self) -> &MetaInfo { &self.meta_info }
+}
 
 
 /// Simple changelog representation for some items.
+/// 
+/// Items, that cannot be read (e.g. written by a newer version
+/// of the library with unknown kinds of values), are skipped.
 #[derive(Serialize, Deserialize)]
+#[serde(bound(deserialize = "T: Deserialize<'de>"))]
 pub(crate) struct SimpleChangelog<T> {
     /// Added items.
+    #[serde(deserialize_with = "tolerant_items")]
     pub added: Vec<T>,
 
     /// Changed items.
+    #[serde(deserialize_with = "tolerant_items")]
     pub changed: Vec<T>,
 
     /// Removed items.
+    #[serde(deserialize_with = "tolerant_items")]
     pub removed: Vec<T>,
 }
 
@@ -29,6 +214,247 @@ impl<T> SimpleChangelog<T> {
 }
 
 
+impl<T: ChangelogItem> SimpleChangelog<T> {
+    fn remove_private(&mut self, private: &HashSet<Id>) {
+        let is_visible = |item: &T| {
+            let private_item = item.id()
+                .is_some_and(|id| private.contains(&id));
+
+            let private_reference = item.references()
+                .iter()
+                .any(|id| private.contains(id));
+
+            !private_item && !private_reference
+        };
+
+        self.added.retain(is_visible);
+        self.changed.retain(is_visible);
+        self.removed.retain(is_visible);
+    }
+
+    fn normalize(&mut self) {
+        //
+        // Added items are kept even if they are removed later: instances,
+        // that have not seen them yet, need them to apply the removals.
+        // Removal is final, so changes of removed items are dropped,
+        // otherwise only the latest state of an item is kept
+        //
+
+        deduplicate(&mut self.added, |_, _| false);
+        deduplicate(&mut self.removed, |kept, candidate| candidate.removed_timestamp < kept.removed_timestamp);
+
+        let removed: HashSet<Id> = self.removed
+            .iter()
+            .filter_map(ChangelogItem::id)
+            .collect();
+
+        self.changed.retain(|item| !item.id().is_some_and(|id| removed.contains(&id)));
+        deduplicate(&mut self.changed, |kept, candidate| kept.changed_timestamp <= candidate.changed_timestamp);
+    }
+}
+
+
+impl<T: ChangelogItem + Clone> SimpleChangelog<T> {
+    fn after_snapshot(&self, snapshot: &SimpleChangelog<T>, taken: &Timestamp) -> Self {
+        let in_snapshot: HashSet<Id> = snapshot.added
+            .iter()
+            .filter_map(ChangelogItem::id)
+            .collect();
+
+        //
+        // Items, that were removed before the snapshot was taken, are
+        // absent in it, so neither their addition nor removal is needed.
+        // Items removed later are in the snapshot, so only removal is
+        //
+
+        let removed_before: HashSet<Id> = self.removed
+            .iter()
+            .filter(|item| item.meta_info().removed_timestamp.is_some_and(|removed| removed < *taken))
+            .filter_map(ChangelogItem::id)
+            .filter(|id| !in_snapshot.contains(id))
+            .collect();
+
+        let is_covered = |item: &T, covered: &HashSet<Id>| item.id()
+            .is_some_and(|id| covered.contains(&id));
+
+        SimpleChangelog {
+            added: self.added
+                .iter()
+                .filter(|item| !is_covered(item, &in_snapshot) && !is_covered(item, &removed_before))
+                .cloned()
+                .collect(),
+            changed: self.changed
+                .iter()
+                .filter(|item| !is_covered(item, &removed_before))
+                .filter(|item| !is_covered(item, &in_snapshot) || 
+                    item.meta_info().changed_timestamp.is_some_and(|changed| *taken <= changed))
+                .cloned()
+                .collect(),
+            removed: self.removed
+                .iter()
+                .filter(|item| !is_covered(item, &removed_before))
+                .cloned()
+                .collect()
+        }
+    }
+}
+
+
+impl<T> Default for SimpleChangelog<T> {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+
+/// Borrowed part of [`SimpleChangelog`], that is serialized
+/// the same way.
+#[derive(Serialize)]
+struct SimpleChangelogSlice<'a, T> {
+    added: &'a [T],
+    changed: &'a [T],
+    removed: &'a [T],
+}
+
+
+impl<'a, T> SimpleChangelogSlice<'a, T> {
+    fn new(changelog: &'a SimpleChangelog<T>) -> Self {
+        SimpleChangelogSlice {
+            added: &changelog.added,
+            changed: &changelog.changed,
+            removed: &changelog.removed
+        }
+    }
+
+    fn len(&self) -> usize {
+        self.added.len() + self.changed.len() + self.removed.len()
+    }
+
+    fn split(&self) -> (Self, Self) {
+        let (added_first, added_second) = self.added.split_at(self.added.len() / 2);
+        let (changed_first, changed_second) = self.changed.split_at(self.changed.len() / 2);
+        let (removed_first, removed_second) = self.removed.split_at(self.removed.len() / 2);
+
+        let first = SimpleChangelogSlice { added: added_first, changed: changed_first, removed: removed_first };
+        let second = SimpleChangelogSlice { added: added_second, changed: changed_second, removed: removed_second };
+
+        (first, second)
+    }
+}
+
+
+/// Borrowed part of [`Changelog`], that is serialized the same way,
+/// hence it is deserialized as a changelog.
+#[derive(Serialize)]
+struct ChangelogSlice<'a> {
+    accounts: SimpleChangelogSlice<'a, Account>,
+    categories: SimpleChangelogSlice<'a, Category>,
+    transactions: SimpleChangelogSlice<'a, Transaction>,
+    plans: SimpleChangelogSlice<'a, Plan>,
+    loans: SimpleChangelogSlice<'a, Loan>,
+    holdings: SimpleChangelogSlice<'a, Holding>,
+    prices: SimpleChangelogSlice<'a, PricePoint>,
+    persons: SimpleChangelogSlice<'a, Person>,
+    category_groups: SimpleChangelogSlice<'a, CategoryGroup>,
+    settings: Option<&'a SharedSettings>,
+    merges: &'a [Merge],
+}
+
+
+impl<'a> ChangelogSlice<'a> {
+    fn new(changelog: &'a Changelog) -> Self {
+        ChangelogSlice {
+            accounts: SimpleChangelogSlice::new(&changelog.accounts),
+            categories: SimpleChangelogSlice::new(&changelog.categories),
+            transactions: SimpleChangelogSlice::new(&changelog.transactions),
+            plans: SimpleChangelogSlice::new(&changelog.plans),
+            loans: SimpleChangelogSlice::new(&changelog.loans),
+            holdings: SimpleChangelogSlice::new(&changelog.holdings),
+            prices: SimpleChangelogSlice::new(&changelog.prices),
+            persons: SimpleChangelogSlice::new(&changelog.persons),
+            category_groups: SimpleChangelogSlice::new(&changelog.category_groups),
+            settings: changelog.settings.as_ref(),
+            merges: &changelog.merges
+        }
+    }
+
+    fn len(&self) -> usize {
+        self.accounts.len() + self.categories.len() + self.transactions.len() + self.plans.len() +
+            self.loans.len() + self.holdings.len() + self.prices.len() + self.persons.len() +
+            self.category_groups.len() + self.merges.len()
+    }
+
+    fn split(&self) -> (Self, Self) {
+        //
+        // Settings are kept in the first half only
+        //
+
+        let (accounts_first, accounts_second) = self.accounts.split();
+        let (categories_first, categories_second) = self.categories.split();
+        let (transactions_first, transactions_second) = self.transactions.split();
+        let (plans_first, plans_second) = self.plans.split();
+        let (loans_first, loans_second) = self.loans.split();
+        let (holdings_first, holdings_second) = self.holdings.split();
+        let (prices_first, prices_second) = self.prices.split();
+        let (persons_first, persons_second) = self.persons.split();
+        let (category_groups_first, category_groups_second) = self.category_groups.split();
+        let (merges_first, merges_second) = self.merges.split_at(self.merges.len() / 2);
+
+        let first = ChangelogSlice {
+            accounts: accounts_first,
+            categories: categories_first,
+            transactions: transactions_first,
+            plans: plans_first,
+            loans: loans_first,
+            holdings: holdings_first,
+            prices: prices_first,
+            persons: persons_first,
+            category_groups: category_groups_first,
+            settings: self.settings,
+            merges: merges_first
+        };
+
+        let second = ChangelogSlice {
+            accounts: accounts_second,
+            categories: categories_second,
+            transactions: transactions_second,
+            plans: plans_second,
+            loans: loans_second,
+            holdings: holdings_second,
+            prices: prices_second,
+            persons: persons_second,
+            category_groups: category_groups_second,
+            settings: None,
+            merges: merges_second
+        };
+
+        (first, second)
+    }
+}
+
+
+/// Description of a segment of chunked changelog.
+#[derive(Serialize, Deserialize)]
+struct Segment {
+    /// Size of compressed segment
+    compressed_size: u64,
+
+    /// Size of serialized segment before compression
+    size: u64,
+
+    /// SHA-256 of compressed segment
+    checksum: Vec<u8>,
+}
+
+
+/// Manifest of chunked changelog, that precedes segments.
+#[derive(Serialize, Deserialize)]
+struct Manifest {
+    /// Segments in order of their placement
+    segments: Vec<Segment>,
+}
+
+
 /// Database changelog representation.
 #[derive(Serialize, Deserialize)]
 pub(crate) struct Changelog {
@@ -43,6 +469,34 @@ pub(crate) struct Changelog {
 
     /// Plans changelog.
     pub plans: SimpleChangelog<Plan>,
+
+    /// Loans changelog.
+    #[serde(default)]
+    pub loans: SimpleChangelog<Loan>,
+
+    /// Investment holdings changelog.
+    #[serde(default)]
+    pub holdings: SimpleChangelog<Holding>,
+
+    /// Security prices changelog.
+    #[serde(default)]
+    pub prices: SimpleChangelog<PricePoint>,
+
+    /// Persons changelog.
+    #[serde(default)]
+    pub persons: SimpleChangelog<Person>,
+
+    /// Groups of categories changelog.
+    #[serde(default)]
+    pub category_groups: SimpleChangelog<CategoryGroup>,
+
+    /// The latest shared settings.
+    #[serde(default, deserialize_with = "tolerant_item")]
+    pub settings: Option<SharedSettings>,
+
+    /// Merges of categories and accounts.
+    #[serde(default, deserialize_with = "tolerant_items")]
+    pub merges: Vec<Merge>,
 }
 
 
@@ -53,20 +507,42 @@ impl Changelog {
             accounts: SimpleChangelog::new(),
             categories: SimpleChangelog::new(),
             transactions: SimpleChangelog::new(),
-            plans: SimpleChangelog::new()
+            plans: SimpleChangelog::new(),
+            loans: SimpleChangelog::new(),
+            holdings: SimpleChangelog::new(),
+            prices: SimpleChangelog::new(),
+            persons: SimpleChangelog::new(),
+            category_groups: SimpleChangelog::new(),
+            settings: None,
+            merges: Vec::new()
         }
     }
 
     /// Creates a new changelog object from binary representation.
     /// 
+    /// Both chunked (see [`Changelog::to_vec`]) and legacy plain
+    /// representations are supported.
+    /// 
     /// * `binary_changelog` - binary changelog representation
     pub(crate) fn from_slice(binary_changelog: &[u8]) -> Result<Self> {
-        flexbuffers::from_slice(binary_changelog)
-            .map_err(Error::from)
+        match binary_changelog.strip_prefix(CHUNKED_MAGIC) {
+            Some(chunked) => Self::from_chunks(chunked),
+            None => flexbuffers::from_slice(binary_changelog)
+                .map_err(Error::from)
+        }
+    }
+
+    /// Returns number of changes of items in the changelog.
+    pub(crate) fn len(&self) -> usize {
+        ChangelogSlice::new(self)
+            .len() + self.settings.iter().count()
     }
 
     /// Appends another changelog to the current one.
     /// 
+    /// Result is normalized: every item is present at most once
+    /// per list and is not changed after removal.
+    /// 
     /// * `changelog` - a changelog to append
     pub(crate) fn append(&mut self, mut changelog: Changelog) -> Result<()> {
         self.accounts.added.append(&mut changelog.accounts.added);
@@ -85,12 +561,340 @@ impl Changelog {
         self.plans.changed.append(&mut changelog.plans.changed);
         self.plans.removed.append(&mut changelog.plans.removed);
 
+        self.loans.added.append(&mut changelog.loans.added);
+        self.loans.changed.append(&mut changelog.loans.changed);
+        self.loans.removed.append(&mut changelog.loans.removed);
+
+        self.holdings.added.append(&mut changelog.holdings.added);
+        self.holdings.changed.append(&mut changelog.holdings.changed);
+        self.holdings.removed.append(&mut changelog.holdings.removed);
+
+        self.prices.added.append(&mut changelog.prices.added);
+        self.prices.changed.append(&mut changelog.prices.changed);
+        self.prices.removed.append(&mut changelog.prices.removed);
+
+        self.persons.added.append(&mut changelog.persons.added);
+        self.persons.changed.append(&mut changelog.persons.changed);
+        self.persons.removed.append(&mut changelog.persons.removed);
+
+        self.category_groups.added.append(&mut changelog.category_groups.added);
+        self.category_groups.changed.append(&mut changelog.category_groups.changed);
+        self.category_groups.removed.append(&mut changelog.category_groups.removed);
+
+        self.merges.append(&mut changelog.merges);
+
+        let mut known_merges = HashSet::new();
+        self.merges.retain(|merge| known_merges.insert((merge.kept, merge.removed)));
+
+        self.accounts.normalize();
+        self.categories.normalize();
+        self.transactions.normalize();
+        self.plans.normalize();
+        self.loans.normalize();
+        self.holdings.normalize();
+        self.prices.normalize();
+        self.persons.normalize();
+        self.category_groups.normalize();
+
+        //
+        // Only the latest settings are kept
+        //
+
+        let is_newer = match (&self.settings, &changelog.settings) {
+            (Some(current), Some(appended)) => 
+                current.meta_info.changed_timestamp <= appended.meta_info.changed_timestamp,
+            (None, Some(_)) => true,
+            _ => false
+        };
+
+        if is_newer {
+            self.settings = changelog.settings.take();
+        }
+
         Ok(())
     }
 
+    /// Returns part of the changelog, that is not covered by a snapshot
+    /// of full state, i.e. changes, that are to be applied after it.
+    /// 
+    /// Items of the snapshot are expected to be added ones.
+    /// 
+    /// * `snapshot` - changelog, that adds all live items
+    /// * `taken` - time, when the snapshot was taken
+    pub(crate) fn after_snapshot(&self, snapshot: &Changelog, taken: &Timestamp) -> Self {
+        Changelog {
+            accounts: self.accounts.after_snapshot(&snapshot.accounts, taken),
+            categories: self.categories.after_snapshot(&snapshot.categories, taken),
+            transactions: self.transactions.after_snapshot(&snapshot.transactions, taken),
+            plans: self.plans.after_snapshot(&snapshot.plans, taken),
+            loans: self.loans.after_snapshot(&snapshot.loans, taken),
+            holdings: self.holdings.after_snapshot(&snapshot.holdings, taken),
+            prices: self.prices.after_snapshot(&snapshot.prices, taken),
+            persons: self.persons.after_snapshot(&snapshot.persons, taken),
+            category_groups: self.category_groups.after_snapshot(&snapshot.category_groups, taken),
+            settings: self.settings.clone(),
+            merges: self.merges.clone()
+        }
+    }
+
+    /// Removes private items from the changelog.
+    /// 
+    /// Items, that reference private ones (e.g. transactions of a private
+    /// account), are considered private too and are removed as well.
+    /// 
+    /// * `private` - identifiers of private items
+    pub(crate) fn remove_private(&mut self, private: &HashSet<Id>) {
+        self.accounts.remove_private(private);
+        self.categories.remove_private(private);
+        self.transactions.remove_private(private);
+        self.plans.remove_private(private);
+        self.loans.remove_private(private);
+        self.holdings.remove_private(private);
+        self.prices.remove_private(private);
+        self.persons.remove_private(private);
+        self.category_groups.remove_private(private);
+
+        self.merges
+            .retain(|merge| !private.contains(&merge.kept) && !private.contains(&merge.removed));
+    }
+
     /// Converts current changelog into a binary representation.
+    /// 
+    /// Changelog is split into segments of capped size, that are
+    /// compressed separately. Representation consists of a marker,
+    /// size of a manifest, the manifest and segments.
     pub(crate) fn to_vec(&self) -> Result<Vec<u8>> {
-        flexbuffers::to_vec(self)
-            .map_err(Error::from)
+        let mut segments = Vec::new();
+        Self::split_into_segments(&ChangelogSlice::new(self), &mut segments)?;
+
+        let mut manifest = Manifest { segments: Vec::new() };
+        let mut data = Vec::new();
+
+        for segment in segments {
+            let compressed = zstd::bulk::compress(&segment, COMPRESSION_LEVEL)?;
+
+            manifest.segments.push(Segment {
+                compressed_size: compressed.len() as u64,
+                size: segment.len() as u64,
+                checksum: Sha256::digest(&compressed).to_vec()
+            });
+
+            data.extend_from_slice(&compressed);
+        }
+
+        let manifest = flexbuffers::to_vec(&manifest)?;
+
+        let mut binary_changelog = Vec::with_capacity(CHUNKED_MAGIC.len() + 12 + manifest.len() + data.len());
+        binary_changelog.extend_from_slice(CHUNKED_MAGIC);
+        binary_changelog.extend_from_slice(&CHANGELOG_VERSION.to_le_bytes());
+        binary_changelog.extend_from_slice(&MIN_READER_VERSION.to_le_bytes());
+        binary_changelog.extend_from_slice(&(manifest.len() as u32).to_le_bytes());
+        binary_changelog.extend_from_slice(&manifest);
+        binary_changelog.extend_from_slice(&data);
+
+        Ok(binary_changelog)
+    }
+}
+
+
+impl Changelog {
+    fn from_chunks(chunked: &[u8]) -> Result<Self> {
+        let malformed = || Error::from_message(MALFORMED_CHANGELOG)
+            .with_kind(ErrorKind::Corruption);
+
+        let mut header = chunked.chunks(4);
+        let mut read_u32 = || header.next()
+            .and_then(|bytes| bytes.try_into().ok())
+            .map(u32::from_le_bytes)
+            .ok_or_else(malformed);
+
+        let version = read_u32()?;
+        let min_reader_version = read_u32()?;
+        let manifest_size = read_u32()? as usize;
+
+        //
+        // Changelogs of newer versions are read unless they
+        // require a newer reader: unknown fields and items
+        // are skipped then
+        //
+
+        if CHANGELOG_VERSION < min_reader_version {
+            return Err(Error::from_message_with_extra(UNSUPPORTED_CHANGELOG_VERSION,
+                format!("version: {}", version)).with_kind(ErrorKind::SyncFailure));
+        }
+
+        let rest = &chunked[12..];
+
+        if rest.len() < manifest_size {
+            return Err(malformed());
+        }
+
+        let (manifest, mut data) = rest.split_at(manifest_size);
+        let manifest: Manifest = flexbuffers::from_slice(manifest)
+            .map_err(|_| malformed())?;
+
+        let mut changelog = Changelog::new();
+        for segment in manifest.segments {
+            let compressed_size = segment.compressed_size as usize;
+            if data.len() < compressed_size {
+                return Err(malformed());
+            }
+
+            let (compressed, rest) = data.split_at(compressed_size);
+            data = rest;
+
+            //
+            // Damaged segments are rejected before decompression
+            //
+
+            if Sha256::digest(compressed).as_slice() != segment.checksum {
+                return Err(malformed());
+            }
+
+            //
+            // Size in the manifest is not covered by the checksum, but it
+            // bounds decompression. Hence it must match the size in the
+            // frame header, that is covered, so that a damaged manifest
+            // cannot force a huge allocation
+            //
+
+            let frame_size = zstd::zstd_safe::get_frame_content_size(compressed)
+                .map_err(|_| malformed())?;
+
+            if frame_size != Some(segment.size) {
+                return Err(malformed());
+            }
+
+            let expected_size = segment.size as usize;
+            let segment = zstd::bulk::decompress(compressed, expected_size)
+                .map_err(|_| malformed())?;
+
+            if segment.len() != expected_size {
+                return Err(malformed());
+            }
+
+            changelog.append(flexbuffers::from_slice(&segment).map_err(|_| malformed())?)?;
+        }
+
+        Ok(changelog)
+    }
+
+    fn split_into_segments(changelog: &ChangelogSlice<'_>, segments: &mut Vec<Vec<u8>>) -> Result<()> {
+        let segment = flexbuffers::to_vec(changelog)?;
+        if segment.len() <= MAX_SEGMENT_SIZE {
+            segments.push(segment);
+            return Ok(());
+        }
+
+        //
+        // Segment is split in halves until it fits or 
+        // cannot be split anymore
+        //
+
+        let (first, second) = changelog.split();
+        if 0 == first.len() || 0 == second.len() {
+            segments.push(segment);
+            return Ok(());
+        }
+
+        Self::split_into_segments(&first, segments)?;
+        Self::split_into_segments(&second, segments)
+    }
+}
+
+
+/// Leaves a single item with each identifier in place of the first one.
+/// 
+/// * `items` - items to deduplicate
+/// * `replaces` - checks if a candidate item's meta information is newer, than a kept one
+fn deduplicate<T, R>(items: &mut Vec<T>, replaces: R)
+where
+    T: ChangelogItem,
+    R: Fn(&MetaInfo, &MetaInfo) -> bool
+{
+    let mut positions: HashMap<Id, usize> = HashMap::new();
+    let mut unique: Vec<T> = Vec::with_capacity(items.len());
+
+    for item in items.drain(..) {
+        match item.id().and_then(|id| positions.get(&id).copied()) {
+            Some(position) => {
+                if replaces(unique[position].meta_info(), item.meta_info()) {
+                    unique[position] = item;
+                }
+            },
+            None => {
+                if let Some(id) = item.id() {
+                    positions.insert(id, unique.len());
+                }
+
+                unique.push(item);
+            }
+        }
+    }
+
+    *items = unique;
+}
+
+
+/// Item, that is `None` if it cannot be deserialized.
+struct Tolerant<T>(Option<T>);
+
+
+impl<'de, T: Deserialize<'de>> Deserialize<'de> for Tolerant<T> {
+    fn deserialize<D: Deserializer<'de>>(deserializer: D) -> std::result::Result<Self, D::Error> {
+        //
+        // Flexbuffers values are self-contained, hence a failure
+        // does not affect deserialization of following values
+        //
+
+        Ok(Tolerant(T::deserialize(deserializer).ok()))
+    }
+}
+
+
+fn tolerant_items<'de, D, T>(deserializer: D) -> std::result::Result<Vec<T>, D::Error>
+where
+    D: Deserializer<'de>,
+    T: Deserialize<'de>
+{
+    let items: Vec<Tolerant<T>> = Vec::deserialize(deserializer)?;
+    Ok(items.into_iter().filter_map(|item| item.0).collect())
+}
+
+
+fn tolerant_item<'de, D, T>(deserializer: D) -> std::result::Result<Option<T>, D::Error>
+where
+    D: Deserializer<'de>,
+    T: Deserialize<'de>
+{
+    let item: Tolerant<Option<T>> = Tolerant::deserialize(deserializer)?;
+    Ok(item.0.flatten())
+}
+
+
+#[cfg(test)]
+mod tests {
+    use crate::error::ErrorKind;
+    use super::{Changelog, Manifest, CHUNKED_MAGIC};
+
+    #[test]
+    fn damaged_segment_size_is_rejected() {
+        let binary_changelog = Changelog::new().to_vec().unwrap();
+        let (header, rest) = binary_changelog[CHUNKED_MAGIC.len()..].split_at(12);
+        let manifest_size = u32::from_le_bytes(header[8..].try_into().unwrap()) as usize;
+        let (manifest, data) = rest.split_at(manifest_size);
+
+        let mut manifest: Manifest = flexbuffers::from_slice(manifest).unwrap();
+        manifest.segments[0].size = u32::MAX as u64;
+        let manifest = flexbuffers::to_vec(&manifest).unwrap();
+
+        let mut damaged = CHUNKED_MAGIC.to_vec();
+        damaged.extend_from_slice(&header[..8]);
+        damaged.extend_from_slice(&(manifest.len() as u32).to_le_bytes());
+        damaged.extend_from_slice(&manifest);
+        damaged.extend_from_slice(data);
+
+        assert!(Changelog::from_slice(&binary_changelog).is_ok());
+        assert_eq!(Changelog::from_slice(&damaged).err().unwrap().kind(), ErrorKind::Corruption);
     }
 }
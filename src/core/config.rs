@@ -1,19 +1,426 @@
-use crate::error::Result;
-use crate::location::Location;
-use crate::crypto::{KeyIdentifier, CryptoEngine};
+use std::collections::BTreeMap;
 
+use serde::{Serialize, Deserialize};
 
-/// File with key identifier name.
+use crate::error::{Result, Error, ErrorKind, Context};
+use crate::location::{Location, restrict_file};
+use crate::crypto::{KeyIdentifier, CryptoEngine, CryptoBuffer};
+use crate::sync::Proxy;
+#[cfg(feature = "keyring")]
+use crate::crypto::Keyring;
+use super::settings::{SettingsLayer, CurrencySettings};
+use super::{INVALID_CONFIG, UNSUPPORTED_CONFIG_VERSION};
+
+
+/// Configuration file name.
+const CONFIG_FILE: &str = "config.toml";
+
+/// File with key identifier name (legacy configuration).
 const KEY_IDENTIFIER_FILE: &str = "key";
 
-/// File with instance identifier name.
+/// File with instance identifier name (legacy configuration).
 const INSTANCE_IDENTIFIER_FILE: &str = "instance";
 
+/// Environment variable with cryptographic engine name.
+const ENV_ENGINE: &str = "BDGT_ENGINE";
+
+/// Environment variable with key identifier.
+const ENV_KEY_ID: &str = "BDGT_KEY_ID";
+
+/// Environment variable with instance identifier.
+const ENV_INSTANCE_ID: &str = "BDGT_INSTANCE_ID";
+
+/// Environment variable with instance name.
+const ENV_INSTANCE_NAME: &str = "BDGT_INSTANCE_NAME";
+
+/// Environment variable with synchronization remote URL.
+const ENV_SYNC_REMOTE: &str = "BDGT_SYNC_REMOTE";
+
+/// Environment variable with synchronization branch name.
+const ENV_SYNC_BRANCH: &str = "BDGT_SYNC_BRANCH";
+
+/// Environment variable with synchronization proxy.
+const ENV_SYNC_PROXY: &str = "BDGT_SYNC_PROXY";
+
+/// Environment variable with name of author of synchronization commits.
+const ENV_COMMIT_NAME: &str = "BDGT_COMMIT_NAME";
+
+/// Environment variable with email of author of synchronization commits.
+const ENV_COMMIT_EMAIL: &str = "BDGT_COMMIT_EMAIL";
+
+/// Environment variable with default currency code.
+const ENV_CURRENCY: &str = "BDGT_CURRENCY";
+
+/// Environment variable with default currency precision.
+const ENV_CURRENCY_PRECISION: &str = "BDGT_CURRENCY_PRECISION";
+
+/// Environment variable with auto-lock timeout in seconds.
+const ENV_AUTO_LOCK_TIMEOUT: &str = "BDGT_AUTO_LOCK_TIMEOUT";
+
+/// Environment variable with trash retention period in days.
+const ENV_TRASH_RETENTION_DAYS: &str = "BDGT_TRASH_RETENTION_DAYS";
+
+/// Current version of configuration file format.
+///
+/// Version 0 corresponds to legacy configuration stored
+/// in separate files.
+const CONFIG_VERSION: u32 = 1;
+
 
 /// Type of local bdgt instance identifier.
 pub type InstanceId = uuid::Uuid;
 
 
+/// Configuration entries, that can be changed.
+#[derive(Clone, PartialEq, Eq, Debug)]
+pub enum ConfigKey {
+    /// Cryptographic engine name
+    Engine,
+
+    /// Key identifier
+    KeyId,
+
+    /// Instance identifier
+    InstanceId,
+
+    /// Instance name
+    InstanceName,
+
+    /// Local settings layer
+    LocalSettings,
+
+    /// Synchronization remote URL
+    SyncRemote,
+
+    /// Synchronization branch name
+    SyncBranch,
+
+    /// Synchronization proxy
+    SyncProxy,
+
+    /// Name or email of author of synchronization commits
+    CommitIdentity,
+
+    /// Sensitive value with a given name
+    Secret(String),
+}
+
+
+/// Serializable representation of configuration file.
+#[derive(Serialize, Deserialize, Clone, PartialEq)]
+struct ConfigFile {
+    /// Version of configuration file format
+    version: u32,
+
+    /// Name of cryptographic engine, that the key belongs to
+    engine: Option<String>,
+
+    /// Key identifier as string
+    key_id: String,
+
+    /// Instance identifier as string
+    instance_id: String,
+
+    /// User-friendly instance name
+    instance_name: Option<String>,
+
+    /// Local settings layer
+    #[serde(flatten)]
+    local: SettingsLayer,
+
+    /// Synchronization remote URL
+    sync_remote: Option<String>,
+
+    /// Synchronization branch name
+    sync_branch: Option<String>,
+
+    /// Synchronization proxy: "auto", "direct" or URL
+    sync_proxy: Option<String>,
+
+    /// Name of author of synchronization commits
+    commit_name: Option<String>,
+
+    /// Email of author of synchronization commits
+    commit_email: Option<String>,
+
+    /// Sensitive values encrypted with instance's key (hex-encoded)
+    #[serde(default, skip_serializing_if = "BTreeMap::is_empty")]
+    secrets: BTreeMap<String, String>,
+}
+
+
+impl ConfigFile {
+    fn empty() -> Self {
+        ConfigFile {
+            version: CONFIG_VERSION,
+            engine: None,
+            key_id: String::new(),
+            instance_id: String::new(),
+            instance_name: None,
+            local: SettingsLayer::default(),
+            sync_remote: None,
+            sync_branch: None,
+            sync_proxy: None,
+            commit_name: None,
+            commit_email: None,
+            secrets: BTreeMap::new()
+        }
+    }
+
+    fn overridden_by(&self, overrides: &ConfigOverrides) -> Self {
+        ConfigFile {
+            version: self.version,
+            engine: overrides.engine
+                .clone()
+                .or_else(|| self.engine.clone()),
+            key_id: overrides.key_id
+                .clone()
+                .unwrap_or_else(|| self.key_id.clone()),
+            instance_id: overrides.instance_id
+                .map(|instance_id| instance_id.to_string())
+                .unwrap_or_else(|| self.instance_id.clone()),
+            instance_name: overrides.instance_name
+                .clone()
+                .or_else(|| self.instance_name.clone()),
+            local: self.local
+                .overridden_by(&overrides.local),
+            sync_remote: overrides.sync_remote
+                .clone()
+                .or_else(|| self.sync_remote.clone()),
+            sync_branch: overrides.sync_branch
+                .clone()
+                .or_else(|| self.sync_branch.clone()),
+            sync_proxy: overrides.sync_proxy
+                .clone()
+                .or_else(|| self.sync_proxy.clone()),
+            commit_name: overrides.commit_name
+                .clone()
+                .or_else(|| self.commit_name.clone()),
+            commit_email: overrides.commit_email
+                .clone()
+                .or_else(|| self.commit_email.clone()),
+            secrets: self.secrets.clone()
+        }
+    }
+}
+
+
+/// Configuration values, that override ones from configuration file.
+/// 
+/// Absent values are not overridden. Credentials in URLs are
+/// redacted in [`std::fmt::Debug`] output.
+#[derive(Clone, Default)]
+pub struct ConfigOverrides {
+    /// Cryptographic engine name
+    pub engine: Option<String>,
+
+    /// Key identifier as string
+    pub key_id: Option<String>,
+
+    /// Instance identifier
+    pub instance_id: Option<InstanceId>,
+
+    /// User-friendly instance name
+    pub instance_name: Option<String>,
+
+    /// Synchronization remote URL
+    pub sync_remote: Option<String>,
+
+    /// Synchronization branch name
+    pub sync_branch: Option<String>,
+
+    /// Synchronization proxy: "auto", "direct" or URL
+    pub sync_proxy: Option<String>,
+
+    /// Name of author of synchronization commits
+    pub commit_name: Option<String>,
+
+    /// Email of author of synchronization commits
+    pub commit_email: Option<String>,
+
+    /// Local settings
+    pub local: SettingsLayer,
+}
+
+
+impl ConfigOverrides {
+    /// Reads overrides from `BDGT_*` environment variables.
+    /// 
+    /// The following variables are supported: `BDGT_ENGINE`, `BDGT_KEY_ID`,
+    /// `BDGT_INSTANCE_ID`, `BDGT_INSTANCE_NAME`, `BDGT_SYNC_REMOTE`, 
+    /// `BDGT_SYNC_BRANCH`, `BDGT_SYNC_PROXY`, `BDGT_COMMIT_NAME`, `BDGT_COMMIT_EMAIL`, `BDGT_CURRENCY`,
+    /// `BDGT_CURRENCY_PRECISION`, `BDGT_AUTO_LOCK_TIMEOUT`
+    /// and `BDGT_TRASH_RETENTION_DAYS`.
+    pub fn from_env() -> Result<Self> {
+        let var = |name: &str| std::env::var(name).ok();
+
+        let instance_id = var(ENV_INSTANCE_ID)
+            .map(|value| uuid::Uuid::parse_str(&value))
+            .transpose()?;
+
+        //
+        // Currency code and precision can be overridden separately
+        //
+
+        let precision = Self::parse_env(ENV_CURRENCY_PRECISION)?;
+        let currency = match (var(ENV_CURRENCY), precision) {
+            (None, None) => None,
+            (code, precision) => {
+                let default = CurrencySettings::default();
+                Some(CurrencySettings { 
+                    code: code.unwrap_or(default.code), 
+                    precision: precision.unwrap_or(default.precision) 
+                })
+            }
+        };
+
+        Ok(ConfigOverrides {
+            engine: var(ENV_ENGINE),
+            key_id: var(ENV_KEY_ID),
+            instance_id,
+            instance_name: var(ENV_INSTANCE_NAME),
+            sync_remote: var(ENV_SYNC_REMOTE),
+            sync_branch: var(ENV_SYNC_BRANCH),
+            sync_proxy: var(ENV_SYNC_PROXY),
+            commit_name: var(ENV_COMMIT_NAME),
+            commit_email: var(ENV_COMMIT_EMAIL),
+            local: SettingsLayer {
+                currency,
+                auto_lock_timeout: Self::parse_env(ENV_AUTO_LOCK_TIMEOUT)?,
+                trash_retention_days: Self::parse_env(ENV_TRASH_RETENTION_DAYS)?,
+                essential_categories: None
+            }
+        })
+    }
+
+    /// Returns overrides with values of `self` overridden by `overrides`.
+    ///
+    /// * `overrides` - overrides with higher priority
+    pub fn overridden_by(&self, overrides: &ConfigOverrides) -> Self {
+        ConfigOverrides {
+            engine: overrides.engine
+                .clone()
+                .or_else(|| self.engine.clone()),
+            key_id: overrides.key_id
+                .clone()
+                .or_else(|| self.key_id.clone()),
+            instance_id: overrides.instance_id
+                .or(self.instance_id),
+            instance_name: overrides.instance_name
+                .clone()
+                .or_else(|| self.instance_name.clone()),
+            sync_remote: overrides.sync_remote
+                .clone()
+                .or_else(|| self.sync_remote.clone()),
+            sync_branch: overrides.sync_branch
+                .clone()
+                .or_else(|| self.sync_branch.clone()),
+            sync_proxy: overrides.sync_proxy
+                .clone()
+                .or_else(|| self.sync_proxy.clone()),
+            commit_name: overrides.commit_name
+                .clone()
+                .or_else(|| self.commit_name.clone()),
+            commit_email: overrides.commit_email
+                .clone()
+                .or_else(|| self.commit_email.clone()),
+            local: self.local
+                .overridden_by(&overrides.local)
+        }
+    }
+
+    fn parse_env<T: std::str::FromStr>(name: &str) -> Result<Option<T>> {
+        std::env::var(name)
+            .ok()
+            .map(|value| value.parse())
+            .transpose()
+            .map_err(|_| Error::from_message_with_extra(INVALID_CONFIG, 
+                format!("malformed environment variable: {}", name)).with_kind(ErrorKind::Config))
+    }
+}
+
+
+/// Loader of configuration from several sources.
+/// 
+/// Sources have the following precedence (from the lowest to the highest):
+/// 
+/// 1. defaults;
+/// 2. configuration file (see [`ConfigLoader::location`]);
+/// 3. environment variables (see [`ConfigLoader::environment`]);
+/// 4. programmatic overrides (see [`ConfigLoader::overrides`]).
+/// 
+/// Precedence does not depend on the order of calls. If no configuration 
+/// file is used, then key identifier and instance identifier MUST be 
+/// provided by other sources.
+#[derive(Default)]
+pub struct ConfigLoader {
+    /// Directory with configuration file
+    root: Option<std::path::PathBuf>,
+
+    /// Whether environment variables should be read
+    environment: bool,
+
+    /// Programmatic overrides
+    overrides: ConfigOverrides,
+}
+
+
+impl ConfigLoader {
+    /// Creates a loader, that uses defaults only.
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Use configuration file from a given location.
+    /// 
+    /// * `loc` - storage location provider
+    pub fn location<L: Location>(mut self, loc: &L) -> Self {
+        self.root = Some(loc.config_dir());
+        self
+    }
+
+    /// Use `BDGT_*` environment variables (see [`ConfigOverrides::from_env`]).
+    pub fn environment(mut self) -> Self {
+        self.environment = true;
+        self
+    }
+
+    /// Use programmatic overrides.
+    /// 
+    /// * `overrides` - values to override
+    pub fn overrides(mut self, overrides: ConfigOverrides) -> Self {
+        self.overrides = overrides;
+        self
+    }
+
+    /// Loads configuration from all specified sources.
+    pub fn load<Ce>(self) -> Result<Config<Ce>>
+    where
+        Ce: CryptoEngine,
+        Ce::KeyId: KeyIdentifier
+    {
+        let overrides = if self.environment {
+            ConfigOverrides::from_env()?
+                .overridden_by(&self.overrides)
+        }
+        else {
+            self.overrides
+        };
+
+        match self.root {
+            Some(root) => {
+                let path = Config::<Ce>::config_file(&root);
+                let file = Config::<Ce>::read_or_convert_config(&root)?;
+
+                Config::from_layers(file, overrides, Some(path))
+            },
+
+            None => Config::from_layers(ConfigFile::empty(), overrides, None)
+        }
+    }
+}
+
+
 /// App's instance configuration, contains long-term info.
 pub struct Config<Ce>
 where
@@ -25,6 +432,21 @@ where
 
     /// Identifier of a local bdgt instance.
     instance_id: InstanceId,
+
+    /// Raw configuration as it is stored in file.
+    file: ConfigFile,
+
+    /// Overrides of values from configuration file.
+    overrides: ConfigOverrides,
+
+    /// Effective configuration: file values with applied overrides.
+    effective: ConfigFile,
+
+    /// Path to configuration file if it is used.
+    path: Option<std::path::PathBuf>,
+
+    /// Modification time of configuration file at the moment of last read.
+    modified: Option<std::time::SystemTime>,
 }
 
 
@@ -34,22 +456,20 @@ where
     Ce::KeyId: KeyIdentifier
 {
     /// Opens an existing storage and load stored configuration.
-    /// 
+    ///
+    /// Only configuration file is used, see [`ConfigLoader`] for other
+    /// configuration sources. Configuration is validated and upgraded to the latest format
+    /// version if necessary.
+    ///
     /// * `loc` - storage location provider
     pub fn open<L: Location>(loc: &L) -> Result<Self> {
-        let raw_id = std::fs::read_to_string(Self::key_file(loc))?;
-        
-        let instance_id = std::fs::read(Self::instance_file(loc))?;
-        let instance_id = uuid::Uuid::from_slice(&instance_id)?;
-
-        Ok(Config { 
-            key_id: Ce::KeyId::from_str(raw_id.as_str()),
-            instance_id: instance_id
-        })
+        ConfigLoader::new()
+            .location(loc)
+            .load()
     }
 
     /// Creates a new storage and then loads configuration.
-    /// 
+    ///
     /// * `loc` - storage location provider
     /// * `key_id` - key identifier
     pub fn create<L: Location>(loc: &L, key_id: &Ce::KeyId) -> Result<Self> {
@@ -60,19 +480,91 @@ where
         loc.create_if_absent()?;
 
         //
-        // Save key into a file, generate new instance identifier,
+        // Save key and a new instance identifier into a file,
         // and then just open config :)
         //
 
-        std::fs::write(Self::key_file(loc), 
-            key_id.as_string())?;
+        let file = ConfigFile {
+            version: CONFIG_VERSION,
+            engine: None,
+            key_id: key_id.as_string(),
+            instance_id: Self::new_instance().to_string(),
+            instance_name: None,
+            local: SettingsLayer::default(),
+            sync_remote: None,
+            sync_branch: None,
+            sync_proxy: None,
+            commit_name: None,
+            commit_email: None,
+            secrets: BTreeMap::new()
+        };
 
-        std::fs::write(Self::instance_file(loc), 
-            Self::new_instance())?;
+        Self::write_config(&Self::config_file(&loc.config_dir()), &file)?;
 
         Self::open(loc)
     }
 
+    /// Writes current configuration into its file.
+    /// 
+    /// Overridden values are not written, file values are written instead.
+    pub fn save(&mut self) -> Result<()> {
+        let path = self.path
+            .as_ref()
+            .ok_or(Error::from_message_with_extra(INVALID_CONFIG, "no configuration file").with_kind(ErrorKind::Config))?;
+
+        Self::validate(&self.effective)?;
+        Self::write_config(path, &self.file)?;
+
+        //
+        // Own changes should not be reported by watch
+        //
+
+        self.modified = Self::modification_time(path);
+
+        Ok(())
+    }
+
+    /// Re-reads configuration file unconditionally.
+    ///
+    /// Returns a list of changed entries. If the new configuration
+    /// is invalid, an error is returned and current one is kept.
+    /// 
+    /// Overrides are kept as is. If no configuration file is used,
+    /// then nothing happens.
+    pub fn reload(&mut self) -> Result<Vec<ConfigKey>> {
+        let path = match &self.path {
+            Some(path) => path.clone(),
+            None => return Ok(Vec::new())
+        };
+
+        let modified = Self::modification_time(&path);
+        let reloaded = Self::from_layers(Self::read_config(&path)?, 
+            self.overrides.clone(), Some(path))?;
+
+        let changes = Self::diff(&self.effective, &reloaded.effective);
+
+        *self = reloaded;
+        self.modified = modified;
+
+        Ok(changes)
+    }
+
+    /// Re-reads configuration file if it was changed on disk since last read.
+    ///
+    /// Intended to be called periodically by long-running applications.
+    /// Returns a list of changed entries (empty if file was not changed).
+    pub fn watch(&mut self) -> Result<Vec<ConfigKey>> {
+        let modified = self.path
+            .as_deref()
+            .and_then(Self::modification_time);
+
+        if modified == self.modified {
+            return Ok(Vec::new());
+        }
+
+        self.reload()
+    }
+
     /// Obtain the stored key identifier.
     pub fn key_id(&self) -> &Ce::KeyId {
         &self.key_id
@@ -82,6 +574,194 @@ where
     pub fn instance_id(&self) -> &InstanceId {
         &self.instance_id
     }
+
+    /// Obtain name of cryptographic engine, that the key belongs to.
+    ///
+    /// [`None`] means, that engine was not specified explicitly.
+    pub fn engine(&self) -> Option<&str> {
+        self.effective.engine.as_deref()
+    }
+
+    /// Obtain user-friendly instance name.
+    pub fn instance_name(&self) -> Option<&str> {
+        self.effective.instance_name.as_deref()
+    }
+
+    /// Obtain local settings layer, that overrides shared settings.
+    pub fn local_settings(&self) -> &SettingsLayer {
+        &self.effective.local
+    }
+
+    /// Obtain synchronization remote URL.
+    pub fn sync_remote(&self) -> Option<&str> {
+        self.effective.sync_remote.as_deref()
+    }
+
+    /// Obtain synchronization branch name.
+    /// 
+    /// [`None`] means, that default branch of remote is used.
+    pub fn sync_branch(&self) -> Option<&str> {
+        self.effective.sync_branch.as_deref()
+    }
+
+    /// Obtain synchronization proxy.
+    /// 
+    /// [`None`] means, that proxy is detected automatically (see [`Proxy::Auto`]).
+    pub fn sync_proxy(&self) -> Result<Option<Proxy>> {
+        self.effective.sync_proxy
+            .as_deref()
+            .map(str::parse)
+            .transpose()
+    }
+
+    /// Obtain name of author of synchronization commits.
+    pub fn commit_name(&self) -> Option<&str> {
+        self.effective.commit_name.as_deref()
+    }
+
+    /// Obtain email of author of synchronization commits.
+    pub fn commit_email(&self) -> Option<&str> {
+        self.effective.commit_email.as_deref()
+    }
+
+    /// Set name of cryptographic engine, that the key belongs to.
+    ///
+    /// Changes are not saved until [`Config::save`] is called.
+    ///
+    /// * `engine` - engine name or [`None`]
+    pub fn set_engine(&mut self, engine: Option<&str>) {
+        self.file.engine = engine.map(str::to_owned);
+        self.effective = self.file.overridden_by(&self.overrides);
+    }
+
+    /// Set user-friendly instance name.
+    ///
+    /// Changes are not saved until [`Config::save`] is called.
+    ///
+    /// * `instance_name` - instance name or [`None`]
+    pub fn set_instance_name(&mut self, instance_name: Option<&str>) {
+        self.file.instance_name = instance_name.map(str::to_owned);
+        self.effective = self.file.overridden_by(&self.overrides);
+    }
+
+    /// Set local settings layer, that overrides shared settings.
+    ///
+    /// Changes are not saved until [`Config::save`] is called.
+    ///
+    /// * `local` - local settings
+    pub fn set_local_settings(&mut self, local: SettingsLayer) {
+        self.file.local = local;
+        self.effective = self.file.overridden_by(&self.overrides);
+    }
+
+    /// Set synchronization remote URL.
+    ///
+    /// Changes are not saved until [`Config::save`] is called.
+    ///
+    /// * `sync_remote` - remote URL or [`None`]
+    pub fn set_sync_remote(&mut self, sync_remote: Option<&str>) {
+        self.file.sync_remote = sync_remote.map(str::to_owned);
+        self.effective = self.file.overridden_by(&self.overrides);
+    }
+
+    /// Set synchronization branch name.
+    ///
+    /// Changes are not saved until [`Config::save`] is called.
+    ///
+    /// * `sync_branch` - branch name or [`None`] for default branch of remote
+    pub fn set_sync_branch(&mut self, sync_branch: Option<&str>) {
+        self.file.sync_branch = sync_branch.map(str::to_owned);
+        self.effective = self.file.overridden_by(&self.overrides);
+    }
+
+    /// Set synchronization proxy. Proxy credentials, if any, are
+    /// stored in configuration file as a part of URL.
+    ///
+    /// Changes are not saved until [`Config::save`] is called.
+    ///
+    /// * `sync_proxy` - proxy or [`None`] for automatic detection
+    pub fn set_sync_proxy(&mut self, sync_proxy: Option<&Proxy>) {
+        self.file.sync_proxy = sync_proxy.map(Proxy::to_config_value);
+        self.effective = self.file.overridden_by(&self.overrides);
+    }
+
+    /// Set author of synchronization commits. Absent values are
+    /// taken from git configuration.
+    ///
+    /// Changes are not saved until [`Config::save`] is called.
+    ///
+    /// * `name` - author's name or [`None`]
+    /// * `email` - author's email or [`None`]
+    pub fn set_commit_identity(&mut self, name: Option<&str>, email: Option<&str>) {
+        self.file.commit_name = name.map(str::to_owned);
+        self.file.commit_email = email.map(str::to_owned);
+        self.effective = self.file.overridden_by(&self.overrides);
+    }
+
+    /// Obtain names of all stored sensitive values.
+    pub fn secrets(&self) -> impl Iterator<Item = &str> {
+        self.file.secrets
+            .keys()
+            .map(String::as_str)
+    }
+
+    /// Obtain a decrypted sensitive value (e.g. remote token or passphrase).
+    ///
+    /// * `crypto_engine` - cryptographic engine to decrypt value with
+    /// * `key` - key to decrypt value with
+    /// * `name` - name of the value
+    pub fn secret(&self, crypto_engine: &Ce, key: &Ce::Key, name: &str) -> Result<Option<CryptoBuffer>> {
+        let ciphertext = match self.file.secrets.get(name) {
+            Some(ciphertext) => Self::decode_hex(ciphertext)?,
+            None => return Ok(None)
+        };
+
+        crypto_engine
+            .decrypt(key, &ciphertext)
+            .map(Some)
+    }
+
+    /// Set a sensitive value. It is stored encrypted with provided key.
+    ///
+    /// Changes are not saved until [`Config::save`] is called.
+    ///
+    /// * `crypto_engine` - cryptographic engine to encrypt value with
+    /// * `key` - key to encrypt value with
+    /// * `name` - name of the value
+    /// * `value` - plaintext value
+    pub fn set_secret(&mut self, crypto_engine: &Ce, key: &Ce::Key, name: &str, value: &[u8]) -> Result<()> {
+        let ciphertext = crypto_engine
+            .encrypt(key, value)?;
+
+        self.file.secrets
+            .insert(name.to_owned(), Self::encode_hex(ciphertext.as_bytes()));
+
+        self.effective = self.file.overridden_by(&self.overrides);
+
+        Ok(())
+    }
+
+    /// Remove a sensitive value. Returns `true` if the value was present.
+    ///
+    /// Changes are not saved until [`Config::save`] is called.
+    ///
+    /// * `name` - name of the value
+    pub fn remove_secret(&mut self, name: &str) -> bool {
+        let removed = self.file.secrets
+            .remove(name)
+            .is_some();
+
+        self.effective = self.file.overridden_by(&self.overrides);
+
+        removed
+    }
+
+    /// Obtain OS keyring with secrets of the instance. It is an alternative
+    /// to sensitive values stored in configuration.
+    #[cfg(feature = "keyring")]
+    pub fn keyring(&self) -> Keyring {
+        Keyring::new(&self.instance_id().to_string())
+    }
 }
 
 
@@ -90,14 +770,16 @@ where
     Ce: CryptoEngine,
     Ce::KeyId: KeyIdentifier
 {
-    fn key_file<L: Location>(loc: &L) -> std::path::PathBuf {
-        loc.root()
-            .join(KEY_IDENTIFIER_FILE)
+    fn config_file(root: &std::path::Path) -> std::path::PathBuf {
+        root.join(CONFIG_FILE)
     }
 
-    fn instance_file<L: Location>(loc: &L) -> std::path::PathBuf {
-        loc.root()
-            .join(INSTANCE_IDENTIFIER_FILE)
+    fn key_file(root: &std::path::Path) -> std::path::PathBuf {
+        root.join(KEY_IDENTIFIER_FILE)
+    }
+
+    fn instance_file(root: &std::path::Path) -> std::path::PathBuf {
+        root.join(INSTANCE_IDENTIFIER_FILE)
     }
 }
 
@@ -110,4 +792,225 @@ where
     fn new_instance() -> InstanceId {
         uuid::Uuid::new_v4()
     }
+
+    fn from_layers(file: ConfigFile, overrides: ConfigOverrides, path: Option<std::path::PathBuf>) -> Result<Self> {
+        let effective = file.overridden_by(&overrides);
+        Self::validate(&effective)?;
+
+        Ok(Config {
+            key_id: Ce::KeyId::from_str(&effective.key_id),
+            instance_id: uuid::Uuid::parse_str(&effective.instance_id)?,
+            modified: path.as_deref().and_then(Self::modification_time),
+            file,
+            overrides,
+            effective,
+            path
+        })
+    }
+
+    fn read_or_convert_config(root: &std::path::Path) -> Result<ConfigFile> {
+        let path = Self::config_file(root);
+        if path.exists() {
+            return Self::read_config(&path);
+        }
+
+        //
+        // No configuration file here, it is a legacy configuration.
+        // I convert it into a new format and save the result
+        //
+
+        let file = Self::read_legacy_config(root)?;
+        Self::write_config(&path, &file)?;
+
+        Ok(file)
+    }
+
+    fn read_config(path: &std::path::Path) -> Result<ConfigFile> {
+        let raw = std::fs::read_to_string(path)
+            .with_context(|| format!("reading configuration file {}", path.display()))?;
+        let mut table: toml::Table = toml::from_str(&raw)?;
+
+        let version = table
+            .get("version")
+            .and_then(toml::Value::as_integer)
+            .ok_or(Error::from_message_with_extra(INVALID_CONFIG, "missing version").with_kind(ErrorKind::Config))?;
+
+        //
+        // Upgrade configuration step by step. If it was upgraded,
+        // then I save it immediately
+        //
+
+        let mut current = version;
+        while current < CONFIG_VERSION as i64 {
+            current = Self::upgrade(&mut table, current)?;
+        }
+
+        if current != CONFIG_VERSION as i64 {
+            return Err(Error::from_message_with_extra(UNSUPPORTED_CONFIG_VERSION,
+                format!("version: {}", version)).with_kind(ErrorKind::Config));
+        }
+
+        let file: ConfigFile = table.try_into()?;
+
+        if version != current {
+            Self::write_config(path, &file)?;
+        }
+
+        Ok(file)
+    }
+
+    fn read_legacy_config(root: &std::path::Path) -> Result<ConfigFile> {
+        let key_id = std::fs::read_to_string(Self::key_file(root))
+            .context("reading legacy key file")?;
+
+        let instance_id = std::fs::read(Self::instance_file(root))
+            .context("reading legacy instance file")?;
+        let instance_id = uuid::Uuid::from_slice(&instance_id)?;
+
+        Ok(ConfigFile {
+            version: CONFIG_VERSION,
+            engine: None,
+            key_id,
+            instance_id: instance_id.to_string(),
+            instance_name: None,
+            local: SettingsLayer::default(),
+            sync_remote: None,
+            sync_branch: None,
+            sync_proxy: None,
+            commit_name: None,
+            commit_email: None,
+            secrets: BTreeMap::new()
+        })
+    }
+
+    fn write_config(path: &std::path::Path, file: &ConfigFile) -> Result<()> {
+        std::fs::write(path, toml::to_string(file)?)?;
+        restrict_file(path)
+    }
+
+    fn upgrade(_table: &mut toml::Table, version: i64) -> Result<i64> {
+        //
+        // Each upgrade step converts configuration from a given version
+        // into the next one. Version 1 is the first one stored in a file,
+        // hence there are no steps for now
+        //
+
+        Err(Error::from_message_with_extra(UNSUPPORTED_CONFIG_VERSION,
+            format!("version: {}", version)).with_kind(ErrorKind::Config))
+    }
+
+    fn modification_time(path: &std::path::Path) -> Option<std::time::SystemTime> {
+        std::fs::metadata(path)
+            .and_then(|metadata| metadata.modified())
+            .ok()
+    }
+
+    fn diff(old: &ConfigFile, new: &ConfigFile) -> Vec<ConfigKey> {
+        let mut changes = Vec::new();
+
+        if old.engine != new.engine {
+            changes.push(ConfigKey::Engine);
+        }
+
+        if old.key_id != new.key_id {
+            changes.push(ConfigKey::KeyId);
+        }
+
+        if old.instance_id != new.instance_id {
+            changes.push(ConfigKey::InstanceId);
+        }
+
+        if old.instance_name != new.instance_name {
+            changes.push(ConfigKey::InstanceName);
+        }
+
+        if old.local != new.local {
+            changes.push(ConfigKey::LocalSettings);
+        }
+
+        if old.sync_remote != new.sync_remote {
+            changes.push(ConfigKey::SyncRemote);
+        }
+
+        if old.sync_branch != new.sync_branch {
+            changes.push(ConfigKey::SyncBranch);
+        }
+
+        if old.sync_proxy != new.sync_proxy {
+            changes.push(ConfigKey::SyncProxy);
+        }
+
+        if old.commit_name != new.commit_name || old.commit_email != new.commit_email {
+            changes.push(ConfigKey::CommitIdentity);
+        }
+
+        let secret_names = old.secrets
+            .keys()
+            .chain(new.secrets.keys())
+            .collect::<std::collections::BTreeSet<_>>();
+
+        for name in secret_names {
+            if old.secrets.get(name) != new.secrets.get(name) {
+                changes.push(ConfigKey::Secret(name.clone()));
+            }
+        }
+
+        changes
+    }
+
+    fn encode_hex(bytes: &[u8]) -> String {
+        bytes
+            .iter()
+            .map(|byte| format!("{:02x}", byte))
+            .collect()
+    }
+
+    fn decode_hex(hex: &str) -> Result<Vec<u8>> {
+        let malformed = || Error::from_message_with_extra(INVALID_CONFIG, "malformed secret value").with_kind(ErrorKind::Config);
+
+        if !hex.len().is_multiple_of(2) {
+            return Err(malformed());
+        }
+
+        (0..hex.len())
+            .step_by(2)
+            .map(|position| hex.get(position..position + 2)
+                .and_then(|byte| u8::from_str_radix(byte, 16).ok())
+                .ok_or_else(malformed))
+            .collect()
+    }
+
+    fn validate(file: &ConfigFile) -> Result<()> {
+        if file.key_id.trim().is_empty() {
+            return Err(Error::from_message_with_extra(INVALID_CONFIG, "empty key identifier").with_kind(ErrorKind::Config));
+        }
+
+        if uuid::Uuid::parse_str(&file.instance_id).is_err() {
+            return Err(Error::from_message_with_extra(INVALID_CONFIG, "malformed instance identifier").with_kind(ErrorKind::Config));
+        }
+
+        let is_blank = |value: &Option<String>| value
+            .as_ref()
+            .is_some_and(|value| value.trim().is_empty());
+
+        if is_blank(&file.engine) || is_blank(&file.instance_name) || is_blank(&file.sync_remote) || is_blank(&file.sync_branch) {
+            return Err(Error::from_message_with_extra(INVALID_CONFIG, "empty value").with_kind(ErrorKind::Config));
+        }
+
+        if file.sync_branch.as_ref().is_some_and(|branch| branch.contains(char::is_whitespace)) {
+            return Err(Error::from_message_with_extra(INVALID_CONFIG, "malformed branch name").with_kind(ErrorKind::Config));
+        }
+
+        if file.sync_proxy.as_deref().is_some_and(|proxy| proxy.parse::<Proxy>().is_err()) {
+            return Err(Error::from_message_with_extra(INVALID_CONFIG, "malformed proxy").with_kind(ErrorKind::Config));
+        }
+
+        if file.secrets.iter().any(|(name, _)| name.trim().is_empty()) {
+            return Err(Error::from_message_with_extra(INVALID_CONFIG, "empty secret name").with_kind(ErrorKind::Config));
+        }
+
+        file.local.validate()?;
+
+        Ok(())
+    }
 }
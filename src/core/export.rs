@@ -0,0 +1,144 @@
+use std::collections::HashMap;
+use std::io::Write;
+
+use serde::Serialize;
+
+use crate::storage::{Transaction, TransactionKind, Id};
+use crate::error::Result;
+
+
+/// Columns of CSV exports.
+const CSV_HEADER: &str = "id,date,description,account,category,amount,kind,note";
+
+
+/// Format of exported transactions.
+#[derive(Clone, Copy, PartialEq, Eq, Debug)]
+pub enum ExportFormat {
+    /// Comma-separated values with a header row (RFC 4180)
+    Csv,
+
+    /// JSON Lines: one JSON object per transaction
+    JsonLines,
+}
+
+
+/// Transaction as it is exported: references are replaced with names.
+#[derive(Serialize)]
+struct ExportedTransaction<'a> {
+    id: String,
+    date: String,
+    description: &'a str,
+    account: &'a str,
+    category: &'a str,
+    amount: isize,
+    kind: &'static str,
+    note: &'a str,
+}
+
+
+/// Writer of exported transactions, that writes them one by one,
+/// hence memory usage does not depend on a number of transactions.
+pub(crate) struct TransactionWriter<W: Write> {
+    /// Destination of the export
+    writer: W,
+
+    /// Format of the export
+    format: ExportFormat,
+
+    /// Names of accounts
+    accounts: HashMap<Id, String>,
+
+    /// Names of categories
+    categories: HashMap<Id, String>,
+
+    /// Number of written transactions
+    written: usize,
+}
+
+
+impl<W: Write> TransactionWriter<W> {
+    /// Creates a writer and writes a header if the format has one.
+    ///
+    /// * `writer` - destination of the export
+    /// * `format` - format of the export
+    /// * `accounts` - names of accounts
+    /// * `categories` - names of categories
+    pub(crate) fn new(mut writer: W, format: ExportFormat, accounts: HashMap<Id, String>, categories: HashMap<Id, String>) -> Result<Self> {
+        if format == ExportFormat::Csv {
+            writeln!(writer, "{}", CSV_HEADER)?;
+        }
+
+        Ok(TransactionWriter { writer, format, accounts, categories, written: 0 })
+    }
+
+    /// Writes a transaction.
+    ///
+    /// * `transaction` - transaction to write
+    pub(crate) fn write(&mut self, transaction: &Transaction) -> Result<()> {
+        let exported = ExportedTransaction {
+            id: transaction.id
+                .map_or(String::new(), |id| uuid::Uuid::from_bytes(id).to_string()),
+            date: transaction.timestamp.to_rfc3339(),
+            description: &transaction.description,
+            account: name_of(&self.accounts, transaction.account_id),
+            category: name_of(&self.categories, transaction.category_id),
+            amount: transaction.amount,
+            kind: kind_name(transaction.kind),
+            note: &transaction.note
+        };
+
+        match self.format {
+            ExportFormat::Csv => writeln!(self.writer, "{},{},{},{},{},{},{},{}",
+                exported.id, exported.date, csv_field(exported.description), csv_field(exported.account),
+                csv_field(exported.category), exported.amount, exported.kind, csv_field(exported.note))?,
+
+            ExportFormat::JsonLines => {
+                serde_json::to_writer(&mut self.writer, &exported)?;
+                writeln!(self.writer)?;
+            }
+        }
+
+        self.written += 1;
+        Ok(())
+    }
+
+    /// Returns number of written transactions.
+    pub(crate) fn written(&self) -> usize {
+        self.written
+    }
+
+    /// Flushes the destination and returns number of written transactions.
+    pub(crate) fn finish(mut self) -> Result<usize> {
+        self.writer.flush()?;
+        Ok(self.written)
+    }
+}
+
+
+fn name_of(names: &HashMap<Id, String>, id: Id) -> &str {
+    //
+    // Removed items have no names, but their
+    // transactions may remain
+    //
+
+    names
+        .get(&id)
+        .map_or("", String::as_str)
+}
+
+
+fn kind_name(kind: TransactionKind) -> &'static str {
+    match kind {
+        TransactionKind::Regular => "regular",
+        TransactionKind::OpeningBalance => "opening_balance",
+        TransactionKind::Adjustment => "adjustment"
+    }
+}
+
+
+fn csv_field(value: &str) -> std::borrow::Cow<'_, str> {
+    match value.contains([',', '"', '\n', '\r']) {
+        true => format!("\"{}\"", value.replace('"', "\"\"")).into(),
+        false => value.into()
+    }
+}
@@ -0,0 +1,67 @@
+use crate::storage::{Transaction, CustomFields, CustomValue};
+use super::reserved::{self, EXTERNAL_ID_PREFIX};
+
+
+/// Returns identifier of an item in an external source (if any).
+///
+/// External identifiers are stored in custom fields of items, hence
+/// they are encrypted and synchronized along with items.
+///
+/// * `custom_fields` - custom fields of an item
+/// * `source` - name of an external source (e.g. bank or importer name)
+pub fn external_id<'a>(custom_fields: &'a CustomFields, source: &str) -> Option<&'a str> {
+    reserved::text(custom_fields, &external_key(source))
+}
+
+
+/// Stores identifier of an item in an external source.
+///
+/// * `custom_fields` - custom fields of an item
+/// * `source` - name of an external source
+/// * `external_id` - identifier of the item in the source
+pub(crate) fn set_external_id(custom_fields: &mut CustomFields, source: &str, external_id: &str) {
+    reserved::set_text(custom_fields, &external_key(source), Some(external_id));
+}
+
+
+/// Returns all pairs of external sources and identifiers of an item in them.
+///
+/// * `custom_fields` - custom fields of an item
+pub(crate) fn external_ids(custom_fields: &CustomFields) -> impl Iterator<Item = (&str, &str)> {
+    custom_fields
+        .iter()
+        .filter_map(|(key, value)| match (key.strip_prefix(EXTERNAL_ID_PREFIX), value) {
+            (Some(source), CustomValue::Text(external_id)) => Some((source, external_id.as_str())),
+            _ => None
+        })
+}
+
+
+/// Checks if an item has an identifier in an external source.
+///
+/// * `custom_fields` - custom fields of an item
+/// * `source` - name of an external source
+/// * `external_id` - identifier of the item in the source
+pub(crate) fn has_external_id(custom_fields: &CustomFields, source: &str, external_id: &str) -> bool {
+    self::external_id(custom_fields, source) == Some(external_id)
+}
+
+
+/// Checks if a stored transaction has the same data, that is owned
+/// by an external source, as an imported one.
+///
+/// Category, note and custom fields may be edited by a user after
+/// an import, hence they are not compared.
+///
+/// * `stored` - previously imported transaction
+/// * `imported` - transaction from the source
+pub(crate) fn same_imported_data(stored: &Transaction, imported: &Transaction) -> bool {
+    stored.timestamp == imported.timestamp &&
+    stored.description == imported.description &&
+    stored.amount == imported.amount
+}
+
+
+fn external_key(source: &str) -> String {
+    format!("{}{}", EXTERNAL_ID_PREFIX, source)
+}
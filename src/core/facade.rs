@@ -0,0 +1,113 @@
+use crate::error::{Result, Context};
+use crate::location::{Location, SelectedLocation};
+use crate::crypto::GpgCryptoEngine;
+use crate::storage::DbStorage;
+use crate::sync::GitSyncEngine;
+use crate::setup::Initializer;
+use super::budget::Budget;
+use super::config::Config;
+
+
+/// Budget composed of native backends: GnuPG for encryption, SQLite
+/// for storage and git for synchronization.
+pub type DefaultBudget = Budget<GpgCryptoEngine, GitSyncEngine, DbStorage>;
+
+
+impl DefaultBudget {
+    /// Opens an existing budget in a given location.
+    ///
+    /// All components are composed from location's configuration.
+    /// Executable synchronization hooks of the location are registered
+    /// (see [`Budget::add_executable_hooks`]).
+    ///
+    /// * `loc` - storage location provider
+    pub fn open<L: Location>(loc: &L) -> Result<Self> {
+        Self::open_with_engine(loc, GpgCryptoEngine::open(loc)?)
+    }
+
+    /// Opens an existing budget in a given location, symmetric key is
+    /// unlocked from local key cache instead of GPG agent (see
+    /// [`DefaultBudget::enable_key_cache`]).
+    ///
+    /// * `loc` - storage location provider
+    /// * `passphrase` - passphrase, that protects the cache
+    pub fn open_with_key_cache<L: Location>(loc: &L, passphrase: &[u8]) -> Result<Self> {
+        let crypto_engine = GpgCryptoEngine::open(loc)?;
+        crypto_engine.unlock_key_cache(passphrase)?;
+
+        Self::open_with_engine(loc, crypto_engine)
+    }
+
+    /// Opens an existing budget in a location selected by environment
+    /// variables (see [`SelectedLocation::from_env`]).
+    pub fn open_default() -> Result<Self> {
+        let loc = SelectedLocation::from_env()?;
+
+        Self::open(&loc)
+            .with_context(|| format!("opening budget in {}", loc.root().display()))
+    }
+
+    /// Creates a new budget in a given location.
+    ///
+    /// Performs the whole first-run initialization. For more control
+    /// over it use [`Initializer`] directly.
+    ///
+    /// * `loc` - location to initialize
+    /// * `key_id` - identifier of a GPG key used to protect data
+    pub fn create<L: Location>(loc: L, key_id: &str) -> Result<Self> {
+        Initializer::new(loc, key_id)
+            .run()
+    }
+
+    /// Enables local key cache protected by a passphrase, so that
+    /// the budget can be opened without GPG agent (see
+    /// [`DefaultBudget::open_with_key_cache`]).
+    ///
+    /// * `passphrase` - passphrase, that protects the cache
+    pub fn enable_key_cache(&self, passphrase: &[u8]) -> Result<()> {
+        self.crypto_engine()
+            .enable_key_cache(self.key(), passphrase)
+    }
+
+    /// Disables local key cache and removes the cached key.
+    pub fn disable_key_cache(&self) -> Result<()> {
+        self.crypto_engine()
+            .disable_key_cache()
+    }
+
+    /// Checks if local key cache is enabled.
+    pub fn is_key_cache_enabled(&self) -> bool {
+        self.crypto_engine()
+            .is_key_cache_enabled()
+    }
+
+    /// Forgets symmetric key unlocked in memory, the next operation
+    /// asks GPG agent for it.
+    pub fn clear_key_cache(&self) -> Result<()> {
+        self.crypto_engine()
+            .clear_key_cache()
+    }
+}
+
+
+impl DefaultBudget {
+    fn open_with_engine<L: Location>(loc: &L, crypto_engine: GpgCryptoEngine) -> Result<Self> {
+        let config = Config::open(loc)?;
+        let storage = DbStorage::open(loc)?;
+        let mut sync_engine = GitSyncEngine::open(loc)?
+            .with_commit_identity(config.commit_name(), config.commit_email());
+
+        if let Some(branch) = config.sync_branch() {
+            sync_engine = sync_engine.with_branch(branch);
+        }
+
+        if let Some(proxy) = config.sync_proxy()? {
+            sync_engine = sync_engine.with_proxy(proxy);
+        }
+
+        let budget = Budget::new(crypto_engine, sync_engine, storage, config)?;
+        budget.add_executable_hooks(loc);
+
+        Ok(budget)
+    }
+}
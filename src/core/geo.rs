@@ -0,0 +1,94 @@
+use crate::storage::CustomFields;
+use super::reserved::{self, LATITUDE_KEY, LONGITUDE_KEY, PLACE_KEY};
+
+/// Number of microdegrees in a degree.
+const MICRODEGREES: f64 = 1_000_000.0;
+
+
+/// Location, where a transaction was made.
+///
+/// Location is stored in custom fields of a transaction, hence it is
+/// encrypted and synchronized as any other sensitive value. It is not
+/// [`std::fmt::Debug`] for the same reason.
+#[derive(Clone, PartialEq)]
+pub struct GeoLocation {
+    /// Latitude in degrees (positive to the north)
+    pub latitude: f64,
+
+    /// Longitude in degrees (positive to the east)
+    pub longitude: f64,
+
+    /// Name of a place (e.g. shop name)
+    pub place: Option<String>,
+}
+
+
+/// Area between two parallels and two meridians.
+#[derive(Clone, Copy, PartialEq, Debug)]
+pub struct BoundingBox {
+    /// Southern latitude in degrees
+    pub south: f64,
+
+    /// Western longitude in degrees
+    pub west: f64,
+
+    /// Northern latitude in degrees
+    pub north: f64,
+
+    /// Eastern longitude in degrees
+    pub east: f64,
+}
+
+
+impl BoundingBox {
+    /// Checks if a location is inside the box (including its borders).
+    ///
+    /// A box, which western longitude is greater than eastern one,
+    /// crosses the antimeridian.
+    ///
+    /// * `location` - location to check
+    pub fn contains(&self, location: &GeoLocation) -> bool {
+        let within_longitudes = match self.west <= self.east {
+            true => (self.west..=self.east).contains(&location.longitude),
+            false => location.longitude >= self.west || location.longitude <= self.east
+        };
+
+        (self.south..=self.north).contains(&location.latitude) && within_longitudes
+    }
+}
+
+
+/// Returns location of a transaction (if any).
+///
+/// * `custom_fields` - custom fields of a transaction
+pub fn location(custom_fields: &CustomFields) -> Option<GeoLocation> {
+    let (Some(latitude), Some(longitude)) =
+        (reserved::integer(custom_fields, LATITUDE_KEY), reserved::integer(custom_fields, LONGITUDE_KEY)) else {
+        return None;
+    };
+
+    Some(GeoLocation {
+        latitude: latitude as f64 / MICRODEGREES,
+        longitude: longitude as f64 / MICRODEGREES,
+        place: reserved::text(custom_fields, PLACE_KEY).map(str::to_owned)
+    })
+}
+
+
+/// Stores or removes location of a transaction.
+///
+/// Coordinates are stored with precision of a microdegree (about
+/// ten centimeters), that is more than enough for a shop.
+///
+/// * `custom_fields` - custom fields of a transaction
+/// * `location` - location of the transaction (`None` removes it)
+pub fn set_location(custom_fields: &mut CustomFields, location: Option<&GeoLocation>) {
+    reserved::set_integer(custom_fields, LATITUDE_KEY, location.map(|location| to_microdegrees(location.latitude)));
+    reserved::set_integer(custom_fields, LONGITUDE_KEY, location.map(|location| to_microdegrees(location.longitude)));
+    reserved::set_text(custom_fields, PLACE_KEY, location.and_then(|location| location.place.as_deref()));
+}
+
+
+fn to_microdegrees(degrees: f64) -> isize {
+    (degrees * MICRODEGREES).round() as isize
+}
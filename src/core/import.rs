@@ -0,0 +1,188 @@
+use std::collections::HashMap;
+
+use crate::storage::{Transaction, Id};
+
+
+/// Share of votes, starting from which a prediction is confident.
+const HIGH_CONFIDENCE: f64 = 0.75;
+
+/// Share of votes, starting from which a prediction is plausible.
+const MEDIUM_CONFIDENCE: f64 = 0.5;
+
+
+/// Preview of an import: transactions, that would be stored by
+/// [`crate::core::Budget::commit_import`].
+///
+/// Nothing is stored until the preview is committed, hence a caller
+/// may edit proposed transactions (e.g. correct categories) or remove
+/// some of them.
+#[derive(Clone)]
+pub struct ImportPreview {
+    /// Name of an external source (e.g. bank or importer name)
+    pub source: String,
+
+    /// Proposed transactions in order of import
+    pub transactions: Vec<ProposedTransaction>,
+}
+
+
+/// Transaction proposed by an import preview.
+#[derive(Clone)]
+pub struct ProposedTransaction {
+    /// Identifier of the transaction in the source
+    pub external_id: String,
+
+    /// Transaction to store. Its category is the predicted one if
+    /// there is a prediction, the category of the already imported
+    /// transaction if it exists, otherwise the provided one.
+    pub transaction: Transaction,
+
+    /// Predicted category (if any)
+    pub prediction: Option<CategoryPrediction>,
+
+    /// What committing the transaction will do
+    pub status: ImportStatus,
+}
+
+
+/// Outcome of committing of a proposed transaction.
+#[derive(Clone, Copy, PartialEq, Eq, Debug)]
+pub enum ImportStatus {
+    /// Transaction is new, it will be added
+    New,
+
+    /// Transaction was imported before, but it has changed in the
+    /// source, it will be replaced
+    Changed,
+
+    /// Transaction was imported before and has not changed
+    Unchanged,
+}
+
+
+/// Category predicted for a transaction.
+#[derive(Clone, Copy, PartialEq, Debug)]
+pub struct CategoryPrediction {
+    /// Predicted category
+    pub category_id: Id,
+
+    /// Score from 0 to 1: share of evidence in favor of the category,
+    /// that is discounted for scarce evidence
+    pub score: f64,
+
+    /// Where the prediction comes from
+    pub basis: PredictionBasis,
+}
+
+
+impl CategoryPrediction {
+    /// Returns coarse confidence level of the prediction.
+    pub fn confidence(&self) -> Confidence {
+        if self.score >= HIGH_CONFIDENCE {
+            Confidence::High
+        } else if self.score >= MEDIUM_CONFIDENCE {
+            Confidence::Medium
+        } else {
+            Confidence::Low
+        }
+    }
+}
+
+
+/// Coarse confidence level of a prediction.
+#[derive(Clone, Copy, PartialEq, Eq, PartialOrd, Ord, Debug)]
+pub enum Confidence {
+    /// Prediction is a guess, it should be reviewed
+    Low,
+
+    /// Prediction is plausible
+    Medium,
+
+    /// Prediction is confirmed by consistent history
+    High,
+}
+
+
+/// Source of a category prediction.
+#[derive(Clone, Copy, PartialEq, Eq, Debug)]
+pub enum PredictionBasis {
+    /// Category of previous transactions with the same payee
+    PayeeHistory,
+
+    /// Categories of previous transactions with similar descriptions
+    SimilarTransactions,
+}
+
+
+/// Categories of previous transactions grouped by payees.
+///
+/// Payee is a description of a transaction normalized for comparison:
+/// case and extra whitespaces do not matter.
+pub(crate) struct PayeeHistory {
+    /// Number of transactions per category for each payee
+    payees: HashMap<String, HashMap<Id, usize>>,
+}
+
+
+impl PayeeHistory {
+    /// Collects history from transactions.
+    ///
+    /// * `transactions` - previous transactions
+    pub(crate) fn new(transactions: &[Transaction]) -> Self {
+        let mut payees: HashMap<String, HashMap<Id, usize>> = HashMap::new();
+        for transaction in transactions {
+            *payees.entry(normalize_payee(&transaction.description))
+                .or_default()
+                .entry(transaction.category_id)
+                .or_default() += 1;
+        }
+
+        PayeeHistory { payees }
+    }
+
+    /// Predicts a category by the most frequent one of a payee.
+    ///
+    /// * `description` - description of a transaction
+    pub(crate) fn predict(&self, description: &str) -> Option<CategoryPrediction> {
+        let categories = self.payees
+            .get(&normalize_payee(description))?;
+
+        let total: usize = categories
+            .values()
+            .sum();
+
+        //
+        // Ties are broken by identifier, so that predictions
+        // do not depend on order of a hash map
+        //
+
+        let (category_id, votes) = categories
+            .iter()
+            .max_by(|(lhs_id, lhs_votes), (rhs_id, rhs_votes)| lhs_votes.cmp(rhs_votes)
+                .then_with(|| rhs_id.cmp(lhs_id)))?;
+
+        //
+        // One extra vote against the category makes scarce
+        // history less convincing: 1 of 1 gives 0.5 only
+        //
+
+        Some(CategoryPrediction {
+            category_id: *category_id,
+            score: *votes as f64 / (total + 1) as f64,
+            basis: PredictionBasis::PayeeHistory
+        })
+    }
+}
+
+
+/// Normalizes a description for comparison of payees: case and
+/// extra whitespaces do not matter.
+///
+/// * `description` - description of a transaction
+pub(crate) fn normalize_payee(description: &str) -> String {
+    description
+        .split_whitespace()
+        .collect::<Vec<_>>()
+        .join(" ")
+        .to_lowercase()
+}
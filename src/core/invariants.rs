@@ -0,0 +1,218 @@
+use std::collections::{HashMap, HashSet};
+
+use crate::storage::{Account, Category, Transaction, Plan, Loan, Holding, PricePoint, Person, CategoryGroup, Id};
+use super::changelog::{Changelog, ChangelogItem, SimpleChangelog};
+
+
+/// Properties, that MUST hold for any consistent budget.
+#[derive(Clone, Copy, PartialEq, Eq, Debug)]
+pub enum Invariant {
+    /// Account balance equals its initial balance plus amounts
+    /// of all its transactions
+    AccountBalance,
+
+    /// Income and outcome legs of transfers compensate each other
+    TransferLegs,
+
+    /// Items reference only existing accounts and categories
+    ForeignKeys,
+
+    /// Replay of the changes journal gives the current set of items
+    /// (only existence of journaled items is checked, if the journal
+    /// was pruned after synchronization)
+    ChangelogReplay,
+}
+
+
+impl Invariant {
+    /// All invariants.
+    pub const ALL: [Invariant; 4] = [
+        Invariant::AccountBalance,
+        Invariant::TransferLegs,
+        Invariant::ForeignKeys,
+        Invariant::ChangelogReplay,
+    ];
+}
+
+
+/// Violation of an invariant.
+#[derive(Clone, Debug)]
+pub struct Violation {
+    /// Violated invariant
+    pub invariant: Invariant,
+
+    /// Identifier of an item, that violates the invariant (if any)
+    pub item: Option<Id>,
+
+    /// Human-readable details
+    pub details: String,
+}
+
+
+impl Violation {
+    fn new(invariant: Invariant, item: Option<Id>, details: String) -> Self {
+        Violation {
+            invariant,
+            item,
+            details
+        }
+    }
+}
+
+
+impl std::fmt::Display for Violation {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self.item {
+            Some(id) => write!(f, "{:?} ({}): {}", self.invariant, uuid::Uuid::from_bytes(id), self.details),
+            None => write!(f, "{:?}: {}", self.invariant, self.details)
+        }
+    }
+}
+
+
+/// All live (not removed) items of a budget.
+pub(crate) struct LiveItems {
+    pub accounts: Vec<Account>,
+    pub categories: Vec<Category>,
+    pub transactions: Vec<Transaction>,
+    pub plans: Vec<Plan>,
+    pub loans: Vec<Loan>,
+    pub holdings: Vec<Holding>,
+    pub prices: Vec<PricePoint>,
+    pub persons: Vec<Person>,
+    pub category_groups: Vec<CategoryGroup>,
+}
+
+
+/// Checks, that account balances match their transactions.
+///
+/// * `items` - live items of a budget
+pub(crate) fn account_balances(items: &LiveItems) -> Vec<Violation> {
+    let mut sums: HashMap<Id, isize> = HashMap::new();
+    for transaction in &items.transactions {
+        *sums.entry(transaction.account_id).or_default() += transaction.amount;
+    }
+
+    items.accounts
+        .iter()
+        .filter_map(|account| {
+            let id = account.id?;
+            let expected = account.initial_balance + sums.get(&id).copied().unwrap_or_default();
+
+            (expected != account.balance).then(|| Violation::new(Invariant::AccountBalance, Some(id),
+                format!("balance is {}, but transactions give {}", account.balance, expected)))
+        })
+        .collect()
+}
+
+
+/// Checks, that transfer legs compensate each other.
+///
+/// * `items` - live items of a budget
+/// * `transfer_income` - identifier of transfer income category
+/// * `transfer_outcome` - identifier of transfer outcome category
+pub(crate) fn transfer_legs(items: &LiveItems, transfer_income: Id, transfer_outcome: Id) -> Vec<Violation> {
+    let total: isize = items.transactions
+        .iter()
+        .filter(|transaction| transaction.category_id == transfer_income || transaction.category_id == transfer_outcome)
+        .map(|transaction| transaction.amount)
+        .sum();
+
+    match total {
+        0 => Vec::new(),
+        _ => vec![Violation::new(Invariant::TransferLegs, None,
+            format!("transfers do not compensate each other, the difference is {}", total))]
+    }
+}
+
+
+/// Checks, that all references point to existing accounts and categories.
+///
+/// * `items` - live items of a budget
+pub(crate) fn foreign_keys(items: &LiveItems) -> Vec<Violation> {
+    let existing: HashSet<Id> = ids(&items.accounts)
+        .chain(ids(&items.categories))
+        .collect();
+
+    let mut violations = Vec::new();
+    dangling_references(&items.transactions, &existing, &mut violations);
+    dangling_references(&items.plans, &existing, &mut violations);
+    dangling_references(&items.loans, &existing, &mut violations);
+    dangling_references(&items.holdings, &existing, &mut violations);
+
+    violations
+}
+
+
+/// Checks, that replay of a changelog gives the current set of items.
+///
+/// * `items` - live items of a budget
+/// * `changelog` - changelog since the beginning of the journal
+/// * `complete` - if the journal has never been pruned
+pub(crate) fn changelog_replay(items: &LiveItems, changelog: &Changelog, complete: bool) -> Vec<Violation> {
+    let mut violations = Vec::new();
+    replay(&changelog.accounts, &items.accounts, complete, &mut violations);
+    replay(&changelog.categories, &items.categories, complete, &mut violations);
+    replay(&changelog.transactions, &items.transactions, complete, &mut violations);
+    replay(&changelog.plans, &items.plans, complete, &mut violations);
+    replay(&changelog.loans, &items.loans, complete, &mut violations);
+    replay(&changelog.holdings, &items.holdings, complete, &mut violations);
+    replay(&changelog.prices, &items.prices, complete, &mut violations);
+    replay(&changelog.persons, &items.persons, complete, &mut violations);
+    replay(&changelog.category_groups, &items.category_groups, complete, &mut violations);
+
+    violations
+}
+
+
+fn ids<T: ChangelogItem>(items: &[T]) -> impl Iterator<Item = Id> + '_ {
+    items.iter()
+        .filter_map(ChangelogItem::id)
+}
+
+
+fn dangling_references<T: ChangelogItem>(items: &[T], existing: &HashSet<Id>, violations: &mut Vec<Violation>) {
+    for item in items {
+        for reference in item.references() {
+            if !existing.contains(&reference) {
+                violations.push(Violation::new(Invariant::ForeignKeys, item.id(),
+                    format!("references missing item {}", uuid::Uuid::from_bytes(reference))));
+            }
+        }
+    }
+}
+
+
+fn replay<T: ChangelogItem>(changelog: &SimpleChangelog<T>, live: &[T], complete: bool, violations: &mut Vec<Violation>) {
+    //
+    // Removal is final, hence removed items are dropped after
+    // all additions and changes are applied
+    //
+
+    let mut replayed: HashSet<Id> = ids(&changelog.added)
+        .chain(ids(&changelog.changed))
+        .collect();
+
+    for id in ids(&changelog.removed) {
+        replayed.remove(&id);
+    }
+
+    let live: HashSet<Id> = ids(live).collect();
+
+    //
+    // Exported changes are pruned from the journal, so items
+    // can be missing in it legitimately
+    //
+
+    if complete {
+        for id in live.difference(&replayed) {
+            violations.push(Violation::new(Invariant::ChangelogReplay, Some(*id),
+                "item is missing in changelog".to_owned()));
+        }
+    }
+
+    for id in replayed.difference(&live) {
+        violations.push(Violation::new(Invariant::ChangelogReplay, Some(*id),
+            "changelog contains item, that does not exist".to_owned()));
+    }
+}
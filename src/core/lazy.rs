@@ -0,0 +1,158 @@
+use std::cell::OnceCell;
+
+use crate::crypto::CryptoEngine;
+use crate::sync::SyncEngine;
+use crate::storage::{DataStorage, EncryptedTransaction, Transaction, TransactionKind, CustomFields, MetaInfo, PrimaryId, Id};
+use crate::datetime::Timestamp;
+use crate::error::Result;
+use super::budget::Budget;
+
+
+/// Transaction, which sensitive fields are decrypted on first access.
+///
+/// Listing of transactions decrypts all fields of all of them, even
+/// if only some fields are shown (e.g. dates and amounts). A lazy
+/// transaction decrypts each field only when it is accessed first
+/// and keeps the result, so repeated accesses are free. Fields,
+/// that are not encrypted, are available immediately.
+pub struct LazyTransaction<'a, Ce, Se, St>
+where
+    Ce: CryptoEngine,
+    Se: SyncEngine,
+    St: DataStorage
+{
+    /// Budget, that decrypts fields
+    budget: &'a Budget<Ce, Se, St>,
+
+    /// Transaction as it is stored
+    encrypted: EncryptedTransaction,
+
+    /// Decrypted description
+    description: OnceCell<String>,
+
+    /// Decrypted amount
+    amount: OnceCell<isize>,
+
+    /// Decrypted note
+    note: OnceCell<String>,
+
+    /// Decrypted custom fields
+    custom_fields: OnceCell<CustomFields>,
+}
+
+
+impl<'a, Ce, Se, St> LazyTransaction<'a, Ce, Se, St>
+where
+    Ce: CryptoEngine,
+    Se: SyncEngine,
+    St: DataStorage
+{
+    /// Wraps a stored transaction.
+    ///
+    /// * `budget` - budget, that decrypts fields
+    /// * `encrypted` - transaction as it is stored
+    pub(crate) fn new(budget: &'a Budget<Ce, Se, St>, encrypted: EncryptedTransaction) -> Self {
+        LazyTransaction {
+            budget,
+            encrypted,
+            description: OnceCell::new(),
+            amount: OnceCell::new(),
+            note: OnceCell::new(),
+            custom_fields: OnceCell::new()
+        }
+    }
+
+    /// Identifier of the transaction.
+    pub fn id(&self) -> PrimaryId {
+        self.encrypted.id
+    }
+
+    /// Date of the transaction.
+    pub fn timestamp(&self) -> Timestamp {
+        self.encrypted.timestamp
+    }
+
+    /// Account of the transaction.
+    pub fn account_id(&self) -> Id {
+        self.encrypted.account_id
+    }
+
+    /// Category of the transaction.
+    pub fn category_id(&self) -> Id {
+        self.encrypted.category_id
+    }
+
+    /// Kind of the transaction.
+    pub fn kind(&self) -> TransactionKind {
+        self.encrypted.kind
+    }
+
+    /// Person, that made the transaction.
+    pub fn spender_id(&self) -> Option<Id> {
+        self.encrypted.spender_id
+    }
+
+    /// Meta information of the transaction.
+    pub fn meta_info(&self) -> MetaInfo {
+        self.encrypted.meta_info
+    }
+
+    /// Description of the transaction, decrypted on first access.
+    pub fn description(&self) -> Result<&str> {
+        get_or_decrypt(&self.description, || self.budget.decrypt_description(&self.encrypted.description))
+            .map(String::as_str)
+    }
+
+    /// Amount of the transaction, decrypted on first access.
+    pub fn amount(&self) -> Result<isize> {
+        get_or_decrypt(&self.amount, || self.budget.decrypt_isize(&self.encrypted.amount))
+            .copied()
+    }
+
+    /// Note of the transaction, decrypted on first access.
+    pub fn note(&self) -> Result<&str> {
+        get_or_decrypt(&self.note, || self.budget.decrypt_note(&self.encrypted.note))
+            .map(String::as_str)
+    }
+
+    /// Custom fields of the transaction, decrypted on first access.
+    pub fn custom_fields(&self) -> Result<&CustomFields> {
+        get_or_decrypt(&self.custom_fields, || self.budget.decrypt_custom_fields(&self.encrypted.custom_fields))
+    }
+
+    /// Decrypts all fields (that are not decrypted yet) and returns
+    /// a regular transaction.
+    pub fn decrypt(&self) -> Result<Transaction> {
+        Ok(Transaction {
+            id: self.id(),
+            timestamp: self.timestamp(),
+            description: self.description()?.to_owned(),
+            account_id: self.account_id(),
+            category_id: self.category_id(),
+            amount: self.amount()?,
+            kind: self.kind(),
+            note: self.note()?.to_owned(),
+            custom_fields: self.custom_fields()?.clone(),
+            spender_id: self.spender_id(),
+            meta_info: self.meta_info()
+        })
+    }
+}
+
+
+fn get_or_decrypt<T, F>(cell: &OnceCell<T>, decrypt: F) -> Result<&T>
+where
+    F: FnOnce() -> Result<T>
+{
+    //
+    // Errors are not cached, so a failed field
+    // is decrypted again on next access
+    //
+
+    if let Some(value) = cell.get() {
+        return Ok(value);
+    }
+
+    let value = decrypt()?;
+    Ok(cell.get_or_init(|| value))
+}
@@ -0,0 +1,144 @@
+use chrono::Months;
+
+use crate::datetime::Timestamp;
+use crate::storage::Loan;
+
+
+/// Number of basis points in one.
+const BASIS_POINTS: f64 = 10_000.0;
+
+/// Number of months in a year.
+const MONTHS_IN_YEAR: f64 = 12.0;
+
+/// Upper bound of simulated payments for payoff projection.
+/// Prevents infinite loops for payments barely exceeding interest.
+const MAX_PROJECTED_PAYMENTS: u32 = 100 * 12;
+
+
+/// One payment of a loan amortization schedule.
+#[derive(Clone, Copy)]
+pub struct AmortizationEntry {
+    /// Sequential number of the payment starting from 1
+    pub number: u32,
+
+    /// Date of the payment
+    pub timestamp: Timestamp,
+
+    /// Total amount of the payment
+    pub payment: isize,
+
+    /// Principal part of the payment
+    pub principal: isize,
+
+    /// Interest part of the payment
+    pub interest: isize,
+
+    /// Outstanding principal after the payment
+    pub remaining: isize,
+}
+
+
+impl Loan {
+    /// Fixed monthly payment of an annuity loan.
+    pub fn monthly_payment(&self) -> isize {
+        if 0 == self.term {
+            return self.principal;
+        }
+
+        let rate = self.monthly_rate();
+        let principal = self.principal as f64;
+        let term = self.term as f64;
+
+        let payment = if 0.0 == rate {
+            principal / term
+        }
+        else {
+            principal * rate / (1.0 - (1.0 + rate).powf(-term))
+        };
+
+        payment.round() as isize
+    }
+
+    /// Interest accrued for one month on a given outstanding principal.
+    /// 
+    /// * `remaining` - outstanding principal
+    pub fn monthly_interest(&self, remaining: isize) -> isize {
+        (remaining as f64 * self.monthly_rate())
+            .round() as isize
+    }
+
+    /// Generates full amortization schedule of the loan.
+    /// 
+    /// Payments are made monthly starting one month after the loan issue.
+    /// The last payment is adjusted to pay off the loan exactly.
+    pub fn amortization_schedule(&self) -> Vec<AmortizationEntry> {
+        let payment = self.monthly_payment();
+        let mut remaining = self.principal;
+        let mut schedule = Vec::new();
+
+        for number in 1..=self.term {
+            let interest = self.monthly_interest(remaining);
+            let principal = if number == self.term {
+                remaining
+            }
+            else {
+                (payment - interest).min(remaining)
+            };
+
+            remaining -= principal;
+
+            schedule.push(AmortizationEntry {
+                number,
+                timestamp: Self::add_months(self.start_timestamp, number),
+                payment: principal + interest,
+                principal,
+                interest,
+                remaining
+            });
+
+            if 0 == remaining {
+                break;
+            }
+        }
+
+        schedule
+    }
+
+    /// Projects the date of full payoff.
+    /// 
+    /// Returns [`None`] if payments don't cover accrued interest, 
+    /// i.e. the loan can never be paid off.
+    /// 
+    /// * `remaining` - current outstanding principal
+    /// * `payment` - regular monthly payment
+    /// * `from` - date to start projection from
+    pub fn payoff_timestamp(&self, remaining: isize, payment: isize, from: Timestamp) -> Option<Timestamp> {
+        let mut remaining = remaining;
+        let mut months = 0;
+
+        while 0 < remaining {
+            let principal = payment - self.monthly_interest(remaining);
+            if principal <= 0 || MAX_PROJECTED_PAYMENTS <= months {
+                return None;
+            }
+
+            remaining -= principal;
+            months += 1;
+        }
+
+        Some(Self::add_months(from, months))
+    }
+}
+
+
+impl Loan {
+    fn monthly_rate(&self) -> f64 {
+        self.interest_rate as f64 / BASIS_POINTS / MONTHS_IN_YEAR
+    }
+
+    fn add_months(timestamp: Timestamp, months: u32) -> Timestamp {
+        timestamp
+            .checked_add_months(Months::new(months))
+            .unwrap_or(timestamp)
+    }
+}
@@ -1,9 +1,103 @@
 mod budget;
 mod config;
 mod changelog;
+mod search;
+mod loan;
+mod settings;
+mod invariants;
+mod anonymize;
+mod import;
+mod categorize;
+mod lazy;
+mod export;
+mod bank;
+mod receipt;
+mod geo;
+mod reserved;
+pub(crate) mod external;
+
+#[cfg(feature = "native")]
+mod facade;
+
+#[cfg(feature = "async")]
+mod async_budget;
 
 pub use self::budget::Budget;
-pub use self::config::{Config, InstanceId};
+pub use self::config::{Config, ConfigKey, ConfigLoader, ConfigOverrides, InstanceId};
+pub use self::settings::{Settings, SettingsLayer, CurrencySettings};
+pub use self::search::SearchResults;
+pub use self::lazy::LazyTransaction;
+pub use self::export::ExportFormat;
+pub use self::bank::{BankProvider, BankAccount, BankTransaction};
+pub use self::loan::AmortizationEntry;
+pub use self::invariants::{Invariant, Violation};
+pub use self::external::external_id;
+pub use self::receipt::{receipt_text, set_receipt_text};
+pub use self::geo::{GeoLocation, BoundingBox, location, set_location};
+pub(crate) use self::import::normalize_payee;
+pub use self::import::{ImportPreview, ProposedTransaction, ImportStatus, CategoryPrediction, Confidence, PredictionBasis};
+
+#[cfg(feature = "native")]
+pub use self::facade::DefaultBudget;
+
+#[cfg(feature = "async")]
+pub use self::async_budget::{AsyncBudget, BudgetFuture};
 
 /// Error shown in case of malformed timestamp file.
 const MALFORMED_TIMESTAMP: &str = "Timestamp file in repository is malformed";
+
+/// Error shown in case of malformed changelog in repository.
+const MALFORMED_CHANGELOG: &str = "Changelog in repository is malformed";
+
+/// Error shown in case of changelog, that cannot be read after receiving from another instance.
+const CORRUPTED_REMOTE_CHANGELOG: &str = "Changelog received from another instance is corrupted";
+
+/// Error shown in case of changelog, that requires a newer version of the library.
+const UNSUPPORTED_CHANGELOG_VERSION: &str = "Changelog version is not supported";
+
+/// Error shown in case of invalid configuration.
+const INVALID_CONFIG: &str = "Configuration is invalid";
+
+/// Error shown in case of configuration created by a newer version of the library.
+const UNSUPPORTED_CONFIG_VERSION: &str = "Configuration version is not supported";
+
+/// Error shown in case of configuration created for another cryptographic engine.
+const ENGINE_MISMATCH: &str = "Configuration belongs to another cryptographic engine";
+
+/// Error shown in case of data protected by another cryptographic engine or algorithm.
+const CRYPTO_MISMATCH: &str = "Data is protected by another cryptographic engine";
+
+/// Error shown in case of data encrypted with another key.
+const KEY_MISMATCH: &str = "Budget belongs to another key";
+
+/// Error shown in case of emergency export without a recovery key.
+const MISSING_RECOVERY_KEY: &str = "Recovery key is not created";
+
+/// Error shown in case of malformed emergency export.
+const MALFORMED_EMERGENCY_EXPORT: &str = "Emergency export is malformed";
+
+/// Error shown in case of emergency export, that is protected by another recovery key.
+const WRONG_RECOVERY_KEY: &str = "Emergency export is protected by another recovery key";
+
+/// Error shown in case of a violated budget invariant.
+const INVARIANT_VIOLATED: &str = "Budget invariant is violated";
+
+/// Error shown in case of an item with invalid values.
+const INVALID_ITEM: &str = "Item is invalid";
+
+/// Error shown in case of items, that cannot be merged.
+const INCOMPATIBLE_MERGE: &str = "Items cannot be merged";
+
+/// Error shown in case of a category group, that does not exist.
+const UNKNOWN_CATEGORY_GROUP: &str = "Category group does not exist";
+
+/// Error shown in case of synchronization attempt while a snapshot is active.
+const SNAPSHOT_IS_ACTIVE: &str = "Budget cannot be synchronized while a snapshot is active";
+
+/// Error shown in case of a job submitted to a stopped worker.
+#[cfg(feature = "async")]
+const WORKER_STOPPED: &str = "Budget worker has stopped";
+
+/// Error shown in case of a panic in a worker's job.
+#[cfg(feature = "async")]
+const WORKER_PANICKED: &str = "Budget operation panicked";
@@ -0,0 +1,25 @@
+use crate::storage::CustomFields;
+use super::reserved::{self, RECEIPT_TEXT_KEY};
+
+
+/// Returns text of a receipt of a transaction (if any).
+///
+/// Text is recognized by a frontend (e.g. with OCR) and stored in
+/// custom fields of a transaction, hence it is encrypted, synchronized
+/// and found by [`crate::core::Budget::search`] along with other
+/// textual custom fields. To attach a receipt to a stored transaction
+/// use [`crate::core::Budget::set_receipt`].
+///
+/// * `custom_fields` - custom fields of a transaction
+pub fn receipt_text(custom_fields: &CustomFields) -> Option<&str> {
+    reserved::text(custom_fields, RECEIPT_TEXT_KEY)
+}
+
+
+/// Stores or removes text of a receipt of a transaction.
+///
+/// * `custom_fields` - custom fields of a transaction
+/// * `text` - text of a receipt (`None` removes it)
+pub fn set_receipt_text(custom_fields: &mut CustomFields, text: Option<&str>) {
+    reserved::set_text(custom_fields, RECEIPT_TEXT_KEY, text)
+}
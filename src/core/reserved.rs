@@ -0,0 +1,82 @@
+use crate::storage::{CustomFields, CustomValue};
+
+
+/// Key of a custom field, that stores text of a receipt.
+pub(crate) const RECEIPT_TEXT_KEY: &str = "receipt_text";
+
+/// Key of a custom field, that stores latitude in microdegrees.
+pub(crate) const LATITUDE_KEY: &str = "location_latitude";
+
+/// Key of a custom field, that stores longitude in microdegrees.
+pub(crate) const LONGITUDE_KEY: &str = "location_longitude";
+
+/// Key of a custom field, that stores name of a place.
+pub(crate) const PLACE_KEY: &str = "location_place";
+
+/// Prefix of custom fields' keys, that store external identifiers.
+pub(crate) const EXTERNAL_ID_PREFIX: &str = "external_id:";
+
+/// Keys of custom fields, that are filled by the library rather 
+/// than by a user.
+const RESERVED_KEYS: &[&str] = &[RECEIPT_TEXT_KEY, LATITUDE_KEY, LONGITUDE_KEY, PLACE_KEY];
+
+
+/// Checks if a key of a custom field is reserved by the library.
+///
+/// Values of reserved fields are user data (e.g. text of a receipt),
+/// but their keys are not, hence keys are not looked for by search.
+///
+/// * `key` - key of a custom field
+pub(crate) fn is_reserved(key: &str) -> bool {
+    RESERVED_KEYS.contains(&key) || key.starts_with(EXTERNAL_ID_PREFIX)
+}
+
+
+/// Returns a textual value of a reserved field (if any).
+///
+/// * `custom_fields` - custom fields of an item
+/// * `key` - reserved key
+pub(crate) fn text<'a>(custom_fields: &'a CustomFields, key: &str) -> Option<&'a str> {
+    match custom_fields.get(key) {
+        Some(CustomValue::Text(text)) => Some(text),
+        _ => None
+    }
+}
+
+
+/// Stores or removes a textual value of a reserved field.
+///
+/// * `custom_fields` - custom fields of an item
+/// * `key` - reserved key
+/// * `text` - value to store (`None` removes it)
+pub(crate) fn set_text(custom_fields: &mut CustomFields, key: &str, text: Option<&str>) {
+    match text {
+        Some(text) => custom_fields.insert(key.to_owned(), CustomValue::Text(text.to_owned())),
+        None => custom_fields.remove(key)
+    };
+}
+
+
+/// Returns an integer value of a reserved field (if any).
+///
+/// * `custom_fields` - custom fields of an item
+/// * `key` - reserved key
+pub(crate) fn integer(custom_fields: &CustomFields, key: &str) -> Option<isize> {
+    match custom_fields.get(key) {
+        Some(CustomValue::Integer(value)) => Some(*value),
+        _ => None
+    }
+}
+
+
+/// Stores or removes an integer value of a reserved field.
+///
+/// * `custom_fields` - custom fields of an item
+/// * `key` - reserved key
+/// * `value` - value to store (`None` removes it)
+pub(crate) fn set_integer(custom_fields: &mut CustomFields, key: &str, value: Option<isize>) {
+    match value {
+        Some(value) => custom_fields.insert(key.to_owned(), CustomValue::Integer(value)),
+        None => custom_fields.remove(key)
+    };
+}
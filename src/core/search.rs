@@ -0,0 +1,90 @@
+use crate::storage::{Transaction, Account, Category, Plan, CustomFields, CustomValue};
+use super::reserved;
+
+
+/// Items matching a search query.
+pub struct SearchResults {
+    /// Matching accounts.
+    pub accounts: Vec<Account>,
+
+    /// Matching categories.
+    pub categories: Vec<Category>,
+
+    /// Matching transactions.
+    pub transactions: Vec<Transaction>,
+
+    /// Matching plans.
+    pub plans: Vec<Plan>,
+}
+
+
+/// Trait for items, that can be searched by text.
+/// 
+/// Search is case-insensitive and looks for a query in
+/// human-readable texts of an item: its name or description,
+/// note and textual custom fields (both keys and values). Keys
+/// reserved by the library (see [`reserved::is_reserved`]) are
+/// skipped, otherwise e.g. "receipt" would match every transaction
+/// with a receipt.
+pub(crate) trait Searchable {
+    /// Checks if the item matches a query.
+    /// 
+    /// * `query` - text to look for, MUST be in lowercase
+    fn matches(&self, query: &str) -> bool;
+}
+
+
+impl Searchable for Transaction {
+    fn matches(&self, query: &str) -> bool {
+        text_matches(&self.description, query) ||
+        text_matches(&self.note, query) ||
+        custom_fields_match(&self.custom_fields, query)
+    }
+}
+
+
+impl Searchable for Account {
+    fn matches(&self, query: &str) -> bool {
+        text_matches(&self.name, query) ||
+        text_matches(&self.note, query) ||
+        custom_fields_match(&self.custom_fields, query)
+    }
+}
+
+
+impl Searchable for Category {
+    fn matches(&self, query: &str) -> bool {
+        text_matches(&self.name, query) ||
+        text_matches(&self.note, query) ||
+        custom_fields_match(&self.custom_fields, query)
+    }
+}
+
+
+impl Searchable for Plan {
+    fn matches(&self, query: &str) -> bool {
+        text_matches(&self.name, query) ||
+        text_matches(&self.note, query) ||
+        custom_fields_match(&self.custom_fields, query)
+    }
+}
+
+
+fn text_matches(text: &str, query: &str) -> bool {
+    text.to_lowercase()
+        .contains(query)
+}
+
+
+fn custom_fields_match(custom_fields: &CustomFields, query: &str) -> bool {
+    custom_fields
+        .iter()
+        .any(|(key, value)| {
+            let value_matches = match value {
+                CustomValue::Text(text) => text_matches(text, query),
+                _ => false
+            };
+
+            (!reserved::is_reserved(key) && text_matches(key, query)) || value_matches
+        })
+}
@@ -0,0 +1,151 @@
+use serde::{Serialize, Deserialize};
+
+use crate::error::{Result, Error, ErrorKind};
+use crate::storage::{MetaInfo, Id};
+use super::INVALID_CONFIG;
+
+
+/// Maximal supported number of digits after decimal point.
+const MAX_CURRENCY_PRECISION: u8 = 8;
+
+
+/// Default currency settings.
+#[derive(Serialize, Deserialize, Clone, PartialEq, Eq, Debug)]
+pub struct CurrencySettings {
+    /// ISO 4217 currency code
+    pub code: String,
+
+    /// Number of digits after decimal point, i.e. amounts are
+    /// stored in units of `10^-precision` of the currency
+    pub precision: u8,
+}
+
+
+impl Default for CurrencySettings {
+    fn default() -> Self {
+        CurrencySettings {
+            code: "USD".to_owned(),
+            precision: 2
+        }
+    }
+}
+
+
+/// Layer of settings.
+///
+/// There are two layers: shared one, that is synchronized between
+/// instances, and local one, that is stored in instance's configuration.
+/// Values from local layer override shared ones. Absent values are
+/// inherited from a lower layer or defaults.
+#[derive(Serialize, Deserialize, Clone, PartialEq, Eq, Default, Debug)]
+pub struct SettingsLayer {
+    /// Default currency settings
+    pub currency: Option<CurrencySettings>,
+
+    /// Inactivity timeout (in seconds), after which an app should lock itself
+    pub auto_lock_timeout: Option<u64>,
+
+    /// Number of days, that removed items are kept for before being purged
+    pub trash_retention_days: Option<u32>,
+
+    /// Expense categories, that count as essential spending
+    pub essential_categories: Option<Vec<Id>>,
+}
+
+
+impl SettingsLayer {
+    /// Checks if all present values are valid.
+    pub fn validate(&self) -> Result<()> {
+        if let Some(currency) = &self.currency {
+            if currency.code.len() != 3 || !currency.code.chars().all(|c| c.is_ascii_uppercase()) {
+                return Err(Error::from_message_with_extra(INVALID_CONFIG,
+                    format!("invalid currency code: {}", currency.code)).with_kind(ErrorKind::Config));
+            }
+
+            if MAX_CURRENCY_PRECISION < currency.precision {
+                return Err(Error::from_message_with_extra(INVALID_CONFIG,
+                    format!("invalid currency precision: {}", currency.precision)).with_kind(ErrorKind::Config));
+            }
+        }
+
+        if self.auto_lock_timeout == Some(0) {
+            return Err(Error::from_message_with_extra(INVALID_CONFIG, "zero auto-lock timeout").with_kind(ErrorKind::Config));
+        }
+
+        Ok(())
+    }
+
+    /// Returns a layer with values of `self` overridden by `overrides`.
+    ///
+    /// * `overrides` - layer with values of higher priority
+    pub fn overridden_by(&self, overrides: &SettingsLayer) -> SettingsLayer {
+        SettingsLayer {
+            currency: overrides.currency
+                .clone()
+                .or_else(|| self.currency.clone()),
+
+            auto_lock_timeout: overrides.auto_lock_timeout
+                .or(self.auto_lock_timeout),
+
+            trash_retention_days: overrides.trash_retention_days
+                .or(self.trash_retention_days),
+
+            essential_categories: overrides.essential_categories
+                .clone()
+                .or_else(|| self.essential_categories.clone())
+        }
+    }
+}
+
+
+/// Shared settings layer along with its meta information.
+#[derive(Serialize, Deserialize, Clone)]
+pub(crate) struct SharedSettings {
+    /// Settings values
+    pub layer: SettingsLayer,
+
+    /// Meta info (only origin and change timestamp are used)
+    pub meta_info: MetaInfo,
+}
+
+
+/// Effective settings of an instance.
+#[derive(Clone, PartialEq, Eq, Debug)]
+pub struct Settings {
+    /// User-friendly instance name
+    pub instance_name: Option<String>,
+
+    /// Default currency settings
+    pub currency: CurrencySettings,
+
+    /// Inactivity timeout, after which an app should lock itself
+    pub auto_lock_timeout: Option<std::time::Duration>,
+
+    /// Number of days, that removed items are kept for (`None` keeps them forever)
+    pub trash_retention_days: Option<u32>,
+
+    /// Expense categories, that count as essential spending (`None` counts
+    /// all expense categories)
+    pub essential_categories: Option<Vec<Id>>,
+}
+
+
+impl Settings {
+    /// Computes effective settings from layers.
+    ///
+    /// * `instance_name` - instance name
+    /// * `shared` - shared settings layer
+    /// * `local` - local settings layer
+    pub(crate) fn from_layers(instance_name: Option<&str>, shared: &SettingsLayer, local: &SettingsLayer) -> Self {
+        let effective = shared.overridden_by(local);
+
+        Settings {
+            instance_name: instance_name.map(str::to_owned),
+            currency: effective.currency.unwrap_or_default(),
+            auto_lock_timeout: effective.auto_lock_timeout
+                .map(std::time::Duration::from_secs),
+            trash_retention_days: effective.trash_retention_days,
+            essential_categories: effective.essential_categories
+        }
+    }
+}
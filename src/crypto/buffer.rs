@@ -1,7 +1,12 @@
+use crate::error::{Result, Error, ErrorKind};
+use super::INVALID_UTF8;
+
+
 /// Struct for wrapping a sensitive data.
 /// 
 /// Implements [`core::ops::Drop`] trait, that erases internal 
-/// data at destruction time.
+/// data at destruction time. Growing and shrinking functions
+/// erase memory, that is released, too.
 pub struct CryptoBuffer {
     /// Raw internal data
     data: Vec<u8>
@@ -21,6 +26,14 @@ impl CryptoBuffer {
         CryptoBuffer { data: vec![0; size] }
     }
 
+    /// Creates an empty buffer, that can hold at least `capacity`
+    /// bytes without reallocation.
+    /// 
+    /// * `capacity` - initial capacity of buffer
+    pub fn with_capacity(capacity: usize) -> Self {
+        CryptoBuffer { data: Vec::with_capacity(capacity) }
+    }
+
     /// Appends one cryptographic buffer this one and returns a concatenated buffer.
     /// 
     /// Takes ownership on both of buffers (current and appended).
@@ -28,10 +41,66 @@ impl CryptoBuffer {
     /// * `buffer` - something convertible to [`CryptoBuffer`]
     pub fn append<B: Into<CryptoBuffer>>(mut self, buffer: B) -> CryptoBuffer {
         let buffer: CryptoBuffer = buffer.into();
-        self.data.extend_from_slice(buffer.as_bytes());
+        self.extend_from_slice(buffer.as_bytes());
         self
     }
 
+    /// Appends bytes to the end of buffer.
+    /// 
+    /// If buffer has to grow, data is moved to a new allocation
+    /// and the old one is erased before it is freed.
+    /// 
+    /// * `bytes` - bytes to append
+    pub fn extend_from_slice(&mut self, bytes: &[u8]) {
+        let required = self.data.len() + bytes.len();
+        if required > self.data.capacity() {
+            //
+            // Vec would reallocate on its own and leave a copy
+            // of data in freed memory, so it is done manually
+            //
+
+            let mut grown = Vec::with_capacity(required.max(2 * self.data.capacity()));
+            grown.extend_from_slice(&self.data);
+
+            let mut old = std::mem::replace(&mut self.data, grown);
+            Self::destroy_data(&mut old);
+        }
+
+        self.data.extend_from_slice(bytes);
+    }
+
+    /// Shortens buffer to `length` bytes, removed bytes are erased.
+    /// Has no effect if buffer is not longer than `length`.
+    /// 
+    /// * `length` - new length of buffer
+    pub fn truncate(&mut self, length: usize) {
+        if length < self.data.len() {
+            Self::destroy_data(&mut self.data[length..]);
+            self.data.truncate(length);
+        }
+    }
+
+    /// Converts buffer into a string without copying. If buffer is
+    /// not a valid UTF-8 text, it is erased and an error is returned.
+    /// 
+    /// Note, that string is not erased at destruction time.
+    pub fn into_string_checked(mut self) -> Result<String> {
+        let data = std::mem::take(&mut self.data);
+
+        String::from_utf8(data)
+            .map_err(|error| {
+                let mut data = error.into_bytes();
+                Self::destroy_data(&mut data);
+
+                Error::from_message(INVALID_UTF8).with_kind(ErrorKind::Corruption)
+            })
+    }
+
+    /// Returns length of buffer in bytes.
+    pub fn len(&self) -> usize {
+        self.data.len()
+    }
+
     /// Returns read-only raw bytes of the stored data.
     pub fn as_bytes(&self) -> &[u8] {
         &self.data
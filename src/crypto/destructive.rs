@@ -0,0 +1,101 @@
+use crate::error::Result;
+use super::buffer::CryptoBuffer;
+
+
+/// Size of chunks, that data is read by from sources without
+/// direct access to their memory.
+#[cfg(feature = "native")]
+const READ_CHUNK_SIZE: usize = 4096;
+
+
+/// Conversion, that moves sensitive data out of a source.
+///
+/// Unlike [`From`] it takes source by mutable reference and leaves
+/// it empty: data is moved without copying if possible, otherwise
+/// the source is erased. Hence no unprotected copies of sensitive
+/// data remain after the conversion.
+pub trait DestructiveFrom<T: ?Sized>: Sized {
+    /// Moves data out of a source.
+    ///
+    /// * `source` - source of sensitive data, it is empty afterwards
+    fn destructive_from(source: &mut T) -> Result<Self>;
+}
+
+
+/// Consuming counterpart of [`DestructiveFrom`], that takes source
+/// by value. It is implemented automatically.
+pub trait DestructiveInto<T>: Sized {
+    /// Moves data out of `self` into a new value.
+    fn destructive_into(self) -> Result<T>;
+}
+
+
+impl<T, U> DestructiveInto<U> for T
+where
+    U: DestructiveFrom<T>
+{
+    fn destructive_into(mut self) -> Result<U> {
+        U::destructive_from(&mut self)
+    }
+}
+
+
+impl DestructiveFrom<String> for CryptoBuffer {
+    fn destructive_from(source: &mut String) -> Result<Self> {
+        //
+        // Allocation is moved, so string is left
+        // without any memory at all
+        //
+
+        Ok(CryptoBuffer::from(std::mem::take(source).into_bytes()))
+    }
+}
+
+
+impl DestructiveFrom<Vec<u8>> for CryptoBuffer {
+    fn destructive_from(source: &mut Vec<u8>) -> Result<Self> {
+        Ok(CryptoBuffer::from(std::mem::take(source)))
+    }
+}
+
+
+impl DestructiveFrom<Box<[u8]>> for CryptoBuffer {
+    fn destructive_from(source: &mut Box<[u8]>) -> Result<Self> {
+        Ok(CryptoBuffer::from(std::mem::take(source).into_vec()))
+    }
+}
+
+
+/// Implemented for data, that owns its content (e.g. created with
+/// [`gpgme::Data::new`]): it is replaced with an empty one.
+#[cfg(feature = "native")]
+impl DestructiveFrom<gpgme::Data<'static>> for CryptoBuffer {
+    fn destructive_from(source: &mut gpgme::Data<'static>) -> Result<Self> {
+        use std::io::{Read, Seek};
+
+        let mut data = std::mem::replace(source, gpgme::Data::new()?);
+        data.rewind()?;
+
+        let mut result = CryptoBuffer::new();
+        let mut chunk = CryptoBuffer::new_with_size(READ_CHUNK_SIZE);
+
+        loop {
+            let read = data.read(chunk.as_mut_bytes())?;
+            if 0 == read {
+                break;
+            }
+
+            result.extend_from_slice(&chunk.as_bytes()[..read]);
+        }
+
+        //
+        // Buffer of memory-based data is erased as well
+        //
+
+        if let Some(bytes) = data.try_into_bytes() {
+            drop(CryptoBuffer::from(bytes));
+        }
+
+        Ok(result)
+    }
+}
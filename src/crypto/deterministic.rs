@@ -0,0 +1,75 @@
+use aes_gcm_siv::aead::Aead;
+use aes_gcm_siv::KeyInit;
+use sha2::{Sha256, Digest};
+
+use crate::error::{Result, Error, ErrorKind};
+use super::buffer::CryptoBuffer;
+use super::INVALID_SYMMETRIC_KEY;
+
+
+/// Cipher in nonce misuse-resistant mode: repeating a nonce leaks
+/// only equality of plaintexts.
+type Cipher = aes_gcm_siv::Aes256GcmSiv;
+
+
+/// Domain, that key of deterministic cipher is derived within, so it
+/// never equals to the key of randomized encryption.
+const KEY_DOMAIN: &[u8] = b"bdgt deterministic encryption";
+
+/// Fixed nonce, that makes encryption deterministic.
+const NONCE: [u8; 12] = [0; 12];
+
+
+/// Deterministic symmetric cipher.
+///
+/// Equal plaintexts are encrypted into equal ciphertexts, so they
+/// can be compared without decryption, e.g. by storage. The price
+/// is that anyone, who sees ciphertexts, learns which of them are
+/// equal, and hence it SHOULD be used for designated fields only.
+/// Ciphertexts are authenticated as well as randomized ones.
+pub(crate) struct DeterministicCipher {
+    /// Internal cipher implementation.
+    cipher: Cipher,
+}
+
+
+impl DeterministicCipher {
+    /// Create a new cipher instance from a key of randomized
+    /// encryption. A separate key is derived from it.
+    ///
+    /// * `key` - key of randomized encryption
+    pub fn new(key: &[u8]) -> Result<Self> {
+        if key.is_empty() {
+            return Err(Error::from_message(INVALID_SYMMETRIC_KEY).with_kind(ErrorKind::CryptoFailure));
+        }
+
+        let derived_key = Sha256::new()
+            .chain_update(KEY_DOMAIN)
+            .chain_update(key)
+            .finalize();
+
+        Ok(DeterministicCipher {
+            cipher: Cipher::new(&derived_key)
+        })
+    }
+
+    /// Encrypt a BLOB.
+    ///
+    /// * `plaintext` - data to encrypt.
+    pub fn encrypt(&self, plaintext: &[u8]) -> Result<CryptoBuffer> {
+        let ciphertext = self.cipher
+            .encrypt(&NONCE.into(), plaintext)?;
+
+        Ok(CryptoBuffer::from(ciphertext))
+    }
+
+    /// Decrypt a BLOB.
+    ///
+    /// * `ciphertext` - data to decrypt.
+    pub fn decrypt(&self, ciphertext: &[u8]) -> Result<CryptoBuffer> {
+        let plaintext = self.cipher
+            .decrypt(&NONCE.into(), ciphertext)?;
+
+        Ok(CryptoBuffer::from(plaintext))
+    }
+}
@@ -22,6 +22,11 @@ pub trait CryptoEngine {
     /// Returns a version of cryptographic engine.
     fn version(&self) -> &'static str;
 
+    /// Returns a name of algorithm, that protects data: how data key
+    /// is obtained and which cipher encrypts data. Data protected by
+    /// one algorithm cannot be read with another one.
+    fn algorithm(&self) -> &'static str;
+
     /// Returns length of a key for symmetric algorithm,
     /// that is used by the engine.
     fn symmetric_key_length(&self) -> usize;
@@ -33,6 +38,13 @@ pub trait CryptoEngine {
     /// * `id` - identifier of a key to look for
     fn lookup_key(&self, id: &Self::KeyId) -> Result<Self::Key>;
 
+    /// Returns a fingerprint of a key. Unlike key identifier, that
+    /// may point to different keys on different machines, fingerprint
+    /// identifies key material itself. It is not secret.
+    /// 
+    /// * `key` - handle to a key.
+    fn key_fingerprint(&self, key: &Self::Key) -> Result<String>;
+
     /// Encrypts a BLOB using a provided key.
     /// 
     /// This method is generic. It is not specified, which encryption 
@@ -53,6 +65,52 @@ pub trait CryptoEngine {
     /// * `ciphertext` - data to decrypt
     fn decrypt(&self, key: &Self::Key, ciphertext: &[u8]) -> Result<CryptoBuffer>;
 
+    /// Encrypts several BLOBs using a provided key. Result is the same
+    /// as of [`CryptoEngine::encrypt`] called for each BLOB, but engines
+    /// may amortize per-call costs (e.g. unwrapping of a data key).
+    /// 
+    /// * `key` - handle to a key.
+    /// * `plaintexts` - data to encrypt
+    fn encrypt_batch(&self, key: &Self::Key, plaintexts: &[&[u8]]) -> Result<Vec<CryptoBuffer>> {
+        plaintexts
+            .iter()
+            .map(|plaintext| self.encrypt(key, plaintext))
+            .collect()
+    }
+
+    /// Decrypts several BLOBs using a provided key. Result is the same
+    /// as of [`CryptoEngine::decrypt`] called for each BLOB, but engines
+    /// may amortize per-call costs (e.g. unwrapping of a data key).
+    /// 
+    /// Fails, if any of BLOBs cannot be decrypted.
+    /// 
+    /// * `key` - handle to a key.
+    /// * `ciphertexts` - data to decrypt
+    fn decrypt_batch(&self, key: &Self::Key, ciphertexts: &[&[u8]]) -> Result<Vec<CryptoBuffer>> {
+        ciphertexts
+            .iter()
+            .map(|ciphertext| self.decrypt(key, ciphertext))
+            .collect()
+    }
+
+    /// Encrypts a BLOB deterministically using a provided key: equal
+    /// plaintexts give equal ciphertexts, so they can be matched
+    /// without decryption.
+    /// 
+    /// Trade-off: anyone, who sees ciphertexts, learns which of them
+    /// are equal (e.g. how often the same payee occurs). Use it for
+    /// designated fields only, that must be queried by exact match.
+    /// 
+    /// * `key` - handle to a key.
+    /// * `plaintext` - data to encrypt
+    fn encrypt_deterministic(&self, key: &Self::Key, plaintext: &[u8]) -> Result<CryptoBuffer>;
+
+    /// Decrypts a BLOB encrypted with [`CryptoEngine::encrypt_deterministic`].
+    /// 
+    /// * `key` - handle to a key.
+    /// * `ciphertext` - data to decrypt
+    fn decrypt_deterministic(&self, key: &Self::Key, ciphertext: &[u8]) -> Result<CryptoBuffer>;
+
     /// Encrypts a BLOB symmetrically using a provided key.
     /// 
     /// This method mey be unsupported by some engines.
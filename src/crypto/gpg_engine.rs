@@ -1,22 +1,35 @@
 use std::ffi::CString;
 use std::cell::{RefCell, RefMut};
 
-use crate::error::{Error, Result};
-use crate::location::Location;
+use crate::error::{Error, Result, ErrorKind, Context};
+use crate::location::{Location, restrict_file};
+use super::kdf::Kdf;
 use super::prng::Prng;
 use super::engine::CryptoEngine;
 use super::buffer::CryptoBuffer;
+use super::destructive::DestructiveInto;
 use super::symmetric::SymmetricCipher;
+use super::deterministic::DeterministicCipher;
 use super::key::{Key, KeyId, KeyHandle, KeyIdentifier};
 use super::{MISSING_SECRET_KEY, KEY_IS_NOT_SUITABLE, ENCRYPTION_ERROR, DECRYPTION_ERROR, INVALID_ENGINE_STATE};
+use super::{WRONG_CACHE_PASSPHRASE, MISSING_KEY_CACHE};
 
 
 /// Homan-friendly name of GPG engine.
 const ENGINE_NAME: &str = "GnuPG";
 
+/// Algorithm, that protects data: key is encrypted with OpenPGP.
+const ALGORITHM: &str = "openpgp/aes-256-gcm";
+
 /// Name of file with symmetric encryption key.
 const SYMMETRIC_KEY_FILE: &str = "symm";
 
+/// Name of file with cached symmetric key protected by a passphrase.
+const KEY_CACHE_FILE: &str = "symm.cache";
+
+/// Size of salt of key cache in bytes.
+const KEY_CACHE_SALT_SIZE: usize = 16;
+
 
 /// Engine-specific key identifier type.
 type NativeId = CString;
@@ -68,7 +81,8 @@ impl EncryptedKey {
         //
 
         Ok(EncryptedKey { 
-            encrypted_buffer: CryptoBuffer::from(std::fs::read(path)?), 
+            encrypted_buffer: CryptoBuffer::from(std::fs::read(path)
+                .with_context(|| format!("reading symmetric key file {}", path.display()))?), 
             decrypted_buffer: CryptoBuffer::default(), 
         })
     }
@@ -112,6 +126,9 @@ pub struct GpgCryptoEngine {
 
     /// Encrypted symmetric key provider.
     symmetric_key: Option<RefCell<EncryptedKey>>,
+
+    /// Path to key cache file.
+    key_cache_file: Option<std::path::PathBuf>,
 }
 
 
@@ -139,6 +156,102 @@ impl GpgCryptoEngine {
         Self::new()
             .and_then(|engine| engine.open_symmetric_key(loc))
     }
+
+    /// Enables local key cache: symmetric key is stored in a file,
+    /// protected by a passphrase with Argon2 instead of GPG key.
+    /// Cached key is unlocked with [`GpgCryptoEngine::unlock_key_cache`],
+    /// hence GPG agent is not asked for each opening of a budget.
+    /// 
+    /// Passphrase is as strong as the cache, so it should not be
+    /// weaker than passphrase of GPG key.
+    /// 
+    /// * `key` - key used to decrypt symmetric key
+    /// * `passphrase` - passphrase, that protects the cache
+    #[cfg_attr(feature = "tracing", tracing::instrument(level = "debug", skip_all))]
+    pub fn enable_key_cache(&self, key: &<Self as CryptoEngine>::Key, passphrase: &[u8]) -> Result<()> {
+        let path = self.key_cache_path()?;
+        let symmetric_key = self.decrypt_symmetric_key(key)?;
+
+        let mut salt = [0u8; KEY_CACHE_SALT_SIZE];
+        Prng::new()
+            .generate(&mut salt)?;
+
+        let cache_key = Kdf::derive_key_argon2(passphrase, &salt, SymmetricCipher::key_size())?;
+        let encrypted_key = SymmetricCipher::new(cache_key.as_bytes())?
+            .encrypt(symmetric_key.decrypted_buffer.as_bytes())?;
+
+        //
+        // File is restricted before the key is written into it
+        //
+
+        std::fs::write(path, [])?;
+        restrict_file(path)?;
+
+        std::fs::write(path, CryptoBuffer::from(&salt[..]).append(encrypted_key).as_bytes())
+            .with_context(|| format!("writing key cache file {}", path.display()))
+    }
+
+    /// Disables local key cache and removes the cached key.
+    /// Key unlocked already remains in memory until the engine
+    /// is dropped or [`GpgCryptoEngine::clear_key_cache`] is called.
+    pub fn disable_key_cache(&self) -> Result<()> {
+        let path = self.key_cache_path()?;
+        if path.exists() {
+            std::fs::remove_file(path)?;
+        }
+
+        Ok(())
+    }
+
+    /// Checks if local key cache is enabled.
+    pub fn is_key_cache_enabled(&self) -> bool {
+        self.key_cache_file
+            .as_ref()
+            .is_some_and(|path| path.exists())
+    }
+
+    /// Unlocks symmetric key from local key cache, so that GPG agent
+    /// is not asked for it.
+    /// 
+    /// * `passphrase` - passphrase, that protects the cache
+    #[cfg_attr(feature = "tracing", tracing::instrument(level = "debug", skip_all))]
+    pub fn unlock_key_cache(&self, passphrase: &[u8]) -> Result<()> {
+        let path = self.key_cache_path()?;
+        if !path.exists() {
+            return Err(Error::from_message(MISSING_KEY_CACHE).with_kind(ErrorKind::NotFound));
+        }
+
+        let cache = CryptoBuffer::from(std::fs::read(path)
+            .with_context(|| format!("reading key cache file {}", path.display()))?);
+
+        if cache.as_bytes().len() <= KEY_CACHE_SALT_SIZE {
+            return Err(Error::from_message(WRONG_CACHE_PASSPHRASE).with_kind(ErrorKind::CryptoFailure));
+        }
+
+        let (salt, encrypted_key) = cache.as_bytes()
+            .split_at(KEY_CACHE_SALT_SIZE);
+
+        let cache_key = Kdf::derive_key_argon2(passphrase, salt, SymmetricCipher::key_size())?;
+        let decrypted_key = SymmetricCipher::new(cache_key.as_bytes())?
+            .decrypt(encrypted_key)
+            .map_err(|error| Error::from_message(WRONG_CACHE_PASSPHRASE)
+                .with_kind(ErrorKind::CryptoFailure)
+                .with_source(error))?;
+
+        self.symmetric_key()?
+            .decrypted_buffer = decrypted_key;
+
+        Ok(())
+    }
+
+    /// Forgets symmetric key unlocked in memory, the next operation
+    /// unlocks it with GPG agent again. Key cache file is kept.
+    pub fn clear_key_cache(&self) -> Result<()> {
+        self.symmetric_key()?
+            .decrypted_buffer = CryptoBuffer::default();
+
+        Ok(())
+    }
 }
 
 
@@ -155,10 +268,15 @@ impl CryptoEngine for GpgCryptoEngine {
             .version()
     }
 
+    fn algorithm(&self) -> &'static str {
+        ALGORITHM
+    }
+
     fn symmetric_key_length(&self) -> usize {
         SymmetricCipher::key_size()
     }
 
+    #[cfg_attr(feature = "tracing", tracing::instrument(level = "debug", skip_all))]
     fn lookup_key(&self, id: &Self::KeyId) -> Result<Self::Key> {
         let internal_key = self.ctx
             .borrow_mut()
@@ -166,6 +284,13 @@ impl CryptoEngine for GpgCryptoEngine {
 
         self.verify_key(Key::new(internal_key, id))
     }
+
+    fn key_fingerprint(&self, key: &Self::Key) -> Result<String> {
+        key.native_handle()
+            .fingerprint()
+            .map(str::to_owned)
+            .map_err(|_| Error::from_message_with_extra(KEY_IS_NOT_SUITABLE, key.id().to_string()).with_kind(ErrorKind::CryptoFailure))
+    }
     
     fn encrypt(&self, key: &Self::Key, plaintext: &[u8]) -> Result<CryptoBuffer> {
         let symmetric_key = self.decrypt_symmetric_key(key)?;
@@ -177,11 +302,52 @@ impl CryptoEngine for GpgCryptoEngine {
         self.decrypt_symmetric(symmetric_key.decrypted_buffer.as_bytes(), ciphertext)
     }
 
+    #[cfg_attr(feature = "tracing", tracing::instrument(level = "debug", skip_all, fields(count = plaintexts.len())))]
+    fn encrypt_batch(&self, key: &Self::Key, plaintexts: &[&[u8]]) -> Result<Vec<CryptoBuffer>> {
+        //
+        // Symmetric key is borrowed (and decrypted if necessary)
+        // only once for the whole batch
+        //
+
+        let symmetric_key = self.decrypt_symmetric_key(key)?;
+        let cipher = SymmetricCipher::new(symmetric_key.decrypted_buffer.as_bytes())?;
+
+        plaintexts
+            .iter()
+            .map(|plaintext| cipher.encrypt(plaintext))
+            .collect()
+    }
+
+    #[cfg_attr(feature = "tracing", tracing::instrument(level = "debug", skip_all, fields(count = ciphertexts.len())))]
+    fn decrypt_batch(&self, key: &Self::Key, ciphertexts: &[&[u8]]) -> Result<Vec<CryptoBuffer>> {
+        let symmetric_key = self.decrypt_symmetric_key(key)?;
+        let cipher = SymmetricCipher::new(symmetric_key.decrypted_buffer.as_bytes())?;
+
+        ciphertexts
+            .iter()
+            .map(|ciphertext| cipher.decrypt(ciphertext))
+            .collect()
+    }
+
+    fn encrypt_deterministic(&self, key: &Self::Key, plaintext: &[u8]) -> Result<CryptoBuffer> {
+        let symmetric_key = self.decrypt_symmetric_key(key)?;
+        DeterministicCipher::new(symmetric_key.decrypted_buffer.as_bytes())?
+            .encrypt(plaintext)
+    }
+
+    fn decrypt_deterministic(&self, key: &Self::Key, ciphertext: &[u8]) -> Result<CryptoBuffer> {
+        let symmetric_key = self.decrypt_symmetric_key(key)?;
+        DeterministicCipher::new(symmetric_key.decrypted_buffer.as_bytes())?
+            .decrypt(ciphertext)
+    }
+
+    #[cfg_attr(feature = "tracing", tracing::instrument(level = "trace", skip_all, fields(length = plaintext.len())))]
     fn encrypt_symmetric(&self, key: &[u8], plaintext: &[u8]) -> Result<CryptoBuffer> {
         let cipher = SymmetricCipher::new(key)?;
         cipher.encrypt(plaintext)
     }
 
+    #[cfg_attr(feature = "tracing", tracing::instrument(level = "trace", skip_all, fields(length = ciphertext.len())))]
     fn decrypt_symmetric(&self, key: &[u8], ciphertext: &[u8]) -> Result<CryptoBuffer> {
         let cipher = SymmetricCipher::new(key)?;
         cipher.decrypt(ciphertext)
@@ -197,6 +363,7 @@ impl GpgCryptoEngine {
             engine: gpgme::init(),
             ctx: RefCell::new(ctx),
             symmetric_key: None,
+            key_cache_file: None,
         })
     }
 
@@ -218,6 +385,7 @@ impl GpgCryptoEngine {
 
         let encrypted_key = self.encrypt_asymmetric(&key, symmetric_key.as_bytes())?;
         std::fs::write(Self::symmetric_key_file(loc), encrypted_key.as_bytes())?;
+        restrict_file(Self::symmetric_key_file(loc))?;
 
         //
         // Set passphrase file in engine just by common opening procedure
@@ -229,14 +397,20 @@ impl GpgCryptoEngine {
     fn open_symmetric_key<L: Location>(mut self, loc: &L) -> Result<Self> {
         let encrypted_symmetric_key = EncryptedKey::new(&Self::symmetric_key_file(loc))?;
         self.symmetric_key = Some(RefCell::new(encrypted_symmetric_key));
+        self.key_cache_file = Some(Self::key_cache_file(loc));
 
         Ok(self)
     }
 
     fn symmetric_key_file<L: Location>(loc: &L) -> std::path::PathBuf {
-        loc.root()
+        loc.data_dir()
             .join(SYMMETRIC_KEY_FILE)
     }
+
+    fn key_cache_file<L: Location>(loc: &L) -> std::path::PathBuf {
+        loc.data_dir()
+            .join(KEY_CACHE_FILE)
+    }
 }
 
 
@@ -260,7 +434,7 @@ impl GpgCryptoEngine {
         let secret_keys = borrowed_ctx.find_secret_keys(key_ids)?;
 
         if 0 == secret_keys.count() {
-            return Err(Error::from_message_with_extra(MISSING_SECRET_KEY, id.to_string()));
+            return Err(Error::from_message_with_extra(MISSING_SECRET_KEY, id.to_string()).with_kind(ErrorKind::CryptoFailure));
         }
 
         //
@@ -269,18 +443,11 @@ impl GpgCryptoEngine {
 
         key.is_suitable()
             .then_some(key)
-            .ok_or(Error::from_message_with_extra(KEY_IS_NOT_SUITABLE, id.to_string()))
+            .ok_or(Error::from_message_with_extra(KEY_IS_NOT_SUITABLE, id.to_string()).with_kind(ErrorKind::CryptoFailure))
     }
 
     fn decrypt_symmetric_key(&self, key: &<Self as CryptoEngine>::Key) -> Result<RefMut<'_, EncryptedKey>> {
-        if self.symmetric_key.is_none() {
-            return Err(Error::from_message(INVALID_ENGINE_STATE));
-        }
-
-        let mut borrowed_symmetric_key = self.symmetric_key
-            .as_ref()
-            .unwrap()
-            .borrow_mut();
+        let mut borrowed_symmetric_key = self.symmetric_key()?;
 
         borrowed_symmetric_key
             .decrypt(key, self)?;
@@ -288,6 +455,20 @@ impl GpgCryptoEngine {
         Ok(borrowed_symmetric_key)
     }
 
+    fn symmetric_key(&self) -> Result<RefMut<'_, EncryptedKey>> {
+        self.symmetric_key
+            .as_ref()
+            .map(RefCell::borrow_mut)
+            .ok_or(Error::from_message(INVALID_ENGINE_STATE).with_kind(ErrorKind::CryptoFailure))
+    }
+
+    fn key_cache_path(&self) -> Result<&std::path::Path> {
+        self.key_cache_file
+            .as_deref()
+            .ok_or(Error::from_message(INVALID_ENGINE_STATE).with_kind(ErrorKind::CryptoFailure))
+    }
+
+    #[cfg_attr(feature = "tracing", tracing::instrument(level = "debug", skip_all, fields(length = plaintext.len())))]
     fn encrypt_asymmetric(&self, key: &<Self as CryptoEngine>::Key, plaintext: &[u8]) -> Result<CryptoBuffer> {
         let keys = [key.native_handle()];
         let mut ciphertext = Vec::new();
@@ -300,15 +481,22 @@ impl GpgCryptoEngine {
             .map(|_| CryptoBuffer::from(ciphertext))
     }
 
+    #[cfg_attr(feature = "tracing", tracing::instrument(level = "debug", skip_all, fields(length = ciphertext.len())))]
     fn decrypt_asymmetric(&self, _key: &<Self as CryptoEngine>::Key, ciphertext: &[u8]) -> Result<CryptoBuffer> {
-        let mut plaintext = Vec::new();
+        //
+        // Plaintext is written into GPG's own buffer, since vector
+        // would leave its copies behind while growing
+        //
+
+        let mut plaintext = gpgme::Data::new()?;
 
         self.ctx
             .borrow_mut()
             .decrypt(ciphertext, &mut plaintext)
             .map_err(Error::from)
-            .and_then(Self::check_decryption_result)
-            .map(|_| CryptoBuffer::from(plaintext))
+            .and_then(Self::check_decryption_result)?;
+
+        plaintext.destructive_into()
     }
 
     fn check_encryption_result(result: gpgme::EncryptionResult) -> Result<()> {
@@ -318,7 +506,7 @@ impl GpgCryptoEngine {
 
         (0 == invalid_count)
             .then_some(())
-            .ok_or(Error::from_message(ENCRYPTION_ERROR))
+            .ok_or(Error::from_message(ENCRYPTION_ERROR).with_kind(ErrorKind::CryptoFailure))
     }
 
     fn check_decryption_result(result: gpgme::DecryptionResult) -> Result<()> {
@@ -326,6 +514,6 @@ impl GpgCryptoEngine {
 
         correct
             .then_some(())
-            .ok_or(Error::from_message(DECRYPTION_ERROR))
+            .ok_or(Error::from_message(DECRYPTION_ERROR).with_kind(ErrorKind::CryptoFailure))
     }
 }
@@ -19,4 +19,18 @@ impl Kdf {
 
         Ok(result)
     }
+
+    /// Derives a symmetric key from password using Argon2id algorithm.
+    /// 
+    /// * `pass` - password to derive key from
+    /// * `salt` - salt to use for key derivation
+    /// * `key_size` - size of key to derive in bytes
+    #[cfg(feature = "native")]
+    pub(crate) fn derive_key_argon2(pass: &[u8], salt: &[u8], key_size: usize) -> Result<CryptoBuffer> {
+        let mut result = CryptoBuffer::new_with_size(key_size);
+        argon2::Argon2::default()
+            .hash_password_into(pass, salt, result.as_mut_bytes())?;
+
+        Ok(result)
+    }
 }
\ No newline at end of file
@@ -84,6 +84,7 @@ where
 /// Structure, that wraps a key handle.
 pub struct Key<NativeHandle, NativeId> {
     /// Internal backend-specific key handle
+    #[cfg_attr(not(feature = "native"), allow(dead_code))]
     key: NativeHandle,
 
     /// Copy of key identifier
@@ -108,6 +109,7 @@ where
     }
 
     /// Returns a native key handle.
+    #[cfg_attr(not(feature = "native"), allow(dead_code))]
     pub(crate) fn native_handle(&self) -> &NativeHandle {
         &self.key
     }
@@ -116,6 +118,7 @@ where
     /// 
     /// Key MUST NOT be expired, revoked nor disabled, and MUST be able 
     /// to perform encryption.
+    #[cfg_attr(not(feature = "native"), allow(dead_code))]
     pub(crate) fn is_suitable(&self) -> bool {
         let is_good = self.key.is_good();
         let can_encrypt = self.key.can_encrypt();
@@ -2,33 +2,81 @@ mod key;
 mod kdf;
 mod prng;
 mod buffer;
+mod destructive;
 mod engine;
 mod symmetric;
+mod deterministic;
+mod recovery;
+mod passphrase_engine;
+
+#[cfg(feature = "native")]
 mod gpg_engine;
 
+#[cfg(feature = "keyring")]
+mod os_keyring;
+
 pub use self::engine::CryptoEngine;
 pub use self::buffer::CryptoBuffer;
-pub use self::gpg_engine::GpgCryptoEngine;
+pub use self::destructive::{DestructiveFrom, DestructiveInto};
+pub use self::passphrase_engine::PassphraseCryptoEngine;
 pub use self::key::{Key, KeyId};
+pub use self::recovery::RecoveryKey;
+
+#[cfg(feature = "native")]
+pub use self::gpg_engine::GpgCryptoEngine;
+
+#[cfg(feature = "keyring")]
+pub use self::os_keyring::Keyring;
 
 pub(crate) use self::kdf::Kdf;
+
+pub(crate) use self::prng::Prng;
+
+#[cfg(feature = "lan")]
+pub(crate) use self::symmetric::SymmetricCipher;
 pub(crate) use self::key::KeyIdentifier;
 
 
 /// Error message for missing secret key.
+#[cfg(feature = "native")]
 const MISSING_SECRET_KEY: &str = "Secret key is missing";
 
 /// Error message for invalid key.
+#[cfg(feature = "native")]
 const KEY_IS_NOT_SUITABLE: &str = "Key is not suitable for bdgt";
 
 /// Error message for invalid engine state.
+#[cfg(feature = "native")]
 const INVALID_ENGINE_STATE: &str = "Engine is in invalid state";
 
 /// Error message for encryption error.
+#[cfg(feature = "native")]
 const ENCRYPTION_ERROR: &str = "An error occurred during encryption";
 
 /// Error message for decryption error.
+#[cfg(feature = "native")]
 const DECRYPTION_ERROR: &str = "An error occurred during decryption";
 
+/// Error message for a wrong passphrase of key cache.
+#[cfg(feature = "native")]
+const WRONG_CACHE_PASSPHRASE: &str = "Passphrase of key cache is wrong";
+
+/// Error message for a missing key cache.
+#[cfg(feature = "native")]
+const MISSING_KEY_CACHE: &str = "Key cache is not enabled";
+
+/// Error message for decrypted data, that is not a text.
+const INVALID_UTF8: &str = "Decrypted data is not a valid UTF-8 text";
+
+/// Error message for a secret without name.
+#[cfg(feature = "keyring")]
+const EMPTY_SECRET_NAME: &str = "Secret name is empty";
+
 /// Malformed symmetric key.
 const INVALID_SYMMETRIC_KEY: &str = "Invalid symmetric key provided";
+
+/// Error message for a key, that is not known to an engine.
+const UNKNOWN_KEY: &str = "Key is unknown to the engine";
+
+/// Error message for a malformed or mistyped recovery key.
+const MALFORMED_RECOVERY_KEY: &str = "Recovery key is malformed";
@@ -0,0 +1,82 @@
+use crate::error::{Result, Error, ErrorKind};
+use crate::trace::trace_debug;
+use super::buffer::CryptoBuffer;
+use super::EMPTY_SECRET_NAME;
+
+
+/// Service name, that secrets are stored under in OS keyring.
+const KEYRING_SERVICE: &str = "bdgt";
+
+
+/// Storage of secrets in OS keyring: Secret Service on Linux,
+/// Keychain on macOS and Credential Manager on Windows.
+///
+/// It is an alternative to encrypted values in configuration (see
+/// [`crate::core::Config::secret`]): secrets are protected by OS and
+/// can be obtained without cryptographic engine, e.g. a passphrase
+/// for [`super::PassphraseCryptoEngine`] or a token of remote.
+/// Secrets of different instances are separated by scope.
+pub struct Keyring {
+    /// Scope of secrets, e.g. instance identifier
+    scope: String,
+}
+
+
+impl Keyring {
+    /// Creates a keyring with secrets of a scope.
+    ///
+    /// * `scope` - scope of secrets, e.g. instance identifier
+    pub fn new(scope: &str) -> Self {
+        Keyring {
+            scope: scope.to_owned()
+        }
+    }
+
+    /// Obtain a secret or [`None`] if it is absent.
+    ///
+    /// * `name` - name of the secret
+    #[cfg_attr(feature = "tracing", tracing::instrument(level = "debug", skip(self)))]
+    pub fn secret(&self, name: &str) -> Result<Option<CryptoBuffer>> {
+        match self.entry(name)?.get_secret() {
+            Ok(secret) => Ok(Some(CryptoBuffer::from(secret))),
+            Err(keyring::Error::NoEntry) => Ok(None),
+            Err(error) => Err(error.into())
+        }
+    }
+
+    /// Store a secret, an existing one is replaced.
+    ///
+    /// * `name` - name of the secret
+    /// * `value` - plaintext value
+    #[cfg_attr(feature = "tracing", tracing::instrument(level = "debug", skip(self, value)))]
+    pub fn set_secret(&self, name: &str, value: &[u8]) -> Result<()> {
+        self.entry(name)?
+            .set_secret(value)
+            .map_err(Error::from)
+    }
+
+    /// Remove a secret. Returns `true` if the secret was present.
+    ///
+    /// * `name` - name of the secret
+    pub fn remove_secret(&self, name: &str) -> Result<bool> {
+        match self.entry(name)?.delete_credential() {
+            Ok(()) => Ok(true),
+            Err(keyring::Error::NoEntry) => Ok(false),
+            Err(error) => Err(error.into())
+        }
+    }
+}
+
+
+impl Keyring {
+    fn entry(&self, name: &str) -> Result<keyring::Entry> {
+        if name.trim().is_empty() {
+            return Err(Error::from_message(EMPTY_SECRET_NAME).with_kind(ErrorKind::InvalidInput));
+        }
+
+        trace_debug!(scope = %self.scope, "accessing keyring");
+
+        keyring::Entry::new(KEYRING_SERVICE, &format!("{}/{}", self.scope, name))
+            .map_err(Error::from)
+    }
+}
@@ -0,0 +1,199 @@
+use sha2::{Sha256, Digest};
+
+use crate::error::{Error, Result, ErrorKind};
+use super::kdf::Kdf;
+use super::prng::Prng;
+use super::engine::CryptoEngine;
+use super::buffer::CryptoBuffer;
+use super::symmetric::SymmetricCipher;
+use super::deterministic::DeterministicCipher;
+use super::key::{Key, KeyId, KeyHandle, KeyIdentifier};
+use super::UNKNOWN_KEY;
+
+
+/// Human-friendly name of passphrase engine.
+const ENGINE_NAME: &str = "Passphrase";
+
+/// Algorithm, that protects data: key is derived from passphrase.
+const ALGORITHM: &str = "scrypt/aes-256-gcm";
+
+/// Recommended salt length in bytes.
+const SALT_LENGTH: usize = 16;
+
+/// Domain of key fingerprints: they never match other digests of the key.
+const FINGERPRINT_DOMAIN: &[u8] = b"bdgt key fingerprint";
+
+/// Length of key fingerprint in bytes.
+const FINGERPRINT_LENGTH: usize = 20;
+
+
+/// Engine-specific key identifier type.
+type NativeId = String;
+
+impl KeyIdentifier for NativeId {
+    fn from_str(id: &str) -> Self {
+        id.to_owned()
+    }
+
+    fn as_string(&self) -> String {
+        self.clone()
+    }
+}
+
+
+/// Engine-specific key handle type.
+///
+/// Key material itself is held by the engine, the handle just
+/// confirms, that the key is known.
+pub struct PassphraseKey;
+
+impl KeyHandle for PassphraseKey {
+    fn is_good(&self) -> bool {
+        true
+    }
+
+    fn can_encrypt(&self) -> bool {
+        true
+    }
+}
+
+
+/// Pure Rust cryptographic engine based on a passphrase.
+///
+/// Symmetric key is derived from a passphrase and a salt using
+/// Scrypt, then it is used to perform actual cryptographic
+/// transformations. The engine has no system dependencies and
+/// does not touch the filesystem, therefore it is suitable for
+/// environments without GnuPG, e.g. browsers.
+///
+/// The salt is not secret, but it MUST be preserved by a caller
+/// along with the data: the same key cannot be derived without it.
+pub struct PassphraseCryptoEngine {
+    /// Identifier of the derived key.
+    key_id: <Self as CryptoEngine>::KeyId,
+
+    /// Derived symmetric key.
+    symmetric_key: CryptoBuffer,
+}
+
+
+impl PassphraseCryptoEngine {
+    /// Creates an engine and derives its key.
+    ///
+    /// * `key_id` - name of the key, that is used to look it up
+    /// * `passphrase` - passphrase to derive key from
+    /// * `salt` - salt to use for key derivation
+    pub fn new(key_id: &<Self as CryptoEngine>::KeyId, passphrase: &[u8], salt: &[u8]) -> Result<Self> {
+        let symmetric_key = Kdf::derive_key(passphrase, salt,
+            SymmetricCipher::key_size())?;
+
+        Ok(PassphraseCryptoEngine {
+            key_id: key_id.clone(),
+            symmetric_key
+        })
+    }
+
+    /// Generates a random salt for key derivation.
+    pub fn generate_salt() -> Result<Vec<u8>> {
+        let mut salt = vec![0u8; SALT_LENGTH];
+        Prng::new()
+            .generate(&mut salt)?;
+
+        Ok(salt)
+    }
+}
+
+
+impl CryptoEngine for PassphraseCryptoEngine {
+    type Key = Key<PassphraseKey, NativeId>;
+    type KeyId = KeyId<NativeId>;
+
+    fn engine(&self) -> &'static str {
+        ENGINE_NAME
+    }
+
+    fn version(&self) -> &'static str {
+        env!("CARGO_PKG_VERSION")
+    }
+
+    fn algorithm(&self) -> &'static str {
+        ALGORITHM
+    }
+
+    fn symmetric_key_length(&self) -> usize {
+        SymmetricCipher::key_size()
+    }
+
+    fn lookup_key(&self, id: &Self::KeyId) -> Result<Self::Key> {
+        if id.native_id() != self.key_id.native_id() {
+            return Err(Error::from_message_with_extra(UNKNOWN_KEY, id.to_string()).with_kind(ErrorKind::CryptoFailure));
+        }
+
+        Ok(Key::new(PassphraseKey, id))
+    }
+
+    fn key_fingerprint(&self, _key: &Self::Key) -> Result<String> {
+        //
+        // Key is derived, so it is fingerprinted by a digest:
+        // it tells keys apart, but does not reveal them
+        //
+
+        let digest = Sha256::new()
+            .chain_update(FINGERPRINT_DOMAIN)
+            .chain_update(self.symmetric_key.as_bytes())
+            .finalize();
+
+        Ok(digest[..FINGERPRINT_LENGTH]
+            .iter()
+            .map(|byte| format!("{:02X}", byte))
+            .collect())
+    }
+
+    fn encrypt(&self, _key: &Self::Key, plaintext: &[u8]) -> Result<CryptoBuffer> {
+        self.encrypt_symmetric(self.symmetric_key.as_bytes(), plaintext)
+    }
+
+    fn decrypt(&self, _key: &Self::Key, ciphertext: &[u8]) -> Result<CryptoBuffer> {
+        self.decrypt_symmetric(self.symmetric_key.as_bytes(), ciphertext)
+    }
+
+    fn encrypt_batch(&self, _key: &Self::Key, plaintexts: &[&[u8]]) -> Result<Vec<CryptoBuffer>> {
+        let cipher = SymmetricCipher::new(self.symmetric_key.as_bytes())?;
+
+        plaintexts
+            .iter()
+            .map(|plaintext| cipher.encrypt(plaintext))
+            .collect()
+    }
+
+    fn decrypt_batch(&self, _key: &Self::Key, ciphertexts: &[&[u8]]) -> Result<Vec<CryptoBuffer>> {
+        let cipher = SymmetricCipher::new(self.symmetric_key.as_bytes())?;
+
+        ciphertexts
+            .iter()
+            .map(|ciphertext| cipher.decrypt(ciphertext))
+            .collect()
+    }
+
+    fn encrypt_deterministic(&self, _key: &Self::Key, plaintext: &[u8]) -> Result<CryptoBuffer> {
+        DeterministicCipher::new(self.symmetric_key.as_bytes())?
+            .encrypt(plaintext)
+    }
+
+    fn decrypt_deterministic(&self, _key: &Self::Key, ciphertext: &[u8]) -> Result<CryptoBuffer> {
+        DeterministicCipher::new(self.symmetric_key.as_bytes())?
+            .decrypt(ciphertext)
+    }
+
+    #[cfg_attr(feature = "tracing", tracing::instrument(level = "trace", skip_all, fields(length = plaintext.len())))]
+    fn encrypt_symmetric(&self, key: &[u8], plaintext: &[u8]) -> Result<CryptoBuffer> {
+        let cipher = SymmetricCipher::new(key)?;
+        cipher.encrypt(plaintext)
+    }
+
+    #[cfg_attr(feature = "tracing", tracing::instrument(level = "trace", skip_all, fields(length = ciphertext.len())))]
+    fn decrypt_symmetric(&self, key: &[u8], ciphertext: &[u8]) -> Result<CryptoBuffer> {
+        let cipher = SymmetricCipher::new(key)?;
+        cipher.decrypt(ciphertext)
+    }
+}
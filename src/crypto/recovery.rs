@@ -0,0 +1,210 @@
+use sha2::{Sha256, Digest};
+
+use crate::error::{Error, Result, ErrorKind};
+use super::kdf::Kdf;
+use super::prng::Prng;
+use super::buffer::CryptoBuffer;
+use super::symmetric::SymmetricCipher;
+use super::MALFORMED_RECOVERY_KEY;
+
+
+/// Size of recovery secret in bytes.
+const SECRET_SIZE: usize = 32;
+
+/// Size of checksum, that detects typos in a phrase, in bytes.
+const CHECKSUM_SIZE: usize = 3;
+
+/// Alphabet of phrases (Crockford's Base32): it has no letters,
+/// that are easily confused with each other or with digits.
+const ALPHABET: &[u8; 32] = b"0123456789ABCDEFGHJKMNPQRSTVWXYZ";
+
+/// Number of characters in a group of a phrase.
+const GROUP_SIZE: usize = 4;
+
+/// Separator of groups of a phrase.
+const GROUP_SEPARATOR: char = '-';
+
+/// Prefix of QR code payloads.
+const QR_PREFIX: &str = "BDGT-RECOVERY:";
+
+
+/// Recovery key, that decrypts emergency exports of a budget
+/// (see [`crate::core::Budget::export_emergency`]).
+///
+/// The key is a random secret, that does not depend on any key
+/// storage: it can be printed on paper (see [`RecoveryKey::to_phrase`])
+/// or encoded into a QR code (see [`RecoveryKey::to_qr_payload`]),
+/// and then used to restore data, if access to the original key
+/// is lost. Hence it MUST be kept as safe as the key itself.
+pub struct RecoveryKey {
+    /// Random secret
+    secret: CryptoBuffer,
+}
+
+
+impl RecoveryKey {
+    /// Generates a new random recovery key.
+    pub fn generate() -> Result<Self> {
+        let mut secret = CryptoBuffer::new_with_size(SECRET_SIZE);
+        Prng::new()
+            .generate(secret.as_mut_bytes())?;
+
+        Ok(RecoveryKey { secret })
+    }
+
+    /// Parses a printable phrase (see [`RecoveryKey::to_phrase`]).
+    ///
+    /// Parsing is forgiving: letters may be of any case, separators
+    /// and whitespaces are ignored, `O`, `I` and `L` are read as
+    /// digits. Typos are detected with a checksum.
+    ///
+    /// * `phrase` - printable phrase
+    pub fn from_phrase(phrase: &str) -> Result<Self> {
+        let symbols = phrase
+            .chars()
+            .filter(|symbol| !symbol.is_whitespace() && *symbol != GROUP_SEPARATOR)
+            .map(decode_symbol)
+            .collect::<Option<Vec<_>>>()
+            .map(CryptoBuffer::from)
+            .ok_or(Error::from_message(MALFORMED_RECOVERY_KEY).with_kind(ErrorKind::InvalidInput))?;
+
+        let mut data = CryptoBuffer::from(unpack(symbols.as_bytes())
+            .ok_or(Error::from_message(MALFORMED_RECOVERY_KEY).with_kind(ErrorKind::InvalidInput))?);
+
+        let (secret, checksum) = data
+            .as_bytes()
+            .split_at(SECRET_SIZE);
+
+        if checksum != &Self::checksum(secret)[..] {
+            return Err(Error::from_message(MALFORMED_RECOVERY_KEY).with_kind(ErrorKind::InvalidInput));
+        }
+
+        data.truncate(SECRET_SIZE);
+        Ok(RecoveryKey { secret: data })
+    }
+
+    /// Parses a QR code payload (see [`RecoveryKey::to_qr_payload`]).
+    ///
+    /// * `payload` - payload of a QR code
+    pub fn from_qr_payload(payload: &str) -> Result<Self> {
+        let phrase = payload
+            .strip_prefix(QR_PREFIX)
+            .ok_or(Error::from_message(MALFORMED_RECOVERY_KEY).with_kind(ErrorKind::InvalidInput))?;
+
+        Self::from_phrase(phrase)
+    }
+
+    /// Returns a printable phrase, e.g. `8F3K-...-Q0ZT`: groups of
+    /// digits and capital letters followed by a checksum.
+    pub fn to_phrase(&self) -> String {
+        let symbols = self.symbols();
+
+        symbols
+            .chunks(GROUP_SIZE)
+            .map(|group| group.iter().collect::<String>())
+            .collect::<Vec<_>>()
+            .join(&GROUP_SEPARATOR.to_string())
+    }
+
+    /// Returns a payload for a QR code. It consists of characters,
+    /// that can be encoded in compact alphanumeric mode.
+    pub fn to_qr_payload(&self) -> String {
+        let symbols: String = self.symbols()
+            .into_iter()
+            .collect();
+
+        format!("{}{}", QR_PREFIX, symbols)
+    }
+
+    /// Derives a key for symmetric encryption.
+    ///
+    /// * `salt` - salt to use for key derivation
+    pub(crate) fn derive_key(&self, salt: &[u8]) -> Result<CryptoBuffer> {
+        Kdf::derive_key(self.secret.as_bytes(), salt, SymmetricCipher::key_size())
+    }
+}
+
+
+impl RecoveryKey {
+    fn symbols(&self) -> Vec<char> {
+        let mut data = CryptoBuffer::from(self.secret.as_bytes());
+        data.extend_from_slice(&Self::checksum(self.secret.as_bytes()));
+
+        pack(data.as_bytes())
+    }
+
+    fn checksum(secret: &[u8]) -> [u8; CHECKSUM_SIZE] {
+        let digest = Sha256::digest(secret);
+
+        let mut checksum = [0u8; CHECKSUM_SIZE];
+        checksum.copy_from_slice(&digest[..CHECKSUM_SIZE]);
+
+        checksum
+    }
+}
+
+
+fn pack(data: &[u8]) -> Vec<char> {
+    //
+    // Bits are taken by five from the most significant one,
+    // the last symbol is padded with zeros if necessary
+    //
+
+    let mut symbols = Vec::new();
+    let mut accumulator = 0u32;
+    let mut bits = 0;
+
+    for byte in data {
+        accumulator = (accumulator << 8) | *byte as u32;
+        bits += 8;
+
+        while bits >= 5 {
+            bits -= 5;
+            symbols.push(ALPHABET[((accumulator >> bits) & 0x1F) as usize] as char);
+        }
+    }
+
+    if bits > 0 {
+        symbols.push(ALPHABET[((accumulator << (5 - bits)) & 0x1F) as usize] as char);
+    }
+
+    symbols
+}
+
+
+fn unpack(symbols: &[u8]) -> Option<Vec<u8>> {
+    let expected_size = SECRET_SIZE + CHECKSUM_SIZE;
+    if symbols.len() != (expected_size * 8).div_ceil(5) {
+        return None;
+    }
+
+    let mut data = Vec::with_capacity(expected_size);
+    let mut accumulator = 0u32;
+    let mut bits = 0;
+
+    for symbol in symbols {
+        accumulator = (accumulator << 5) | *symbol as u32;
+        bits += 5;
+
+        if bits >= 8 {
+            bits -= 8;
+            data.push((accumulator >> bits) as u8);
+        }
+    }
+
+    Some(data)
+}
+
+
+fn decode_symbol(symbol: char) -> Option<u8> {
+    let symbol = match symbol.to_ascii_uppercase() {
+        'O' => '0',
+        'I' | 'L' => '1',
+        symbol => symbol
+    };
+
+    ALPHABET
+        .iter()
+        .position(|candidate| *candidate as char == symbol)
+        .map(|position| position as u8)
+}
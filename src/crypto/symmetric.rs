@@ -2,7 +2,7 @@ use typenum::Unsigned;
 use aes_gcm::aead::Aead;
 use aes_gcm::{KeySizeUser, AeadCore, KeyInit};
 
-use crate::error::{Result, Error};
+use crate::error::{Result, Error, ErrorKind};
 use super::prng::Prng;
 use super::buffer::CryptoBuffer;
 use super::INVALID_SYMMETRIC_KEY;
@@ -47,7 +47,7 @@ impl SymmetricCipher {
     /// * `key` - key used to encrypt or decrypt data
     pub fn new(key: &[u8]) -> Result<Self> {
         if key.len() != Self::key_size() {
-            return Err(Error::from_message(INVALID_SYMMETRIC_KEY));
+            return Err(Error::from_message(INVALID_SYMMETRIC_KEY).with_kind(ErrorKind::CryptoFailure));
         }
 
         Ok(SymmetricCipher { 
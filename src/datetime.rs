@@ -1,3 +1,9 @@
+use chrono::{NaiveDate, NaiveTime};
+use serde::{Serialize, Deserialize};
+
+use crate::period::TimeZonePolicy;
+
+
 /// Clock used for all timestamps.
 pub type Clock = chrono::Utc;
 
@@ -16,3 +22,81 @@ pub(crate) static ref FIRST_AFTER_JANUARY_1970: Timestamp = Timestamp::from_time
     .expect("One second after January 1970 is a valid timestamp");
 
 );
+
+
+/// Date of a transaction: calendar date with optional time of day.
+///
+/// Transactions are dated by wall-clock time of a place, where they
+/// were made, hence a purchase made at 23:30 stays on its day after
+/// synchronization with an instance in another time zone. Canonically
+/// it is stored as a timestamp with the same wall-clock value in UTC,
+/// so periods over transaction dates should use UTC boundaries (see
+/// [`crate::period::TimeZonePolicy::Utc`]). Date without time is stored 
+/// as midnight, therefore midnight is read back as absent time.
+#[derive(Clone, Copy, PartialEq, Eq, PartialOrd, Ord, Hash, Debug, Serialize, Deserialize)]
+pub struct TxnDate {
+    /// Calendar date
+    date: NaiveDate,
+
+    /// Time of day (if known)
+    time: Option<NaiveTime>,
+}
+
+
+impl TxnDate {
+    /// Creates a transaction date.
+    ///
+    /// * `date` - calendar date
+    /// * `time` - time of day (if known)
+    pub fn new(date: NaiveDate, time: Option<NaiveTime>) -> Self {
+        TxnDate {
+            date,
+            time: time.filter(|time| *time != NaiveTime::MIN)
+        }
+    }
+
+    /// Creates a transaction date from a moment in time as it was
+    /// seen on a wall clock in some time zone.
+    ///
+    /// * `instant` - moment of a transaction
+    /// * `policy` - time zone, that the transaction was made in
+    pub fn from_instant(instant: &Timestamp, policy: TimeZonePolicy) -> Self {
+        let local = policy.local_time_of(instant);
+        Self::new(local.date(), Some(local.time()))
+    }
+
+    /// Creates a transaction date from its canonical representation.
+    ///
+    /// * `timestamp` - canonical timestamp (e.g. [`crate::storage::Transaction::timestamp`])
+    pub fn from_canonical(timestamp: &Timestamp) -> Self {
+        let local = timestamp.naive_utc();
+        Self::new(local.date(), Some(local.time()))
+    }
+
+    /// Returns canonical representation of the date.
+    pub fn to_canonical(&self) -> Timestamp {
+        self.date
+            .and_time(self.time.unwrap_or(NaiveTime::MIN))
+            .and_utc()
+    }
+
+    /// Returns calendar date.
+    pub fn date(&self) -> NaiveDate {
+        self.date
+    }
+
+    /// Returns time of day (if known).
+    pub fn time(&self) -> Option<NaiveTime> {
+        self.time
+    }
+}
+
+
+impl std::fmt::Display for TxnDate {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self.time {
+            Some(time) => write!(f, "{} {}", self.date, time.format("%H:%M")),
+            None => write!(f, "{}", self.date)
+        }
+    }
+}
@@ -1,32 +1,151 @@
+use serde::{Serialize, Deserialize};
+
+
+/// Kinds of errors, that frontends can branch on.
+///
+/// Each kind has a stable numeric code (see [`ErrorKind::code`]) and
+/// a stable string name (see [`ErrorKind::name`]), that can be used by
+/// non-Rust frontends. Codes and names are never reused or changed.
+#[derive(Serialize, Deserialize, Clone, Copy, Debug, PartialEq, Eq, Hash)]
+#[serde(rename_all = "snake_case")]
+#[non_exhaustive]
+#[repr(u32)]
+pub enum ErrorKind {
+    /// Error, that doesn't fit into any other kind
+    Other = 1,
+
+    /// Requested item, file or resource doesn't exist
+    NotFound = 2,
+
+    /// Item, file or resource already exists
+    AlreadyExists = 3,
+
+    /// Invalid argument provided by a caller
+    InvalidInput = 4,
+
+    /// Operation would break consistency of stored data
+    ConsistencyViolation = 5,
+
+    /// Budget or database is locked by someone else
+    Locked = 6,
+
+    /// Error in cryptographic engine
+    CryptoFailure = 7,
+
+    /// Conflicting changes are made in different instances
+    SyncConflict = 8,
+
+    /// Error in synchronization engine, that is not a conflict
+    SyncFailure = 9,
+
+    /// Network is unreachable or remote failed to respond
+    Network = 10,
+
+    /// Error in storage, that is not covered by another kind
+    Storage = 11,
+
+    /// Input/output error
+    Io = 12,
+
+    /// Stored data is malformed
+    Corruption = 13,
+
+    /// Configuration is invalid or unsupported
+    Config = 14,
+
+    /// Operation was cancelled by a caller
+    Cancelled = 15,
+}
+
+
+impl ErrorKind {
+    /// All kinds of errors.
+    pub const ALL: &'static [ErrorKind] = &[
+        ErrorKind::Other,
+        ErrorKind::NotFound,
+        ErrorKind::AlreadyExists,
+        ErrorKind::InvalidInput,
+        ErrorKind::ConsistencyViolation,
+        ErrorKind::Locked,
+        ErrorKind::CryptoFailure,
+        ErrorKind::SyncConflict,
+        ErrorKind::SyncFailure,
+        ErrorKind::Network,
+        ErrorKind::Storage,
+        ErrorKind::Io,
+        ErrorKind::Corruption,
+        ErrorKind::Config,
+        ErrorKind::Cancelled,
+    ];
+
+    /// Returns stable numeric code of a kind.
+    pub fn code(&self) -> u32 {
+        *self as u32
+    }
+
+    /// Returns stable string name of a kind.
+    pub fn name(&self) -> &'static str {
+        match self {
+            ErrorKind::Other => "other",
+            ErrorKind::NotFound => "not_found",
+            ErrorKind::AlreadyExists => "already_exists",
+            ErrorKind::InvalidInput => "invalid_input",
+            ErrorKind::ConsistencyViolation => "consistency_violation",
+            ErrorKind::Locked => "locked",
+            ErrorKind::CryptoFailure => "crypto_failure",
+            ErrorKind::SyncConflict => "sync_conflict",
+            ErrorKind::SyncFailure => "sync_failure",
+            ErrorKind::Network => "network",
+            ErrorKind::Storage => "storage",
+            ErrorKind::Io => "io",
+            ErrorKind::Corruption => "corruption",
+            ErrorKind::Config => "config",
+            ErrorKind::Cancelled => "cancelled",
+        }
+    }
+
+    /// Returns a kind with a given numeric code if any.
+    ///
+    /// * `code` - numeric code of a kind
+    pub fn from_code(code: u32) -> Option<Self> {
+        Self::ALL
+            .iter()
+            .copied()
+            .find(|kind| kind.code() == code)
+    }
+}
+
+
 /// Structure, that describes all errors in libbdgt.
-#[derive(Debug, PartialEq)]
+#[derive(Debug)]
 pub struct Error {
+    kind: ErrorKind,
     msg: String,
-    extra: String
+    extra: String,
+    context: Vec<String>,
+    source: Option<Box<dyn std::error::Error + Send + Sync + 'static>>,
+    backtrace: Option<Box<std::backtrace::Backtrace>>
 }
 
 
-/// Crate-specific alias for [`std::result::Result`] instantiated 
+/// Crate-specific alias for [`std::result::Result`] instantiated
 /// with [`crate::error::Error`].
 pub type Result<T> = std::result::Result<T, Error>;
 
 
 impl Error {
     /// Constructs an error from message.
-    /// 
+    ///
     /// * `msg` - error message as something convertible into a [`alloc::string::String`]
-    pub fn from_message<M>(msg: M) -> Self 
+    pub fn from_message<M>(msg: M) -> Self
     where
         M: Into<String>
     {
-        Error { 
-            msg: msg.into(), 
-            extra: String::new() 
-        }
+        Self::from_message_with_extra(msg, String::new())
     }
 
     /// Constructs an error from message with some extra information.
-    /// 
+    ///
     /// * `msg` - error message as something convertible into a [`alloc::string::String`]
     /// * `extra` - extra information as something convertible into a [`alloc::string::String`]
     pub fn from_message_with_extra<M, E>(msg: M, extra: E) -> Self
@@ -34,65 +153,434 @@ impl Error {
         M: Into<String>,
         E: Into<String>
     {
-        Error { 
-            msg: msg.into(), 
-            extra: extra.into() 
+        //
+        // Backtrace is captured only if it is enabled via
+        // `RUST_BACKTRACE` or `RUST_LIB_BACKTRACE` variables
+        //
+
+        let backtrace = std::backtrace::Backtrace::capture();
+        let backtrace = match backtrace.status() {
+            std::backtrace::BacktraceStatus::Captured => Some(Box::new(backtrace)),
+            _ => None
+        };
+
+        Error {
+            kind: ErrorKind::Other,
+            msg: msg.into(),
+            extra: extra.into(),
+            context: Vec::new(),
+            source: None,
+            backtrace
+        }
+    }
+
+    /// Sets kind of an error.
+    ///
+    /// * `kind` - kind of an error
+    pub fn with_kind(mut self, kind: ErrorKind) -> Self {
+        self.kind = kind;
+        self
+    }
+
+    /// Sets an underlying error, that caused this one.
+    ///
+    /// * `source` - underlying error
+    pub fn with_source<S>(mut self, source: S) -> Self
+    where
+        S: std::error::Error + Send + Sync + 'static
+    {
+        self.source = Some(Box::new(source));
+        self
+    }
+
+    /// Attaches a description of an operation, that was being 
+    /// performed when an error occurred.
+    ///
+    /// * `context` - description of an operation (e.g. entity type and id, file path)
+    pub fn context<C>(mut self, context: C) -> Self
+    where
+        C: Into<String>
+    {
+        self.context.push(context.into());
+        self
+    }
+
+    /// Returns kind of an error.
+    pub fn kind(&self) -> ErrorKind {
+        self.kind
+    }
+
+    /// Checks if an operation may succeed if retried later without
+    /// any changes, e.g. if budget is temporarily locked or network
+    /// is unavailable.
+    pub fn is_transient(&self) -> bool {
+        match self.kind {
+            ErrorKind::Locked | ErrorKind::Network => true,
+            ErrorKind::Io => self.source
+                .as_deref()
+                .and_then(|source| source.downcast_ref::<std::io::Error>())
+                .is_some_and(|error| matches!(error.kind(), 
+                    std::io::ErrorKind::Interrupted | 
+                    std::io::ErrorKind::TimedOut | 
+                    std::io::ErrorKind::WouldBlock)),
+            _ => false
+        }
+    }
+
+    /// Checks if an error is caused by user's input or requires user's
+    /// decision, so it should be reported to user instead of retrying.
+    pub fn is_user_error(&self) -> bool {
+        matches!(self.kind, 
+            ErrorKind::NotFound | 
+            ErrorKind::AlreadyExists | 
+            ErrorKind::InvalidInput | 
+            ErrorKind::ConsistencyViolation | 
+            ErrorKind::SyncConflict | 
+            ErrorKind::Config)
+    }
+
+    /// Checks if stored data is malformed. An app should refuse to 
+    /// continue and avoid writing anything in this case.
+    pub fn is_data_corruption(&self) -> bool {
+        self.kind == ErrorKind::Corruption
+    }
+
+    /// Checks if an operation was cancelled by a caller. It is not
+    /// a failure and usually should not be reported at all.
+    pub fn is_cancelled(&self) -> bool {
+        self.kind == ErrorKind::Cancelled
+    }
+
+    /// Returns stable numeric code of an error, that is a code of its kind.
+    pub fn code(&self) -> u32 {
+        self.kind
+            .code()
+    }
+
+    /// Returns error message.
+    pub fn message(&self) -> &str {
+        &self.msg
+    }
+
+    /// Returns extra information (may be empty).
+    pub fn extra(&self) -> &str {
+        &self.extra
+    }
+
+    /// Returns descriptions of operations, that were being performed 
+    /// when an error occurred, from the innermost to the outermost one.
+    pub fn contexts(&self) -> &[String] {
+        &self.context
+    }
+
+    /// Returns a backtrace captured at the moment of error creation.
+    ///
+    /// It is captured only if enabled via `RUST_BACKTRACE` or `RUST_LIB_BACKTRACE`.
+    pub fn backtrace(&self) -> Option<&std::backtrace::Backtrace> {
+        self.backtrace
+            .as_deref()
+    }
+}
+
+
+/// Extension for results, that allows to attach context to errors.
+pub trait Context<T> {
+    /// Attaches a description of an operation to an error if any.
+    ///
+    /// * `context` - description of an operation
+    fn context<C>(self, context: C) -> Result<T>
+    where
+        C: Into<String>;
+
+    /// Attaches a lazily evaluated description of an operation to an error if any.
+    ///
+    /// * `context` - function, that returns description of an operation
+    fn with_context<C, F>(self, context: F) -> Result<T>
+    where
+        C: Into<String>,
+        F: FnOnce() -> C;
+}
+
+
+impl<T, E> Context<T> for std::result::Result<T, E>
+where
+    E: Into<Error>
+{
+    fn context<C>(self, context: C) -> Result<T>
+    where
+        C: Into<String>
+    {
+        self.map_err(|error| error.into().context(context))
+    }
+
+    fn with_context<C, F>(self, context: F) -> Result<T>
+    where
+        C: Into<String>,
+        F: FnOnce() -> C
+    {
+        self.map_err(|error| error.into().context(context()))
+    }
+}
+
+
+impl PartialEq for Error {
+    fn eq(&self, other: &Self) -> bool {
+        //
+        // Sources and backtraces are not comparable in 
+        // general, so they are ignored here
+        //
+
+        self.kind == other.kind &&
+        self.msg == other.msg &&
+        self.extra == other.extra
+    }
+}
+
+
+/// Serializable representation of an error.
+///
+/// Both numeric code and string name of a kind are included for
+/// convenience of consumers. Source and backtrace are not serialized.
+#[derive(Serialize, Deserialize)]
+struct SerializedError {
+    code: u32,
+    kind: ErrorKind,
+    message: String,
+    extra: String,
+    context: Vec<String>,
+}
+
+
+impl Serialize for Error {
+    fn serialize<S>(&self, serializer: S) -> std::result::Result<S::Ok, S::Error>
+    where
+        S: serde::Serializer
+    {
+        SerializedError {
+            code: self.code(),
+            kind: self.kind,
+            message: self.msg.clone(),
+            extra: self.extra.clone(),
+            context: self.context.clone()
         }
+        .serialize(serializer)
+    }
+}
+
+
+impl<'de> Deserialize<'de> for Error {
+    fn deserialize<D>(deserializer: D) -> std::result::Result<Self, D::Error>
+    where
+        D: serde::Deserializer<'de>
+    {
+        let serialized = SerializedError::deserialize(deserializer)?;
+
+        let mut error = Error::from_message_with_extra(serialized.message, serialized.extra)
+            .with_kind(serialized.kind);
+
+        error.context = serialized.context;
+        Ok(error)
     }
 }
 
 
 impl std::fmt::Display for Error {
     fn fmt(&self, f: &mut std::fmt::Formatter) -> std::fmt::Result {
-        write!(f, r#"Message: "{}" (extra: "{}")"#, self.msg, self.extra)
+        //
+        // Context is printed from the outermost operation
+        // to the innermost one, e.g. "opening budget: reading
+        // file: No such file or directory"
+        //
+
+        for context in self.context.iter().rev() {
+            write!(f, "{}: ", context)?;
+        }
+
+        match self.extra.is_empty() {
+            true => write!(f, "{}", self.msg),
+            false => write!(f, "{} ({})", self.msg, self.extra)
+        }
     }
 }
 
 
 impl std::error::Error for Error {
-    fn description(&self) -> &str {
-        &self.msg
+    fn source(&self) -> Option<&(dyn std::error::Error + 'static)> {
+        self.source
+            .as_deref()
+            .map(|source| source as &(dyn std::error::Error + 'static))
     }
 }
 
 
+#[cfg(feature = "native")]
 impl From<gpgme::Error> for Error {
     fn from(value: gpgme::Error) -> Self {
         let msg = value.to_string();
         let extra = format!("code: {}", value.code());
 
         Error::from_message_with_extra(msg, extra)
+            .with_kind(ErrorKind::CryptoFailure)
+            .with_source(value)
     }
 }
 
 
 /// Macro for implementing [`From<SomeError>`] in a beautiful way.
 /// It simplifies implementing the trait for a new error type
-/// to writing only one line of code.
+/// to writing only one line of code: a type and a function, that
+/// classifies an error of this type.
 macro_rules! implement_from_error {
-    ($err_type:ty, $($err_types:ty),+ $(,)?) => {
-        implement_from_error!($err_type);
-        implement_from_error!($($err_types, )+);
+    ($err_type:ty => $kind:expr, $($err_types:ty => $kinds:expr),+ $(,)?) => {
+        implement_from_error!($err_type => $kind);
+        implement_from_error!($($err_types => $kinds, )+);
     };
-    ($err_type:ty $(,)?) => {
+    ($err_type:ty => $kind:expr $(,)?) => {
         impl From<$err_type> for Error {
             fn from(value: $err_type) -> Self {
                 let msg = value.to_string();
+                let kind = ($kind)(&value);
+
                 Error::from_message(msg)
+                    .with_kind(kind)
+                    .with_source(value)
             }
         }
     }
 }
 
+#[cfg(feature = "native")]
+implement_from_error!(
+    rusqlite::Error => classify_rusqlite_error,
+    git2::Error => classify_git_error,
+);
+
+#[cfg(feature = "sftp")]
+implement_from_error!(
+    ssh2::Error => classify_ssh_error,
+);
+
+#[cfg(feature = "imap")]
+implement_from_error!(
+    native_tls::Error => |_| ErrorKind::Network,
+);
+
+#[cfg(feature = "lan")]
+implement_from_error!(
+    mdns_sd::Error => |_| ErrorKind::Network,
+);
+
+#[cfg(feature = "keyring")]
+implement_from_error!(
+    keyring::Error => classify_keyring_error,
+);
+
 implement_from_error!(
-    rusqlite::Error,
-    std::io::Error,
-    rand::Error,
-    aes_gcm::Error,
-    std::convert::Infallible,
-    git2::Error,
-    scrypt::errors::InvalidOutputLen,
-    flexbuffers::DeserializationError,
-    flexbuffers::SerializationError,
-    uuid::Error,
+    std::io::Error => classify_io_error,
+    rand::Error => |_| ErrorKind::CryptoFailure,
+    std::convert::Infallible => |_| ErrorKind::Other,
+    flexbuffers::DeserializationError => |_| ErrorKind::Corruption,
+    flexbuffers::SerializationError => |_| ErrorKind::Other,
+    uuid::Error => |_| ErrorKind::Corruption,
+    std::array::TryFromSliceError => |_| ErrorKind::Corruption,
+    toml::de::Error => |_| ErrorKind::Config,
+    toml::ser::Error => |_| ErrorKind::Config,
+    serde_json::Error => |_| ErrorKind::Other,
+);
+
+
+/// Macro for implementing [`From<SomeError>`] for error types, that
+/// don't implement [`std::error::Error`] and hence cannot be a source.
+macro_rules! implement_from_opaque_error {
+    ($($err_type:ty => $kind:expr),+ $(,)?) => {
+        $(
+            impl From<$err_type> for Error {
+                fn from(value: $err_type) -> Self {
+                    let kind = ($kind)(&value);
+
+                    Error::from_message(value.to_string())
+                        .with_kind(kind)
+                }
+            }
+        )+
+    }
+}
+
+implement_from_opaque_error!(
+    aes_gcm::Error => |_| ErrorKind::CryptoFailure,
+    scrypt::errors::InvalidOutputLen => |_| ErrorKind::CryptoFailure,
 );
+
+#[cfg(feature = "native")]
+implement_from_opaque_error!(
+    argon2::Error => |_| ErrorKind::CryptoFailure,
+);
+
+
+#[cfg(feature = "native")]
+fn classify_rusqlite_error(error: &rusqlite::Error) -> ErrorKind {
+    use rusqlite::ErrorCode;
+
+    match error {
+        rusqlite::Error::QueryReturnedNoRows => ErrorKind::NotFound,
+        rusqlite::Error::SqliteFailure(failure, _) => match failure.code {
+            ErrorCode::DatabaseBusy | ErrorCode::DatabaseLocked => ErrorKind::Locked,
+            ErrorCode::DatabaseCorrupt | ErrorCode::NotADatabase => ErrorKind::Corruption,
+            ErrorCode::ConstraintViolation => ErrorKind::ConsistencyViolation,
+            ErrorCode::CannotOpen | ErrorCode::SystemIoFailure | ErrorCode::DiskFull => ErrorKind::Io,
+            _ => ErrorKind::Storage
+        },
+        _ => ErrorKind::Storage
+    }
+}
+
+
+fn classify_io_error(error: &std::io::Error) -> ErrorKind {
+    match error.kind() {
+        std::io::ErrorKind::NotFound => ErrorKind::NotFound,
+        std::io::ErrorKind::AlreadyExists => ErrorKind::AlreadyExists,
+        _ => ErrorKind::Io
+    }
+}
+
+
+#[cfg(feature = "native")]
+fn classify_git_error(error: &git2::Error) -> ErrorKind {
+    match (error.code(), error.class()) {
+        (git2::ErrorCode::Conflict | git2::ErrorCode::MergeConflict, _) => ErrorKind::SyncConflict,
+        (git2::ErrorCode::Locked, _) => ErrorKind::Locked,
+        (git2::ErrorCode::NotFound, _) => ErrorKind::NotFound,
+        (git2::ErrorCode::Auth | git2::ErrorCode::Certificate, _) => ErrorKind::Network,
+        (_, git2::ErrorClass::Net | git2::ErrorClass::Http | git2::ErrorClass::Ssh | git2::ErrorClass::Ssl) => ErrorKind::Network,
+        _ => ErrorKind::SyncFailure
+    }
+}
+
+
+#[cfg(feature = "keyring")]
+fn classify_keyring_error(error: &keyring::Error) -> ErrorKind {
+    match error {
+        keyring::Error::NoEntry => ErrorKind::NotFound,
+        keyring::Error::NoStorageAccess(_) => ErrorKind::Locked,
+        keyring::Error::BadEncoding(_) => ErrorKind::Corruption,
+        keyring::Error::TooLong(..) | keyring::Error::Invalid(..) => ErrorKind::InvalidInput,
+        keyring::Error::PlatformFailure(_) => ErrorKind::Io,
+        _ => ErrorKind::Other
+    }
+}
+
+
+#[cfg(feature = "sftp")]
+fn classify_ssh_error(error: &ssh2::Error) -> ErrorKind {
+    //
+    // SFTP status codes are defined by protocol: 2 is "no such
+    // file", 11 is "file already exists"
+    //
+
+    match error.code() {
+        ssh2::ErrorCode::SFTP(2) => ErrorKind::NotFound,
+        ssh2::ErrorCode::SFTP(11) => ErrorKind::AlreadyExists,
+        ssh2::ErrorCode::SFTP(_) => ErrorKind::Io,
+        ssh2::ErrorCode::Session(_) => ErrorKind::Network
+    }
+}
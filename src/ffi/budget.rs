@@ -0,0 +1,521 @@
+use std::ffi::{c_char, CStr};
+
+use crate::error::{Result, Error, ErrorKind};
+use crate::location::CustomLocation;
+use crate::storage::{Id, Transaction, Account, MetaInfo, TransactionKind, CustomFields};
+use crate::datetime::{Clock, Timestamp};
+use crate::period::CustomPeriod;
+use crate::core::DefaultBudget;
+use crate::reports;
+use super::types::*;
+use super::status::guard;
+use super::{NULL_POINTER, INVALID_STRING, INVALID_TIMESTAMP, INVALID_PERIOD, INVALID_AMOUNT};
+
+
+/// Opens an existing budget.
+/// 
+/// * `root` - path to budget's root directory or null to select location using environment variables
+/// * `budget` - pointer to a variable, that receives a budget handle
+/// 
+/// # Safety
+/// 
+/// `root` must be null or a valid null-terminated string, `budget` must be 
+/// a valid pointer.
+#[no_mangle]
+pub unsafe extern "C" fn bdgt_budget_open(root: *const c_char, budget: *mut *mut BdgtBudget) -> u32 {
+    guard(|| {
+        let budget = non_null(budget)?;
+
+        let handle = match root.is_null() {
+            true => DefaultBudget::open_default()?,
+            false => DefaultBudget::open(&CustomLocation::new(to_str(root)?))?
+        };
+
+        *budget = Box::into_raw(Box::new(BdgtBudget { budget: handle }));
+        Ok(())
+    })
+}
+
+
+/// Closes a budget and releases its handle.
+/// 
+/// * `budget` - budget handle (may be null)
+/// 
+/// # Safety
+/// 
+/// `budget` must be a handle returned by [`bdgt_budget_open`] or null.
+/// Handle must not be used after the call.
+#[no_mangle]
+pub unsafe extern "C" fn bdgt_budget_close(budget: *mut BdgtBudget) {
+    if !budget.is_null() {
+        drop(Box::from_raw(budget));
+    }
+}
+
+
+/// Adds a new transaction.
+/// 
+/// * `budget` - budget handle
+/// * `account_id` - pointer to 16 bytes of account identifier
+/// * `category_id` - pointer to 16 bytes of category identifier
+/// * `description` - null-terminated UTF-8 string
+/// * `amount` - signed amount of money
+/// * `timestamp` - Unix timestamp of transaction
+/// 
+/// # Safety
+/// 
+/// All pointers must be valid.
+#[no_mangle]
+pub unsafe extern "C" fn bdgt_add_transaction(budget: *const BdgtBudget, account_id: *const Id, category_id: *const Id, 
+    description: *const c_char, amount: i64, timestamp: i64) -> u32 
+{
+    guard(|| {
+        let budget = &non_null_ref(budget)?.budget;
+        let timestamp = to_timestamp(timestamp)?;
+
+        budget.add_transaction(&Transaction {
+            id: None,
+            timestamp,
+            description: to_str(description)?.to_owned(),
+            account_id: *non_null_ref(account_id)?,
+            category_id: *non_null_ref(category_id)?,
+            amount: to_amount(amount)?,
+            kind: TransactionKind::Regular,
+            note: String::new(),
+            custom_fields: CustomFields::new(),
+            spender_id: None,
+            meta_info: MetaInfo::new(Some(Clock::now()), None, None)
+        })
+    })
+}
+
+
+/// Updates a transaction in place, so that its identifier is kept.
+/// 
+/// Note, custom fields and a note of the transaction are kept as is.
+/// 
+/// * `budget` - budget handle
+/// * `transaction_id` - pointer to 16 bytes of transaction identifier
+/// * `account_id` - pointer to 16 bytes of account identifier
+/// * `category_id` - pointer to 16 bytes of category identifier
+/// * `description` - null-terminated UTF-8 string
+/// * `amount` - signed amount of money
+/// * `timestamp` - Unix timestamp of transaction
+/// 
+/// # Safety
+/// 
+/// All pointers must be valid.
+#[no_mangle]
+pub unsafe extern "C" fn bdgt_update_transaction(budget: *const BdgtBudget, transaction_id: *const Id, account_id: *const Id, 
+    category_id: *const Id, description: *const c_char, amount: i64, timestamp: i64) -> u32 
+{
+    guard(|| {
+        let budget = &non_null_ref(budget)?.budget;
+        let stored = budget.transaction(*non_null_ref(transaction_id)?)?;
+
+        budget.update_transaction(&Transaction {
+            timestamp: to_timestamp(timestamp)?,
+            description: to_str(description)?.to_owned(),
+            account_id: *non_null_ref(account_id)?,
+            category_id: *non_null_ref(category_id)?,
+            amount: to_amount(amount)?,
+            meta_info: MetaInfo { changed_timestamp: None, ..stored.meta_info },
+            ..stored
+        })
+    })
+}
+
+
+/// Removes a transaction.
+/// 
+/// * `budget` - budget handle
+/// * `transaction_id` - pointer to 16 bytes of transaction identifier
+/// * `timestamp` - Unix timestamp of removal
+/// 
+/// # Safety
+/// 
+/// All pointers must be valid.
+#[no_mangle]
+pub unsafe extern "C" fn bdgt_remove_transaction(budget: *const BdgtBudget, transaction_id: *const Id, timestamp: i64) -> u32 {
+    guard(|| {
+        non_null_ref(budget)?
+            .budget
+            .remove_transaction(*non_null_ref(transaction_id)?, false, to_timestamp(timestamp)?)
+    })
+}
+
+
+/// Returns all transactions.
+/// 
+/// * `budget` - budget handle
+/// * `list` - pointer to a list to fill, must be released with [`bdgt_transaction_list_free`]
+/// 
+/// # Safety
+/// 
+/// All pointers must be valid.
+#[no_mangle]
+pub unsafe extern "C" fn bdgt_transactions(budget: *const BdgtBudget, list: *mut BdgtTransactionList) -> u32 {
+    guard(|| {
+        let list = non_null(list)?;
+        *list = BdgtList::empty();
+
+        let transactions = non_null_ref(budget)?
+            .budget
+            .transactions()?;
+
+        *list = BdgtList::from_vec(transactions
+            .iter()
+            .map(BdgtTransaction::from)
+            .collect());
+
+        Ok(())
+    })
+}
+
+
+/// Releases a list of transactions.
+/// 
+/// * `list` - list to release
+/// 
+/// # Safety
+/// 
+/// List must be filled by libbdgt and must not be used after the call.
+#[no_mangle]
+pub unsafe extern "C" fn bdgt_transaction_list_free(list: BdgtTransactionList) {
+    for transaction in list.into_vec() {
+        free_c_string(transaction.description);
+    }
+}
+
+
+/// Adds a new account.
+/// 
+/// * `budget` - budget handle
+/// * `name` - null-terminated UTF-8 string
+/// * `initial_balance` - initial balance of an account
+/// 
+/// # Safety
+/// 
+/// All pointers must be valid.
+#[no_mangle]
+pub unsafe extern "C" fn bdgt_add_account(budget: *const BdgtBudget, name: *const c_char, initial_balance: i64) -> u32 {
+    guard(|| {
+        non_null_ref(budget)?
+            .budget
+            .add_account(&Account {
+                id: None,
+                name: to_str(name)?.to_owned(),
+                balance: to_amount(initial_balance)?,
+                initial_balance: to_amount(initial_balance)?,
+                note: String::new(),
+                custom_fields: CustomFields::new(),
+                meta_info: MetaInfo::new(Some(Clock::now()), None, None)
+            })
+    })
+}
+
+
+/// Removes an account.
+/// 
+/// * `budget` - budget handle
+/// * `account_id` - pointer to 16 bytes of account identifier
+/// * `force` - remove account with all of its transactions
+/// * `timestamp` - Unix timestamp of removal
+/// 
+/// # Safety
+/// 
+/// All pointers must be valid.
+#[no_mangle]
+pub unsafe extern "C" fn bdgt_remove_account(budget: *const BdgtBudget, account_id: *const Id, force: bool, timestamp: i64) -> u32 {
+    guard(|| {
+        non_null_ref(budget)?
+            .budget
+            .remove_account(*non_null_ref(account_id)?, force, to_timestamp(timestamp)?)
+    })
+}
+
+
+/// Returns all accounts.
+/// 
+/// * `budget` - budget handle
+/// * `list` - pointer to a list to fill, must be released with [`bdgt_account_list_free`]
+/// 
+/// # Safety
+/// 
+/// All pointers must be valid.
+#[no_mangle]
+pub unsafe extern "C" fn bdgt_accounts(budget: *const BdgtBudget, list: *mut BdgtAccountList) -> u32 {
+    guard(|| {
+        let list = non_null(list)?;
+        *list = BdgtList::empty();
+
+        let accounts = non_null_ref(budget)?
+            .budget
+            .accounts()?;
+
+        *list = BdgtList::from_vec(accounts
+            .iter()
+            .map(BdgtAccount::from)
+            .collect());
+
+        Ok(())
+    })
+}
+
+
+/// Releases a list of accounts.
+/// 
+/// * `list` - list to release
+/// 
+/// # Safety
+/// 
+/// List must be filled by libbdgt and must not be used after the call.
+#[no_mangle]
+pub unsafe extern "C" fn bdgt_account_list_free(list: BdgtAccountList) {
+    for account in list.into_vec() {
+        free_c_string(account.name);
+    }
+}
+
+
+/// Returns all categories.
+/// 
+/// * `budget` - budget handle
+/// * `list` - pointer to a list to fill, must be released with [`bdgt_category_list_free`]
+/// 
+/// # Safety
+/// 
+/// All pointers must be valid.
+#[no_mangle]
+pub unsafe extern "C" fn bdgt_categories(budget: *const BdgtBudget, list: *mut BdgtCategoryList) -> u32 {
+    guard(|| {
+        let list = non_null(list)?;
+        *list = BdgtList::empty();
+
+        let categories = non_null_ref(budget)?
+            .budget
+            .categories()?;
+
+        *list = BdgtList::from_vec(categories
+            .iter()
+            .map(BdgtCategory::from)
+            .collect());
+
+        Ok(())
+    })
+}
+
+
+/// Releases a list of categories.
+/// 
+/// * `list` - list to release
+/// 
+/// # Safety
+/// 
+/// List must be filled by libbdgt and must not be used after the call.
+#[no_mangle]
+pub unsafe extern "C" fn bdgt_category_list_free(list: BdgtCategoryList) {
+    for category in list.into_vec() {
+        free_c_string(category.name);
+    }
+}
+
+
+/// Computes net worth (sum of all account values) at a given moment.
+/// 
+/// * `budget` - budget handle
+/// * `timestamp` - Unix timestamp to compute net worth at
+/// * `net_worth` - pointer to a variable, that receives net worth
+/// 
+/// # Safety
+/// 
+/// All pointers must be valid.
+#[no_mangle]
+pub unsafe extern "C" fn bdgt_net_worth(budget: *const BdgtBudget, timestamp: i64, net_worth: *mut i64) -> u32 {
+    guard(|| {
+        let net_worth = non_null(net_worth)?;
+
+        *net_worth = non_null_ref(budget)?
+            .budget
+            .net_worth(to_timestamp(timestamp)?)? as i64;
+
+        Ok(())
+    })
+}
+
+
+/// Computes value of an account (cash balance and holdings) at a given moment.
+/// 
+/// * `budget` - budget handle
+/// * `account_id` - pointer to 16 bytes of account identifier
+/// * `timestamp` - Unix timestamp to compute value at
+/// * `value` - pointer to a variable, that receives account value
+/// 
+/// # Safety
+/// 
+/// All pointers must be valid.
+#[no_mangle]
+pub unsafe extern "C" fn bdgt_account_value(budget: *const BdgtBudget, account_id: *const Id, timestamp: i64, value: *mut i64) -> u32 {
+    guard(|| {
+        let value = non_null(value)?;
+
+        *value = non_null_ref(budget)?
+            .budget
+            .account_value(*non_null_ref(account_id)?, to_timestamp(timestamp)?)? as i64;
+
+        Ok(())
+    })
+}
+
+
+/// Returns totals of transactions per category within a period
+/// (see [`crate::reports::CategoryBreakdown`]) as a JSON string.
+/// 
+/// * `budget` - budget handle
+/// * `start` - Unix timestamp of the beginning of the period (inclusive)
+/// * `end` - Unix timestamp of the end of the period (exclusive)
+/// * `json` - pointer to a variable, that receives the report, must be released with [`bdgt_string_free`]
+/// 
+/// # Safety
+/// 
+/// All pointers must be valid.
+#[no_mangle]
+pub unsafe extern "C" fn bdgt_category_breakdown(budget: *const BdgtBudget, start: i64, end: i64, json: *mut *mut c_char) -> u32 {
+    guard(|| {
+        let json = non_null(json)?;
+        let report = non_null_ref(budget)?
+            .budget
+            .category_breakdown(&to_period(start, end)?)?;
+
+        *json = into_c_string(&reports::to_json(&report)?);
+        Ok(())
+    })
+}
+
+
+/// Returns state of all plans within a period (see [`crate::reports::BudgetStatus`])
+/// as a JSON string.
+/// 
+/// * `budget` - budget handle
+/// * `start` - Unix timestamp of the beginning of the period (inclusive)
+/// * `end` - Unix timestamp of the end of the period (exclusive)
+/// * `json` - pointer to a variable, that receives the report, must be released with [`bdgt_string_free`]
+/// 
+/// # Safety
+/// 
+/// All pointers must be valid.
+#[no_mangle]
+pub unsafe extern "C" fn bdgt_budget_status(budget: *const BdgtBudget, start: i64, end: i64, json: *mut *mut c_char) -> u32 {
+    guard(|| {
+        let json = non_null(json)?;
+        let report = non_null_ref(budget)?
+            .budget
+            .budget_status(&to_period(start, end)?)?;
+
+        *json = into_c_string(&reports::to_json(&report)?);
+        Ok(())
+    })
+}
+
+
+/// Returns money flows between accounts and categories within a period
+/// (see [`crate::reports::FlowReport`]) as a JSON string.
+/// 
+/// * `budget` - budget handle
+/// * `start` - Unix timestamp of the beginning of the period (inclusive)
+/// * `end` - Unix timestamp of the end of the period (exclusive)
+/// * `json` - pointer to a variable, that receives the report, must be released with [`bdgt_string_free`]
+/// 
+/// # Safety
+/// 
+/// All pointers must be valid.
+#[no_mangle]
+pub unsafe extern "C" fn bdgt_money_flows(budget: *const BdgtBudget, start: i64, end: i64, json: *mut *mut c_char) -> u32 {
+    guard(|| {
+        let json = non_null(json)?;
+        let report = non_null_ref(budget)?
+            .budget
+            .money_flows(&to_period(start, end)?)?;
+
+        *json = into_c_string(&reports::to_json(&report)?);
+        Ok(())
+    })
+}
+
+
+/// Releases a string returned by libbdgt.
+/// 
+/// * `value` - string to release (may be null)
+/// 
+/// # Safety
+/// 
+/// String must be returned by libbdgt or be null and must not be used after the call.
+#[no_mangle]
+pub unsafe extern "C" fn bdgt_string_free(value: *mut c_char) {
+    free_c_string(value);
+}
+
+
+/// Performs synchronization with remote instances.
+/// 
+/// * `budget` - budget handle
+/// * `auth` - pointer to authentication data (may be null if `auth_length` is 0)
+/// * `auth_length` - length of authentication data
+/// 
+/// # Safety
+/// 
+/// All pointers must be valid, `auth` must point to at least `auth_length` bytes.
+#[no_mangle]
+pub unsafe extern "C" fn bdgt_perform_sync(budget: *const BdgtBudget, auth: *const u8, auth_length: usize) -> u32 {
+    guard(|| {
+        let auth = match auth_length {
+            0 => &[][..],
+            _ => std::slice::from_raw_parts(non_null_ref(auth)?, auth_length)
+        };
+
+        non_null_ref(budget)?
+            .budget
+            .perform_sync(auth)
+    })
+}
+
+
+unsafe fn non_null<'a, T>(pointer: *mut T) -> Result<&'a mut T> {
+    pointer
+        .as_mut()
+        .ok_or_else(|| Error::from_message(NULL_POINTER).with_kind(ErrorKind::InvalidInput))
+}
+
+
+unsafe fn non_null_ref<'a, T>(pointer: *const T) -> Result<&'a T> {
+    pointer
+        .as_ref()
+        .ok_or_else(|| Error::from_message(NULL_POINTER).with_kind(ErrorKind::InvalidInput))
+}
+
+
+unsafe fn to_str<'a>(value: *const c_char) -> Result<&'a str> {
+    CStr::from_ptr(non_null_ref(value)?)
+        .to_str()
+        .map_err(|_| Error::from_message(INVALID_STRING).with_kind(ErrorKind::InvalidInput))
+}
+
+
+fn to_timestamp(timestamp: i64) -> Result<Timestamp> {
+    Timestamp::from_timestamp(timestamp, 0)
+        .ok_or_else(|| Error::from_message_with_extra(INVALID_TIMESTAMP, timestamp.to_string())
+            .with_kind(ErrorKind::InvalidInput))
+}
+
+
+fn to_amount(amount: i64) -> Result<isize> {
+    isize::try_from(amount)
+        .map_err(|_| Error::from_message_with_extra(INVALID_AMOUNT, amount.to_string())
+            .with_kind(ErrorKind::InvalidInput))
+}
+
+
+fn to_period(start: i64, end: i64) -> Result<CustomPeriod> {
+    CustomPeriod::new(to_timestamp(start)?, to_timestamp(end)?)
+        .ok_or_else(|| Error::from_message(INVALID_PERIOD).with_kind(ErrorKind::InvalidInput))
+}
@@ -0,0 +1,46 @@
+//! C ABI for non-Rust frontends.
+//! 
+//! The module is available with `ffi` feature. All functions return 
+//! a status code: [`BDGT_OK`] on success or a code of 
+//! [`crate::error::ErrorKind`] on failure. A message of the last error 
+//! that occurred on current thread can be obtained with
+//! [`bdgt_last_error_message`].
+//! 
+//! Budget is represented by an opaque handle, that is created by 
+//! [`bdgt_budget_open`] and must be released by [`bdgt_budget_close`].
+//! Identifiers are passed as pointers to 16 bytes, amounts -- as 
+//! signed 64-bit integers and timestamps -- as Unix timestamps in 
+//! seconds. Reports are returned as JSON strings (see 
+//! [`crate::reports::to_json`]), that must be released by
+//! [`bdgt_string_free`].
+//! 
+//! To produce a shared or static library build the crate with 
+//! `cdylib` or `staticlib` crate type, e.g.:
+//! `cargo rustc --release --features ffi --crate-type cdylib`.
+
+mod types;
+mod status;
+mod budget;
+
+pub use self::types::*;
+pub use self::status::*;
+pub use self::budget::*;
+
+
+/// Error message for a null pointer passed to a function.
+const NULL_POINTER: &str = "Null pointer is passed";
+
+/// Error message for a string, that is not a valid UTF-8.
+const INVALID_STRING: &str = "String is not a valid UTF-8";
+
+/// Error message for a timestamp out of supported range.
+const INVALID_TIMESTAMP: &str = "Timestamp is out of range";
+
+/// Error message for a period, that ends before it starts.
+const INVALID_PERIOD: &str = "Period is empty";
+
+/// Error message for an amount, that does not fit into native integer.
+const INVALID_AMOUNT: &str = "Amount is out of range";
+
+/// Error message for a panic caught on FFI boundary.
+const PANIC_CAUGHT: &str = "Unexpected panic in libbdgt";
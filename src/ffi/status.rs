@@ -0,0 +1,86 @@
+use std::cell::RefCell;
+use std::ffi::{c_char, CString};
+
+use crate::error::{Result, Error, ErrorKind};
+use super::PANIC_CAUGHT;
+
+
+/// Status code of a successful call.
+pub const BDGT_OK: u32 = 0;
+
+
+thread_local! {
+    /// Last error occurred on current thread.
+    static LAST_ERROR: RefCell<Option<CString>> = const { RefCell::new(None) };
+}
+
+
+/// Returns a message of the last error occurred on current thread.
+/// 
+/// Returns null if there was no error. The pointer is valid until 
+/// the next call of any function on the same thread.
+#[no_mangle]
+pub extern "C" fn bdgt_last_error_message() -> *const c_char {
+    LAST_ERROR.with(|last_error| {
+        last_error
+            .borrow()
+            .as_ref()
+            .map_or(std::ptr::null(), |message| message.as_ptr())
+    })
+}
+
+
+/// Executes a function, saves its error (if any) and converts 
+/// result into a status code. Panics are not propagated across 
+/// FFI boundary.
+/// 
+/// * `func` - function to execute
+pub(super) fn guard<F>(func: F) -> u32
+where
+    F: FnOnce() -> Result<()>
+{
+    let result = std::panic::catch_unwind(std::panic::AssertUnwindSafe(func))
+        .unwrap_or_else(|_| Err(Error::from_message(PANIC_CAUGHT)));
+
+    let error = result.err();
+
+    let status = error
+        .as_ref()
+        .map_or(BDGT_OK, Error::code);
+
+    //
+    // Interior null bytes are not expected in messages,
+    // but I replace message with an empty one just in case
+    //
+
+    let message = error
+        .map(|error| CString::new(error.to_string()).unwrap_or_default());
+
+    LAST_ERROR.with(|last_error| *last_error.borrow_mut() = message);
+
+    status
+}
+
+
+/// Returns code of a given kind. Useful for comparison of statuses
+/// in C code without duplication of constants.
+/// 
+/// * `kind` - string name of a kind (e.g. `not_found`)
+/// 
+/// # Safety
+/// 
+/// `kind` must be a valid null-terminated string or null.
+#[no_mangle]
+pub unsafe extern "C" fn bdgt_error_code(kind: *const c_char) -> u32 {
+    if kind.is_null() {
+        return ErrorKind::Other.code();
+    }
+
+    let kind = std::ffi::CStr::from_ptr(kind);
+
+    ErrorKind::ALL
+        .iter()
+        .find(|candidate| candidate.name().as_bytes() == kind.to_bytes())
+        .unwrap_or(&ErrorKind::Other)
+        .code()
+}
@@ -0,0 +1,180 @@
+use std::ffi::{c_char, CString};
+
+use crate::core::DefaultBudget;
+use crate::storage::{Id, Transaction, Account, Category, CategoryType};
+
+
+/// Opaque budget handle.
+pub struct BdgtBudget {
+    pub(super) budget: DefaultBudget,
+}
+
+
+/// Transaction representation for C code.
+#[repr(C)]
+pub struct BdgtTransaction {
+    /// Identifier
+    pub id: Id,
+
+    /// Unix timestamp (in seconds)
+    pub timestamp: i64,
+
+    /// Brief description (null-terminated UTF-8 string)
+    pub description: *mut c_char,
+
+    /// Identifier of an account
+    pub account_id: Id,
+
+    /// Identifier of a category
+    pub category_id: Id,
+
+    /// Amount of money
+    pub amount: i64,
+}
+
+
+/// Account representation for C code.
+#[repr(C)]
+pub struct BdgtAccount {
+    /// Identifier
+    pub id: Id,
+
+    /// Account name (null-terminated UTF-8 string)
+    pub name: *mut c_char,
+
+    /// Current balance
+    pub balance: i64,
+
+    /// Initial balance
+    pub initial_balance: i64,
+}
+
+
+/// Category representation for C code.
+#[repr(C)]
+pub struct BdgtCategory {
+    /// Identifier
+    pub id: Id,
+
+    /// Category name (null-terminated UTF-8 string)
+    pub name: *mut c_char,
+
+    /// Category type: 0 for incomes, 1 for spendings
+    pub category_type: u8,
+}
+
+
+/// Array of items allocated by libbdgt.
+#[repr(C)]
+pub struct BdgtList<T> {
+    /// Pointer to the first item
+    pub items: *mut T,
+
+    /// Number of items
+    pub count: usize,
+}
+
+
+/// List of transactions.
+pub type BdgtTransactionList = BdgtList<BdgtTransaction>;
+
+/// List of accounts.
+pub type BdgtAccountList = BdgtList<BdgtAccount>;
+
+/// List of categories.
+pub type BdgtCategoryList = BdgtList<BdgtCategory>;
+
+
+impl<T> BdgtList<T> {
+    pub(super) fn from_vec(items: Vec<T>) -> Self {
+        let items = Box::into_raw(items.into_boxed_slice());
+
+        BdgtList {
+            items: items as *mut T,
+            count: items.len()
+        }
+    }
+
+    pub(super) fn empty() -> Self {
+        Self::from_vec(Vec::new())
+    }
+
+    /// Converts list back into a vector.
+    /// 
+    /// # Safety
+    /// 
+    /// List must be created by [`BdgtList::from_vec`] and must not be used after the call.
+    pub(super) unsafe fn into_vec(self) -> Vec<T> {
+        let items = std::ptr::slice_from_raw_parts_mut(self.items, self.count);
+        Box::from_raw(items).into_vec()
+    }
+}
+
+
+impl From<&Transaction> for BdgtTransaction {
+    fn from(value: &Transaction) -> Self {
+        BdgtTransaction {
+            id: value.id.unwrap_or_default(),
+            timestamp: value.timestamp.timestamp(),
+            description: into_c_string(&value.description),
+            account_id: value.account_id,
+            category_id: value.category_id,
+            amount: value.amount as i64
+        }
+    }
+}
+
+
+impl From<&Account> for BdgtAccount {
+    fn from(value: &Account) -> Self {
+        BdgtAccount {
+            id: value.id.unwrap_or_default(),
+            name: into_c_string(&value.name),
+            balance: value.balance as i64,
+            initial_balance: value.initial_balance as i64
+        }
+    }
+}
+
+
+impl From<&Category> for BdgtCategory {
+    fn from(value: &Category) -> Self {
+        BdgtCategory {
+            id: value.id.unwrap_or_default(),
+            name: into_c_string(&value.name),
+            category_type: match value.category_type {
+                CategoryType::Income => 0,
+                CategoryType::Outcome => 1
+            }
+        }
+    }
+}
+
+
+/// Converts a string into a C string owned by caller.
+/// 
+/// Interior null bytes truncate the string.
+/// 
+/// * `value` - string to convert
+pub(super) fn into_c_string(value: &str) -> *mut c_char {
+    let value = value
+        .split('\0')
+        .next()
+        .unwrap_or_default();
+
+    CString::new(value)
+        .unwrap_or_default()
+        .into_raw()
+}
+
+
+/// Releases a C string allocated by libbdgt.
+/// 
+/// # Safety
+/// 
+/// Pointer must be returned by libbdgt or be null.
+pub(super) unsafe fn free_c_string(value: *mut c_char) {
+    if !value.is_null() {
+        drop(CString::from_raw(value));
+    }
+}
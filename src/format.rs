@@ -0,0 +1,372 @@
+use crate::core::CurrencySettings;
+use crate::error::{Result, Error, ErrorKind};
+
+
+/// Error message for malformed amount of money.
+const MALFORMED_MONEY: &str = "Amount of money is malformed";
+
+/// Error message for unknown currency sign.
+const UNKNOWN_CURRENCY: &str = "Currency is unknown";
+
+
+/// Well-known currencies: ISO 4217 code, symbol and number of
+/// digits after decimal point. If several currencies share the
+/// same symbol, the first one is used when parsing.
+const CURRENCIES: &[(&str, &str, u8)] = &[
+    ("USD", "$", 2),
+    ("EUR", "€", 2),
+    ("GBP", "£", 2),
+    ("JPY", "¥", 0),
+    ("CNY", "¥", 2),
+    ("RUB", "₽", 2),
+    ("UAH", "₴", 2),
+    ("INR", "₹", 2),
+    ("KRW", "₩", 0),
+    ("TRY", "₺", 2),
+    ("ILS", "₪", 2),
+    ("PLN", "zł", 2),
+    ("CHF", "CHF", 2),
+    ("KWD", "KD", 3),
+];
+
+/// Default number of digits after decimal point of unknown currencies.
+const DEFAULT_PRECISION: u8 = 2;
+
+
+/// Amount of money in a currency.
+#[derive(Clone, PartialEq, Eq, Debug)]
+pub struct Money {
+    /// Amount in minimal units, i.e. `10^-precision` of the currency
+    pub amount: isize,
+
+    /// Currency of the amount
+    pub currency: CurrencySettings,
+}
+
+
+impl Money {
+    /// Creates an amount of money.
+    ///
+    /// * `amount` - amount in minimal units of the currency
+    /// * `currency` - currency of the amount
+    pub fn new(amount: isize, currency: CurrencySettings) -> Self {
+        Money {
+            amount,
+            currency
+        }
+    }
+}
+
+
+impl std::fmt::Display for Money {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        //
+        // Locale-independent representation, e.g. "-1234.56 USD"
+        //
+
+        let sign = if self.amount < 0 { "-" } else { "" };
+        let (integer, fraction) = split_amount(self.amount, self.currency.precision);
+
+        match fraction {
+            Some(fraction) => write!(f, "{}{}.{} {}", sign, integer, fraction, self.currency.code),
+            None => write!(f, "{}{} {}", sign, integer, self.currency.code)
+        }
+    }
+}
+
+
+/// Position of a currency symbol relative to an amount.
+#[derive(Clone, Copy, PartialEq, Eq, Debug)]
+pub enum SymbolPosition {
+    /// Symbol precedes an amount, e.g. "$1.00"
+    Before,
+
+    /// Symbol follows an amount, e.g. "1,00 €"
+    After,
+}
+
+
+/// Conventions of writing amounts of money.
+#[derive(Clone, Copy, PartialEq, Eq, Debug)]
+pub struct Locale {
+    /// Separator of integer and fractional parts
+    pub decimal_separator: char,
+
+    /// Separator of groups of three digits (if any)
+    pub group_separator: Option<char>,
+
+    /// Position of a currency symbol
+    pub symbol_position: SymbolPosition,
+
+    /// If a symbol is separated from an amount with a space
+    pub symbol_spacing: bool,
+}
+
+
+impl Locale {
+    /// English (United States): "$1,234.56".
+    pub const EN_US: Locale = Locale {
+        decimal_separator: '.',
+        group_separator: Some(','),
+        symbol_position: SymbolPosition::Before,
+        symbol_spacing: false
+    };
+
+    /// German (Germany): "1.234,56 €".
+    pub const DE_DE: Locale = Locale {
+        decimal_separator: ',',
+        group_separator: Some('.'),
+        symbol_position: SymbolPosition::After,
+        symbol_spacing: true
+    };
+
+    /// French (France): "1 234,56 €" (with narrow no-break space).
+    pub const FR_FR: Locale = Locale {
+        decimal_separator: ',',
+        group_separator: Some('\u{202F}'),
+        symbol_position: SymbolPosition::After,
+        symbol_spacing: true
+    };
+
+    /// Russian (Russia): "1 234,56 ₽" (with no-break space).
+    pub const RU_RU: Locale = Locale {
+        decimal_separator: ',',
+        group_separator: Some('\u{00A0}'),
+        symbol_position: SymbolPosition::After,
+        symbol_spacing: true
+    };
+}
+
+
+impl Default for Locale {
+    fn default() -> Self {
+        Locale::EN_US
+    }
+}
+
+
+/// Returns symbol of a currency (if it is well-known).
+///
+/// * `code` - ISO 4217 currency code
+pub fn currency_symbol(code: &str) -> Option<&'static str> {
+    CURRENCIES
+        .iter()
+        .find(|(known, _, _)| *known == code)
+        .map(|(_, symbol, _)| *symbol)
+}
+
+
+/// Renders an amount of money for a user.
+///
+/// Currency symbol is used if it is well-known, otherwise
+/// currency code is written instead.
+///
+/// * `money` - amount to render
+/// * `locale` - conventions of writing amounts
+pub fn format_money(money: &Money, locale: &Locale) -> String {
+    let (integer, fraction) = split_amount(money.amount, money.currency.precision);
+
+    let mut number = group_digits(&integer, locale.group_separator);
+    if let Some(fraction) = fraction {
+        number.push(locale.decimal_separator);
+        number.push_str(&fraction);
+    }
+
+    let symbol = currency_symbol(&money.currency.code)
+        .unwrap_or(&money.currency.code);
+
+    let spacing = if locale.symbol_spacing { "\u{00A0}" } else { "" };
+    let sign = if money.amount < 0 { "-" } else { "" };
+
+    match locale.symbol_position {
+        SymbolPosition::Before => format!("{}{}{}{}", sign, symbol, spacing, number),
+        SymbolPosition::After => format!("{}{}{}{}", sign, number, spacing, symbol)
+    }
+}
+
+
+/// Parses an amount of money entered by a user, e.g. "1.234,56 €",
+/// "$1,234.56", "-12.5", "(100 USD)".
+///
+/// Currency is recognized by its symbol or code, if none is present,
+/// default currency is used. If both '.' and ',' are present, the last
+/// one is a decimal separator. A single separator followed by exactly
+/// three digits is considered a group separator, unless it is the
+/// decimal separator of the locale.
+///
+/// * `input` - text to parse
+/// * `locale` - conventions of writing amounts, that resolve ambiguities
+/// * `default_currency` - currency of amounts without a currency sign
+pub fn parse_money(input: &str, locale: &Locale, default_currency: &CurrencySettings) -> Result<Money> {
+    let malformed = || Error::from_message_with_extra(MALFORMED_MONEY, input)
+        .with_kind(ErrorKind::InvalidInput);
+
+    //
+    // Accounting notation: negative amounts are in parentheses
+    //
+
+    let mut text = input.trim();
+    let mut negative = false;
+
+    if let Some(inner) = text.strip_prefix('(').and_then(|text| text.strip_suffix(')')) {
+        text = inner;
+        negative = true;
+    }
+
+    //
+    // Everything, that is not a part of a number, is a currency sign
+    //
+
+    let sign: String = text.chars()
+        .filter(|c| !is_number_char(*c))
+        .collect();
+
+    let currency = match sign.trim() {
+        "" => default_currency.clone(),
+        sign => parse_currency(sign, default_currency)?
+    };
+
+    let mut number: String = text.chars()
+        .filter(|c| is_number_char(*c) && !c.is_whitespace() && *c != '\'')
+        .collect();
+
+    match number.matches('-').count() {
+        0 => {},
+        1 if number.starts_with('-') || number.ends_with('-') => {
+            number = number.replace('-', "");
+            negative = !negative;
+        },
+        _ => return Err(malformed())
+    }
+
+    if let Some(unsigned) = number.strip_prefix('+') {
+        number = unsigned.to_owned();
+    }
+
+    let (integer, fraction) = split_number(&number, locale)
+        .ok_or_else(malformed)?;
+
+    let precision = currency.precision as usize;
+    if precision < fraction.len() {
+        return Err(malformed());
+    }
+
+    let digits = format!("{}{:0<width$}", integer, fraction, width = precision);
+    let amount: isize = digits.parse()
+        .map_err(|_| malformed())?;
+
+    Ok(Money::new(if negative { -amount } else { amount }, currency))
+}
+
+
+fn is_number_char(c: char) -> bool {
+    c.is_ascii_digit() || c.is_whitespace() || matches!(c, '.' | ',' | '\'' | '-' | '+')
+}
+
+
+fn parse_currency(sign: &str, default_currency: &CurrencySettings) -> Result<CurrencySettings> {
+    let code = CURRENCIES
+        .iter()
+        .find(|(code, symbol, _)| *symbol == sign || code.eq_ignore_ascii_case(sign))
+        .map(|(code, _, _)| code.to_string())
+        .or_else(|| (3 == sign.len() && sign.chars().all(|c| c.is_ascii_alphabetic()))
+            .then(|| sign.to_ascii_uppercase()))
+        .ok_or_else(|| Error::from_message_with_extra(UNKNOWN_CURRENCY, sign).with_kind(ErrorKind::InvalidInput))?;
+
+    //
+    // Precision of default currency is configured by user,
+    // so it takes priority over the built-in one
+    //
+
+    let precision = match code == default_currency.code {
+        true => default_currency.precision,
+        false => CURRENCIES
+            .iter()
+            .find(|(known, _, _)| *known == code)
+            .map(|(_, _, precision)| *precision)
+            .unwrap_or(DEFAULT_PRECISION)
+    };
+
+    Ok(CurrencySettings {
+        code,
+        precision
+    })
+}
+
+
+fn split_number<'a>(number: &'a str, locale: &Locale) -> Option<(String, &'a str)> {
+    if number.is_empty() {
+        return None;
+    }
+
+    let last_dot = number.rfind('.');
+    let last_comma = number.rfind(',');
+
+    let decimal = match (last_dot, last_comma) {
+        (Some(dot), Some(comma)) => Some(dot.max(comma)),
+        (Some(position), None) | (None, Some(position)) => {
+            let separator = number[position..].chars().next()?;
+            let is_single = 1 == number.matches(separator).count();
+            let digits_after = number.len() - position - 1;
+
+            let is_group = !is_single ||
+                (3 == digits_after && separator != locale.decimal_separator);
+
+            (!is_group).then_some(position)
+        },
+        (None, None) => None
+    };
+
+    let (integer, fraction) = match decimal {
+        Some(position) => (&number[..position], &number[position + 1..]),
+        None => (number, "")
+    };
+
+    //
+    // Group separators are allowed in integer part only
+    //
+
+    if !fraction.chars().all(|c| c.is_ascii_digit()) {
+        return None;
+    }
+
+    let integer: String = integer.chars()
+        .filter(|c| *c != '.' && *c != ',')
+        .collect();
+
+    if integer.is_empty() && fraction.is_empty() {
+        return None;
+    }
+
+    Some((integer, fraction))
+}
+
+
+fn split_amount(amount: isize, precision: u8) -> (String, Option<String>) {
+    let digits = format!("{:0>width$}", amount.unsigned_abs(), width = precision as usize + 1);
+    let (integer, fraction) = digits.split_at(digits.len() - precision as usize);
+
+    match precision {
+        0 => (integer.to_owned(), None),
+        _ => (integer.to_owned(), Some(fraction.to_owned()))
+    }
+}
+
+
+fn group_digits(integer: &str, separator: Option<char>) -> String {
+    let separator = match separator {
+        Some(separator) => separator,
+        None => return integer.to_owned()
+    };
+
+    let mut grouped = String::with_capacity(integer.len() * 4 / 3);
+    for (index, digit) in integer.chars().enumerate() {
+        if 0 != index && (integer.len() - index).is_multiple_of(3) {
+            grouped.push(separator);
+        }
+
+        grouped.push(digit);
+    }
+
+    grouped
+}
@@ -1,28 +1,70 @@
 //! # libbdgt
 //! 
 //! `libbdgt` is a backend library for `bdgt` app.
+//! 
+//! Native backends (GnuPG, Git and SQLite) are enabled by `native` 
+//! feature, which is on by default. Without it the library has no 
+//! system dependencies and can be built for `wasm32` targets, e.g. 
+//! with [`crypto::PassphraseCryptoEngine`], [`storage::MemoryStorage`]
+//! and [`sync::OfflineSyncEngine`].
+//! 
+//! `tracing` feature enables diagnostic spans and events for storage 
+//! mutations, cryptographic calls and synchronization phases. They 
+//! never contain sensitive values (amounts, names, keys), so logs 
+//! can be safely attached to bug reports. Types holding sensitive
+//! values print them redacted (see [`redact::Redacted`]).
 
+#[cfg(feature = "native")]
 extern crate dirs;
+#[cfg(feature = "native")]
 extern crate git2;
 extern crate uuid;
 extern crate rand;
+#[cfg(feature = "native")]
 extern crate gpgme;
 extern crate scrypt;
 extern crate chrono;
 extern crate typenum;
 extern crate aes_gcm;
+extern crate aes_gcm_siv;
+#[cfg(feature = "native")]
 extern crate rusqlite;
 extern crate lazy_static;
 extern crate flexbuffers;
+extern crate zstd;
+extern crate sha2;
+extern crate serde_json;
 
 //
 // Public modules
 //
 
 pub mod datetime;
+pub mod cancel;
+pub mod progress;
+pub mod format;
+pub mod period;
+pub mod seed;
 pub mod location;
 pub mod storage;
 pub mod crypto;
 pub mod error;
 pub mod core;
 pub mod sync;
+pub mod redact;
+pub mod reports;
+
+#[cfg(feature = "native")]
+pub mod setup;
+
+#[cfg(feature = "ffi")]
+pub mod ffi;
+
+#[cfg(any(test, feature = "testing"))]
+pub mod testing;
+
+//
+// Private modules
+//
+
+mod trace;
@@ -0,0 +1,55 @@
+use crate::error::Result;
+use super::location::Location;
+use super::permissions::create_private_dir;
+
+
+/// Environment variable with a path to app's data.
+const ENV_HOME: &str = "BDGT_HOME";
+
+
+/// App's location in an arbitrary directory.
+pub struct CustomLocation {
+    /// Root directory
+    root: std::path::PathBuf,
+}
+
+
+impl CustomLocation {
+    /// Creates a location with a given root directory.
+    /// 
+    /// * `root` - path to root directory
+    pub fn new<P: Into<std::path::PathBuf>>(root: P) -> Self {
+        CustomLocation { 
+            root: root.into() 
+        }
+    }
+
+    /// Creates a location with a root directory taken from 
+    /// `BDGT_HOME` environment variable if it is set.
+    pub fn from_env() -> Option<Self> {
+        std::env::var_os(ENV_HOME)
+            .filter(|root| !root.is_empty())
+            .map(Self::new)
+    }
+}
+
+
+impl Location for CustomLocation {
+    fn root(&self) -> std::path::PathBuf {
+        self.root
+            .clone()
+    }
+
+    fn exists(&self) -> bool {
+        self.root
+            .exists()
+    }
+
+    fn create_if_absent(&self) -> Result<()> {
+        if !self.exists() {
+            create_private_dir(&self.root)?;
+        }
+
+        Ok(())
+    }
+}
@@ -1,40 +1,68 @@
-use crate::error::Result;
+use crate::error::{Result, Error, ErrorKind};
 use super::location::Location;
+use super::permissions::create_private_dir;
+use super::NO_HOME_DIRECTORY;
 
 
 /// Root folder for app's data.
-const ROOT_FOLDER: &str = ".bdgt";
+pub(super) const ROOT_FOLDER: &str = ".bdgt";
 
 
 /// App's location based on current user's home directory.
-pub struct HomeLocation;
+pub struct HomeLocation {
+    /// Root directory resolved at construction time
+    root: std::path::PathBuf,
+}
 
 
 impl HomeLocation {
-    /// Just creates an instance.
-    pub fn new() -> Self {
-        HomeLocation
+    /// Creates an instance.
+    /// 
+    /// Fails if current user's home directory cannot be determined
+    /// (e.g. in minimal containers or service contexts).
+    pub fn new() -> Result<Self> {
+        Ok(HomeLocation {
+            root: home_dir()?.join(ROOT_FOLDER)
+        })
     }
 }
 
 
 impl Location for HomeLocation {
     fn root(&self) -> std::path::PathBuf {
-        dirs::home_dir()
-            .unwrap()
-            .join(ROOT_FOLDER)
+        self.root
+            .clone()
     }
 
     fn exists(&self) -> bool {
-        self.root()
+        self.root
             .exists()
     }
 
     fn create_if_absent(&self) -> Result<()> {
         if !self.exists() {
-            std::fs::create_dir_all(self.root())?;
+            create_private_dir(&self.root)?;
         }
 
         Ok(())
     }
 }
+
+
+/// Returns current user's home directory or an error if it is unavailable.
+pub(super) fn home_dir() -> Result<std::path::PathBuf> {
+    #[cfg(feature = "native")]
+    let home = dirs::home_dir();
+
+    //
+    // Without platform directories only HOME variable is
+    // available (it is absent in browsers at all)
+    //
+
+    #[cfg(not(feature = "native"))]
+    let home = std::env::var_os("HOME")
+        .map(std::path::PathBuf::from)
+        .filter(|path| path.is_absolute());
+
+    home.ok_or_else(|| Error::from_message(NO_HOME_DIRECTORY).with_kind(ErrorKind::NotFound))
+}
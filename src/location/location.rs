@@ -1,4 +1,12 @@
 use crate::error::Result;
+use super::permissions::insecure_paths;
+
+
+/// Default cache folder name.
+const CACHE_FOLDER: &str = "cache";
+
+/// Default runtime folder name.
+const RUNTIME_FOLDER: &str = "run";
 
 
 /// Traits, that manages application's data location.
@@ -6,6 +14,51 @@ pub trait Location {
     /// Get root path of app's data location.
     fn root(&self) -> std::path::PathBuf;
 
+    /// Get path of app's configuration directory.
+    /// 
+    /// By default configuration is stored in root directory.
+    fn config_dir(&self) -> std::path::PathBuf {
+        self.root()
+    }
+
+    /// Get path of app's persistent data directory (DB, sync repository, keys).
+    /// 
+    /// By default data is stored in root directory.
+    fn data_dir(&self) -> std::path::PathBuf {
+        self.root()
+    }
+
+    /// Get path of app's cache directory. Its content can be safely
+    /// removed and excluded from backups.
+    /// 
+    /// By default it is `cache` subdirectory of root directory.
+    fn cache_dir(&self) -> std::path::PathBuf {
+        self.root()
+            .join(CACHE_FOLDER)
+    }
+
+    /// Get path of app's runtime directory (lock files, temporary files).
+    /// 
+    /// By default it is `run` subdirectory of root directory.
+    fn runtime_dir(&self) -> std::path::PathBuf {
+        self.root()
+            .join(RUNTIME_FOLDER)
+    }
+
+    /// Checks if app's directories or files are accessible by other users.
+    /// 
+    /// Returns a list of directories and files, that are readable or
+    /// writable by group or others. Empty list means, that permissions
+    /// are fine.
+    fn check_permissions(&self) -> Result<Vec<std::path::PathBuf>> {
+        insecure_paths(&[
+            self.root(),
+            self.config_dir(),
+            self.data_dir(),
+            self.runtime_dir()
+        ])
+    }
+
     /// Checks if root directory is present.
     fn exists(&self) -> bool;
 
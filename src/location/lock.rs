@@ -0,0 +1,216 @@
+use std::io::Write;
+use std::collections::BTreeMap;
+
+use crate::error::{Result, Error, ErrorKind};
+use super::location::Location;
+use super::permissions::{create_private_dir, restrict_file};
+use super::LOCATION_IN_USE;
+
+
+/// Name of lock file inside of runtime directory.
+const LOCK_FILE: &str = "bdgt.lock";
+
+/// Name of a file, that is held while a stale lock is being removed.
+const BREAK_FILE: &str = "bdgt.lock.break";
+
+
+/// Locks held by current process along with their reference counters.
+/// 
+/// Storage and synchronization engine lock the same location, hence
+/// lock must be reentrant within a process.
+static HELD_LOCKS: std::sync::Mutex<BTreeMap<std::path::PathBuf, usize>> = std::sync::Mutex::new(BTreeMap::new());
+
+
+/// Advisory cross-process lock of app's location.
+/// 
+/// Lock is represented by a file with PID of an owning process inside
+/// of location's runtime directory. Lock is released when the last
+/// instance within a process is dropped. Stale lock files left by
+/// dead processes are removed automatically where liveness of a 
+/// process can be checked, that is Linux only for now. On other 
+/// systems stale locks must be removed with [`LocationLock::force_unlock`].
+pub struct LocationLock {
+    /// Path to lock file
+    path: std::path::PathBuf,
+}
+
+
+impl LocationLock {
+    /// Acquires a lock of a location.
+    /// 
+    /// Fails if location is locked by another process.
+    /// 
+    /// * `loc` - location to lock
+    pub fn acquire<L: Location + ?Sized>(loc: &L) -> Result<Self> {
+        let path = Self::lock_path(loc);
+        let mut held_locks = HELD_LOCKS
+            .lock()
+            .unwrap_or_else(|poisoned| poisoned.into_inner());
+
+        if let Some(counter) = held_locks.get_mut(&path) {
+            *counter += 1;
+            return Ok(LocationLock { path });
+        }
+
+        create_private_dir(loc.runtime_dir())?;
+
+        if !Self::create_lock_file(&path)? {
+            //
+            // Lock file exists, so I need to check if it
+            // is left by a dead process. If so, I remove it and 
+            // try again, otherwise the location is in use.
+            // Lock file without a valid PID may be written by an
+            // older version right now, so it is considered in use
+            //
+
+            match Self::owner(&path) {
+                Some(pid) if !Self::is_alive(pid) => {
+                    if !Self::break_stale_lock(loc, &path, pid)? || !Self::create_lock_file(&path)? {
+                        return Err(Self::in_use(Self::owner(&path)));
+                    }
+                }
+                owner => return Err(Self::in_use(owner))
+            }
+        }
+
+        held_locks.insert(path.clone(), 1);
+        Ok(LocationLock { path })
+    }
+
+    /// Returns PID of a process, that holds a lock of a location, if any.
+    /// 
+    /// * `loc` - location to check
+    pub fn owner_of<L: Location + ?Sized>(loc: &L) -> Option<u32> {
+        Self::owner(&Self::lock_path(loc))
+    }
+
+    /// Forcibly removes a lock of a location.
+    /// 
+    /// Should be used only if a user is sure, that no other process
+    /// uses the location, e.g. after a crash on a system, where
+    /// stale locks cannot be detected automatically.
+    /// 
+    /// * `loc` - location to unlock
+    pub fn force_unlock<L: Location + ?Sized>(loc: &L) -> Result<()> {
+        for path in [Self::lock_path(loc), loc.runtime_dir().join(BREAK_FILE)] {
+            if path.exists() {
+                std::fs::remove_file(path)?;
+            }
+        }
+
+        Ok(())
+    }
+}
+
+
+impl Drop for LocationLock {
+    fn drop(&mut self) {
+        let mut held_locks = HELD_LOCKS
+            .lock()
+            .unwrap_or_else(|poisoned| poisoned.into_inner());
+
+        if let Some(counter) = held_locks.get_mut(&self.path) {
+            *counter -= 1;
+
+            if *counter == 0 {
+                held_locks.remove(&self.path);
+
+                //
+                // Nothing can be done with an error here, and 
+                // a stale lock will be detected on next acquisition
+                //
+
+                let _ = std::fs::remove_file(&self.path);
+            }
+        }
+    }
+}
+
+
+impl LocationLock {
+    fn lock_path<L: Location + ?Sized>(loc: &L) -> std::path::PathBuf {
+        loc.runtime_dir()
+            .join(LOCK_FILE)
+    }
+
+    fn create_lock_file(path: &std::path::Path) -> Result<bool> {
+        //
+        // PID is written to a temporary file first, that is linked
+        // into place then. Linking fails if the lock file exists,
+        // so nobody can see a lock file without PID
+        //
+
+        let temp_path = path.with_extension(format!("lock.{}", std::process::id()));
+
+        let mut file = std::fs::File::create(&temp_path)?;
+        write!(file, "{}", std::process::id())?;
+        file.sync_all()?;
+        restrict_file(&temp_path)?;
+
+        let linked = std::fs::hard_link(&temp_path, path);
+        std::fs::remove_file(&temp_path)?;
+
+        match linked {
+            Ok(()) => Ok(true),
+            Err(error) if error.kind() == std::io::ErrorKind::AlreadyExists => Ok(false),
+            Err(error) => Err(error.into())
+        }
+    }
+
+    fn break_stale_lock<L: Location + ?Sized>(loc: &L, path: &std::path::Path, stale_pid: u32) -> Result<bool> {
+        //
+        // Several processes may find the same stale lock at once.
+        // Only one of them removes it while holding a break file,
+        // and only if the lock still belongs to the dead process.
+        // Otherwise one process could remove a fresh lock of another
+        //
+
+        let break_path = loc.runtime_dir().join(BREAK_FILE);
+        if !Self::create_lock_file(&break_path)? {
+            return Ok(false);
+        }
+
+        let broken = match Self::owner(path) {
+            Some(pid) if pid == stale_pid => std::fs::remove_file(path).map_err(Error::from),
+            _ => Ok(())
+        };
+
+        std::fs::remove_file(&break_path)?;
+        broken.map(|_| true)
+    }
+
+    fn in_use(owner: Option<u32>) -> Error {
+        let owner = owner
+            .map(|pid| pid.to_string())
+            .unwrap_or_else(|| "unknown".to_owned());
+
+        Error::from_message_with_extra(LOCATION_IN_USE, owner).with_kind(ErrorKind::Locked)
+    }
+
+    fn owner(path: &std::path::Path) -> Option<u32> {
+        std::fs::read_to_string(path)
+            .ok()?
+            .trim()
+            .parse()
+            .ok()
+    }
+
+    #[cfg(target_os = "linux")]
+    fn is_alive(pid: u32) -> bool {
+        std::path::Path::new("/proc")
+            .join(pid.to_string())
+            .exists()
+    }
+
+    #[cfg(not(target_os = "linux"))]
+    fn is_alive(_pid: u32) -> bool {
+        //
+        // No portable way to check it without additional 
+        // dependencies, so I assume the process is alive.
+        // Hence stale locks are never removed automatically
+        // here, see `LocationLock::force_unlock`
+        //
+
+        true
+    }
+}
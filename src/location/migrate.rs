@@ -0,0 +1,185 @@
+use crate::error::{Result, Error, ErrorKind};
+use super::location::Location;
+use super::lock::LocationLock;
+use super::permissions::create_private_dir;
+use super::xdg::CONFIG_FILES;
+use super::{SOURCE_LOCATION_NOT_FOUND, TARGET_LOCATION_NOT_EMPTY, LOCATIONS_OVERLAP, MIGRATION_VERIFICATION_FAILED};
+
+
+/// Moves an existing budget from one location into another.
+/// 
+/// Database, configuration, keys and synchronization repository are
+/// copied first and verified byte by byte. Source files are removed
+/// only after successful verification, so a failure leaves the source
+/// location intact. Cache and runtime files are not moved.
+/// 
+/// Both locations are locked during migration, hence the budget must
+/// not be opened by any process.
+/// 
+/// * `from` - location to move budget from
+/// * `to` - location to move budget into
+pub fn migrate<F, T>(from: &F, to: &T) -> Result<()>
+where
+    F: Location + ?Sized,
+    T: Location + ?Sized
+{
+    if !from.exists() {
+        return Err(Error::from_message_with_extra(SOURCE_LOCATION_NOT_FOUND, 
+            from.root().to_string_lossy()).with_kind(ErrorKind::NotFound));
+    }
+
+    let (source_data, target_data) = (from.data_dir(), to.data_dir());
+    if source_data.starts_with(&target_data) || target_data.starts_with(&source_data) {
+        return Err(Error::from_message_with_extra(LOCATIONS_OVERLAP, 
+            target_data.to_string_lossy()).with_kind(ErrorKind::InvalidInput));
+    }
+
+    //
+    // Non-movable directories are excluded from both copying 
+    // and check for emptiness of target location
+    //
+
+    let source_excluded = [from.runtime_dir(), from.cache_dir()];
+    let target_excluded = [to.runtime_dir(), to.cache_dir()];
+
+    if !is_empty(&target_data, &target_excluded)? || has_config(to) {
+        return Err(Error::from_message_with_extra(TARGET_LOCATION_NOT_EMPTY, 
+            target_data.to_string_lossy()).with_kind(ErrorKind::AlreadyExists));
+    }
+
+    let source_lock = LocationLock::acquire(from)?;
+    let _target_lock = LocationLock::acquire(to)?;
+
+    //
+    // Copy everything and verify copies
+    //
+
+    to.create_if_absent()?;
+
+    let mut copied = Vec::new();
+    copy_tree(&source_data, &target_data, &source_excluded, &mut copied)?;
+
+    for file in CONFIG_FILES {
+        let source = from.config_dir().join(file);
+        if source.is_file() && !copied.iter().any(|(copied_source, _)| copied_source == &source) {
+            let target = to.config_dir().join(file);
+            
+            create_private_dir(to.config_dir())?;
+            std::fs::copy(&source, &target)?;
+
+            copied.push((source, target));
+        }
+    }
+
+    for (source, target) in &copied {
+        if std::fs::read(source)? != std::fs::read(target)? {
+            return Err(Error::from_message_with_extra(MIGRATION_VERIFICATION_FAILED, 
+                target.to_string_lossy()).with_kind(ErrorKind::Corruption));
+        }
+    }
+
+    //
+    // Configuration files may be copied into data directory of
+    // target location if source stores them in the same directory.
+    // I move them into their actual directory then
+    //
+
+    if to.config_dir() != target_data {
+        for file in CONFIG_FILES {
+            let misplaced = target_data.join(file);
+            if misplaced.is_file() {
+                create_private_dir(to.config_dir())?;
+                std::fs::rename(&misplaced, to.config_dir().join(file))?;
+            }
+        }
+    }
+
+    //
+    // Now source can be safely removed
+    //
+
+    for (source, _) in &copied {
+        std::fs::remove_file(source)?;
+    }
+
+    drop(source_lock);
+
+    remove_empty_dirs(&source_data)?;
+    remove_empty_dirs(&from.config_dir())?;
+
+    Ok(())
+}
+
+
+fn has_config<L: Location + ?Sized>(loc: &L) -> bool {
+    CONFIG_FILES
+        .iter()
+        .any(|file| loc.config_dir().join(file).exists())
+}
+
+
+fn is_empty(path: &std::path::Path, excluded: &[std::path::PathBuf]) -> Result<bool> {
+    if !path.exists() {
+        return Ok(true);
+    }
+
+    for entry in std::fs::read_dir(path)? {
+        if !excluded.contains(&entry?.path()) {
+            return Ok(false);
+        }
+    }
+
+    Ok(true)
+}
+
+
+pub(super) fn copy_tree(source: &std::path::Path, target: &std::path::Path, excluded: &[std::path::PathBuf], 
+    copied: &mut Vec<(std::path::PathBuf, std::path::PathBuf)>) -> Result<()>
+{
+    create_private_dir(target)?;
+
+    for entry in std::fs::read_dir(source)? {
+        let entry = entry?;
+        let source_path = entry.path();
+        let target_path = target.join(entry.file_name());
+
+        if excluded.contains(&source_path) {
+            continue;
+        }
+
+        if entry.file_type()?.is_dir() {
+            copy_tree(&source_path, &target_path, excluded, copied)?;
+        }
+        else {
+            std::fs::copy(&source_path, &target_path)?;
+            copied.push((source_path, target_path));
+        }
+    }
+
+    Ok(())
+}
+
+
+fn remove_empty_dirs(path: &std::path::Path) -> Result<()> {
+    if !path.is_dir() {
+        return Ok(());
+    }
+
+    for entry in std::fs::read_dir(path)? {
+        let entry = entry?;
+        if entry.file_type()?.is_dir() {
+            remove_empty_dirs(&entry.path())?;
+        }
+    }
+
+    //
+    // Directory may still contain excluded files (e.g. cache),
+    // it is not an error, so I just leave it
+    //
+
+    if std::fs::read_dir(path)?.next().is_none() {
+        std::fs::remove_dir(path)?;
+    }
+
+    Ok(())
+}
@@ -1,5 +1,48 @@
 mod home;
+mod xdg;
+mod custom;
+mod portable;
+mod lock;
+mod permissions;
+mod migrate;
+mod selected;
 mod location;
 
 pub use self::location::Location;
 pub use self::home::HomeLocation;
+pub use self::xdg::XdgLocation;
+pub use self::custom::CustomLocation;
+pub use self::portable::PortableLocation;
+pub use self::lock::LocationLock;
+pub use self::migrate::migrate;
+
+pub(crate) use self::permissions::restrict_file;
+pub use self::selected::SelectedLocation;
+
+#[cfg(feature = "native")]
+pub(crate) use self::permissions::create_private_dir;
+
+
+/// Error message for unknown location kind.
+const UNKNOWN_LOCATION_KIND: &str = "Unknown location kind";
+
+/// Error message for unavailable home directory.
+const NO_HOME_DIRECTORY: &str = "Cannot determine home directory of current user";
+
+/// Error message for location locked by another process.
+const LOCATION_IN_USE: &str = "Budget is in use by another process with PID";
+
+/// Error message for absent source location of migration.
+const SOURCE_LOCATION_NOT_FOUND: &str = "Budget to migrate does not exist";
+
+/// Error message for non-empty target location of migration.
+const TARGET_LOCATION_NOT_EMPTY: &str = "Target location already contains data";
+
+/// Error message for nested locations.
+const LOCATIONS_OVERLAP: &str = "Source and target locations overlap";
+
+/// Error message for failed verification of migrated data.
+const MIGRATION_VERIFICATION_FAILED: &str = "Migrated file differs from the original one";
+
+/// Error message for executable without parent directory.
+const NO_EXECUTABLE_DIRECTORY: &str = "Cannot determine directory of the executable";
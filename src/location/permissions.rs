@@ -0,0 +1,118 @@
+use crate::error::Result;
+
+
+/// Permissions of directories created by the library.
+#[cfg(unix)]
+const PRIVATE_DIR_MODE: u32 = 0o700;
+
+/// Permissions of files created by the library.
+#[cfg(unix)]
+const PRIVATE_FILE_MODE: u32 = 0o600;
+
+/// Permission bits for group and others.
+#[cfg(unix)]
+const FOREIGN_ACCESS_MASK: u32 = 0o077;
+
+
+/// Creates a directory (and its parents if necessary), that is 
+/// accessible by its owner only.
+/// 
+/// Parents are created with default permissions. On Windows directory
+/// inherits ACL of its parent, that is user's profile usually.
+/// 
+/// * `path` - path to directory
+pub(crate) fn create_private_dir<P: AsRef<std::path::Path>>(path: P) -> Result<()> {
+    let path = path.as_ref();
+    if path.exists() {
+        return Ok(());
+    }
+
+    if let Some(parent) = path.parent() {
+        std::fs::create_dir_all(parent)?;
+    }
+
+    let mut builder = std::fs::DirBuilder::new();
+
+    #[cfg(unix)]
+    {
+        use std::os::unix::fs::DirBuilderExt;
+        builder.mode(PRIVATE_DIR_MODE);
+    }
+
+    builder.create(path)?;
+    Ok(())
+}
+
+
+/// Makes a file accessible by its owner only.
+/// 
+/// On Windows file inherits ACL of its parent directory, hence
+/// nothing is done.
+/// 
+/// * `path` - path to file
+pub(crate) fn restrict_file<P: AsRef<std::path::Path>>(path: P) -> Result<()> {
+    #[cfg(unix)]
+    {
+        use std::os::unix::fs::PermissionsExt;
+        std::fs::set_permissions(path, std::fs::Permissions::from_mode(PRIVATE_FILE_MODE))?;
+    }
+
+    #[cfg(not(unix))]
+    let _ = path;
+
+    Ok(())
+}
+
+
+/// Returns paths among provided ones and their direct children, that 
+/// are accessible by users other than owner.
+/// 
+/// Non-existing paths are skipped. On Windows nothing is reported.
+/// 
+/// * `paths` - paths to check
+pub(crate) fn insecure_paths(paths: &[std::path::PathBuf]) -> Result<Vec<std::path::PathBuf>> {
+    let mut result = Vec::new();
+
+    for path in paths.iter().filter(|path| path.exists()) {
+        if is_insecure(path)? && !result.contains(path) {
+            result.push(path.clone());
+        }
+
+        if !path.is_dir() {
+            continue;
+        }
+
+        for entry in std::fs::read_dir(path)? {
+            let entry = entry?.path();
+            if is_insecure(&entry)? && !result.contains(&entry) {
+                result.push(entry);
+            }
+        }
+    }
+
+    Ok(result)
+}
+
+
+#[cfg(unix)]
+fn is_insecure(path: &std::path::Path) -> Result<bool> {
+    use std::os::unix::fs::PermissionsExt;
+
+    //
+    // Symlinks are not followed, because their own
+    // permissions are meaningless
+    //
+
+    let metadata = std::fs::symlink_metadata(path)?;
+    if metadata.file_type().is_symlink() {
+        return Ok(false);
+    }
+
+    Ok(metadata.permissions().mode() & FOREIGN_ACCESS_MASK != 0)
+}
+
+
+#[cfg(not(unix))]
+fn is_insecure(_path: &std::path::Path) -> Result<bool> {
+    Ok(false)
+}
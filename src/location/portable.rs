@@ -0,0 +1,74 @@
+use crate::error::{Result, Error, ErrorKind};
+use super::location::Location;
+use super::permissions::create_private_dir;
+use super::NO_EXECUTABLE_DIRECTORY;
+
+
+/// Name of app's folder next to the executable.
+const PORTABLE_FOLDER: &str = "bdgt-data";
+
+
+/// Portable app's location.
+/// 
+/// All data, configuration, cache and runtime files are stored
+/// in a single directory, e.g. next to the executable on removable
+/// media. Nothing is written to user's home directory.
+pub struct PortableLocation {
+    /// Root directory
+    root: std::path::PathBuf,
+}
+
+
+impl PortableLocation {
+    /// Creates a location in a given directory.
+    /// 
+    /// * `root` - path to root directory
+    pub fn new<P: Into<std::path::PathBuf>>(root: P) -> Self {
+        PortableLocation { 
+            root: root.into() 
+        }
+    }
+
+    /// Creates a location in `bdgt-data` directory next to 
+    /// the current executable.
+    pub fn next_to_executable() -> Result<Self> {
+        let executable = std::env::current_exe()?;
+
+        //
+        // Resolve symlinks to store data next to the real
+        // executable, not next to a link to it
+        //
+
+        let executable = executable
+            .canonicalize()
+            .unwrap_or(executable);
+
+        let directory = executable
+            .parent()
+            .ok_or_else(|| Error::from_message_with_extra(NO_EXECUTABLE_DIRECTORY, 
+                executable.to_string_lossy()).with_kind(ErrorKind::NotFound))?;
+
+        Ok(Self::new(directory.join(PORTABLE_FOLDER)))
+    }
+}
+
+
+impl Location for PortableLocation {
+    fn root(&self) -> std::path::PathBuf {
+        self.root
+            .clone()
+    }
+
+    fn exists(&self) -> bool {
+        self.root
+            .exists()
+    }
+
+    fn create_if_absent(&self) -> Result<()> {
+        if !self.exists() {
+            create_private_dir(&self.root)?;
+        }
+
+        Ok(())
+    }
+}
@@ -0,0 +1,179 @@
+use crate::error::{Result, Error, ErrorKind};
+use super::location::Location;
+use super::home::HomeLocation;
+use super::xdg::XdgLocation;
+use super::custom::CustomLocation;
+use super::portable::PortableLocation;
+use super::permissions::{create_private_dir, restrict_file};
+use super::UNKNOWN_LOCATION_KIND;
+
+
+/// Environment variable with a kind of location to use.
+const ENV_LOCATION: &str = "BDGT_LOCATION";
+
+/// File with a kind of location to use inside of XDG configuration
+/// directory. It cannot be stored in a selected location itself,
+/// hence it is kept in a fixed place.
+const LOCATION_FILE: &str = "location";
+
+/// Supported kinds of locations.
+const LOCATION_KINDS: &[&str] = &["home", "xdg", "portable"];
+
+
+/// Location selected at runtime.
+pub enum SelectedLocation {
+    /// `~/.bdgt` directory
+    Home(HomeLocation),
+
+    /// XDG base directories
+    Xdg(XdgLocation),
+
+    /// Arbitrary directory
+    Custom(CustomLocation),
+
+    /// Directory next to the executable
+    Portable(PortableLocation),
+}
+
+
+impl SelectedLocation {
+    /// Selects a location by its kind name.
+    /// 
+    /// Supported kinds are `home`, `xdg` and `portable`. Legacy directory is
+    /// migrated automatically if XDG location is selected.
+    /// 
+    /// * `kind` - kind of location
+    pub fn from_kind(kind: &str) -> Result<Self> {
+        match kind {
+            "home" => Ok(SelectedLocation::Home(HomeLocation::new()?)),
+            "xdg" => Ok(SelectedLocation::Xdg(XdgLocation::open()?)),
+            "portable" => Ok(SelectedLocation::Portable(PortableLocation::next_to_executable()?)),
+            _ => Err(Error::from_message_with_extra(UNKNOWN_LOCATION_KIND, kind).with_kind(ErrorKind::InvalidInput))
+        }
+    }
+
+    /// Selects a location using environment variables and configuration.
+    /// 
+    /// If `BDGT_HOME` is set, then its value is used as root directory.
+    /// Otherwise, location is selected by `BDGT_LOCATION` variable or, 
+    /// if it is not set, by configured kind (see 
+    /// [`SelectedLocation::set_configured_kind`]). If none of them is
+    /// set, home location is used.
+    pub fn from_env() -> Result<Self> {
+        if let Some(location) = CustomLocation::from_env() {
+            return Ok(SelectedLocation::Custom(location));
+        }
+
+        if let Ok(kind) = std::env::var(ENV_LOCATION) {
+            return Self::from_kind(&kind);
+        }
+
+        match Self::configured_kind()? {
+            Some(kind) => Self::from_kind(&kind),
+            None => Ok(SelectedLocation::Home(HomeLocation::new()?))
+        }
+    }
+
+    /// Returns a kind of location, that is configured to be used
+    /// by default, if any.
+    /// 
+    /// Kind is stored in `location` file inside of XDG configuration
+    /// directory (e.g. `~/.config/bdgt/location`).
+    pub fn configured_kind() -> Result<Option<String>> {
+        let path = Self::location_file()?;
+        if !path.exists() {
+            return Ok(None);
+        }
+
+        let kind = std::fs::read_to_string(path)?
+            .trim()
+            .to_owned();
+
+        Ok(Some(kind).filter(|kind| !kind.is_empty()))
+    }
+
+    /// Configures a kind of location to use by default.
+    /// 
+    /// Environment variables take precedence over configured kind
+    /// (see [`SelectedLocation::from_env`]).
+    /// 
+    /// * `kind` - kind of location (see [`SelectedLocation::from_kind`]) or [`None`] to use the default one
+    pub fn set_configured_kind(kind: Option<&str>) -> Result<()> {
+        let path = Self::location_file()?;
+
+        match kind {
+            Some(kind) => {
+                Self::ensure_known_kind(kind)?;
+
+                if let Some(parent) = path.parent() {
+                    create_private_dir(parent)?;
+                }
+
+                std::fs::write(&path, kind)?;
+                restrict_file(&path)
+            },
+            None if path.exists() => Ok(std::fs::remove_file(path)?),
+            None => Ok(())
+        }
+    }
+
+    fn location_file() -> Result<std::path::PathBuf> {
+        Ok(XdgLocation::new()?
+            .config_dir()
+            .join(LOCATION_FILE))
+    }
+
+    fn ensure_known_kind(kind: &str) -> Result<()> {
+        match LOCATION_KINDS.contains(&kind) {
+            true => Ok(()),
+            false => Err(Error::from_message_with_extra(UNKNOWN_LOCATION_KIND, kind).with_kind(ErrorKind::InvalidInput))
+        }
+    }
+
+    fn inner(&self) -> &dyn Location {
+        match self {
+            SelectedLocation::Home(location) => location,
+            SelectedLocation::Xdg(location) => location,
+            SelectedLocation::Custom(location) => location,
+            SelectedLocation::Portable(location) => location,
+        }
+    }
+}
+
+
+impl Location for SelectedLocation {
+    fn root(&self) -> std::path::PathBuf {
+        self.inner()
+            .root()
+    }
+
+    fn config_dir(&self) -> std::path::PathBuf {
+        self.inner()
+            .config_dir()
+    }
+
+    fn data_dir(&self) -> std::path::PathBuf {
+        self.inner()
+            .data_dir()
+    }
+
+    fn cache_dir(&self) -> std::path::PathBuf {
+        self.inner()
+            .cache_dir()
+    }
+
+    fn runtime_dir(&self) -> std::path::PathBuf {
+        self.inner()
+            .runtime_dir()
+    }
+
+    fn exists(&self) -> bool {
+        self.inner()
+            .exists()
+    }
+
+    fn create_if_absent(&self) -> Result<()> {
+        self.inner()
+            .create_if_absent()
+    }
+}
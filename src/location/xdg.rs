@@ -0,0 +1,168 @@
+use crate::error::Result;
+use super::location::Location;
+use super::permissions::create_private_dir;
+use super::home::{ROOT_FOLDER, home_dir};
+use super::migrate::copy_tree;
+
+
+/// Name of app's folder inside of XDG base directories.
+const APP_FOLDER: &str = "bdgt";
+
+/// Files, that are moved into configuration directory during migration.
+pub(super) const CONFIG_FILES: &[&str] = &["config.toml", "key", "instance"];
+
+
+/// App's location compliant with XDG Base Directory specification.
+///
+/// Data is stored in `$XDG_DATA_HOME/bdgt`, configuration -- in
+/// `$XDG_CONFIG_HOME/bdgt`, cache -- in `$XDG_CACHE_HOME/bdgt` and
+/// runtime files -- in `$XDG_RUNTIME_DIR/bdgt`.
+/// If a variable is not set, the default from the specification is used.
+pub struct XdgLocation {
+    /// Current user's home directory
+    home: std::path::PathBuf,
+}
+
+
+impl XdgLocation {
+    /// Creates an instance.
+    /// 
+    /// Fails if current user's home directory cannot be determined.
+    pub fn new() -> Result<Self> {
+        Ok(XdgLocation {
+            home: home_dir()?
+        })
+    }
+
+    /// Creates an instance and migrates legacy `~/.bdgt` directory
+    /// into XDG directories if necessary.
+    pub fn open() -> Result<Self> {
+        let location = Self::new()?;
+        location.migrate_legacy()?;
+
+        Ok(location)
+    }
+
+    /// Moves legacy `~/.bdgt` directory into XDG directories.
+    ///
+    /// Migration is performed only if legacy directory exists and data
+    /// directory does not. Returns `true` if migration was performed.
+    pub fn migrate_legacy(&self) -> Result<bool> {
+        let legacy_root = self.home
+            .join(ROOT_FOLDER);
+
+        if !legacy_root.exists() || self.exists() {
+            return Ok(false);
+        }
+
+        //
+        // Move the whole directory as data first, and then
+        // move configuration files into their own directory
+        //
+
+        if let Some(parent) = self.root().parent() {
+            std::fs::create_dir_all(parent)?;
+        }
+
+        Self::move_path(&legacy_root, &self.root())?;
+        create_private_dir(self.config_dir())?;
+
+        for file in CONFIG_FILES {
+            let source = self.root().join(file);
+            if source.exists() {
+                Self::move_path(&source, &self.config_dir().join(file))?;
+            }
+        }
+
+        Ok(true)
+    }
+}
+
+
+impl Location for XdgLocation {
+    fn root(&self) -> std::path::PathBuf {
+        self.base_dir("XDG_DATA_HOME", ".local/share")
+            .join(APP_FOLDER)
+    }
+
+    fn config_dir(&self) -> std::path::PathBuf {
+        self.base_dir("XDG_CONFIG_HOME", ".config")
+            .join(APP_FOLDER)
+    }
+
+    fn cache_dir(&self) -> std::path::PathBuf {
+        self.base_dir("XDG_CACHE_HOME", ".cache")
+            .join(APP_FOLDER)
+    }
+
+    fn runtime_dir(&self) -> std::path::PathBuf {
+        //
+        // There is no default value for runtime directory
+        // in the specification, hence I use cache as fallback
+        //
+
+        match std::env::var_os("XDG_RUNTIME_DIR").map(std::path::PathBuf::from) {
+            Some(runtime) if runtime.is_absolute() => runtime.join(APP_FOLDER),
+            _ => self.cache_dir().join("run")
+        }
+    }
+
+    fn exists(&self) -> bool {
+        self.root()
+            .exists()
+    }
+
+    fn create_if_absent(&self) -> Result<()> {
+        create_private_dir(self.root())?;
+        create_private_dir(self.config_dir())?;
+
+        Ok(())
+    }
+}
+
+
+impl XdgLocation {
+    fn move_path(source: &std::path::Path, target: &std::path::Path) -> Result<()> {
+        match std::fs::rename(source, target) {
+            Err(error) if error.kind() == std::io::ErrorKind::CrossesDevices => (),
+            result => return Ok(result?)
+        }
+
+        //
+        // Home and XDG directories may be on different file systems,
+        // so data is copied and then removed. It is copied next to the
+        // target first, so that an interrupted copy is not taken for
+        // migrated data on the next start
+        //
+
+        if source.is_dir() {
+            let temp = target.with_extension("migrating");
+            if temp.exists() {
+                std::fs::remove_dir_all(&temp)?;
+            }
+
+            copy_tree(source, &temp, &[], &mut Vec::new())?;
+            std::fs::rename(&temp, target)?;
+            std::fs::remove_dir_all(source)?;
+        }
+        else {
+            std::fs::copy(source, target)?;
+            std::fs::remove_file(source)?;
+        }
+
+        Ok(())
+    }
+
+    fn base_dir(&self, variable: &str, default: &str) -> std::path::PathBuf {
+        //
+        // According to the specification relative paths
+        // are invalid and should be ignored
+        //
+
+        std::env::var_os(variable)
+            .map(std::path::PathBuf::from)
+            .filter(|path| path.is_absolute())
+            .unwrap_or_else(|| self.home
+                .join(default))
+    }
+}
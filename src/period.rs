@@ -0,0 +1,329 @@
+use chrono::{Datelike, Days, Months, NaiveDate, NaiveDateTime, TimeZone, FixedOffset, Local};
+
+use crate::datetime::Timestamp;
+
+
+/// Policy of mapping timestamps onto calendar days.
+///
+/// Timestamps are stored in UTC, but budgeting periods are calendar
+/// ones, e.g. a purchase made at 23:30 on January 31 belongs to
+/// January, even if it is February 1 in UTC already.
+#[derive(Clone, Copy, PartialEq, Eq, Debug, Default)]
+pub enum TimeZonePolicy {
+    /// Days begin at UTC midnight
+    #[default]
+    Utc,
+
+    /// Days begin at midnight in a fixed offset from UTC
+    Fixed(FixedOffset),
+
+    /// Days begin at midnight in the system time zone
+    /// (daylight saving time is taken into account)
+    Local,
+}
+
+
+impl TimeZonePolicy {
+    /// Returns calendar date of a timestamp.
+    ///
+    /// * `timestamp` - timestamp to return date of
+    pub fn date_of(&self, timestamp: &Timestamp) -> NaiveDate {
+        self.local_time_of(timestamp)
+            .date()
+    }
+
+    /// Returns wall-clock date and time of a timestamp.
+    ///
+    /// * `timestamp` - timestamp to return wall-clock time of
+    pub fn local_time_of(&self, timestamp: &Timestamp) -> NaiveDateTime {
+        match self {
+            TimeZonePolicy::Utc => timestamp.naive_utc(),
+            TimeZonePolicy::Fixed(offset) => timestamp.with_timezone(offset).naive_local(),
+            TimeZonePolicy::Local => timestamp.with_timezone(&Local).naive_local()
+        }
+    }
+
+    /// Returns timestamp of the beginning of a calendar date.
+    ///
+    /// * `date` - calendar date
+    pub fn start_of(&self, date: NaiveDate) -> Timestamp {
+        let midnight = date.and_time(chrono::NaiveTime::MIN);
+
+        match self {
+            TimeZonePolicy::Utc => midnight.and_utc(),
+            TimeZonePolicy::Fixed(offset) => Self::earliest(offset, midnight),
+            TimeZonePolicy::Local => Self::earliest(&Local, midnight)
+        }
+    }
+}
+
+
+impl TimeZonePolicy {
+    fn earliest<Tz: TimeZone>(zone: &Tz, local: NaiveDateTime) -> Timestamp {
+        //
+        // Midnight may be skipped by a daylight saving time transition,
+        // in this case the day begins right after the transition
+        //
+
+        zone.from_local_datetime(&local)
+            .earliest()
+            .map(|start| start.to_utc())
+            .unwrap_or_else(|| Self::earliest(zone, local + chrono::Duration::hours(1)))
+    }
+}
+
+
+/// Budgeting period: a half-open range of time `[start, end)`.
+pub trait Period: Sized {
+    /// Beginning of the period (inclusive).
+    fn start(&self) -> Timestamp;
+
+    /// End of the period (exclusive).
+    fn end(&self) -> Timestamp;
+
+    /// The period, that follows this one.
+    fn next(&self) -> Self;
+
+    /// The period, that precedes this one.
+    fn previous(&self) -> Self;
+
+    /// Checks if a timestamp belongs to the period.
+    ///
+    /// * `timestamp` - timestamp to check
+    fn contains(&self, timestamp: &Timestamp) -> bool {
+        self.start() <= *timestamp && *timestamp < self.end()
+    }
+
+    /// Returns iterator over this period and the following ones,
+    /// that begin before a timestamp.
+    ///
+    /// * `until` - timestamp, that iteration stops at
+    fn until(self, until: Timestamp) -> PeriodIter<Self> {
+        PeriodIter {
+            current: Some(self),
+            until
+        }
+    }
+}
+
+
+/// Calendar month.
+#[derive(Clone, Copy, PartialEq, Eq, Debug)]
+pub struct MonthPeriod {
+    /// The first day of the month
+    first_day: NaiveDate,
+
+    /// Policy of mapping timestamps onto days
+    policy: TimeZonePolicy,
+}
+
+
+impl MonthPeriod {
+    /// Creates a month period.
+    ///
+    /// Returns `None` if the month is invalid.
+    ///
+    /// * `year` - year
+    /// * `month` - month number (1-based)
+    /// * `policy` - policy of mapping timestamps onto days
+    pub fn new(year: i32, month: u32, policy: TimeZonePolicy) -> Option<Self> {
+        Some(MonthPeriod {
+            first_day: NaiveDate::from_ymd_opt(year, month, 1)?,
+            policy
+        })
+    }
+
+    /// Creates a period of a month, that a timestamp belongs to.
+    ///
+    /// * `timestamp` - timestamp within the month
+    /// * `policy` - policy of mapping timestamps onto days
+    pub fn containing(timestamp: &Timestamp, policy: TimeZonePolicy) -> Self {
+        let date = policy.date_of(timestamp);
+
+        MonthPeriod {
+            first_day: date.with_day(1).expect("The first day exists in any month"),
+            policy
+        }
+    }
+
+    /// Returns year of the month.
+    pub fn year(&self) -> i32 {
+        self.first_day.year()
+    }
+
+    /// Returns number of the month (1-based).
+    pub fn month(&self) -> u32 {
+        self.first_day.month()
+    }
+
+    /// Returns the first day of the month.
+    pub fn first_day(&self) -> NaiveDate {
+        self.first_day
+    }
+
+    /// Returns policy of mapping timestamps onto days.
+    pub fn policy(&self) -> TimeZonePolicy {
+        self.policy
+    }
+}
+
+
+impl Period for MonthPeriod {
+    fn start(&self) -> Timestamp {
+        self.policy.start_of(self.first_day)
+    }
+
+    fn end(&self) -> Timestamp {
+        self.next().start()
+    }
+
+    fn next(&self) -> Self {
+        MonthPeriod {
+            first_day: self.first_day + Months::new(1),
+            policy: self.policy
+        }
+    }
+
+    fn previous(&self) -> Self {
+        MonthPeriod {
+            first_day: self.first_day - Months::new(1),
+            policy: self.policy
+        }
+    }
+}
+
+
+/// Calendar week starting on Monday.
+#[derive(Clone, Copy, PartialEq, Eq, Debug)]
+pub struct WeekPeriod {
+    /// Monday of the week
+    first_day: NaiveDate,
+
+    /// Policy of mapping timestamps onto days
+    policy: TimeZonePolicy,
+}
+
+
+impl WeekPeriod {
+    /// Creates a period of a week, that a timestamp belongs to.
+    ///
+    /// * `timestamp` - timestamp within the week
+    /// * `policy` - policy of mapping timestamps onto days
+    pub fn containing(timestamp: &Timestamp, policy: TimeZonePolicy) -> Self {
+        let date = policy.date_of(timestamp);
+        let days_from_monday = date.weekday().num_days_from_monday() as u64;
+
+        WeekPeriod {
+            first_day: date - Days::new(days_from_monday),
+            policy
+        }
+    }
+
+    /// Returns Monday of the week.
+    pub fn first_day(&self) -> NaiveDate {
+        self.first_day
+    }
+}
+
+
+impl Period for WeekPeriod {
+    fn start(&self) -> Timestamp {
+        self.policy.start_of(self.first_day)
+    }
+
+    fn end(&self) -> Timestamp {
+        self.next().start()
+    }
+
+    fn next(&self) -> Self {
+        WeekPeriod {
+            first_day: self.first_day + Days::new(7),
+            policy: self.policy
+        }
+    }
+
+    fn previous(&self) -> Self {
+        WeekPeriod {
+            first_day: self.first_day - Days::new(7),
+            policy: self.policy
+        }
+    }
+}
+
+
+/// Arbitrary range of time. Following and preceding periods
+/// have the same duration.
+#[derive(Clone, Copy, PartialEq, Eq, Debug)]
+pub struct CustomPeriod {
+    /// Beginning of the period (inclusive)
+    start: Timestamp,
+
+    /// End of the period (exclusive)
+    end: Timestamp,
+}
+
+
+impl CustomPeriod {
+    /// Creates a custom period.
+    ///
+    /// Returns `None` if the period is empty.
+    ///
+    /// * `start` - beginning of the period (inclusive)
+    /// * `end` - end of the period (exclusive)
+    pub fn new(start: Timestamp, end: Timestamp) -> Option<Self> {
+        (start < end).then_some(CustomPeriod {
+            start,
+            end
+        })
+    }
+}
+
+
+impl Period for CustomPeriod {
+    fn start(&self) -> Timestamp {
+        self.start
+    }
+
+    fn end(&self) -> Timestamp {
+        self.end
+    }
+
+    fn next(&self) -> Self {
+        CustomPeriod {
+            start: self.end,
+            end: self.end + (self.end - self.start)
+        }
+    }
+
+    fn previous(&self) -> Self {
+        CustomPeriod {
+            start: self.start - (self.end - self.start),
+            end: self.start
+        }
+    }
+}
+
+
+/// Iterator over consecutive periods (see [`Period::until`]).
+pub struct PeriodIter<P> {
+    /// Period to yield next
+    current: Option<P>,
+
+    /// Timestamp, that iteration stops at
+    until: Timestamp,
+}
+
+
+impl<P: Period> Iterator for PeriodIter<P> {
+    type Item = P;
+
+    fn next(&mut self) -> Option<Self::Item> {
+        let current = self.current.take()?;
+        if self.until <= current.start() {
+            return None;
+        }
+
+        self.current = Some(current.next());
+        Some(current)
+    }
+}
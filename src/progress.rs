@@ -0,0 +1,55 @@
+/// Phases of long-running operations.
+#[derive(Clone, Copy, PartialEq, Eq, Debug)]
+#[non_exhaustive]
+pub enum Phase {
+    /// Cloning of a remote repository
+    Clone,
+
+    /// Receiving of remote changes
+    Fetch,
+
+    /// Merging of remote changes and exporting of local ones
+    Merge,
+
+    /// Sending of local changes to remote
+    Push,
+
+    /// Writing of exported items
+    Export,
+}
+
+
+/// Receiver of progress reports from long-running operations.
+///
+/// Reports are sent from the thread, that performs an operation,
+/// so implementations should return quickly, e.g. just update a
+/// progress bar. Any `Fn(Phase, usize, usize)` closure is a valid
+/// receiver.
+pub trait Progress {
+    /// Reports current state of an operation.
+    ///
+    /// * `phase` - current phase of the operation
+    /// * `done` - number of completed units of work in the phase
+    /// * `total` - total number of units of work in the phase (zero if unknown)
+    fn report(&self, phase: Phase, done: usize, total: usize);
+}
+
+
+impl<F> Progress for F
+where
+    F: Fn(Phase, usize, usize)
+{
+    fn report(&self, phase: Phase, done: usize, total: usize) {
+        self(phase, done, total)
+    }
+}
+
+
+/// Receiver, that ignores all reports.
+#[derive(Clone, Copy, Default, Debug)]
+pub struct NoProgress;
+
+
+impl Progress for NoProgress {
+    fn report(&self, _phase: Phase, _done: usize, _total: usize) {}
+}
@@ -0,0 +1,94 @@
+use serde::{Serialize, Deserialize};
+
+use crate::storage::{Plan, Id};
+
+
+/// Number of basis points in one.
+const BASIS_POINTS: isize = 10_000;
+
+
+/// Triggered alert of a plan.
+#[derive(Clone, Serialize, Deserialize)]
+pub struct Alert {
+    /// Plan identifier
+    #[serde(with = "super::id")]
+    pub plan_id: Id,
+
+    /// Identifier of the plan's category
+    #[serde(with = "super::id")]
+    pub category_id: Id,
+
+    /// Plan name
+    pub name: String,
+
+    /// Why the alert is triggered
+    pub kind: AlertKind,
+
+    /// Limit of the plan
+    pub limit: isize,
+
+    /// Amount spent (or earned) within the period (non-negative)
+    pub spent: isize,
+
+    /// Amount projected to be spent by the end of the period
+    /// (non-negative)
+    pub projected: isize,
+}
+
+
+/// Kinds of alerts.
+#[derive(Clone, Copy, PartialEq, Eq, PartialOrd, Ord, Debug, Serialize, Deserialize)]
+pub enum AlertKind {
+    /// Spent amount reached the alert threshold of the plan
+    ThresholdReached,
+
+    /// Spent amount exceeded the limit
+    LimitExceeded,
+
+    /// Spent amount is within the limit, but it is projected to
+    /// exceed the limit by the end of the period at the current pace
+    ProjectedOverrun,
+}
+
+
+impl Alert {
+    /// Checks a plan and returns its triggered alerts. A plan, that
+    /// exceeded its limit, has no other alerts.
+    ///
+    /// * `plan` - plan to check
+    /// * `spent` - amount spent within a period
+    /// * `projected` - amount projected to be spent by the end of the period
+    pub(crate) fn check(plan: &Plan, spent: isize, projected: isize) -> Vec<Alert> {
+        let limit = plan.amount_limit;
+
+        let mut kinds = Vec::new();
+        if spent > limit {
+            kinds.push(AlertKind::LimitExceeded);
+        }
+        else {
+            let threshold_reached = plan.alert_threshold
+                .is_some_and(|alert_threshold| spent * BASIS_POINTS >= limit * alert_threshold);
+
+            if threshold_reached {
+                kinds.push(AlertKind::ThresholdReached);
+            }
+
+            if projected > limit {
+                kinds.push(AlertKind::ProjectedOverrun);
+            }
+        }
+
+        kinds
+            .into_iter()
+            .map(|kind| Alert {
+                plan_id: plan.id.unwrap_or_default(),
+                category_id: plan.category_id,
+                name: plan.name.clone(),
+                kind,
+                limit,
+                spent,
+                projected
+            })
+            .collect()
+    }
+}
@@ -0,0 +1,235 @@
+use std::collections::HashMap;
+
+use serde::{Serialize, Deserialize};
+
+use crate::datetime::Timestamp;
+use crate::storage::{Transaction, Id};
+use crate::core::normalize_payee;
+
+
+/// Minimal number of transactions in a group (a category or a payee),
+/// that makes statistics of the group meaningful.
+const MIN_SAMPLES: usize = 5;
+
+/// Modified z-score, starting from which an amount is an outlier.
+const OUTLIER_SCORE: f64 = 3.5;
+
+/// Factor of the median absolute deviation, that makes it consistent
+/// with a standard deviation of normally distributed amounts.
+const MAD_SCALE: f64 = 1.4826;
+
+/// Minimal spread of amounts relative to the median. Amounts of some
+/// groups never change (e.g. subscriptions), so without it any cent
+/// of difference would be an outlier.
+const MIN_RELATIVE_SPREAD: f64 = 0.05;
+
+/// How many times shorter than usual an interval between transactions
+/// of a payee must be to be unusual (e.g. a duplicated charge).
+const FREQUENCY_FACTOR: f64 = 4.0;
+
+
+/// Transaction, that looks unusual.
+#[derive(Clone, Serialize, Deserialize)]
+pub struct Anomaly {
+    /// Unusual transaction
+    #[serde(with = "super::id")]
+    pub transaction_id: Id,
+
+    /// Timestamp of the transaction
+    pub timestamp: Timestamp,
+
+    /// Category of the transaction
+    #[serde(with = "super::id")]
+    pub category_id: Id,
+
+    /// Amount of the transaction
+    pub amount: isize,
+
+    /// Why the transaction looks unusual (at least one reason)
+    pub reasons: Vec<AnomalyReason>,
+}
+
+
+/// Reason, why a transaction looks unusual.
+///
+/// Scores are modified z-scores: deviations from the median amount
+/// of a group in units of robust standard deviation. Their sign
+/// matches the sign of the deviation.
+#[derive(Clone, Copy, PartialEq, Debug, Serialize, Deserialize)]
+pub enum AnomalyReason {
+    /// Amount is an outlier among transactions of the same category
+    UnusualForCategory {
+        /// Modified z-score of the amount
+        score: f64,
+
+        /// Median amount of the category
+        typical: isize,
+    },
+
+    /// Amount is an outlier among transactions of the same payee
+    UnusualForPayee {
+        /// Modified z-score of the amount
+        score: f64,
+
+        /// Median amount of the payee
+        typical: isize,
+    },
+
+    /// Transaction follows the previous one of the same payee much
+    /// sooner than usual
+    UnusualFrequency {
+        /// Days since the previous transaction of the payee
+        interval_days: f64,
+
+        /// Median interval between transactions of the payee in days
+        typical_days: f64,
+    },
+}
+
+
+/// Looks for unusual transactions within a period, sorted by
+/// timestamp in descending order.
+///
+/// Transactions are compared with all transactions of the same
+/// direction (income or expense) in the same category and with the
+/// same payee (normalized description). Median-based statistics are
+/// used, so that outliers do not hide themselves.
+///
+/// * `start` - beginning of the period (inclusive)
+/// * `end` - end of the period (exclusive)
+/// * `transactions` - historical transactions
+pub(crate) fn detect_anomalies(start: Timestamp, end: Timestamp, transactions: &[Transaction]) -> Vec<Anomaly> {
+    let regular: Vec<_> = transactions
+        .iter()
+        .filter(|transaction| transaction.kind.is_regular() && transaction.id.is_some())
+        .collect();
+
+    let mut categories: HashMap<(Id, bool), Vec<&Transaction>> = HashMap::new();
+    let mut payees: HashMap<(String, bool), Vec<&Transaction>> = HashMap::new();
+
+    for transaction in &regular {
+        let is_income = transaction.amount > 0;
+
+        categories.entry((transaction.category_id, is_income))
+            .or_default()
+            .push(transaction);
+
+        payees.entry((normalize_payee(&transaction.description), is_income))
+            .or_default()
+            .push(transaction);
+    }
+
+    let mut reasons: HashMap<Id, Vec<AnomalyReason>> = HashMap::new();
+    let in_period = |transaction: &Transaction| start <= transaction.timestamp && transaction.timestamp < end;
+
+    for group in categories.values() {
+        for (transaction, score, typical) in amount_outliers(group).filter(|(transaction, ..)| in_period(transaction)) {
+            reasons.entry(transaction.id.unwrap_or_default())
+                .or_default()
+                .push(AnomalyReason::UnusualForCategory { score, typical });
+        }
+    }
+
+    for group in payees.values_mut() {
+        for (transaction, score, typical) in amount_outliers(group).filter(|(transaction, ..)| in_period(transaction)) {
+            reasons.entry(transaction.id.unwrap_or_default())
+                .or_default()
+                .push(AnomalyReason::UnusualForPayee { score, typical });
+        }
+
+        for (transaction, interval_days, typical_days) in frequency_outliers(group).into_iter().filter(|(transaction, ..)| in_period(transaction)) {
+            reasons.entry(transaction.id.unwrap_or_default())
+                .or_default()
+                .push(AnomalyReason::UnusualFrequency { interval_days, typical_days });
+        }
+    }
+
+    let mut anomalies: Vec<_> = regular
+        .into_iter()
+        .filter_map(|transaction| Some(Anomaly {
+            transaction_id: transaction.id?,
+            timestamp: transaction.timestamp,
+            category_id: transaction.category_id,
+            amount: transaction.amount,
+            reasons: reasons.remove(&transaction.id?)?
+        }))
+        .collect();
+
+    anomalies.sort_by(|lhs, rhs| rhs.timestamp.cmp(&lhs.timestamp)
+        .then_with(|| lhs.transaction_id.cmp(&rhs.transaction_id)));
+
+    anomalies
+}
+
+
+/// Returns transactions of a group with outlying amounts together
+/// with their scores and the median amount.
+fn amount_outliers<'a>(group: &'a [&'a Transaction]) -> impl Iterator<Item = (&'a Transaction, f64, isize)> + 'a {
+    let statistics = match group.len() >= MIN_SAMPLES {
+        true => robust_statistics(group.iter().map(|transaction| transaction.amount as f64).collect()),
+        false => None
+    };
+
+    group
+        .iter()
+        .filter_map(move |transaction| {
+            let (median, spread) = statistics?;
+            let score = (transaction.amount as f64 - median) / spread;
+
+            match score.abs() >= OUTLIER_SCORE {
+                true => Some((*transaction, score, median.round() as isize)),
+                false => None
+            }
+        })
+}
+
+
+/// Returns transactions of a group, that follow the previous ones
+/// much sooner than usual, together with intervals and the median
+/// interval in days. Sorts the group by timestamp.
+fn frequency_outliers<'a>(group: &mut [&'a Transaction]) -> Vec<(&'a Transaction, f64, f64)> {
+    if group.len() <= MIN_SAMPLES {
+        return Vec::new();
+    }
+
+    group.sort_by_key(|transaction| transaction.timestamp);
+
+    let intervals: Vec<f64> = group
+        .windows(2)
+        .map(|pair| (pair[1].timestamp - pair[0].timestamp).num_hours() as f64 / 24.0)
+        .collect();
+
+    let Some(typical) = median(intervals.clone()) else {
+        return Vec::new();
+    };
+
+    group[1..]
+        .iter()
+        .zip(intervals)
+        .filter(|(_, interval)| *interval < typical / FREQUENCY_FACTOR)
+        .map(|(transaction, interval)| (*transaction, interval, typical))
+        .collect()
+}
+
+
+/// Returns the median and a robust standard deviation of values.
+fn robust_statistics(values: Vec<f64>) -> Option<(f64, f64)> {
+    let center = median(values.clone())?;
+    let deviation = median(values
+        .iter()
+        .map(|value| (value - center).abs())
+        .collect())?;
+
+    let spread = (deviation * MAD_SCALE)
+        .max(center.abs() * MIN_RELATIVE_SPREAD)
+        .max(1.0);
+
+    Some((center, spread))
+}
+
+
+fn median(mut values: Vec<f64>) -> Option<f64> {
+    values.sort_by(f64::total_cmp);
+    values.get(values.len() / 2)
+        .copied()
+}
@@ -0,0 +1,105 @@
+use std::collections::HashMap;
+
+use serde::{Serialize, Deserialize};
+
+use crate::datetime::Timestamp;
+use crate::storage::{Transaction, Category, Id};
+
+
+/// Average monthly essential spending (cost of living) over several
+/// complete months. Only regular transactions are counted.
+#[derive(Clone, Serialize, Deserialize)]
+pub struct BaselineExpenses {
+    /// Beginning of the first month (inclusive)
+    pub start: Timestamp,
+
+    /// End of the last month (exclusive)
+    pub end: Timestamp,
+
+    /// Number of months
+    pub months: usize,
+
+    /// Average monthly spending in all essential categories (non-negative)
+    pub total: isize,
+
+    /// Essential categories with spending sorted by averages in
+    /// descending order
+    pub categories: Vec<CategoryAverage>,
+}
+
+
+/// Average monthly spending in a category.
+#[derive(Clone, Serialize, Deserialize)]
+pub struct CategoryAverage {
+    /// Category identifier
+    #[serde(with = "super::id")]
+    pub category_id: Id,
+
+    /// Category name
+    pub name: String,
+
+    /// Average monthly spending (non-negative)
+    pub average: isize,
+}
+
+
+impl BaselineExpenses {
+    /// Averages spending in essential categories.
+    ///
+    /// * `start` - beginning of the first month
+    /// * `end` - end of the last month
+    /// * `months` - number of months
+    /// * `essential_categories` - categories, that count as essential
+    /// * `transactions` - transactions of the months
+    pub(crate) fn new(start: Timestamp, end: Timestamp, months: usize, essential_categories: &[Category],
+        transactions: &[Transaction]) -> Self
+    {
+        let mut totals: HashMap<Id, isize> = HashMap::new();
+        for transaction in transactions.iter().filter(|transaction| transaction.kind.is_regular()) {
+            *totals.entry(transaction.category_id).or_default() += transaction.amount;
+        }
+
+        //
+        // Refunds reduce spending, so categories with net
+        // income are not spending at all
+        //
+
+        let mut categories: Vec<_> = essential_categories
+            .iter()
+            .filter_map(|category| {
+                let average = -totals.get(&category.id?)? / months.max(1) as isize;
+
+                match average > 0 {
+                    true => Some(CategoryAverage {
+                        category_id: category.id?,
+                        name: category.name.clone(),
+                        average
+                    }),
+                    false => None
+                }
+            })
+            .collect();
+
+        categories.sort_by(|lhs, rhs| rhs.average.cmp(&lhs.average)
+            .then_with(|| lhs.name.cmp(&rhs.name)));
+
+        BaselineExpenses {
+            start,
+            end,
+            months,
+            total: categories
+                .iter()
+                .map(|category| category.average)
+                .sum(),
+            categories
+        }
+    }
+
+    /// Returns suggested size of an emergency fund, that covers
+    /// essential spending for a number of months.
+    ///
+    /// * `months` - number of months to cover (3 to 6 is common)
+    pub fn emergency_fund(&self, months: usize) -> isize {
+        self.total * months as isize
+    }
+}
@@ -0,0 +1,179 @@
+use std::collections::HashMap;
+
+use serde::{Serialize, Deserialize};
+
+use crate::datetime::Timestamp;
+use crate::storage::{Transaction, Category, CategoryType, Id};
+use super::is_income_or_expense;
+
+
+/// Totals of transactions per category within a period.
+///
+/// Only regular transactions are counted (transfers and balance
+/// adjustments are not incomes or spendings).
+#[derive(Clone, Serialize, Deserialize)]
+pub struct CategoryBreakdown {
+    /// Beginning of the period (inclusive)
+    pub start: Timestamp,
+
+    /// End of the period (exclusive)
+    pub end: Timestamp,
+
+    /// Total income (non-negative)
+    pub income: isize,
+
+    /// Total expenses (non-positive)
+    pub expenses: isize,
+
+    /// Categories with transactions sorted by magnitude of totals
+    /// in descending order
+    pub categories: Vec<CategoryTotal>,
+}
+
+
+/// Total of transactions of one category.
+#[derive(Clone, Serialize, Deserialize)]
+pub struct CategoryTotal {
+    /// Category identifier
+    #[serde(with = "super::id")]
+    pub category_id: Id,
+
+    /// Category name
+    pub name: String,
+
+    /// Category type
+    pub category_type: CategoryType,
+
+    /// Sum of amounts
+    pub total: isize,
+
+    /// Share of total income or expenses from 0 to 1 (depending on
+    /// category type)
+    pub share: f64,
+
+    /// Number of transactions
+    pub transactions: usize,
+}
+
+
+impl CategoryBreakdown {
+    /// Aggregates transactions of a period.
+    ///
+    /// * `start` - beginning of the period
+    /// * `end` - end of the period
+    /// * `categories` - all categories
+    /// * `transactions` - transactions of the period
+    /// * `transfer_income_id` - identifier of incoming transfers category
+    /// * `transfer_outcome_id` - identifier of outgoing transfers category
+    pub(crate) fn new(start: Timestamp, end: Timestamp, categories: &[Category], transactions: &[Transaction],
+        transfer_income_id: Id, transfer_outcome_id: Id) -> Self
+    {
+        let counted: Vec<_> = transactions
+            .iter()
+            .filter(|transaction| is_income_or_expense(transaction, transfer_income_id, transfer_outcome_id))
+            .collect();
+
+        let mut totals: HashMap<Id, (isize, usize)> = HashMap::new();
+        for transaction in &counted {
+            let (total, count) = totals
+                .entry(transaction.category_id)
+                .or_default();
+
+            *total += transaction.amount;
+            *count += 1;
+        }
+
+        let income: isize = counted
+            .iter()
+            .filter(|transaction| transaction.amount > 0)
+            .map(|transaction| transaction.amount)
+            .sum();
+
+        let expenses: isize = counted
+            .iter()
+            .filter(|transaction| transaction.amount < 0)
+            .map(|transaction| transaction.amount)
+            .sum();
+
+        let mut category_totals: Vec<_> = categories
+            .iter()
+            .filter_map(|category| {
+                let (total, count) = totals.get(&category.id?)?;
+                let base = match category.category_type {
+                    CategoryType::Income => income,
+                    CategoryType::Outcome => expenses
+                };
+
+                Some(CategoryTotal {
+                    category_id: category.id?,
+                    name: category.name.clone(),
+                    category_type: category.category_type,
+                    total: *total,
+                    share: share(*total, base),
+                    transactions: *count
+                })
+            })
+            .collect();
+
+        category_totals.sort_by(|lhs, rhs| rhs.total.abs().cmp(&lhs.total.abs())
+            .then_with(|| lhs.name.cmp(&rhs.name)));
+
+        CategoryBreakdown {
+            start,
+            end,
+            income,
+            expenses,
+            categories: category_totals
+        }
+    }
+}
+
+
+fn share(part: isize, whole: isize) -> f64 {
+    match whole {
+        0 => 0.0,
+        whole => part as f64 / whole as f64
+    }
+}
+
+
+#[cfg(test)]
+mod tests {
+    use crate::datetime::Clock;
+    use crate::storage::{CategoryType, Id};
+    use super::super::{test_transaction, test_category};
+    use super::CategoryBreakdown;
+
+    const TRANSFER_INCOME_ID: Id = [0x00; 16];
+    const TRANSFER_OUTCOME_ID: Id = [0xFF; 16];
+
+    #[test]
+    fn transfers_are_not_counted() {
+        let (checking, savings) = ([1; 16], [2; 16]);
+        let (salary, groceries) = ([3; 16], [4; 16]);
+
+        let categories = [
+            test_category(TRANSFER_INCOME_ID, CategoryType::Income),
+            test_category(TRANSFER_OUTCOME_ID, CategoryType::Outcome),
+            test_category(salary, CategoryType::Income),
+            test_category(groceries, CategoryType::Outcome)
+        ];
+
+        let mut transactions = vec![
+            test_transaction(checking, salary, 1000),
+            test_transaction(checking, groceries, -300)
+        ];
+
+        let now = Clock::now();
+        let before = CategoryBreakdown::new(now, now, &categories, &transactions, TRANSFER_INCOME_ID, TRANSFER_OUTCOME_ID);
+
+        transactions.push(test_transaction(checking, TRANSFER_OUTCOME_ID, -500));
+        transactions.push(test_transaction(savings, TRANSFER_INCOME_ID, 500));
+
+        let after = CategoryBreakdown::new(now, now, &categories, &transactions, TRANSFER_INCOME_ID, TRANSFER_OUTCOME_ID);
+
+        assert_eq!((after.income, after.expenses), (1000, -300));
+        assert_eq!((after.income, after.expenses), (before.income, before.expenses));
+        assert_eq!(after.categories.len(), 2);
+    }
+}
@@ -0,0 +1,89 @@
+use sha2::{Sha256, Digest};
+
+use crate::error::Result;
+use crate::location::{Location, restrict_file};
+
+
+/// Folder with cached reports within cache directory.
+const REPORTS_FOLDER: &str = "reports";
+
+/// Extension of cached report files.
+const REPORT_EXTENSION: &str = "bin";
+
+/// Extension of temporary files, that reports are written to.
+const TEMPORARY_EXTENSION: &str = "tmp";
+
+
+/// On-disk cache of reports: one file per query.
+///
+/// The cache stores opaque entries, protection and invalidation of
+/// entries is up to a caller (see [`crate::core::Budget::cached_report`]).
+/// File names are hashes of queries, so queries are not disclosed.
+#[derive(Clone)]
+pub(crate) struct ReportCache {
+    /// Folder with cached reports
+    folder: std::path::PathBuf,
+}
+
+
+impl ReportCache {
+    /// Creates a cache in cache directory of a location.
+    ///
+    /// * `loc` - location of a budget
+    pub(crate) fn new<L: Location + ?Sized>(loc: &L) -> Self {
+        ReportCache {
+            folder: loc.cache_dir().join(REPORTS_FOLDER)
+        }
+    }
+
+    /// Reads an entry of a query if it exists.
+    ///
+    /// * `query` - query, that identifies a report
+    pub(crate) fn read(&self, query: &str) -> Result<Option<Vec<u8>>> {
+        match std::fs::read(self.entry_path(query)) {
+            Ok(entry) => Ok(Some(entry)),
+            Err(error) if error.kind() == std::io::ErrorKind::NotFound => Ok(None),
+            Err(error) => Err(error.into())
+        }
+    }
+
+    /// Replaces an entry of a query.
+    ///
+    /// Entry is written into a temporary file first, so that
+    /// concurrent readers never see a partially written one.
+    ///
+    /// * `query` - query, that identifies a report
+    /// * `entry` - entry to write
+    pub(crate) fn write(&self, query: &str, entry: &[u8]) -> Result<()> {
+        std::fs::create_dir_all(&self.folder)?;
+
+        let path = self.entry_path(query);
+        let temporary = path.with_extension(TEMPORARY_EXTENSION);
+
+        std::fs::write(&temporary, entry)?;
+        restrict_file(&temporary)?;
+        std::fs::rename(&temporary, &path)?;
+
+        Ok(())
+    }
+
+    /// Removes all entries.
+    pub(crate) fn clear(&self) -> Result<()> {
+        match std::fs::remove_dir_all(&self.folder) {
+            Err(error) if error.kind() != std::io::ErrorKind::NotFound => Err(error.into()),
+            _ => Ok(())
+        }
+    }
+
+    fn entry_path(&self, query: &str) -> std::path::PathBuf {
+        let digest = Sha256::digest(query.as_bytes());
+        let name: String = digest
+            .iter()
+            .map(|byte| format!("{:02x}", byte))
+            .collect();
+
+        self.folder
+            .join(name)
+            .with_extension(REPORT_EXTENSION)
+    }
+}
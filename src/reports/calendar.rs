@@ -0,0 +1,178 @@
+use chrono::NaiveDate;
+use serde::{Serialize, Deserialize};
+
+use crate::datetime::Timestamp;
+use crate::period::{Period, MonthPeriod};
+use crate::storage::{Transaction, Account, Loan, Id};
+use super::recurring::RecurringCandidate;
+
+
+/// Aggregates of a calendar day.
+#[derive(Clone, Serialize, Deserialize)]
+pub struct CalendarDay {
+    /// Calendar date
+    pub date: NaiveDate,
+
+    /// Total income of the day (non-negative)
+    pub income: isize,
+
+    /// Total expenses of the day (non-positive)
+    pub expenses: isize,
+
+    /// Number of transactions of the day
+    pub transactions: usize,
+
+    /// Expected payments of the day, for today and later days only
+    pub due: Vec<DueItem>,
+
+    /// Total balance of accounts at the end of the day: actual one
+    /// for past days and projected one with expected payments for
+    /// today and later days
+    pub balance: isize,
+
+    /// If the balance is projected
+    pub is_projected: bool,
+}
+
+
+/// Expected payment.
+#[derive(Clone, Serialize, Deserialize)]
+pub struct DueItem {
+    /// Where the payment comes from
+    pub kind: DueItemKind,
+
+    /// Description of a recurring transaction or name of a loan
+    pub description: String,
+
+    /// Account of the payment
+    #[serde(with = "super::id")]
+    pub account_id: Id,
+
+    /// Expected amount
+    pub amount: isize,
+
+    /// Expected timestamp
+    pub timestamp: Timestamp,
+}
+
+
+/// Sources of expected payments.
+#[derive(Clone, Copy, PartialEq, Eq, Debug, Serialize, Deserialize)]
+pub enum DueItemKind {
+    /// Recurring transaction (see [`crate::core::Budget::detect_recurring`])
+    Recurring,
+
+    /// Payment of a loan according to its amortization schedule
+    LoanPayment,
+}
+
+
+/// Builds aggregates of each day of a month.
+///
+/// * `month` - month to build calendar of
+/// * `now` - current time point
+/// * `accounts` - all accounts
+/// * `transactions` - all transactions
+/// * `recurring` - recurring transactions
+/// * `loans` - all loans
+pub(crate) fn calendar(month: &MonthPeriod, now: Timestamp, accounts: &[Account], transactions: &[Transaction],
+    recurring: &[RecurringCandidate], loans: &[Loan]) -> Vec<CalendarDay>
+{
+    let policy = month.policy();
+    let today = policy.date_of(&now);
+
+    let mut due = Vec::new();
+    for candidate in recurring {
+        let mut timestamp = candidate.next_timestamp;
+        while timestamp < month.end() {
+            if month.start() <= timestamp && policy.date_of(&timestamp) >= today {
+                due.push(DueItem {
+                    kind: DueItemKind::Recurring,
+                    description: candidate.description.clone(),
+                    account_id: candidate.account_id,
+                    amount: candidate.amount,
+                    timestamp
+                });
+            }
+
+            timestamp = candidate.periodicity.next_after(timestamp);
+        }
+    }
+
+    for loan in loans {
+        let payments = loan.amortization_schedule()
+            .into_iter()
+            .filter(|entry| month.contains(&entry.timestamp) && policy.date_of(&entry.timestamp) >= today);
+
+        for entry in payments {
+            due.push(DueItem {
+                kind: DueItemKind::LoanPayment,
+                description: loan.name.clone(),
+                account_id: loan.account_id,
+                amount: -entry.payment,
+                timestamp: entry.timestamp
+            });
+        }
+    }
+
+    due.sort_by_key(|item| item.timestamp);
+
+    let balance: isize = accounts
+        .iter()
+        .map(|account| account.balance)
+        .sum();
+
+    let mut days = Vec::new();
+    let mut date = month.first_day();
+    while policy.start_of(date) < month.end() {
+        let Some(next_date) = date.succ_opt() else {
+            break;
+        };
+
+        let day_end = policy.start_of(next_date);
+        let mut day = CalendarDay {
+            date,
+            income: 0,
+            expenses: 0,
+            transactions: 0,
+            due: Vec::new(),
+            balance,
+            is_projected: day_end > now
+        };
+
+        //
+        // Current balance includes all transactions, even ones
+        // dated in the future, so later ones are subtracted
+        //
+
+        for transaction in transactions {
+            if transaction.timestamp >= day_end {
+                day.balance -= transaction.amount;
+            }
+            else if policy.date_of(&transaction.timestamp) == date {
+                day.transactions += 1;
+
+                match transaction.kind.is_regular() {
+                    true if transaction.amount > 0 => day.income += transaction.amount,
+                    true => day.expenses += transaction.amount,
+                    false => ()
+                }
+            }
+        }
+
+        for item in &due {
+            if item.timestamp < day_end {
+                day.balance += item.amount;
+            }
+
+            if policy.date_of(&item.timestamp) == date {
+                day.due.push(item.clone());
+            }
+        }
+
+        days.push(day);
+        date = next_date;
+    }
+
+    days
+}
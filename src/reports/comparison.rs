@@ -0,0 +1,148 @@
+use serde::{Serialize, Deserialize};
+
+use crate::datetime::Timestamp;
+use crate::storage::{CategoryType, Id};
+use super::breakdown::CategoryBreakdown;
+
+
+/// Comparison of two periods, e.g. this month vs. the last one.
+///
+/// Changes are computed from the second period to the first one,
+/// i.e. the second period is a base of comparison.
+#[derive(Clone, Serialize, Deserialize)]
+pub struct PeriodComparison {
+    /// Beginning of the first period (inclusive)
+    pub start: Timestamp,
+
+    /// End of the first period (exclusive)
+    pub end: Timestamp,
+
+    /// Beginning of the base period (inclusive)
+    pub base_start: Timestamp,
+
+    /// End of the base period (exclusive)
+    pub base_end: Timestamp,
+
+    /// Change of total income
+    pub income: Delta,
+
+    /// Change of total expenses
+    pub expenses: Delta,
+
+    /// Changes of categories, that have transactions in at least one
+    /// of the periods, sorted by magnitude of changes in descending order
+    pub categories: Vec<CategoryDelta>,
+}
+
+
+/// Change of an amount between two periods.
+#[derive(Clone, Copy, PartialEq, Serialize, Deserialize)]
+pub struct Delta {
+    /// Amount in the first period
+    pub value: isize,
+
+    /// Amount in the base period
+    pub base: isize,
+
+    /// Difference between the amounts
+    pub change: isize,
+
+    /// Change relative to magnitude of the base amount in percents,
+    /// absent if the base amount is zero
+    pub percent: Option<f64>,
+}
+
+
+/// Change of a category total between two periods.
+#[derive(Clone, Serialize, Deserialize)]
+pub struct CategoryDelta {
+    /// Category identifier
+    #[serde(with = "super::id")]
+    pub category_id: Id,
+
+    /// Category name
+    pub name: String,
+
+    /// Category type
+    pub category_type: CategoryType,
+
+    /// Change of the total, amount is zero for a period without
+    /// transactions in the category
+    pub delta: Delta,
+}
+
+
+impl Delta {
+    /// Computes change between two amounts.
+    ///
+    /// * `value` - amount in the first period
+    /// * `base` - amount in the base period
+    pub(crate) fn new(value: isize, base: isize) -> Self {
+        let change = value - base;
+        let percent = match base {
+            0 => None,
+            base => Some(change as f64 * 100.0 / base.abs() as f64)
+        };
+
+        Delta {
+            value,
+            base,
+            change,
+            percent
+        }
+    }
+}
+
+
+impl PeriodComparison {
+    /// Compares breakdowns of two periods.
+    ///
+    /// * `breakdown` - breakdown of the first period
+    /// * `base_breakdown` - breakdown of the base period
+    pub(crate) fn new(breakdown: CategoryBreakdown, base_breakdown: CategoryBreakdown) -> Self {
+        let base_total = |category_id: &Id| base_breakdown.categories
+            .iter()
+            .find(|base| base.category_id == *category_id)
+            .map_or(0, |base| base.total);
+
+        let mut categories: Vec<_> = breakdown.categories
+            .iter()
+            .map(|category| CategoryDelta {
+                category_id: category.category_id,
+                name: category.name.clone(),
+                category_type: category.category_type,
+                delta: Delta::new(category.total, base_total(&category.category_id))
+            })
+            .collect();
+
+        //
+        // Categories without transactions in the first period
+        // are missing in its breakdown
+        //
+
+        let missing: Vec<_> = base_breakdown.categories
+            .iter()
+            .filter(|base| breakdown.categories.iter().all(|category| category.category_id != base.category_id))
+            .map(|base| CategoryDelta {
+                category_id: base.category_id,
+                name: base.name.clone(),
+                category_type: base.category_type,
+                delta: Delta::new(0, base.total)
+            })
+            .collect();
+
+        categories.extend(missing);
+        categories.sort_by(|lhs, rhs| rhs.delta.change.abs().cmp(&lhs.delta.change.abs())
+            .then_with(|| lhs.name.cmp(&rhs.name)));
+
+        PeriodComparison {
+            start: breakdown.start,
+            end: breakdown.end,
+            base_start: base_breakdown.start,
+            base_end: base_breakdown.end,
+            income: Delta::new(breakdown.income, base_breakdown.income),
+            expenses: Delta::new(breakdown.expenses, base_breakdown.expenses),
+            categories
+        }
+    }
+}
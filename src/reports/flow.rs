@@ -0,0 +1,235 @@
+use std::collections::BTreeMap;
+
+use serde::{Serialize, Deserialize};
+
+use crate::datetime::Timestamp;
+use crate::storage::{Transaction, Category, Account, CategoryType, Id};
+use super::is_income_or_expense;
+
+
+/// Flows of money within a period: from income categories into
+/// accounts and from accounts into expense categories. The layout
+/// matches Sankey diagrams: nodes are referenced by edges by indices.
+///
+/// Only incomes and expenses are taken into account (transfers
+/// between own accounts and balance adjustments are not). Amounts are
+/// netted per category and account, so that refunds reduce flows
+/// instead of reversing them, and the graph has no cycles.
+#[derive(Clone, Serialize, Deserialize)]
+pub struct FlowReport {
+    /// Beginning of the period (inclusive)
+    pub start: Timestamp,
+
+    /// End of the period (exclusive)
+    pub end: Timestamp,
+
+    /// Nodes: income categories, then accounts, then expense categories
+    pub nodes: Vec<FlowNode>,
+
+    /// Edges with positive amounts
+    pub edges: Vec<FlowEdge>,
+}
+
+
+/// Node of a flow graph.
+#[derive(Clone, Serialize, Deserialize)]
+pub struct FlowNode {
+    /// Kind of the node
+    pub kind: FlowNodeKind,
+
+    /// Identifier of a category or an account
+    #[serde(with = "super::id")]
+    pub item_id: Id,
+
+    /// Name of a category or an account
+    pub name: String,
+}
+
+
+/// Kinds of nodes of a flow graph.
+#[derive(Clone, Copy, PartialEq, Eq, PartialOrd, Ord, Debug, Serialize, Deserialize)]
+pub enum FlowNodeKind {
+    /// Income category, money flows from it
+    IncomeSource,
+
+    /// Account, money flows through it
+    Account,
+
+    /// Expense category, money flows into it
+    Expense,
+}
+
+
+/// Edge of a flow graph.
+#[derive(Clone, Copy, Serialize, Deserialize)]
+pub struct FlowEdge {
+    /// Index of a source node
+    pub source: usize,
+
+    /// Index of a target node
+    pub target: usize,
+
+    /// Amount of money (positive)
+    pub amount: isize,
+}
+
+
+impl FlowReport {
+    /// Builds flows of transactions of a period.
+    ///
+    /// * `start` - beginning of the period
+    /// * `end` - end of the period
+    /// * `categories` - all categories
+    /// * `accounts` - all accounts
+    /// * `transactions` - transactions of the period
+    /// * `transfer_income_id` - identifier of incoming transfers category
+    /// * `transfer_outcome_id` - identifier of outgoing transfers category
+    pub(crate) fn new(start: Timestamp, end: Timestamp, categories: &[Category], accounts: &[Account], transactions: &[Transaction],
+        transfer_income_id: Id, transfer_outcome_id: Id) -> Self
+    {
+        let mut net: BTreeMap<(Id, Id), isize> = BTreeMap::new();
+        for transaction in transactions.iter().filter(|transaction| is_income_or_expense(transaction, transfer_income_id, transfer_outcome_id)) {
+            *net.entry((transaction.category_id, transaction.account_id))
+                .or_default() += transaction.amount;
+        }
+
+        //
+        // Direction of a flow is defined by category type,
+        // flows, that are netted out, are dropped
+        //
+
+        let category_type = |category_id: &Id| categories
+            .iter()
+            .find(|category| category.id == Some(*category_id))
+            .map(|category| category.category_type);
+
+        let flows: Vec<_> = net
+            .into_iter()
+            .filter_map(|((category_id, account_id), amount)| match category_type(&category_id)? {
+                CategoryType::Income if amount > 0 => Some((FlowNodeKind::IncomeSource, category_id, account_id, amount)),
+                CategoryType::Outcome if amount < 0 => Some((FlowNodeKind::Expense, category_id, account_id, -amount)),
+                _ => None
+            })
+            .collect();
+
+        let mut nodes: Vec<FlowNode> = Vec::new();
+        let mut node_index = |kind: FlowNodeKind, item_id: Id| -> usize {
+            if let Some(index) = nodes.iter().position(|node| node.kind == kind && node.item_id == item_id) {
+                return index;
+            }
+
+            let name = match kind {
+                FlowNodeKind::Account => accounts
+                    .iter()
+                    .find(|account| account.id == Some(item_id))
+                    .map(|account| account.name.clone()),
+                _ => categories
+                    .iter()
+                    .find(|category| category.id == Some(item_id))
+                    .map(|category| category.name.clone())
+            };
+
+            nodes.push(FlowNode {
+                kind,
+                item_id,
+                name: name.unwrap_or_default()
+            });
+
+            nodes.len() - 1
+        };
+
+        let edges: Vec<_> = flows
+            .into_iter()
+            .map(|(kind, category_id, account_id, amount)| {
+                let category = node_index(kind, category_id);
+                let account = node_index(FlowNodeKind::Account, account_id);
+
+                match kind {
+                    FlowNodeKind::IncomeSource => FlowEdge { source: category, target: account, amount },
+                    _ => FlowEdge { source: account, target: category, amount }
+                }
+            })
+            .collect();
+
+        Self::ordered(start, end, nodes, edges)
+    }
+}
+
+
+impl FlowReport {
+    fn ordered(start: Timestamp, end: Timestamp, nodes: Vec<FlowNode>, edges: Vec<FlowEdge>) -> Self {
+        //
+        // Nodes are laid out by layers (and by names within a layer),
+        // edges are renumbered accordingly
+        //
+
+        let mut order: Vec<usize> = (0..nodes.len()).collect();
+        order.sort_by(|lhs, rhs| nodes[*lhs].kind.cmp(&nodes[*rhs].kind)
+            .then_with(|| nodes[*lhs].name.cmp(&nodes[*rhs].name)));
+
+        let mut new_index = vec![0; nodes.len()];
+        for (index, old_index) in order.iter().enumerate() {
+            new_index[*old_index] = index;
+        }
+
+        let mut edges: Vec<_> = edges
+            .into_iter()
+            .map(|edge| FlowEdge {
+                source: new_index[edge.source],
+                target: new_index[edge.target],
+                amount: edge.amount
+            })
+            .collect();
+
+        edges.sort_by_key(|edge| (edge.source, edge.target));
+
+        let nodes = order
+            .into_iter()
+            .map(|index| nodes[index].clone())
+            .collect();
+
+        FlowReport {
+            start,
+            end,
+            nodes,
+            edges
+        }
+    }
+}
+
+
+#[cfg(test)]
+mod tests {
+    use crate::datetime::Clock;
+    use crate::storage::{CategoryType, Id};
+    use super::super::{test_transaction, test_category};
+    use super::FlowReport;
+
+    const TRANSFER_INCOME_ID: Id = [0x00; 16];
+    const TRANSFER_OUTCOME_ID: Id = [0xFF; 16];
+
+    #[test]
+    fn transfers_are_not_flows() {
+        let (checking, savings) = ([1; 16], [2; 16]);
+        let groceries = [3; 16];
+
+        let categories = [
+            test_category(TRANSFER_INCOME_ID, CategoryType::Income),
+            test_category(TRANSFER_OUTCOME_ID, CategoryType::Outcome),
+            test_category(groceries, CategoryType::Outcome)
+        ];
+
+        let transactions = [
+            test_transaction(checking, groceries, -300),
+            test_transaction(checking, TRANSFER_OUTCOME_ID, -500),
+            test_transaction(savings, TRANSFER_INCOME_ID, 500)
+        ];
+
+        let now = Clock::now();
+        let report = FlowReport::new(now, now, &categories, &[], &transactions, TRANSFER_INCOME_ID, TRANSFER_OUTCOME_ID);
+
+        assert_eq!(report.edges.len(), 1);
+        assert_eq!(report.edges[0].amount, 300);
+        assert!(report.nodes.iter().all(|node| node.item_id != TRANSFER_INCOME_ID && node.item_id != TRANSFER_OUTCOME_ID));
+    }
+}
@@ -0,0 +1,20 @@
+use serde::{Serialize, Deserialize};
+
+use crate::datetime::Timestamp;
+
+
+/// Projected state of cash at a time point.
+#[derive(Clone, Copy, Serialize, Deserialize)]
+pub struct ForecastPoint {
+    /// Time point (end of a month)
+    pub timestamp: Timestamp,
+
+    /// Projected total balance of accounts
+    pub balance: isize,
+
+    /// Projected income since the previous point (non-negative)
+    pub income: isize,
+
+    /// Projected expenses since the previous point (non-positive)
+    pub expenses: isize,
+}
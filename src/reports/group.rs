@@ -0,0 +1,115 @@
+use std::collections::HashMap;
+
+use serde::{Serialize, Deserialize};
+
+use crate::datetime::Timestamp;
+use crate::storage::{CategoryType, CategoryGroup, Id};
+use super::CategoryBreakdown;
+
+
+/// Totals of transactions per group of categories within a period.
+///
+/// Totals are rolled up from [`CategoryBreakdown`], hence only
+/// regular transactions are counted.
+#[derive(Clone, Serialize, Deserialize)]
+pub struct GroupBreakdown {
+    /// Beginning of the period (inclusive)
+    pub start: Timestamp,
+
+    /// End of the period (exclusive)
+    pub end: Timestamp,
+
+    /// Groups with transactions sorted by magnitude of expenses
+    /// in descending order
+    pub groups: Vec<GroupTotal>,
+}
+
+
+/// Totals of transactions of one group of categories.
+#[derive(Clone, Serialize, Deserialize)]
+pub struct GroupTotal {
+    /// Group identifier (`None` for categories without a group)
+    #[serde(with = "super::id::option")]
+    pub group_id: Option<Id>,
+
+    /// Group name
+    pub name: Option<String>,
+
+    /// Total of income categories (non-negative)
+    pub income: isize,
+
+    /// Total of expense categories (non-positive)
+    pub expenses: isize,
+
+    /// Share of total expenses from 0 to 1
+    pub share: f64,
+
+    /// Number of transactions
+    pub transactions: usize,
+
+    /// Categories of the group with transactions
+    #[serde(with = "super::id::list")]
+    pub categories: Vec<Id>,
+}
+
+
+impl GroupBreakdown {
+    /// Rolls up totals of categories into their groups.
+    ///
+    /// * `breakdown` - totals per category
+    /// * `groups` - all groups of categories
+    pub(crate) fn new(breakdown: &CategoryBreakdown, groups: &[CategoryGroup]) -> Self {
+        let group_of: HashMap<Id, &CategoryGroup> = groups
+            .iter()
+            .flat_map(|group| group.categories
+                .iter()
+                .map(move |category| (*category, group)))
+            .collect();
+
+        let mut totals: HashMap<Option<Id>, GroupTotal> = HashMap::new();
+        for category in &breakdown.categories {
+            let group = group_of.get(&category.category_id);
+            let group_id = group.and_then(|group| group.id);
+            let total = totals
+                .entry(group_id)
+                .or_insert_with(|| GroupTotal {
+                    group_id,
+                    name: group.map(|group| group.name.clone()),
+                    income: 0,
+                    expenses: 0,
+                    share: 0.0,
+                    transactions: 0,
+                    categories: Vec::new()
+                });
+
+            match category.category_type {
+                CategoryType::Income => total.income += category.total,
+                CategoryType::Outcome => total.expenses += category.total
+            }
+
+            total.transactions += category.transactions;
+            total.categories.push(category.category_id);
+        }
+
+        let mut group_totals: Vec<_> = totals
+            .into_values()
+            .map(|mut total| {
+                total.share = match breakdown.expenses {
+                    0 => 0.0,
+                    expenses => total.expenses as f64 / expenses as f64
+                };
+
+                total
+            })
+            .collect();
+
+        group_totals.sort_by(|lhs, rhs| rhs.expenses.abs().cmp(&lhs.expenses.abs())
+            .then_with(|| lhs.name.cmp(&rhs.name)));
+
+        GroupBreakdown {
+            start: breakdown.start,
+            end: breakdown.end,
+            groups: group_totals
+        }
+    }
+}
@@ -0,0 +1,72 @@
+//! Serialization of identifiers as UUID strings, that are
+//! readable and stable in JSON unlike arrays of bytes.
+
+use serde::{Serializer, Deserializer, Deserialize};
+
+use crate::storage::Id;
+
+
+pub(crate) fn serialize<S: Serializer>(id: &Id, serializer: S) -> std::result::Result<S::Ok, S::Error> {
+    serializer.collect_str(&uuid::Uuid::from_bytes(*id))
+}
+
+
+pub(crate) fn deserialize<'de, D: Deserializer<'de>>(deserializer: D) -> std::result::Result<Id, D::Error> {
+    let id = String::deserialize(deserializer)?;
+
+    uuid::Uuid::parse_str(&id)
+        .map(uuid::Uuid::into_bytes)
+        .map_err(serde::de::Error::custom)
+}
+
+
+/// Serialization of lists of identifiers as lists of UUID strings.
+pub(crate) mod list {
+    use serde::{Serializer, Deserializer, Deserialize};
+    use serde::ser::SerializeSeq;
+
+    use crate::storage::Id;
+
+
+    pub(crate) fn serialize<S: Serializer>(ids: &[Id], serializer: S) -> std::result::Result<S::Ok, S::Error> {
+        let mut sequence = serializer.serialize_seq(Some(ids.len()))?;
+        for id in ids {
+            sequence.serialize_element(&uuid::Uuid::from_bytes(*id).to_string())?;
+        }
+
+        sequence.end()
+    }
+
+
+    pub(crate) fn deserialize<'de, D: Deserializer<'de>>(deserializer: D) -> std::result::Result<Vec<Id>, D::Error> {
+        Vec::<String>::deserialize(deserializer)?
+            .iter()
+            .map(|id| uuid::Uuid::parse_str(id).map(uuid::Uuid::into_bytes))
+            .collect::<std::result::Result<_, _>>()
+            .map_err(serde::de::Error::custom)
+    }
+}
+
+
+/// Serialization of optional identifiers as UUID strings or nulls.
+pub(crate) mod option {
+    use serde::{Serializer, Deserializer, Deserialize};
+
+    use crate::storage::Id;
+
+
+    pub(crate) fn serialize<S: Serializer>(id: &Option<Id>, serializer: S) -> std::result::Result<S::Ok, S::Error> {
+        match id {
+            Some(id) => serializer.collect_str(&uuid::Uuid::from_bytes(*id)),
+            None => serializer.serialize_none()
+        }
+    }
+
+
+    pub(crate) fn deserialize<'de, D: Deserializer<'de>>(deserializer: D) -> std::result::Result<Option<Id>, D::Error> {
+        Option::<String>::deserialize(deserializer)?
+            .map(|id| uuid::Uuid::parse_str(&id).map(uuid::Uuid::into_bytes))
+            .transpose()
+            .map_err(serde::de::Error::custom)
+    }
+}
@@ -0,0 +1,160 @@
+//! Reports: aggregated views of a budget.
+//!
+//! Reports are built by [`crate::core::Budget`] (e.g. with
+//! [`crate::core::Budget::category_breakdown`]). All report types
+//! implement [`serde::Serialize`] and [`serde::Deserialize`], so they
+//! can be passed to frontends and scripts as is, e.g. as JSON (see
+//! [`to_json`]). Field names are stable, identifiers are serialized
+//! as UUID strings and timestamps as RFC 3339 strings.
+
+mod id;
+mod breakdown;
+mod net_worth;
+mod status;
+mod forecast;
+mod comparison;
+mod flow;
+mod recurring;
+mod anomaly;
+mod statement;
+mod calendar;
+mod alert;
+mod projection;
+mod baseline;
+mod cache;
+mod spender;
+mod group;
+
+pub use self::breakdown::{CategoryBreakdown, CategoryTotal};
+pub use self::net_worth::{NetWorthSeries, NetWorthPoint};
+pub use self::status::BudgetStatus;
+pub use self::forecast::ForecastPoint;
+pub use self::comparison::{PeriodComparison, Delta, CategoryDelta};
+pub use self::flow::{FlowReport, FlowNode, FlowNodeKind, FlowEdge};
+pub use self::recurring::{RecurringCandidate, Periodicity};
+pub use self::anomaly::{Anomaly, AnomalyReason};
+pub use self::statement::{Statement, StatementLine, StatementLineKind};
+pub use self::calendar::{CalendarDay, DueItem, DueItemKind};
+pub use self::alert::{Alert, AlertKind};
+pub use self::projection::SpendProjection;
+pub use self::baseline::{BaselineExpenses, CategoryAverage};
+pub use self::spender::{SpenderBreakdown, SpenderTotal};
+pub use self::group::{GroupBreakdown, GroupTotal};
+
+pub(crate) use self::recurring::detect_recurring;
+pub(crate) use self::anomaly::detect_anomalies;
+pub(crate) use self::calendar::calendar;
+pub(crate) use self::cache::ReportCache;
+
+use crate::error::Result;
+use crate::storage::{Transaction, Id};
+
+#[cfg(feature = "parallel")]
+use rayon::prelude::*;
+
+
+/// Serializes a report into a pretty-printed JSON.
+///
+/// * `report` - report to serialize
+pub fn to_json<T: serde::Serialize + ?Sized>(report: &T) -> Result<String> {
+    serde_json::to_string_pretty(report)
+        .map_err(Into::into)
+}
+
+
+/// Checks if a transaction is an income or an expense, i.e. it is
+/// neither a transfer between own accounts nor a balance adjustment.
+///
+/// Transfers are regular transactions in special categories (see
+/// [`crate::core::Budget::add_transfer`]), so they are recognized by
+/// their categories.
+///
+/// * `transaction` - transaction to check
+/// * `transfer_income_id` - identifier of incoming transfers category
+/// * `transfer_outcome_id` - identifier of outgoing transfers category
+pub(crate) fn is_income_or_expense(transaction: &Transaction, transfer_income_id: Id, transfer_outcome_id: Id) -> bool {
+    transaction.kind.is_regular() &&
+        transaction.category_id != transfer_income_id &&
+        transaction.category_id != transfer_outcome_id
+}
+
+
+/// Maps items independently, in parallel if `parallel` feature is enabled.
+///
+/// Storage and cryptographic engines are not touched by workers:
+/// data is read and decrypted beforehand by a caller.
+///
+/// * `items` - items to map
+/// * `f` - mapping function
+#[cfg(feature = "parallel")]
+pub(crate) fn map_items<T, R, F>(items: &[T], f: F) -> Vec<R>
+where
+    T: Sync,
+    R: Send,
+    F: Fn(&T) -> R + Sync + Send
+{
+    items
+        .par_iter()
+        .map(f)
+        .collect()
+}
+
+
+/// Maps items independently, in parallel if `parallel` feature is enabled.
+///
+/// * `items` - items to map
+/// * `f` - mapping function
+#[cfg(not(feature = "parallel"))]
+pub(crate) fn map_items<T, R, F>(items: &[T], f: F) -> Vec<R>
+where
+    F: Fn(&T) -> R
+{
+    items
+        .iter()
+        .map(f)
+        .collect()
+}
+
+
+/// Builds a regular transaction for tests of reports.
+///
+/// * `account_id` - account of the transaction
+/// * `category_id` - category of the transaction
+/// * `amount` - amount of the transaction
+#[cfg(test)]
+pub(crate) fn test_transaction(account_id: Id, category_id: Id, amount: isize) -> Transaction {
+    use crate::storage::{TransactionKind, CustomFields, MetaInfo};
+
+    Transaction {
+        id: Some(uuid::Uuid::new_v4().into_bytes()),
+        timestamp: crate::datetime::Clock::now(),
+        description: String::new(),
+        account_id,
+        category_id,
+        amount,
+        kind: TransactionKind::Regular,
+        note: String::new(),
+        custom_fields: CustomFields::new(),
+        spender_id: None,
+        meta_info: MetaInfo::new(None, None, None)
+    }
+}
+
+
+/// Builds a category for tests of reports.
+///
+/// * `id` - identifier of the category
+/// * `category_type` - type of the category
+#[cfg(test)]
+pub(crate) fn test_category(id: Id, category_type: crate::storage::CategoryType) -> crate::storage::Category {
+    use crate::storage::{Category, CustomFields, MetaInfo};
+
+    Category {
+        id: Some(id),
+        name: String::new(),
+        category_type,
+        note: String::new(),
+        custom_fields: CustomFields::new(),
+        meta_info: MetaInfo::new(None, None, None)
+    }
+}
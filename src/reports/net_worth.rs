@@ -0,0 +1,106 @@
+use std::collections::HashMap;
+
+use serde::{Serialize, Deserialize};
+
+use crate::datetime::Timestamp;
+use crate::storage::{Transaction, Holding, PricePoint, Loan};
+
+
+/// Net worth at a sequence of time points, e.g. at the end of each month.
+#[derive(Clone, Serialize, Deserialize)]
+pub struct NetWorthSeries {
+    /// Points in order of requested time points
+    pub points: Vec<NetWorthPoint>,
+}
+
+
+/// Net worth at a time point.
+#[derive(Clone, Copy, Serialize, Deserialize)]
+pub struct NetWorthPoint {
+    /// Time point
+    pub timestamp: Timestamp,
+
+    /// Total value of accounts including investment holdings
+    pub assets: isize,
+
+    /// Outstanding principal of loans
+    pub liabilities: isize,
+
+    /// Assets reduced by liabilities
+    pub net_worth: isize,
+}
+
+
+impl NetWorthSeries {
+    /// Restores net worth at each time point from current balances.
+    ///
+    /// Time points are evaluated independently, in parallel if
+    /// `parallel` feature is enabled.
+    ///
+    /// * `timestamps` - time points to evaluate net worth at
+    /// * `balance` - current total balance of accounts
+    /// * `transactions` - all transactions
+    /// * `holdings` - all investment holdings
+    /// * `prices` - all security prices sorted by timestamp in descending order
+    /// * `loans` - all loans
+    pub(crate) fn new(timestamps: &[Timestamp], balance: isize, transactions: &[Transaction], holdings: &[Holding],
+        prices: &[PricePoint], loans: &[Loan]) -> Self
+    {
+        let mut prices_of: HashMap<&str, Vec<&PricePoint>> = HashMap::new();
+        for price in prices {
+            prices_of.entry(price.symbol.as_str())
+                .or_default()
+                .push(price);
+        }
+
+        let points = super::map_items(timestamps, |timestamp| {
+            let timestamp = *timestamp;
+            let mut assets = balance - transactions
+                .iter()
+                .filter(|transaction| transaction.timestamp >= timestamp)
+                .map(|transaction| transaction.amount)
+                .sum::<isize>();
+
+            //
+            // Prices are sorted by timestamp in descending order,
+            // so the first suitable one is the latest
+            //
+
+            for holding in holdings {
+                let price = prices_of
+                    .get(holding.symbol.as_str())
+                    .and_then(|prices| prices.iter().find(|price| price.timestamp <= timestamp));
+
+                if let Some(price) = price {
+                    assets += (price.price as f64 * holding.quantity).round() as isize;
+                }
+            }
+
+            let liabilities: isize = loans
+                .iter()
+                .filter(|loan| loan.start_timestamp <= timestamp)
+                .map(|loan| {
+                    let paid: isize = transactions
+                        .iter()
+                        .filter(|transaction| transaction.category_id == loan.principal_category_id &&
+                            transaction.account_id == loan.account_id &&
+                            transaction.timestamp >= loan.start_timestamp &&
+                            transaction.timestamp < timestamp)
+                        .map(|transaction| -transaction.amount)
+                        .sum();
+
+                    (loan.principal - paid).max(0)
+                })
+                .sum();
+
+            NetWorthPoint {
+                timestamp,
+                assets,
+                liabilities,
+                net_worth: assets - liabilities
+            }
+        });
+
+        NetWorthSeries { points }
+    }
+}
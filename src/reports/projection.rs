@@ -0,0 +1,92 @@
+use serde::{Serialize, Deserialize};
+
+use crate::datetime::Timestamp;
+use crate::storage::Id;
+
+
+/// Projected spending (or earning) of a category by the end of a period.
+///
+/// Two projections are combined. The pace one extrapolates spending
+/// proportionally to elapsed share of the period. The historical one
+/// adds to spent amount an average amount, that was spent after the
+/// same share of previous periods: it follows seasonal patterns within
+/// periods (e.g. a rent paid at the beginning of each month does not
+/// inflate projection made in the middle of a month).
+#[derive(Clone, Serialize, Deserialize)]
+pub struct SpendProjection {
+    /// Category identifier
+    #[serde(with = "super::id")]
+    pub category_id: Id,
+
+    /// Beginning of the period (inclusive)
+    pub start: Timestamp,
+
+    /// End of the period (exclusive)
+    pub end: Timestamp,
+
+    /// Elapsed share of the period from 0 to 1
+    pub elapsed: f64,
+
+    /// Amount spent so far (non-negative)
+    pub spent: isize,
+
+    /// Projection by current pace, if any part of the period elapsed
+    pub pace: Option<isize>,
+
+    /// Projection by previous periods, if they have any transactions
+    pub historical: Option<isize>,
+
+    /// Resulting projection: an average of available projections or
+    /// spent amount if there are none (non-negative)
+    pub projected: isize,
+}
+
+
+impl SpendProjection {
+    /// Combines projections.
+    ///
+    /// * `category_id` - category identifier
+    /// * `start` - beginning of the period
+    /// * `end` - end of the period
+    /// * `elapsed` - elapsed share of the period
+    /// * `spent` - amount spent so far
+    /// * `history` - amounts spent in previous periods before and after
+    ///   the same elapsed share
+    pub(crate) fn new(category_id: Id, start: Timestamp, end: Timestamp, elapsed: f64, spent: isize,
+        history: &[(isize, isize)]) -> Self
+    {
+        let pace = match elapsed > 0.0 {
+            true => Some((spent as f64 / elapsed).round() as isize),
+            false => None
+        };
+
+        let historical = match history.iter().any(|(before, after)| 0 != before + after) {
+            true => {
+                let remaining: isize = history
+                    .iter()
+                    .map(|(_, after)| after)
+                    .sum();
+
+                Some(spent + remaining / history.len() as isize)
+            },
+            false => None
+        };
+
+        let projected = match (pace, historical) {
+            (Some(pace), Some(historical)) => (pace + historical) / 2,
+            (Some(projection), None) | (None, Some(projection)) => projection,
+            (None, None) => spent
+        };
+
+        SpendProjection {
+            category_id,
+            start,
+            end,
+            elapsed,
+            spent,
+            pace,
+            historical,
+            projected: projected.max(spent)
+        }
+    }
+}
@@ -0,0 +1,235 @@
+use std::collections::HashMap;
+
+use chrono::Months;
+use serde::{Serialize, Deserialize};
+
+use crate::datetime::Timestamp;
+use crate::storage::{Transaction, Id};
+use crate::core::normalize_payee;
+
+
+/// Minimal number of occurrences of a recurring transaction.
+const MIN_OCCURRENCES: usize = 3;
+
+/// Relative deviation of amounts from their median, that is still
+/// considered the same amount (e.g. utility bills vary slightly).
+const AMOUNT_TOLERANCE: f64 = 0.15;
+
+/// Share of intervals, that must match periodicity.
+const REGULARITY_THRESHOLD: f64 = 0.75;
+
+/// Number of missed periods, after which a recurring transaction
+/// is considered cancelled.
+const MAX_MISSED_PERIODS: f64 = 2.0;
+
+
+/// Transactions, that likely recur, e.g. a subscription or a bill.
+#[derive(Clone, Serialize, Deserialize)]
+pub struct RecurringCandidate {
+    /// Description of the latest transaction
+    pub description: String,
+
+    /// Account of the transactions
+    #[serde(with = "super::id")]
+    pub account_id: Id,
+
+    /// Category of the latest transaction
+    #[serde(with = "super::id")]
+    pub category_id: Id,
+
+    /// Typical (median) amount
+    pub amount: isize,
+
+    /// How often the transaction recurs
+    pub periodicity: Periodicity,
+
+    /// Number of matching transactions
+    pub occurrences: usize,
+
+    /// Timestamp of the latest transaction
+    pub last_timestamp: Timestamp,
+
+    /// Expected timestamp of the next transaction
+    pub next_timestamp: Timestamp,
+
+    /// Identifiers of matching transactions in chronological order
+    #[serde(with = "super::id::list")]
+    pub transactions: Vec<Id>,
+}
+
+
+/// Periodicity of a recurring transaction.
+#[derive(Clone, Copy, PartialEq, Eq, Debug, Serialize, Deserialize)]
+pub enum Periodicity {
+    /// Every week
+    Weekly,
+
+    /// Every two weeks
+    Biweekly,
+
+    /// Every month
+    Monthly,
+
+    /// Every three months
+    Quarterly,
+
+    /// Every year
+    Yearly,
+}
+
+
+impl Periodicity {
+    /// All periodicities from the shortest one.
+    const ALL: [Periodicity; 5] = [Periodicity::Weekly, Periodicity::Biweekly,
+        Periodicity::Monthly, Periodicity::Quarterly, Periodicity::Yearly];
+
+    /// Returns average length of a period in days.
+    pub fn days(&self) -> f64 {
+        match self {
+            Periodicity::Weekly => 7.0,
+            Periodicity::Biweekly => 14.0,
+            Periodicity::Monthly => 30.44,
+            Periodicity::Quarterly => 91.31,
+            Periodicity::Yearly => 365.25
+        }
+    }
+
+    /// Returns deviation of an interval from the average length,
+    /// that still matches the periodicity (e.g. months differ in
+    /// length and payments shift on weekends).
+    fn tolerance(&self) -> f64 {
+        match self {
+            Periodicity::Weekly => 1.0,
+            Periodicity::Biweekly => 2.0,
+            Periodicity::Monthly => 4.0,
+            Periodicity::Quarterly => 8.0,
+            Periodicity::Yearly => 12.0
+        }
+    }
+
+    fn matches(&self, days: f64) -> bool {
+        (days - self.days()).abs() <= self.tolerance()
+    }
+
+    /// Returns expected timestamp of the next transaction.
+    ///
+    /// * `timestamp` - timestamp of the previous transaction
+    pub(crate) fn next_after(&self, timestamp: Timestamp) -> Timestamp {
+        let months = match self {
+            Periodicity::Weekly => return timestamp + chrono::Duration::days(7),
+            Periodicity::Biweekly => return timestamp + chrono::Duration::days(14),
+            Periodicity::Monthly => 1,
+            Periodicity::Quarterly => 3,
+            Periodicity::Yearly => 12
+        };
+
+        timestamp.checked_add_months(Months::new(months))
+            .unwrap_or(timestamp)
+    }
+}
+
+
+/// Looks for recurring transactions, sorted by expected timestamp
+/// of the next transaction.
+///
+/// Transactions are grouped by payee (normalized description),
+/// account and direction, then amounts close to the median are
+/// checked for regular intervals. Recurring transactions missed
+/// for several periods are considered cancelled and skipped.
+///
+/// * `transactions` - historical transactions
+/// * `now` - current time point
+pub(crate) fn detect_recurring(transactions: &[Transaction], now: Timestamp) -> Vec<RecurringCandidate> {
+    let mut groups: HashMap<(String, Id, bool), Vec<&Transaction>> = HashMap::new();
+    for transaction in transactions.iter().filter(|transaction| transaction.kind.is_regular()) {
+        groups.entry((normalize_payee(&transaction.description), transaction.account_id, transaction.amount > 0))
+            .or_default()
+            .push(transaction);
+    }
+
+    let mut candidates: Vec<_> = groups
+        .into_values()
+        .filter_map(|group| candidate(group, now))
+        .collect();
+
+    candidates.sort_by(|lhs, rhs| lhs.next_timestamp.cmp(&rhs.next_timestamp)
+        .then_with(|| lhs.description.cmp(&rhs.description)));
+
+    candidates
+}
+
+
+fn candidate(mut group: Vec<&Transaction>, now: Timestamp) -> Option<RecurringCandidate> {
+    if group.len() < MIN_OCCURRENCES {
+        return None;
+    }
+
+    //
+    // Occasional purchases from the same payee (e.g. an extra
+    // order in the shop with subscription) are filtered out
+    //
+
+    let amount = median(group.iter().map(|transaction| transaction.amount).collect())?;
+    let tolerance = (amount as f64 * AMOUNT_TOLERANCE).abs();
+
+    group.retain(|transaction| ((transaction.amount - amount) as f64).abs() <= tolerance);
+    if group.len() < MIN_OCCURRENCES {
+        return None;
+    }
+
+    group.sort_by_key(|transaction| transaction.timestamp);
+
+    let intervals: Vec<f64> = group
+        .windows(2)
+        .map(|pair| (pair[1].timestamp - pair[0].timestamp).num_hours() as f64 / 24.0)
+        .collect();
+
+    let typical_interval = median_f64(intervals.clone())?;
+    let periodicity = Periodicity::ALL
+        .into_iter()
+        .find(|periodicity| periodicity.matches(typical_interval))?;
+
+    let regular = intervals
+        .iter()
+        .filter(|interval| periodicity.matches(**interval))
+        .count();
+
+    if (regular as f64) < REGULARITY_THRESHOLD * intervals.len() as f64 {
+        return None;
+    }
+
+    let last = group.last()?;
+    let missed = (now - last.timestamp).num_hours() as f64 / 24.0 / periodicity.days();
+    if missed > MAX_MISSED_PERIODS {
+        return None;
+    }
+
+    Some(RecurringCandidate {
+        description: last.description.clone(),
+        account_id: last.account_id,
+        category_id: last.category_id,
+        amount,
+        periodicity,
+        occurrences: group.len(),
+        last_timestamp: last.timestamp,
+        next_timestamp: periodicity.next_after(last.timestamp),
+        transactions: group
+            .iter()
+            .filter_map(|transaction| transaction.id)
+            .collect()
+    })
+}
+
+
+fn median(mut values: Vec<isize>) -> Option<isize> {
+    values.sort_unstable();
+    values.get(values.len() / 2)
+        .copied()
+}
+
+
+fn median_f64(mut values: Vec<f64>) -> Option<f64> {
+    values.sort_by(f64::total_cmp);
+    values.get(values.len() / 2)
+        .copied()
+}
@@ -0,0 +1,108 @@
+use std::collections::HashMap;
+
+use serde::{Serialize, Deserialize};
+
+use crate::datetime::Timestamp;
+use crate::storage::{Transaction, Person, Id};
+use super::is_income_or_expense;
+
+
+/// Totals of transactions per person within a period.
+///
+/// Moving money between own accounts is not spending, so transfers
+/// are skipped along with balance adjustments.
+#[derive(Clone, Serialize, Deserialize)]
+pub struct SpenderBreakdown {
+    /// Beginning of the period (inclusive)
+    pub start: Timestamp,
+
+    /// End of the period (exclusive)
+    pub end: Timestamp,
+
+    /// Persons with transactions sorted by magnitude of expenses
+    /// in descending order
+    pub spenders: Vec<SpenderTotal>,
+}
+
+
+/// Totals of transactions of one person.
+#[derive(Clone, Serialize, Deserialize)]
+pub struct SpenderTotal {
+    /// Person identifier (`None` for transactions without a known
+    /// spender)
+    #[serde(with = "super::id::option")]
+    pub person_id: Option<Id>,
+
+    /// Person name
+    pub name: Option<String>,
+
+    /// Total income (non-negative)
+    pub income: isize,
+
+    /// Total expenses (non-positive)
+    pub expenses: isize,
+
+    /// Number of transactions
+    pub transactions: usize,
+}
+
+
+impl SpenderBreakdown {
+    /// Aggregates transactions of a period.
+    ///
+    /// * `start` - beginning of the period
+    /// * `end` - end of the period
+    /// * `persons` - all persons
+    /// * `transactions` - transactions of the period
+    /// * `transfer_income_id` - identifier of incoming transfers category
+    /// * `transfer_outcome_id` - identifier of outgoing transfers category
+    pub(crate) fn new(start: Timestamp, end: Timestamp, persons: &[Person], transactions: &[Transaction],
+        transfer_income_id: Id, transfer_outcome_id: Id) -> Self
+    {
+        let names: HashMap<Id, &str> = persons
+            .iter()
+            .filter_map(|person| Some((person.id?, person.name.as_str())))
+            .collect();
+
+        let mut totals: HashMap<Option<Id>, SpenderTotal> = HashMap::new();
+        for transaction in transactions.iter().filter(|transaction| is_income_or_expense(transaction, transfer_income_id, transfer_outcome_id)) {
+            //
+            // Removed persons are not known anymore, hence
+            // their transactions are treated as unattributed
+            //
+
+            let person_id = transaction.spender_id
+                .filter(|id| names.contains_key(id));
+
+            let total = totals
+                .entry(person_id)
+                .or_insert_with(|| SpenderTotal {
+                    person_id,
+                    name: person_id.map(|id| names[&id].to_owned()),
+                    income: 0,
+                    expenses: 0,
+                    transactions: 0
+                });
+
+            match transaction.amount > 0 {
+                true => total.income += transaction.amount,
+                false => total.expenses += transaction.amount
+            }
+
+            total.transactions += 1;
+        }
+
+        let mut spenders: Vec<_> = totals
+            .into_values()
+            .collect();
+
+        spenders.sort_by(|lhs, rhs| rhs.expenses.abs().cmp(&lhs.expenses.abs())
+            .then_with(|| lhs.name.cmp(&rhs.name)));
+
+        SpenderBreakdown {
+            start,
+            end,
+            spenders
+        }
+    }
+}
@@ -0,0 +1,183 @@
+use serde::{Serialize, Deserialize};
+
+use crate::datetime::Timestamp;
+use crate::storage::{Transaction, TransactionKind, Account, Id};
+
+
+/// Statement of an account within a period: transactions with running
+/// balance, opening and closing balances and totals.
+///
+/// Transfers, opening balances and adjustments are listed and change
+/// running balance, but they are totaled separately from incomes and
+/// expenses.
+#[derive(Clone, Serialize, Deserialize)]
+pub struct Statement {
+    /// Account identifier
+    #[serde(with = "super::id")]
+    pub account_id: Id,
+
+    /// Account name
+    pub name: String,
+
+    /// Beginning of the period (inclusive)
+    pub start: Timestamp,
+
+    /// End of the period (exclusive)
+    pub end: Timestamp,
+
+    /// Balance at the beginning of the period
+    pub opening_balance: isize,
+
+    /// Balance at the end of the period
+    pub closing_balance: isize,
+
+    /// Total income (non-negative)
+    pub income: isize,
+
+    /// Total expenses (non-positive)
+    pub expenses: isize,
+
+    /// Total of incoming transfers (non-negative)
+    pub transfers_in: isize,
+
+    /// Total of outgoing transfers (non-positive)
+    pub transfers_out: isize,
+
+    /// Total of opening balances and adjustments
+    pub adjustments: isize,
+
+    /// Transactions sorted by timestamp in ascending order
+    pub lines: Vec<StatementLine>,
+}
+
+
+/// Transaction of a statement.
+#[derive(Clone, Serialize, Deserialize)]
+pub struct StatementLine {
+    /// Transaction identifier
+    #[serde(with = "super::id")]
+    pub transaction_id: Id,
+
+    /// Timestamp of the transaction
+    pub timestamp: Timestamp,
+
+    /// Description of the transaction
+    pub description: String,
+
+    /// Category of the transaction
+    #[serde(with = "super::id")]
+    pub category_id: Id,
+
+    /// Kind of the line
+    pub kind: StatementLineKind,
+
+    /// Amount of the transaction
+    pub amount: isize,
+
+    /// Balance after the transaction
+    pub balance: isize,
+}
+
+
+/// Kinds of statement lines.
+#[derive(Clone, Copy, PartialEq, Eq, Debug, Serialize, Deserialize)]
+pub enum StatementLineKind {
+    /// Ordinary income
+    Income,
+
+    /// Ordinary spending
+    Expense,
+
+    /// Transfer from another account
+    TransferIn,
+
+    /// Transfer to another account
+    TransferOut,
+
+    /// Opening balance of the account
+    OpeningBalance,
+
+    /// Correction of the balance
+    Adjustment,
+}
+
+
+impl Statement {
+    /// Builds a statement of an account.
+    ///
+    /// Opening balance is restored from the current balance of the
+    /// account and transactions made since the beginning of the period.
+    ///
+    /// * `start` - beginning of the period
+    /// * `end` - end of the period
+    /// * `account` - account to build statement of
+    /// * `transactions` - all transactions of the account
+    /// * `transfer_income_id` - identifier of incoming transfers category
+    /// * `transfer_outcome_id` - identifier of outgoing transfers category
+    pub(crate) fn new(start: Timestamp, end: Timestamp, account: &Account, transactions: &[Transaction],
+        transfer_income_id: Id, transfer_outcome_id: Id) -> Self
+    {
+        let opening_balance = account.balance - transactions
+            .iter()
+            .filter(|transaction| transaction.timestamp >= start)
+            .map(|transaction| transaction.amount)
+            .sum::<isize>();
+
+        let mut period: Vec<_> = transactions
+            .iter()
+            .filter(|transaction| start <= transaction.timestamp && transaction.timestamp < end)
+            .collect();
+
+        period.sort_by(|lhs, rhs| lhs.timestamp.cmp(&rhs.timestamp)
+            .then_with(|| lhs.id.cmp(&rhs.id)));
+
+        let mut statement = Statement {
+            account_id: account.id.unwrap_or_default(),
+            name: account.name.clone(),
+            start,
+            end,
+            opening_balance,
+            closing_balance: opening_balance,
+            income: 0,
+            expenses: 0,
+            transfers_in: 0,
+            transfers_out: 0,
+            adjustments: 0,
+            lines: Vec::with_capacity(period.len())
+        };
+
+        for transaction in period {
+            let kind = match transaction.kind {
+                TransactionKind::OpeningBalance => StatementLineKind::OpeningBalance,
+                TransactionKind::Adjustment => StatementLineKind::Adjustment,
+                TransactionKind::Regular if transaction.category_id == transfer_income_id => StatementLineKind::TransferIn,
+                TransactionKind::Regular if transaction.category_id == transfer_outcome_id => StatementLineKind::TransferOut,
+                TransactionKind::Regular if transaction.amount > 0 => StatementLineKind::Income,
+                TransactionKind::Regular => StatementLineKind::Expense
+            };
+
+            let total = match kind {
+                StatementLineKind::Income => &mut statement.income,
+                StatementLineKind::Expense => &mut statement.expenses,
+                StatementLineKind::TransferIn => &mut statement.transfers_in,
+                StatementLineKind::TransferOut => &mut statement.transfers_out,
+                StatementLineKind::OpeningBalance | StatementLineKind::Adjustment => &mut statement.adjustments
+            };
+
+            *total += transaction.amount;
+            statement.closing_balance += transaction.amount;
+
+            statement.lines.push(StatementLine {
+                transaction_id: transaction.id.unwrap_or_default(),
+                timestamp: transaction.timestamp,
+                description: transaction.description.clone(),
+                category_id: transaction.category_id,
+                kind,
+                amount: transaction.amount,
+                balance: statement.closing_balance
+            });
+        }
+
+        statement
+    }
+}
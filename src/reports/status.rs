@@ -0,0 +1,63 @@
+use serde::{Serialize, Deserialize};
+
+use crate::storage::Id;
+
+
+/// State of a plan within a period: how much of its limit is used.
+#[derive(Clone, Serialize, Deserialize)]
+pub struct BudgetStatus {
+    /// Plan identifier
+    #[serde(with = "super::id")]
+    pub plan_id: Id,
+
+    /// Identifier of the plan's category
+    #[serde(with = "super::id")]
+    pub category_id: Id,
+
+    /// Plan name
+    pub name: String,
+
+    /// Limit of the plan
+    pub limit: isize,
+
+    /// Amount spent (or earned) within the period (non-negative)
+    pub spent: isize,
+
+    /// Remaining amount, negative if the limit is exceeded
+    pub remaining: isize,
+
+    /// Used share of the limit, may exceed 1
+    pub utilization: f64,
+}
+
+
+impl BudgetStatus {
+    /// Creates a status from a limit and spent amount.
+    ///
+    /// * `plan_id` - plan identifier
+    /// * `category_id` - identifier of the plan's category
+    /// * `name` - plan name
+    /// * `limit` - limit of the plan
+    /// * `spent` - amount spent within a period
+    pub(crate) fn new(plan_id: Id, category_id: Id, name: String, limit: isize, spent: isize) -> Self {
+        let utilization = match limit {
+            0 => 0.0,
+            limit => spent as f64 / limit as f64
+        };
+
+        BudgetStatus {
+            plan_id,
+            category_id,
+            name,
+            limit,
+            spent,
+            remaining: limit - spent,
+            utilization
+        }
+    }
+
+    /// Checks if the limit is exceeded.
+    pub fn is_exceeded(&self) -> bool {
+        self.remaining < 0
+    }
+}
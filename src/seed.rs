@@ -0,0 +1,167 @@
+use crate::core::{Budget, external_id};
+use crate::core::external::set_external_id;
+use crate::crypto::CryptoEngine;
+use crate::datetime::Clock;
+use crate::error::Result;
+use crate::storage::{DataStorage, Account, Category, CategoryType, CustomFields, MetaInfo};
+use crate::sync::SyncEngine;
+
+
+/// Name of an external source, that seeded items are attributed to.
+const SEED_SOURCE: &str = "seed";
+
+/// Starter categories: key, type and names in English, German,
+/// French and Russian (in order of [`Language`] variants).
+const CATEGORIES: &[(&str, CategoryType, [&str; 4])] = &[
+    ("salary", CategoryType::Income, ["Salary", "Gehalt", "Salaire", "Зарплата"]),
+    ("interest", CategoryType::Income, ["Interest", "Zinsen", "Intérêts", "Проценты"]),
+    ("other_income", CategoryType::Income, ["Other income", "Sonstige Einnahmen", "Autres revenus", "Прочие доходы"]),
+    ("groceries", CategoryType::Outcome, ["Groceries", "Lebensmittel", "Courses", "Продукты"]),
+    ("housing", CategoryType::Outcome, ["Housing", "Wohnen", "Logement", "Жильё"]),
+    ("utilities", CategoryType::Outcome, ["Utilities", "Nebenkosten", "Charges", "Коммунальные услуги"]),
+    ("transport", CategoryType::Outcome, ["Transport", "Verkehr", "Transport", "Транспорт"]),
+    ("dining_out", CategoryType::Outcome, ["Dining out", "Restaurants", "Restaurants", "Кафе и рестораны"]),
+    ("health", CategoryType::Outcome, ["Health", "Gesundheit", "Santé", "Здоровье"]),
+    ("entertainment", CategoryType::Outcome, ["Entertainment", "Freizeit", "Loisirs", "Развлечения"]),
+    ("clothing", CategoryType::Outcome, ["Clothing", "Kleidung", "Vêtements", "Одежда"]),
+    ("other_expenses", CategoryType::Outcome, ["Other expenses", "Sonstige Ausgaben", "Autres dépenses", "Прочие расходы"]),
+];
+
+/// Starter accounts: key and names (see [`CATEGORIES`]).
+const ACCOUNTS: &[(&str, [&str; 4])] = &[
+    ("cash", ["Cash", "Bargeld", "Espèces", "Наличные"]),
+    ("checking", ["Checking account", "Girokonto", "Compte courant", "Текущий счёт"]),
+    ("savings", ["Savings", "Sparkonto", "Épargne", "Сбережения"]),
+    ("credit_card", ["Credit card", "Kreditkarte", "Carte de crédit", "Кредитная карта"]),
+];
+
+
+/// Language of names of seeded items.
+#[derive(Clone, Copy, PartialEq, Eq, Debug, Default)]
+pub enum Language {
+    /// English
+    #[default]
+    English,
+
+    /// German
+    German,
+
+    /// French
+    French,
+
+    /// Russian
+    Russian,
+}
+
+
+/// Set of starter items to add to a new budget.
+#[derive(Clone, Copy, PartialEq, Eq, Debug)]
+pub struct Template {
+    /// Language of names of items
+    pub language: Language,
+
+    /// If standard income and expense categories are added
+    pub categories: bool,
+
+    /// If typical accounts (cash, checking, savings, credit card) are added
+    pub accounts: bool,
+}
+
+
+impl Template {
+    /// Creates a template with both categories and accounts.
+    ///
+    /// * `language` - language of names of items
+    pub fn starter(language: Language) -> Self {
+        Template {
+            language,
+            categories: true,
+            accounts: true
+        }
+    }
+
+    /// Creates a template with categories only.
+    ///
+    /// * `language` - language of names of items
+    pub fn categories(language: Language) -> Self {
+        Template {
+            language,
+            categories: true,
+            accounts: false
+        }
+    }
+}
+
+
+/// Adds starter items of a template to a budget.
+///
+/// Seeded items are marked with their keys (see [`external_id`] with
+/// "seed" source), items added before are skipped, hence re-applying
+/// a template does not duplicate them even if they were renamed. Items
+/// are added locally, so a template should be applied once on the
+/// instance, that creates the budget. Returns number of added items.
+///
+/// * `budget` - budget to add items to
+/// * `template` - set of items to add
+pub fn apply<Ce, Se, St>(budget: &Budget<Ce, Se, St>, template: &Template) -> Result<usize>
+where
+    Ce: CryptoEngine,
+    Se: SyncEngine,
+    St: DataStorage
+{
+    let language = template.language as usize;
+    let mut added = 0;
+
+    if template.categories {
+        let existing = budget.categories()?;
+
+        for (key, category_type, names) in CATEGORIES {
+            if existing.iter().any(|category| external_id(&category.custom_fields, SEED_SOURCE) == Some(key)) {
+                continue;
+            }
+
+            budget.add_category(&Category {
+                id: None,
+                name: names[language].to_owned(),
+                category_type: *category_type,
+                note: String::new(),
+                custom_fields: seed_fields(key),
+                meta_info: MetaInfo::new(Some(Clock::now()), None, None)
+            })?;
+
+            added += 1;
+        }
+    }
+
+    if template.accounts {
+        let existing = budget.accounts()?;
+
+        for (key, names) in ACCOUNTS {
+            if existing.iter().any(|account| external_id(&account.custom_fields, SEED_SOURCE) == Some(key)) {
+                continue;
+            }
+
+            budget.add_account(&Account {
+                id: None,
+                name: names[language].to_owned(),
+                balance: 0,
+                initial_balance: 0,
+                note: String::new(),
+                custom_fields: seed_fields(key),
+                meta_info: MetaInfo::new(Some(Clock::now()), None, None)
+            })?;
+
+            added += 1;
+        }
+    }
+
+    Ok(added)
+}
+
+
+fn seed_fields(key: &str) -> CustomFields {
+    let mut custom_fields = CustomFields::new();
+    set_external_id(&mut custom_fields, SEED_SOURCE, key);
+
+    custom_fields
+}
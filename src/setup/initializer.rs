@@ -0,0 +1,224 @@
+use crate::error::{Result, Error, ErrorKind};
+use crate::cancel::CancellationToken;
+use crate::progress::{Progress, NoProgress};
+use crate::location::Location;
+use crate::crypto::{CryptoEngine, GpgCryptoEngine, KeyId};
+use crate::storage::DbStorage;
+use crate::sync::{GitSyncEngine, Proxy};
+use crate::core::{Budget, Config, DefaultBudget};
+use crate::seed::{self, Template};
+use super::ALREADY_INITIALIZED;
+
+
+/// Steps of first-run initialization in order of execution.
+#[derive(Clone, Copy, PartialEq, Eq, Debug)]
+pub enum SetupStep {
+    /// Creation of app's location
+    CreateLocation,
+
+    /// Validation of a key, that will be used to protect data
+    ValidateKey,
+
+    /// Creation of configuration (including instance name and remote)
+    CreateConfig,
+
+    /// Creation of cryptographic engine with a new symmetric key
+    CreateCryptoEngine,
+
+    /// Creation of DB schema
+    CreateStorage,
+
+    /// Creation of synchronization engine (cloning of a remote if specified)
+    CreateSyncEngine,
+
+    /// Creation of predefined and starter items
+    InitializeBudget,
+}
+
+
+/// Orchestrator of first-run initialization tasks.
+///
+/// Performs all steps listed in [`SetupStep`] in one call and returns
+/// a ready-to-use budget instance.
+pub struct Initializer<L: Location> {
+    /// Location to initialize
+    loc: L,
+
+    /// Identifier of a key used to protect data
+    key_id: <GpgCryptoEngine as CryptoEngine>::KeyId,
+
+    /// User-friendly instance name
+    instance_name: Option<String>,
+
+    /// Remote to clone synchronization repository from
+    remote: Option<String>,
+
+    /// Proxy to connect to remote through
+    proxy: Option<Proxy>,
+
+    /// Callback invoked before each step
+    on_step: Option<Box<dyn FnMut(SetupStep)>>,
+
+    /// Token checked before each step
+    cancel: CancellationToken,
+
+    /// Receiver of cloning progress reports
+    progress: Box<dyn Progress>,
+
+    /// Starter items to add to a new budget
+    template: Option<Template>,
+}
+
+
+impl<L: Location> Initializer<L> {
+    /// Creates an initializer.
+    ///
+    /// * `loc` - location to initialize
+    /// * `key_id` - identifier of a GPG key used to protect data
+    pub fn new(loc: L, key_id: &str) -> Self {
+        Initializer {
+            loc,
+            key_id: KeyId::new(key_id),
+            instance_name: None,
+            remote: None,
+            proxy: None,
+            on_step: None,
+            cancel: CancellationToken::new(),
+            progress: Box::new(NoProgress),
+            template: None
+        }
+    }
+
+    /// Sets user-friendly instance name.
+    ///
+    /// * `instance_name` - instance name
+    pub fn instance_name(mut self, instance_name: &str) -> Self {
+        self.instance_name = Some(instance_name.to_owned());
+        self
+    }
+
+    /// Sets a remote to clone synchronization repository from.
+    ///
+    /// * `remote` - remote URL
+    pub fn remote(mut self, remote: &str) -> Self {
+        self.remote = Some(remote.to_owned());
+        self
+    }
+
+    /// Sets a proxy to connect to remote through. It is saved
+    /// into configuration and used for synchronization later.
+    ///
+    /// * `proxy` - proxy to use
+    pub fn proxy(mut self, proxy: Proxy) -> Self {
+        self.proxy = Some(proxy);
+        self
+    }
+
+    /// Sets a callback, that is invoked before each step.
+    ///
+    /// * `callback` - callback receiving the step to perform
+    pub fn on_step<F>(mut self, callback: F) -> Self
+    where
+        F: FnMut(SetupStep) + 'static
+    {
+        self.on_step = Some(Box::new(callback));
+        self
+    }
+
+    /// Sets a token, that allows to cancel initialization.
+    ///
+    /// Token is checked before each step and while cloning a remote.
+    ///
+    /// * `cancel` - cancellation token
+    pub fn cancellation(mut self, cancel: CancellationToken) -> Self {
+        self.cancel = cancel;
+        self
+    }
+
+    /// Sets a receiver of progress reports for cloning of a remote.
+    ///
+    /// * `progress` - progress receiver
+    pub fn progress<P>(mut self, progress: P) -> Self
+    where
+        P: Progress + 'static
+    {
+        self.progress = Box::new(progress);
+        self
+    }
+
+    /// Sets starter items to add to a new budget (see [`seed::apply`]).
+    ///
+    /// Items are not added, if a remote is set, because a cloned
+    /// budget has items already.
+    ///
+    /// * `template` - set of starter items
+    pub fn seed(mut self, template: Template) -> Self {
+        self.template = Some(template);
+        self
+    }
+
+    /// Performs all initialization steps.
+    pub fn run(mut self) -> Result<DefaultBudget> {
+        self.step(SetupStep::CreateLocation)?;
+
+        if Config::<GpgCryptoEngine>::open(&self.loc).is_ok() {
+            return Err(Error::from_message_with_extra(ALREADY_INITIALIZED,
+                self.loc.root().to_string_lossy()).with_kind(ErrorKind::AlreadyExists));
+        }
+
+        self.loc.create_if_absent()?;
+
+        //
+        // Check if the key exists and is suitable before
+        // any files are written
+        //
+
+        self.step(SetupStep::ValidateKey)?;
+        GpgCryptoEngine::new_dummy()?
+            .lookup_key(&self.key_id)?;
+
+        self.step(SetupStep::CreateConfig)?;
+        let mut config = Config::<GpgCryptoEngine>::create(&self.loc, &self.key_id)?;
+
+        config.set_instance_name(self.instance_name.as_deref());
+        config.set_sync_remote(self.remote.as_deref());
+        config.set_sync_proxy(self.proxy.as_ref());
+        config.save()?;
+
+        self.step(SetupStep::CreateCryptoEngine)?;
+        let crypto_engine = GpgCryptoEngine::create(&self.loc, &self.key_id)?;
+
+        config.set_engine(Some(crypto_engine.engine()));
+        config.save()?;
+
+        self.step(SetupStep::CreateStorage)?;
+        let storage = DbStorage::create(&self.loc)?;
+
+        self.step(SetupStep::CreateSyncEngine)?;
+        let sync_engine = GitSyncEngine::create_with(&self.loc, self.remote.as_deref(), 
+            &self.cancel, self.progress.as_ref(), &self.proxy.clone().unwrap_or_default())?;
+
+        self.step(SetupStep::InitializeBudget)?;
+        let budget = Budget::new(crypto_engine, sync_engine, storage, config)?;
+        budget.initialize()?;
+
+        if let (Some(template), None) = (&self.template, &self.remote) {
+            seed::apply(&budget, template)?;
+        }
+
+        Ok(budget)
+    }
+}
+
+
+impl<L: Location> Initializer<L> {
+    fn step(&mut self, step: SetupStep) -> Result<()> {
+        self.cancel.check()?;
+
+        if let Some(callback) = self.on_step.as_mut() {
+            callback(step);
+        }
+
+        Ok(())
+    }
+}
@@ -0,0 +1,7 @@
+mod initializer;
+
+pub use self::initializer::{Initializer, SetupStep};
+
+
+/// Error message for an attempt to initialize already initialized location.
+const ALREADY_INITIALIZED: &str = "Location is already initialized";
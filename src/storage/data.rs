@@ -1,7 +1,10 @@
+use std::collections::BTreeMap;
+
 use serde::{Serialize, Deserialize};
 
 use crate::core::InstanceId;
-use crate::datetime::Timestamp;
+use crate::datetime::{Timestamp, TxnDate};
+use super::events::EntityKind;
 
 
 /// Identifier type.
@@ -15,6 +18,16 @@ pub type Id = [u8; 16];
 pub type PrimaryId = Option<Id>;
 
 
+/// Position in a storage's changes journal.
+/// 
+/// Each change of an item gets a new strictly increasing position.
+pub type JournalPosition = u64;
+
+
+/// Identifier of a logical snapshot of a storage's state.
+pub type SnapshotId = u64;
+
+
 /// Types of categories.
 #[derive(Clone, Copy, PartialEq, Eq, PartialOrd, Ord, Serialize, Deserialize)]
 pub enum CategoryType {
@@ -26,6 +39,54 @@ pub enum CategoryType {
 }
 
 
+/// Kinds of transactions.
+/// 
+/// Opening balances and adjustments affect account balances, but
+/// they are not real incomes or spendings. Hence they should not be
+/// taken into account in income/outcome reports.
+#[derive(Clone, Copy, PartialEq, Eq, PartialOrd, Ord, Default, Serialize, Deserialize)]
+pub enum TransactionKind {
+    /// Ordinary income or spending
+    #[default]
+    Regular,
+
+    /// Initial balance of an account
+    OpeningBalance,
+
+    /// Correction of an account balance to match the observed one
+    Adjustment,
+}
+
+
+impl TransactionKind {
+    /// Checks if transactions of this kind are real incomes or spendings.
+    pub fn is_regular(&self) -> bool {
+        *self == TransactionKind::Regular
+    }
+}
+
+
+/// Value of a user-defined custom field.
+#[derive(Clone, PartialEq, Serialize, Deserialize)]
+pub enum CustomValue {
+    /// Free text
+    Text(String),
+
+    /// Integer number (e.g. amount of money)
+    Integer(isize),
+
+    /// Boolean flag
+    Flag(bool),
+
+    /// Point in time
+    Timestamp(Timestamp),
+}
+
+
+/// User-defined custom fields of an entity: typed values by their keys.
+pub type CustomFields = BTreeMap<String, CustomValue>;
+
+
 /// Meta information about an entity
 #[derive(Serialize, Deserialize, Clone, Copy)]
 pub struct MetaInfo {
@@ -70,7 +131,7 @@ impl MetaInfo {
 
 
 /// User-friendly transaction structure.
-#[derive(Serialize, Deserialize)]
+#[derive(Serialize, Deserialize, Clone)]
 pub struct Transaction {
     /// Identifier
     pub id: PrimaryId,
@@ -90,11 +151,36 @@ pub struct Transaction {
     /// Amount of money affected
     pub amount: isize,
 
+    /// Kind of transaction
+    #[serde(default)]
+    pub kind: TransactionKind,
+
+    /// Free-text note
+    #[serde(default)]
+    pub note: String,
+
+    /// User-defined custom fields
+    #[serde(default)]
+    pub custom_fields: CustomFields,
+
+    /// Identifier of a person, that made the transaction (see [`Person`])
+    #[serde(default)]
+    pub spender_id: Option<Id>,
+
     /// Meta info
     pub meta_info: MetaInfo
 }
 
 
+impl Transaction {
+    /// Returns date of the transaction, if its timestamp is a
+    /// canonical transaction date (see [`TxnDate`]).
+    pub fn date(&self) -> TxnDate {
+        TxnDate::from_canonical(&self.timestamp)
+    }
+}
+
+
 /// Protected transaction structure.
 /// 
 /// For fields description refer to [`Transaction`].
@@ -106,12 +192,54 @@ pub struct EncryptedTransaction {
     pub account_id: Id,
     pub category_id: Id,
     pub amount: Vec<u8>,
+    pub kind: TransactionKind,
+    pub note: Option<Vec<u8>>,
+    pub custom_fields: Option<Vec<u8>>,
+    pub spender_id: Option<Id>,
     pub meta_info: MetaInfo
 }
 
 
+/// Transaction without heavy fields (description, note and custom
+/// fields), e.g. for list views and totals, that do not show them.
+#[derive(Serialize, Deserialize, Clone)]
+pub struct TransactionSummary {
+    /// Identifier
+    pub id: PrimaryId,
+
+    /// Creation time
+    pub timestamp: Timestamp,
+
+    /// Identifier of an account, which the transaction belongs to
+    pub account_id: Id,
+
+    /// Identifier of a category
+    pub category_id: Id,
+
+    /// Amount of money affected
+    pub amount: isize,
+
+    /// Kind of transaction
+    pub kind: TransactionKind,
+}
+
+
+/// Protected transaction summary.
+/// 
+/// For fields description refer to [`TransactionSummary`].
+#[derive(Clone)]
+pub struct EncryptedTransactionSummary {
+    pub id: PrimaryId,
+    pub timestamp: Timestamp,
+    pub account_id: Id,
+    pub category_id: Id,
+    pub amount: Vec<u8>,
+    pub kind: TransactionKind,
+}
+
+
 /// User-friendly category structure.
-#[derive(Serialize, Deserialize)]
+#[derive(Serialize, Deserialize, Clone)]
 pub struct Category {
     /// Identifier
     pub id: PrimaryId,
@@ -122,6 +250,14 @@ pub struct Category {
     /// Type of category
     pub category_type: CategoryType,
 
+    /// Free-text note
+    #[serde(default)]
+    pub note: String,
+
+    /// User-defined custom fields
+    #[serde(default)]
+    pub custom_fields: CustomFields,
+
     /// Meta info
     pub meta_info: MetaInfo
 }
@@ -135,6 +271,8 @@ pub struct EncryptedCategory {
     pub id: PrimaryId,
     pub name: Vec<u8>,
     pub category_type: CategoryType,
+    pub note: Option<Vec<u8>>,
+    pub custom_fields: Option<Vec<u8>>,
     pub meta_info: MetaInfo
 }
 
@@ -154,6 +292,14 @@ pub struct Account {
     /// Initial account balance
     pub initial_balance: isize,
 
+    /// Free-text note
+    #[serde(default)]
+    pub note: String,
+
+    /// User-defined custom fields
+    #[serde(default)]
+    pub custom_fields: CustomFields,
+
     /// Meta info
     pub meta_info: MetaInfo
 }
@@ -168,12 +314,14 @@ pub struct EncryptedAccount {
     pub name: Vec<u8>,
     pub balance: Vec<u8>,
     pub initial_balance: Vec<u8>,
+    pub note: Option<Vec<u8>>,
+    pub custom_fields: Option<Vec<u8>>,
     pub meta_info: MetaInfo
 }
 
 
 /// User-friendly plan structure.
-#[derive(Serialize, Deserialize)]
+#[derive(Serialize, Deserialize, Clone)]
 pub struct Plan {
     /// Identifier
     pub id: PrimaryId,
@@ -187,6 +335,20 @@ pub struct Plan {
     /// Current plan balance
     pub amount_limit: isize,
 
+    /// Share of the limit in basis points (hundredths of percent),
+    /// starting from which an alert is triggered, e.g. 8000 to warn
+    /// at 80% of the limit
+    #[serde(default)]
+    pub alert_threshold: Option<isize>,
+
+    /// Free-text note
+    #[serde(default)]
+    pub note: String,
+
+    /// User-defined custom fields
+    #[serde(default)]
+    pub custom_fields: CustomFields,
+
     /// Meta info
     pub meta_info: MetaInfo
 }
@@ -201,5 +363,325 @@ pub struct EncryptedPlan {
     pub category_id: Id,
     pub name: Vec<u8>,
     pub amount_limit: Vec<u8>,
+    pub alert_threshold: Option<Vec<u8>>,
+    pub note: Option<Vec<u8>>,
+    pub custom_fields: Option<Vec<u8>>,
+    pub meta_info: MetaInfo
+}
+
+
+/// User-friendly loan structure.
+/// 
+/// Principal category is expected to be dedicated to the loan, since
+/// outstanding principal is computed from transactions with this category.
+#[derive(Serialize, Deserialize, Clone)]
+pub struct Loan {
+    /// Identifier
+    pub id: PrimaryId,
+
+    /// User-friendly loan name
+    pub name: String,
+
+    /// Identifier of an account, which payments are made from
+    pub account_id: Id,
+
+    /// Identifier of a category for principal part of payments
+    pub principal_category_id: Id,
+
+    /// Identifier of a category for interest part of payments
+    pub interest_category_id: Id,
+
+    /// Borrowed amount of money
+    pub principal: isize,
+
+    /// Annual interest rate in basis points (hundredths of percent)
+    pub interest_rate: isize,
+
+    /// Number of monthly payments
+    pub term: u32,
+
+    /// Date of the loan issue
+    pub start_timestamp: Timestamp,
+
+    /// Free-text note
+    #[serde(default)]
+    pub note: String,
+
+    /// User-defined custom fields
+    #[serde(default)]
+    pub custom_fields: CustomFields,
+
+    /// Meta info
+    pub meta_info: MetaInfo
+}
+
+
+/// Protected loan structure.
+/// 
+/// For fields description refer to [`Loan`].
+#[derive(Clone)]
+pub struct EncryptedLoan {
+    pub id: PrimaryId,
+    pub name: Vec<u8>,
+    pub account_id: Id,
+    pub principal_category_id: Id,
+    pub interest_category_id: Id,
+    pub principal: Vec<u8>,
+    pub interest_rate: Vec<u8>,
+    pub term: u32,
+    pub start_timestamp: Timestamp,
+    pub note: Option<Vec<u8>>,
+    pub custom_fields: Option<Vec<u8>>,
+    pub meta_info: MetaInfo
+}
+
+
+/// User-friendly investment holding structure.
+#[derive(Serialize, Deserialize, Clone)]
+pub struct Holding {
+    /// Identifier
+    pub id: PrimaryId,
+
+    /// Identifier of an investment account, which the holding belongs to
+    pub account_id: Id,
+
+    /// Ticker symbol or another security identifier
+    pub symbol: String,
+
+    /// Number of units held (can be fractional)
+    pub quantity: f64,
+
+    /// Free-text note
+    #[serde(default)]
+    pub note: String,
+
+    /// User-defined custom fields
+    #[serde(default)]
+    pub custom_fields: CustomFields,
+
+    /// Meta info
+    pub meta_info: MetaInfo
+}
+
+
+/// Protected investment holding structure.
+/// 
+/// For fields description refer to [`Holding`].
+#[derive(Clone)]
+pub struct EncryptedHolding {
+    pub id: PrimaryId,
+    pub account_id: Id,
+    pub symbol: Vec<u8>,
+    pub quantity: Vec<u8>,
+    pub note: Option<Vec<u8>>,
+    pub custom_fields: Option<Vec<u8>>,
+    pub meta_info: MetaInfo
+}
+
+
+/// User-friendly security price structure.
+#[derive(Serialize, Deserialize, Clone)]
+pub struct PricePoint {
+    /// Identifier
+    pub id: PrimaryId,
+
+    /// Ticker symbol or another security identifier
+    pub symbol: String,
+
+    /// Time point the price is actual for
+    pub timestamp: Timestamp,
+
+    /// Price of one unit of the security
+    pub price: isize,
+
+    /// Meta info
+    pub meta_info: MetaInfo
+}
+
+
+/// Protected security price structure.
+/// 
+/// For fields description refer to [`PricePoint`].
+#[derive(Clone)]
+pub struct EncryptedPricePoint {
+    pub id: PrimaryId,
+    pub symbol: Vec<u8>,
+    pub timestamp: Timestamp,
+    pub price: Vec<u8>,
+    pub meta_info: MetaInfo
+}
+
+
+/// User-friendly structure of a person within a household, that
+/// spends money of a budget.
+///
+/// Persons are distinct from instances, that are used for
+/// synchronization: one device may be shared by several persons,
+/// and one person may use several devices.
+#[derive(Serialize, Deserialize, Clone)]
+pub struct Person {
+    /// Identifier
+    pub id: PrimaryId,
+
+    /// Name
+    pub name: String,
+
+    /// Meta info
+    pub meta_info: MetaInfo
+}
+
+
+/// Protected structure of a person.
+/// 
+/// For fields description refer to [`Person`].
+#[derive(Clone)]
+pub struct EncryptedPerson {
+    pub id: PrimaryId,
+    pub name: Vec<u8>,
+    pub meta_info: MetaInfo
+}
+
+
+/// User-friendly structure of a group of categories for reporting
+/// (e.g. needs, wants and savings).
+///
+/// Groups are independent of the hierarchy of categories: a group may
+/// contain categories with different parents. A category belongs to
+/// at most one group.
+#[derive(Serialize, Deserialize, Clone)]
+pub struct CategoryGroup {
+    /// Identifier
+    pub id: PrimaryId,
+
+    /// Name
+    pub name: String,
+
+    /// Categories of the group
+    pub categories: Vec<Id>,
+
+    /// Meta info
+    pub meta_info: MetaInfo
+}
+
+
+/// Protected structure of a group of categories.
+/// 
+/// For fields description refer to [`CategoryGroup`].
+#[derive(Clone)]
+pub struct EncryptedCategoryGroup {
+    pub id: PrimaryId,
+    pub name: Vec<u8>,
+    pub categories: Vec<Id>,
+    pub meta_info: MetaInfo
+}
+
+
+/// Protected settings shared between instances.
+#[derive(Clone)]
+pub struct EncryptedSettings {
+    /// Encrypted serialized settings
+    pub data: Vec<u8>,
+
+    /// Meta info (only origin and change timestamp are used)
+    pub meta_info: MetaInfo
+}
+
+
+/// Record of cryptographic engine and algorithm, that protect data
+/// of a storage. It is written, when a budget is opened for the first
+/// time, and prevents reading data with another engine.
+#[derive(Clone, PartialEq, Eq, Debug)]
+pub struct CryptoRecord {
+    /// Name of cryptographic engine (see [`crate::crypto::CryptoEngine::engine`])
+    pub engine: String,
+
+    /// Name of algorithm (see [`crate::crypto::CryptoEngine::algorithm`])
+    pub algorithm: String,
+}
+
+
+impl std::fmt::Display for CryptoRecord {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(f, "{} ({})", self.engine, self.algorithm)
+    }
+}
+
+
+/// Record of a recovery key (see [`crate::crypto::RecoveryKey`]).
+/// The key itself is never stored, only a key for emergency exports
+/// derived from it.
+#[derive(Clone, PartialEq, Eq, Debug)]
+pub struct RecoveryRecord {
+    /// Salt, that export key is derived with
+    pub salt: Vec<u8>,
+
+    /// Export key encrypted by cryptographic engine
+    pub export_key: Vec<u8>,
+}
+
+
+/// Number of stored items of an entity and range of their dates.
+/// 
+/// Dates are dates of transactions and prices and creation
+/// timestamps of items of other entities.
+#[derive(Clone, Copy, PartialEq, Eq, Debug)]
+pub struct ItemCount {
+    /// Kind of items
+    pub entity: EntityKind,
+
+    /// Number of items, that are not removed
+    pub count: usize,
+
+    /// Date of the earliest item
+    pub first: Option<Timestamp>,
+
+    /// Date of the latest item
+    pub last: Option<Timestamp>,
+}
+
+
+/// Field of transactions, that is indexed locally by an instance.
+///
+/// Indexed values are derived from decrypted transactions by
+/// [`crate::core::Budget`], hence they are encrypted too. Values,
+/// that are looked up by exact match, are encrypted deterministically.
+#[derive(Clone, Copy, PartialEq, Eq, Debug)]
+pub enum IndexedField {
+    /// Identifiers of transactions in external sources
+    ExternalId,
+
+    /// Locations, where transactions were made
+    Location,
+}
+
+
+/// Kinds of merged items.
+#[derive(Clone, Copy, PartialEq, Eq, Debug, Serialize, Deserialize)]
+pub enum MergeKind {
+    /// Two categories were merged
+    Category,
+
+    /// Two accounts were merged
+    Account,
+}
+
+
+/// Record of merging one item into another.
+/// 
+/// All references to the removed item are repointed to the kept one,
+/// then the removed item is removed. Merges are synchronized, so
+/// other instances can repoint their references too.
+#[derive(Clone, Copy, Serialize, Deserialize)]
+pub struct Merge {
+    /// Kind of merged items
+    pub kind: MergeKind,
+
+    /// Identifier of the item, that is kept
+    pub kept: Id,
+
+    /// Identifier of the item, that is merged into the kept one
+    pub removed: Id,
+
+    /// Meta info (only origin and creation timestamp are used)
     pub meta_info: MetaInfo
 }
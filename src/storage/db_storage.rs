@@ -1,14 +1,782 @@
-use crate::location::Location;
-use crate::error::{Result, Error};
+use std::sync::atomic::{AtomicBool, AtomicU64, AtomicUsize, Ordering};
+
+use parking_lot::{Mutex, ReentrantMutex, ReentrantMutexGuard};
+
+use rusqlite::OptionalExtension;
+
+use crate::location::{Location, LocationLock, restrict_file};
+use crate::error::{Result, Error, ErrorKind, Context};
+use crate::trace::trace_debug;
 use crate::datetime::Timestamp;
-use super::data::{EncryptedTransaction, EncryptedCategory, EncryptedAccount, EncryptedPlan, Id, CategoryType, MetaInfo};
+use super::data::{EncryptedTransaction, EncryptedTransactionSummary, EncryptedCategory, EncryptedAccount, EncryptedPlan, EncryptedLoan, Id, CategoryType, TransactionKind, MetaInfo};
+use super::data::{EncryptedHolding, EncryptedPricePoint, EncryptedPerson, EncryptedCategoryGroup, EncryptedSettings, JournalPosition, SnapshotId, Merge, MergeKind, CryptoRecord, RecoveryRecord, ItemCount, IndexedField};
+use super::events::{EntityKind, ChangeKind, StorageEvent, ChangeCallback};
 use super::storage::DataStorage;
-use super::{CONSISTENCY_VIOLATION, CANNOT_DELETE_PREDEFINED};
+use super::reader_pool::ReaderPool;
+use super::{CONSISTENCY_VIOLATION, CANNOT_DELETE_PREDEFINED, ITEM_NOT_FOUND, SETTINGS_ID, UNKNOWN_SNAPSHOT, PERIOD_IS_LOCKED};
 
 
 /// Name of DB file.
 const DB_FILE: &str = "database";
 
+/// Maximal number of idle read-only connections.
+const READER_POOL_SIZE: usize = 4;
+
+
+/// Schema migrations applied on top of the initial schema.
+/// 
+/// Each migration is applied exactly once. Number of applied
+/// migrations is stored in DB's `user_version` pragma. New
+/// migrations MUST be appended to the end of the list only.
+const MIGRATIONS: &[&str] = &[
+    // Transaction kinds (opening balances and adjustments)
+    r#"
+        ALTER TABLE transactions
+            ADD COLUMN kind TINYINT NOT NULL DEFAULT 0;
+    "#,
+
+    // Notes and custom fields
+    r#"
+        ALTER TABLE accounts ADD COLUMN note BYTEA NULL;
+        ALTER TABLE accounts ADD COLUMN custom_fields BYTEA NULL;
+
+        ALTER TABLE categories ADD COLUMN note BYTEA NULL;
+        ALTER TABLE categories ADD COLUMN custom_fields BYTEA NULL;
+
+        ALTER TABLE transactions ADD COLUMN note BYTEA NULL;
+        ALTER TABLE transactions ADD COLUMN custom_fields BYTEA NULL;
+
+        ALTER TABLE plans ADD COLUMN note BYTEA NULL;
+        ALTER TABLE plans ADD COLUMN custom_fields BYTEA NULL;
+    "#,
+
+    // Loans
+    r#"
+        CREATE TABLE loans (
+            loan_id                 BLOB        PRIMARY KEY DEFAULT (randomblob(16)),
+            name                    BYTEA       NOT NULL,
+            account_id              BLOB        REFERENCES accounts(account_id),
+            principal_category_id   BLOB        REFERENCES categories(category_id),
+            interest_category_id    BLOB        REFERENCES categories(category_id),
+            principal               BYTEA       NOT NULL,
+            interest_rate           BYTEA       NOT NULL,
+            term                    INTEGER     NOT NULL,
+            start_timestamp         DATETIME    NOT NULL,
+            note                    BYTEA       NULL,
+            custom_fields           BYTEA       NULL,
+            _origin                 BYTEA       NOT NULL,
+            _creation_timestamp     DATETIME    NOT NULL,
+            _change_timestamp       DATETIME    NULL,
+            _removal_timestamp      DATETIME    NULL
+        ) WITHOUT ROWID;
+
+        CREATE INDEX loans_by_account
+            ON loans (account_id);
+
+        CREATE INDEX loans_by_creation_timestamp
+            ON loans (_creation_timestamp);
+
+        CREATE INDEX loans_by_change_timestamp
+            ON loans (_change_timestamp);
+
+        CREATE INDEX loans_by_removal_timestamp
+            ON loans (_removal_timestamp);
+    "#,
+
+    // Investment holdings and prices
+    r#"
+        CREATE TABLE holdings (
+            holding_id          BLOB        PRIMARY KEY DEFAULT (randomblob(16)),
+            account_id          BLOB        REFERENCES accounts(account_id),
+            symbol              BYTEA       NOT NULL,
+            quantity            BYTEA       NOT NULL,
+            note                BYTEA       NULL,
+            custom_fields       BYTEA       NULL,
+            _origin             BYTEA       NOT NULL,
+            _creation_timestamp DATETIME    NOT NULL,
+            _change_timestamp   DATETIME    NULL,
+            _removal_timestamp  DATETIME    NULL
+        ) WITHOUT ROWID;
+
+        CREATE INDEX holdings_by_account
+            ON holdings (account_id);
+
+        CREATE INDEX holdings_by_creation_timestamp
+            ON holdings (_creation_timestamp);
+
+        CREATE INDEX holdings_by_change_timestamp
+            ON holdings (_change_timestamp);
+
+        CREATE INDEX holdings_by_removal_timestamp
+            ON holdings (_removal_timestamp);
+
+        CREATE TABLE prices (
+            price_id            BLOB        PRIMARY KEY DEFAULT (randomblob(16)),
+            symbol              BYTEA       NOT NULL,
+            timestamp           DATETIME    NOT NULL,
+            price               BYTEA       NOT NULL,
+            _origin             BYTEA       NOT NULL,
+            _creation_timestamp DATETIME    NOT NULL,
+            _change_timestamp   DATETIME    NULL,
+            _removal_timestamp  DATETIME    NULL
+        ) WITHOUT ROWID;
+
+        CREATE INDEX prices_by_timestamp
+            ON prices (timestamp);
+
+        CREATE INDEX prices_by_creation_timestamp
+            ON prices (_creation_timestamp);
+
+        CREATE INDEX prices_by_change_timestamp
+            ON prices (_change_timestamp);
+
+        CREATE INDEX prices_by_removal_timestamp
+            ON prices (_removal_timestamp);
+    "#,
+
+    // Per-item visibility
+    r#"
+        CREATE TABLE visibility (
+            item_id             BLOB        PRIMARY KEY,
+            private_to          BLOB        NOT NULL
+        ) WITHOUT ROWID;
+
+        CREATE INDEX visibility_by_instance
+            ON visibility (private_to);
+    "#,
+    // Changes journal filled by triggers
+    r#"
+        CREATE TABLE changes (
+            seq                 INTEGER     PRIMARY KEY AUTOINCREMENT,
+            item_id             BLOB        NOT NULL,
+            change              TINYINT     NOT NULL
+        );
+
+        CREATE TABLE journal_cursor (
+            exported_seq        INTEGER     NOT NULL
+        );
+
+        INSERT INTO journal_cursor (exported_seq) VALUES (0);
+
+        INSERT INTO changes (item_id, change)
+            SELECT transaction_id, 0 FROM transactions ORDER BY _creation_timestamp;
+
+        INSERT INTO changes (item_id, change)
+            SELECT account_id, 0 FROM accounts ORDER BY _creation_timestamp;
+
+        INSERT INTO changes (item_id, change)
+            SELECT category_id, 0 FROM categories ORDER BY _creation_timestamp;
+
+        INSERT INTO changes (item_id, change)
+            SELECT plan_id, 0 FROM plans ORDER BY _creation_timestamp;
+
+        INSERT INTO changes (item_id, change)
+            SELECT loan_id, 0 FROM loans ORDER BY _creation_timestamp;
+
+        INSERT INTO changes (item_id, change)
+            SELECT holding_id, 0 FROM holdings ORDER BY _creation_timestamp;
+
+        INSERT INTO changes (item_id, change)
+            SELECT price_id, 0 FROM prices ORDER BY _creation_timestamp;
+
+        INSERT INTO changes (item_id, change)
+            SELECT transaction_id, 1 FROM transactions WHERE _change_timestamp IS NOT NULL;
+
+        INSERT INTO changes (item_id, change)
+            SELECT account_id, 1 FROM accounts WHERE _change_timestamp IS NOT NULL;
+
+        INSERT INTO changes (item_id, change)
+            SELECT category_id, 1 FROM categories WHERE _change_timestamp IS NOT NULL;
+
+        INSERT INTO changes (item_id, change)
+            SELECT plan_id, 1 FROM plans WHERE _change_timestamp IS NOT NULL;
+
+        INSERT INTO changes (item_id, change)
+            SELECT loan_id, 1 FROM loans WHERE _change_timestamp IS NOT NULL;
+
+        INSERT INTO changes (item_id, change)
+            SELECT holding_id, 1 FROM holdings WHERE _change_timestamp IS NOT NULL;
+
+        INSERT INTO changes (item_id, change)
+            SELECT price_id, 1 FROM prices WHERE _change_timestamp IS NOT NULL;
+
+        INSERT INTO changes (item_id, change)
+            SELECT transaction_id, 2 FROM transactions WHERE _removal_timestamp IS NOT NULL;
+
+        INSERT INTO changes (item_id, change)
+            SELECT account_id, 2 FROM accounts WHERE _removal_timestamp IS NOT NULL;
+
+        INSERT INTO changes (item_id, change)
+            SELECT category_id, 2 FROM categories WHERE _removal_timestamp IS NOT NULL;
+
+        INSERT INTO changes (item_id, change)
+            SELECT plan_id, 2 FROM plans WHERE _removal_timestamp IS NOT NULL;
+
+        INSERT INTO changes (item_id, change)
+            SELECT loan_id, 2 FROM loans WHERE _removal_timestamp IS NOT NULL;
+
+        INSERT INTO changes (item_id, change)
+            SELECT holding_id, 2 FROM holdings WHERE _removal_timestamp IS NOT NULL;
+
+        INSERT INTO changes (item_id, change)
+            SELECT price_id, 2 FROM prices WHERE _removal_timestamp IS NOT NULL;
+
+        CREATE TRIGGER transactions_added AFTER INSERT ON transactions
+        BEGIN
+            INSERT INTO changes (item_id, change) VALUES (NEW.transaction_id, 0);
+        END;
+
+        CREATE TRIGGER transactions_changed AFTER UPDATE OF _change_timestamp ON transactions
+            WHEN NEW._change_timestamp IS NOT NULL AND
+                 NEW._change_timestamp IS NOT OLD._change_timestamp
+        BEGIN
+            INSERT INTO changes (item_id, change) VALUES (NEW.transaction_id, 1);
+        END;
+
+        CREATE TRIGGER transactions_removed AFTER UPDATE OF _removal_timestamp ON transactions
+            WHEN NEW._removal_timestamp IS NOT NULL AND
+                 OLD._removal_timestamp IS NULL
+        BEGIN
+            INSERT INTO changes (item_id, change) VALUES (NEW.transaction_id, 2);
+        END;
+
+        CREATE TRIGGER accounts_added AFTER INSERT ON accounts
+        BEGIN
+            INSERT INTO changes (item_id, change) VALUES (NEW.account_id, 0);
+        END;
+
+        CREATE TRIGGER accounts_changed AFTER UPDATE OF _change_timestamp ON accounts
+            WHEN NEW._change_timestamp IS NOT NULL AND
+                 NEW._change_timestamp IS NOT OLD._change_timestamp
+        BEGIN
+            INSERT INTO changes (item_id, change) VALUES (NEW.account_id, 1);
+        END;
+
+        CREATE TRIGGER accounts_removed AFTER UPDATE OF _removal_timestamp ON accounts
+            WHEN NEW._removal_timestamp IS NOT NULL AND
+                 OLD._removal_timestamp IS NULL
+        BEGIN
+            INSERT INTO changes (item_id, change) VALUES (NEW.account_id, 2);
+        END;
+
+        CREATE TRIGGER categories_added AFTER INSERT ON categories
+        BEGIN
+            INSERT INTO changes (item_id, change) VALUES (NEW.category_id, 0);
+        END;
+
+        CREATE TRIGGER categories_changed AFTER UPDATE OF _change_timestamp ON categories
+            WHEN NEW._change_timestamp IS NOT NULL AND
+                 NEW._change_timestamp IS NOT OLD._change_timestamp
+        BEGIN
+            INSERT INTO changes (item_id, change) VALUES (NEW.category_id, 1);
+        END;
+
+        CREATE TRIGGER categories_removed AFTER UPDATE OF _removal_timestamp ON categories
+            WHEN NEW._removal_timestamp IS NOT NULL AND
+                 OLD._removal_timestamp IS NULL
+        BEGIN
+            INSERT INTO changes (item_id, change) VALUES (NEW.category_id, 2);
+        END;
+
+        CREATE TRIGGER plans_added AFTER INSERT ON plans
+        BEGIN
+            INSERT INTO changes (item_id, change) VALUES (NEW.plan_id, 0);
+        END;
+
+        CREATE TRIGGER plans_changed AFTER UPDATE OF _change_timestamp ON plans
+            WHEN NEW._change_timestamp IS NOT NULL AND
+                 NEW._change_timestamp IS NOT OLD._change_timestamp
+        BEGIN
+            INSERT INTO changes (item_id, change) VALUES (NEW.plan_id, 1);
+        END;
+
+        CREATE TRIGGER plans_removed AFTER UPDATE OF _removal_timestamp ON plans
+            WHEN NEW._removal_timestamp IS NOT NULL AND
+                 OLD._removal_timestamp IS NULL
+        BEGIN
+            INSERT INTO changes (item_id, change) VALUES (NEW.plan_id, 2);
+        END;
+
+        CREATE TRIGGER loans_added AFTER INSERT ON loans
+        BEGIN
+            INSERT INTO changes (item_id, change) VALUES (NEW.loan_id, 0);
+        END;
+
+        CREATE TRIGGER loans_changed AFTER UPDATE OF _change_timestamp ON loans
+            WHEN NEW._change_timestamp IS NOT NULL AND
+                 NEW._change_timestamp IS NOT OLD._change_timestamp
+        BEGIN
+            INSERT INTO changes (item_id, change) VALUES (NEW.loan_id, 1);
+        END;
+
+        CREATE TRIGGER loans_removed AFTER UPDATE OF _removal_timestamp ON loans
+            WHEN NEW._removal_timestamp IS NOT NULL AND
+                 OLD._removal_timestamp IS NULL
+        BEGIN
+            INSERT INTO changes (item_id, change) VALUES (NEW.loan_id, 2);
+        END;
+
+        CREATE TRIGGER holdings_added AFTER INSERT ON holdings
+        BEGIN
+            INSERT INTO changes (item_id, change) VALUES (NEW.holding_id, 0);
+        END;
+
+        CREATE TRIGGER holdings_changed AFTER UPDATE OF _change_timestamp ON holdings
+            WHEN NEW._change_timestamp IS NOT NULL AND
+                 NEW._change_timestamp IS NOT OLD._change_timestamp
+        BEGIN
+            INSERT INTO changes (item_id, change) VALUES (NEW.holding_id, 1);
+        END;
+
+        CREATE TRIGGER holdings_removed AFTER UPDATE OF _removal_timestamp ON holdings
+            WHEN NEW._removal_timestamp IS NOT NULL AND
+                 OLD._removal_timestamp IS NULL
+        BEGIN
+            INSERT INTO changes (item_id, change) VALUES (NEW.holding_id, 2);
+        END;
+
+        CREATE TRIGGER prices_added AFTER INSERT ON prices
+        BEGIN
+            INSERT INTO changes (item_id, change) VALUES (NEW.price_id, 0);
+        END;
+
+        CREATE TRIGGER prices_changed AFTER UPDATE OF _change_timestamp ON prices
+            WHEN NEW._change_timestamp IS NOT NULL AND
+                 NEW._change_timestamp IS NOT OLD._change_timestamp
+        BEGIN
+            INSERT INTO changes (item_id, change) VALUES (NEW.price_id, 1);
+        END;
+
+        CREATE TRIGGER prices_removed AFTER UPDATE OF _removal_timestamp ON prices
+            WHEN NEW._removal_timestamp IS NOT NULL AND
+                 OLD._removal_timestamp IS NULL
+        BEGIN
+            INSERT INTO changes (item_id, change) VALUES (NEW.price_id, 2);
+        END;
+    "#,
+    // Settings shared between instances
+    r#"
+        CREATE TABLE settings (
+            settings_id         INTEGER     PRIMARY KEY CHECK (settings_id = 0),
+            data                BYTEA       NOT NULL,
+            _origin             BYTEA       NOT NULL,
+            _change_timestamp   DATETIME    NOT NULL
+        );
+
+        CREATE TRIGGER settings_added AFTER INSERT ON settings
+        BEGIN
+            INSERT INTO changes (item_id, change) VALUES (X'73657474696E67730000000000000000', 1);
+        END;
+
+        CREATE TRIGGER settings_changed AFTER UPDATE ON settings
+        BEGIN
+            INSERT INTO changes (item_id, change) VALUES (X'73657474696E67730000000000000000', 1);
+        END;
+    "#,
+    // Period lock (local to an instance, hence not journaled)
+    r#"
+        CREATE TABLE period_lock (
+            period_lock_id      INTEGER     PRIMARY KEY CHECK (period_lock_id = 0),
+            locked_until        DATETIME    NULL
+        );
+
+        INSERT INTO period_lock (period_lock_id, locked_until) VALUES (0, NULL);
+    "#,
+    // Semantics of transaction timestamps (local to an instance)
+    r#"
+        CREATE TABLE transaction_dates (
+            transaction_dates_id    INTEGER     PRIMARY KEY CHECK (transaction_dates_id = 0),
+            canonical               BOOLEAN     NOT NULL
+        );
+
+        INSERT INTO transaction_dates (transaction_dates_id, canonical) VALUES (0, FALSE);
+    "#,
+    // Merges of categories and accounts
+    r#"
+        CREATE TABLE merges (
+            merge_id            INTEGER     PRIMARY KEY,
+            kind                TINYINT     NOT NULL,
+            kept                BLOB        NOT NULL,
+            removed             BLOB        NOT NULL,
+            seq                 INTEGER     NOT NULL,
+            _origin             BYTEA       NOT NULL,
+            _creation_timestamp DATETIME    NOT NULL
+        );
+    "#,
+    // Record of cryptographic engine (local to an instance)
+    r#"
+        CREATE TABLE crypto_record (
+            crypto_record_id    INTEGER     PRIMARY KEY CHECK (crypto_record_id = 0),
+            engine              TEXT        NOT NULL,
+            algorithm           TEXT        NOT NULL
+        );
+    "#,
+    // Deterministic encryption of descriptions (local to an instance)
+    r#"
+        CREATE TABLE queryable_fields (
+            queryable_fields_id INTEGER     PRIMARY KEY CHECK (queryable_fields_id = 0),
+            descriptions        BOOLEAN     NOT NULL
+        );
+
+        INSERT INTO queryable_fields (queryable_fields_id, descriptions) VALUES (0, FALSE);
+
+        CREATE INDEX transactions_by_description
+            ON transactions (description);
+    "#,
+    // Fingerprint of a key, that data belongs to (local to an instance)
+    r#"
+        CREATE TABLE key_pin (
+            key_pin_id          INTEGER     PRIMARY KEY CHECK (key_pin_id = 0),
+            fingerprint         TEXT        NOT NULL
+        );
+    "#,
+    // Recovery key of emergency exports (local to an instance)
+    r#"
+        CREATE TABLE recovery_key (
+            recovery_key_id     INTEGER     PRIMARY KEY CHECK (recovery_key_id = 0),
+            salt                BYTEA       NOT NULL,
+            export_key          BYTEA       NOT NULL
+        );
+    "#,
+
+    // Alert thresholds of plans
+    r#"
+        ALTER TABLE plans ADD COLUMN alert_threshold BYTEA NULL;
+    "#,
+
+    // Indexes for filtering of transactions by accounts and categories
+    r#"
+        CREATE INDEX transactions_by_account
+            ON transactions (account_id, timestamp);
+
+        CREATE INDEX transactions_by_category
+            ON transactions (category_id, timestamp);
+    "#,
+
+    // Numbers of items maintained by triggers
+    r#"
+        CREATE TABLE item_counts (
+            entity              TEXT        PRIMARY KEY,
+            count               INTEGER     NOT NULL
+        ) WITHOUT ROWID;
+
+        INSERT INTO item_counts (entity, count)
+            SELECT 'transactions', COUNT(*) FROM transactions WHERE _removal_timestamp IS NULL;
+
+        INSERT INTO item_counts (entity, count)
+            SELECT 'accounts', COUNT(*) FROM accounts WHERE _removal_timestamp IS NULL;
+
+        INSERT INTO item_counts (entity, count)
+            SELECT 'categories', COUNT(*) FROM categories WHERE _removal_timestamp IS NULL;
+
+        INSERT INTO item_counts (entity, count)
+            SELECT 'plans', COUNT(*) FROM plans WHERE _removal_timestamp IS NULL;
+
+        INSERT INTO item_counts (entity, count)
+            SELECT 'loans', COUNT(*) FROM loans WHERE _removal_timestamp IS NULL;
+
+        INSERT INTO item_counts (entity, count)
+            SELECT 'holdings', COUNT(*) FROM holdings WHERE _removal_timestamp IS NULL;
+
+        INSERT INTO item_counts (entity, count)
+            SELECT 'prices', COUNT(*) FROM prices WHERE _removal_timestamp IS NULL;
+
+        CREATE TRIGGER transactions_counted_added AFTER INSERT ON transactions
+            WHEN NEW._removal_timestamp IS NULL
+        BEGIN
+            UPDATE item_counts SET count = count + 1 WHERE entity = 'transactions';
+        END;
+
+        CREATE TRIGGER transactions_counted_removed AFTER UPDATE OF _removal_timestamp ON transactions
+            WHEN (NEW._removal_timestamp IS NULL) != (OLD._removal_timestamp IS NULL)
+        BEGIN
+            UPDATE item_counts
+               SET count = count + CASE WHEN NEW._removal_timestamp IS NULL THEN 1 ELSE -1 END
+             WHERE entity = 'transactions';
+        END;
+
+        CREATE TRIGGER transactions_counted_deleted AFTER DELETE ON transactions
+            WHEN OLD._removal_timestamp IS NULL
+        BEGIN
+            UPDATE item_counts SET count = count - 1 WHERE entity = 'transactions';
+        END;
+
+        CREATE TRIGGER accounts_counted_added AFTER INSERT ON accounts
+            WHEN NEW._removal_timestamp IS NULL
+        BEGIN
+            UPDATE item_counts SET count = count + 1 WHERE entity = 'accounts';
+        END;
+
+        CREATE TRIGGER accounts_counted_removed AFTER UPDATE OF _removal_timestamp ON accounts
+            WHEN (NEW._removal_timestamp IS NULL) != (OLD._removal_timestamp IS NULL)
+        BEGIN
+            UPDATE item_counts
+               SET count = count + CASE WHEN NEW._removal_timestamp IS NULL THEN 1 ELSE -1 END
+             WHERE entity = 'accounts';
+        END;
+
+        CREATE TRIGGER accounts_counted_deleted AFTER DELETE ON accounts
+            WHEN OLD._removal_timestamp IS NULL
+        BEGIN
+            UPDATE item_counts SET count = count - 1 WHERE entity = 'accounts';
+        END;
+
+        CREATE TRIGGER categories_counted_added AFTER INSERT ON categories
+            WHEN NEW._removal_timestamp IS NULL
+        BEGIN
+            UPDATE item_counts SET count = count + 1 WHERE entity = 'categories';
+        END;
+
+        CREATE TRIGGER categories_counted_removed AFTER UPDATE OF _removal_timestamp ON categories
+            WHEN (NEW._removal_timestamp IS NULL) != (OLD._removal_timestamp IS NULL)
+        BEGIN
+            UPDATE item_counts
+               SET count = count + CASE WHEN NEW._removal_timestamp IS NULL THEN 1 ELSE -1 END
+             WHERE entity = 'categories';
+        END;
+
+        CREATE TRIGGER categories_counted_deleted AFTER DELETE ON categories
+            WHEN OLD._removal_timestamp IS NULL
+        BEGIN
+            UPDATE item_counts SET count = count - 1 WHERE entity = 'categories';
+        END;
+
+        CREATE TRIGGER plans_counted_added AFTER INSERT ON plans
+            WHEN NEW._removal_timestamp IS NULL
+        BEGIN
+            UPDATE item_counts SET count = count + 1 WHERE entity = 'plans';
+        END;
+
+        CREATE TRIGGER plans_counted_removed AFTER UPDATE OF _removal_timestamp ON plans
+            WHEN (NEW._removal_timestamp IS NULL) != (OLD._removal_timestamp IS NULL)
+        BEGIN
+            UPDATE item_counts
+               SET count = count + CASE WHEN NEW._removal_timestamp IS NULL THEN 1 ELSE -1 END
+             WHERE entity = 'plans';
+        END;
+
+        CREATE TRIGGER plans_counted_deleted AFTER DELETE ON plans
+            WHEN OLD._removal_timestamp IS NULL
+        BEGIN
+            UPDATE item_counts SET count = count - 1 WHERE entity = 'plans';
+        END;
+
+        CREATE TRIGGER loans_counted_added AFTER INSERT ON loans
+            WHEN NEW._removal_timestamp IS NULL
+        BEGIN
+            UPDATE item_counts SET count = count + 1 WHERE entity = 'loans';
+        END;
+
+        CREATE TRIGGER loans_counted_removed AFTER UPDATE OF _removal_timestamp ON loans
+            WHEN (NEW._removal_timestamp IS NULL) != (OLD._removal_timestamp IS NULL)
+        BEGIN
+            UPDATE item_counts
+               SET count = count + CASE WHEN NEW._removal_timestamp IS NULL THEN 1 ELSE -1 END
+             WHERE entity = 'loans';
+        END;
+
+        CREATE TRIGGER loans_counted_deleted AFTER DELETE ON loans
+            WHEN OLD._removal_timestamp IS NULL
+        BEGIN
+            UPDATE item_counts SET count = count - 1 WHERE entity = 'loans';
+        END;
+
+        CREATE TRIGGER holdings_counted_added AFTER INSERT ON holdings
+            WHEN NEW._removal_timestamp IS NULL
+        BEGIN
+            UPDATE item_counts SET count = count + 1 WHERE entity = 'holdings';
+        END;
+
+        CREATE TRIGGER holdings_counted_removed AFTER UPDATE OF _removal_timestamp ON holdings
+            WHEN (NEW._removal_timestamp IS NULL) != (OLD._removal_timestamp IS NULL)
+        BEGIN
+            UPDATE item_counts
+               SET count = count + CASE WHEN NEW._removal_timestamp IS NULL THEN 1 ELSE -1 END
+             WHERE entity = 'holdings';
+        END;
+
+        CREATE TRIGGER holdings_counted_deleted AFTER DELETE ON holdings
+            WHEN OLD._removal_timestamp IS NULL
+        BEGIN
+            UPDATE item_counts SET count = count - 1 WHERE entity = 'holdings';
+        END;
+
+        CREATE TRIGGER prices_counted_added AFTER INSERT ON prices
+            WHEN NEW._removal_timestamp IS NULL
+        BEGIN
+            UPDATE item_counts SET count = count + 1 WHERE entity = 'prices';
+        END;
+
+        CREATE TRIGGER prices_counted_removed AFTER UPDATE OF _removal_timestamp ON prices
+            WHEN (NEW._removal_timestamp IS NULL) != (OLD._removal_timestamp IS NULL)
+        BEGIN
+            UPDATE item_counts
+               SET count = count + CASE WHEN NEW._removal_timestamp IS NULL THEN 1 ELSE -1 END
+             WHERE entity = 'prices';
+        END;
+
+        CREATE TRIGGER prices_counted_deleted AFTER DELETE ON prices
+            WHEN OLD._removal_timestamp IS NULL
+        BEGIN
+            UPDATE item_counts SET count = count - 1 WHERE entity = 'prices';
+        END;
+    "#,
+
+    // Index of values derived from encrypted transactions (local to an instance)
+    r#"
+        CREATE TABLE transaction_index (
+            transaction_id      BLOB        NOT NULL,
+            field               TINYINT     NOT NULL,
+            value               BLOB        NOT NULL
+        );
+
+        CREATE INDEX transaction_index_by_value
+            ON transaction_index (field, value);
+
+        CREATE INDEX transaction_index_by_transaction
+            ON transaction_index (transaction_id, field);
+
+        CREATE TABLE transaction_index_state (
+            transaction_index_state_id  INTEGER     PRIMARY KEY CHECK (transaction_index_state_id = 0),
+            version                     INTEGER     NOT NULL
+        );
+
+        INSERT INTO transaction_index_state (transaction_index_state_id, version) VALUES (0, 0);
+
+        CREATE TRIGGER transactions_unindexed AFTER DELETE ON transactions
+        BEGIN
+            DELETE FROM transaction_index WHERE transaction_id = OLD.transaction_id;
+        END;
+    "#,
+
+    // Persons within a household and spenders of transactions
+    r#"
+        CREATE TABLE persons (
+            person_id           BLOB        PRIMARY KEY DEFAULT (randomblob(16)),
+            name                BYTEA       NOT NULL,
+            _origin             BYTEA       NOT NULL,
+            _creation_timestamp DATETIME    NOT NULL,
+            _change_timestamp   DATETIME    NULL,
+            _removal_timestamp  DATETIME    NULL
+        ) WITHOUT ROWID;
+
+        CREATE INDEX persons_by_creation_timestamp
+            ON persons (_creation_timestamp);
+
+        CREATE INDEX persons_by_change_timestamp
+            ON persons (_change_timestamp);
+
+        CREATE INDEX persons_by_removal_timestamp
+            ON persons (_removal_timestamp);
+
+        ALTER TABLE transactions ADD COLUMN spender_id BLOB NULL;
+
+        CREATE TRIGGER persons_added AFTER INSERT ON persons
+        BEGIN
+            INSERT INTO changes (item_id, change) VALUES (NEW.person_id, 0);
+        END;
+
+        CREATE TRIGGER persons_changed AFTER UPDATE OF _change_timestamp ON persons
+            WHEN NEW._change_timestamp IS NOT NULL AND
+                 NEW._change_timestamp IS NOT OLD._change_timestamp
+        BEGIN
+            INSERT INTO changes (item_id, change) VALUES (NEW.person_id, 1);
+        END;
+
+        CREATE TRIGGER persons_removed AFTER UPDATE OF _removal_timestamp ON persons
+            WHEN NEW._removal_timestamp IS NOT NULL AND
+                 OLD._removal_timestamp IS NULL
+        BEGIN
+            INSERT INTO changes (item_id, change) VALUES (NEW.person_id, 2);
+        END;
+
+        INSERT INTO item_counts (entity, count) VALUES ('persons', 0);
+
+        CREATE TRIGGER persons_counted_added AFTER INSERT ON persons
+            WHEN NEW._removal_timestamp IS NULL
+        BEGIN
+            UPDATE item_counts SET count = count + 1 WHERE entity = 'persons';
+        END;
+
+        CREATE TRIGGER persons_counted_removed AFTER UPDATE OF _removal_timestamp ON persons
+            WHEN (NEW._removal_timestamp IS NULL) != (OLD._removal_timestamp IS NULL)
+        BEGIN
+            UPDATE item_counts
+               SET count = count + CASE WHEN NEW._removal_timestamp IS NULL THEN 1 ELSE -1 END
+             WHERE entity = 'persons';
+        END;
+
+        CREATE TRIGGER persons_counted_deleted AFTER DELETE ON persons
+            WHEN OLD._removal_timestamp IS NULL
+        BEGIN
+            UPDATE item_counts SET count = count - 1 WHERE entity = 'persons';
+        END;
+    "#,
+
+    // Groups of categories (categories are stored as concatenated identifiers)
+    r#"
+        CREATE TABLE category_groups (
+            group_id            BLOB        PRIMARY KEY DEFAULT (randomblob(16)),
+            name                BYTEA       NOT NULL,
+            categories          BLOB        NOT NULL,
+            _origin             BYTEA       NOT NULL,
+            _creation_timestamp DATETIME    NOT NULL,
+            _change_timestamp   DATETIME    NULL,
+            _removal_timestamp  DATETIME    NULL
+        ) WITHOUT ROWID;
+
+        CREATE INDEX category_groups_by_creation_timestamp
+            ON category_groups (_creation_timestamp);
+
+        CREATE INDEX category_groups_by_change_timestamp
+            ON category_groups (_change_timestamp);
+
+        CREATE INDEX category_groups_by_removal_timestamp
+            ON category_groups (_removal_timestamp);
+
+        CREATE TRIGGER category_groups_added AFTER INSERT ON category_groups
+        BEGIN
+            INSERT INTO changes (item_id, change) VALUES (NEW.group_id, 0);
+        END;
+
+        CREATE TRIGGER category_groups_changed AFTER UPDATE OF _change_timestamp ON category_groups
+            WHEN NEW._change_timestamp IS NOT NULL AND
+                 NEW._change_timestamp IS NOT OLD._change_timestamp
+        BEGIN
+            INSERT INTO changes (item_id, change) VALUES (NEW.group_id, 1);
+        END;
+
+        CREATE TRIGGER category_groups_removed AFTER UPDATE OF _removal_timestamp ON category_groups
+            WHEN NEW._removal_timestamp IS NOT NULL AND
+                 OLD._removal_timestamp IS NULL
+        BEGIN
+            INSERT INTO changes (item_id, change) VALUES (NEW.group_id, 2);
+        END;
+
+        INSERT INTO item_counts (entity, count) VALUES ('category_groups', 0);
+
+        CREATE TRIGGER category_groups_counted_added AFTER INSERT ON category_groups
+            WHEN NEW._removal_timestamp IS NULL
+        BEGIN
+            UPDATE item_counts SET count = count + 1 WHERE entity = 'category_groups';
+        END;
+
+        CREATE TRIGGER category_groups_counted_removed AFTER UPDATE OF _removal_timestamp ON category_groups
+            WHEN (NEW._removal_timestamp IS NULL) != (OLD._removal_timestamp IS NULL)
+        BEGIN
+            UPDATE item_counts
+               SET count = count + CASE WHEN NEW._removal_timestamp IS NULL THEN 1 ELSE -1 END
+             WHERE entity = 'category_groups';
+        END;
+
+        CREATE TRIGGER category_groups_counted_deleted AFTER DELETE ON category_groups
+            WHEN OLD._removal_timestamp IS NULL
+        BEGIN
+            UPDATE item_counts SET count = count - 1 WHERE entity = 'category_groups';
+        END;
+    "#,
+];
+
 
 /// Implementation of [`rusqlite::types::ToSql`] trait for [`CategoryType`].
 /// 
@@ -43,10 +811,136 @@ impl rusqlite::types::FromSql for CategoryType {
 }
 
 
+/// Implementation of [`rusqlite::types::ToSql`] trait for [`TransactionKind`].
+/// 
+/// [`TransactionKind::Regular`] translates into 0, [`TransactionKind::OpeningBalance`] -- 
+/// into 1, [`TransactionKind::Adjustment`] -- into 2.
+impl rusqlite::types::ToSql for TransactionKind {
+    fn to_sql(&self) -> rusqlite::Result<rusqlite::types::ToSqlOutput<'_>> {
+        let internal_value = match self {
+            TransactionKind::Regular        => 0i64,
+            TransactionKind::OpeningBalance => 1i64,
+            TransactionKind::Adjustment     => 2i64,
+        };
+
+        Ok(rusqlite::types::ToSqlOutput::Borrowed(
+            rusqlite::types::ValueRef::Integer(internal_value)
+        ))
+    }
+}
+
+
+/// Implementation of [`rusqlite::types::FromSql`] for [`TransactionKind`].
+/// 
+/// Checks for invalid values in database, translates only valid values.
+impl rusqlite::types::FromSql for TransactionKind {
+    fn column_result(value: rusqlite::types::ValueRef<'_>) -> rusqlite::types::FromSqlResult<Self> {
+        match value.as_i64()? {
+            0 => Ok(TransactionKind::Regular),
+            1 => Ok(TransactionKind::OpeningBalance),
+            2 => Ok(TransactionKind::Adjustment),
+            
+            // Other integer values are wrong!
+            v => Err(rusqlite::types::FromSqlError::OutOfRange(v)),
+        }
+    }
+}
+
+
+/// Implementation of [`rusqlite::types::ToSql`] trait for [`MergeKind`].
+/// 
+/// [`MergeKind::Category`] translates into 0, [`MergeKind::Account`] -- into 1.
+impl rusqlite::types::ToSql for MergeKind {
+    fn to_sql(&self) -> rusqlite::Result<rusqlite::types::ToSqlOutput<'_>> {
+        let internal_value = match self {
+            MergeKind::Category => 0i64,
+            MergeKind::Account  => 1i64,
+        };
+
+        Ok(rusqlite::types::ToSqlOutput::Borrowed(
+            rusqlite::types::ValueRef::Integer(internal_value)
+        ))
+    }
+}
+
+
+/// Implementation of [`rusqlite::types::ToSql`] trait for [`IndexedField`].
+/// 
+/// [`IndexedField::ExternalId`] translates into 0.
+/// [`IndexedField::Location`] translates into 1.
+impl rusqlite::types::ToSql for IndexedField {
+    fn to_sql(&self) -> rusqlite::Result<rusqlite::types::ToSqlOutput<'_>> {
+        let internal_value = match self {
+            IndexedField::ExternalId => 0i64,
+            IndexedField::Location => 1i64,
+        };
+
+        Ok(rusqlite::types::ToSqlOutput::Borrowed(
+            rusqlite::types::ValueRef::Integer(internal_value)
+        ))
+    }
+}
+
+
+/// Implementation of [`rusqlite::types::FromSql`] for [`MergeKind`].
+/// 
+/// Checks for invalid values in database, translates only valid values.
+impl rusqlite::types::FromSql for MergeKind {
+    fn column_result(value: rusqlite::types::ValueRef<'_>) -> rusqlite::types::FromSqlResult<Self> {
+        match value.as_i64()? {
+            0 => Ok(MergeKind::Category),
+            1 => Ok(MergeKind::Account),
+            
+            // Other integer values are wrong!
+            v => Err(rusqlite::types::FromSqlError::OutOfRange(v)),
+        }
+    }
+}
+
+
 /// Storage implemented using SQLite.
+///
+/// The storage is [`Send`] and [`Sync`], so it can be shared between
+/// threads (e.g. a GUI thread and worker threads) via [`std::sync::Arc`].
+/// Queries run concurrently on a pool of read-only connections, while
+/// changes are serialized through a single writer connection.
+///
+/// Snapshots are global: changes made by any thread after a snapshot
+/// are rolled back with it, and until the last snapshot is dropped
+/// queries of all threads go through the writer connection. Change
+/// callbacks are invoked on a thread, that committed a change: events
+/// are queued until changes are committed and dropped on rollback.
 pub struct DbStorage {
-    /// Database connection
-    db: rusqlite::Connection
+    /// Database connection, that all changes are made through
+    writer: ReentrantMutex<rusqlite::Connection>,
+
+    /// Read-only connections for queries
+    readers: ReaderPool,
+
+    /// Callbacks to invoke after each mutation
+    observers: Mutex<Vec<ChangeCallback>>,
+
+    /// Events of changes, that are not committed yet
+    events: Mutex<Vec<StorageEvent>>,
+
+    /// Active snapshots (savepoints) from the oldest to the newest
+    /// along with numbers of events queued before them
+    snapshots: Mutex<Vec<(SnapshotId, usize)>>,
+
+    /// Identifier of the next snapshot
+    next_snapshot: AtomicU64,
+
+    /// If locked transactions can be modified
+    lock_overridden: AtomicBool,
+
+    /// Number of changes per batch transaction (zero if batching is disabled)
+    batch_size: AtomicUsize,
+
+    /// Number of changes made in the current batch transaction
+    pending: AtomicUsize,
+
+    /// Lock of location, that prevents concurrent access from other processes
+    _lock: LocationLock,
 } 
 
 
@@ -62,22 +956,65 @@ impl DbStorage {
         loc.create_if_absent()?;
 
         //
-        // Now I just open DB and create schema
+        // Now I just open DB, create initial schema and
+        // bring it to the actual version
         //
 
-        let storage = Self::open(loc)?;
-        storage
-            .create_db()
-            .and(Ok(storage))
+        let storage = Self::open_connection(loc)?;
+        storage.create_db()?;
+        storage.migrate_db()?;
+
+        restrict_file(Self::db_path(loc))?;
+
+        Ok(storage)
     }
 
     /// Opens an existing database in provided location.
     /// 
+    /// Schema of the database is upgraded to the latest version if necessary.
+    /// 
     /// * `loc` - storage location provider
     pub fn open<L: Location>(loc: &L) -> Result<Self> {
-        Ok(DbStorage { 
-            db: rusqlite::Connection::open(Self::db_path(loc))?
-        })
+        let storage = Self::open_connection(loc)?;
+        storage.migrate_db()?;
+
+        Ok(storage)
+    }
+
+    /// Returns a plan of a query (output of `EXPLAIN QUERY PLAN`) one
+    /// step per line, nested steps are indented. Intended for diagnostics
+    /// of slow queries, e.g. to check if a filter uses an index.
+    /// 
+    /// Parameters of the query are bound to `NULL`, they do not affect a plan.
+    /// 
+    /// * `query` - SQL query to explain
+    pub fn query_plan(&self, query: &str) -> Result<Vec<String>> {
+        let db = self.db();
+        let mut statement = db.prepare(&format!("EXPLAIN QUERY PLAN {}", query))?;
+
+        let params = vec![rusqlite::types::Null; statement.parameter_count()];
+        let steps = statement
+            .query_map(rusqlite::params_from_iter(params), 
+                |row| Ok((row.get(0)?, row.get(1)?, row.get::<_, String>(3)?)))?
+            .collect::<rusqlite::Result<Vec<(i64, i64, String)>>>()?;
+
+        //
+        // Steps refer to their parents, that always precede them
+        //
+
+        let mut depths = std::collections::HashMap::new();
+        let plan = steps
+            .into_iter()
+            .map(|(id, parent, detail)| {
+                let depth = depths.get(&parent)
+                    .map_or(0, |depth| depth + 1);
+
+                depths.insert(id, depth);
+                format!("{}{}", "  ".repeat(depth), detail)
+            })
+            .collect();
+
+        Ok(plan)
     }
 }
 
@@ -87,43 +1024,132 @@ impl DataStorage for DbStorage {
 
     const TRANSFER_OUTCOME_ID: Id = [0xFF; 16];
 
+    const ADJUSTMENT_INCOME_ID: Id = [0x0F; 16];
+
+    const ADJUSTMENT_OUTCOME_ID: Id = [0xF0; 16];
+
     fn add_transaction(&self, transaction: EncryptedTransaction) -> Result<()> {
+        let _guard = self.db();
+
+        self.ensure_unlocked(transaction.timestamp)?;
+
         let statement_fmt = match transaction.id {
             None => r#"
-                INSERT INTO transactions (timestamp, description, account_id, category_id, amount, _origin, _creation_timestamp)
-                VALUES (?1, ?2, ?3, ?4, ?5, ?6, ?7)
+                INSERT INTO transactions (timestamp, description, account_id, category_id, amount, kind, note, custom_fields, spender_id, _origin, _creation_timestamp)
+                VALUES (?1, ?2, ?3, ?4, ?5, ?6, ?7, ?8, ?9, ?10, ?11)
+                RETURNING transaction_id
             "#,
             Some(_) => r#"
-                INSERT INTO transactions (transaction_id, timestamp, description, account_id, category_id, amount, _origin, _creation_timestamp)
-                VALUES (?1, ?2, ?3, ?4, ?5, ?6, ?7, ?8)
+                INSERT INTO transactions (transaction_id, timestamp, description, account_id, category_id, amount, kind, note, custom_fields, spender_id, _origin, _creation_timestamp)
+                VALUES (?1, ?2, ?3, ?4, ?5, ?6, ?7, ?8, ?9, ?10, ?11, ?12)
+                RETURNING transaction_id
             "#
         };
         
-        match transaction.id {
-            None => self.db.execute(statement_fmt, 
+        let id: Id = match transaction.id {
+            None => self.db().query_row(statement_fmt, 
                 rusqlite::params![transaction.timestamp, transaction.description, transaction.account_id, 
-                    transaction.category_id, transaction.amount, transaction.meta_info.origin, 
-                    transaction.meta_info.added_timestamp])?,
+                    transaction.category_id, transaction.amount, transaction.kind, transaction.note, 
+                    transaction.custom_fields, transaction.spender_id, transaction.meta_info.origin, 
+                    transaction.meta_info.added_timestamp],
+                |row| row.get(0))?,
                 
-            Some(id) => self.db.execute(statement_fmt, 
+            Some(id) => self.db().query_row(statement_fmt, 
                 rusqlite::params![id, transaction.timestamp, transaction.description, transaction.account_id, 
-                    transaction.category_id, transaction.amount, transaction.meta_info.origin,
-                    transaction.meta_info.added_timestamp])?
+                    transaction.category_id, transaction.amount, transaction.kind, transaction.note, 
+                    transaction.custom_fields, transaction.spender_id, transaction.meta_info.origin,
+                    transaction.meta_info.added_timestamp],
+                |row| row.get(0))?
         };
 
+        self.notify(EntityKind::Transaction, ChangeKind::Added, id)?;
+
+        Ok(())
+    }
+
+    fn update_transaction(&self, transaction: EncryptedTransaction) -> Result<()> {
+        let _guard = self.db();
+
+        let statement_fmt = r#"
+            SELECT timestamp
+              FROM transactions
+             WHERE transaction_id = ?1 AND
+                   _removal_timestamp IS NULL
+        "#;
+
+        let timestamp: Option<Timestamp> = self.db()
+            .query_row(statement_fmt, rusqlite::params![transaction.id], |row| row.get(0))
+            .optional()?;
+
+        if let Some(timestamp) = timestamp {
+            self.ensure_unlocked(timestamp)?;
+        }
+
+        self.ensure_unlocked(transaction.timestamp)?;
+
+        //
+        // Change timestamp is taken from meta information, 
+        // it is not updated if absent
+        //
+
+        let statement_fmt = r#"
+            UPDATE transactions
+               SET timestamp = ?1,
+                   description = ?2,
+                   account_id = ?3,
+                   category_id = ?4,
+                   amount = ?5,
+                   kind = ?6,
+                   note = ?7,
+                   custom_fields = ?8,
+                   spender_id = ?9,
+                   _change_timestamp = COALESCE(?10, _change_timestamp)
+             WHERE transaction_id = ?11 AND 
+                   _removal_timestamp IS NULL
+        "#;
+
+        self.db()
+            .execute(statement_fmt, rusqlite::params![transaction.timestamp, transaction.description, 
+                transaction.account_id, transaction.category_id, transaction.amount, transaction.kind, 
+                transaction.note, transaction.custom_fields, transaction.spender_id, 
+                transaction.meta_info.changed_timestamp, transaction.id])?;
+
+        if let Some(id) = transaction.id {
+            self.notify(EntityKind::Transaction, ChangeKind::Updated, id)?;
+        }
+
         Ok(())
     }
 
     fn remove_transaction(&self, transaction: Id, removal_timestamp: Timestamp) -> Result<()> {
+        let _guard = self.db();
+
+        let statement_fmt = r#"
+            SELECT timestamp
+              FROM transactions
+             WHERE transaction_id = ?1 AND
+                   _removal_timestamp IS NULL
+        "#;
+
+        let timestamp: Option<Timestamp> = self.db()
+            .query_row(statement_fmt, rusqlite::params![transaction], |row| row.get(0))
+            .optional()?;
+
+        if let Some(timestamp) = timestamp {
+            self.ensure_unlocked(timestamp)?;
+        }
+
         let statement_fmt = r#"
             UPDATE transactions
                SET _removal_timestamp = ?1
              WHERE transaction_id = ?2
         "#;
 
-        self.db
+        self.db()
             .execute(statement_fmt, rusqlite::params![removal_timestamp, transaction])?;
 
+        self.notify(EntityKind::Transaction, ChangeKind::Removed, transaction)?;
+
         Ok(())
     }
 
@@ -133,14 +1159,14 @@ impl DataStorage for DbStorage {
                   _removal_timestamp IS NULL
         "#));
 
-        let mut result = self.query_with_params(statement_fmt, 
-            rusqlite::params![transaction], Self::transaction_from_row)?;
-
         //
-        // The only row is returned here
+        // The only row is returned here, if the transaction exists
         //
 
-        Ok(result.remove(0))
+        self.query_with_params(statement_fmt, rusqlite::params![transaction], Self::transaction_from_row)?
+            .pop()
+            .ok_or_else(|| Error::from_message_with_extra(ITEM_NOT_FOUND, "Table: transactions")
+                .with_kind(ErrorKind::NotFound))
     }
 
     fn transactions(&self) -> Result<Vec<EncryptedTransaction>> {
@@ -173,6 +1199,27 @@ impl DataStorage for DbStorage {
         self.query_with_params(statement_fmt, rusqlite::params![start_timestamp, end_timestamp], Self::transaction_from_row)
     }
 
+    fn transaction_summaries_between(&self, start_timestamp: Timestamp, end_timestamp: Timestamp) -> Result<Vec<EncryptedTransactionSummary>> {
+        let statement_fmt = r#"
+            SELECT transaction_id, timestamp, account_id, category_id, amount, kind
+              FROM transactions
+             WHERE timestamp >= ?1 AND 
+                   timestamp < ?2 AND 
+                   _removal_timestamp IS NULL
+             ORDER BY timestamp DESC
+        "#;
+
+        self.query_with_params(statement_fmt, rusqlite::params![start_timestamp, end_timestamp], 
+            |row| Ok(EncryptedTransactionSummary {
+                id: row.get(0)?,
+                timestamp: row.get(1)?,
+                account_id: row.get(2)?,
+                category_id: row.get(3)?,
+                amount: row.get(4)?,
+                kind: row.get(5)?
+            }))
+    }
+
     fn transactions_of(&self, account: Id) -> Result<Vec<EncryptedTransaction>> {
         let statement_fmt = Self::select_from_transactions(Some(r#"
             WHERE account_id = ?1 AND 
@@ -239,29 +1286,52 @@ impl DataStorage for DbStorage {
         self.query_with_params(statement_fmt, rusqlite::params![category, start_timestamp, end_timestamp], Self::transaction_from_row)
     }
 
-    fn transactions_added_since(&self, base: Timestamp) -> Result<Vec<EncryptedTransaction>> {
+    fn transactions_described(&self, description: &[u8]) -> Result<Vec<EncryptedTransaction>> {
+        let statement_fmt = Self::select_from_transactions(Some(r#"
+            WHERE description = ?1 AND
+                  _removal_timestamp IS NULL
+            ORDER BY timestamp DESC
+        "#));
+
+        self.query_with_params(statement_fmt, rusqlite::params![description], Self::transaction_from_row)
+    }
+
+    fn transactions_added_since(&self, base: JournalPosition) -> Result<Vec<EncryptedTransaction>> {
         let statement_fmt = Self::select_from_transactions(Some(r#"
-            WHERE _creation_timestamp > ?1
+            WHERE transaction_id IN (
+                SELECT item_id
+                  FROM changes
+                 WHERE seq > ?1 AND
+                       change = 0
+            )
             ORDER BY _creation_timestamp DESC
         "#));
 
         self.query_with_params(statement_fmt, rusqlite::params![base], Self::transaction_from_row)
     }
 
-    fn transactions_changed_since(&self, base: Timestamp) -> Result<Vec<EncryptedTransaction>> {
+    fn transactions_changed_since(&self, base: JournalPosition) -> Result<Vec<EncryptedTransaction>> {
         let statement_fmt = Self::select_from_transactions(Some(r#"
-            WHERE _change_timestamp IS NOT NULL AND
-                  _change_timestamp > ?1
+            WHERE transaction_id IN (
+                SELECT item_id
+                  FROM changes
+                 WHERE seq > ?1 AND
+                       change = 1
+            )
             ORDER BY _change_timestamp DESC
         "#));
 
         self.query_with_params(statement_fmt, rusqlite::params![base], Self::transaction_from_row)
     }
 
-    fn transactions_removed_since(&self, base: Timestamp) -> Result<Vec<EncryptedTransaction>> {
+    fn transactions_removed_since(&self, base: JournalPosition) -> Result<Vec<EncryptedTransaction>> {
         let statement_fmt = Self::select_from_transactions(Some(r#"
-            WHERE _removal_timestamp IS NOT NULL AND
-                  _removal_timestamp > ?1
+            WHERE transaction_id IN (
+                SELECT item_id
+                  FROM changes
+                 WHERE seq > ?1 AND
+                       change = 2
+            )
             ORDER BY _removal_timestamp DESC
         "#));
 
@@ -271,25 +1341,33 @@ impl DataStorage for DbStorage {
     fn add_account(&self, account: EncryptedAccount) -> Result<()> {
         let statement_fmt = match account.id {
             None => r#"
-                INSERT INTO accounts (name, balance, initial_balance, _origin, _creation_timestamp)
-                VALUES (?1, ?2, ?3, ?4, ?5)
+                INSERT INTO accounts (name, balance, initial_balance, note, custom_fields, _origin, _creation_timestamp)
+                VALUES (?1, ?2, ?3, ?4, ?5, ?6, ?7)
+                RETURNING account_id
             "#,
             Some(_) => r#"
-                INSERT INTO accounts (account_id, name, balance, initial_balance, _origin, _creation_timestamp)
-                VALUES (?1, ?2, ?3, ?4, ?5, ?6)
+                INSERT INTO accounts (account_id, name, balance, initial_balance, note, custom_fields, _origin, _creation_timestamp)
+                VALUES (?1, ?2, ?3, ?4, ?5, ?6, ?7, ?8)
+                RETURNING account_id
             "#
         };
 
-        match account.id {
-            None => self.db.execute(statement_fmt, rusqlite::params![account.name, 
-                account.balance, account.initial_balance, account.meta_info.origin,
-                account.meta_info.added_timestamp])?,
-
-            Some(id) => self.db.execute(statement_fmt, rusqlite::params![id, account.name, 
-                account.balance, account.initial_balance, account.meta_info.origin,
-                account.meta_info.added_timestamp])?
+        let id: Id = match account.id {
+            None => self.db().query_row(statement_fmt, rusqlite::params![account.name, 
+                account.balance, account.initial_balance, account.note, account.custom_fields, 
+                account.meta_info.origin,
+                account.meta_info.added_timestamp],
+                |row| row.get(0))?,
+
+            Some(id) => self.db().query_row(statement_fmt, rusqlite::params![id, account.name, 
+                account.balance, account.initial_balance, account.note, account.custom_fields, 
+                account.meta_info.origin,
+                account.meta_info.added_timestamp],
+                |row| row.get(0))?
         };
 
+        self.notify(EntityKind::Account, ChangeKind::Added, id)?;
+
         Ok(())
     }
 
@@ -302,25 +1380,35 @@ impl DataStorage for DbStorage {
         let statement_fmt = r#"
             UPDATE accounts
                SET name = ?1,
-                   balance = ?2
-             WHERE account_id = ?3 AND 
+                   balance = ?2,
+                   note = ?3,
+                   custom_fields = ?4
+             WHERE account_id = ?5 AND 
                    _removal_timestamp IS NULL
         "#;
 
-        self.db
+        self.db()
             .execute(statement_fmt, rusqlite::params![account.name, 
-                account.balance, account.id])?;
+                account.balance, account.note, account.custom_fields, account.id])?;
+
+        if let Some(id) = account.id {
+            self.notify(EntityKind::Account, ChangeKind::Updated, id)?;
+        }
 
         Ok(())
     }
 
     fn remove_account(&self, account: Id, removal_timestamp: Timestamp) -> Result<()> {
+        let _guard = self.db();
+
         //
         // Check if we can delete account: no transaction should belong to it.
         // Only after that I can remove account
         //
 
         self.ensure_consistency("transactions", "account_id", account)?;
+        self.ensure_consistency("loans", "account_id", account)?;
+        self.ensure_consistency("holdings", "account_id", account)?;
 
         let statement_fmt = r#"
             UPDATE accounts
@@ -328,9 +1416,11 @@ impl DataStorage for DbStorage {
              WHERE account_id = ?2
         "#;
 
-        self.db
+        self.db()
             .execute(statement_fmt, rusqlite::params![removal_timestamp, account])?;
 
+        self.notify(EntityKind::Account, ChangeKind::Removed, account)?;
+
         Ok(())
     }
 
@@ -358,29 +1448,42 @@ impl DataStorage for DbStorage {
         self.query(statement, Self::account_from_row)
     }
 
-    fn accounts_added_since(&self, base: Timestamp) -> Result<Vec<EncryptedAccount>> {
+    fn accounts_added_since(&self, base: JournalPosition) -> Result<Vec<EncryptedAccount>> {
         let statement_fmt = Self::select_from_accounts(Some(r#"
-            WHERE _creation_timestamp > ?1
+            WHERE account_id IN (
+                SELECT item_id
+                  FROM changes
+                 WHERE seq > ?1 AND
+                       change = 0
+            )
             ORDER BY _creation_timestamp DESC
         "#));
 
         self.query_with_params(statement_fmt, rusqlite::params![base], Self::account_from_row)
     }
 
-    fn accounts_changed_since(&self, base: Timestamp) -> Result<Vec<EncryptedAccount>> {
+    fn accounts_changed_since(&self, base: JournalPosition) -> Result<Vec<EncryptedAccount>> {
         let statement_fmt = Self::select_from_accounts(Some(r#"
-            WHERE _change_timestamp IS NOT NULL AND
-                  _change_timestamp > ?1
+            WHERE account_id IN (
+                SELECT item_id
+                  FROM changes
+                 WHERE seq > ?1 AND
+                       change = 1
+            )
             ORDER BY _change_timestamp DESC
         "#));
 
         self.query_with_params(statement_fmt, rusqlite::params![base], Self::account_from_row)
     }
 
-    fn accounts_removed_since(&self, base: Timestamp) -> Result<Vec<EncryptedAccount>> {
+    fn accounts_removed_since(&self, base: JournalPosition) -> Result<Vec<EncryptedAccount>> {
         let statement_fmt = Self::select_from_accounts(Some(r#"
-            WHERE _removal_timestamp IS NOT NULL AND
-                  _removal_timestamp > ?1
+            WHERE account_id IN (
+                SELECT item_id
+                  FROM changes
+                 WHERE seq > ?1 AND
+                       change = 2
+            )
             ORDER BY _removal_timestamp DESC
         "#));
 
@@ -390,38 +1493,50 @@ impl DataStorage for DbStorage {
     fn add_category(&self, category: EncryptedCategory) -> Result<()> {
         let statement_fmt = match category.id {
             None => r#"
-                    INSERT INTO categories (name, type, _origin, _creation_timestamp)
-                    VALUES (?1, ?2, ?3, ?4)
+                    INSERT INTO categories (name, type, note, custom_fields, _origin, _creation_timestamp)
+                    VALUES (?1, ?2, ?3, ?4, ?5, ?6)
+                    RETURNING category_id
                 "#,
 
             Some(_) => r#"
-                    INSERT INTO categories (category_id, name, type, _origin, _creation_timestamp)
-                    VALUES (?1, ?2, ?3, ?4, ?5)
+                    INSERT INTO categories (category_id, name, type, note, custom_fields, _origin, _creation_timestamp)
+                    VALUES (?1, ?2, ?3, ?4, ?5, ?6, ?7)
+                    RETURNING category_id
                 "#
         };
 
-        match category.id {
-            None => self.db.execute(statement_fmt, rusqlite::params![category.name, 
-                category.category_type, category.meta_info.origin, category.meta_info.added_timestamp])?,
+        let id: Id = match category.id {
+            None => self.db().query_row(statement_fmt, rusqlite::params![category.name, 
+                category.category_type, category.note, category.custom_fields, category.meta_info.origin, 
+                category.meta_info.added_timestamp],
+                |row| row.get(0))?,
 
-            Some(id) => self.db.execute(statement_fmt, rusqlite::params![id, category.name, 
-                category.category_type, category.meta_info.origin, category.meta_info.added_timestamp])?
+            Some(id) => self.db().query_row(statement_fmt, rusqlite::params![id, category.name, 
+                category.category_type, category.note, category.custom_fields, category.meta_info.origin, 
+                category.meta_info.added_timestamp],
+                |row| row.get(0))?
         };
 
+        self.notify(EntityKind::Category, ChangeKind::Added, id)?;
+
         Ok(())
     }
 
     fn remove_category(&self, category: Id, removal_timestamp: Timestamp) -> Result<()> {
+        let _guard = self.db();
+
         //
         // Check if no transactions and plans reference this category
         //
 
         if Self::is_predefined_category(category) {
-            return Err(Error::from_message(CANNOT_DELETE_PREDEFINED));
+            return Err(Error::from_message(CANNOT_DELETE_PREDEFINED).with_kind(ErrorKind::ConsistencyViolation));
         }
 
         self.ensure_consistency("transactions", "category_id", category)?;
         self.ensure_consistency("plans", "category_id", category)?;
+        self.ensure_consistency("loans", "principal_category_id", category)?;
+        self.ensure_consistency("loans", "interest_category_id", category)?;
 
         let statement_fmt = r#"
             UPDATE categories
@@ -429,9 +1544,11 @@ impl DataStorage for DbStorage {
              WHERE category_id = ?2
         "#;
 
-        self.db
+        self.db()
             .execute(statement_fmt, rusqlite::params![removal_timestamp, category])?;
 
+        self.notify(EntityKind::Category, ChangeKind::Removed, category)?;
+
         Ok(())
     }
 
@@ -470,29 +1587,42 @@ impl DataStorage for DbStorage {
         self.query_with_params(statement_fmt, rusqlite::params![category_type], Self::category_from_row)
     }
 
-    fn categories_added_since(&self, base: Timestamp) -> Result<Vec<EncryptedCategory>> {
+    fn categories_added_since(&self, base: JournalPosition) -> Result<Vec<EncryptedCategory>> {
         let statement_fmt = Self::select_from_categories(Some(r#"
-            WHERE _creation_timestamp > ?1
+            WHERE category_id IN (
+                SELECT item_id
+                  FROM changes
+                 WHERE seq > ?1 AND
+                       change = 0
+            )
             ORDER BY _creation_timestamp DESC
         "#));
 
         self.query_with_params(statement_fmt, rusqlite::params![base], Self::category_from_row)
     }
 
-    fn categories_changed_since(&self, base: Timestamp) -> Result<Vec<EncryptedCategory>> {
+    fn categories_changed_since(&self, base: JournalPosition) -> Result<Vec<EncryptedCategory>> {
         let statement_fmt = Self::select_from_categories(Some(r#"
-            WHERE _change_timestamp IS NOT NULL AND
-                  _change_timestamp > ?1
+            WHERE category_id IN (
+                SELECT item_id
+                  FROM changes
+                 WHERE seq > ?1 AND
+                       change = 1
+            )
             ORDER BY _change_timestamp DESC
         "#));
 
         self.query_with_params(statement_fmt, rusqlite::params![base], Self::category_from_row)
     }
 
-    fn categories_removed_since(&self, base: Timestamp) -> Result<Vec<EncryptedCategory>> {
+    fn categories_removed_since(&self, base: JournalPosition) -> Result<Vec<EncryptedCategory>> {
         let statement_fmt = Self::select_from_categories(Some(r#"
-            WHERE _removal_timestamp IS NOT NULL AND
-                  _removal_timestamp > ?1
+            WHERE category_id IN (
+                SELECT item_id
+                  FROM changes
+                 WHERE seq > ?1 AND
+                       change = 2
+            )
             ORDER BY _removal_timestamp DESC
         "#));
 
@@ -502,23 +1632,31 @@ impl DataStorage for DbStorage {
     fn add_plan(&self, plan: EncryptedPlan) -> Result<()> {
         let statement_fmt = match plan.id {
             None => r#"
-                INSERT INTO plans (category_id, name, amount_limit, _origin, _creation_timestamp)
-                VALUES (?1, ?2, ?3, ?4, ?5)
+                INSERT INTO plans (category_id, name, amount_limit, alert_threshold, note, custom_fields, _origin, _creation_timestamp)
+                VALUES (?1, ?2, ?3, ?4, ?5, ?6, ?7, ?8)
+                RETURNING plan_id
             "#,
             Some(_) => r#"
-                INSERT INTO plans (plan_id, category_id, name, amount_limit, _origin, _creation_timestamp)
-                VALUES (?1, ?2, ?3, ?4, ?5, ?6)
+                INSERT INTO plans (plan_id, category_id, name, amount_limit, alert_threshold, note, custom_fields, _origin, _creation_timestamp)
+                VALUES (?1, ?2, ?3, ?4, ?5, ?6, ?7, ?8, ?9)
+                RETURNING plan_id
             "#
         };
 
-        match plan.id {
-            None => self.db.execute(statement_fmt, rusqlite::params![plan.category_id, 
-                plan.name, plan.amount_limit, plan.meta_info.origin, plan.meta_info.added_timestamp])?,
+        let id: Id = match plan.id {
+            None => self.db().query_row(statement_fmt, rusqlite::params![plan.category_id, 
+                plan.name, plan.amount_limit, plan.alert_threshold, plan.note, plan.custom_fields, plan.meta_info.origin, 
+                plan.meta_info.added_timestamp],
+                |row| row.get(0))?,
 
-            Some(id) => self.db.execute(statement_fmt, rusqlite::params![id, plan.category_id, 
-                plan.name, plan.amount_limit, plan.meta_info.origin, plan.meta_info.added_timestamp])?
+            Some(id) => self.db().query_row(statement_fmt, rusqlite::params![id, plan.category_id, 
+                plan.name, plan.amount_limit, plan.alert_threshold, plan.note, plan.custom_fields, plan.meta_info.origin, 
+                plan.meta_info.added_timestamp],
+                |row| row.get(0))?
         };
 
+        self.notify(EntityKind::Plan, ChangeKind::Added, id)?;
+
         Ok(())
     }
 
@@ -529,9 +1667,11 @@ impl DataStorage for DbStorage {
              WHERE plan_id = ?2
         "#;
 
-        self.db
+        self.db()
             .execute(statement_fmt, rusqlite::params![removal_timestamp, plan])?;
 
+        self.notify(EntityKind::Plan, ChangeKind::Removed, plan)?;
+
         Ok(())
     }
 
@@ -569,109 +1709,1411 @@ impl DataStorage for DbStorage {
         self.query_with_params(statement_fmt, rusqlite::params![category], Self::plan_from_row)
     }
 
-    fn plans_added_since(&self, base: Timestamp) -> Result<Vec<EncryptedPlan>> {
+    fn plans_added_since(&self, base: JournalPosition) -> Result<Vec<EncryptedPlan>> {
         let statement_fmt = Self::select_from_plans(Some(r#"
-            WHERE _creation_timestamp > ?1
+            WHERE plan_id IN (
+                SELECT item_id
+                  FROM changes
+                 WHERE seq > ?1 AND
+                       change = 0
+            )
             ORDER BY _creation_timestamp DESC
         "#));
 
         self.query_with_params(statement_fmt, rusqlite::params![base], Self::plan_from_row)
     }
 
-    fn plans_changed_since(&self, base: Timestamp) -> Result<Vec<EncryptedPlan>> {
+    fn plans_changed_since(&self, base: JournalPosition) -> Result<Vec<EncryptedPlan>> {
         let statement_fmt = Self::select_from_plans(Some(r#"
-            WHERE _change_timestamp IS NOT NULL AND
-                  _change_timestamp > ?1
+            WHERE plan_id IN (
+                SELECT item_id
+                  FROM changes
+                 WHERE seq > ?1 AND
+                       change = 1
+            )
             ORDER BY _change_timestamp DESC
         "#));
 
         self.query_with_params(statement_fmt, rusqlite::params![base], Self::plan_from_row)
     }
 
-    fn plans_removed_since(&self, base: Timestamp) -> Result<Vec<EncryptedPlan>> {
+    fn plans_removed_since(&self, base: JournalPosition) -> Result<Vec<EncryptedPlan>> {
         let statement_fmt = Self::select_from_plans(Some(r#"
-            WHERE _removal_timestamp IS NOT NULL AND
-                  _removal_timestamp > ?1
+            WHERE plan_id IN (
+                SELECT item_id
+                  FROM changes
+                 WHERE seq > ?1 AND
+                       change = 2
+            )
             ORDER BY _removal_timestamp DESC
         "#));
 
         self.query_with_params(statement_fmt, rusqlite::params![base], Self::plan_from_row)
     }
 
-    fn clean_removed(&self) -> Result<()> {
-        let statement = r#"
-            DELETE FROM plans
-             WHERE _removal_timestamp IS NOT NULL;
+    fn add_loan(&self, loan: EncryptedLoan) -> Result<()> {
+        let statement_fmt = match loan.id {
+            None => r#"
+                INSERT INTO loans (name, account_id, principal_category_id, interest_category_id, principal, 
+                                   interest_rate, term, start_timestamp, note, custom_fields, _origin, _creation_timestamp)
+                VALUES (?1, ?2, ?3, ?4, ?5, ?6, ?7, ?8, ?9, ?10, ?11, ?12)
+                RETURNING loan_id
+            "#,
+            Some(_) => r#"
+                INSERT INTO loans (loan_id, name, account_id, principal_category_id, interest_category_id, principal, 
+                                   interest_rate, term, start_timestamp, note, custom_fields, _origin, _creation_timestamp)
+                VALUES (?1, ?2, ?3, ?4, ?5, ?6, ?7, ?8, ?9, ?10, ?11, ?12, ?13)
+                RETURNING loan_id
+            "#
+        };
 
-            DELETE FROM transactions
-             WHERE _removal_timestamp IS NOT NULL;
-            
-            DELETE FROM categories
-             WHERE _removal_timestamp IS NOT NULL;
+        let id: Id = match loan.id {
+            None => self.db().query_row(statement_fmt, rusqlite::params![loan.name, loan.account_id, 
+                loan.principal_category_id, loan.interest_category_id, loan.principal, loan.interest_rate, 
+                loan.term, loan.start_timestamp, loan.note, loan.custom_fields, loan.meta_info.origin, 
+                loan.meta_info.added_timestamp],
+                |row| row.get(0))?,
+
+            Some(id) => self.db().query_row(statement_fmt, rusqlite::params![id, loan.name, loan.account_id, 
+                loan.principal_category_id, loan.interest_category_id, loan.principal, loan.interest_rate, 
+                loan.term, loan.start_timestamp, loan.note, loan.custom_fields, loan.meta_info.origin, 
+                loan.meta_info.added_timestamp],
+                |row| row.get(0))?
+        };
 
-            DELETE FROM accounts
-             WHERE _removal_timestamp IS NOT NULL;
+        self.notify(EntityKind::Loan, ChangeKind::Added, id)?;
+
+        Ok(())
+    }
+
+    fn remove_loan(&self, loan: Id, removal_timestamp: Timestamp) -> Result<()> {
+        let statement_fmt = r#"
+            UPDATE loans
+               SET _removal_timestamp = ?1
+             WHERE loan_id = ?2
         "#;
 
-        self.db
-            .execute_batch(statement)?;
-        
+        self.db()
+            .execute(statement_fmt, rusqlite::params![removal_timestamp, loan])?;
+
+        self.notify(EntityKind::Loan, ChangeKind::Removed, loan)?;
+
         Ok(())
     }
-}
 
+    fn loan(&self, loan: Id) -> Result<EncryptedLoan> {
+        let statement_fmt = Self::select_from_loans(Some(r#"
+            WHERE loan_id = ?1 AND 
+                  _removal_timestamp IS NULL
+        "#));
 
-impl DbStorage {
-    fn create_db(&self) -> Result<()> {
-        //
-        // Database will contain table for each entity: transaction, 
-        // account, category and plan.
-        // For optimization purposes categories table will be
-        // additionally indexed by its type, transactions table --
-        // by timestamp, plans table -- by category.
+        let mut result = self.query_with_params(statement_fmt, 
+            rusqlite::params![loan], Self::loan_from_row)?;
+        
         //
-        // Each table has two internal columns: `_change_timestamp`
-        // and `_removal_timestamp`, that are suitable for syncing
-        // content between different instances of the app.
-        // All tables are addtionally indexed by mentioned timestamps.
+        // The only row is returned here
         //
 
-        let create_statement = r#"
-            CREATE TABLE accounts (
-                account_id          BLOB        PRIMARY KEY DEFAULT (randomblob(16)),
-                balance             BYTEA       NOT NULL,
-                initial_balance     BYTEA       NOT NULL,
-                name                BYTEA       NOT NULL,
-                _origin             BYTEA       NOT NULL,
-                _creation_timestamp DATETIME    NOT NULL,
-                _change_timestamp   DATETIME    NULL,
-                _removal_timestamp  DATETIME    NULL
-            ) WITHOUT ROWID;
+        Ok(result.remove(0))
+    }
 
-            CREATE INDEX accounts_by_creation_timestamp
-                ON accounts (_creation_timestamp);
+    fn loans(&self) -> Result<Vec<EncryptedLoan>> {
+        let statement = Self::select_from_loans(Some(r#"
+            WHERE _removal_timestamp IS NULL
+            ORDER BY start_timestamp
+        "#));
 
-            CREATE INDEX accounts_by_change_timestamp
-                ON accounts (_change_timestamp);
+        self.query(statement, Self::loan_from_row)
+    }
 
-            CREATE INDEX accounts_by_removal_timestamp
-                ON accounts (_removal_timestamp);
-                
-            CREATE TABLE categories (
-                category_id         BLOB        PRIMARY KEY DEFAULT (randomblob(16)),
-                name                BYTEA       NOT NULL,
-                type                TINYINT     NOT NULL,
-                _origin             BYTEA       NOT NULL,
-                _creation_timestamp DATETIME    NOT NULL,
-                _change_timestamp   DATETIME    NULL,
-                _removal_timestamp  DATETIME    NULL
-            ) WITHOUT ROWID;
+    fn loans_added_since(&self, base: JournalPosition) -> Result<Vec<EncryptedLoan>> {
+        let statement_fmt = Self::select_from_loans(Some(r#"
+            WHERE loan_id IN (
+                SELECT item_id
+                  FROM changes
+                 WHERE seq > ?1 AND
+                       change = 0
+            )
+            ORDER BY _creation_timestamp DESC
+        "#));
 
-            CREATE INDEX categories_by_type
-                ON categories (type);
+        self.query_with_params(statement_fmt, rusqlite::params![base], Self::loan_from_row)
+    }
 
-            CREATE INDEX categories_by_creation_timestamp
-                ON categories (_creation_timestamp);
+    fn loans_changed_since(&self, base: JournalPosition) -> Result<Vec<EncryptedLoan>> {
+        let statement_fmt = Self::select_from_loans(Some(r#"
+            WHERE loan_id IN (
+                SELECT item_id
+                  FROM changes
+                 WHERE seq > ?1 AND
+                       change = 1
+            )
+            ORDER BY _change_timestamp DESC
+        "#));
+
+        self.query_with_params(statement_fmt, rusqlite::params![base], Self::loan_from_row)
+    }
+
+    fn loans_removed_since(&self, base: JournalPosition) -> Result<Vec<EncryptedLoan>> {
+        let statement_fmt = Self::select_from_loans(Some(r#"
+            WHERE loan_id IN (
+                SELECT item_id
+                  FROM changes
+                 WHERE seq > ?1 AND
+                       change = 2
+            )
+            ORDER BY _removal_timestamp DESC
+        "#));
+
+        self.query_with_params(statement_fmt, rusqlite::params![base], Self::loan_from_row)
+    }
+
+    fn add_holding(&self, holding: EncryptedHolding) -> Result<()> {
+        let statement_fmt = match holding.id {
+            None => r#"
+                INSERT INTO holdings (account_id, symbol, quantity, note, custom_fields, _origin, _creation_timestamp)
+                VALUES (?1, ?2, ?3, ?4, ?5, ?6, ?7)
+                RETURNING holding_id
+            "#,
+            Some(_) => r#"
+                INSERT INTO holdings (holding_id, account_id, symbol, quantity, note, custom_fields, _origin, _creation_timestamp)
+                VALUES (?1, ?2, ?3, ?4, ?5, ?6, ?7, ?8)
+                RETURNING holding_id
+            "#
+        };
+
+        let id: Id = match holding.id {
+            None => self.db().query_row(statement_fmt, rusqlite::params![holding.account_id, holding.symbol, 
+                holding.quantity, holding.note, holding.custom_fields, holding.meta_info.origin, 
+                holding.meta_info.added_timestamp],
+                |row| row.get(0))?,
+
+            Some(id) => self.db().query_row(statement_fmt, rusqlite::params![id, holding.account_id, holding.symbol, 
+                holding.quantity, holding.note, holding.custom_fields, holding.meta_info.origin, 
+                holding.meta_info.added_timestamp],
+                |row| row.get(0))?
+        };
+
+        self.notify(EntityKind::Holding, ChangeKind::Added, id)?;
+
+        Ok(())
+    }
+
+    fn update_holding(&self, holding: EncryptedHolding) -> Result<()> {
+        //
+        // Change timestamp is taken from meta information, 
+        // it is not updated if absent
+        //
+
+        let statement_fmt = r#"
+            UPDATE holdings
+               SET symbol = ?1,
+                   quantity = ?2,
+                   note = ?3,
+                   custom_fields = ?4,
+                   _change_timestamp = COALESCE(?5, _change_timestamp)
+             WHERE holding_id = ?6 AND 
+                   _removal_timestamp IS NULL
+        "#;
+
+        self.db()
+            .execute(statement_fmt, rusqlite::params![holding.symbol, holding.quantity, holding.note, 
+                holding.custom_fields, holding.meta_info.changed_timestamp, holding.id])?;
+
+        if let Some(id) = holding.id {
+            self.notify(EntityKind::Holding, ChangeKind::Updated, id)?;
+        }
+
+        Ok(())
+    }
+
+    fn remove_holding(&self, holding: Id, removal_timestamp: Timestamp) -> Result<()> {
+        let statement_fmt = r#"
+            UPDATE holdings
+               SET _removal_timestamp = ?1
+             WHERE holding_id = ?2
+        "#;
+
+        self.db()
+            .execute(statement_fmt, rusqlite::params![removal_timestamp, holding])?;
+
+        self.notify(EntityKind::Holding, ChangeKind::Removed, holding)?;
+
+        Ok(())
+    }
+
+    fn holding(&self, holding: Id) -> Result<EncryptedHolding> {
+        let statement_fmt = Self::select_from_holdings(Some(r#"
+            WHERE holding_id = ?1 AND 
+                  _removal_timestamp IS NULL
+        "#));
+
+        let mut result = self.query_with_params(statement_fmt, 
+            rusqlite::params![holding], Self::holding_from_row)?;
+        
+        //
+        // The only row is returned here
+        //
+
+        Ok(result.remove(0))
+    }
+
+    fn holdings(&self) -> Result<Vec<EncryptedHolding>> {
+        let statement = Self::select_from_holdings(Some(r#"
+            WHERE _removal_timestamp IS NULL
+            ORDER BY account_id
+        "#));
+
+        self.query(statement, Self::holding_from_row)
+    }
+
+    fn holdings_of(&self, account: Id) -> Result<Vec<EncryptedHolding>> {
+        let statement_fmt = Self::select_from_holdings(Some(r#"
+            WHERE account_id = ?1 AND 
+                  _removal_timestamp IS NULL
+        "#));
+
+        self.query_with_params(statement_fmt, rusqlite::params![account], Self::holding_from_row)
+    }
+
+    fn holdings_added_since(&self, base: JournalPosition) -> Result<Vec<EncryptedHolding>> {
+        let statement_fmt = Self::select_from_holdings(Some(r#"
+            WHERE holding_id IN (
+                SELECT item_id
+                  FROM changes
+                 WHERE seq > ?1 AND
+                       change = 0
+            )
+            ORDER BY _creation_timestamp DESC
+        "#));
+
+        self.query_with_params(statement_fmt, rusqlite::params![base], Self::holding_from_row)
+    }
+
+    fn holdings_changed_since(&self, base: JournalPosition) -> Result<Vec<EncryptedHolding>> {
+        let statement_fmt = Self::select_from_holdings(Some(r#"
+            WHERE holding_id IN (
+                SELECT item_id
+                  FROM changes
+                 WHERE seq > ?1 AND
+                       change = 1
+            )
+            ORDER BY _change_timestamp DESC
+        "#));
+
+        self.query_with_params(statement_fmt, rusqlite::params![base], Self::holding_from_row)
+    }
+
+    fn holdings_removed_since(&self, base: JournalPosition) -> Result<Vec<EncryptedHolding>> {
+        let statement_fmt = Self::select_from_holdings(Some(r#"
+            WHERE holding_id IN (
+                SELECT item_id
+                  FROM changes
+                 WHERE seq > ?1 AND
+                       change = 2
+            )
+            ORDER BY _removal_timestamp DESC
+        "#));
+
+        self.query_with_params(statement_fmt, rusqlite::params![base], Self::holding_from_row)
+    }
+
+    fn add_price(&self, price: EncryptedPricePoint) -> Result<()> {
+        let statement_fmt = match price.id {
+            None => r#"
+                INSERT INTO prices (symbol, timestamp, price, _origin, _creation_timestamp)
+                VALUES (?1, ?2, ?3, ?4, ?5)
+                RETURNING price_id
+            "#,
+            Some(_) => r#"
+                INSERT INTO prices (price_id, symbol, timestamp, price, _origin, _creation_timestamp)
+                VALUES (?1, ?2, ?3, ?4, ?5, ?6)
+                RETURNING price_id
+            "#
+        };
+
+        let id: Id = match price.id {
+            None => self.db().query_row(statement_fmt, rusqlite::params![price.symbol, price.timestamp, 
+                price.price, price.meta_info.origin, price.meta_info.added_timestamp],
+                |row| row.get(0))?,
+
+            Some(id) => self.db().query_row(statement_fmt, rusqlite::params![id, price.symbol, price.timestamp, 
+                price.price, price.meta_info.origin, price.meta_info.added_timestamp],
+                |row| row.get(0))?
+        };
+
+        self.notify(EntityKind::PricePoint, ChangeKind::Added, id)?;
+
+        Ok(())
+    }
+
+    fn remove_price(&self, price: Id, removal_timestamp: Timestamp) -> Result<()> {
+        let statement_fmt = r#"
+            UPDATE prices
+               SET _removal_timestamp = ?1
+             WHERE price_id = ?2
+        "#;
+
+        self.db()
+            .execute(statement_fmt, rusqlite::params![removal_timestamp, price])?;
+
+        self.notify(EntityKind::PricePoint, ChangeKind::Removed, price)?;
+
+        Ok(())
+    }
+
+    fn prices(&self) -> Result<Vec<EncryptedPricePoint>> {
+        let statement = Self::select_from_prices(Some(r#"
+            WHERE _removal_timestamp IS NULL
+            ORDER BY timestamp DESC
+        "#));
+
+        self.query(statement, Self::price_from_row)
+    }
+
+    fn prices_added_since(&self, base: JournalPosition) -> Result<Vec<EncryptedPricePoint>> {
+        let statement_fmt = Self::select_from_prices(Some(r#"
+            WHERE price_id IN (
+                SELECT item_id
+                  FROM changes
+                 WHERE seq > ?1 AND
+                       change = 0
+            )
+            ORDER BY _creation_timestamp DESC
+        "#));
+
+        self.query_with_params(statement_fmt, rusqlite::params![base], Self::price_from_row)
+    }
+
+    fn prices_changed_since(&self, base: JournalPosition) -> Result<Vec<EncryptedPricePoint>> {
+        let statement_fmt = Self::select_from_prices(Some(r#"
+            WHERE price_id IN (
+                SELECT item_id
+                  FROM changes
+                 WHERE seq > ?1 AND
+                       change = 1
+            )
+            ORDER BY _change_timestamp DESC
+        "#));
+
+        self.query_with_params(statement_fmt, rusqlite::params![base], Self::price_from_row)
+    }
+
+    fn prices_removed_since(&self, base: JournalPosition) -> Result<Vec<EncryptedPricePoint>> {
+        let statement_fmt = Self::select_from_prices(Some(r#"
+            WHERE price_id IN (
+                SELECT item_id
+                  FROM changes
+                 WHERE seq > ?1 AND
+                       change = 2
+            )
+            ORDER BY _removal_timestamp DESC
+        "#));
+
+        self.query_with_params(statement_fmt, rusqlite::params![base], Self::price_from_row)
+    }
+
+    fn add_person(&self, person: EncryptedPerson) -> Result<()> {
+        let statement_fmt = match person.id {
+            None => r#"
+                INSERT INTO persons (name, _origin, _creation_timestamp)
+                VALUES (?1, ?2, ?3)
+                RETURNING person_id
+            "#,
+            Some(_) => r#"
+                INSERT INTO persons (person_id, name, _origin, _creation_timestamp)
+                VALUES (?1, ?2, ?3, ?4)
+                RETURNING person_id
+            "#
+        };
+
+        let id: Id = match person.id {
+            None => self.db().query_row(statement_fmt, rusqlite::params![person.name, 
+                person.meta_info.origin, person.meta_info.added_timestamp],
+                |row| row.get(0))?,
+
+            Some(id) => self.db().query_row(statement_fmt, rusqlite::params![id, person.name, 
+                person.meta_info.origin, person.meta_info.added_timestamp],
+                |row| row.get(0))?
+        };
+
+        self.notify(EntityKind::Person, ChangeKind::Added, id)?;
+
+        Ok(())
+    }
+
+    fn update_person(&self, person: EncryptedPerson) -> Result<()> {
+        //
+        // Change timestamp is taken from meta information, 
+        // it is not updated if absent
+        //
+
+        let statement_fmt = r#"
+            UPDATE persons
+               SET name = ?1,
+                   _change_timestamp = COALESCE(?2, _change_timestamp)
+             WHERE person_id = ?3 AND 
+                   _removal_timestamp IS NULL
+        "#;
+
+        self.db()
+            .execute(statement_fmt, rusqlite::params![person.name, person.meta_info.changed_timestamp, person.id])?;
+
+        if let Some(id) = person.id {
+            self.notify(EntityKind::Person, ChangeKind::Updated, id)?;
+        }
+
+        Ok(())
+    }
+
+    fn remove_person(&self, person: Id, removal_timestamp: Timestamp) -> Result<()> {
+        let statement_fmt = r#"
+            UPDATE persons
+               SET _removal_timestamp = ?1
+             WHERE person_id = ?2
+        "#;
+
+        self.db()
+            .execute(statement_fmt, rusqlite::params![removal_timestamp, person])?;
+
+        self.notify(EntityKind::Person, ChangeKind::Removed, person)?;
+
+        Ok(())
+    }
+
+    fn person(&self, person: Id) -> Result<EncryptedPerson> {
+        let statement_fmt = Self::select_from_persons(Some(r#"
+            WHERE person_id = ?1 AND 
+                  _removal_timestamp IS NULL
+        "#));
+
+        //
+        // The only row is returned here, if the person exists
+        //
+
+        self.query_with_params(statement_fmt, rusqlite::params![person], Self::person_from_row)?
+            .pop()
+            .ok_or_else(|| Error::from_message_with_extra(ITEM_NOT_FOUND, "Table: persons")
+                .with_kind(ErrorKind::NotFound))
+    }
+
+    fn persons(&self) -> Result<Vec<EncryptedPerson>> {
+        let statement = Self::select_from_persons(Some(r#"
+            WHERE _removal_timestamp IS NULL
+            ORDER BY _creation_timestamp
+        "#));
+
+        self.query(statement, Self::person_from_row)
+    }
+
+    fn persons_added_since(&self, base: JournalPosition) -> Result<Vec<EncryptedPerson>> {
+        let statement_fmt = Self::select_from_persons(Some(r#"
+            WHERE person_id IN (
+                SELECT item_id
+                  FROM changes
+                 WHERE seq > ?1 AND
+                       change = 0
+            )
+            ORDER BY _creation_timestamp DESC
+        "#));
+
+        self.query_with_params(statement_fmt, rusqlite::params![base], Self::person_from_row)
+    }
+
+    fn persons_changed_since(&self, base: JournalPosition) -> Result<Vec<EncryptedPerson>> {
+        let statement_fmt = Self::select_from_persons(Some(r#"
+            WHERE person_id IN (
+                SELECT item_id
+                  FROM changes
+                 WHERE seq > ?1 AND
+                       change = 1
+            )
+            ORDER BY _change_timestamp DESC
+        "#));
+
+        self.query_with_params(statement_fmt, rusqlite::params![base], Self::person_from_row)
+    }
+
+    fn persons_removed_since(&self, base: JournalPosition) -> Result<Vec<EncryptedPerson>> {
+        let statement_fmt = Self::select_from_persons(Some(r#"
+            WHERE person_id IN (
+                SELECT item_id
+                  FROM changes
+                 WHERE seq > ?1 AND
+                       change = 2
+            )
+            ORDER BY _removal_timestamp DESC
+        "#));
+
+        self.query_with_params(statement_fmt, rusqlite::params![base], Self::person_from_row)
+    }
+
+    fn add_category_group(&self, group: EncryptedCategoryGroup) -> Result<()> {
+        let statement_fmt = match group.id {
+            None => r#"
+                INSERT INTO category_groups (name, categories, _origin, _creation_timestamp)
+                VALUES (?1, ?2, ?3, ?4)
+                RETURNING group_id
+            "#,
+            Some(_) => r#"
+                INSERT INTO category_groups (group_id, name, categories, _origin, _creation_timestamp)
+                VALUES (?1, ?2, ?3, ?4, ?5)
+                RETURNING group_id
+            "#
+        };
+
+        let categories = group.categories.concat();
+        let id: Id = match group.id {
+            None => self.db().query_row(statement_fmt, rusqlite::params![group.name, categories,
+                group.meta_info.origin, group.meta_info.added_timestamp],
+                |row| row.get(0))?,
+
+            Some(id) => self.db().query_row(statement_fmt, rusqlite::params![id, group.name, categories,
+                group.meta_info.origin, group.meta_info.added_timestamp],
+                |row| row.get(0))?
+        };
+
+        self.notify(EntityKind::CategoryGroup, ChangeKind::Added, id)?;
+
+        Ok(())
+    }
+
+    fn update_category_group(&self, group: EncryptedCategoryGroup) -> Result<()> {
+        //
+        // Change timestamp is taken from meta information, 
+        // it is not updated if absent
+        //
+
+        let statement_fmt = r#"
+            UPDATE category_groups
+               SET name = ?1,
+                   categories = ?2,
+                   _change_timestamp = COALESCE(?3, _change_timestamp)
+             WHERE group_id = ?4 AND 
+                   _removal_timestamp IS NULL
+        "#;
+
+        self.db()
+            .execute(statement_fmt, rusqlite::params![group.name, group.categories.concat(), 
+                group.meta_info.changed_timestamp, group.id])?;
+
+        if let Some(id) = group.id {
+            self.notify(EntityKind::CategoryGroup, ChangeKind::Updated, id)?;
+        }
+
+        Ok(())
+    }
+
+    fn remove_category_group(&self, group: Id, removal_timestamp: Timestamp) -> Result<()> {
+        let statement_fmt = r#"
+            UPDATE category_groups
+               SET _removal_timestamp = ?1
+             WHERE group_id = ?2
+        "#;
+
+        self.db()
+            .execute(statement_fmt, rusqlite::params![removal_timestamp, group])?;
+
+        self.notify(EntityKind::CategoryGroup, ChangeKind::Removed, group)?;
+
+        Ok(())
+    }
+
+    fn category_group(&self, group: Id) -> Result<EncryptedCategoryGroup> {
+        let statement_fmt = Self::select_from_category_groups(Some(r#"
+            WHERE group_id = ?1 AND 
+                  _removal_timestamp IS NULL
+        "#));
+
+        //
+        // The only row is returned here, if the group exists
+        //
+
+        self.query_with_params(statement_fmt, rusqlite::params![group], Self::category_group_from_row)?
+            .pop()
+            .ok_or_else(|| Error::from_message_with_extra(ITEM_NOT_FOUND, "Table: category_groups")
+                .with_kind(ErrorKind::NotFound))
+    }
+
+    fn category_groups(&self) -> Result<Vec<EncryptedCategoryGroup>> {
+        let statement = Self::select_from_category_groups(Some(r#"
+            WHERE _removal_timestamp IS NULL
+            ORDER BY _creation_timestamp
+        "#));
+
+        self.query(statement, Self::category_group_from_row)
+    }
+
+    fn category_groups_added_since(&self, base: JournalPosition) -> Result<Vec<EncryptedCategoryGroup>> {
+        let statement_fmt = Self::select_from_category_groups(Some(r#"
+            WHERE group_id IN (
+                SELECT item_id
+                  FROM changes
+                 WHERE seq > ?1 AND
+                       change = 0
+            )
+            ORDER BY _creation_timestamp DESC
+        "#));
+
+        self.query_with_params(statement_fmt, rusqlite::params![base], Self::category_group_from_row)
+    }
+
+    fn category_groups_changed_since(&self, base: JournalPosition) -> Result<Vec<EncryptedCategoryGroup>> {
+        let statement_fmt = Self::select_from_category_groups(Some(r#"
+            WHERE group_id IN (
+                SELECT item_id
+                  FROM changes
+                 WHERE seq > ?1 AND
+                       change = 1
+            )
+            ORDER BY _change_timestamp DESC
+        "#));
+
+        self.query_with_params(statement_fmt, rusqlite::params![base], Self::category_group_from_row)
+    }
+
+    fn category_groups_removed_since(&self, base: JournalPosition) -> Result<Vec<EncryptedCategoryGroup>> {
+        let statement_fmt = Self::select_from_category_groups(Some(r#"
+            WHERE group_id IN (
+                SELECT item_id
+                  FROM changes
+                 WHERE seq > ?1 AND
+                       change = 2
+            )
+            ORDER BY _removal_timestamp DESC
+        "#));
+
+        self.query_with_params(statement_fmt, rusqlite::params![base], Self::category_group_from_row)
+    }
+
+    fn settings(&self) -> Result<Option<EncryptedSettings>> {
+        let statement_fmt = r#"
+            SELECT data, _origin, _change_timestamp
+              FROM settings
+        "#;
+
+        let mut settings = self.query(statement_fmt, Self::settings_from_row)?;
+        Ok(settings.pop())
+    }
+
+    fn set_settings(&self, settings: EncryptedSettings) -> Result<()> {
+        let statement_fmt = r#"
+            INSERT OR REPLACE INTO settings (settings_id, data, _origin, _change_timestamp)
+            VALUES (0, ?1, ?2, ?3)
+        "#;
+
+        self.db()
+            .execute(statement_fmt, rusqlite::params![settings.data, settings.meta_info.origin,
+                settings.meta_info.changed_timestamp])?;
+
+        Ok(())
+    }
+
+    fn settings_changed_since(&self, base: JournalPosition) -> Result<Option<EncryptedSettings>> {
+        let statement_fmt = r#"
+            SELECT data, _origin, _change_timestamp
+              FROM settings
+             WHERE EXISTS (
+                SELECT 1
+                  FROM changes
+                 WHERE seq > ?1 AND
+                       item_id = ?2
+            )
+        "#;
+
+        let mut settings = self.query_with_params(statement_fmt, rusqlite::params![base, SETTINGS_ID], 
+            Self::settings_from_row)?;
+
+        Ok(settings.pop())
+    }
+
+    fn journal_position(&self) -> Result<JournalPosition> {
+        //
+        // Journal is pruned, hence I take the last position from 
+        // SQLite's sequence instead of the journal itself
+        //
+
+        let statement_fmt = r#"
+            SELECT COALESCE(MAX(seq), 0)
+              FROM sqlite_sequence
+             WHERE name = 'changes'
+        "#;
+
+        let position = self.db()
+            .query_row(statement_fmt, [], |row| row.get(0))?;
+
+        Ok(position)
+    }
+
+    fn exported_position(&self) -> Result<JournalPosition> {
+        let statement_fmt = r#"
+            SELECT exported_seq
+              FROM journal_cursor
+        "#;
+
+        let position = self.db()
+            .query_row(statement_fmt, [], |row| row.get(0))?;
+
+        Ok(position)
+    }
+
+    fn set_exported_position(&self, position: JournalPosition) -> Result<()> {
+        let statement_fmt = r#"
+            UPDATE journal_cursor
+               SET exported_seq = ?1
+        "#;
+
+        self.db()
+            .execute(statement_fmt, rusqlite::params![position])?;
+
+        Ok(())
+    }
+
+    fn set_private_to(&self, item: Id, instance: Option<Id>) -> Result<()> {
+        match instance {
+            Some(instance) => self.db().execute(r#"
+                INSERT OR REPLACE INTO visibility (item_id, private_to)
+                VALUES (?1, ?2)
+            "#, rusqlite::params![item, instance])?,
+
+            None => self.db().execute(r#"
+                DELETE FROM visibility
+                 WHERE item_id = ?1
+            "#, rusqlite::params![item])?
+        };
+
+        Ok(())
+    }
+
+    fn private_items(&self, instance: Id) -> Result<Vec<Id>> {
+        let statement_fmt = r#"
+            SELECT item_id
+              FROM visibility
+             WHERE private_to = ?1
+        "#;
+
+        self.query_with_params(statement_fmt, rusqlite::params![instance], 
+            |row| Ok(row.get(0)?))
+    }
+
+    fn on_change(&self, callback: ChangeCallback) {
+        self.observers
+            .lock()
+            .push(callback);
+    }
+
+    #[cfg_attr(feature = "tracing", tracing::instrument(level = "debug", skip_all))]
+    fn clean_removed(&self) -> Result<()> {
+        let statement = r#"
+            DELETE FROM category_groups
+             WHERE _removal_timestamp IS NOT NULL;
+
+            DELETE FROM persons
+             WHERE _removal_timestamp IS NOT NULL;
+
+            DELETE FROM prices
+             WHERE _removal_timestamp IS NOT NULL;
+
+            DELETE FROM holdings
+             WHERE _removal_timestamp IS NOT NULL;
+
+            DELETE FROM loans
+             WHERE _removal_timestamp IS NOT NULL;
+
+            DELETE FROM plans
+             WHERE _removal_timestamp IS NOT NULL;
+
+            DELETE FROM transactions
+             WHERE _removal_timestamp IS NOT NULL;
+            
+            DELETE FROM categories
+             WHERE _removal_timestamp IS NOT NULL;
+
+            DELETE FROM accounts
+             WHERE _removal_timestamp IS NOT NULL;
+
+            DELETE FROM visibility
+             WHERE item_id NOT IN (SELECT account_id FROM accounts) AND
+                   item_id NOT IN (SELECT category_id FROM categories) AND
+                   item_id NOT IN (SELECT transaction_id FROM transactions) AND
+                   item_id NOT IN (SELECT plan_id FROM plans) AND
+                   item_id NOT IN (SELECT loan_id FROM loans) AND
+                   item_id NOT IN (SELECT holding_id FROM holdings) AND
+                   item_id NOT IN (SELECT price_id FROM prices) AND
+                   item_id NOT IN (SELECT person_id FROM persons) AND
+                   item_id NOT IN (SELECT group_id FROM category_groups);
+        
+            DELETE FROM merges
+             WHERE seq <= (SELECT exported_seq FROM journal_cursor);
+
+            DELETE FROM changes
+             WHERE seq <= (SELECT exported_seq FROM journal_cursor);
+        "#;
+
+        self.db()
+            .execute_batch(statement)?;
+        
+        Ok(())
+    }
+
+    #[cfg_attr(feature = "tracing", tracing::instrument(level = "debug", skip_all))]
+    fn purge_removed(&self, removed_before: Timestamp) -> Result<usize> {
+        let _guard = self.db();
+
+        let tables = [
+            ("category_groups", "group_id"),
+            ("persons", "person_id"),
+            ("prices", "price_id"),
+            ("holdings", "holding_id"),
+            ("loans", "loan_id"),
+            ("plans", "plan_id"),
+            ("transactions", "transaction_id"),
+            ("categories", "category_id"),
+            ("accounts", "account_id"),
+        ];
+
+        let mut purged = 0;
+        for (table, key) in tables {
+            let statement_fmt = format!(r#"
+                DELETE FROM {table}
+                 WHERE _removal_timestamp < ?1 AND
+                       {key} NOT IN (SELECT item_id FROM changes
+                                      WHERE seq > (SELECT exported_seq FROM journal_cursor))
+            "#);
+
+            purged += self.db()
+                .execute(&statement_fmt, rusqlite::params![removed_before])?;
+        }
+
+        let statement = r#"
+            DELETE FROM visibility
+             WHERE item_id NOT IN (SELECT account_id FROM accounts) AND
+                   item_id NOT IN (SELECT category_id FROM categories) AND
+                   item_id NOT IN (SELECT transaction_id FROM transactions) AND
+                   item_id NOT IN (SELECT plan_id FROM plans) AND
+                   item_id NOT IN (SELECT loan_id FROM loans) AND
+                   item_id NOT IN (SELECT holding_id FROM holdings) AND
+                   item_id NOT IN (SELECT price_id FROM prices) AND
+                   item_id NOT IN (SELECT person_id FROM persons) AND
+                   item_id NOT IN (SELECT group_id FROM category_groups);
+        "#;
+
+        self.db()
+            .execute_batch(statement)?;
+
+        Ok(purged)
+    }
+
+    fn counts(&self) -> Result<Vec<ItemCount>> {
+        let entities = [
+            (EntityKind::Transaction, "transactions", "timestamp"),
+            (EntityKind::Account, "accounts", "_creation_timestamp"),
+            (EntityKind::Category, "categories", "_creation_timestamp"),
+            (EntityKind::Plan, "plans", "_creation_timestamp"),
+            (EntityKind::Loan, "loans", "_creation_timestamp"),
+            (EntityKind::Holding, "holdings", "_creation_timestamp"),
+            (EntityKind::PricePoint, "prices", "timestamp"),
+            (EntityKind::Person, "persons", "_creation_timestamp"),
+            (EntityKind::CategoryGroup, "category_groups", "_creation_timestamp"),
+        ];
+
+        //
+        // Numbers of items are maintained by triggers, dates
+        // are taken from the beginning and the end of indexes
+        //
+
+        let mut counts = Vec::with_capacity(entities.len());
+        for (entity, table, date) in entities {
+            let statement = format!(r#"
+                SELECT (SELECT count FROM item_counts WHERE entity = '{table}'),
+                       (SELECT {date} FROM {table} WHERE _removal_timestamp IS NULL ORDER BY {date} ASC LIMIT 1),
+                       (SELECT {date} FROM {table} WHERE _removal_timestamp IS NULL ORDER BY {date} DESC LIMIT 1)
+            "#);
+
+            let mut result = self.query(statement, |row| Ok(ItemCount {
+                entity,
+                count: row.get(0)?,
+                first: row.get(1)?,
+                last: row.get(2)?
+            }))?;
+
+            //
+            // The only row is returned here
+            //
+
+            counts.push(result.remove(0));
+        }
+
+        Ok(counts)
+    }
+
+    fn snapshot(&self) -> Result<SnapshotId> {
+        //
+        // Snapshots are implemented via savepoints, i.e. all changes
+        // made after the first snapshot are kept in an open transaction
+        // until it is dropped
+        //
+
+        let _guard = self.db();
+
+        let snapshot = self.next_snapshot.fetch_add(1, Ordering::Relaxed);
+        self.db()
+            .execute_batch(&format!("SAVEPOINT {}", Self::savepoint_name(snapshot)))?;
+
+        let queued_events = self.events
+            .lock()
+            .len();
+
+        self.snapshots
+            .lock()
+            .push((snapshot, queued_events));
+
+        Ok(snapshot)
+    }
+
+    fn rollback_to(&self, snapshot: SnapshotId) -> Result<()> {
+        let _guard = self.db();
+
+        let position = self.snapshot_position(snapshot)?;
+        let savepoint = Self::savepoint_name(snapshot);
+
+        //
+        // Rollback keeps the savepoint, hence it must be released then
+        //
+
+        self.db()
+            .execute_batch(&format!("ROLLBACK TO {savepoint}; RELEASE {savepoint}"))?;
+
+        //
+        // Changes made after the snapshot never happened,
+        // hence nobody is notified about them
+        //
+
+        let (_, queued_events) = self.snapshots
+            .lock()
+            .drain(position..)
+            .next()
+            .expect("Snapshot position MUST be valid");
+
+        self.events
+            .lock()
+            .truncate(queued_events);
+
+        self.batched()
+    }
+
+    fn drop_snapshot(&self, snapshot: SnapshotId) -> Result<()> {
+        let _guard = self.db();
+
+        let position = self.snapshot_position(snapshot)?;
+
+        self.db()
+            .execute_batch(&format!("RELEASE {}", Self::savepoint_name(snapshot)))?;
+
+        self.snapshots
+            .lock()
+            .truncate(position);
+
+        self.batched()
+    }
+
+    fn has_snapshots(&self) -> bool {
+        !self.snapshots
+            .lock()
+            .is_empty()
+    }
+
+    fn set_write_batching(&self, batch_size: Option<usize>) -> Result<()> {
+        let _guard = self.db();
+
+        self.batch_size.store(batch_size.map_or(0, |size| size.max(1)), Ordering::Relaxed);
+        self.batched()
+    }
+
+    fn flush(&self) -> Result<()> {
+        let _guard = self.db();
+
+        self.commit_batch()?;
+        self.batched()
+    }
+
+    fn period_lock(&self) -> Result<Option<Timestamp>> {
+        let statement_fmt = r#"
+            SELECT locked_until
+              FROM period_lock
+        "#;
+
+        let until = self.db()
+            .query_row(statement_fmt, [], |row| row.get(0))?;
+
+        Ok(until)
+    }
+
+    fn set_period_lock(&self, until: Option<Timestamp>) -> Result<()> {
+        let statement_fmt = r#"
+            UPDATE period_lock
+               SET locked_until = ?1
+        "#;
+
+        self.db()
+            .execute(statement_fmt, rusqlite::params![until])?;
+
+        Ok(())
+    }
+
+    fn override_period_lock(&self, overridden: bool) -> bool {
+        self.lock_overridden
+            .swap(overridden, Ordering::Relaxed)
+    }
+
+    fn set_transaction_timestamp(&self, transaction: Id, timestamp: Timestamp) -> Result<()> {
+        let statement_fmt = r#"
+            UPDATE transactions
+               SET timestamp = ?1
+             WHERE transaction_id = ?2
+        "#;
+
+        self.db()
+            .execute(statement_fmt, rusqlite::params![timestamp, transaction])?;
+
+        self.notify(EntityKind::Transaction, ChangeKind::Updated, transaction)?;
+
+        Ok(())
+    }
+
+    fn canonical_dates(&self) -> Result<bool> {
+        let statement_fmt = r#"
+            SELECT canonical
+              FROM transaction_dates
+        "#;
+
+        let canonical = self.db()
+            .query_row(statement_fmt, [], |row| row.get(0))?;
+
+        Ok(canonical)
+    }
+
+    fn set_canonical_dates(&self) -> Result<()> {
+        let statement_fmt = r#"
+            UPDATE transaction_dates
+               SET canonical = TRUE
+        "#;
+
+        self.db()
+            .execute(statement_fmt, [])?;
+
+        Ok(())
+    }
+
+    fn crypto_record(&self) -> Result<Option<CryptoRecord>> {
+        let statement_fmt = r#"
+            SELECT engine, algorithm
+              FROM crypto_record
+        "#;
+
+        let record = self.db()
+            .query_row(statement_fmt, [], |row| Ok(CryptoRecord {
+                engine: row.get(0)?,
+                algorithm: row.get(1)?
+            }))
+            .optional()?;
+
+        Ok(record)
+    }
+
+    fn set_crypto_record(&self, record: &CryptoRecord) -> Result<()> {
+        let statement_fmt = r#"
+            INSERT OR REPLACE INTO crypto_record (crypto_record_id, engine, algorithm)
+            VALUES (0, ?1, ?2)
+        "#;
+
+        self.db()
+            .execute(statement_fmt, rusqlite::params![record.engine, record.algorithm])?;
+
+        Ok(())
+    }
+
+    fn key_fingerprint(&self) -> Result<Option<String>> {
+        let statement_fmt = r#"
+            SELECT fingerprint
+              FROM key_pin
+        "#;
+
+        let fingerprint = self.db()
+            .query_row(statement_fmt, [], |row| row.get(0))
+            .optional()?;
+
+        Ok(fingerprint)
+    }
+
+    fn set_key_fingerprint(&self, fingerprint: &str) -> Result<()> {
+        let statement_fmt = r#"
+            INSERT OR REPLACE INTO key_pin (key_pin_id, fingerprint)
+            VALUES (0, ?1)
+        "#;
+
+        self.db()
+            .execute(statement_fmt, [fingerprint])?;
+
+        Ok(())
+    }
+
+    fn recovery_record(&self) -> Result<Option<RecoveryRecord>> {
+        let statement_fmt = r#"
+            SELECT salt, export_key
+              FROM recovery_key
+        "#;
+
+        let record = self.db()
+            .query_row(statement_fmt, [], |row| Ok(RecoveryRecord {
+                salt: row.get(0)?,
+                export_key: row.get(1)?
+            }))
+            .optional()?;
+
+        Ok(record)
+    }
+
+    fn set_recovery_record(&self, record: &RecoveryRecord) -> Result<()> {
+        let statement_fmt = r#"
+            INSERT OR REPLACE INTO recovery_key (recovery_key_id, salt, export_key)
+            VALUES (0, ?1, ?2)
+        "#;
+
+        self.db()
+            .execute(statement_fmt, rusqlite::params![record.salt, record.export_key])?;
+
+        Ok(())
+    }
+
+    fn remove_recovery_record(&self) -> Result<bool> {
+        let statement_fmt = r#"
+            DELETE FROM recovery_key
+        "#;
+
+        let removed = self.db()
+            .execute(statement_fmt, [])?;
+
+        Ok(removed > 0)
+    }
+
+    fn deterministic_descriptions(&self) -> Result<bool> {
+        let statement_fmt = r#"
+            SELECT descriptions
+              FROM queryable_fields
+        "#;
+
+        let enabled = self.db()
+            .query_row(statement_fmt, [], |row| row.get(0))?;
+
+        Ok(enabled)
+    }
+
+    fn set_deterministic_descriptions(&self, enabled: bool) -> Result<()> {
+        let statement_fmt = r#"
+            UPDATE queryable_fields
+               SET descriptions = ?1
+        "#;
+
+        self.db()
+            .execute(statement_fmt, rusqlite::params![enabled])?;
+
+        Ok(())
+    }
+
+    fn set_transaction_description(&self, transaction: Id, description: Vec<u8>) -> Result<()> {
+        let statement_fmt = r#"
+            UPDATE transactions
+               SET description = ?1
+             WHERE transaction_id = ?2
+        "#;
+
+        self.db()
+            .execute(statement_fmt, rusqlite::params![description, transaction])?;
+
+        self.notify(EntityKind::Transaction, ChangeKind::Updated, transaction)?;
+
+        Ok(())
+    }
+
+    fn set_indexed_values(&self, transaction: Id, field: IndexedField, values: &[Vec<u8>]) -> Result<()> {
+        let _guard = self.db();
+
+        let statement_fmt = r#"
+            DELETE FROM transaction_index
+             WHERE transaction_id = ?1 AND
+                   field = ?2
+        "#;
+
+        self.db()
+            .execute(statement_fmt, rusqlite::params![transaction, field])?;
+
+        let statement_fmt = r#"
+            INSERT INTO transaction_index (transaction_id, field, value)
+            VALUES (?1, ?2, ?3)
+        "#;
+
+        for value in values {
+            self.db()
+                .execute(statement_fmt, rusqlite::params![transaction, field, value])?;
+        }
+
+        Ok(())
+    }
+
+    fn transactions_indexed(&self, field: IndexedField, value: &[u8]) -> Result<Vec<EncryptedTransaction>> {
+        let statement_fmt = Self::select_from_transactions(Some(r#"
+            WHERE transaction_id IN (
+                SELECT transaction_id
+                  FROM transaction_index
+                 WHERE field = ?1 AND
+                       value = ?2
+            ) AND
+            _removal_timestamp IS NULL
+            ORDER BY timestamp DESC
+        "#));
+
+        self.query_with_params(statement_fmt, rusqlite::params![field, value], Self::transaction_from_row)
+    }
+
+    fn indexed_values(&self, field: IndexedField) -> Result<Vec<(Id, Vec<u8>)>> {
+        let statement_fmt = r#"
+            SELECT transaction_index.transaction_id, transaction_index.value
+              FROM transaction_index
+             INNER JOIN transactions
+                ON transactions.transaction_id = transaction_index.transaction_id
+             WHERE transaction_index.field = ?1 AND
+                   transactions._removal_timestamp IS NULL
+        "#;
+
+        self.query_with_params(statement_fmt, rusqlite::params![field], |row| Ok((row.get(0)?, row.get(1)?)))
+    }
+
+    fn index_version(&self) -> Result<u32> {
+        let statement_fmt = r#"
+            SELECT version
+              FROM transaction_index_state
+        "#;
+
+        let version = self.db()
+            .query_row(statement_fmt, [], |row| row.get(0))?;
+
+        Ok(version)
+    }
+
+    fn set_index_version(&self, version: u32) -> Result<()> {
+        let statement_fmt = r#"
+            UPDATE transaction_index_state
+               SET version = ?1
+        "#;
+
+        self.db()
+            .execute(statement_fmt, rusqlite::params![version])?;
+
+        Ok(())
+    }
+
+    fn redirect_references(&self, from: Id, to: Id) -> Result<()> {
+        let statements = [
+            "UPDATE transactions SET account_id = ?2 WHERE account_id = ?1",
+            "UPDATE transactions SET category_id = ?2 WHERE category_id = ?1",
+            "UPDATE plans SET category_id = ?2 WHERE category_id = ?1",
+            "UPDATE loans SET account_id = ?2 WHERE account_id = ?1",
+            "UPDATE loans SET principal_category_id = ?2 WHERE principal_category_id = ?1",
+            "UPDATE loans SET interest_category_id = ?2 WHERE interest_category_id = ?1",
+            "UPDATE holdings SET account_id = ?2 WHERE account_id = ?1",
+        ];
+
+        for statement in statements {
+            self.db()
+                .execute(statement, rusqlite::params![from, to])?;
+        }
+
+        Ok(())
+    }
+
+    fn add_merge(&self, merge: Merge) -> Result<()> {
+        //
+        // Merge is positioned at the latest change in the journal,
+        // so it is exported along with removal of the merged item
+        //
+
+        let statement_fmt = r#"
+            INSERT INTO merges (kind, kept, removed, seq, _origin, _creation_timestamp)
+            VALUES (?1, ?2, ?3, ?4, ?5, ?6)
+        "#;
+
+        self.db()
+            .execute(statement_fmt, rusqlite::params![merge.kind, merge.kept, merge.removed,
+                self.journal_position()?, merge.meta_info.origin, merge.meta_info.added_timestamp])?;
+
+        Ok(())
+    }
+
+    fn merges_since(&self, base: JournalPosition) -> Result<Vec<Merge>> {
+        let statement_fmt = r#"
+            SELECT kind, kept, removed, _origin, _creation_timestamp
+              FROM merges
+             WHERE seq > ?1
+          ORDER BY merge_id
+        "#;
+
+        self.query_with_params(statement_fmt, rusqlite::params![base], Self::merge_from_row)
+    }
+}
+
+
+impl DbStorage {
+    fn create_db(&self) -> Result<()> {
+        //
+        // Database will contain table for each entity: transaction, 
+        // account, category and plan.
+        // For optimization purposes categories table will be
+        // additionally indexed by its type, transactions table --
+        // by timestamp, plans table -- by category.
+        //
+        // Each table has two internal columns: `_change_timestamp`
+        // and `_removal_timestamp`, that are suitable for syncing
+        // content between different instances of the app.
+        // All tables are addtionally indexed by mentioned timestamps.
+        //
+
+        let create_statement = r#"
+            CREATE TABLE accounts (
+                account_id          BLOB        PRIMARY KEY DEFAULT (randomblob(16)),
+                balance             BYTEA       NOT NULL,
+                initial_balance     BYTEA       NOT NULL,
+                name                BYTEA       NOT NULL,
+                _origin             BYTEA       NOT NULL,
+                _creation_timestamp DATETIME    NOT NULL,
+                _change_timestamp   DATETIME    NULL,
+                _removal_timestamp  DATETIME    NULL
+            ) WITHOUT ROWID;
+
+            CREATE INDEX accounts_by_creation_timestamp
+                ON accounts (_creation_timestamp);
+
+            CREATE INDEX accounts_by_change_timestamp
+                ON accounts (_change_timestamp);
+
+            CREATE INDEX accounts_by_removal_timestamp
+                ON accounts (_removal_timestamp);
+                
+            CREATE TABLE categories (
+                category_id         BLOB        PRIMARY KEY DEFAULT (randomblob(16)),
+                name                BYTEA       NOT NULL,
+                type                TINYINT     NOT NULL,
+                _origin             BYTEA       NOT NULL,
+                _creation_timestamp DATETIME    NOT NULL,
+                _change_timestamp   DATETIME    NULL,
+                _removal_timestamp  DATETIME    NULL
+            ) WITHOUT ROWID;
+
+            CREATE INDEX categories_by_type
+                ON categories (type);
+
+            CREATE INDEX categories_by_creation_timestamp
+                ON categories (_creation_timestamp);
 
             CREATE INDEX categories_by_change_timestamp
                 ON categories (_change_timestamp);
@@ -728,13 +3170,81 @@ impl DbStorage {
                 ON plans (_removal_timestamp);
         "#;
 
-        self.db
+        self.db()
             .execute_batch(create_statement)
             .map_err(Error::from)
     }
 
+    fn migrate_db(&self) -> Result<()> {
+        //
+        // Apply all migrations, that were not applied yet, one by one.
+        // Each migration is applied in its own DB transaction together
+        // with schema version update, so the schema is never left in
+        // a half-migrated state
+        //
+
+        let applied: usize = self.db()
+            .query_row("PRAGMA user_version", [], |row| row.get(0))?;
+
+        for (version, migration) in MIGRATIONS.iter().enumerate().skip(applied) {
+            let statement = format!(r#"
+                BEGIN;
+                {}
+                PRAGMA user_version = {};
+                COMMIT;
+            "#, migration, version + 1);
+
+            self.db()
+                .execute_batch(&statement)?;
+        }
+
+        Ok(())
+    }
+
+    fn open_connection<L: Location>(loc: &L) -> Result<Self> {
+        //
+        // Lock is acquired before the connection is opened
+        // to prevent any access in case of a conflict
+        //
+
+        let lock = LocationLock::acquire(loc)?;
+
+        let db = rusqlite::Connection::open(Self::db_path(loc))
+            .with_context(|| format!("opening database {}", Self::db_path(loc).display()))?;
+
+        //
+        // In WAL mode readers work alongside the writer
+        // without blocking each other
+        //
+
+        db.pragma_update(None, "journal_mode", "WAL")?;
+
+        Ok(DbStorage { 
+            writer: ReentrantMutex::new(db),
+            readers: ReaderPool::new(Self::db_path(loc), READER_POOL_SIZE),
+            observers: Mutex::new(Vec::new()),
+            events: Mutex::new(Vec::new()),
+            snapshots: Mutex::new(Vec::new()),
+            next_snapshot: AtomicU64::new(0),
+            lock_overridden: AtomicBool::new(false),
+            batch_size: AtomicUsize::new(0),
+            pending: AtomicUsize::new(0),
+            _lock: lock
+        })
+    }
+
+    /// Locks the writer connection.
+    ///
+    /// The lock is reentrant: an operation, that checks something
+    /// before a change, holds it for the whole operation, so that
+    /// other threads cannot interleave their changes with it.
+    fn db(&self) -> ReentrantMutexGuard<'_, rusqlite::Connection> {
+        self.writer
+            .lock()
+    }
+
     fn db_path<L: Location>(loc: &L) -> std::path::PathBuf {
-        loc.root()
+        loc.data_dir()
             .join(DB_FILE)
     }
 
@@ -744,15 +3254,27 @@ impl DbStorage {
         P: rusqlite::Params,
         C: Fn(&rusqlite::Row<'_>) -> Result<T>
     {
-        let mut statement = self.db.prepare(statement.as_ref())?;
-        let mut rows = statement.query(params)?;
+        let run = |db: &rusqlite::Connection| {
+            let mut statement = db.prepare(statement.as_ref())?;
+            let mut rows = statement.query(params)?;
 
-        let mut result = Vec::new();
-        while let Some(row) = rows.next()? {
-            result.push(convert(row)?)
-        }
+            let mut result = Vec::new();
+            while let Some(row) = rows.next()? {
+                result.push(convert(row)?)
+            }
+
+            Ok(result)
+        };
+
+        //
+        // Changes made after a snapshot or batched ones are not committed
+        // yet, so they are visible through the main connection only
+        //
 
-        Ok(result)
+        match self.has_snapshots() || self.batch_size.load(Ordering::Relaxed) != 0 {
+            true => run(&self.db()),
+            false => self.readers.with(run)
+        }
     }
 
     fn query<S, T, C>(&self, statement: S, convert: C) -> Result<Vec<T>>
@@ -763,6 +3285,90 @@ impl DbStorage {
         self.query_with_params(statement, [], convert)
     }
 
+    fn notify(&self, entity: EntityKind, change: ChangeKind, id: Id) -> Result<()> {
+        trace_debug!(?entity, ?change, id = %uuid::Uuid::from_bytes(id), "storage item changed");
+
+        //
+        // Observers are notified once the change is committed,
+        // since it may be rolled back with a snapshot or a batch
+        //
+
+        self.events
+            .lock()
+            .push(StorageEvent { entity, change, id });
+
+        self.pending.fetch_add(1, Ordering::Relaxed);
+        self.batched()
+    }
+
+    fn dispatch_events(&self) {
+        let events = std::mem::take(&mut *self.events.lock());
+        if events.is_empty() {
+            return;
+        }
+
+        //
+        // Observers are invoked without the lock held, since
+        // they may register other observers
+        //
+
+        let observers = self.observers
+            .lock()
+            .clone();
+
+        for event in &events {
+            for observer in &observers {
+                observer(event);
+            }
+        }
+    }
+
+    /// Commits a batch transaction if it is full or batching is disabled,
+    /// then begins a new one if batching is enabled. Observers are
+    /// notified about committed changes.
+    /// 
+    /// Transaction is kept open while there are snapshots: their
+    /// savepoints are nested into it.
+    fn batched(&self) -> Result<()> {
+        {
+            let db = self.db();
+            if self.has_snapshots() {
+                return Ok(());
+            }
+
+            let batch_size = self.batch_size.load(Ordering::Relaxed);
+            if batch_size == 0 || self.pending.load(Ordering::Relaxed) >= batch_size {
+                self.commit_batch()?;
+            }
+
+            let committed = db.is_autocommit();
+            if batch_size != 0 && committed {
+                db.execute_batch("BEGIN")?;
+            }
+
+            if !committed {
+                return Ok(());
+            }
+        }
+
+        self.dispatch_events();
+        Ok(())
+    }
+
+    fn commit_batch(&self) -> Result<()> {
+        let db = self.db();
+        if self.has_snapshots() || db.is_autocommit() {
+            return Ok(());
+        }
+
+        trace_debug!(changes = self.pending.load(Ordering::Relaxed), "committing batched changes");
+
+        db.execute_batch("COMMIT")?;
+        self.pending.store(0, Ordering::Relaxed);
+
+        Ok(())
+    }
+
     fn ensure_consistency(&self, table: &str, foreign_key: &str, foreign_key_value: Id) -> Result<()> {
         let statement_fmt = format!(r#"
             SELECT COUNT(*) FROM {}
@@ -770,22 +3376,49 @@ impl DbStorage {
                AND {} = ?1
             "#, table, foreign_key);
 
-        let count: usize = self.db
-            .query_row(statement_fmt.as_str(), rusqlite::params![foreign_key_value], 
+        let count: usize = self.db()
+            .query_row(statement_fmt.as_str(), rusqlite::params![foreign_key_value],
                 |row| row.get(0))?;
 
         if 0 < count {
             return Err(Error::from_message_with_extra(CONSISTENCY_VIOLATION,
-                format!("Table: {}, foreign key: {}", table, foreign_key)));
+                format!("Table: {}, foreign key: {}", table, foreign_key)).with_kind(ErrorKind::ConsistencyViolation));
         }
 
         Ok(())
     }
 
+    fn ensure_unlocked(&self, timestamp: Timestamp) -> Result<()> {
+        if self.lock_overridden.load(Ordering::Relaxed) {
+            return Ok(());
+        }
+
+        match self.period_lock()? {
+            Some(until) if timestamp < until => 
+                Err(Error::from_message_with_extra(PERIOD_IS_LOCKED, format!("Locked until: {}", until))
+                    .with_kind(ErrorKind::Locked)),
+            _ => Ok(())
+        }
+    }
+
+    fn snapshot_position(&self, snapshot: SnapshotId) -> Result<usize> {
+        self.snapshots
+            .lock()
+            .iter()
+            .position(|(active, _)| *active == snapshot)
+            .ok_or_else(|| Error::from_message_with_extra(UNKNOWN_SNAPSHOT, snapshot.to_string()).with_kind(ErrorKind::NotFound))
+    }
+
+    fn savepoint_name(snapshot: SnapshotId) -> String {
+        format!("snapshot_{}", snapshot)
+    }
+
     fn is_predefined_category(category: Id) -> bool {
         let predefined = [
             Self::TRANSFER_INCOME_ID,
-            Self::TRANSFER_OUTCOME_ID
+            Self::TRANSFER_OUTCOME_ID,
+            Self::ADJUSTMENT_INCOME_ID,
+            Self::ADJUSTMENT_OUTCOME_ID
         ];
 
         predefined.contains(&category)
@@ -799,8 +3432,9 @@ impl DbStorage {
             .map_or(String::new(), S::into);
 
         return format!(r#"
-            SELECT transaction_id, timestamp, description, account_id, category_id, amount, 
-                   _origin, _creation_timestamp, _change_timestamp, _removal_timestamp
+            SELECT transaction_id, timestamp, description, account_id, category_id, amount, kind, note, custom_fields,
+                   _origin, _creation_timestamp, _change_timestamp, _removal_timestamp,
+                   spender_id
               FROM transactions
                 {}
         "#, modifiers);
@@ -811,7 +3445,8 @@ impl DbStorage {
             .map_or(String::new(), S::into);
 
         return format!(r#"
-            SELECT account_id, name, balance, initial_balance, _origin, _creation_timestamp, _change_timestamp, _removal_timestamp
+            SELECT account_id, name, balance, initial_balance, note, custom_fields, 
+                   _origin, _creation_timestamp, _change_timestamp, _removal_timestamp
               FROM accounts
                 {}
         "#, modifiers);
@@ -822,18 +3457,82 @@ impl DbStorage {
             .map_or(String::new(), S::into);
 
         return format!(r#"
-            SELECT category_id, name, type, _origin, _creation_timestamp, _change_timestamp, _removal_timestamp
+            SELECT category_id, name, type, note, custom_fields, 
+                   _origin, _creation_timestamp, _change_timestamp, _removal_timestamp
               FROM categories
                 {}
         "#, modifiers);
     }
 
+    fn select_from_loans<S: Into<String>>(modifiers: Option<S>) -> String {
+        let modifiers = modifiers
+            .map_or(String::new(), S::into);
+
+        return format!(r#"
+            SELECT loan_id, name, account_id, principal_category_id, interest_category_id, principal, 
+                   interest_rate, term, start_timestamp, note, custom_fields, 
+                   _origin, _creation_timestamp, _change_timestamp, _removal_timestamp
+              FROM loans
+                {}
+        "#, modifiers);
+    }
+
+    fn select_from_holdings<S: Into<String>>(modifiers: Option<S>) -> String {
+        let modifiers = modifiers
+            .map_or(String::new(), S::into);
+
+        return format!(r#"
+            SELECT holding_id, account_id, symbol, quantity, note, custom_fields, 
+                   _origin, _creation_timestamp, _change_timestamp, _removal_timestamp
+              FROM holdings
+                {}
+        "#, modifiers);
+    }
+
+    fn select_from_persons<S: Into<String>>(modifiers: Option<S>) -> String {
+        let modifiers = modifiers
+            .map_or(String::new(), S::into);
+
+        return format!(r#"
+            SELECT person_id, name, 
+                   _origin, _creation_timestamp, _change_timestamp, _removal_timestamp
+              FROM persons
+                {}
+        "#, modifiers);
+    }
+
+    fn select_from_category_groups<S: Into<String>>(modifiers: Option<S>) -> String {
+        let modifiers = modifiers
+            .map_or(String::new(), S::into);
+
+        return format!(r#"
+            SELECT group_id, name, categories, 
+                   _origin, _creation_timestamp, _change_timestamp, _removal_timestamp
+              FROM category_groups
+                {}
+        "#, modifiers);
+    }
+
+    fn select_from_prices<S: Into<String>>(modifiers: Option<S>) -> String {
+        let modifiers = modifiers
+            .map_or(String::new(), S::into);
+
+        return format!(r#"
+            SELECT price_id, symbol, timestamp, price, 
+                   _origin, _creation_timestamp, _change_timestamp, _removal_timestamp
+              FROM prices
+                {}
+        "#, modifiers);
+    }
+
     fn select_from_plans<S: Into<String>>(modifiers: Option<S>) -> String {
         let modifiers = modifiers
             .map_or(String::new(), S::into);
 
         return format!(r#"
-            SELECT plan_id, category_id, name, amount_limit, _origin, _creation_timestamp, _change_timestamp, _removal_timestamp
+            SELECT plan_id, category_id, name, amount_limit, note, custom_fields, 
+                   _origin, _creation_timestamp, _change_timestamp, _removal_timestamp,
+                   alert_threshold
               FROM plans
                 {}
         "#, modifiers);
@@ -844,26 +3543,28 @@ impl DbStorage {
 impl DbStorage {
     fn category_from_row(row: &rusqlite::Row<'_>) -> Result<EncryptedCategory> {
         let meta_info = MetaInfo {
-            origin: row.get(3)?,
-            added_timestamp: row.get(4)?,
-            changed_timestamp: row.get(5)?,
-            removed_timestamp: row.get(6)?
+            origin: row.get(5)?,
+            added_timestamp: row.get(6)?,
+            changed_timestamp: row.get(7)?,
+            removed_timestamp: row.get(8)?
         };
 
         Ok(EncryptedCategory { 
             id: row.get(0)?, 
             name: row.get(1)?, 
             category_type: row.get(2)?,
+            note: row.get(3)?,
+            custom_fields: row.get(4)?,
             meta_info: meta_info
         })
     }
 
     fn account_from_row(row: &rusqlite::Row<'_>) -> Result<EncryptedAccount> {
         let meta_info = MetaInfo {
-            origin: row.get(4)?,
-            added_timestamp: row.get(5)?,
-            changed_timestamp: row.get(6)?,
-            removed_timestamp: row.get(7)?
+            origin: row.get(6)?,
+            added_timestamp: row.get(7)?,
+            changed_timestamp: row.get(8)?,
+            removed_timestamp: row.get(9)?
         };
 
         Ok(EncryptedAccount { 
@@ -871,16 +3572,18 @@ impl DbStorage {
             name: row.get(1)?, 
             balance: row.get(2)?,
             initial_balance: row.get(3)?,
+            note: row.get(4)?,
+            custom_fields: row.get(5)?,
             meta_info: meta_info
         })
     }
 
     fn transaction_from_row(row: &rusqlite::Row<'_>) -> Result<EncryptedTransaction> {
         let meta_info = MetaInfo {
-            origin: row.get(6)?,
-            added_timestamp: row.get(7)?,
-            changed_timestamp: row.get(8)?,
-            removed_timestamp: row.get(9)?
+            origin: row.get(9)?,
+            added_timestamp: row.get(10)?,
+            changed_timestamp: row.get(11)?,
+            removed_timestamp: row.get(12)?
         };
 
         Ok(EncryptedTransaction { 
@@ -890,16 +3593,20 @@ impl DbStorage {
             account_id: row.get(3)?, 
             category_id: row.get(4)?, 
             amount: row.get(5)?,
+            kind: row.get(6)?,
+            note: row.get(7)?,
+            custom_fields: row.get(8)?,
+            spender_id: row.get(13)?,
             meta_info: meta_info
         })
     }
 
     fn plan_from_row(row: &rusqlite::Row<'_>) -> Result<EncryptedPlan> {
         let meta_info = MetaInfo {
-            origin: row.get(4)?,
-            added_timestamp: row.get(5)?,
-            changed_timestamp: row.get(6)?,
-            removed_timestamp: row.get(7)?
+            origin: row.get(6)?,
+            added_timestamp: row.get(7)?,
+            changed_timestamp: row.get(8)?,
+            removed_timestamp: row.get(9)?
         };
 
         Ok(EncryptedPlan {
@@ -907,7 +3614,178 @@ impl DbStorage {
             category_id: row.get(1)?,
             name: row.get(2)?,
             amount_limit: row.get(3)?,
+            alert_threshold: row.get(10)?,
+            note: row.get(4)?,
+            custom_fields: row.get(5)?,
             meta_info: meta_info
         })
     }
+
+    fn loan_from_row(row: &rusqlite::Row<'_>) -> Result<EncryptedLoan> {
+        let meta_info = MetaInfo {
+            origin: row.get(11)?,
+            added_timestamp: row.get(12)?,
+            changed_timestamp: row.get(13)?,
+            removed_timestamp: row.get(14)?
+        };
+
+        Ok(EncryptedLoan {
+            id: row.get(0)?,
+            name: row.get(1)?,
+            account_id: row.get(2)?,
+            principal_category_id: row.get(3)?,
+            interest_category_id: row.get(4)?,
+            principal: row.get(5)?,
+            interest_rate: row.get(6)?,
+            term: row.get(7)?,
+            start_timestamp: row.get(8)?,
+            note: row.get(9)?,
+            custom_fields: row.get(10)?,
+            meta_info
+        })
+    }
+
+    fn holding_from_row(row: &rusqlite::Row<'_>) -> Result<EncryptedHolding> {
+        let meta_info = MetaInfo {
+            origin: row.get(6)?,
+            added_timestamp: row.get(7)?,
+            changed_timestamp: row.get(8)?,
+            removed_timestamp: row.get(9)?
+        };
+
+        Ok(EncryptedHolding {
+            id: row.get(0)?,
+            account_id: row.get(1)?,
+            symbol: row.get(2)?,
+            quantity: row.get(3)?,
+            note: row.get(4)?,
+            custom_fields: row.get(5)?,
+            meta_info
+        })
+    }
+
+    fn price_from_row(row: &rusqlite::Row<'_>) -> Result<EncryptedPricePoint> {
+        let meta_info = MetaInfo {
+            origin: row.get(4)?,
+            added_timestamp: row.get(5)?,
+            changed_timestamp: row.get(6)?,
+            removed_timestamp: row.get(7)?
+        };
+
+        Ok(EncryptedPricePoint {
+            id: row.get(0)?,
+            symbol: row.get(1)?,
+            timestamp: row.get(2)?,
+            price: row.get(3)?,
+            meta_info
+        })
+    }
+
+    fn person_from_row(row: &rusqlite::Row<'_>) -> Result<EncryptedPerson> {
+        let meta_info = MetaInfo {
+            origin: row.get(2)?,
+            added_timestamp: row.get(3)?,
+            changed_timestamp: row.get(4)?,
+            removed_timestamp: row.get(5)?
+        };
+
+        Ok(EncryptedPerson {
+            id: row.get(0)?,
+            name: row.get(1)?,
+            meta_info
+        })
+    }
+
+    fn category_group_from_row(row: &rusqlite::Row<'_>) -> Result<EncryptedCategoryGroup> {
+        let meta_info = MetaInfo {
+            origin: row.get(3)?,
+            added_timestamp: row.get(4)?,
+            changed_timestamp: row.get(5)?,
+            removed_timestamp: row.get(6)?
+        };
+
+        //
+        // Categories are stored as concatenated identifiers
+        //
+
+        let categories: Vec<u8> = row.get(2)?;
+        let categories = categories
+            .chunks_exact(std::mem::size_of::<Id>())
+            .map(|chunk| chunk.try_into().unwrap())
+            .collect();
+
+        Ok(EncryptedCategoryGroup {
+            id: row.get(0)?,
+            name: row.get(1)?,
+            categories,
+            meta_info
+        })
+    }
+
+    fn merge_from_row(row: &rusqlite::Row<'_>) -> Result<Merge> {
+        let meta_info = MetaInfo {
+            origin: row.get(3)?,
+            added_timestamp: row.get(4)?,
+            changed_timestamp: None,
+            removed_timestamp: None
+        };
+
+        Ok(Merge {
+            kind: row.get(0)?,
+            kept: row.get(1)?,
+            removed: row.get(2)?,
+            meta_info
+        })
+    }
+
+    fn settings_from_row(row: &rusqlite::Row<'_>) -> Result<EncryptedSettings> {
+        let meta_info = MetaInfo {
+            origin: row.get(1)?,
+            added_timestamp: None,
+            changed_timestamp: row.get(2)?,
+            removed_timestamp: None
+        };
+
+        Ok(EncryptedSettings {
+            data: row.get(0)?,
+            meta_info
+        })
+    }
+}
+
+
+impl Drop for DbStorage {
+    fn drop(&mut self) {
+        //
+        // Changes made after a snapshot are discarded as usual,
+        // batched ones are committed. Nothing can be done with
+        // an error here, so it is just traced
+        //
+
+        let first_snapshot = self.snapshots
+            .lock()
+            .first()
+            .copied();
+
+        let result = match first_snapshot {
+            Some((snapshot, _)) => self.rollback_to(snapshot),
+            None => Ok(())
+        };
+
+        if let Err(_error) = result.and_then(|_| self.set_write_batching(None)) {
+            trace_debug!(%_error, "batched changes are not committed");
+        }
+    }
 }
+
+
+//
+// Frontends share the storage between threads: this fails
+// to compile if it stops being thread-safe
+//
+
+const _: fn() = || {
+    fn assert_send_sync<T: Send + Sync>() {}
+
+    assert_send_sync::<DbStorage>();
+};
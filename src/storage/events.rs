@@ -0,0 +1,70 @@
+use super::data::Id;
+
+
+/// Kinds of entities stored in a storage.
+#[derive(Clone, Copy, PartialEq, Eq, Debug)]
+pub enum EntityKind {
+    /// Transactions
+    Transaction,
+
+    /// Accounts
+    Account,
+
+    /// Categories
+    Category,
+
+    /// Plans
+    Plan,
+
+    /// Loans
+    Loan,
+
+    /// Investment holdings
+    Holding,
+
+    /// Security prices
+    PricePoint,
+
+    /// Persons within a household
+    Person,
+
+    /// Groups of categories
+    CategoryGroup,
+}
+
+
+/// Kinds of changes performed on entities.
+#[derive(Clone, Copy, PartialEq, Eq, Debug)]
+pub enum ChangeKind {
+    /// Entity was added
+    Added,
+
+    /// Entity was updated
+    Updated,
+
+    /// Entity was removed
+    Removed,
+}
+
+
+/// Event emitted by a storage after a mutation is committed.
+#[derive(Clone, Copy, PartialEq, Eq, Debug)]
+pub struct StorageEvent {
+    /// Kind of a changed entity
+    pub entity: EntityKind,
+
+    /// Kind of a change
+    pub change: ChangeKind,
+
+    /// Identifier of a changed entity
+    pub id: Id,
+}
+
+
+/// Callback, that is invoked after each committed storage mutation.
+///
+/// Callbacks are [`Send`] and [`Sync`], so that thread-safe storages
+/// can invoke them from threads, that make changes. They are shared,
+/// so that storages invoke them without holding a lock: a callback
+/// may register another one or change a storage.
+pub type ChangeCallback = std::sync::Arc<dyn Fn(&StorageEvent) + Send + Sync>;
@@ -0,0 +1,1519 @@
+use std::cell::{Cell, RefCell};
+use std::cmp::Reverse;
+use std::collections::{BTreeMap, BTreeSet};
+
+use crate::error::{Result, Error, ErrorKind};
+use crate::trace::trace_debug;
+use crate::datetime::Timestamp;
+use super::data::{EncryptedTransaction, EncryptedTransactionSummary, EncryptedCategory, EncryptedAccount, EncryptedPlan, EncryptedLoan, Id, PrimaryId, CategoryType, MetaInfo};
+use super::data::{EncryptedHolding, EncryptedPricePoint, EncryptedPerson, EncryptedCategoryGroup, EncryptedSettings, JournalPosition, SnapshotId, Merge, CryptoRecord, RecoveryRecord, ItemCount, IndexedField};
+use super::events::{EntityKind, ChangeKind, StorageEvent, ChangeCallback};
+use super::storage::DataStorage;
+use super::{CONSISTENCY_VIOLATION, CANNOT_DELETE_PREDEFINED, ITEM_NOT_FOUND, ITEM_ALREADY_EXISTS, SETTINGS_ID};
+use super::{UNKNOWN_SNAPSHOT, PERIOD_IS_LOCKED};
+
+
+/// Common interface of items kept in [`MemoryStorage`].
+trait StoredItem: Clone {
+    /// Kind of the item
+    const ENTITY: EntityKind;
+
+    /// Name of a table (used in error messages)
+    const TABLE: &'static str;
+
+    /// Returns a table with items of this type.
+    fn table(state: &MemoryState) -> &Vec<Self>;
+
+    /// Returns a mutable table with items of this type.
+    fn table_mut(state: &mut MemoryState) -> &mut Vec<Self>;
+
+    /// Returns item's identifier.
+    fn id(&self) -> PrimaryId;
+
+    /// Sets item's identifier.
+    fn set_id(&mut self, id: Id);
+
+    /// Returns item's meta info.
+    fn meta_info(&self) -> &MetaInfo;
+
+    /// Returns item's mutable meta info.
+    fn meta_info_mut(&mut self) -> &mut MetaInfo;
+}
+
+
+/// Macro for implementing [`StoredItem`] for all encrypted
+/// structures, that share the same layout of id and meta info.
+macro_rules! implement_stored_item {
+    ($($item:ty => ($entity:expr, $table:ident)),+ $(,)?) => {
+        $(
+            impl StoredItem for $item {
+                const ENTITY: EntityKind = $entity;
+                const TABLE: &'static str = stringify!($table);
+
+                fn table(state: &MemoryState) -> &Vec<Self> {
+                    &state.$table
+                }
+
+                fn table_mut(state: &mut MemoryState) -> &mut Vec<Self> {
+                    &mut state.$table
+                }
+
+                fn id(&self) -> PrimaryId {
+                    self.id
+                }
+
+                fn set_id(&mut self, id: Id) {
+                    self.id = Some(id);
+                }
+
+                fn meta_info(&self) -> &MetaInfo {
+                    &self.meta_info
+                }
+
+                fn meta_info_mut(&mut self) -> &mut MetaInfo {
+                    &mut self.meta_info
+                }
+            }
+        )+
+    }
+}
+
+implement_stored_item!(
+    EncryptedTransaction => (EntityKind::Transaction, transactions),
+    EncryptedAccount => (EntityKind::Account, accounts),
+    EncryptedCategory => (EntityKind::Category, categories),
+    EncryptedPlan => (EntityKind::Plan, plans),
+    EncryptedLoan => (EntityKind::Loan, loans),
+    EncryptedHolding => (EntityKind::Holding, holdings),
+    EncryptedPricePoint => (EntityKind::PricePoint, prices),
+    EncryptedPerson => (EntityKind::Person, persons),
+    EncryptedCategoryGroup => (EntityKind::CategoryGroup, category_groups),
+);
+
+
+/// Entry of changes journal.
+#[derive(Clone)]
+struct Change {
+    /// Position in the journal
+    seq: JournalPosition,
+
+    /// Identifier of a changed item
+    item: Id,
+
+    /// Kind of a change
+    change: ChangeKind,
+}
+
+
+/// Contents of [`MemoryStorage`].
+#[derive(Default, Clone)]
+struct MemoryState {
+    /// Transactions
+    transactions: Vec<EncryptedTransaction>,
+
+    /// Accounts
+    accounts: Vec<EncryptedAccount>,
+
+    /// Categories
+    categories: Vec<EncryptedCategory>,
+
+    /// Plans
+    plans: Vec<EncryptedPlan>,
+
+    /// Loans
+    loans: Vec<EncryptedLoan>,
+
+    /// Investment holdings
+    holdings: Vec<EncryptedHolding>,
+
+    /// Security prices
+    prices: Vec<EncryptedPricePoint>,
+
+    /// Persons within a household
+    persons: Vec<EncryptedPerson>,
+
+    /// Groups of categories
+    category_groups: Vec<EncryptedCategoryGroup>,
+
+    /// Settings shared between instances
+    settings: Option<EncryptedSettings>,
+
+    /// Changes journal
+    changes: Vec<Change>,
+
+    /// Position of the latest change (journal is pruned, so it is kept separately)
+    last_seq: JournalPosition,
+
+    /// Position, that all changes up to were exported
+    exported_seq: JournalPosition,
+
+    /// Timestamp, that transactions dated before are locked
+    locked_until: Option<Timestamp>,
+
+    /// If timestamps of transactions are canonical transaction dates
+    canonical_dates: bool,
+
+    /// Record of cryptographic engine, that protects data
+    crypto_record: Option<CryptoRecord>,
+
+    /// Fingerprint of a key, that data belongs to
+    key_fingerprint: Option<String>,
+
+    /// Record of a recovery key
+    recovery_record: Option<RecoveryRecord>,
+
+    /// If descriptions of transactions are encrypted deterministically
+    deterministic_descriptions: bool,
+
+    /// Merges of items with journal positions they were recorded at
+    merges: Vec<(JournalPosition, Merge)>,
+
+    /// Instances, that items are private to
+    visibility: BTreeMap<Id, Id>,
+
+    /// Locally indexed values of transactions
+    index: Vec<(Id, IndexedField, Vec<u8>)>,
+
+    /// Version of the local index of transactions
+    index_version: u32,
+}
+
+
+/// Storage, that keeps all data in memory.
+///
+/// It has no system dependencies and does not touch the filesystem,
+/// hence it is suitable for environments without SQLite, e.g.
+/// browsers. Semantics (soft removal, consistency checks, changes
+/// journal) are the same as in [`super::DbStorage`]. Persistent
+/// backends for such environments (e.g. IndexedDB) can be added by
+/// implementing [`DataStorage`] trait.
+#[derive(Default)]
+pub struct MemoryStorage {
+    /// Stored data
+    state: RefCell<MemoryState>,
+
+    /// Callbacks to invoke after each mutation
+    observers: RefCell<Vec<ChangeCallback>>,
+
+    /// Events of changes made after snapshots, that are not reported yet
+    events: RefCell<Vec<StorageEvent>>,
+
+    /// Copies of the state taken by active snapshots from the oldest to the newest
+    /// along with numbers of events queued before them
+    snapshots: RefCell<Vec<(SnapshotId, MemoryState, usize)>>,
+
+    /// Identifier of the next snapshot
+    next_snapshot: Cell<SnapshotId>,
+
+    /// If locked transactions can be modified
+    lock_overridden: Cell<bool>,
+}
+
+
+impl MemoryStorage {
+    /// Creates an empty storage.
+    pub fn new() -> Self {
+        Self::default()
+    }
+}
+
+
+impl DataStorage for MemoryStorage {
+    const TRANSFER_INCOME_ID: Id = [0x00; 16];
+
+    const TRANSFER_OUTCOME_ID: Id = [0xFF; 16];
+
+    const ADJUSTMENT_INCOME_ID: Id = [0x0F; 16];
+
+    const ADJUSTMENT_OUTCOME_ID: Id = [0xF0; 16];
+
+    fn add_transaction(&self, transaction: EncryptedTransaction) -> Result<()> {
+        self.ensure_unlocked(transaction.timestamp)?;
+        self.add(transaction)
+    }
+
+    fn update_transaction(&self, transaction: EncryptedTransaction) -> Result<()> {
+        if let Some(existing) = transaction.id.and_then(|id| self.find::<EncryptedTransaction>(id).ok()) {
+            self.ensure_unlocked(existing.timestamp)?;
+        }
+
+        self.ensure_unlocked(transaction.timestamp)?;
+
+        //
+        // Change timestamp is taken from meta information,
+        // it is not updated if absent
+        //
+
+        self.update(&transaction, |stored| {
+            stored.timestamp = transaction.timestamp;
+            stored.description = transaction.description.clone();
+            stored.account_id = transaction.account_id;
+            stored.category_id = transaction.category_id;
+            stored.amount = transaction.amount.clone();
+            stored.kind = transaction.kind;
+            stored.note = transaction.note.clone();
+            stored.custom_fields = transaction.custom_fields.clone();
+            stored.spender_id = transaction.spender_id;
+        });
+
+        Ok(())
+    }
+
+    fn remove_transaction(&self, transaction: Id, removal_timestamp: Timestamp) -> Result<()> {
+        if let Ok(existing) = self.find::<EncryptedTransaction>(transaction) {
+            self.ensure_unlocked(existing.timestamp)?;
+        }
+
+        self.remove::<EncryptedTransaction>(transaction, removal_timestamp)
+    }
+
+    fn transaction(&self, transaction: Id) -> Result<EncryptedTransaction> {
+        self.find(transaction)
+    }
+
+    fn transactions(&self) -> Result<Vec<EncryptedTransaction>> {
+        Ok(self.transactions_where(|_| true))
+    }
+
+    fn transactions_after(&self, start_timestamp: Timestamp) -> Result<Vec<EncryptedTransaction>> {
+        Ok(self.transactions_where(|transaction| start_timestamp <= transaction.timestamp))
+    }
+
+    fn transactions_between(&self, start_timestamp: Timestamp, end_timestamp: Timestamp) -> Result<Vec<EncryptedTransaction>> {
+        Ok(self.transactions_where(|transaction| (start_timestamp..end_timestamp).contains(&transaction.timestamp)))
+    }
+
+    fn transaction_summaries_between(&self, start_timestamp: Timestamp, end_timestamp: Timestamp) -> Result<Vec<EncryptedTransactionSummary>> {
+        let summaries = self.transactions_between(start_timestamp, end_timestamp)?
+            .into_iter()
+            .map(|transaction| EncryptedTransactionSummary {
+                id: transaction.id,
+                timestamp: transaction.timestamp,
+                account_id: transaction.account_id,
+                category_id: transaction.category_id,
+                amount: transaction.amount,
+                kind: transaction.kind
+            })
+            .collect();
+
+        Ok(summaries)
+    }
+
+    fn transactions_of(&self, account: Id) -> Result<Vec<EncryptedTransaction>> {
+        Ok(self.transactions_where(|transaction| transaction.account_id == account))
+    }
+
+    fn transactions_of_after(&self, account: Id, start_timestamp: Timestamp) -> Result<Vec<EncryptedTransaction>> {
+        Ok(self.transactions_where(|transaction| transaction.account_id == account &&
+            start_timestamp <= transaction.timestamp))
+    }
+
+    fn transactions_of_between(&self, account: Id, start_timestamp: Timestamp, end_timestamp: Timestamp) -> Result<Vec<EncryptedTransaction>> {
+        Ok(self.transactions_where(|transaction| transaction.account_id == account &&
+            (start_timestamp..end_timestamp).contains(&transaction.timestamp)))
+    }
+
+    fn transactions_with(&self, category: Id) -> Result<Vec<EncryptedTransaction>> {
+        Ok(self.transactions_where(|transaction| transaction.category_id == category))
+    }
+
+    fn transactions_with_after(&self, category: Id, start_timestamp: Timestamp) -> Result<Vec<EncryptedTransaction>> {
+        Ok(self.transactions_where(|transaction| transaction.category_id == category &&
+            start_timestamp <= transaction.timestamp))
+    }
+
+    fn transactions_with_between(&self, category: Id, start_timestamp: Timestamp, end_timestamp: Timestamp) -> Result<Vec<EncryptedTransaction>> {
+        Ok(self.transactions_where(|transaction| transaction.category_id == category &&
+            (start_timestamp..end_timestamp).contains(&transaction.timestamp)))
+    }
+
+    fn transactions_described(&self, description: &[u8]) -> Result<Vec<EncryptedTransaction>> {
+        Ok(self.transactions_where(|transaction| transaction.description == description))
+    }
+
+    fn transactions_added_since(&self, base: JournalPosition) -> Result<Vec<EncryptedTransaction>> {
+        Ok(self.changed_since(base, ChangeKind::Added))
+    }
+
+    fn transactions_changed_since(&self, base: JournalPosition) -> Result<Vec<EncryptedTransaction>> {
+        Ok(self.changed_since(base, ChangeKind::Updated))
+    }
+
+    fn transactions_removed_since(&self, base: JournalPosition) -> Result<Vec<EncryptedTransaction>> {
+        Ok(self.changed_since(base, ChangeKind::Removed))
+    }
+
+    fn add_account(&self, account: EncryptedAccount) -> Result<()> {
+        self.add(account)
+    }
+
+    fn update_account(&self, account: EncryptedAccount) -> Result<()> {
+        //
+        // As in DB storage change timestamp is not set here,
+        // initial balance is not changed as well
+        //
+
+        self.update(&account, |stored| {
+            stored.name = account.name.clone();
+            stored.balance = account.balance.clone();
+            stored.note = account.note.clone();
+            stored.custom_fields = account.custom_fields.clone();
+        });
+
+        Ok(())
+    }
+
+    fn remove_account(&self, account: Id, removal_timestamp: Timestamp) -> Result<()> {
+        //
+        // Check if we can delete account: no items should belong to it.
+        // Only after that I can remove account
+        //
+
+        self.ensure_consistency::<EncryptedTransaction, _>("account_id", |transaction| transaction.account_id == account)?;
+        self.ensure_consistency::<EncryptedLoan, _>("account_id", |loan| loan.account_id == account)?;
+        self.ensure_consistency::<EncryptedHolding, _>("account_id", |holding| holding.account_id == account)?;
+
+        self.remove::<EncryptedAccount>(account, removal_timestamp)
+    }
+
+    fn account(&self, account: Id) -> Result<EncryptedAccount> {
+        self.find(account)
+    }
+
+    fn accounts(&self) -> Result<Vec<EncryptedAccount>> {
+        Ok(self.select(|_| true))
+    }
+
+    fn accounts_added_since(&self, base: JournalPosition) -> Result<Vec<EncryptedAccount>> {
+        Ok(self.changed_since(base, ChangeKind::Added))
+    }
+
+    fn accounts_changed_since(&self, base: JournalPosition) -> Result<Vec<EncryptedAccount>> {
+        Ok(self.changed_since(base, ChangeKind::Updated))
+    }
+
+    fn accounts_removed_since(&self, base: JournalPosition) -> Result<Vec<EncryptedAccount>> {
+        Ok(self.changed_since(base, ChangeKind::Removed))
+    }
+
+    fn add_category(&self, category: EncryptedCategory) -> Result<()> {
+        self.add(category)
+    }
+
+    fn remove_category(&self, category: Id, removal_timestamp: Timestamp) -> Result<()> {
+        //
+        // Check if no transactions, plans and loans reference this category
+        //
+
+        if Self::is_predefined_category(category) {
+            return Err(Error::from_message(CANNOT_DELETE_PREDEFINED).with_kind(ErrorKind::ConsistencyViolation));
+        }
+
+        self.ensure_consistency::<EncryptedTransaction, _>("category_id", |transaction| transaction.category_id == category)?;
+        self.ensure_consistency::<EncryptedPlan, _>("category_id", |plan| plan.category_id == category)?;
+        self.ensure_consistency::<EncryptedLoan, _>("principal_category_id", |loan| loan.principal_category_id == category)?;
+        self.ensure_consistency::<EncryptedLoan, _>("interest_category_id", |loan| loan.interest_category_id == category)?;
+
+        self.remove::<EncryptedCategory>(category, removal_timestamp)
+    }
+
+    fn category(&self, category: Id) -> Result<EncryptedCategory> {
+        self.find(category)
+    }
+
+    fn categories(&self) -> Result<Vec<EncryptedCategory>> {
+        let mut categories: Vec<EncryptedCategory> = self.select(|_| true);
+        categories.sort_by_key(|category| category.category_type);
+
+        Ok(categories)
+    }
+
+    fn categories_of(&self, category_type: CategoryType) -> Result<Vec<EncryptedCategory>> {
+        Ok(self.select(|category: &EncryptedCategory| category.category_type == category_type))
+    }
+
+    fn categories_added_since(&self, base: JournalPosition) -> Result<Vec<EncryptedCategory>> {
+        Ok(self.changed_since(base, ChangeKind::Added))
+    }
+
+    fn categories_changed_since(&self, base: JournalPosition) -> Result<Vec<EncryptedCategory>> {
+        Ok(self.changed_since(base, ChangeKind::Updated))
+    }
+
+    fn categories_removed_since(&self, base: JournalPosition) -> Result<Vec<EncryptedCategory>> {
+        Ok(self.changed_since(base, ChangeKind::Removed))
+    }
+
+    fn add_plan(&self, plan: EncryptedPlan) -> Result<()> {
+        self.add(plan)
+    }
+
+    fn remove_plan(&self, plan: Id, removal_timestamp: Timestamp) -> Result<()> {
+        self.remove::<EncryptedPlan>(plan, removal_timestamp)
+    }
+
+    fn plan(&self, plan: Id) -> Result<EncryptedPlan> {
+        self.find(plan)
+    }
+
+    fn plans(&self) -> Result<Vec<EncryptedPlan>> {
+        let mut plans: Vec<EncryptedPlan> = self.select(|_| true);
+        plans.sort_by_key(|plan| plan.category_id);
+
+        Ok(plans)
+    }
+
+    fn plans_for(&self, category: Id) -> Result<Vec<EncryptedPlan>> {
+        Ok(self.select(|plan: &EncryptedPlan| plan.category_id == category))
+    }
+
+    fn plans_added_since(&self, base: JournalPosition) -> Result<Vec<EncryptedPlan>> {
+        Ok(self.changed_since(base, ChangeKind::Added))
+    }
+
+    fn plans_changed_since(&self, base: JournalPosition) -> Result<Vec<EncryptedPlan>> {
+        Ok(self.changed_since(base, ChangeKind::Updated))
+    }
+
+    fn plans_removed_since(&self, base: JournalPosition) -> Result<Vec<EncryptedPlan>> {
+        Ok(self.changed_since(base, ChangeKind::Removed))
+    }
+
+    fn add_loan(&self, loan: EncryptedLoan) -> Result<()> {
+        self.add(loan)
+    }
+
+    fn remove_loan(&self, loan: Id, removal_timestamp: Timestamp) -> Result<()> {
+        self.remove::<EncryptedLoan>(loan, removal_timestamp)
+    }
+
+    fn loan(&self, loan: Id) -> Result<EncryptedLoan> {
+        self.find(loan)
+    }
+
+    fn loans(&self) -> Result<Vec<EncryptedLoan>> {
+        let mut loans: Vec<EncryptedLoan> = self.select(|_| true);
+        loans.sort_by_key(|loan| loan.start_timestamp);
+
+        Ok(loans)
+    }
+
+    fn loans_added_since(&self, base: JournalPosition) -> Result<Vec<EncryptedLoan>> {
+        Ok(self.changed_since(base, ChangeKind::Added))
+    }
+
+    fn loans_changed_since(&self, base: JournalPosition) -> Result<Vec<EncryptedLoan>> {
+        Ok(self.changed_since(base, ChangeKind::Updated))
+    }
+
+    fn loans_removed_since(&self, base: JournalPosition) -> Result<Vec<EncryptedLoan>> {
+        Ok(self.changed_since(base, ChangeKind::Removed))
+    }
+
+    fn add_holding(&self, holding: EncryptedHolding) -> Result<()> {
+        self.add(holding)
+    }
+
+    fn update_holding(&self, holding: EncryptedHolding) -> Result<()> {
+        //
+        // Change timestamp is taken from meta information,
+        // it is not updated if absent
+        //
+
+        self.update(&holding, |stored| {
+            stored.symbol = holding.symbol.clone();
+            stored.quantity = holding.quantity.clone();
+            stored.note = holding.note.clone();
+            stored.custom_fields = holding.custom_fields.clone();
+        });
+
+        Ok(())
+    }
+
+    fn remove_holding(&self, holding: Id, removal_timestamp: Timestamp) -> Result<()> {
+        self.remove::<EncryptedHolding>(holding, removal_timestamp)
+    }
+
+    fn holding(&self, holding: Id) -> Result<EncryptedHolding> {
+        self.find(holding)
+    }
+
+    fn holdings(&self) -> Result<Vec<EncryptedHolding>> {
+        let mut holdings: Vec<EncryptedHolding> = self.select(|_| true);
+        holdings.sort_by_key(|holding| holding.account_id);
+
+        Ok(holdings)
+    }
+
+    fn holdings_of(&self, account: Id) -> Result<Vec<EncryptedHolding>> {
+        Ok(self.select(|holding: &EncryptedHolding| holding.account_id == account))
+    }
+
+    fn holdings_added_since(&self, base: JournalPosition) -> Result<Vec<EncryptedHolding>> {
+        Ok(self.changed_since(base, ChangeKind::Added))
+    }
+
+    fn holdings_changed_since(&self, base: JournalPosition) -> Result<Vec<EncryptedHolding>> {
+        Ok(self.changed_since(base, ChangeKind::Updated))
+    }
+
+    fn holdings_removed_since(&self, base: JournalPosition) -> Result<Vec<EncryptedHolding>> {
+        Ok(self.changed_since(base, ChangeKind::Removed))
+    }
+
+    fn add_price(&self, price: EncryptedPricePoint) -> Result<()> {
+        self.add(price)
+    }
+
+    fn remove_price(&self, price: Id, removal_timestamp: Timestamp) -> Result<()> {
+        self.remove::<EncryptedPricePoint>(price, removal_timestamp)
+    }
+
+    fn prices(&self) -> Result<Vec<EncryptedPricePoint>> {
+        let mut prices: Vec<EncryptedPricePoint> = self.select(|_| true);
+        prices.sort_by_key(|price| Reverse(price.timestamp));
+
+        Ok(prices)
+    }
+
+    fn prices_added_since(&self, base: JournalPosition) -> Result<Vec<EncryptedPricePoint>> {
+        Ok(self.changed_since(base, ChangeKind::Added))
+    }
+
+    fn prices_changed_since(&self, base: JournalPosition) -> Result<Vec<EncryptedPricePoint>> {
+        Ok(self.changed_since(base, ChangeKind::Updated))
+    }
+
+    fn prices_removed_since(&self, base: JournalPosition) -> Result<Vec<EncryptedPricePoint>> {
+        Ok(self.changed_since(base, ChangeKind::Removed))
+    }
+
+    fn add_person(&self, person: EncryptedPerson) -> Result<()> {
+        self.add(person)
+    }
+
+    fn update_person(&self, person: EncryptedPerson) -> Result<()> {
+        //
+        // Change timestamp is taken from meta information,
+        // it is not updated if absent
+        //
+
+        self.update(&person, |stored| {
+            stored.name = person.name.clone();
+        });
+
+        Ok(())
+    }
+
+    fn remove_person(&self, person: Id, removal_timestamp: Timestamp) -> Result<()> {
+        self.remove::<EncryptedPerson>(person, removal_timestamp)
+    }
+
+    fn person(&self, person: Id) -> Result<EncryptedPerson> {
+        self.find(person)
+    }
+
+    fn persons(&self) -> Result<Vec<EncryptedPerson>> {
+        let mut persons: Vec<EncryptedPerson> = self.select(|_| true);
+        persons.sort_by_key(|person| person.meta_info.added_timestamp);
+
+        Ok(persons)
+    }
+
+    fn persons_added_since(&self, base: JournalPosition) -> Result<Vec<EncryptedPerson>> {
+        Ok(self.changed_since(base, ChangeKind::Added))
+    }
+
+    fn persons_changed_since(&self, base: JournalPosition) -> Result<Vec<EncryptedPerson>> {
+        Ok(self.changed_since(base, ChangeKind::Updated))
+    }
+
+    fn persons_removed_since(&self, base: JournalPosition) -> Result<Vec<EncryptedPerson>> {
+        Ok(self.changed_since(base, ChangeKind::Removed))
+    }
+
+    fn add_category_group(&self, group: EncryptedCategoryGroup) -> Result<()> {
+        self.add(group)
+    }
+
+    fn update_category_group(&self, group: EncryptedCategoryGroup) -> Result<()> {
+        //
+        // Change timestamp is taken from meta information,
+        // it is not updated if absent
+        //
+
+        self.update(&group, |stored| {
+            stored.name = group.name.clone();
+            stored.categories = group.categories.clone();
+        });
+
+        Ok(())
+    }
+
+    fn remove_category_group(&self, group: Id, removal_timestamp: Timestamp) -> Result<()> {
+        self.remove::<EncryptedCategoryGroup>(group, removal_timestamp)
+    }
+
+    fn category_group(&self, group: Id) -> Result<EncryptedCategoryGroup> {
+        self.find(group)
+    }
+
+    fn category_groups(&self) -> Result<Vec<EncryptedCategoryGroup>> {
+        let mut groups: Vec<EncryptedCategoryGroup> = self.select(|_| true);
+        groups.sort_by_key(|group| group.meta_info.added_timestamp);
+
+        Ok(groups)
+    }
+
+    fn category_groups_added_since(&self, base: JournalPosition) -> Result<Vec<EncryptedCategoryGroup>> {
+        Ok(self.changed_since(base, ChangeKind::Added))
+    }
+
+    fn category_groups_changed_since(&self, base: JournalPosition) -> Result<Vec<EncryptedCategoryGroup>> {
+        Ok(self.changed_since(base, ChangeKind::Updated))
+    }
+
+    fn category_groups_removed_since(&self, base: JournalPosition) -> Result<Vec<EncryptedCategoryGroup>> {
+        Ok(self.changed_since(base, ChangeKind::Removed))
+    }
+
+    fn settings(&self) -> Result<Option<EncryptedSettings>> {
+        Ok(self.state
+            .borrow()
+            .settings
+            .clone())
+    }
+
+    fn set_settings(&self, settings: EncryptedSettings) -> Result<()> {
+        let mut state = self.state.borrow_mut();
+
+        state.settings = Some(settings);
+        state.record(SETTINGS_ID, ChangeKind::Updated);
+
+        Ok(())
+    }
+
+    fn settings_changed_since(&self, base: JournalPosition) -> Result<Option<EncryptedSettings>> {
+        let state = self.state.borrow();
+
+        let changed = state.changes
+            .iter()
+            .any(|change| base < change.seq && change.item == SETTINGS_ID);
+
+        Ok(state.settings
+            .clone()
+            .filter(|_| changed))
+    }
+
+    fn journal_position(&self) -> Result<JournalPosition> {
+        Ok(self.state
+            .borrow()
+            .last_seq)
+    }
+
+    fn exported_position(&self) -> Result<JournalPosition> {
+        Ok(self.state
+            .borrow()
+            .exported_seq)
+    }
+
+    fn set_exported_position(&self, position: JournalPosition) -> Result<()> {
+        self.state
+            .borrow_mut()
+            .exported_seq = position;
+
+        Ok(())
+    }
+
+    fn set_private_to(&self, item: Id, instance: Option<Id>) -> Result<()> {
+        let mut state = self.state.borrow_mut();
+
+        match instance {
+            Some(instance) => state.visibility.insert(item, instance),
+            None => state.visibility.remove(&item)
+        };
+
+        Ok(())
+    }
+
+    fn private_items(&self, instance: Id) -> Result<Vec<Id>> {
+        Ok(self.state
+            .borrow()
+            .visibility
+            .iter()
+            .filter(|(_, private_to)| **private_to == instance)
+            .map(|(item, _)| *item)
+            .collect())
+    }
+
+    fn on_change(&self, callback: ChangeCallback) {
+        self.observers
+            .borrow_mut()
+            .push(callback);
+    }
+
+    #[cfg_attr(feature = "tracing", tracing::instrument(level = "debug", skip_all))]
+    fn clean_removed(&self) -> Result<()> {
+        let mut state = self.state.borrow_mut();
+
+        state.purge::<EncryptedCategoryGroup>();
+        state.purge::<EncryptedPerson>();
+        state.purge::<EncryptedPricePoint>();
+        state.purge::<EncryptedHolding>();
+        state.purge::<EncryptedLoan>();
+        state.purge::<EncryptedPlan>();
+        state.purge::<EncryptedTransaction>();
+        state.purge::<EncryptedCategory>();
+        state.purge::<EncryptedAccount>();
+
+        //
+        // Visibility of deleted items is not needed anymore
+        //
+
+        let mut existing = BTreeSet::new();
+        existing.extend(state.ids::<EncryptedAccount>());
+        existing.extend(state.ids::<EncryptedCategory>());
+        existing.extend(state.ids::<EncryptedTransaction>());
+        existing.extend(state.ids::<EncryptedPlan>());
+        existing.extend(state.ids::<EncryptedLoan>());
+        existing.extend(state.ids::<EncryptedHolding>());
+        existing.extend(state.ids::<EncryptedPricePoint>());
+        existing.extend(state.ids::<EncryptedPerson>());
+        existing.extend(state.ids::<EncryptedCategoryGroup>());
+
+        state.visibility
+            .retain(|item, _| existing.contains(item));
+
+        state.index
+            .retain(|(transaction, _, _)| existing.contains(transaction));
+
+        let exported_seq = state.exported_seq;
+        state.changes
+            .retain(|change| exported_seq < change.seq);
+
+        state.merges
+            .retain(|(seq, _)| exported_seq < *seq);
+
+        Ok(())
+    }
+
+    #[cfg_attr(feature = "tracing", tracing::instrument(level = "debug", skip_all))]
+    fn purge_removed(&self, removed_before: Timestamp) -> Result<usize> {
+        let mut state = self.state.borrow_mut();
+
+        //
+        // Items with changes, that are not exported yet, are kept
+        //
+
+        let exported_seq = state.exported_seq;
+        let pending: BTreeSet<Id> = state.changes
+            .iter()
+            .filter(|change| exported_seq < change.seq)
+            .map(|change| change.item)
+            .collect();
+
+        let purged = state.purge_before::<EncryptedCategoryGroup>(removed_before, &pending) +
+            state.purge_before::<EncryptedPerson>(removed_before, &pending) +
+            state.purge_before::<EncryptedPricePoint>(removed_before, &pending) +
+            state.purge_before::<EncryptedHolding>(removed_before, &pending) +
+            state.purge_before::<EncryptedLoan>(removed_before, &pending) +
+            state.purge_before::<EncryptedPlan>(removed_before, &pending) +
+            state.purge_before::<EncryptedTransaction>(removed_before, &pending) +
+            state.purge_before::<EncryptedCategory>(removed_before, &pending) +
+            state.purge_before::<EncryptedAccount>(removed_before, &pending);
+
+        let mut existing = BTreeSet::new();
+        existing.extend(state.ids::<EncryptedAccount>());
+        existing.extend(state.ids::<EncryptedCategory>());
+        existing.extend(state.ids::<EncryptedTransaction>());
+        existing.extend(state.ids::<EncryptedPlan>());
+        existing.extend(state.ids::<EncryptedLoan>());
+        existing.extend(state.ids::<EncryptedHolding>());
+        existing.extend(state.ids::<EncryptedPricePoint>());
+        existing.extend(state.ids::<EncryptedPerson>());
+        existing.extend(state.ids::<EncryptedCategoryGroup>());
+
+        state.visibility
+            .retain(|item, _| existing.contains(item));
+
+        state.index
+            .retain(|(transaction, _, _)| existing.contains(transaction));
+
+        Ok(purged)
+    }
+
+    fn counts(&self) -> Result<Vec<ItemCount>> {
+        //
+        // In-memory data sets are small, so items are just counted
+        //
+
+        let added = |meta_info: &MetaInfo| meta_info.added_timestamp;
+
+        Ok(vec![
+            self.count::<EncryptedTransaction, _>(|transaction| Some(transaction.timestamp)),
+            self.count::<EncryptedAccount, _>(|account| added(&account.meta_info)),
+            self.count::<EncryptedCategory, _>(|category| added(&category.meta_info)),
+            self.count::<EncryptedPlan, _>(|plan| added(&plan.meta_info)),
+            self.count::<EncryptedLoan, _>(|loan| added(&loan.meta_info)),
+            self.count::<EncryptedHolding, _>(|holding| added(&holding.meta_info)),
+            self.count::<EncryptedPricePoint, _>(|price| Some(price.timestamp)),
+            self.count::<EncryptedPerson, _>(|person| added(&person.meta_info)),
+            self.count::<EncryptedCategoryGroup, _>(|group| added(&group.meta_info)),
+        ])
+    }
+
+    fn snapshot(&self) -> Result<SnapshotId> {
+        //
+        // The whole state is copied, that is fine
+        // for in-memory data sets
+        //
+
+        let snapshot = self.next_snapshot.get();
+        self.next_snapshot.set(snapshot + 1);
+
+        let queued_events = self.events
+            .borrow()
+            .len();
+
+        self.snapshots
+            .borrow_mut()
+            .push((snapshot, self.state.borrow().clone(), queued_events));
+
+        Ok(snapshot)
+    }
+
+    fn rollback_to(&self, snapshot: SnapshotId) -> Result<()> {
+        let position = self.snapshot_position(snapshot)?;
+        let (_, state, queued_events) = self.snapshots
+            .borrow_mut()
+            .drain(position..)
+            .next()
+            .expect("Snapshot position MUST be valid");
+
+        self.state.replace(state);
+        self.events
+            .borrow_mut()
+            .truncate(queued_events);
+
+        Ok(())
+    }
+
+    fn drop_snapshot(&self, snapshot: SnapshotId) -> Result<()> {
+        let position = self.snapshot_position(snapshot)?;
+
+        self.snapshots
+            .borrow_mut()
+            .truncate(position);
+
+        self.dispatch_events();
+        Ok(())
+    }
+
+    fn has_snapshots(&self) -> bool {
+        !self.snapshots
+            .borrow()
+            .is_empty()
+    }
+
+    fn set_write_batching(&self, _batch_size: Option<usize>) -> Result<()> {
+        //
+        // Nothing is written to a disk, hence there
+        // is nothing to batch
+        //
+
+        Ok(())
+    }
+
+    fn flush(&self) -> Result<()> {
+        Ok(())
+    }
+
+    fn period_lock(&self) -> Result<Option<Timestamp>> {
+        Ok(self.state
+            .borrow()
+            .locked_until)
+    }
+
+    fn set_period_lock(&self, until: Option<Timestamp>) -> Result<()> {
+        self.state
+            .borrow_mut()
+            .locked_until = until;
+
+        Ok(())
+    }
+
+    fn override_period_lock(&self, overridden: bool) -> bool {
+        self.lock_overridden
+            .replace(overridden)
+    }
+
+    fn set_transaction_timestamp(&self, transaction: Id, timestamp: Timestamp) -> Result<()> {
+        let changed = self.state
+            .borrow_mut()
+            .transactions
+            .iter_mut()
+            .find(|stored| stored.id == Some(transaction))
+            .map(|stored| stored.timestamp = timestamp)
+            .is_some();
+
+        if changed {
+            self.notify(EntityKind::Transaction, ChangeKind::Updated, transaction);
+        }
+
+        Ok(())
+    }
+
+    fn canonical_dates(&self) -> Result<bool> {
+        Ok(self.state
+            .borrow()
+            .canonical_dates)
+    }
+
+    fn set_canonical_dates(&self) -> Result<()> {
+        self.state
+            .borrow_mut()
+            .canonical_dates = true;
+
+        Ok(())
+    }
+
+    fn crypto_record(&self) -> Result<Option<CryptoRecord>> {
+        Ok(self.state
+            .borrow()
+            .crypto_record
+            .clone())
+    }
+
+    fn set_crypto_record(&self, record: &CryptoRecord) -> Result<()> {
+        self.state
+            .borrow_mut()
+            .crypto_record = Some(record.clone());
+
+        Ok(())
+    }
+
+    fn key_fingerprint(&self) -> Result<Option<String>> {
+        Ok(self.state
+            .borrow()
+            .key_fingerprint
+            .clone())
+    }
+
+    fn set_key_fingerprint(&self, fingerprint: &str) -> Result<()> {
+        self.state
+            .borrow_mut()
+            .key_fingerprint = Some(fingerprint.to_owned());
+
+        Ok(())
+    }
+
+    fn recovery_record(&self) -> Result<Option<RecoveryRecord>> {
+        Ok(self.state
+            .borrow()
+            .recovery_record
+            .clone())
+    }
+
+    fn set_recovery_record(&self, record: &RecoveryRecord) -> Result<()> {
+        self.state
+            .borrow_mut()
+            .recovery_record = Some(record.clone());
+
+        Ok(())
+    }
+
+    fn remove_recovery_record(&self) -> Result<bool> {
+        Ok(self.state
+            .borrow_mut()
+            .recovery_record
+            .take()
+            .is_some())
+    }
+
+    fn deterministic_descriptions(&self) -> Result<bool> {
+        Ok(self.state
+            .borrow()
+            .deterministic_descriptions)
+    }
+
+    fn set_deterministic_descriptions(&self, enabled: bool) -> Result<()> {
+        self.state
+            .borrow_mut()
+            .deterministic_descriptions = enabled;
+
+        Ok(())
+    }
+
+    fn set_transaction_description(&self, transaction: Id, description: Vec<u8>) -> Result<()> {
+        let changed = self.state
+            .borrow_mut()
+            .transactions
+            .iter_mut()
+            .find(|stored| stored.id == Some(transaction))
+            .map(|stored| stored.description = description)
+            .is_some();
+
+        if changed {
+            self.notify(EntityKind::Transaction, ChangeKind::Updated, transaction);
+        }
+
+        Ok(())
+    }
+
+    fn set_indexed_values(&self, transaction: Id, field: IndexedField, values: &[Vec<u8>]) -> Result<()> {
+        let mut state = self.state.borrow_mut();
+
+        state.index
+            .retain(|(indexed, indexed_field, _)| *indexed != transaction || *indexed_field != field);
+
+        state.index
+            .extend(values.iter().map(|value| (transaction, field, value.clone())));
+
+        Ok(())
+    }
+
+    fn transactions_indexed(&self, field: IndexedField, value: &[u8]) -> Result<Vec<EncryptedTransaction>> {
+        let indexed: BTreeSet<Id> = self.state
+            .borrow()
+            .index
+            .iter()
+            .filter(|(_, indexed_field, indexed_value)| *indexed_field == field && indexed_value == value)
+            .map(|(transaction, _, _)| *transaction)
+            .collect();
+
+        Ok(self.transactions_where(|transaction| transaction.id.is_some_and(|id| indexed.contains(&id))))
+    }
+
+    fn indexed_values(&self, field: IndexedField) -> Result<Vec<(Id, Vec<u8>)>> {
+        let live: BTreeSet<Id> = self.transactions_where(|_| true)
+            .into_iter()
+            .filter_map(|transaction| transaction.id)
+            .collect();
+
+        Ok(self.state
+            .borrow()
+            .index
+            .iter()
+            .filter(|(transaction, indexed_field, _)| *indexed_field == field && live.contains(transaction))
+            .map(|(transaction, _, value)| (*transaction, value.clone()))
+            .collect())
+    }
+
+    fn index_version(&self) -> Result<u32> {
+        Ok(self.state
+            .borrow()
+            .index_version)
+    }
+
+    fn set_index_version(&self, version: u32) -> Result<()> {
+        self.state
+            .borrow_mut()
+            .index_version = version;
+
+        Ok(())
+    }
+
+    fn redirect_references(&self, from: Id, to: Id) -> Result<()> {
+        let redirect = |reference: &mut Id| {
+            if *reference == from {
+                *reference = to;
+            }
+        };
+
+        let mut state = self.state.borrow_mut();
+
+        for transaction in state.transactions.iter_mut() {
+            redirect(&mut transaction.account_id);
+            redirect(&mut transaction.category_id);
+        }
+
+        for plan in state.plans.iter_mut() {
+            redirect(&mut plan.category_id);
+        }
+
+        for loan in state.loans.iter_mut() {
+            redirect(&mut loan.account_id);
+            redirect(&mut loan.principal_category_id);
+            redirect(&mut loan.interest_category_id);
+        }
+
+        for holding in state.holdings.iter_mut() {
+            redirect(&mut holding.account_id);
+        }
+
+        Ok(())
+    }
+
+    fn add_merge(&self, merge: Merge) -> Result<()> {
+        //
+        // Merge is positioned at the latest change in the journal,
+        // so it is exported along with removal of the merged item
+        //
+
+        let mut state = self.state.borrow_mut();
+        let seq = state.last_seq;
+
+        state.merges.push((seq, merge));
+
+        Ok(())
+    }
+
+    fn merges_since(&self, base: JournalPosition) -> Result<Vec<Merge>> {
+        Ok(self.state
+            .borrow()
+            .merges
+            .iter()
+            .filter(|(seq, _)| base < *seq)
+            .map(|(_, merge)| *merge)
+            .collect())
+    }
+}
+
+
+impl MemoryStorage {
+    fn snapshot_position(&self, snapshot: SnapshotId) -> Result<usize> {
+        self.snapshots
+            .borrow()
+            .iter()
+            .position(|(active, _, _)| *active == snapshot)
+            .ok_or_else(|| Error::from_message_with_extra(UNKNOWN_SNAPSHOT, snapshot.to_string()).with_kind(ErrorKind::NotFound))
+    }
+
+    fn add<T: StoredItem>(&self, item: T) -> Result<()> {
+        let id = self.state
+            .borrow_mut()
+            .insert(item)?;
+
+        self.notify(T::ENTITY, ChangeKind::Added, id);
+
+        Ok(())
+    }
+
+    fn update<T, F>(&self, item: &T, apply: F)
+    where
+        T: StoredItem,
+        F: FnOnce(&mut T)
+    {
+        let id = match item.id() {
+            Some(id) => id,
+            None => return
+        };
+
+        self.state
+            .borrow_mut()
+            .update(id, item.meta_info().changed_timestamp, apply);
+
+        self.notify(T::ENTITY, ChangeKind::Updated, id);
+    }
+
+    fn remove<T: StoredItem>(&self, id: Id, removal_timestamp: Timestamp) -> Result<()> {
+        self.state
+            .borrow_mut()
+            .mark_removed::<T>(id, removal_timestamp);
+
+        self.notify(T::ENTITY, ChangeKind::Removed, id);
+
+        Ok(())
+    }
+
+    fn ensure_unlocked(&self, timestamp: Timestamp) -> Result<()> {
+        let locked_until = self.state
+            .borrow()
+            .locked_until;
+
+        match locked_until {
+            Some(until) if timestamp < until && !self.lock_overridden.get() => 
+                Err(Error::from_message_with_extra(PERIOD_IS_LOCKED, format!("Locked until: {}", until))
+                    .with_kind(ErrorKind::Locked)),
+            _ => Ok(())
+        }
+    }
+
+    fn find<T: StoredItem>(&self, id: Id) -> Result<T> {
+        self.select(|item: &T| item.id() == Some(id))
+            .pop()
+            .ok_or_else(|| Error::from_message_with_extra(ITEM_NOT_FOUND,
+                format!("Table: {}", T::TABLE)).with_kind(ErrorKind::NotFound))
+    }
+
+    fn select<T, P>(&self, predicate: P) -> Vec<T>
+    where
+        T: StoredItem,
+        P: Fn(&T) -> bool
+    {
+        T::table(&self.state.borrow())
+            .iter()
+            .filter(|item| item.meta_info().removed_timestamp.is_none())
+            .filter(|item| predicate(item))
+            .cloned()
+            .collect()
+    }
+
+    fn count<T, D>(&self, date: D) -> ItemCount
+    where
+        T: StoredItem,
+        D: Fn(&T) -> Option<Timestamp>
+    {
+        let items: Vec<T> = self.select(|_| true);
+        let dates: Vec<Timestamp> = items
+            .iter()
+            .filter_map(date)
+            .collect();
+
+        ItemCount {
+            entity: T::ENTITY,
+            count: items.len(),
+            first: dates.iter().min().copied(),
+            last: dates.iter().max().copied()
+        }
+    }
+
+    fn transactions_where<P>(&self, predicate: P) -> Vec<EncryptedTransaction>
+    where
+        P: Fn(&EncryptedTransaction) -> bool
+    {
+        let mut transactions = self.select(predicate);
+        transactions.sort_by_key(|transaction| Reverse(transaction.timestamp));
+
+        transactions
+    }
+
+    fn changed_since<T: StoredItem>(&self, base: JournalPosition, change: ChangeKind) -> Vec<T> {
+        let state = self.state.borrow();
+
+        let changed: BTreeSet<Id> = state.changes
+            .iter()
+            .filter(|entry| base < entry.seq && entry.change == change)
+            .map(|entry| entry.item)
+            .collect();
+
+        let mut items: Vec<T> = T::table(&state)
+            .iter()
+            .filter(|item| item.id().is_some_and(|id| changed.contains(&id)))
+            .cloned()
+            .collect();
+
+        //
+        // The latest changes go first
+        //
+
+        let timestamp = |item: &T| match change {
+            ChangeKind::Added => item.meta_info().added_timestamp,
+            ChangeKind::Updated => item.meta_info().changed_timestamp,
+            ChangeKind::Removed => item.meta_info().removed_timestamp
+        };
+
+        items.sort_by_key(|item| Reverse(timestamp(item)));
+        items
+    }
+
+    fn notify(&self, entity: EntityKind, change: ChangeKind, id: Id) {
+        trace_debug!(?entity, ?change, id = %uuid::Uuid::from_bytes(id), "storage item changed");
+
+        //
+        // Changes made after a snapshot may be rolled back,
+        // hence observers are notified once it is dropped
+        //
+
+        self.events
+            .borrow_mut()
+            .push(StorageEvent { entity, change, id });
+
+        self.dispatch_events();
+    }
+
+    fn dispatch_events(&self) {
+        if self.has_snapshots() {
+            return;
+        }
+
+        let events = self.events.take();
+
+        //
+        // Observers are invoked without the borrow held, since
+        // they may register other observers
+        //
+
+        let observers = self.observers
+            .borrow()
+            .clone();
+
+        for event in &events {
+            for observer in &observers {
+                observer(event);
+            }
+        }
+    }
+
+    fn ensure_consistency<T, P>(&self, foreign_key: &str, references: P) -> Result<()>
+    where
+        T: StoredItem,
+        P: Fn(&T) -> bool
+    {
+        if !self.select(references).is_empty() {
+            return Err(Error::from_message_with_extra(CONSISTENCY_VIOLATION,
+                format!("Table: {}, foreign key: {}", T::TABLE, foreign_key)).with_kind(ErrorKind::ConsistencyViolation));
+        }
+
+        Ok(())
+    }
+
+    fn is_predefined_category(category: Id) -> bool {
+        let predefined = [
+            Self::TRANSFER_INCOME_ID,
+            Self::TRANSFER_OUTCOME_ID,
+            Self::ADJUSTMENT_INCOME_ID,
+            Self::ADJUSTMENT_OUTCOME_ID
+        ];
+
+        predefined.contains(&category)
+    }
+}
+
+
+impl MemoryState {
+    fn record(&mut self, item: Id, change: ChangeKind) {
+        self.last_seq += 1;
+        self.changes.push(Change { seq: self.last_seq, item, change });
+    }
+
+    fn insert<T: StoredItem>(&mut self, mut item: T) -> Result<Id> {
+        let id = item.id()
+            .unwrap_or_else(rand::random);
+
+        if T::table(self).iter().any(|stored| stored.id() == Some(id)) {
+            return Err(Error::from_message_with_extra(ITEM_ALREADY_EXISTS,
+                format!("Table: {}", T::TABLE)).with_kind(ErrorKind::AlreadyExists));
+        }
+
+        //
+        // Only creation timestamp is written on addition
+        //
+
+        item.set_id(id);
+        item.meta_info_mut().changed_timestamp = None;
+        item.meta_info_mut().removed_timestamp = None;
+
+        T::table_mut(self).push(item);
+        self.record(id, ChangeKind::Added);
+
+        Ok(id)
+    }
+
+    fn update<T, F>(&mut self, id: Id, changed_timestamp: Option<Timestamp>, apply: F)
+    where
+        T: StoredItem,
+        F: FnOnce(&mut T)
+    {
+        let stored = T::table_mut(self)
+            .iter_mut()
+            .find(|stored| stored.id() == Some(id) && stored.meta_info().removed_timestamp.is_none());
+
+        let stored = match stored {
+            Some(stored) => stored,
+            None => return
+        };
+
+        apply(stored);
+
+        //
+        // Change is journaled only if change timestamp is updated
+        //
+
+        let meta_info = stored.meta_info_mut();
+        let changed = changed_timestamp.is_some() && changed_timestamp != meta_info.changed_timestamp;
+
+        meta_info.changed_timestamp = changed_timestamp.or(meta_info.changed_timestamp);
+
+        if changed {
+            self.record(id, ChangeKind::Updated);
+        }
+    }
+
+    fn mark_removed<T: StoredItem>(&mut self, id: Id, removal_timestamp: Timestamp) {
+        let stored = T::table_mut(self)
+            .iter_mut()
+            .find(|stored| stored.id() == Some(id));
+
+        let stored = match stored {
+            Some(stored) => stored,
+            None => return
+        };
+
+        let was_present = stored.meta_info().removed_timestamp.is_none();
+        stored.meta_info_mut().removed_timestamp = Some(removal_timestamp);
+
+        if was_present {
+            self.record(id, ChangeKind::Removed);
+        }
+    }
+
+    fn purge<T: StoredItem>(&mut self) {
+        T::table_mut(self)
+            .retain(|item| item.meta_info().removed_timestamp.is_none());
+    }
+
+    fn purge_before<T: StoredItem>(&mut self, removed_before: Timestamp, pending: &BTreeSet<Id>) -> usize {
+        let table = T::table_mut(self);
+        let count = table.len();
+
+        table.retain(|item| {
+            let expired = item.meta_info().removed_timestamp
+                .is_some_and(|removed| removed < removed_before);
+
+            !expired || item.id().is_some_and(|id| pending.contains(&id))
+        });
+
+        count - table.len()
+    }
+
+    fn ids<T: StoredItem>(&self) -> Vec<Id> {
+        T::table(self)
+            .iter()
+            .filter_map(StoredItem::id)
+            .collect()
+    }
+}
+
+
+#[cfg(test)]
+mod tests {
+    use std::sync::{Arc, atomic::{AtomicUsize, Ordering}};
+
+    use crate::datetime::Clock;
+    use super::super::{DataStorage, EncryptedAccount, MetaInfo, StorageEvent};
+    use super::MemoryStorage;
+
+    fn test_account(id: u8) -> EncryptedAccount {
+        EncryptedAccount {
+            id: Some([id; 16]),
+            name: Vec::new(),
+            balance: Vec::new(),
+            initial_balance: Vec::new(),
+            note: None,
+            custom_fields: None,
+            meta_info: MetaInfo::new(Some(Clock::now()), None, None)
+        }
+    }
+
+    #[test]
+    fn rolled_back_changes_are_not_reported() {
+        let storage = MemoryStorage::new();
+        let events = Arc::new(AtomicUsize::new(0));
+
+        let counter = events.clone();
+        storage.on_change(Arc::new(move |_: &StorageEvent| { counter.fetch_add(1, Ordering::Relaxed); }));
+
+        let snapshot = storage.snapshot().unwrap();
+        storage.add_account(test_account(1)).unwrap();
+        storage.rollback_to(snapshot).unwrap();
+        assert_eq!(events.load(Ordering::Relaxed), 0);
+
+        let snapshot = storage.snapshot().unwrap();
+        storage.add_account(test_account(2)).unwrap();
+        assert_eq!(events.load(Ordering::Relaxed), 0);
+
+        storage.drop_snapshot(snapshot).unwrap();
+        assert_eq!(events.load(Ordering::Relaxed), 1);
+    }
+}
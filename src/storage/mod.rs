@@ -1,10 +1,27 @@
 mod data;
 mod storage;
+mod memory_storage;
+mod events;
+mod validation;
+
+#[cfg(feature = "native")]
 mod db_storage;
+#[cfg(feature = "native")]
+mod reader_pool;
 
 pub use self::storage::DataStorage;
-pub use self::db_storage::DbStorage;
+pub use self::memory_storage::MemoryStorage;
+
 pub use self::data::*;
+pub use self::events::*;
+pub use self::validation::{Validate, ValidationError, MAX_AMOUNT};
+
+#[cfg(feature = "native")]
+pub use self::db_storage::DbStorage;
+
+
+/// Identifier of shared settings in changes journal ("settings" in ASCII).
+const SETTINGS_ID: Id = *b"settings\0\0\0\0\0\0\0\0";
 
 
 /// Error message for DB consistency violation.
@@ -12,3 +29,15 @@ const CONSISTENCY_VIOLATION: &str = "Cannot remove item from DB because of anoth
 
 /// Error message for removing of predefined item prohibition.
 const CANNOT_DELETE_PREDEFINED: &str = "Cannot remove predefined item";
+
+/// Error message for missing item.
+const ITEM_NOT_FOUND: &str = "Item is not found";
+
+/// Error message for an attempt to add an item with existing identifier.
+const ITEM_ALREADY_EXISTS: &str = "Item with the same identifier already exists";
+
+/// Error message for unknown or already dropped snapshot.
+const UNKNOWN_SNAPSHOT: &str = "Snapshot does not exist";
+
+/// Error message for modification of a transaction in a locked period.
+const PERIOD_IS_LOCKED: &str = "Transaction belongs to a locked period";
@@ -0,0 +1,76 @@
+use parking_lot::Mutex;
+
+use crate::error::{Result, Context};
+
+
+/// Pool of read-only connections to a database.
+///
+/// In WAL mode readers do not block each other and a writer, hence
+/// queries from different threads run concurrently, each on its own
+/// connection. The pool is locked only to take or return a connection.
+pub(crate) struct ReaderPool {
+    /// Path to a database
+    path: std::path::PathBuf,
+
+    /// Connections, that are not used at the moment
+    idle: Mutex<Vec<rusqlite::Connection>>,
+
+    /// Maximal number of idle connections, extra ones are closed
+    capacity: usize,
+}
+
+
+impl ReaderPool {
+    /// Creates an empty pool. Connections are opened on demand.
+    ///
+    /// * `path` - path to a database
+    /// * `capacity` - maximal number of idle connections
+    pub(crate) fn new(path: std::path::PathBuf, capacity: usize) -> Self {
+        ReaderPool {
+            path,
+            idle: Mutex::new(Vec::new()),
+            capacity
+        }
+    }
+
+    /// Runs a function with a read-only connection.
+    ///
+    /// * `f` - function to run
+    pub(crate) fn with<R, F>(&self, f: F) -> Result<R>
+    where
+        F: FnOnce(&rusqlite::Connection) -> Result<R>
+    {
+        let connection = match self.take() {
+            Some(connection) => connection,
+            None => self.open()?
+        };
+
+        let result = f(&connection);
+        self.give_back(connection);
+
+        result
+    }
+
+    fn take(&self) -> Option<rusqlite::Connection> {
+        self.idle
+            .lock()
+            .pop()
+    }
+
+    fn give_back(&self, connection: rusqlite::Connection) {
+        let mut idle = self.idle.lock();
+
+        if idle.len() < self.capacity {
+            idle.push(connection);
+        }
+    }
+
+    fn open(&self) -> Result<rusqlite::Connection> {
+        let flags = rusqlite::OpenFlags::SQLITE_OPEN_READ_ONLY |
+            rusqlite::OpenFlags::SQLITE_OPEN_NO_MUTEX |
+            rusqlite::OpenFlags::SQLITE_OPEN_URI;
+
+        rusqlite::Connection::open_with_flags(&self.path, flags)
+            .with_context(|| format!("opening database {} for reading", self.path.display()))
+    }
+}
@@ -1,6 +1,8 @@
 use crate::error::Result;
 use crate::datetime::Timestamp;
-use super::data::{EncryptedTransaction, EncryptedCategory, EncryptedAccount, EncryptedPlan, Id, CategoryType};
+use super::data::{EncryptedTransaction, EncryptedTransactionSummary, EncryptedCategory, EncryptedAccount, EncryptedPlan, EncryptedLoan, Id, CategoryType, JournalPosition};
+use super::data::{EncryptedHolding, EncryptedPricePoint, EncryptedPerson, EncryptedCategoryGroup, EncryptedSettings, SnapshotId, Merge, CryptoRecord, RecoveryRecord, ItemCount, IndexedField};
+use super::events::ChangeCallback;
 
 
 /// Storage trait, that provides protected data reading and writing.
@@ -26,11 +28,23 @@ pub trait DataStorage {
     ///Predefined outcome transfer category identifier.
     const TRANSFER_OUTCOME_ID: Id;
 
+    /// Predefined income balance adjustment category identifier.
+    const ADJUSTMENT_INCOME_ID: Id;
+
+    /// Predefined outcome balance adjustment category identifier.
+    const ADJUSTMENT_OUTCOME_ID: Id;
+
     /// Add a new transaction.
     /// 
     /// * `transaction` - protected transaction data
     fn add_transaction(&self, transaction: EncryptedTransaction) -> Result<()>;
 
+    /// Update transaction. Both its stored and new timestamps must be
+    /// outside of a locked period.
+    /// 
+    /// * `transaction` - transaction to update (with updated data)
+    fn update_transaction(&self, transaction: EncryptedTransaction) -> Result<()>;
+
     /// Remove transaction.
     /// 
     /// * `transaction` - identifier of a transaction to remove
@@ -63,6 +77,17 @@ pub trait DataStorage {
     /// * `end_timestamp` - point in time to end before
     fn transactions_between(&self, start_timestamp: Timestamp, end_timestamp: Timestamp) -> Result<Vec<EncryptedTransaction>>;
 
+    /// Return summaries of all transactions between given time points 
+    /// (including start of the interval and excluding the end) sorted by 
+    /// timestamp in descending order.
+    /// 
+    /// Heavy fields are not fetched at all, hence it is much cheaper
+    /// than [`DataStorage::transactions_between`].
+    /// 
+    /// * `start_timestamp` - point in time to start from
+    /// * `end_timestamp` - point in time to end before
+    fn transaction_summaries_between(&self, start_timestamp: Timestamp, end_timestamp: Timestamp) -> Result<Vec<EncryptedTransactionSummary>>;
+
     /// Return all transactions bound with a given account sorted by timestamp 
     /// in descending order.
     /// 
@@ -119,20 +144,29 @@ pub trait DataStorage {
     /// * `end_timestamp` - point in time to end before
     fn transactions_with_between(&self, category: Id, start_timestamp: Timestamp, end_timestamp: Timestamp) -> Result<Vec<EncryptedTransaction>>;
 
-    /// Returns all transactions added to storage since a given time point.
+    /// Return all transactions with exactly given encrypted description
+    /// sorted by timestamp in descending order.
+    /// 
+    /// Makes sense only for deterministically encrypted descriptions
+    /// (see [`DataStorage::deterministic_descriptions`]).
+    /// 
+    /// * `description` - encrypted description
+    fn transactions_described(&self, description: &[u8]) -> Result<Vec<EncryptedTransaction>>;
+
+    /// Returns all transactions added to storage since a given journal position.
     /// 
-    /// * `base` - point in time. All transactions added strictly after this time point are returned.
-    fn transactions_added_since(&self, base: Timestamp) -> Result<Vec<EncryptedTransaction>>;
+    /// * `base` - journal position. All transactions added strictly after this position are returned.
+    fn transactions_added_since(&self, base: JournalPosition) -> Result<Vec<EncryptedTransaction>>;
 
-    /// Returns all transactions changed in storage since a given time point.
+    /// Returns all transactions changed in storage since a given journal position.
     /// 
-    /// * `base` - point in time. All transactions changed strictly after this time point are returned.
-    fn transactions_changed_since(&self, base: Timestamp) -> Result<Vec<EncryptedTransaction>>;
+    /// * `base` - journal position. All transactions changed strictly after this position are returned.
+    fn transactions_changed_since(&self, base: JournalPosition) -> Result<Vec<EncryptedTransaction>>;
 
-    /// Returns all transactions removed from storage since a given time point.
+    /// Returns all transactions removed from storage since a given journal position.
     /// 
-    /// * `base` - point in time. All transactions removed strictly after this time point are returned.
-    fn transactions_removed_since(&self, base: Timestamp) -> Result<Vec<EncryptedTransaction>>;
+    /// * `base` - journal position. All transactions removed strictly after this position are returned.
+    fn transactions_removed_since(&self, base: JournalPosition) -> Result<Vec<EncryptedTransaction>>;
 
     /// Add a new account.
     /// 
@@ -160,20 +194,20 @@ pub trait DataStorage {
     /// Return all accounts.
     fn accounts(&self) -> Result<Vec<EncryptedAccount>>;
 
-    /// Returns all accounts added to storage since a given time point.
+    /// Returns all accounts added to storage since a given journal position.
     /// 
-    /// * `base` - point in time. All accounts added strictly after this time point are returned.
-    fn accounts_added_since(&self, base: Timestamp) -> Result<Vec<EncryptedAccount>>;
+    /// * `base` - journal position. All accounts added strictly after this position are returned.
+    fn accounts_added_since(&self, base: JournalPosition) -> Result<Vec<EncryptedAccount>>;
 
-    /// Returns all accounts changed in storage since a given time point.
+    /// Returns all accounts changed in storage since a given journal position.
     /// 
-    /// * `base` - point in time. All accounts changed strictly after this time point are returned.
-    fn accounts_changed_since(&self, base: Timestamp) -> Result<Vec<EncryptedAccount>>;
+    /// * `base` - journal position. All accounts changed strictly after this position are returned.
+    fn accounts_changed_since(&self, base: JournalPosition) -> Result<Vec<EncryptedAccount>>;
 
-    /// Returns all accounts removed from storage since a given time point.
+    /// Returns all accounts removed from storage since a given journal position.
     /// 
-    /// * `base` - point in time. All accounts removed strictly after this time point are returned.
-    fn accounts_removed_since(&self, base: Timestamp) -> Result<Vec<EncryptedAccount>>;
+    /// * `base` - journal position. All accounts removed strictly after this position are returned.
+    fn accounts_removed_since(&self, base: JournalPosition) -> Result<Vec<EncryptedAccount>>;
 
     /// Add a new category.
     /// 
@@ -203,20 +237,20 @@ pub trait DataStorage {
     /// * `category_type` - type to return categories of
     fn categories_of(&self, category_type: CategoryType) -> Result<Vec<EncryptedCategory>>;
 
-    /// Returns all categories added to storage since a given time point.
+    /// Returns all categories added to storage since a given journal position.
     /// 
-    /// * `base` - point in time. All categories added strictly after this time point are returned.
-    fn categories_added_since(&self, base: Timestamp) -> Result<Vec<EncryptedCategory>>;
+    /// * `base` - journal position. All categories added strictly after this position are returned.
+    fn categories_added_since(&self, base: JournalPosition) -> Result<Vec<EncryptedCategory>>;
 
-    /// Returns all categories changed in storage since a given time point.
+    /// Returns all categories changed in storage since a given journal position.
     /// 
-    /// * `base` - point in time. All categories changed strictly after this time point are returned.
-    fn categories_changed_since(&self, base: Timestamp) -> Result<Vec<EncryptedCategory>>;
+    /// * `base` - journal position. All categories changed strictly after this position are returned.
+    fn categories_changed_since(&self, base: JournalPosition) -> Result<Vec<EncryptedCategory>>;
 
-    /// Returns all categories removed from storage since a given time point.
+    /// Returns all categories removed from storage since a given journal position.
     /// 
-    /// * `base` - point in time. All categories removed strictly after this time point are returned.
-    fn categories_removed_since(&self, base: Timestamp) -> Result<Vec<EncryptedCategory>>;
+    /// * `base` - journal position. All categories removed strictly after this position are returned.
+    fn categories_removed_since(&self, base: JournalPosition) -> Result<Vec<EncryptedCategory>>;
 
     /// Add a new plan.
     /// 
@@ -242,20 +276,256 @@ pub trait DataStorage {
     /// * `category` - category to return plans for
     fn plans_for(&self, category: Id) -> Result<Vec<EncryptedPlan>>;
 
-    /// Returns all plans added to storage since a given time point.
+    /// Returns all plans added to storage since a given journal position.
+    /// 
+    /// * `base` - journal position. All plans added strictly after this position are returned.
+    fn plans_added_since(&self, base: JournalPosition) -> Result<Vec<EncryptedPlan>>;
+
+    /// Returns all plans changed in storage since a given journal position.
+    /// 
+    /// * `base` - journal position. All plans changed strictly after this position are returned.
+    fn plans_changed_since(&self, base: JournalPosition) -> Result<Vec<EncryptedPlan>>;
+
+    /// Returns all plans removed from storage since a given journal position.
+    /// 
+    /// * `base` - journal position. All plans removed strictly after this position are returned.
+    fn plans_removed_since(&self, base: JournalPosition) -> Result<Vec<EncryptedPlan>>;
+
+    /// Add a new loan.
+    /// 
+    /// * `loan` - protected loan data
+    fn add_loan(&self, loan: EncryptedLoan) -> Result<()>;
+
+    /// Remove loan.
+    /// 
+    /// * `loan` - identifier of loan to remove
+    /// * `removal_timestamp` - this value will be written as removal timestamp
+    fn remove_loan(&self, loan: Id, removal_timestamp: Timestamp) -> Result<()>;
+
+    /// Return loan with a given identifier.
+    /// 
+    /// * `loan` - identifier to return record for
+    fn loan(&self, loan: Id) -> Result<EncryptedLoan>;
+
+    /// Return all loans sorted by start timestamp.
+    fn loans(&self) -> Result<Vec<EncryptedLoan>>;
+
+    /// Returns all loans added to storage since a given journal position.
+    /// 
+    /// * `base` - journal position. All loans added strictly after this position are returned.
+    fn loans_added_since(&self, base: JournalPosition) -> Result<Vec<EncryptedLoan>>;
+
+    /// Returns all loans changed in storage since a given journal position.
+    /// 
+    /// * `base` - journal position. All loans changed strictly after this position are returned.
+    fn loans_changed_since(&self, base: JournalPosition) -> Result<Vec<EncryptedLoan>>;
+
+    /// Returns all loans removed from storage since a given journal position.
+    /// 
+    /// * `base` - journal position. All loans removed strictly after this position are returned.
+    fn loans_removed_since(&self, base: JournalPosition) -> Result<Vec<EncryptedLoan>>;
+
+    /// Add a new investment holding.
+    /// 
+    /// * `holding` - protected holding data
+    fn add_holding(&self, holding: EncryptedHolding) -> Result<()>;
+
+    /// Update investment holding.
+    /// 
+    /// * `holding` - holding to update (with updated data)
+    fn update_holding(&self, holding: EncryptedHolding) -> Result<()>;
+
+    /// Remove investment holding.
+    /// 
+    /// * `holding` - identifier of holding to remove
+    /// * `removal_timestamp` - this value will be written as removal timestamp
+    fn remove_holding(&self, holding: Id, removal_timestamp: Timestamp) -> Result<()>;
+
+    /// Return investment holding with a given identifier.
+    /// 
+    /// * `holding` - identifier to return record for
+    fn holding(&self, holding: Id) -> Result<EncryptedHolding>;
+
+    /// Return all investment holdings sorted by account.
+    fn holdings(&self) -> Result<Vec<EncryptedHolding>>;
+
+    /// Return all investment holdings of a given account.
     /// 
-    /// * `base` - point in time. All plans added strictly after this time point are returned.
-    fn plans_added_since(&self, base: Timestamp) -> Result<Vec<EncryptedPlan>>;
+    /// * `account` - account identifier to return holdings for
+    fn holdings_of(&self, account: Id) -> Result<Vec<EncryptedHolding>>;
 
-    /// Returns all plans changed in storage since a given time point.
+    /// Returns all holdings added to storage since a given journal position.
     /// 
-    /// * `base` - point in time. All plans changed strictly after this time point are returned.
-    fn plans_changed_since(&self, base: Timestamp) -> Result<Vec<EncryptedPlan>>;
+    /// * `base` - journal position. All holdings added strictly after this position are returned.
+    fn holdings_added_since(&self, base: JournalPosition) -> Result<Vec<EncryptedHolding>>;
 
-    /// Returns all plans removed from storage since a given time point.
+    /// Returns all holdings changed in storage since a given journal position.
     /// 
-    /// * `base` - point in time. All plans removed strictly after this time point are returned.
-    fn plans_removed_since(&self, base: Timestamp) -> Result<Vec<EncryptedPlan>>;
+    /// * `base` - journal position. All holdings changed strictly after this position are returned.
+    fn holdings_changed_since(&self, base: JournalPosition) -> Result<Vec<EncryptedHolding>>;
+
+    /// Returns all holdings removed from storage since a given journal position.
+    /// 
+    /// * `base` - journal position. All holdings removed strictly after this position are returned.
+    fn holdings_removed_since(&self, base: JournalPosition) -> Result<Vec<EncryptedHolding>>;
+
+    /// Add a new security price.
+    /// 
+    /// * `price` - protected price data
+    fn add_price(&self, price: EncryptedPricePoint) -> Result<()>;
+
+    /// Remove security price.
+    /// 
+    /// * `price` - identifier of price to remove
+    /// * `removal_timestamp` - this value will be written as removal timestamp
+    fn remove_price(&self, price: Id, removal_timestamp: Timestamp) -> Result<()>;
+
+    /// Return all security prices sorted by timestamp in descending order.
+    fn prices(&self) -> Result<Vec<EncryptedPricePoint>>;
+
+    /// Returns all prices added to storage since a given journal position.
+    /// 
+    /// * `base` - journal position. All prices added strictly after this position are returned.
+    fn prices_added_since(&self, base: JournalPosition) -> Result<Vec<EncryptedPricePoint>>;
+
+    /// Returns all prices changed in storage since a given journal position.
+    /// 
+    /// * `base` - journal position. All prices changed strictly after this position are returned.
+    fn prices_changed_since(&self, base: JournalPosition) -> Result<Vec<EncryptedPricePoint>>;
+
+    /// Returns all prices removed from storage since a given journal position.
+    /// 
+    /// * `base` - journal position. All prices removed strictly after this position are returned.
+    fn prices_removed_since(&self, base: JournalPosition) -> Result<Vec<EncryptedPricePoint>>;
+
+    /// Add a new person.
+    /// 
+    /// * `person` - protected person data
+    fn add_person(&self, person: EncryptedPerson) -> Result<()>;
+
+    /// Update person.
+    /// 
+    /// * `person` - person to update (with updated data)
+    fn update_person(&self, person: EncryptedPerson) -> Result<()>;
+
+    /// Remove person. Transactions of the person are not changed.
+    /// 
+    /// * `person` - identifier of person to remove
+    /// * `removal_timestamp` - this value will be written as removal timestamp
+    fn remove_person(&self, person: Id, removal_timestamp: Timestamp) -> Result<()>;
+
+    /// Return person with a given identifier.
+    /// 
+    /// * `person` - identifier to return record for
+    fn person(&self, person: Id) -> Result<EncryptedPerson>;
+
+    /// Return all persons sorted by creation timestamp.
+    fn persons(&self) -> Result<Vec<EncryptedPerson>>;
+
+    /// Returns all persons added to storage since a given journal position.
+    /// 
+    /// * `base` - journal position. All persons added strictly after this position are returned.
+    fn persons_added_since(&self, base: JournalPosition) -> Result<Vec<EncryptedPerson>>;
+
+    /// Returns all persons changed in storage since a given journal position.
+    /// 
+    /// * `base` - journal position. All persons changed strictly after this position are returned.
+    fn persons_changed_since(&self, base: JournalPosition) -> Result<Vec<EncryptedPerson>>;
+
+    /// Returns all persons removed from storage since a given journal position.
+    /// 
+    /// * `base` - journal position. All persons removed strictly after this position are returned.
+    fn persons_removed_since(&self, base: JournalPosition) -> Result<Vec<EncryptedPerson>>;
+
+    /// Add a new group of categories.
+    /// 
+    /// * `group` - protected group data
+    fn add_category_group(&self, group: EncryptedCategoryGroup) -> Result<()>;
+
+    /// Update group of categories.
+    /// 
+    /// * `group` - group to update (with updated data)
+    fn update_category_group(&self, group: EncryptedCategoryGroup) -> Result<()>;
+
+    /// Remove group of categories. Categories of the group are not changed.
+    /// 
+    /// * `group` - identifier of group to remove
+    /// * `removal_timestamp` - this value will be written as removal timestamp
+    fn remove_category_group(&self, group: Id, removal_timestamp: Timestamp) -> Result<()>;
+
+    /// Return group of categories with a given identifier.
+    /// 
+    /// * `group` - identifier to return record for
+    fn category_group(&self, group: Id) -> Result<EncryptedCategoryGroup>;
+
+    /// Return all groups of categories sorted by creation timestamp.
+    fn category_groups(&self) -> Result<Vec<EncryptedCategoryGroup>>;
+
+    /// Returns all groups of categories added to storage since a given journal position.
+    /// 
+    /// * `base` - journal position. All groups added strictly after this position are returned.
+    fn category_groups_added_since(&self, base: JournalPosition) -> Result<Vec<EncryptedCategoryGroup>>;
+
+    /// Returns all groups of categories changed in storage since a given journal position.
+    /// 
+    /// * `base` - journal position. All groups changed strictly after this position are returned.
+    fn category_groups_changed_since(&self, base: JournalPosition) -> Result<Vec<EncryptedCategoryGroup>>;
+
+    /// Returns all groups of categories removed from storage since a given journal position.
+    /// 
+    /// * `base` - journal position. All groups removed strictly after this position are returned.
+    fn category_groups_removed_since(&self, base: JournalPosition) -> Result<Vec<EncryptedCategoryGroup>>;
+
+    /// Return settings shared between instances if they were set.
+    fn settings(&self) -> Result<Option<EncryptedSettings>>;
+
+    /// Set settings shared between instances.
+    /// 
+    /// Change timestamp is taken from meta info and MUST be present.
+    /// 
+    /// * `settings` - protected settings
+    fn set_settings(&self, settings: EncryptedSettings) -> Result<()>;
+
+    /// Returns shared settings if they were changed since a given journal position.
+    /// 
+    /// * `base` - journal position
+    fn settings_changed_since(&self, base: JournalPosition) -> Result<Option<EncryptedSettings>>;
+
+    /// Return position of the latest change in the journal.
+    fn journal_position(&self) -> Result<JournalPosition>;
+
+    /// Return journal position, that all changes up to were exported 
+    /// to other instances.
+    fn exported_position(&self) -> Result<JournalPosition>;
+
+    /// Set journal position, that all changes up to were exported 
+    /// to other instances.
+    /// 
+    /// * `position` - journal position
+    fn set_exported_position(&self, position: JournalPosition) -> Result<()>;
+
+    /// Mark an item as private to an instance or make it shared again.
+    /// 
+    /// Visibility is a local property and is never synchronized.
+    /// 
+    /// * `item` - identifier of an item of any kind
+    /// * `instance` - instance the item is private to or [`None`] to make it shared
+    fn set_private_to(&self, item: Id, instance: Option<Id>) -> Result<()>;
+
+    /// Return identifiers of all items private to a given instance.
+    /// 
+    /// * `instance` - instance identifier
+    fn private_items(&self, instance: Id) -> Result<Vec<Id>>;
+
+    /// Register a callback, that is invoked after each successful addition,
+    /// update or removal of an item.
+    /// 
+    /// Callback is invoked once a change is committed. Changes made
+    /// after a snapshot are reported when it is dropped and never
+    /// reported if it is rolled back.
+    /// 
+    /// * `callback` - callback receiving an event with changed item description
+    fn on_change(&self, callback: ChangeCallback);
 
     /// Delete permanently all previously removed items.
     /// 
@@ -263,4 +533,200 @@ pub trait DataStorage {
     /// just mark items as removed. This function therefore permanently
     /// deletes such marked items.
     fn clean_removed(&self) -> Result<()>;
+
+    /// Delete permanently items removed before a given time point.
+    /// 
+    /// Items, which removal is not exported to other instances yet, are
+    /// kept, so removals are always synchronized before items are purged.
+    /// Returns number of deleted items.
+    /// 
+    /// * `removed_before` - items removed strictly before this point are deleted
+    fn purge_removed(&self, removed_before: Timestamp) -> Result<usize>;
+
+    /// Return numbers of items and ranges of their dates for all entities.
+    /// 
+    /// Implementations SHOULD NOT scan all items, so that the function
+    /// is cheap for large storages.
+    fn counts(&self) -> Result<Vec<ItemCount>>;
+
+    /// Take a logical snapshot of the current state.
+    /// 
+    /// All changes made after a snapshot can be discarded later.
+    /// Snapshots are nested: rolling back to or dropping a snapshot
+    /// affects all snapshots taken after it as well.
+    fn snapshot(&self) -> Result<SnapshotId>;
+
+    /// Discard all changes made after a snapshot and drop it.
+    /// 
+    /// Observers are not notified about discarded changes.
+    /// 
+    /// * `snapshot` - snapshot to roll back to
+    fn rollback_to(&self, snapshot: SnapshotId) -> Result<()>;
+
+    /// Drop a snapshot keeping all changes made after it.
+    /// 
+    /// * `snapshot` - snapshot to drop
+    fn drop_snapshot(&self, snapshot: SnapshotId) -> Result<()>;
+
+    /// Check if there are snapshots, that are not dropped yet.
+    fn has_snapshots(&self) -> bool;
+
+    /// Enable or disable batching of writes.
+    /// 
+    /// In batching mode changes are grouped into transactions, that are
+    /// committed after a given number of changes, on [`DataStorage::flush`]
+    /// and when the storage is dropped. Changes, that are not flushed yet,
+    /// are lost on a crash. Disabling the mode flushes pending changes.
+    /// 
+    /// * `batch_size` - number of changes per transaction (`None` disables batching)
+    fn set_write_batching(&self, batch_size: Option<usize>) -> Result<()>;
+
+    /// Commit changes, that are batched so far.
+    /// 
+    /// Changes made after a snapshot are committed only after
+    /// all snapshots are dropped.
+    fn flush(&self) -> Result<()>;
+
+    /// Return timestamp, that transactions dated before are locked.
+    /// 
+    /// Locked transactions cannot be added or removed unless the
+    /// lock is overridden.
+    fn period_lock(&self) -> Result<Option<Timestamp>>;
+
+    /// Lock transactions dated before a timestamp or remove the lock.
+    /// 
+    /// * `until` - timestamp to lock transactions before (`None` removes the lock)
+    fn set_period_lock(&self, until: Option<Timestamp>) -> Result<()>;
+
+    /// Allow or prohibit modifications of locked transactions.
+    /// 
+    /// Override is not persisted. Returns previous state of the override.
+    /// 
+    /// * `overridden` - if locked transactions can be modified
+    fn override_period_lock(&self, overridden: bool) -> bool;
+
+    /// Change timestamp of a transaction locally.
+    /// 
+    /// The change is not recorded in the changes journal, hence it is
+    /// not synchronized. Used to convert dates of existing transactions.
+    /// 
+    /// * `transaction` - identifier of a transaction
+    /// * `timestamp` - new timestamp
+    fn set_transaction_timestamp(&self, transaction: Id, timestamp: Timestamp) -> Result<()>;
+
+    /// Check if timestamps of transactions are canonical transaction dates 
+    /// (see [`crate::datetime::TxnDate`]).
+    fn canonical_dates(&self) -> Result<bool>;
+
+    /// Mark timestamps of transactions as canonical transaction dates.
+    fn set_canonical_dates(&self) -> Result<()>;
+
+    /// Return record of cryptographic engine, that protects data,
+    /// or [`None`] if it is not written yet.
+    fn crypto_record(&self) -> Result<Option<CryptoRecord>>;
+
+    /// Write record of cryptographic engine, that protects data.
+    /// The record is local to an instance, hence it is not journaled.
+    /// 
+    /// * `record` - record to write
+    fn set_crypto_record(&self, record: &CryptoRecord) -> Result<()>;
+
+    /// Return fingerprint of a key, that the budget is pinned to,
+    /// or [`None`] if it is not pinned yet.
+    fn key_fingerprint(&self) -> Result<Option<String>>;
+
+    /// Pin the budget to a key. The pin is local to an instance,
+    /// hence it is not journaled.
+    /// 
+    /// * `fingerprint` - fingerprint of the key
+    fn set_key_fingerprint(&self, fingerprint: &str) -> Result<()>;
+
+    /// Return record of a recovery key or [`None`] if there is no one.
+    fn recovery_record(&self) -> Result<Option<RecoveryRecord>>;
+
+    /// Write record of a recovery key replacing the previous one.
+    /// The record is local to an instance, hence it is not journaled.
+    /// 
+    /// * `record` - record to write
+    fn set_recovery_record(&self, record: &RecoveryRecord) -> Result<()>;
+
+    /// Remove record of a recovery key. Returns `false` if there
+    /// was no record.
+    fn remove_recovery_record(&self) -> Result<bool>;
+
+    /// Check if descriptions of transactions are encrypted deterministically.
+    fn deterministic_descriptions(&self) -> Result<bool>;
+
+    /// Set if descriptions of transactions are encrypted deterministically.
+    /// The mode is local to an instance, hence it is not journaled.
+    /// 
+    /// * `enabled` - `true` if descriptions are encrypted deterministically
+    fn set_deterministic_descriptions(&self, enabled: bool) -> Result<()>;
+
+    /// Change encrypted description of a transaction locally.
+    /// 
+    /// The change is not recorded in the changes journal, hence it is
+    /// not synchronized. Used to re-encrypt descriptions.
+    /// 
+    /// * `transaction` - identifier of a transaction
+    /// * `description` - new encrypted description
+    fn set_transaction_description(&self, transaction: Id, description: Vec<u8>) -> Result<()>;
+
+    /// Replace locally indexed values of a field of a transaction.
+    /// 
+    /// Index is local to an instance, hence it is not journaled. Values
+    /// of removed transactions are kept until they are purged, but they
+    /// are not found.
+    /// 
+    /// * `transaction` - identifier of a transaction
+    /// * `field` - indexed field
+    /// * `values` - new values of the field (empty to clear them)
+    fn set_indexed_values(&self, transaction: Id, field: IndexedField, values: &[Vec<u8>]) -> Result<()>;
+
+    /// Return all transactions with a given indexed value.
+    /// 
+    /// * `field` - indexed field
+    /// * `value` - value to look up
+    fn transactions_indexed(&self, field: IndexedField, value: &[u8]) -> Result<Vec<EncryptedTransaction>>;
+
+    /// Return all indexed values of a field along with identifiers of
+    /// transactions, that they belong to. Values of removed transactions
+    /// are not returned.
+    /// 
+    /// Used to filter transactions by a value, that cannot be looked up
+    /// by exact match (e.g. location within an area).
+    /// 
+    /// * `field` - indexed field
+    fn indexed_values(&self, field: IndexedField) -> Result<Vec<(Id, Vec<u8>)>>;
+
+    /// Return version of the local index of transactions, that it was
+    /// built with, or 0 if it is not built yet.
+    fn index_version(&self) -> Result<u32>;
+
+    /// Set version of the local index of transactions.
+    /// 
+    /// * `version` - version, that the index is built with
+    fn set_index_version(&self, version: u32) -> Result<()>;
+
+    /// Repoint all references to an item to another one.
+    /// 
+    /// Transactions, plans, loans and holdings (including removed ones)
+    /// are updated. The change is not recorded in the changes journal,
+    /// merges are synchronized as separate records instead.
+    /// 
+    /// * `from` - identifier of a referenced item
+    /// * `to` - identifier of an item to reference instead
+    fn redirect_references(&self, from: Id, to: Id) -> Result<()>;
+
+    /// Record a merge of two items.
+    /// 
+    /// Creation timestamp and origin are taken from meta info and MUST be present.
+    /// 
+    /// * `merge` - merge record
+    fn add_merge(&self, merge: Merge) -> Result<()>;
+
+    /// Returns all merges recorded since a given journal position.
+    /// 
+    /// * `base` - journal position. All merges recorded strictly after this position are returned.
+    fn merges_since(&self, base: JournalPosition) -> Result<Vec<Merge>>;
 }
@@ -0,0 +1,213 @@
+use chrono::Datelike;
+
+use crate::datetime::Timestamp;
+use super::data::{Account, Category, Transaction, Plan, Loan, Holding, PricePoint, Person, CategoryGroup, CustomFields};
+
+
+/// Maximal absolute value of an amount of money (in minimal units).
+///
+/// It is large enough for any real budget and small enough to
+/// sum many amounts without an overflow.
+pub const MAX_AMOUNT: isize = 1_000_000_000_000_000;
+
+/// The latest year, that items can be dated.
+const MAX_YEAR: i32 = 9999;
+
+
+/// Problem with a value of an item's field.
+#[derive(Clone, PartialEq, Eq, Debug)]
+pub struct ValidationError {
+    /// Name of the field
+    pub field: &'static str,
+
+    /// Human-readable description of the problem
+    pub reason: String,
+}
+
+
+impl ValidationError {
+    fn new<R: Into<String>>(field: &'static str, reason: R) -> Self {
+        ValidationError {
+            field,
+            reason: reason.into()
+        }
+    }
+}
+
+
+impl std::fmt::Display for ValidationError {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(f, "{}: {}", self.field, self.reason)
+    }
+}
+
+
+/// Trait for items, that can be checked before being stored.
+pub trait Validate {
+    /// Checks values of the item's fields.
+    ///
+    /// Returns all found problems, empty result means, that
+    /// the item is valid.
+    fn validate(&self) -> Vec<ValidationError>;
+}
+
+
+impl Validate for Account {
+    fn validate(&self) -> Vec<ValidationError> {
+        let mut errors = Vec::new();
+        non_empty("name", &self.name, &mut errors);
+        bounded("balance", self.balance, &mut errors);
+        bounded("initial_balance", self.initial_balance, &mut errors);
+        custom_fields(&self.custom_fields, &mut errors);
+
+        errors
+    }
+}
+
+
+impl Validate for Category {
+    fn validate(&self) -> Vec<ValidationError> {
+        let mut errors = Vec::new();
+        non_empty("name", &self.name, &mut errors);
+        custom_fields(&self.custom_fields, &mut errors);
+
+        errors
+    }
+}
+
+
+impl Validate for Transaction {
+    fn validate(&self) -> Vec<ValidationError> {
+        let mut errors = Vec::new();
+        sane_date("timestamp", &self.timestamp, &mut errors);
+        bounded("amount", self.amount, &mut errors);
+        custom_fields(&self.custom_fields, &mut errors);
+
+        errors
+    }
+}
+
+
+impl Validate for Plan {
+    fn validate(&self) -> Vec<ValidationError> {
+        let mut errors = Vec::new();
+        non_empty("name", &self.name, &mut errors);
+        non_negative("amount_limit", self.amount_limit, &mut errors);
+        if let Some(alert_threshold) = self.alert_threshold {
+            non_negative("alert_threshold", alert_threshold, &mut errors);
+        }
+        custom_fields(&self.custom_fields, &mut errors);
+
+        errors
+    }
+}
+
+
+impl Validate for Loan {
+    fn validate(&self) -> Vec<ValidationError> {
+        let mut errors = Vec::new();
+        non_empty("name", &self.name, &mut errors);
+        non_negative("principal", self.principal, &mut errors);
+        non_negative("interest_rate", self.interest_rate, &mut errors);
+        sane_date("start_timestamp", &self.start_timestamp, &mut errors);
+        custom_fields(&self.custom_fields, &mut errors);
+
+        if 0 == self.principal {
+            errors.push(ValidationError::new("principal", "must be positive"));
+        }
+
+        if 0 == self.term {
+            errors.push(ValidationError::new("term", "must be at least one month"));
+        }
+
+        errors
+    }
+}
+
+
+impl Validate for Holding {
+    fn validate(&self) -> Vec<ValidationError> {
+        let mut errors = Vec::new();
+        non_empty("symbol", &self.symbol, &mut errors);
+        custom_fields(&self.custom_fields, &mut errors);
+
+        if !self.quantity.is_finite() || self.quantity < 0.0 {
+            errors.push(ValidationError::new("quantity", "must be a non-negative number"));
+        }
+
+        errors
+    }
+}
+
+
+impl Validate for PricePoint {
+    fn validate(&self) -> Vec<ValidationError> {
+        let mut errors = Vec::new();
+        non_empty("symbol", &self.symbol, &mut errors);
+        sane_date("timestamp", &self.timestamp, &mut errors);
+        non_negative("price", self.price, &mut errors);
+
+        errors
+    }
+}
+
+
+impl Validate for Person {
+    fn validate(&self) -> Vec<ValidationError> {
+        let mut errors = Vec::new();
+        non_empty("name", &self.name, &mut errors);
+
+        errors
+    }
+}
+
+
+impl Validate for CategoryGroup {
+    fn validate(&self) -> Vec<ValidationError> {
+        let mut errors = Vec::new();
+        non_empty("name", &self.name, &mut errors);
+
+        errors
+    }
+}
+
+
+fn non_empty(field: &'static str, value: &str, errors: &mut Vec<ValidationError>) {
+    if value.trim().is_empty() {
+        errors.push(ValidationError::new(field, "must not be empty"));
+    }
+}
+
+
+fn bounded(field: &'static str, value: isize, errors: &mut Vec<ValidationError>) {
+    if MAX_AMOUNT < value.saturating_abs() {
+        errors.push(ValidationError::new(field, format!("must not exceed {} by absolute value", MAX_AMOUNT)));
+    }
+}
+
+
+fn non_negative(field: &'static str, value: isize, errors: &mut Vec<ValidationError>) {
+    match value {
+        value if value < 0 => errors.push(ValidationError::new(field, "must not be negative")),
+        value => bounded(field, value, errors)
+    }
+}
+
+
+fn sane_date(field: &'static str, value: &Timestamp, errors: &mut Vec<ValidationError>) {
+    //
+    // Budgets cover recent history, so dates before 1970 or
+    // far in the future are typos most likely
+    //
+
+    if value.timestamp() < 0 || MAX_YEAR < value.year() {
+        errors.push(ValidationError::new(field, format!("must be between 1970 and {}", MAX_YEAR)));
+    }
+}
+
+
+fn custom_fields(fields: &CustomFields, errors: &mut Vec<ValidationError>) {
+    if fields.keys().any(|key| key.trim().is_empty()) {
+        errors.push(ValidationError::new("custom_fields", "keys must not be empty"));
+    }
+}
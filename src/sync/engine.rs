@@ -1,5 +1,8 @@
 use crate::error::Result;
+use crate::cancel::CancellationToken;
+use crate::progress::Progress;
 use super::syncable::Syncable;
+use super::status::SyncStatus;
 
 
 /// Synchronization engine.
@@ -8,9 +11,21 @@ pub trait SyncEngine {
     /// 
     /// Receives remote updates, sends local updates and applies remote ones.
     /// 
+    /// Cancellation is checked only until local changes are merged,
+    /// after that synchronization is always completed.
+    /// 
     /// * `current_instance` - name of current app instance
     /// * `syncable` - object to perform syncronization for
-    fn perform_sync<S: Syncable>(&self, current_instance: &S::InstanceId, syncable: &S, context: &S::Context) -> Result<()>;
+    /// * `context` - user-provided context
+    /// * `cancel` - token to cancel synchronization with
+    /// * `progress` - receiver of progress reports
+    fn perform_sync<S: Syncable>(&self, current_instance: &S::InstanceId, syncable: &S, context: &S::Context, 
+        cancel: &CancellationToken, progress: &dyn Progress) -> Result<()>;
+
+    /// Returns state of synchronization without contacting a remote.
+    /// 
+    /// * `syncable` - object to report pending changes of
+    fn status<S: Syncable>(&self, syncable: &S) -> Result<SyncStatus>;
 
     /// Add a remote. Note, that there can be only one remote. Therefore,
     /// the function fails, if there's already a remote associated.
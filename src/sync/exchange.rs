@@ -0,0 +1,346 @@
+use crate::location::{Location, create_private_dir, restrict_file};
+use crate::error::{Result, Error, ErrorKind, Context};
+use crate::trace::trace_debug;
+use crate::progress::{Progress, Phase};
+use crate::datetime::{Clock, Timestamp, FIRST_AFTER_JANUARY_1970};
+use super::syncable::Syncable;
+use super::history::InstanceHistory;
+use super::MALFORMED_LAST_SYNC_TIMESTAMP;
+
+
+/// Synchronization folder.
+const SYNC_FORDER: &str = "sync";
+
+/// File that holds last synchronization time.
+const LAST_SYNC_FILE: &str = "last-sync";
+
+/// Folder with files of a changelog, that is to be joined into
+/// the shared one on the next synchronization.
+const PENDING_FOLDER: &str = "pending";
+
+/// File with last synchronization timestamp.
+pub(crate) const TIMESTAMP_FILE: &str = "timestamp";
+
+/// File with last synchronized instance timestamp.
+pub(crate) const LAST_INSTANCE_FILE: &str = "instance";
+
+/// File with full changelog.
+pub(crate) const CHANGELOG_FILE: &str = "changelog";
+
+/// File with snapshot of full state.
+pub(crate) const STATE_SNAPSHOT_FILE: &str = "snapshot";
+
+
+/// Returns path to synchronization folder of a location.
+///
+/// * `loc` - storage location provider
+pub(crate) fn sync_folder<L: Location>(loc: &L) -> std::path::PathBuf {
+    loc.data_dir()
+        .join(SYNC_FORDER)
+}
+
+
+/// Returns path to file with local time of the last synchronization.
+///
+/// * `loc` - storage location provider
+pub(crate) fn last_sync_path<L: Location>(loc: &L) -> std::path::PathBuf {
+    sync_folder(loc)
+        .join(LAST_SYNC_FILE)
+}
+
+
+/// Returns path to folder with changelog kept aside (see [`keep_aside`]).
+///
+/// * `loc` - storage location provider
+pub(crate) fn pending_path<L: Location>(loc: &L) -> std::path::PathBuf {
+    sync_folder(loc)
+        .join(PENDING_FOLDER)
+}
+
+
+/// Returns paths of files and folders, that are shared by all
+/// instances, relative to a working folder.
+pub(crate) fn shared_paths() -> [&'static str; 5] {
+    [TIMESTAMP_FILE, LAST_INSTANCE_FILE, CHANGELOG_FILE, STATE_SNAPSHOT_FILE, InstanceHistory::pathspec()]
+}
+
+
+/// Exchanges changes through files of a working folder: merges remote
+/// changes and exports local ones. An engine brings remote versions of
+/// files into the folder before and sends new versions back after.
+///
+/// * `work_dir` - working folder with shared files
+/// * `last_sync_path` - path to file with local time of the last synchronization
+/// * `pending_path` - folder with changelog kept aside by reset of repository (if any)
+/// * `current_instance` - name of current app instance
+/// * `syncable` - object to perform syncronization for
+/// * `context` - user-provided context
+/// * `progress` - receiver of progress reports
+pub(crate) fn exchange_changes<S: Syncable>(work_dir: &std::path::Path, last_sync_path: &std::path::Path, 
+    pending_path: Option<&std::path::Path>, current_instance: &S::InstanceId, syncable: &S, context: &S::Context, 
+    progress: &dyn Progress) -> Result<()> 
+{
+    //
+    // Files are missing in a fresh repository, so they are created
+    // empty then (this is the case of the very first synchronization)
+    //
+
+    let mut timestamp_file = open_sync_file(&work_dir.join(TIMESTAMP_FILE))?;
+    let mut last_instance_file = open_sync_file(&work_dir.join(LAST_INSTANCE_FILE))?;
+    let mut changelog_file = open_sync_file(&work_dir.join(CHANGELOG_FILE))?;
+    let mut state_snapshot_file = open_sync_file(&work_dir.join(STATE_SNAPSHOT_FILE))?;
+
+    //
+    // Perform actual synchronization (read last sync timestamp just before and
+    // write right after the process). Missing file means, that the instance
+    // has never been synchronized
+    //
+
+    let mut last_sync_file = open_sync_file(last_sync_path)?;
+    restrict_file(last_sync_path)?;
+
+    let last_sync = read_last_sync(&mut last_sync_file)?;
+
+    //
+    // Each instance appends its diffs to its own history, so that other
+    // instances receive them even if they skip several synchronizations.
+    // Instance, that is not registered in history yet, merges the full
+    // changelog instead, existing diffs are acknowledged then
+    //
+
+    let pending_path = pending_path.filter(|pending_path| pending_path.exists());
+    let mut history = InstanceHistory::open(work_dir, &current_instance.to_string())?;
+
+    if let Some(pending_path) = pending_path {
+        history.restore(pending_path)?;
+    }
+
+    let first_sync = !history.is_registered();
+    if first_sync {
+        trace_debug!(%last_sync, "first synchronization of instance");
+        history.register()?;
+    }
+
+    let mut diff = Vec::new();
+    if syncable.export_diff(&mut diff, context)? {
+        std::fs::write(history.next_diff_path(&Clock::now())?, diff)?;
+    }
+
+    progress.report(Phase::Merge, 0, 1);
+    syncable.merge_and_export_changes(&mut timestamp_file, &mut last_instance_file, 
+        &mut changelog_file, &mut state_snapshot_file, first_sync.then_some(&last_sync), context)
+        .map_err(|error| match error.is_data_corruption() {
+            true => error.context(format!("reading {}", work_dir.join(CHANGELOG_FILE).display())),
+            false => error
+        })?;
+
+    for (instance, sequence, path) in history.unacknowledged()? {
+        syncable.merge_diff(&mut std::fs::File::open(&path)?, context)
+            .map_err(|error| match error.is_data_corruption() {
+                true => error.context(format!("reading {}", path.display())),
+                false => error
+            })?;
+
+        history.acknowledge(&instance, sequence);
+    }
+
+    history.prune()?;
+    history.save()?;
+    progress.report(Phase::Merge, 1, 1);
+
+    //
+    // Changes kept aside by reset of repository are joined
+    // into the changelog, so that they reach remote
+    //
+
+    if let Some(pending_path) = pending_path {
+        let read_pending = |file: &str| std::fs::read(pending_path.join(file));
+
+        trace_debug!("joining pending changelog");
+        syncable.join_changelog(&mut timestamp_file, &mut last_instance_file, &mut changelog_file,
+            (&read_pending(TIMESTAMP_FILE)?, &read_pending(LAST_INSTANCE_FILE)?, &read_pending(CHANGELOG_FILE)?), context)
+            .context("joining pending changelog")?;
+    }
+
+    //
+    // Files are rewritten in place, so leftovers of longer
+    // previous versions are cut off. Snapshot is prefixed
+    // with its size and is not necessarily rewritten
+    //
+
+    for file in [&mut timestamp_file, &mut last_instance_file, &mut changelog_file] {
+        truncate_at_position(file)?;
+    }
+
+    prepare_for_overwrite(&mut last_sync_file)?;
+    write_last_sync(&mut last_sync_file, &Clock::now())
+}
+
+
+/// Keeps changelog and history of a working folder aside, so that they
+/// are joined into shared ones on the next synchronization (see
+/// [`exchange_changes`]). This way changes, that did not reach
+/// remote, are not lost, when working folder is replaced with
+/// remote's version. Changelog, that is kept aside already, is 
+/// not replaced, since it is not joined yet. Empty changelog
+/// is not kept.
+///
+/// * `work_dir` - working folder with shared files
+/// * `pending_path` - folder to keep files in
+pub(crate) fn keep_aside(work_dir: &std::path::Path, pending_path: &std::path::Path) -> Result<()> {
+    let files = [TIMESTAMP_FILE, LAST_INSTANCE_FILE, CHANGELOG_FILE];
+    let is_empty = |file: &str| std::fs::metadata(work_dir.join(file))
+        .map_or(true, |metadata| 0 == metadata.len());
+
+    if pending_path.exists() || files.iter().any(|file| is_empty(file)) {
+        return Ok(());
+    }
+
+    create_private_dir(pending_path)?;
+    InstanceHistory::copy(work_dir, pending_path)?;
+
+    for file in files {
+        let path = pending_path.join(file);
+
+        std::fs::copy(work_dir.join(file), &path)?;
+        restrict_file(&path)?;
+    }
+
+    Ok(())
+}
+
+
+/// Creates file with local time of the last synchronization unless
+/// it exists. The file holds a placeholder until the first synchronization.
+///
+/// * `last_sync_path` - path to the file
+pub(crate) fn init_last_sync(last_sync_path: &std::path::Path) -> Result<()> {
+    if last_sync_path.exists() {
+        return Ok(());
+    }
+
+    //
+    // I write first nonzero timestamp after January 1970 to
+    // ensure, that all predefined items will not by
+    // synced between instances
+    //
+
+    let mut file = std::fs::File::create(last_sync_path)?;
+    restrict_file(last_sync_path)?;
+
+    write_last_sync(&mut file, &FIRST_AFTER_JANUARY_1970)
+}
+
+
+/// Returns local time of the last successful synchronization or
+/// [`None`] if the instance has never been synchronized.
+///
+/// * `last_sync_path` - path to file with the time
+pub(crate) fn last_sync(last_sync_path: &std::path::Path) -> Result<Option<Timestamp>> {
+    let mut last_sync_file = match std::fs::File::open(last_sync_path) {
+        Ok(last_sync_file) => last_sync_file,
+        Err(error) if error.kind() == std::io::ErrorKind::NotFound => return Ok(None),
+        Err(error) => return Err(error.into())
+    };
+
+    Ok(Some(read_last_sync(&mut last_sync_file)?)
+        .filter(|last_sync| *FIRST_AFTER_JANUARY_1970 < *last_sync))
+}
+
+
+fn read_last_sync<R: std::io::Read>(last_sync: &mut R) -> Result<Timestamp> {
+    let mut buffer = [0; std::mem::size_of::<i64>()];
+    let seconds = match last_sync.read_exact(&mut buffer) {
+        Ok(_) => i64::from_le_bytes(buffer),
+        _ => 0i64
+    };
+
+    Timestamp::from_timestamp(seconds, 0)
+        .ok_or(Error::from_message(MALFORMED_LAST_SYNC_TIMESTAMP).with_kind(ErrorKind::Corruption))
+}
+
+
+fn write_last_sync<W: std::io::Write>(last_sync: &mut W, timestamp: &Timestamp) -> Result<()> {
+    let timestamp = timestamp
+        .timestamp()
+        .to_le_bytes();
+
+    last_sync
+        .write_all(&timestamp)
+        .map_err(Error::from)
+}
+
+
+fn prepare_for_overwrite<S: std::io::Seek>(s: &mut S) -> Result<()> {
+    s.rewind()
+        .map_err(Error::from)
+}
+
+
+fn truncate_at_position(file: &mut std::fs::File) -> Result<()> {
+    let position = std::io::Seek::stream_position(file)?;
+
+    file.set_len(position)
+        .map_err(Error::from)
+}
+
+
+fn open_sync_file(path: &std::path::Path) -> Result<std::fs::File> {
+    std::fs::OpenOptions::new()
+        .read(true)
+        .write(true)
+        .create(true)
+        .truncate(false)
+        .open(path)
+        .with_context(|| format!("opening {}", path.display()))
+}
+
+
+#[cfg(test)]
+mod tests {
+    use std::io::{Read, Write};
+
+    use super::{open_sync_file, prepare_for_overwrite, truncate_at_position};
+
+    fn temp_path() -> std::path::PathBuf {
+        std::env::temp_dir()
+            .join(format!("bdgt-exchange-{}", uuid::Uuid::new_v4()))
+    }
+
+    #[test]
+    fn missing_file_is_created_empty() {
+        let path = temp_path();
+        let file = open_sync_file(&path).unwrap();
+
+        assert_eq!(file.metadata().unwrap().len(), 0);
+        std::fs::remove_file(&path).unwrap();
+    }
+
+    #[test]
+    fn existing_file_is_not_truncated_on_open() {
+        let path = temp_path();
+        std::fs::write(&path, b"previous changelog").unwrap();
+
+        let mut contents = Vec::new();
+        open_sync_file(&path).unwrap()
+            .read_to_end(&mut contents)
+            .unwrap();
+
+        assert_eq!(contents, b"previous changelog");
+        std::fs::remove_file(&path).unwrap();
+    }
+
+    #[test]
+    fn shorter_rewrite_truncates_tail() {
+        let path = temp_path();
+        std::fs::write(&path, b"longer previous changelog").unwrap();
+
+        let mut file = open_sync_file(&path).unwrap();
+        prepare_for_overwrite(&mut file).unwrap();
+        file.write_all(b"shorter").unwrap();
+        truncate_at_position(&mut file).unwrap();
+
+        assert_eq!(std::fs::read(&path).unwrap(), b"shorter");
+        std::fs::remove_file(&path).unwrap();
+    }
+}
@@ -0,0 +1,182 @@
+use crate::error::{Result, Error, ErrorKind};
+use crate::trace::trace_debug;
+use super::transport::SyncTransport;
+use super::{MALFORMED_REMOTE_PATH, REMOTE_LOCKED};
+
+
+/// Lock file within the folder.
+const LOCK_FILE: &str = ".lock";
+
+/// Age of a lock, after which it is considered abandoned
+/// (e.g. an instance has crashed during synchronization).
+const STALE_LOCK_AGE: std::time::Duration = std::time::Duration::from_secs(15 * 60);
+
+
+/// Transport, that stores synchronization files in a local folder.
+///
+/// The folder may be shared by other means, e.g. it may reside on
+/// a removable drive or be served to devices in local network (see
+/// [`super::LanShare`]). Files are replaced atomically, so a reader
+/// never sees a partially written file.
+#[derive(Clone)]
+pub struct FolderTransport {
+    /// Folder with synchronization files
+    root: std::path::PathBuf,
+}
+
+
+impl FolderTransport {
+    /// Creates a transport for a folder. The folder is created
+    /// on the first write if it is missing.
+    ///
+    /// * `root` - folder with synchronization files
+    pub fn new<P: AsRef<std::path::Path>>(root: P) -> Self {
+        FolderTransport {
+            root: root.as_ref().to_owned()
+        }
+    }
+}
+
+
+impl SyncTransport for FolderTransport {
+    fn remote(&self) -> String {
+        self.root
+            .display()
+            .to_string()
+    }
+
+    fn list(&self) -> Result<Vec<String>> {
+        let mut files = Vec::new();
+        if self.root.exists() {
+            list_files(&self.root, "", &mut files)?;
+        }
+
+        Ok(files)
+    }
+
+    fn read(&self, path: &str) -> Result<Vec<u8>> {
+        std::fs::read(self.local_path(path)?)
+            .map_err(Error::from)
+    }
+
+    fn write(&self, path: &str, content: &[u8]) -> Result<()> {
+        let local_path = self.local_path(path)?;
+        if let Some(parent) = local_path.parent() {
+            std::fs::create_dir_all(parent)?;
+        }
+
+        let file_name = local_path.file_name()
+            .unwrap_or_default()
+            .to_string_lossy();
+
+        let temp_path = local_path.with_file_name(format!(".{}.tmp", file_name));
+        std::fs::write(&temp_path, content)?;
+
+        std::fs::rename(&temp_path, &local_path)
+            .map_err(Error::from)
+    }
+
+    fn remove(&self, path: &str) -> Result<()> {
+        std::fs::remove_file(self.local_path(path)?)
+            .map_err(Error::from)
+    }
+
+    fn lock(&self, owner: &str) -> Result<()> {
+        std::fs::create_dir_all(&self.root)?;
+
+        let lock_path = self.root.join(LOCK_FILE);
+        let create_lock = || std::fs::OpenOptions::new()
+            .write(true)
+            .create_new(true)
+            .open(&lock_path);
+
+        let mut lock = match create_lock() {
+            Ok(lock) => lock,
+            Err(error) if error.kind() == std::io::ErrorKind::AlreadyExists => {
+                //
+                // Lock may be left by an instance, that crashed during
+                // synchronization, such lock is broken after a while
+                //
+
+                let is_stale = std::fs::metadata(&lock_path)
+                    .and_then(|metadata| metadata.modified())
+                    .ok()
+                    .and_then(|modified| modified.elapsed().ok())
+                    .is_some_and(|age| age > STALE_LOCK_AGE);
+
+                if !is_stale {
+                    let holder = std::fs::read_to_string(&lock_path)
+                        .unwrap_or_default();
+
+                    return Err(Error::from_message_with_extra(REMOTE_LOCKED, holder.trim())
+                        .with_kind(ErrorKind::Locked));
+                }
+
+                trace_debug!("breaking stale lock of folder");
+                std::fs::remove_file(&lock_path)?;
+                create_lock()?
+            },
+            Err(error) => return Err(error.into())
+        };
+
+        std::io::Write::write_all(&mut lock, owner.as_bytes())
+            .map_err(Error::from)
+    }
+
+    fn unlock(&self) -> Result<()> {
+        std::fs::remove_file(self.root.join(LOCK_FILE))
+            .map_err(Error::from)
+    }
+}
+
+
+impl FolderTransport {
+    fn local_path(&self, path: &str) -> Result<std::path::PathBuf> {
+        //
+        // Paths may come from other devices, so they must
+        // not lead out of the folder
+        //
+
+        let is_valid = |component: &str| !component.is_empty() && !component.starts_with('.') &&
+            !component.contains('\\');
+
+        if !path.split('/').all(is_valid) {
+            return Err(Error::from_message_with_extra(MALFORMED_REMOTE_PATH, path)
+                .with_kind(ErrorKind::InvalidInput));
+        }
+
+        Ok(path
+            .split('/')
+            .fold(self.root.clone(), |local_path, component| local_path.join(component)))
+    }
+}
+
+
+fn list_files(folder: &std::path::Path, prefix: &str, files: &mut Vec<String>) -> Result<()> {
+    for entry in std::fs::read_dir(folder)? {
+        let entry = entry?;
+        let name = entry.file_name()
+            .to_string_lossy()
+            .into_owned();
+
+        //
+        // Hidden files are lock and partially written ones
+        //
+
+        if name.starts_with('.') {
+            continue;
+        }
+
+        let path = match prefix.is_empty() {
+            true => name,
+            false => format!("{}/{}", prefix, name)
+        };
+
+        match entry.file_type()?.is_dir() {
+            true => list_files(&entry.path(), &path, files)?,
+            false => files.push(path)
+        }
+    }
+
+    Ok(())
+}
@@ -1,46 +1,49 @@
-use crate::location::Location;
-use crate::error::{Result, Error};
-use crate::datetime::{Clock, Timestamp, FIRST_AFTER_JANUARY_1970};
+use crate::location::{Location, LocationLock, create_private_dir};
+use crate::error::{Result, Error, ErrorKind, Context};
+use crate::cancel::CancellationToken;
+use crate::trace::trace_debug;
+use crate::progress::{Progress, Phase, NoProgress};
 use super::engine::SyncEngine;
 use super::syncable::Syncable;
-use super::{REMOTE_ALREADY_EXIST, MALFORMED_LAST_SYNC_TIMESTAMP, REMOTE_CONFLICT};
+use super::status::SyncStatus;
+use super::proxy::Proxy;
+use super::exchange::{self, TIMESTAMP_FILE, LAST_INSTANCE_FILE, CHANGELOG_FILE};
+use super::{REMOTE_ALREADY_EXIST, REMOTE_CONFLICT};
 
 
 /// Name of git's remote for the repository.
 const REMOTE_NAME: &str = "origin";
 
-/// Name of reference to update on commit.
+/// Name of reference to current commit.
 const REF_NAME: &str = "HEAD";
 
 /// Name of reference to fetched head.
 const FETCH_REF_NAME: &str = "FETCH_HEAD";
 
-/// Branch name.
+/// Name of branch, that is used if neither it is configured,
+/// nor remote has a default one.
 const BRANCH_NAME: &str = "main";
 
+/// Prefix of local branches' references.
+const BRANCHES_PREFIX: &str = "refs/heads/";
+
 /// Name of configuration parameter that contains a username.
 const CFG_NAME: &str = "user.name";
 
 /// Name of configuration parameter that contains an email.
 const CFG_EMAIL: &str = "user.email";
 
-/// Synchronization folder.
-const SYNC_FORDER: &str = "sync";
-
-/// File that holds last synchronization time.
-const LAST_SYNC_FILE: &str = "last-sync";
+/// Domain of synthetic emails of commit authors, that is reserved
+/// for invalid addresses (RFC 2606).
+const SYNTHETIC_EMAIL_DOMAIN: &str = "bdgt.invalid";
 
 /// Repository folder.
 const SYNC_REPO: &str = "repository";
 
-/// File with last synchronization timestamp.
-const TIMESTAMP_FILE: &str = "timestamp";
+/// Folder with backup of repository made by reset.
+const SYNC_REPO_BACKUP: &str = "repository.backup";
 
-/// File with last synchronized instance timestamp.
-const LAST_INSTANCE_FILE: &str = "instance";
 
-/// File with full changelog.
-const CHANGELOG_FILE: &str = "changelog";
 
 
 /// Synchronization engine that uses git internally.
@@ -54,21 +57,65 @@ pub struct GitSyncEngine {
     /// Path to last sync timestamp file.
     last_sync_path: std::path::PathBuf,
 
+    /// Path to folder with changelog to join (see [`GitSyncEngine::reset`]).
+    pending_path: std::path::PathBuf,
+
     /// Default authenticator
     /// Usually it is used with `config`
     authenticator: auth_git2::GitAuthenticator,
+
+    /// Name of synchronization branch (default branch of remote if absent)
+    branch: Option<String>,
+
+    /// Name of author of commits, that overrides git configuration
+    commit_name: Option<String>,
+
+    /// Email of author of commits, that overrides git configuration
+    commit_email: Option<String>,
+
+    /// Proxy to connect to remote through
+    proxy: Proxy,
+
+    /// Lock of location, that prevents concurrent synchronization from other processes
+    _lock: LocationLock,
 }
 
 
 impl GitSyncEngine {
     pub fn create<L: Location>(loc: &L, remote: Option<&str>) -> Result<Self> {
+        Self::create_cancellable(loc, remote, &CancellationToken::new())
+    }
+
+    /// Creates an engine, cloning of a remote can be cancelled.
+    /// 
+    /// * `loc` - storage location provider
+    /// * `remote` - remote to clone repository from
+    /// * `cancel` - token to cancel cloning with
+    pub fn create_cancellable<L: Location>(loc: &L, remote: Option<&str>, cancel: &CancellationToken) -> Result<Self> {
+        Self::create_with(loc, remote, cancel, &NoProgress, &Proxy::default())
+    }
+
+    /// Creates an engine, cloning of a remote can be cancelled 
+    /// and reports its progress.
+    /// 
+    /// Proxy is used for cloning only, call [`GitSyncEngine::with_proxy`]
+    /// to use it for synchronization too.
+    /// 
+    /// * `loc` - storage location provider
+    /// * `remote` - remote to clone repository from
+    /// * `cancel` - token to cancel cloning with
+    /// * `progress` - receiver of cloning progress reports
+    /// * `proxy` - proxy to connect to remote through
+    pub fn create_with<L: Location>(loc: &L, remote: Option<&str>, cancel: &CancellationToken, 
+        progress: &dyn Progress, proxy: &Proxy) -> Result<Self> 
+    {
         //
         // Check is root location exists and create it if necessary.
         // Sync folder should be created manually
         //
 
         loc.create_if_absent()?;
-        std::fs::create_dir(Self::sync_folder(loc))?;
+        create_private_dir(exchange::sync_folder(loc))?;
 
         //
         // Init or clone repository
@@ -77,8 +124,7 @@ impl GitSyncEngine {
         let repo_path = Self::sync_repo_path(loc);
         match remote {
             Some(remote) => {
-                auth_git2::GitAuthenticator::default()
-                    .clone_repo(remote, repo_path)?
+                Self::clone_repo(remote, &repo_path, cancel, progress, proxy)?
             }
             None => {
                 git2::Repository::init(repo_path)?
@@ -87,15 +133,9 @@ impl GitSyncEngine {
 
         //
         // Create last sync file
-        // I write first nonzero timestamp after January 1970 to
-        // ensure, that all predefined items will not by
-        // synced between instances
         //
 
-        let last_sync_path = Self::sync_last_sync_path(loc);
-        let mut file = std::fs::File::create(last_sync_path)?;
-
-        Self::write_last_sync(&mut file, &FIRST_AFTER_JANUARY_1970)?;
+        exchange::init_last_sync(&exchange::last_sync_path(loc))?;
 
         //
         // Now I can just open repository and build engine
@@ -105,74 +145,181 @@ impl GitSyncEngine {
     }
 
     pub fn open<L: Location>(loc: &L) -> Result<Self> {
+        let lock = LocationLock::acquire(loc)?;
         let repo_path = Self::sync_repo_path(loc);
-        let last_sync_path = Self::sync_last_sync_path(loc);
+        let last_sync_path = exchange::last_sync_path(loc);
+        let pending_path = exchange::pending_path(loc);
 
         Ok(GitSyncEngine {
-            repo: git2::Repository::open(&repo_path)?,
+            repo: git2::Repository::open(&repo_path)
+                .with_context(|| format!("opening synchronization repository {}", repo_path.display()))?,
             repo_path: repo_path,
             last_sync_path: last_sync_path,
+            pending_path,
             authenticator: auth_git2::GitAuthenticator::default(),
+            branch: None,
+            commit_name: None,
+            commit_email: None,
+            proxy: Proxy::default(),
+            _lock: lock,
         })
     }
-}
 
+    /// Recreates damaged or deleted synchronization repository.
+    /// 
+    /// Current repository (if any) is moved into `repository.backup` folder,
+    /// then a fresh one is cloned from a remote. Files of the backup are
+    /// plain ones, so they are readable even if repository is damaged. If
+    /// they differ from the remote ones (e.g. local commits were not pushed),
+    /// they are kept aside and their changelog is joined into repository's
+    /// one on the next synchronization, so local changes are not lost.
+    /// 
+    /// * `loc` - storage location provider
+    /// * `remote` - remote to clone repository from (remote of current 
+    ///   repository is used if absent and readable)
+    pub fn reset<L: Location>(loc: &L, remote: Option<&str>) -> Result<Self> {
+        let _lock = LocationLock::acquire(loc)?;
+        create_private_dir(exchange::sync_folder(loc))?;
+
+        let repo_path = Self::sync_repo_path(loc);
+        let backup_path = exchange::sync_folder(loc)
+            .join(SYNC_REPO_BACKUP);
+
+        let remote = remote
+            .map(str::to_owned)
+            .or_else(|| Self::remote_of(&repo_path));
 
-impl SyncEngine for GitSyncEngine {
-    fn perform_sync<S: Syncable>(&self, current_instance: &S::InstanceId, syncable: &S, context: &S::Context) -> Result<()> {
         //
-        // Get all changes from remote and open raw files
+        // Previous backup is replaced, since its changes are
+        // either joined already or kept aside
         //
 
-        self.pull_remote()?;
+        if repo_path.exists() {
+            if backup_path.exists() {
+                std::fs::remove_dir_all(&backup_path)?;
+            }
 
-        let mut timestamp_file = std::fs::OpenOptions::new()
-            .read(true)
-            .write(true)
-            .create(true)
-            .open(self.syncable_file_path(TIMESTAMP_FILE))?;
+            std::fs::rename(&repo_path, &backup_path)
+                .context("backing up synchronization repository")?;
+        }
 
-        let mut last_instance_file = std::fs::OpenOptions::new()
-            .read(true)
-            .write(true)
-            .create(true)
-            .open(self.syncable_file_path(LAST_INSTANCE_FILE))?;
+        match &remote {
+            Some(remote) => {
+                Self::clone_repo(remote, &repo_path, &CancellationToken::new(), &NoProgress, &Proxy::default())?
+            }
+            None => {
+                git2::Repository::init(&repo_path)?
+            }
+        };
 
-        let mut changelog_file = std::fs::OpenOptions::new()
-            .read(true)
-            .write(true)
-            .create(true)
-            .open(self.syncable_file_path(CHANGELOG_FILE))?;
+        Self::keep_pending(loc, &backup_path, &repo_path)?;
 
         //
-        // Perform actual synchronization (read last sync timestamp just before and
-        // write right after the process)
+        // Last sync file can be lost along with repository
         //
 
-        let mut last_sync_file = std::fs::OpenOptions::new()
-            .read(true)
-            .write(true)
-            .open(&self.last_sync_path)?;
+        exchange::init_last_sync(&exchange::last_sync_path(loc))?;
+
+        Self::open(loc)
+    }
+
+    /// Sets branch, that is used for synchronization.
+    /// 
+    /// By default the branch, that remote's HEAD points to, is used
+    /// (e.g. `main` or `master`), or `main` if remote has no default
+    /// branch. If local branch is missing, then it is created from 
+    /// remote's one or from current commit on synchronization.
+    /// 
+    /// * `branch` - branch name
+    pub fn with_branch(mut self, branch: &str) -> Self {
+        self.branch = Some(branch.to_owned());
+        self
+    }
+
+    /// Sets author of commits made by synchronization.
+    /// 
+    /// Absent values are taken from git configuration (`user.name` and
+    /// `user.email`). If git has no values too, then synthetic ones are
+    /// derived from current instance's name.
+    /// 
+    /// * `name` - author's name
+    /// * `email` - author's email
+    pub fn with_commit_identity(mut self, name: Option<&str>, email: Option<&str>) -> Self {
+        self.commit_name = name.map(str::to_owned);
+        self.commit_email = email.map(str::to_owned);
+        self
+    }
+
+    /// Sets proxy to connect to remote through.
+    /// 
+    /// By default proxy is detected by git from `http.proxy`
+    /// configuration parameter and environment variables.
+    /// 
+    /// * `proxy` - proxy to use
+    pub fn with_proxy(mut self, proxy: Proxy) -> Self {
+        self.proxy = proxy;
+        self
+    }
+}
+
+
+impl SyncEngine for GitSyncEngine {
+    #[cfg_attr(feature = "tracing", tracing::instrument(level = "debug", skip_all, fields(instance = %current_instance)))]
+    fn perform_sync<S: Syncable>(&self, current_instance: &S::InstanceId, syncable: &S, context: &S::Context, 
+        cancel: &CancellationToken, progress: &dyn Progress) -> Result<()> 
+    {
+        //
+        // Get all changes from remote and open raw files.
+        // Cancellation is impossible after this point: merged
+        // changes must be committed and pushed
+        //
 
-        syncable.merge_and_export_changes(&mut timestamp_file, &mut last_instance_file, 
-            &mut changelog_file, &Self::read_last_sync(&mut last_sync_file)?, context)?;
+        cancel.check()?;
+        self.ensure_branch()?;
+        self.pull_remote(cancel, progress)?;
+        cancel.check()?;
 
-        Self::prepare_for_overwrite(&mut last_sync_file)?;
-        Self::write_last_sync(&mut last_sync_file, &Clock::now())?;
+        let has_pending = self.pending_path.exists();
+        exchange::exchange_changes(&self.repo_path, &self.last_sync_path, Some(&self.pending_path), 
+            current_instance, syncable, context, progress)?;
 
         //
         // Now commit new versions of files and push to remote
         //
 
-        let branch_ref = self.commit_files([TIMESTAMP_FILE, LAST_INSTANCE_FILE, CHANGELOG_FILE].iter(), 
+        let signature = self.commit_signature(&current_instance.to_string())?;
+        let branch_ref = self.commit_files(&exchange::shared_paths(), &signature, 
             &format!("Updates from {}", current_instance))?;
 
-        self.push_remote(&branch_ref)
+        if has_pending {
+            std::fs::remove_dir_all(&self.pending_path)?;
+        }
+
+        self.push_remote(&branch_ref, progress)
+    }
+
+    fn status<S: Syncable>(&self, syncable: &S) -> Result<SyncStatus> {
+        let remote = self.repo
+            .find_remote(REMOTE_NAME)
+            .ok()
+            .and_then(|remote| remote.url().map(str::to_owned));
+
+        let (ahead, behind) = self.ahead_behind()?;
+
+        let last_sync = exchange::last_sync(&self.last_sync_path)?;
+
+        Ok(SyncStatus {
+            remote,
+            ahead,
+            behind,
+            pending_changes: syncable.pending_changes()?,
+            last_sync
+        })
     }
 
     fn add_remote(&self, remote: &str) -> Result<()> {
         if let Ok(_) = self.repo.find_remote(REMOTE_NAME) {
-            return Err(Error::from_message(REMOTE_ALREADY_EXIST));
+            return Err(Error::from_message(REMOTE_ALREADY_EXIST).with_kind(ErrorKind::AlreadyExists));
         }
 
         self.repo
@@ -196,21 +343,30 @@ impl SyncEngine for GitSyncEngine {
 
 
 impl GitSyncEngine {
-    fn pull_remote(&self) -> Result<()> {
+    #[cfg_attr(feature = "tracing", tracing::instrument(level = "debug", skip_all))]
+    fn pull_remote(&self, cancel: &CancellationToken, progress: &dyn Progress) -> Result<()> {
         //
         // Fetch remote changes
         //
 
         let config = self.repo.config()?;
+        let mut callbacks = self.remote_callbacks(&config);
+        callbacks.transfer_progress(|stats| Self::transfer_progress(Phase::Fetch, &stats, cancel, progress));
+
         let mut fetch_options = git2::FetchOptions::default();
-        fetch_options.remote_callbacks(self.remote_callbacks(&config));
+        fetch_options.remote_callbacks(callbacks);
+        fetch_options.proxy_options(Self::proxy_options(&self.proxy));
 
         self.repo.find_remote(REMOTE_NAME)
-            .and_then(|mut remote| remote.fetch(&[BRANCH_NAME], Some(&mut fetch_options), None))?;
+            .and_then(|mut remote| remote.fetch(&[self.branch_name()], Some(&mut fetch_options), None))
+            .map_err(|error| Self::cancelled_or(cancel, error))?;
 
         let fetch_head = match self.repo.find_reference(FETCH_REF_NAME) {
             Ok(r) => r,
-            _ => return Ok(())  // Pulling an empty repository
+            _ => {
+                trace_debug!("remote repository is empty");
+                return Ok(());
+            }
         };
 
         let fetch_commit = self.repo
@@ -224,6 +380,7 @@ impl GitSyncEngine {
             .merge_analysis(&[&fetch_commit])?;
 
         if merge_analysis.is_up_to_date() {
+            trace_debug!("local repository is up to date");
             return Ok(());
         }
 
@@ -233,7 +390,8 @@ impl GitSyncEngine {
             // is occurred, it is considered to be an error.
             //
 
-            return Err(Error::from_message(REMOTE_CONFLICT));
+            trace_debug!(commit = %fetch_commit.id(), "fast-forward is impossible");
+            return Err(Error::from_message(REMOTE_CONFLICT).with_kind(ErrorKind::SyncConflict));
         }
 
         //
@@ -242,7 +400,7 @@ impl GitSyncEngine {
         // detect pulling into empty repository
         //
 
-        let ref_name = format!("refs/heads/{}", BRANCH_NAME);
+        let ref_name = self.branch_ref();
         match self.repo.find_reference(&ref_name) {
             Ok(mut branch_ref) => {
                 //
@@ -252,6 +410,7 @@ impl GitSyncEngine {
                 let reflog_msg = format!("Fast-forward: Setting {} to {}", 
                     ref_name, fetch_commit.id());
 
+                trace_debug!(commit = %fetch_commit.id(), "fast-forwarding local branch");
                 branch_ref.set_target(fetch_commit.id(), &reflog_msg)?;
                 self.repo.set_head(&ref_name)?;
 
@@ -283,29 +442,31 @@ impl GitSyncEngine {
         Ok(())
     }
 
-    fn push_remote(&self, branch_ref: &str) -> Result<()> {
+    #[cfg_attr(feature = "tracing", tracing::instrument(level = "debug", skip_all))]
+    fn push_remote(&self, branch_ref: &str, progress: &dyn Progress) -> Result<()> {
         let config = self.repo.config()?;
+        let mut callbacks = self.remote_callbacks(&config);
+        callbacks.push_transfer_progress(|done, total, _| progress.report(Phase::Push, done, total));
+
         let mut push_options = git2::PushOptions::default();
-        push_options.remote_callbacks(self.remote_callbacks(&config));
+        push_options.remote_callbacks(callbacks);
+        push_options.proxy_options(Self::proxy_options(&self.proxy));
 
         self.repo.find_remote(REMOTE_NAME)
             .and_then(|mut remote| remote.push(&[branch_ref], Some(&mut push_options)))
             .map_err(Error::from)
     }
 
-    fn commit_files<T, I>(&self, pathspecs: I, message: &str) -> Result<String> 
-    where
-        T: git2::IntoCString,
-        I: Iterator<Item = T>
-    {
+    fn commit_files(&self, pathspecs: &[&str], signature: &git2::Signature<'_>, message: &str) -> Result<String> {
         //
-        // Let's stage our changes
+        // Let's stage our changes (removed files too)
         //
 
         let tree = self.repo
             .index()
             .and_then(|mut index| {
                 index.add_all(pathspecs, git2::IndexAddOption::DEFAULT, None)?;
+                index.update_all(pathspecs, None)?;
                 index.write()?;
                 index.write_tree()
             })?;
@@ -314,54 +475,201 @@ impl GitSyncEngine {
             .find_tree(tree)?;
 
         //
-        // Create commit changes and author
+        // Now let's find out parent commit and perform commit
+        // right into synchronization branch
+        //
+
+        let branch_ref = self.branch_ref();
+        let head = self.repo
+            .refname_to_id(&branch_ref)
+            .and_then(|oid| self.repo.find_commit(oid))
+            .ok();
+
+        let mut parents = Vec::new();
+        if let Some(head) = head.as_ref() {
+            parents.push(head);
+        }
+
+        let commit = self.repo.commit(None, signature, 
+            signature, message, &tree, &parents)?;
+
+        trace_debug!(%commit, "local changes committed");
+
+        //
+        // HEAD follows the branch, so the working tree corresponds to it
+        //
+
+        self.repo.reference(&branch_ref, commit, true, &format!("commit: {}", message))?;
+        self.repo.set_head(&branch_ref)?;
+
+        Ok(branch_ref)
+    }
+
+    fn commit_signature(&self, instance: &str) -> Result<git2::Signature<'static>> {
+        //
+        // Own configuration takes priority over git's one,
+        // synthetic identity is used as a last resort
         //
 
         let mut config = self.repo.config()?;
         let config = config.snapshot()?;
 
-        let name = config.get_str(CFG_NAME)?;
-        let email = config.get_str(CFG_EMAIL)?;
-        let signature = git2::Signature::now(name, email)?;
+        let name = self.commit_name
+            .clone()
+            .or_else(|| config.get_string(CFG_NAME).ok())
+            .unwrap_or_else(|| format!("bdgt {}", instance));
+
+        let email = self.commit_email
+            .clone()
+            .or_else(|| config.get_string(CFG_EMAIL).ok())
+            .unwrap_or_else(|| format!("{}@{}", instance, SYNTHETIC_EMAIL_DOMAIN));
+
+        Ok(git2::Signature::now(&name, &email)?)
+    }
+
+    fn branch_name(&self) -> String {
+        if let Some(branch) = &self.branch {
+            return branch.clone();
+        }
 
         //
-        // Now let's find out parent commit and perform commit
+        // Clone remembers remote's default branch in its HEAD
         //
 
-        let head = self.repo
-            .refname_to_id(REF_NAME)
-            .and_then(|oid| self.repo.find_commit(oid))
+        let remote_head = format!("refs/remotes/{}/HEAD", REMOTE_NAME);
+        let remote_prefix = format!("refs/remotes/{}/", REMOTE_NAME);
+
+        self.repo
+            .find_reference(&remote_head)
+            .ok()
+            .and_then(|reference| reference.symbolic_target().map(str::to_owned))
+            .and_then(|target| target.strip_prefix(&remote_prefix).map(str::to_owned))
+            .unwrap_or_else(|| BRANCH_NAME.to_owned())
+    }
+
+    fn branch_ref(&self) -> String {
+        format!("{}{}", BRANCHES_PREFIX, self.branch_name())
+    }
+
+    fn ensure_branch(&self) -> Result<()> {
+        let branch_ref = self.branch_ref();
+        if self.repo.find_reference(&branch_ref).is_ok() {
+            return Ok(());
+        }
+
+        //
+        // Missing branch starts from remote's one if it is known,
+        // otherwise from the current commit. Repository without 
+        // commits gets the branch with the first commit
+        //
+
+        let remote_branch = format!("refs/remotes/{}/{}", REMOTE_NAME, self.branch_name());
+        let start = self.repo
+            .refname_to_id(&remote_branch)
+            .or_else(|_| self.repo.refname_to_id(REF_NAME))
             .ok();
 
-        let mut parents = Vec::new();
-        if let Some(head) = head.as_ref() {
-            parents.push(head);
+        if let Some(start) = start {
+            trace_debug!(commit = %start, "creating synchronization branch");
+
+            self.repo.reference(&branch_ref, start, false, "Create synchronization branch")?;
+            self.repo.set_head(&branch_ref)?;
+            self.repo.checkout_head(Some(
+                git2::build::CheckoutBuilder::default()
+                    .force()
+            ))?;
         }
 
-        let commit = self.repo.commit(Some(REF_NAME), &signature, 
-            &signature, &message, &tree, &parents)?;
+        Ok(())
+    }
+
+    #[cfg_attr(feature = "tracing", tracing::instrument(level = "debug", skip_all))]
+    fn clone_repo(remote: &str, repo_path: &std::path::Path, cancel: &CancellationToken, 
+        progress: &dyn Progress, proxy: &Proxy) -> Result<git2::Repository> 
+    {
+        let config = git2::Config::open_default()?;
+        let authenticator = auth_git2::GitAuthenticator::default();
+
+        let mut callbacks = git2::RemoteCallbacks::new();
+        callbacks.credentials(authenticator.credentials(&config));
+        callbacks.transfer_progress(|stats| Self::transfer_progress(Phase::Clone, &stats, cancel, progress));
+
+        let mut fetch_options = git2::FetchOptions::new();
+        fetch_options.remote_callbacks(callbacks);
+        fetch_options.proxy_options(Self::proxy_options(proxy));
+
+        let result = git2::build::RepoBuilder::new()
+            .fetch_options(fetch_options)
+            .clone(remote, repo_path);
 
         //
-        // Update branch pointer
+        // Do not leave partially cloned repository behind
         //
 
-        let commit = self.repo.find_commit(commit)?;
-        self.update_branch_pointer(&commit)
+        result.map_err(|error| {
+            let _ = std::fs::remove_dir_all(repo_path);
+            Self::cancelled_or(cancel, error)
+        })
     }
 
-    fn update_branch_pointer(&self, commit: &git2::Commit<'_>) -> Result<String> {
-        let branch = match self.repo.find_branch(BRANCH_NAME, git2::BranchType::Local) {
-            Ok(branch) => branch,
-            _ => self.repo.branch(BRANCH_NAME, commit, false)?
-        };
-        
-        let branch_ref = branch
-            .into_reference()
-            .name()
-            .expect("Branch MUST have name")
-            .to_owned();
+    fn ahead_behind(&self) -> Result<(usize, usize)> {
+        //
+        // Remote state is known as of the latest fetch or push,
+        // network is not used here
+        //
 
-        Ok(branch_ref)
+        let local = self.repo
+            .refname_to_id(&self.branch_ref())
+            .ok();
+
+        let remote = self.repo
+            .refname_to_id(&format!("refs/remotes/{}/{}", REMOTE_NAME, self.branch_name()))
+            .or_else(|_| self.repo.refname_to_id(FETCH_REF_NAME))
+            .ok();
+
+        match (local, remote) {
+            (Some(local), Some(remote)) => Ok(self.repo.graph_ahead_behind(local, remote)?),
+            (Some(local), None) => Ok((self.count_commits(local)?, 0)),
+            (None, Some(remote)) => Ok((0, self.count_commits(remote)?)),
+            (None, None) => Ok((0, 0))
+        }
+    }
+
+    fn count_commits(&self, head: git2::Oid) -> Result<usize> {
+        let mut revwalk = self.repo.revwalk()?;
+        revwalk.push(head)?;
+
+        Ok(revwalk.count())
+    }
+
+    fn transfer_progress(phase: Phase, stats: &git2::Progress<'_>, cancel: &CancellationToken, 
+        progress: &dyn Progress) -> bool 
+    {
+        progress.report(phase, stats.received_objects(), stats.total_objects());
+        !cancel.is_cancelled()
+    }
+
+    fn cancelled_or(cancel: &CancellationToken, error: git2::Error) -> Error {
+        match cancel.check() {
+            Err(cancelled) => cancelled,
+            Ok(_) => Error::from(error)
+        }
+    }
+
+    fn proxy_options(proxy: &Proxy) -> git2::ProxyOptions<'static> {
+        let mut proxy_options = git2::ProxyOptions::new();
+
+        match proxy {
+            Proxy::Auto => {
+                proxy_options.auto();
+            },
+            Proxy::Direct => {},
+            Proxy::Manual { .. } => {
+                proxy_options.url(&proxy.url().unwrap_or_default());
+            }
+        }
+
+        proxy_options
     }
 
     fn remote_callbacks<'a>(&'a self, config: &'a git2::Config) -> git2::RemoteCallbacks {
@@ -378,52 +686,51 @@ impl GitSyncEngine {
 
 
 impl GitSyncEngine {
-    fn read_last_sync<R: std::io::Read>(last_sync: &mut R) -> Result<Timestamp> {
-        let mut buffer = [0; std::mem::size_of::<i64>()];
-        let seconds = match last_sync.read_exact(&mut buffer) {
-            Ok(_) => i64::from_le_bytes(buffer),
-            _ => 0i64
-        };
+}
+
 
-        Timestamp::from_timestamp(seconds, 0)
-            .ok_or(Error::from_message(MALFORMED_LAST_SYNC_TIMESTAMP))
+impl GitSyncEngine {
+    fn sync_repo_path<L: Location>(loc: &L) -> std::path::PathBuf {
+        exchange::sync_folder(loc)
+            .join(SYNC_REPO)
     }
 
-    fn write_last_sync<W: std::io::Write>(last_sync: &mut W, timestamp: &Timestamp) -> Result<()> {
-        let timestamp = timestamp
-            .timestamp()
-            .to_le_bytes();
+    fn remote_of(repo_path: &std::path::Path) -> Option<String> {
+        let repo = git2::Repository::open(repo_path).ok()?;
+        let remote = repo.find_remote(REMOTE_NAME).ok()?;
 
-        last_sync
-            .write_all(&timestamp)
-            .map_err(Error::from)
+        remote.url()
+            .map(str::to_owned)
     }
 
-    fn prepare_for_overwrite<S: std::io::Seek>(s: &mut S) -> Result<()> {
-        s.rewind()
-            .map_err(Error::from)
-    }
-}
+    fn keep_pending<L: Location>(loc: &L, backup_path: &std::path::Path, repo_path: &std::path::Path) -> Result<()> {
+        let pending_path = exchange::pending_path(loc);
+        let files = [TIMESTAMP_FILE, LAST_INSTANCE_FILE, CHANGELOG_FILE];
 
+        //
+        // Changes kept aside by a previous reset are not joined yet,
+        // hence the backup is just a clone, that has nothing new
+        //
 
-impl GitSyncEngine {
-    fn sync_folder<L: Location>(loc: &L) -> std::path::PathBuf {
-        loc.root()
-            .join(SYNC_FORDER)
-    }
+        if pending_path.exists() {
+            return Ok(());
+        }
 
-    fn sync_repo_path<L: Location>(loc: &L) -> std::path::PathBuf {
-        Self::sync_folder(loc)
-            .join(SYNC_REPO)
-    }
+        let backup: Vec<Vec<u8>> = match files.iter().map(|file| std::fs::read(backup_path.join(file))).collect() {
+            Ok(backup) => backup,
+            Err(_) => return Ok(())
+        };
 
-    fn sync_last_sync_path<L: Location>(loc: &L) -> std::path::PathBuf {
-        Self::sync_folder(loc)
-            .join(LAST_SYNC_FILE)
-    }
+        let has_changes = files
+            .iter()
+            .zip(&backup)
+            .any(|(file, content)| std::fs::read(repo_path.join(file)).ok().as_ref() != Some(content));
+
+        if backup.iter().any(Vec::is_empty) || !has_changes {
+            return Ok(());
+        }
 
-    fn syncable_file_path(&self, file: &str) -> std::path::PathBuf {
-        self.repo_path
-            .join(file)
+        trace_debug!("keeping changelog of backup aside");
+        exchange::keep_aside(backup_path, &pending_path)
     }
 }
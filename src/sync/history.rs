@@ -0,0 +1,335 @@
+use std::collections::BTreeMap;
+
+use serde::{Serialize, Deserialize};
+
+use crate::error::{Result, Error, ErrorKind};
+use crate::datetime::Timestamp;
+use crate::trace::trace_debug;
+use super::MALFORMED_INSTANCE_HISTORY;
+
+
+/// Folder with per-instance histories within repository.
+const INSTANCES_FOLDER: &str = "instances";
+
+/// File with state of an instance within its history folder.
+const STATE_FILE: &str = "state";
+
+
+/// State of an instance, that is shared with other ones.
+#[derive(Serialize, Deserialize, Clone, Default)]
+struct InstanceState {
+    /// Sequence number of the latest diff of the instance
+    sequence: u64,
+
+    /// Sequence numbers of the latest diffs of other instances,
+    /// that the instance has merged
+    acknowledged: BTreeMap<String, u64>,
+}
+
+
+/// History of diffs of instances within synchronization repository.
+///
+/// Each instance appends its diffs to its own folder and never touches
+/// folders of other ones, so no diff is overwritten before all instances
+/// receive it. Instances acknowledge merged diffs of other ones, and a
+/// diff is removed once all of them have acknowledged it.
+///
+/// Layout is the following:
+///
+/// ```text
+/// instances/
+///   <instance>/
+///     state                 -- sequence number and acknowledgements
+///     <sequence>-<time>     -- diff of a single synchronization
+/// ```
+pub(crate) struct InstanceHistory {
+    /// Path to folder with histories of all instances
+    root: std::path::PathBuf,
+
+    /// Identifier of the current instance
+    instance: String,
+
+    /// State of the current instance, [`None`] if it is not registered yet
+    state: Option<InstanceState>,
+}
+
+
+impl InstanceHistory {
+    /// Opens history within a repository.
+    ///
+    /// * `repo_path` - path to repository's working tree
+    /// * `instance` - identifier of the current instance
+    pub(crate) fn open(repo_path: &std::path::Path, instance: &str) -> Result<Self> {
+        let root = repo_path.join(INSTANCES_FOLDER);
+        let state = Self::read_state(&root.join(instance))?;
+
+        Ok(InstanceHistory {
+            root,
+            instance: instance.to_owned(),
+            state
+        })
+    }
+
+    /// Returns pathspec of history within repository.
+    pub(crate) fn pathspec() -> &'static str {
+        INSTANCES_FOLDER
+    }
+
+    /// Checks if the current instance is registered in history,
+    /// i.e. it has synchronized since history was introduced.
+    pub(crate) fn is_registered(&self) -> bool {
+        self.state.is_some()
+    }
+
+    /// Registers the current instance. Diffs existing at the moment are
+    /// acknowledged, since a new instance receives their changes from
+    /// the full changelog.
+    pub(crate) fn register(&mut self) -> Result<()> {
+        let mut state = InstanceState::default();
+
+        for instance in self.other_instances()? {
+            if let Some(sequence) = self.diffs_of(&instance)?.last().map(|(sequence, _)| *sequence) {
+                state.acknowledged.insert(instance, sequence);
+            }
+        }
+
+        trace_debug!(instance = %self.instance, "instance registered in history");
+        self.state = Some(state);
+
+        Ok(())
+    }
+
+    /// Returns diffs of other instances, that the current one has not
+    /// acknowledged yet: identifier of an instance, sequence number
+    /// and path of each diff. Diffs of an instance are ordered.
+    pub(crate) fn unacknowledged(&self) -> Result<Vec<(String, u64, std::path::PathBuf)>> {
+        let mut diffs = Vec::new();
+
+        for instance in self.other_instances()? {
+            let acknowledged = self.acknowledged(&instance);
+
+            for (sequence, path) in self.diffs_of(&instance)? {
+                if acknowledged < sequence {
+                    diffs.push((instance.clone(), sequence, path));
+                }
+            }
+        }
+
+        Ok(diffs)
+    }
+
+    /// Acknowledges a merged diff of another instance.
+    ///
+    /// * `instance` - identifier of the instance, that made the diff
+    /// * `sequence` - sequence number of the diff
+    pub(crate) fn acknowledge(&mut self, instance: &str, sequence: u64) {
+        if let Some(state) = self.state.as_mut() {
+            state.acknowledged.insert(instance.to_owned(), sequence);
+        }
+    }
+
+    /// Allocates path for a new diff of the current instance.
+    ///
+    /// * `timestamp` - time of synchronization, that makes the diff
+    pub(crate) fn next_diff_path(&mut self, timestamp: &Timestamp) -> Result<std::path::PathBuf> {
+        let state = self.state
+            .get_or_insert_with(InstanceState::default);
+
+        state.sequence += 1;
+
+        let folder = self.root.join(&self.instance);
+        std::fs::create_dir_all(&folder)?;
+
+        Ok(folder.join(format!("{:010}-{}", state.sequence, timestamp.format("%Y%m%dT%H%M%SZ"))))
+    }
+
+    /// Removes diffs of the current instance, that are acknowledged
+    /// by all other registered instances.
+    pub(crate) fn prune(&self) -> Result<()> {
+        let mut acknowledged_by_all = self.state
+            .as_ref()
+            .map_or(0, |state| state.sequence);
+
+        for instance in self.other_instances()? {
+            if let Some(state) = Self::read_state(&self.root.join(&instance))? {
+                let acknowledged = state.acknowledged
+                    .get(&self.instance)
+                    .copied()
+                    .unwrap_or_default();
+
+                acknowledged_by_all = acknowledged_by_all.min(acknowledged);
+            }
+        }
+
+        for (sequence, path) in self.diffs_of(&self.instance)? {
+            if sequence <= acknowledged_by_all {
+                std::fs::remove_file(path)?;
+            }
+        }
+
+        Ok(())
+    }
+
+    /// Restores history of the current instance kept aside by reset
+    /// of repository, if it is ahead of the current one.
+    ///
+    /// * `backup_path` - folder, that history was copied to (see [`InstanceHistory::copy`])
+    pub(crate) fn restore(&mut self, backup_path: &std::path::Path) -> Result<()> {
+        let backup_folder = backup_path
+            .join(INSTANCES_FOLDER)
+            .join(&self.instance);
+
+        let backup_state = match Self::read_state(&backup_folder)? {
+            Some(backup_state) => backup_state,
+            None => return Ok(())
+        };
+
+        if self.state.as_ref().is_some_and(|state| backup_state.sequence <= state.sequence) {
+            return Ok(());
+        }
+
+        //
+        // Diffs of the backup were not pushed, and its acknowledgements
+        // were merged into local data already, so the backup wins
+        //
+
+        let folder = self.root.join(&self.instance);
+        if folder.exists() {
+            std::fs::remove_dir_all(&folder)?;
+        }
+
+        copy_files(&backup_folder, &folder)?;
+        self.state = Some(backup_state);
+
+        Ok(())
+    }
+
+    /// Copies histories of all instances from a repository.
+    ///
+    /// * `repo_path` - path to repository's working tree
+    /// * `destination` - folder to copy history to
+    pub(crate) fn copy(repo_path: &std::path::Path, destination: &std::path::Path) -> Result<()> {
+        let entries = match std::fs::read_dir(repo_path.join(INSTANCES_FOLDER)) {
+            Ok(entries) => entries,
+            Err(error) if error.kind() == std::io::ErrorKind::NotFound => return Ok(()),
+            Err(error) => return Err(error.into())
+        };
+
+        for entry in entries {
+            let entry = entry?;
+            if entry.file_type()?.is_dir() {
+                copy_files(&entry.path(), &destination.join(INSTANCES_FOLDER).join(entry.file_name()))?;
+            }
+        }
+
+        Ok(())
+    }
+
+    /// Writes state of the current instance.
+    pub(crate) fn save(&self) -> Result<()> {
+        let state = match &self.state {
+            Some(state) => state,
+            None => return Ok(())
+        };
+
+        let folder = self.root.join(&self.instance);
+        std::fs::create_dir_all(&folder)?;
+
+        std::fs::write(folder.join(STATE_FILE), toml::to_string(state)?)
+            .map_err(Error::from)
+    }
+}
+
+
+impl InstanceHistory {
+    fn read_state(folder: &std::path::Path) -> Result<Option<InstanceState>> {
+        let state = match std::fs::read_to_string(folder.join(STATE_FILE)) {
+            Ok(state) => state,
+            Err(error) if error.kind() == std::io::ErrorKind::NotFound => return Ok(None),
+            Err(error) => return Err(error.into())
+        };
+
+        toml::from_str(&state)
+            .map(Some)
+            .map_err(|error| Error::from_message_with_extra(MALFORMED_INSTANCE_HISTORY, folder.display().to_string())
+                .with_kind(ErrorKind::Corruption).with_source(error))
+    }
+
+    fn acknowledged(&self, instance: &str) -> u64 {
+        self.state
+            .as_ref()
+            .and_then(|state| state.acknowledged.get(instance))
+            .copied()
+            .unwrap_or_default()
+    }
+
+    fn other_instances(&self) -> Result<Vec<String>> {
+        let entries = match std::fs::read_dir(&self.root) {
+            Ok(entries) => entries,
+            Err(error) if error.kind() == std::io::ErrorKind::NotFound => return Ok(Vec::new()),
+            Err(error) => return Err(error.into())
+        };
+
+        let mut instances = Vec::new();
+        for entry in entries {
+            let entry = entry?;
+            if !entry.file_type()?.is_dir() {
+                continue;
+            }
+
+            let instance = entry.file_name()
+                .to_string_lossy()
+                .into_owned();
+
+            if instance != self.instance {
+                instances.push(instance);
+            }
+        }
+
+        instances.sort();
+        Ok(instances)
+    }
+
+    fn diffs_of(&self, instance: &str) -> Result<Vec<(u64, std::path::PathBuf)>> {
+        let entries = match std::fs::read_dir(self.root.join(instance)) {
+            Ok(entries) => entries,
+            Err(error) if error.kind() == std::io::ErrorKind::NotFound => return Ok(Vec::new()),
+            Err(error) => return Err(error.into())
+        };
+
+        //
+        // Diffs are named "<sequence>-<time>", other files
+        // (e.g. state) are skipped
+        //
+
+        let mut diffs = Vec::new();
+        for entry in entries {
+            let entry = entry?;
+            let sequence = entry.file_name()
+                .to_str()
+                .and_then(|name| name.split_once('-'))
+                .and_then(|(sequence, _)| sequence.parse().ok());
+
+            if let Some(sequence) = sequence {
+                diffs.push((sequence, entry.path()));
+            }
+        }
+
+        diffs.sort_by_key(|(sequence, _)| *sequence);
+        Ok(diffs)
+    }
+}
+
+
+fn copy_files(from: &std::path::Path, to: &std::path::Path) -> Result<()> {
+    std::fs::create_dir_all(to)?;
+
+    for entry in std::fs::read_dir(from)? {
+        let entry = entry?;
+        if entry.file_type()?.is_file() {
+            std::fs::copy(entry.path(), to.join(entry.file_name()))?;
+        }
+    }
+
+    Ok(())
+}
@@ -0,0 +1,129 @@
+use std::cell::RefCell;
+
+use crate::error::{Result, Error, ErrorKind};
+use crate::location::Location;
+use crate::trace::trace_debug;
+use super::HOOK_FAILED;
+
+
+/// Folder with executable hooks within configuration directory.
+const HOOKS_FOLDER: &str = "hooks";
+
+/// Name of an executable, that runs before synchronization.
+const PRE_SYNC_HOOK: &str = "pre-sync";
+
+/// Name of an executable, that runs after synchronization.
+const POST_SYNC_HOOK: &str = "post-sync";
+
+
+/// Callback invoked before synchronization. An error returned
+/// from it prevents synchronization.
+pub type PreSyncHook = Box<dyn Fn() -> Result<()>>;
+
+/// Callback invoked after synchronization with its result.
+pub type PostSyncHook = Box<dyn Fn(&Result<()>)>;
+
+
+/// Hooks, that run around synchronization.
+pub(crate) struct SyncHooks {
+    /// Hooks, that run before synchronization
+    pre_sync: RefCell<Vec<PreSyncHook>>,
+
+    /// Hooks, that run after synchronization
+    post_sync: RefCell<Vec<PostSyncHook>>,
+}
+
+
+impl SyncHooks {
+    /// Creates an empty set of hooks.
+    pub(crate) fn new() -> Self {
+        SyncHooks {
+            pre_sync: RefCell::new(Vec::new()),
+            post_sync: RefCell::new(Vec::new())
+        }
+    }
+
+    /// Registers a hook, that runs before synchronization.
+    ///
+    /// * `hook` - hook to register
+    pub(crate) fn add_pre_sync(&self, hook: PreSyncHook) {
+        self.pre_sync
+            .borrow_mut()
+            .push(hook);
+    }
+
+    /// Registers a hook, that runs after synchronization.
+    ///
+    /// * `hook` - hook to register
+    pub(crate) fn add_post_sync(&self, hook: PostSyncHook) {
+        self.post_sync
+            .borrow_mut()
+            .push(hook);
+    }
+
+    /// Registers executables from location's `hooks` folder: `pre-sync`
+    /// and `post-sync`. The latter receives "succeeded" or "failed" as
+    /// its argument. Missing executables are skipped.
+    ///
+    /// * `loc` - location to look hooks up in
+    pub(crate) fn add_executables<L: Location>(&self, loc: &L) {
+        let hooks_folder = loc.config_dir()
+            .join(HOOKS_FOLDER);
+
+        let pre_sync = hooks_folder.join(PRE_SYNC_HOOK);
+        if pre_sync.is_file() {
+            self.add_pre_sync(Box::new(move || run_executable(&pre_sync, None)));
+        }
+
+        let post_sync = hooks_folder.join(POST_SYNC_HOOK);
+        if post_sync.is_file() {
+            self.add_post_sync(Box::new(move |result| {
+                let outcome = match result {
+                    Ok(_) => "succeeded",
+                    Err(_) => "failed"
+                };
+
+                //
+                // Synchronization is over already, so a failed
+                // hook cannot affect it anymore
+                //
+
+                if run_executable(&post_sync, Some(outcome)).is_err() {
+                    trace_debug!("post-sync hook failed");
+                }
+            }));
+        }
+    }
+
+    /// Runs hooks before synchronization, stops at the first failed one.
+    pub(crate) fn run_pre_sync(&self) -> Result<()> {
+        for hook in self.pre_sync.borrow().iter() {
+            hook()?;
+        }
+
+        Ok(())
+    }
+
+    /// Runs hooks after synchronization.
+    ///
+    /// * `result` - result of synchronization
+    pub(crate) fn run_post_sync(&self, result: &Result<()>) {
+        for hook in self.post_sync.borrow().iter() {
+            hook(result);
+        }
+    }
+}
+
+
+fn run_executable(path: &std::path::Path, argument: Option<&str>) -> Result<()> {
+    let mut command = std::process::Command::new(path);
+    command.args(argument);
+
+    let status = command.status()?;
+    if !status.success() {
+        return Err(Error::from_message_with_extra(HOOK_FAILED, path.display().to_string())
+            .with_kind(ErrorKind::Other));
+    }
+
+    Ok(())
+}
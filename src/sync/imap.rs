@@ -0,0 +1,641 @@
+use std::cell::{Cell, RefCell};
+use std::collections::HashMap;
+use std::io::{BufRead, Write};
+
+use base64::Engine;
+
+use crate::error::{Result, Error, ErrorKind, Context};
+use crate::trace::trace_debug;
+use crate::redact::redact_url;
+use super::transport::SyncTransport;
+use super::transport_engine::TransportSyncEngine;
+use super::{MALFORMED_IMAP_URL, IMAP_COMMAND_FAILED, IMAP_CONNECTION_CLOSED, MALFORMED_IMAP_MESSAGE, REMOTE_LOCKED};
+
+
+/// Scheme of IMAP remote URLs.
+const IMAP_SCHEME: &str = "imaps://";
+
+/// Default port of IMAP servers with implicit TLS.
+const DEFAULT_PORT: u16 = 993;
+
+/// Timeout of network operations.
+const TIMEOUT: std::time::Duration = std::time::Duration::from_secs(30);
+
+/// Prefix of subjects of messages with synchronization files.
+const SUBJECT_PREFIX: &str = "bdgt-sync ";
+
+/// Path of messages, that lock the folder.
+const LOCK_PATH: &str = ".lock";
+
+/// Age of a lock in seconds, after which it is considered abandoned
+/// (e.g. an instance has crashed during synchronization).
+const STALE_LOCK_AGE: u64 = 15 * 60;
+
+/// Length of lines of base64-encoded attachments.
+const LINE_LENGTH: usize = 76;
+
+
+/// Synchronization engine, that stores files as messages in an IMAP folder.
+pub type ImapSyncEngine = TransportSyncEngine<ImapTransport>;
+
+
+/// Experimental transport, that stores synchronization files as
+/// attachments of messages in a dedicated IMAP folder. It suits users,
+/// who have nothing but an email account.
+///
+/// Each file is a message with the file's path in subject. Writing
+/// a file appends a new message and removes the old one, reading
+/// fetches the latest message for a path. Folder is locked by
+/// appending a lock message: the earliest live one holds the lock.
+/// Only servers with implicit TLS are supported.
+pub struct ImapTransport {
+    /// Host name of the server
+    host: String,
+
+    /// Port of the server
+    port: u16,
+
+    /// Name of the user
+    username: String,
+
+    /// Password of the user
+    password: String,
+
+    /// Folder with synchronization messages
+    folder: String,
+
+    /// Established session
+    session: RefCell<Option<ImapSession>>,
+
+    /// Identifiers of messages for each path, ordered from the oldest one
+    index: RefCell<HashMap<String, Vec<u32>>>,
+
+    /// Identifier of the lock message of this transport
+    lock_uid: Cell<Option<u32>>,
+}
+
+
+impl ImapTransport {
+    /// Creates a transport for an IMAP folder. URL has the following form:
+    /// `imaps://user@host[:port]/folder`. User may be an email address
+    /// itself. Folder is created if it is missing, its name must be ASCII.
+    ///
+    /// * `url` - URL of the folder
+    /// * `password` - password of the user (often an application password)
+    pub fn new(url: &str, password: &str) -> Result<Self> {
+        let malformed = || Error::from_message_with_extra(MALFORMED_IMAP_URL, redact_url(url))
+            .with_kind(ErrorKind::InvalidInput);
+
+        let (authority, folder) = url
+            .strip_prefix(IMAP_SCHEME)
+            .and_then(|rest| rest.split_once('/'))
+            .ok_or_else(malformed)?;
+
+        let (username, address) = authority
+            .rsplit_once('@')
+            .ok_or_else(malformed)?;
+
+        let (host, port) = match address.rsplit_once(':') {
+            Some((host, port)) => (host, port.parse().map_err(|_| malformed())?),
+            None => (address, DEFAULT_PORT)
+        };
+
+        let folder = folder.trim_end_matches('/');
+        let is_valid = |value: &str| !value.is_empty() && value.chars().all(|c| c.is_ascii() && !c.is_ascii_control());
+
+        if !is_valid(host) || !is_valid(username) || !is_valid(folder) {
+            return Err(malformed());
+        }
+
+        Ok(ImapTransport {
+            host: host.to_owned(),
+            port,
+            username: username.to_owned(),
+            password: password.to_owned(),
+            folder: folder.to_owned(),
+            session: RefCell::new(None),
+            index: RefCell::new(HashMap::new()),
+            lock_uid: Cell::new(None)
+        })
+    }
+}
+
+
+impl SyncTransport for ImapTransport {
+    fn remote(&self) -> String {
+        let port = match self.port {
+            DEFAULT_PORT => String::new(),
+            port => format!(":{}", port)
+        };
+
+        format!("{}{}@{}{}/{}", IMAP_SCHEME, self.username, self.host, port, self.folder)
+    }
+
+    fn list(&self) -> Result<Vec<String>> {
+        self.with_session(|session| self.refresh_index(session))?;
+
+        //
+        // Hidden paths are service ones, e.g. lock
+        //
+
+        Ok(self.index
+            .borrow()
+            .keys()
+            .filter(|path| !path.starts_with('.'))
+            .cloned()
+            .collect())
+    }
+
+    fn read(&self, path: &str) -> Result<Vec<u8>> {
+        self.with_session(|session| {
+            if !self.index.borrow().contains_key(path) {
+                self.refresh_index(session)?;
+            }
+
+            let uid = self.index
+                .borrow()
+                .get(path)
+                .and_then(|uids| uids.last().copied())
+                .ok_or_else(|| Error::from_message_with_extra(MALFORMED_IMAP_MESSAGE, path)
+                    .with_kind(ErrorKind::NotFound))?;
+
+            session.fetch_attachment(uid)
+                .with_context(|| format!("fetching {}", path))
+        })
+    }
+
+    fn write(&self, path: &str, content: &[u8]) -> Result<()> {
+        self.with_session(|session| {
+            //
+            // Old messages are removed after the new one is appended,
+            // so the file is never missing. Identifier of the new
+            // message is unknown until the index is refreshed
+            //
+
+            session.append(&self.folder, &compose_message(&self.username, path, content))?;
+
+            let old_uids = self.index
+                .borrow_mut()
+                .remove(path)
+                .unwrap_or_default();
+
+            session.delete(&old_uids)
+        })
+    }
+
+    fn remove(&self, path: &str) -> Result<()> {
+        self.with_session(|session| {
+            let uids = self.index
+                .borrow_mut()
+                .remove(path)
+                .unwrap_or_default();
+
+            session.delete(&uids)
+        })
+    }
+
+    fn lock(&self, owner: &str) -> Result<()> {
+        let token = format!("{} {:016x}", owner, rand::random::<u64>());
+        let now = unix_now();
+
+        self.with_session(|session| {
+            session.append(&self.folder, &compose_message(&self.username, LOCK_PATH, format!("{}\n{}", token, now).as_bytes()))?;
+            self.refresh_index(session)?;
+
+            let lock_uids = self.index
+                .borrow()
+                .get(LOCK_PATH)
+                .cloned()
+                .unwrap_or_default();
+
+            //
+            // Lock messages are ordered by identifiers, that servers
+            // assign in ascending order, so the earliest live one wins.
+            // Locks of crashed instances are removed after a while
+            //
+
+            let mut holder = None;
+            let mut own_uid = None;
+
+            for uid in lock_uids {
+                let lock = session.fetch_attachment(uid)?;
+                let (lock_token, locked_at) = parse_lock(&lock);
+
+                if lock_token == token {
+                    own_uid = Some(uid);
+                    break;
+                }
+
+                if locked_at.saturating_add(STALE_LOCK_AGE) < now {
+                    trace_debug!(uid, "removing stale lock of remote");
+                    session.delete(&[uid])?;
+                }
+                else if holder.is_none() {
+                    holder = lock_token.rsplit_once(' ').map(|(owner, _)| owner.to_owned());
+                }
+            }
+
+            let own_uid = own_uid
+                .ok_or_else(|| Error::from_message_with_extra(MALFORMED_IMAP_MESSAGE, LOCK_PATH)
+                    .with_kind(ErrorKind::SyncFailure))?;
+
+            if let Some(holder) = holder {
+                session.delete(&[own_uid])?;
+
+                return Err(Error::from_message_with_extra(REMOTE_LOCKED, holder)
+                    .with_kind(ErrorKind::Locked));
+            }
+
+            self.lock_uid.set(Some(own_uid));
+            Ok(())
+        })
+    }
+
+    fn unlock(&self) -> Result<()> {
+        match self.lock_uid.take() {
+            Some(uid) => self.with_session(|session| session.delete(&[uid])),
+            None => Ok(())
+        }
+    }
+}
+
+
+impl ImapTransport {
+    fn with_session<R, F>(&self, f: F) -> Result<R>
+    where
+        F: FnOnce(&mut ImapSession) -> Result<R>
+    {
+        let mut session = self.session.borrow_mut();
+        if session.is_none() {
+            *session = Some(self.connect()?);
+        }
+
+        let result = f(session
+            .as_mut()
+            .expect("session is established above"));
+
+        //
+        // Session is dropped after network failures, so that
+        // the next operation reconnects
+        //
+
+        if result.as_ref().is_err_and(|error| error.kind() == ErrorKind::Network) {
+            *session = None;
+        }
+
+        result
+    }
+
+    #[cfg_attr(feature = "tracing", tracing::instrument(level = "debug", skip_all, fields(host = %self.host)))]
+    fn connect(&self) -> Result<ImapSession> {
+        let mut session = ImapSession::connect(&self.host, self.port)
+            .with_context(|| format!("connecting to {}:{}", self.host, self.port))?;
+
+        session.command(&format!("LOGIN {} {}", quote(&self.username), quote(&self.password)))
+            .map_err(|error| error.with_kind(ErrorKind::Network))
+            .context("logging in")?;
+
+        //
+        // Folder is created on the first use. Creation fails if
+        // another instance has just created it, which is fine
+        //
+
+        if session.command(&format!("SELECT {}", quote(&self.folder))).is_err() {
+            let _ = session.command(&format!("CREATE {}", quote(&self.folder)));
+            session.command(&format!("SELECT {}", quote(&self.folder)))
+                .with_context(|| format!("selecting {}", self.folder))?;
+        }
+
+        trace_debug!("connected to IMAP server");
+        Ok(session)
+    }
+
+    fn refresh_index(&self, session: &mut ImapSession) -> Result<()> {
+        let mut index: HashMap<String, Vec<u32>> = HashMap::new();
+
+        for (uid, path) in session.fetch_paths()? {
+            index.entry(path)
+                .or_default()
+                .push(uid);
+        }
+
+        for uids in index.values_mut() {
+            uids.sort_unstable();
+        }
+
+        *self.index.borrow_mut() = index;
+        Ok(())
+    }
+}
+
+
+/// Untagged response of a server.
+struct Response {
+    /// Text of the response, literals are replaced with their sizes
+    text: String,
+
+    /// Literals of the response
+    literals: Vec<Vec<u8>>,
+}
+
+
+/// Minimal IMAP client, that supports commands required by transport only.
+struct ImapSession {
+    /// Stream to the server
+    stream: std::io::BufReader<native_tls::TlsStream<std::net::TcpStream>>,
+
+    /// Number of the last sent command
+    tag: u32,
+}
+
+
+impl ImapSession {
+    fn connect(host: &str, port: u16) -> Result<Self> {
+        let network_error = |error: std::io::Error| Error::from(error).with_kind(ErrorKind::Network);
+
+        let stream = std::net::TcpStream::connect((host, port))
+            .map_err(network_error)?;
+
+        stream.set_read_timeout(Some(TIMEOUT)).map_err(network_error)?;
+        stream.set_write_timeout(Some(TIMEOUT)).map_err(network_error)?;
+
+        let stream = native_tls::TlsConnector::new()?
+            .connect(host, stream)
+            .map_err(|error| Error::from_message(error.to_string()).with_kind(ErrorKind::Network))?;
+
+        let mut session = ImapSession {
+            stream: std::io::BufReader::new(stream),
+            tag: 0
+        };
+
+        //
+        // Server greets a client with an untagged response
+        //
+
+        session.next_response()?;
+        Ok(session)
+    }
+
+    fn command(&mut self, command: &str) -> Result<Vec<Response>> {
+        let tag = self.next_tag();
+        self.send(format!("{} {}\r\n", tag, command).as_bytes())?;
+        self.complete(&tag)
+    }
+
+    fn append(&mut self, folder: &str, message: &[u8]) -> Result<()> {
+        let tag = self.next_tag();
+        self.send(format!("{} APPEND {} (\\Seen) {{{}}}\r\n", tag, quote(folder), message.len()).as_bytes())?;
+
+        //
+        // Message is sent once server asks to continue
+        //
+
+        loop {
+            let response = self.next_response()?;
+            if response.text.starts_with('+') {
+                break;
+            }
+
+            if response.text.starts_with(&tag) {
+                return Err(Error::from_message_with_extra(IMAP_COMMAND_FAILED, response.text)
+                    .with_kind(ErrorKind::SyncFailure));
+            }
+        }
+
+        self.send(message)?;
+        self.send(b"\r\n")?;
+        self.complete(&tag)?;
+
+        Ok(())
+    }
+
+    fn fetch_paths(&mut self) -> Result<Vec<(u32, String)>> {
+        let uids = self.command("UID SEARCH ALL")?
+            .iter()
+            .filter_map(|response| response.text.strip_prefix("* SEARCH"))
+            .flat_map(str::split_whitespace)
+            .filter(|uid| uid.parse::<u32>().is_ok())
+            .map(str::to_owned)
+            .collect::<Vec<_>>();
+
+        if uids.is_empty() {
+            return Ok(Vec::new());
+        }
+
+        let responses = self.command(&format!("UID FETCH {} (UID BODY.PEEK[HEADER.FIELDS (SUBJECT)])", uids.join(",")))?;
+
+        //
+        // Messages, that are not made by transport, are skipped
+        //
+
+        Ok(responses
+            .iter()
+            .filter_map(|response| {
+                let uid = fetched_uid(&response.text)?;
+                let path = response.literals
+                    .first()
+                    .and_then(|header| subject_path(header))?;
+
+                Some((uid, path))
+            })
+            .collect())
+    }
+
+    fn fetch_attachment(&mut self, uid: u32) -> Result<Vec<u8>> {
+        let body = self.command(&format!("UID FETCH {} (UID BODY.PEEK[TEXT])", uid))?
+            .into_iter()
+            .filter(|response| fetched_uid(&response.text) == Some(uid))
+            .find_map(|response| response.literals.into_iter().next());
+
+        let malformed = || Error::from_message_with_extra(MALFORMED_IMAP_MESSAGE, uid.to_string())
+            .with_kind(ErrorKind::Corruption);
+
+        let body: Vec<u8> = body
+            .ok_or_else(malformed)?
+            .into_iter()
+            .filter(|byte| !byte.is_ascii_whitespace())
+            .collect();
+
+        base64::engine::general_purpose::STANDARD
+            .decode(body)
+            .map_err(|error| malformed().with_source(error))
+    }
+
+    fn delete(&mut self, uids: &[u32]) -> Result<()> {
+        if uids.is_empty() {
+            return Ok(());
+        }
+
+        let uids: Vec<String> = uids
+            .iter()
+            .map(u32::to_string)
+            .collect();
+
+        self.command(&format!("UID STORE {} +FLAGS.SILENT (\\Deleted)", uids.join(",")))?;
+        self.command("EXPUNGE")?;
+
+        Ok(())
+    }
+}
+
+
+impl ImapSession {
+    fn next_tag(&mut self) -> String {
+        self.tag += 1;
+        format!("A{:04}", self.tag)
+    }
+
+    fn send(&mut self, data: &[u8]) -> Result<()> {
+        self.stream
+            .get_mut()
+            .write_all(data)
+            .map_err(|error| Error::from(error).with_kind(ErrorKind::Network))
+    }
+
+    fn complete(&mut self, tag: &str) -> Result<Vec<Response>> {
+        let mut responses = Vec::new();
+
+        loop {
+            let response = self.next_response()?;
+
+            let status = match response.text.strip_prefix(tag) {
+                Some(status) => status.trim_start(),
+                None => {
+                    responses.push(response);
+                    continue;
+                }
+            };
+
+            return match status.starts_with("OK") {
+                true => Ok(responses),
+                false => Err(Error::from_message_with_extra(IMAP_COMMAND_FAILED, status)
+                    .with_kind(ErrorKind::SyncFailure))
+            };
+        }
+    }
+
+    fn next_response(&mut self) -> Result<Response> {
+        let mut response = Response {
+            text: String::new(),
+            literals: Vec::new()
+        };
+
+        //
+        // Line, that ends with "{size}", is followed by a literal
+        // of that size and then by continuation of the line
+        //
+
+        loop {
+            let line = self.read_line()?;
+            response.text.push_str(&line);
+
+            let size = line
+                .strip_suffix('}')
+                .and_then(|line| line.rsplit_once('{'))
+                .and_then(|(_, size)| size.parse::<usize>().ok());
+
+            let size = match size {
+                Some(size) => size,
+                None => return Ok(response)
+            };
+
+            let mut literal = vec![0; size];
+            std::io::Read::read_exact(&mut self.stream, &mut literal)
+                .map_err(|error| Error::from(error).with_kind(ErrorKind::Network))?;
+
+            response.literals.push(literal);
+        }
+    }
+
+    fn read_line(&mut self) -> Result<String> {
+        let mut line = Vec::new();
+        let read = self.stream
+            .read_until(b'\n', &mut line)
+            .map_err(|error| Error::from(error).with_kind(ErrorKind::Network))?;
+
+        if 0 == read {
+            return Err(Error::from_message(IMAP_CONNECTION_CLOSED)
+                .with_kind(ErrorKind::Network));
+        }
+
+        Ok(String::from_utf8_lossy(&line)
+            .trim_end_matches(['\r', '\n'])
+            .to_owned())
+    }
+}
+
+
+fn compose_message(username: &str, path: &str, content: &[u8]) -> Vec<u8> {
+    let file_name = path
+        .rsplit('/')
+        .next()
+        .unwrap_or(path);
+
+    let encoded = base64::engine::general_purpose::STANDARD.encode(content);
+
+    let mut message = format!(
+        "From: <{}>\r\n\
+         Date: {}\r\n\
+         Subject: {}{}\r\n\
+         MIME-Version: 1.0\r\n\
+         Content-Type: application/octet-stream\r\n\
+         Content-Transfer-Encoding: base64\r\n\
+         Content-Disposition: attachment; filename=\"{}\"\r\n\
+         \r\n",
+        username, chrono::Utc::now().to_rfc2822(), SUBJECT_PREFIX, path, file_name);
+
+    //
+    // Lines of messages are limited, so attachment is split
+    //
+
+    for line in encoded.as_bytes().chunks(LINE_LENGTH) {
+        message.push_str(&String::from_utf8_lossy(line));
+        message.push_str("\r\n");
+    }
+
+    message.into_bytes()
+}
+
+
+fn subject_path(header: &[u8]) -> Option<String> {
+    String::from_utf8_lossy(header)
+        .lines()
+        .find_map(|line| line
+            .split_once(':')
+            .filter(|(name, _)| name.eq_ignore_ascii_case("subject"))
+            .and_then(|(_, subject)| subject.trim().strip_prefix(SUBJECT_PREFIX.trim_end()))
+            .map(|path| path.trim().to_owned()))
+        .filter(|path| !path.is_empty())
+}
+
+
+fn fetched_uid(text: &str) -> Option<u32> {
+    let (_, rest) = text.split_once("UID ")?;
+
+    rest.split(|c: char| !c.is_ascii_digit())
+        .next()?
+        .parse()
+        .ok()
+}
+
+
+fn parse_lock(lock: &[u8]) -> (String, u64) {
+    let lock = String::from_utf8_lossy(lock);
+    let (token, locked_at) = lock
+        .split_once('\n')
+        .unwrap_or((&lock, ""));
+
+    (token.to_owned(), locked_at.trim().parse().unwrap_or_default())
+}
+
+
+fn quote(value: &str) -> String {
+    format!("\"{}\"", value.replace('\\', "\\\\").replace('"', "\\\""))
+}
+
+
+fn unix_now() -> u64 {
+    std::time::SystemTime::now()
+        .duration_since(std::time::UNIX_EPOCH)
+        .map_or(0, |elapsed| elapsed.as_secs())
+}
@@ -0,0 +1,551 @@
+use std::cell::RefCell;
+use std::io::{Read, Write};
+use std::time::{Duration, Instant};
+
+use serde::{Serialize, Deserialize};
+use serde::de::DeserializeOwned;
+
+use crate::error::{Result, Error, ErrorKind, Context};
+use crate::crypto::{CryptoBuffer, Kdf, Prng, SymmetricCipher};
+use crate::location::Location;
+use crate::cancel::CancellationToken;
+use crate::trace::trace_debug;
+use super::transport::SyncTransport;
+use super::transport_engine::TransportSyncEngine;
+use super::folder::FolderTransport;
+use super::exchange;
+use super::{PEER_NOT_FOUND, PEER_HANDSHAKE_FAILED, MALFORMED_PEER_MESSAGE};
+
+
+/// Type of mDNS service, that shares are advertised with.
+const SERVICE_TYPE: &str = "_bdgt-sync._tcp.local.";
+
+/// Property of mDNS service with identifier of an instance, that shares files.
+const INSTANCE_PROPERTY: &str = "instance";
+
+/// Folder with shared synchronization files.
+const SHARE_FOLDER: &str = "lan-share";
+
+/// Size of random challenges, that session keys are derived from.
+const CHALLENGE_SIZE: usize = 16;
+
+/// Size of nonce and authentication tag, that each message is prefixed
+/// and suffixed with by cipher.
+const CIPHER_OVERHEAD: usize = 12 + 16;
+
+/// Maximal size of a message.
+const MAX_MESSAGE_SIZE: usize = 1 << 30;
+
+/// Timeout of network operations.
+const TIMEOUT: Duration = Duration::from_secs(30);
+
+/// Time, that a share waits for the next request of a peer. Peer merges
+/// changes between requests, which may take a while.
+const IDLE_TIMEOUT: Duration = Duration::from_secs(15 * 60);
+
+/// Interval of checking for cancellation while waiting for peers.
+const ACCEPT_INTERVAL: Duration = Duration::from_millis(100);
+
+
+/// Synchronization engine, that exchanges files with a device in local network.
+pub type LanSyncEngine = TransportSyncEngine<LanTransport>;
+
+
+/// Request of a peer to a share.
+#[derive(Serialize, Deserialize)]
+enum Request {
+    Hello,
+    List,
+    Read(String),
+    Write(String, Vec<u8>),
+    Remove(String),
+    Lock(String),
+    Unlock,
+}
+
+
+/// Response of a share to a peer.
+#[derive(Serialize, Deserialize)]
+enum Response {
+    Hello,
+    Paths(Vec<String>),
+    Content(Vec<u8>),
+    Done,
+    Failed(Error),
+}
+
+
+/// Share of synchronization files with devices in local network.
+///
+/// One device of a household serves its folder of synchronization files,
+/// others discover it with mDNS and synchronize through [`LanTransport`].
+/// The device itself synchronizes through [`LanShare::local_transport`].
+/// Devices are paired with a secret, that all of them know: each connection
+/// is encrypted with keys derived from it, so devices without the secret
+/// can neither read, nor alter files.
+pub struct LanShare {
+    /// Shared folder
+    transport: FolderTransport,
+
+    /// Pairing secret
+    secret: CryptoBuffer,
+
+    /// Listener of peers' connections
+    listener: std::net::TcpListener,
+
+    /// Daemon, that advertises the share
+    daemon: mdns_sd::ServiceDaemon,
+}
+
+
+impl LanShare {
+    /// Opens a share of a location and advertises it in local network.
+    ///
+    /// * `loc` - storage location provider
+    /// * `instance` - identifier of the current instance
+    /// * `secret` - pairing secret of devices
+    pub fn open<L: Location>(loc: &L, instance: &str, secret: &[u8]) -> Result<Self> {
+        let listener = std::net::TcpListener::bind(("0.0.0.0", 0))
+            .map_err(|error| Error::from(error).with_kind(ErrorKind::Network))?;
+
+        listener.set_nonblocking(true)?;
+        let port = listener.local_addr()?.port();
+
+        let daemon = mdns_sd::ServiceDaemon::new()?;
+        let service = mdns_sd::ServiceInfo::new(SERVICE_TYPE, instance, &format!("bdgt-{}.local.", instance),
+            "", port, &[(INSTANCE_PROPERTY, instance)][..])?;
+
+        daemon.register(service.enable_addr_auto())
+            .context("advertising share")?;
+
+        trace_debug!(port, "share is advertised");
+
+        Ok(LanShare {
+            transport: FolderTransport::new(Self::share_path(loc)),
+            secret: CryptoBuffer::from(secret),
+            listener,
+            daemon
+        })
+    }
+
+    /// Returns transport, that the sharing device synchronizes through.
+    pub fn local_transport(&self) -> FolderTransport {
+        self.transport.clone()
+    }
+
+    /// Returns port, that the share listens on.
+    pub fn port(&self) -> Result<u16> {
+        Ok(self.listener.local_addr()?.port())
+    }
+
+    /// Serves peers until cancellation is requested. Peers are served one
+        /// by one, failures of peers (e.g. a wrong pairing secret) are skipped.
+    /// A peer is disconnected if it is idle for too long.
+    ///
+    /// * `cancel` - token, that stops serving
+    pub fn serve(&self, cancel: &CancellationToken) -> Result<()> {
+        while !cancel.is_cancelled() {
+            match self.listener.accept() {
+                Ok((stream, _peer)) => {
+                    trace_debug!(%_peer, "peer connected");
+
+                    if let Err(_error) = self.serve_peer(stream) {
+                        trace_debug!(%_error, "peer is disconnected with an error");
+                    }
+                },
+                Err(error) if error.kind() == std::io::ErrorKind::WouldBlock => std::thread::sleep(ACCEPT_INTERVAL),
+                Err(error) => return Err(Error::from(error).with_kind(ErrorKind::Network))
+            }
+        }
+
+        Ok(())
+    }
+}
+
+
+impl Drop for LanShare {
+    fn drop(&mut self) {
+        let _ = self.daemon.shutdown();
+    }
+}
+
+
+impl LanShare {
+    fn share_path<L: Location>(loc: &L) -> std::path::PathBuf {
+        exchange::sync_folder(loc)
+            .join(SHARE_FOLDER)
+    }
+
+    fn serve_peer(&self, stream: std::net::TcpStream) -> Result<()> {
+        stream.set_nonblocking(false)?;
+
+        let mut channel = LanChannel::accept(stream, self.secret.as_bytes())?;
+        channel.stream.set_read_timeout(Some(IDLE_TIMEOUT))?;
+        let mut locked = false;
+
+        let result = loop {
+            //
+            // Peer closes connection once it is done
+            //
+
+            let request = match channel.receive() {
+                Ok(request) => request,
+                Err(error) => break Err(error)
+            };
+
+            let response = match request {
+                Request::Hello => Ok(Response::Hello),
+                Request::List => self.transport.list().map(Response::Paths),
+                Request::Read(path) => self.transport.read(&path).map(Response::Content),
+                Request::Write(path, content) => self.transport.write(&path, &content).map(|_| Response::Done),
+                Request::Remove(path) => self.transport.remove(&path).map(|_| Response::Done),
+                Request::Lock(owner) => self.transport.lock(&owner)
+                    .inspect(|_| locked = true)
+                    .map(|_| Response::Done),
+                Request::Unlock => self.transport.unlock()
+                    .inspect(|_| locked = false)
+                    .map(|_| Response::Done)
+            };
+
+            if let Err(error) = channel.send(&response.unwrap_or_else(Response::Failed)) {
+                break Err(error);
+            }
+        };
+
+        //
+        // Lock of a peer, that disconnected abruptly, is released,
+        // so that others do not wait for it to become stale
+        //
+
+        if locked {
+            self.transport.unlock()?;
+        }
+
+        match result {
+            Err(error) if error.kind() == ErrorKind::Io => Ok(()),
+            result => result
+        }
+    }
+}
+
+
+/// Transport, that exchanges files with a [`LanShare`] of another
+/// device in local network.
+pub struct LanTransport {
+    /// Address of the share
+    address: std::net::SocketAddr,
+
+    /// Pairing secret
+    secret: CryptoBuffer,
+
+    /// Established channel
+    channel: RefCell<Option<LanChannel>>,
+}
+
+
+impl LanTransport {
+    /// Creates a transport for a share with a known address.
+    ///
+    /// * `address` - address of the share
+    /// * `secret` - pairing secret of devices
+    pub fn new(address: std::net::SocketAddr, secret: &[u8]) -> Self {
+        LanTransport {
+            address,
+            secret: CryptoBuffer::from(secret),
+            channel: RefCell::new(None)
+        }
+    }
+
+    /// Discovers a share in local network with mDNS.
+    ///
+    /// * `instance` - identifier of the current instance, its own share is skipped
+    /// * `secret` - pairing secret of devices
+    /// * `timeout` - time to wait for a share to respond
+    pub fn discover(instance: &str, secret: &[u8], timeout: Duration) -> Result<Self> {
+        let daemon = mdns_sd::ServiceDaemon::new()?;
+        let events = daemon.browse(SERVICE_TYPE)?;
+        let deadline = Instant::now() + timeout;
+
+        let address = loop {
+            let remaining = deadline.saturating_duration_since(Instant::now());
+            let service = match events.recv_timeout(remaining) {
+                Ok(mdns_sd::ServiceEvent::ServiceResolved(service)) => service,
+                Ok(_) => continue,
+                Err(_) => break None
+            };
+
+            if service.get_property_val_str(INSTANCE_PROPERTY) == Some(instance) {
+                continue;
+            }
+
+            //
+            // IPv6 link-local addresses require scope to connect,
+            // hence they are skipped, and IPv4 ones are preferred
+            //
+
+            let ip = service.get_addresses()
+                .iter()
+                .filter(|ip| !is_link_local_v6(ip))
+                .min_by_key(|ip| ip.is_ipv6())
+                .copied();
+
+            if let Some(ip) = ip {
+                break Some(std::net::SocketAddr::new(ip, service.get_port()));
+            }
+        };
+
+        let _ = daemon.shutdown();
+
+        let address = address
+            .ok_or_else(|| Error::from_message(PEER_NOT_FOUND).with_kind(ErrorKind::NotFound))?;
+
+        trace_debug!(%address, "share discovered");
+        Ok(Self::new(address, secret))
+    }
+}
+
+
+impl SyncTransport for LanTransport {
+    fn remote(&self) -> String {
+        format!("lan://{}", self.address)
+    }
+
+    fn list(&self) -> Result<Vec<String>> {
+        match self.request(Request::List)? {
+            Response::Paths(paths) => Ok(paths),
+            _ => Err(malformed_message())
+        }
+    }
+
+    fn read(&self, path: &str) -> Result<Vec<u8>> {
+        match self.request(Request::Read(path.to_owned()))? {
+            Response::Content(content) => Ok(content),
+            _ => Err(malformed_message())
+        }
+    }
+
+    fn write(&self, path: &str, content: &[u8]) -> Result<()> {
+        self.request_done(Request::Write(path.to_owned(), content.to_vec()))
+    }
+
+    fn remove(&self, path: &str) -> Result<()> {
+        self.request_done(Request::Remove(path.to_owned()))
+    }
+
+    fn lock(&self, owner: &str) -> Result<()> {
+        self.request_done(Request::Lock(owner.to_owned()))
+    }
+
+    fn unlock(&self) -> Result<()> {
+        let result = self.request_done(Request::Unlock);
+
+        //
+        // Share serves peers one by one, so connection
+        // is closed as soon as synchronization is over
+        //
+
+        *self.channel.borrow_mut() = None;
+        result
+    }
+}
+
+
+impl LanTransport {
+    fn request(&self, request: Request) -> Result<Response> {
+        let mut channel = self.channel.borrow_mut();
+        if channel.is_none() {
+            *channel = Some(self.connect()?);
+        }
+
+        let result = channel
+            .as_mut()
+            .expect("channel is established above")
+            .exchange(&request);
+
+        //
+        // Channel is dropped after failures, since it may be
+        // out of sequence, the next request reconnects
+        //
+
+        match result {
+            Ok(Response::Failed(error)) => Err(error),
+            Ok(response) => Ok(response),
+            Err(error) => {
+                *channel = None;
+                Err(error)
+            }
+        }
+    }
+
+    fn request_done(&self, request: Request) -> Result<()> {
+        match self.request(request)? {
+            Response::Done => Ok(()),
+            _ => Err(malformed_message())
+        }
+    }
+
+    #[cfg_attr(feature = "tracing", tracing::instrument(level = "debug", skip_all, fields(address = %self.address)))]
+    fn connect(&self) -> Result<LanChannel> {
+        let stream = std::net::TcpStream::connect_timeout(&self.address, TIMEOUT)
+            .map_err(|error| Error::from(error).with_kind(ErrorKind::Network))
+            .with_context(|| format!("connecting to {}", self.address))?;
+
+        let mut channel = LanChannel::connect(stream, self.secret.as_bytes())?;
+
+        //
+        // Share drops connection, if it cannot decrypt a message,
+        // so a mismatch of secrets is detected on the first one
+        //
+
+        match channel.exchange(&Request::Hello) {
+            Ok(Response::Hello) => Ok(channel),
+            Ok(_) => Err(Error::from_message(PEER_HANDSHAKE_FAILED).with_kind(ErrorKind::CryptoFailure)),
+            Err(error) => Err(Error::from_message(PEER_HANDSHAKE_FAILED)
+                .with_kind(ErrorKind::CryptoFailure)
+                .with_source(error))
+        }
+    }
+}
+
+
+/// Encrypted channel between a share and a peer.
+///
+/// Each side sends a random challenge, keys of both directions are
+/// derived from the pairing secret and challenges. Messages are
+/// numbered, so they cannot be replayed or reordered.
+struct LanChannel {
+    /// Connection
+    stream: std::net::TcpStream,
+
+    /// Cipher of outgoing messages
+    sender: SymmetricCipher,
+
+    /// Cipher of incoming messages
+    receiver: SymmetricCipher,
+
+    /// Number of sent messages
+    sent: u64,
+
+    /// Number of received messages
+    received: u64,
+}
+
+
+impl LanChannel {
+    fn connect(stream: std::net::TcpStream, secret: &[u8]) -> Result<Self> {
+        Self::prepare(&stream)?;
+
+        let mut challenges = [0u8; 2 * CHALLENGE_SIZE];
+        Prng::new().generate(&mut challenges[..CHALLENGE_SIZE])?;
+
+        (&stream).write_all(&challenges[..CHALLENGE_SIZE])?;
+        (&stream).read_exact(&mut challenges[CHALLENGE_SIZE..])?;
+
+        let (sender, receiver) = Self::derive_ciphers(secret, &challenges)?;
+        Ok(Self::new(stream, sender, receiver))
+    }
+
+    fn accept(stream: std::net::TcpStream, secret: &[u8]) -> Result<Self> {
+        Self::prepare(&stream)?;
+
+        let mut challenges = [0u8; 2 * CHALLENGE_SIZE];
+        Prng::new().generate(&mut challenges[CHALLENGE_SIZE..])?;
+
+        (&stream).read_exact(&mut challenges[..CHALLENGE_SIZE])?;
+        (&stream).write_all(&challenges[CHALLENGE_SIZE..])?;
+
+        let (receiver, sender) = Self::derive_ciphers(secret, &challenges)?;
+        Ok(Self::new(stream, sender, receiver))
+    }
+
+    fn exchange(&mut self, request: &Request) -> Result<Response> {
+        self.send(request)?;
+        self.receive()
+    }
+
+    fn send<T: Serialize>(&mut self, message: &T) -> Result<()> {
+        let mut plaintext = self.sent.to_le_bytes().to_vec();
+        plaintext.extend_from_slice(&flexbuffers::to_vec(message)?);
+
+        let ciphertext = self.sender.encrypt(&plaintext)?;
+        let size = u32::try_from(ciphertext.as_bytes().len())
+            .map_err(|_| malformed_message())?;
+
+        self.stream.write_all(&size.to_le_bytes())?;
+        self.stream.write_all(ciphertext.as_bytes())?;
+
+        self.sent += 1;
+        Ok(())
+    }
+
+    fn receive<T: DeserializeOwned>(&mut self) -> Result<T> {
+        let mut size = [0u8; 4];
+        self.stream.read_exact(&mut size)?;
+
+        let size = u32::from_le_bytes(size) as usize;
+        if !(CIPHER_OVERHEAD + 8..=MAX_MESSAGE_SIZE).contains(&size) {
+            return Err(malformed_message());
+        }
+
+        let mut ciphertext = vec![0; size];
+        self.stream.read_exact(&mut ciphertext)?;
+
+        let plaintext = self.receiver.decrypt(&ciphertext)?;
+        let (number, message) = plaintext.as_bytes()
+            .split_at(8);
+
+        if number != self.received.to_le_bytes() {
+            return Err(malformed_message());
+        }
+
+        self.received += 1;
+        flexbuffers::from_slice(message)
+            .map_err(Error::from)
+    }
+}
+
+
+impl LanChannel {
+    fn new(stream: std::net::TcpStream, sender: SymmetricCipher, receiver: SymmetricCipher) -> Self {
+        LanChannel {
+            stream,
+            sender,
+            receiver,
+            sent: 0,
+            received: 0
+        }
+    }
+
+    fn prepare(stream: &std::net::TcpStream) -> Result<()> {
+        stream.set_read_timeout(Some(TIMEOUT))?;
+        stream.set_write_timeout(Some(TIMEOUT))?;
+        stream.set_nodelay(true)?;
+
+        Ok(())
+    }
+
+    /// Derives ciphers of messages from the connecting side
+    /// and from the accepting one.
+    fn derive_ciphers(secret: &[u8], challenges: &[u8]) -> Result<(SymmetricCipher, SymmetricCipher)> {
+        let key_size = SymmetricCipher::key_size();
+        let keys = Kdf::derive_key(secret, challenges, 2 * key_size)?;
+        let (connecting, accepting) = keys.as_bytes()
+            .split_at(key_size);
+
+        Ok((SymmetricCipher::new(connecting)?, SymmetricCipher::new(accepting)?))
+    }
+}
+
+
+fn is_link_local_v6(ip: &std::net::IpAddr) -> bool {
+    match ip {
+        std::net::IpAddr::V6(ip) => 0xfe80 == ip.segments()[0] & 0xffc0,
+        std::net::IpAddr::V4(_) => false
+    }
+}
+
+
+fn malformed_message() -> Error {
+    Error::from_message(MALFORMED_PEER_MESSAGE)
+        .with_kind(ErrorKind::Corruption)
+}
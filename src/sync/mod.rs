@@ -1,19 +1,152 @@
-mod git_engine;
 mod syncable;
 mod engine;
+mod offline_engine;
+mod hooks;
+mod status;
+mod proxy;
+mod scheduler;
+
+#[cfg(feature = "native")]
+mod git_engine;
+
+#[cfg(feature = "native")]
+mod history;
+
+#[cfg(feature = "native")]
+mod exchange;
+
+#[cfg(feature = "native")]
+mod transport;
+
+#[cfg(feature = "native")]
+mod transport_engine;
+
+#[cfg(feature = "native")]
+mod folder;
+
+#[cfg(feature = "sftp")]
+mod sftp;
+
+#[cfg(feature = "imap")]
+mod imap;
 
+#[cfg(feature = "lan")]
+mod lan;
+
+pub use self::offline_engine::OfflineSyncEngine;
+pub use self::hooks::{PreSyncHook, PostSyncHook};
+pub use self::status::SyncStatus;
+pub use self::proxy::Proxy;
+pub use self::scheduler::{SyncScheduler, SyncPolicy};
+
+#[cfg(feature = "native")]
 pub use self::git_engine::GitSyncEngine;
 
+#[cfg(feature = "native")]
+pub use self::transport::SyncTransport;
+
+#[cfg(feature = "native")]
+pub use self::transport_engine::TransportSyncEngine;
+
+#[cfg(feature = "native")]
+pub use self::folder::FolderTransport;
+
+#[cfg(feature = "sftp")]
+pub use self::sftp::{SftpTransport, SftpSyncEngine};
+
+#[cfg(feature = "imap")]
+pub use self::imap::{ImapTransport, ImapSyncEngine};
+
+#[cfg(feature = "lan")]
+pub use self::lan::{LanShare, LanTransport, LanSyncEngine};
+
 pub(crate) use self::engine::SyncEngine;
 pub(crate) use self::syncable::Syncable;
+pub(crate) use self::hooks::SyncHooks;
 
 
 /// Error message for case of adding of new remote, 
 /// when another one already exists.
+#[cfg(feature = "native")]
 const REMOTE_ALREADY_EXIST: &str = "Remote is already associated with repository";
 
 /// Error shown in case of malformed timestamp file.
+#[cfg(feature = "native")]
 const MALFORMED_LAST_SYNC_TIMESTAMP: &str = "Last synchronization timestamp file is malformed";
 
 /// Merge with remote changes is required, which is not intended to happen.
+#[cfg(feature = "native")]
 const REMOTE_CONFLICT: &str = "Conflicting changes are made in local and remote repositories";
+
+/// State of an instance in history of diffs is malformed.
+#[cfg(feature = "native")]
+const MALFORMED_INSTANCE_HISTORY: &str = "Instance state in synchronization history is malformed";
+
+/// Remote of a transport cannot be changed by synchronization engine.
+#[cfg(feature = "native")]
+const REMOTE_IS_FIXED: &str = "Remote is defined by synchronization transport";
+
+/// Remote file has a path, that leads out of synchronization folder.
+#[cfg(feature = "native")]
+const MALFORMED_REMOTE_PATH: &str = "Remote synchronization file has malformed path";
+
+/// Remote file with bundled changelog is truncated or malformed.
+#[cfg(feature = "native")]
+const MALFORMED_REMOTE_BUNDLE: &str = "Remote changelog bundle is malformed";
+
+/// SFTP remote URL is malformed.
+#[cfg(feature = "sftp")]
+const MALFORMED_SFTP_URL: &str = "SFTP remote URL is malformed";
+
+/// Host key of SSH server is not present in known hosts.
+#[cfg(feature = "sftp")]
+const UNKNOWN_HOST_KEY: &str = "Host key of SSH server is unknown";
+
+/// Host key of SSH server differs from the known one.
+#[cfg(feature = "sftp")]
+const HOST_KEY_MISMATCH: &str = "Host key of SSH server does not match the known one";
+
+/// SSH server rejected user's credentials.
+#[cfg(feature = "sftp")]
+const SFTP_AUTH_FAILED: &str = "Authentication on SSH server failed";
+
+/// Another instance is synchronizing through the remote.
+#[cfg(feature = "native")]
+const REMOTE_LOCKED: &str = "Remote is locked by another instance";
+
+/// IMAP remote URL is malformed.
+#[cfg(feature = "imap")]
+const MALFORMED_IMAP_URL: &str = "IMAP remote URL is malformed";
+
+/// IMAP server rejected a command.
+#[cfg(feature = "imap")]
+const IMAP_COMMAND_FAILED: &str = "IMAP server rejected command";
+
+/// IMAP server closed connection unexpectedly.
+#[cfg(feature = "imap")]
+const IMAP_CONNECTION_CLOSED: &str = "IMAP server closed connection";
+
+/// Message with a synchronization file is missing or malformed.
+#[cfg(feature = "imap")]
+const MALFORMED_IMAP_MESSAGE: &str = "Synchronization message is missing or malformed";
+
+/// No share of synchronization files responded in local network.
+#[cfg(feature = "lan")]
+const PEER_NOT_FOUND: &str = "No device shares synchronization files in local network";
+
+/// Device in local network rejected connection, e.g. due to a different pairing secret.
+#[cfg(feature = "lan")]
+const PEER_HANDSHAKE_FAILED: &str = "Pairing with device failed, check pairing secret";
+
+/// Message from a device in local network is malformed or out of sequence.
+#[cfg(feature = "lan")]
+const MALFORMED_PEER_MESSAGE: &str = "Message from device is malformed";
+
+/// Operation requires a remote, but synchronization is not supported.
+const SYNC_NOT_SUPPORTED: &str = "Synchronization is not supported by the engine";
+
+/// Executable hook exited with an error.
+const HOOK_FAILED: &str = "Synchronization hook failed";
+
+/// Proxy URL is malformed.
+const MALFORMED_PROXY: &str = "Proxy URL is malformed";
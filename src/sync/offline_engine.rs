@@ -0,0 +1,57 @@
+use crate::error::{Result, Error, ErrorKind};
+use crate::cancel::CancellationToken;
+use crate::progress::Progress;
+use crate::redact::redact_url;
+use super::engine::SyncEngine;
+use super::syncable::Syncable;
+use super::status::SyncStatus;
+use super::SYNC_NOT_SUPPORTED;
+
+
+/// Synchronization engine for standalone instances.
+///
+/// It has no remote and no other instances to exchange changes with,
+/// hence synchronization is a no-op. Attempts to associate a remote
+/// fail. Suitable for environments without git, e.g. browsers.
+#[derive(Default)]
+pub struct OfflineSyncEngine;
+
+
+impl OfflineSyncEngine {
+    /// Creates an engine.
+    pub fn new() -> Self {
+        OfflineSyncEngine
+    }
+}
+
+
+impl SyncEngine for OfflineSyncEngine {
+    fn perform_sync<S: Syncable>(&self, _current_instance: &S::InstanceId, _syncable: &S, _context: &S::Context, 
+        cancel: &CancellationToken, _progress: &dyn Progress) -> Result<()> 
+    {
+        //
+        // There is nothing to exchange changes with
+        //
+
+        cancel.check()
+    }
+
+    fn status<S: Syncable>(&self, syncable: &S) -> Result<SyncStatus> {
+        Ok(SyncStatus {
+            pending_changes: syncable.pending_changes()?,
+            ..Default::default()
+        })
+    }
+
+    fn add_remote(&self, remote: &str) -> Result<()> {
+        Err(Error::from_message_with_extra(SYNC_NOT_SUPPORTED, redact_url(remote)).with_kind(ErrorKind::SyncFailure))
+    }
+
+    fn remove_remote(&self) -> Result<()> {
+        Err(Error::from_message(SYNC_NOT_SUPPORTED).with_kind(ErrorKind::SyncFailure))
+    }
+
+    fn change_remote(&self, remote: &str) -> Result<()> {
+        Err(Error::from_message_with_extra(SYNC_NOT_SUPPORTED, redact_url(remote)).with_kind(ErrorKind::SyncFailure))
+    }
+}
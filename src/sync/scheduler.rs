@@ -0,0 +1,206 @@
+use std::time::Duration;
+
+use rand::Rng;
+use serde::{Serialize, Deserialize};
+
+use crate::error::Result;
+use crate::location::{Location, restrict_file};
+use crate::datetime::{Clock, Timestamp};
+use crate::trace::trace_debug;
+use super::status::SyncStatus;
+
+
+/// File with persistent state of scheduler within data directory.
+const SCHEDULE_FILE: &str = "sync-schedule";
+
+/// Maximal power of two, that minimal interval is multiplied by
+/// after consecutive failures.
+const MAX_BACKOFF_EXPONENT: u32 = 6;
+
+
+/// Policy of automatic synchronization.
+#[derive(Clone, Copy, PartialEq, Eq, Debug)]
+pub struct SyncPolicy {
+    /// Minimal interval between synchronization attempts. It grows
+    /// exponentially after consecutive failures
+    pub min_interval: Duration,
+
+    /// Interval of synchronization without local changes (to receive
+    /// remote ones), [`None`] disables periodic synchronization
+    pub periodic_interval: Option<Duration>,
+
+    /// Maximal random delay added to periodic interval, so that
+    /// instances do not contact a remote simultaneously
+    pub jitter: Duration,
+
+    /// If synchronization is due as soon as local changes appear
+    pub on_changes: bool,
+}
+
+
+impl Default for SyncPolicy {
+    fn default() -> Self {
+        SyncPolicy {
+            min_interval: Duration::from_secs(5 * 60),
+            periodic_interval: Some(Duration::from_secs(60 * 60)),
+            jitter: Duration::from_secs(5 * 60),
+            on_changes: true
+        }
+    }
+}
+
+
+/// Persistent state of scheduler.
+#[derive(Serialize, Deserialize, Clone, Default)]
+struct ScheduleState {
+    /// Time of the latest synchronization attempt
+    last_attempt: Option<Timestamp>,
+
+    /// Time of the latest successful synchronization
+    last_success: Option<Timestamp>,
+
+    /// Number of consecutive failed attempts
+    failures: u32,
+
+    /// Random delay of the next periodic synchronization in seconds
+    jitter: u64,
+}
+
+
+/// Helper, that decides when to synchronize automatically.
+///
+/// Scheduler does not synchronize by itself: a frontend asks it with
+/// [`SyncScheduler::should_sync_now`] (e.g. on a timer or after an
+/// edit), performs synchronization if it is due and reports result
+/// with [`SyncScheduler::record`]. State is persisted in location's
+/// data directory, so restarts of a frontend do not reset intervals.
+pub struct SyncScheduler {
+    /// Policy of synchronization
+    policy: SyncPolicy,
+
+    /// Path to state file
+    path: std::path::PathBuf,
+
+    /// Current state
+    state: ScheduleState,
+}
+
+
+impl SyncScheduler {
+    /// Opens scheduler of a location. Missing or malformed state
+    /// is replaced with initial one.
+    ///
+    /// * `loc` - storage location provider
+    /// * `policy` - policy of synchronization
+    pub fn open<L: Location>(loc: &L, policy: SyncPolicy) -> Self {
+        let path = loc.data_dir()
+            .join(SCHEDULE_FILE);
+
+        let state = std::fs::read_to_string(&path)
+            .ok()
+            .and_then(|state| toml::from_str(&state)
+                .inspect_err(|_| { trace_debug!("synchronization schedule is malformed, starting over"); })
+                .ok())
+            .unwrap_or_default();
+
+        SyncScheduler {
+            policy,
+            path,
+            state
+        }
+    }
+
+    /// Obtain policy of synchronization.
+    pub fn policy(&self) -> &SyncPolicy {
+        &self.policy
+    }
+
+    /// Returns time, when synchronization is due, or [`None`] if it
+    /// is not needed (e.g. there is no remote or no local changes
+    /// with periodic synchronization disabled).
+    ///
+    /// * `status` - current synchronization status (see [`crate::core::Budget::sync_status`])
+    pub fn next_sync(&self, status: &SyncStatus) -> Option<Timestamp> {
+        if !status.has_remote() {
+            return None;
+        }
+
+        //
+        // Attempts are never made more often than minimal interval
+        // allows, the latter doubles with each consecutive failure
+        //
+
+        let backoff = 1u32 << self.state.failures.min(MAX_BACKOFF_EXPONENT);
+        let earliest = self.state.last_attempt
+            .map(|last_attempt| after(&last_attempt, self.policy.min_interval.checked_mul(backoff).unwrap_or(Duration::MAX)));
+
+        let on_changes = (self.policy.on_changes && 0 != status.pending_changes)
+            .then(|| earliest.unwrap_or(Timestamp::MIN_UTC));
+
+        let periodic = self.policy.periodic_interval.map(|interval| {
+            let jitter = Duration::from_secs(self.state.jitter);
+
+            self.state.last_success
+                .or(status.last_sync)
+                .map(|last_success| after(&last_success, interval.saturating_add(jitter)))
+                .into_iter()
+                .chain(earliest)
+                .max()
+                .unwrap_or(Timestamp::MIN_UTC)
+        });
+
+        on_changes
+            .into_iter()
+            .chain(periodic)
+            .min()
+    }
+
+    /// Checks if synchronization is due now.
+    ///
+    /// * `status` - current synchronization status (see [`crate::core::Budget::sync_status`])
+    pub fn should_sync_now(&self, status: &SyncStatus) -> bool {
+        self.next_sync(status)
+            .is_some_and(|next_sync| next_sync <= Clock::now())
+    }
+
+    /// Records result of a synchronization attempt and saves state.
+    ///
+    /// * `result` - result of synchronization
+    pub fn record(&mut self, result: &Result<()>) -> Result<()> {
+        let now = Clock::now();
+
+        self.state.last_attempt = Some(now);
+        match result {
+            Ok(_) => {
+                self.state.last_success = Some(now);
+                self.state.failures = 0;
+            },
+            Err(_) => {
+                self.state.failures = self.state.failures.saturating_add(1);
+            }
+        }
+
+        self.state.jitter = match self.policy.jitter.as_secs() {
+            0 => 0,
+            jitter => rand::thread_rng().gen_range(0..=jitter)
+        };
+
+        self.save()
+    }
+}
+
+
+impl SyncScheduler {
+    fn save(&self) -> Result<()> {
+        std::fs::write(&self.path, toml::to_string(&self.state)?)?;
+        restrict_file(&self.path)
+    }
+}
+
+
+fn after(timestamp: &Timestamp, interval: Duration) -> Timestamp {
+    chrono::Duration::from_std(interval)
+        .ok()
+        .and_then(|interval| timestamp.checked_add_signed(interval))
+        .unwrap_or(Timestamp::MAX_UTC)
+}
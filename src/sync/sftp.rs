@@ -0,0 +1,430 @@
+use std::cell::RefCell;
+use std::io::{Read, Write};
+
+use crate::error::{Result, Error, ErrorKind, Context};
+use crate::trace::trace_debug;
+use crate::redact::redact_url;
+use super::transport::SyncTransport;
+use super::transport_engine::TransportSyncEngine;
+use super::{MALFORMED_SFTP_URL, UNKNOWN_HOST_KEY, HOST_KEY_MISMATCH, SFTP_AUTH_FAILED, REMOTE_LOCKED};
+
+
+/// Scheme of SFTP remote URLs.
+const SFTP_SCHEME: &str = "sftp://";
+
+/// Default port of SSH servers.
+const DEFAULT_PORT: u16 = 22;
+
+/// Timeout of network operations in milliseconds.
+const TIMEOUT_MS: u32 = 30_000;
+
+/// Lock file within remote folder.
+const LOCK_FILE: &str = ".lock";
+
+/// Age of a lock in seconds, after which it is considered abandoned
+/// (e.g. an instance has crashed during synchronization).
+const STALE_LOCK_AGE: u64 = 15 * 60;
+
+/// Mode of created folders.
+const FOLDER_MODE: i32 = 0o700;
+
+/// Mode of created files.
+const FILE_MODE: i32 = 0o600;
+
+/// SFTP status code for missing files.
+const SFTP_NO_SUCH_FILE: i32 = 2;
+
+
+/// Synchronization engine, that stores files in a folder on an SSH server.
+pub type SftpSyncEngine = TransportSyncEngine<SftpTransport>;
+
+
+/// Transport, that stores synchronization files in a folder on an
+/// SSH server using SFTP. It suits users with a shell account, but
+/// without git hosting.
+///
+/// Host key of the server is verified against known hosts file, unknown
+/// hosts are rejected. User is authenticated with a private key or, if
+/// no key is set, with SSH agent. Connection is established lazily and
+/// reused for subsequent operations.
+pub struct SftpTransport {
+    /// Host name of the server
+    host: String,
+
+    /// Port of the server
+    port: u16,
+
+    /// Name of remote user
+    username: String,
+
+    /// Remote folder with synchronization files
+    root: std::path::PathBuf,
+
+    /// Path to private key and its passphrase, SSH agent is used if absent
+    private_key: Option<(std::path::PathBuf, Option<String>)>,
+
+    /// Path to known hosts file
+    known_hosts: Option<std::path::PathBuf>,
+
+    /// Established connection
+    connection: RefCell<Option<(ssh2::Session, ssh2::Sftp)>>,
+}
+
+
+impl SftpTransport {
+    /// Creates a transport for a remote folder. URL has the following form:
+    /// `sftp://[user@]host[:port]/path`. Path is absolute, use `/~/path` for
+    /// a path relative to user's home folder. If user is omitted, the local
+    /// one is used.
+    ///
+    /// * `url` - URL of remote folder
+    pub fn new(url: &str) -> Result<Self> {
+        let malformed = || Error::from_message_with_extra(MALFORMED_SFTP_URL, redact_url(url))
+            .with_kind(ErrorKind::InvalidInput);
+
+        let (authority, path) = url
+            .strip_prefix(SFTP_SCHEME)
+            .and_then(|rest| rest.split_once('/'))
+            .ok_or_else(malformed)?;
+
+        let (username, address) = match authority.rsplit_once('@') {
+            Some((username, address)) => (username.to_owned(), address),
+            None => (local_username().ok_or_else(malformed)?, authority)
+        };
+
+        let (host, port) = match address.rsplit_once(':') {
+            Some((host, port)) => (host, port.parse().map_err(|_| malformed())?),
+            None => (address, DEFAULT_PORT)
+        };
+
+        let root = match path.strip_prefix('~') {
+            Some(relative) => relative.trim_start_matches('/').to_owned(),
+            None => format!("/{}", path)
+        };
+
+        if host.is_empty() || username.is_empty() || root.is_empty() {
+            return Err(malformed());
+        }
+
+        Ok(SftpTransport {
+            host: host.to_owned(),
+            port,
+            username,
+            root: root.into(),
+            private_key: None,
+            known_hosts: dirs::home_dir().map(|home| home.join(".ssh").join("known_hosts")),
+            connection: RefCell::new(None)
+        })
+    }
+
+    /// Sets private key to authenticate with instead of SSH agent.
+    ///
+    /// * `path` - path to private key file
+    /// * `passphrase` - passphrase of the key (if it is encrypted)
+    pub fn with_private_key<P: AsRef<std::path::Path>>(mut self, path: P, passphrase: Option<&str>) -> Self {
+        self.private_key = Some((path.as_ref().to_owned(), passphrase.map(str::to_owned)));
+        self
+    }
+
+    /// Sets known hosts file to verify server with instead of `~/.ssh/known_hosts`.
+    ///
+    /// * `path` - path to known hosts file in OpenSSH format
+    pub fn with_known_hosts<P: AsRef<std::path::Path>>(mut self, path: P) -> Self {
+        self.known_hosts = Some(path.as_ref().to_owned());
+        self
+    }
+}
+
+
+impl SyncTransport for SftpTransport {
+    fn remote(&self) -> String {
+        let port = match self.port {
+            DEFAULT_PORT => String::new(),
+            port => format!(":{}", port)
+        };
+
+        let path = self.root.to_string_lossy();
+        let path = match path.starts_with('/') {
+            true => path.into_owned(),
+            false => format!("/~/{}", path)
+        };
+
+        format!("{}{}@{}{}{}", SFTP_SCHEME, self.username, self.host, port, path)
+    }
+
+    fn list(&self) -> Result<Vec<String>> {
+        self.with_sftp(|sftp| {
+            let mut files = Vec::new();
+
+            match sftp.stat(&self.root) {
+                Ok(_) => list_files(sftp, &self.root, "", &mut files)?,
+                Err(error) if is_not_found(&error) => (),
+                Err(error) => return Err(error.into())
+            }
+
+            Ok(files)
+        })
+    }
+
+    fn read(&self, path: &str) -> Result<Vec<u8>> {
+        self.with_sftp(|sftp| {
+            let mut content = Vec::new();
+            sftp.open(self.remote_path(path))?
+                .read_to_end(&mut content)?;
+
+            Ok(content)
+        })
+    }
+
+    fn write(&self, path: &str, content: &[u8]) -> Result<()> {
+        self.with_sftp(|sftp| {
+            let remote_path = self.remote_path(path);
+            if let Some(parent) = remote_path.parent() {
+                create_folders(sftp, parent)?;
+            }
+
+            //
+            // Content is written to a temporary file first, so that
+            // other instances never read partially written files
+            //
+
+            let file_name = remote_path.file_name()
+                .unwrap_or_default()
+                .to_string_lossy();
+
+            let temp_path = remote_path.with_file_name(format!(".{}.tmp", file_name));
+            let flags = ssh2::OpenFlags::WRITE | ssh2::OpenFlags::CREATE | ssh2::OpenFlags::TRUNCATE;
+
+            sftp.open_mode(&temp_path, flags, FILE_MODE, ssh2::OpenType::File)?
+                .write_all(content)?;
+
+            //
+            // SFTP servers usually do not replace existing files on
+            // rename, hence the old file is removed in such case
+            //
+
+            if sftp.rename(&temp_path, &remote_path, None).is_err() {
+                match sftp.unlink(&remote_path) {
+                    Err(error) if !is_not_found(&error) => return Err(error.into()),
+                    _ => ()
+                }
+
+                sftp.rename(&temp_path, &remote_path, None)?;
+            }
+
+            Ok(())
+        })
+    }
+
+    fn remove(&self, path: &str) -> Result<()> {
+        self.with_sftp(|sftp| sftp
+            .unlink(&self.remote_path(path))
+            .map_err(Error::from))
+    }
+
+    fn lock(&self, owner: &str) -> Result<()> {
+        self.with_sftp(|sftp| {
+            create_folders(sftp, &self.root)?;
+
+            let lock_path = self.root.join(LOCK_FILE);
+            let flags = ssh2::OpenFlags::WRITE | ssh2::OpenFlags::EXCLUSIVE;
+
+            let mut lock = match sftp.open_mode(&lock_path, flags, FILE_MODE, ssh2::OpenType::File) {
+                Ok(lock) => lock,
+                Err(error) => {
+                    //
+                    // Lock may be left by an instance, that crashed during
+                    // synchronization, such lock is broken after a while
+                    //
+
+                    let modified = sftp.stat(&lock_path)
+                        .map_err(|_| error)?
+                        .mtime
+                        .unwrap_or_default();
+
+                    if modified.saturating_add(STALE_LOCK_AGE) > unix_now() {
+                        let mut holder = String::new();
+                        if let Ok(mut lock) = sftp.open(&lock_path) {
+                            let _ = lock.read_to_string(&mut holder);
+                        }
+
+                        return Err(Error::from_message_with_extra(REMOTE_LOCKED, holder.trim())
+                            .with_kind(ErrorKind::Locked));
+                    }
+
+                    trace_debug!("breaking stale lock of remote");
+                    sftp.unlink(&lock_path)?;
+                    sftp.open_mode(&lock_path, flags, FILE_MODE, ssh2::OpenType::File)?
+                }
+            };
+
+            lock.write_all(owner.as_bytes())
+                .map_err(Error::from)
+        })
+    }
+
+    fn unlock(&self) -> Result<()> {
+        self.with_sftp(|sftp| sftp
+            .unlink(&self.root.join(LOCK_FILE))
+            .map_err(Error::from))
+    }
+}
+
+
+impl SftpTransport {
+    fn with_sftp<R, F>(&self, f: F) -> Result<R>
+    where
+        F: FnOnce(&ssh2::Sftp) -> Result<R>
+    {
+        let mut connection = self.connection.borrow_mut();
+        if connection.is_none() {
+            *connection = Some(self.connect()?);
+        }
+
+        let (_, sftp) = connection
+            .as_ref()
+            .expect("connection is established above");
+
+        let result = f(sftp);
+
+        //
+        // Connection is dropped after network failures, so that
+        // the next operation reconnects
+        //
+
+        if result.as_ref().is_err_and(|error| error.kind() == ErrorKind::Network) {
+            *connection = None;
+        }
+
+        result
+    }
+
+    #[cfg_attr(feature = "tracing", tracing::instrument(level = "debug", skip_all, fields(host = %self.host)))]
+    fn connect(&self) -> Result<(ssh2::Session, ssh2::Sftp)> {
+        let stream = std::net::TcpStream::connect((self.host.as_str(), self.port))
+            .map_err(|error| Error::from(error).with_kind(ErrorKind::Network))
+            .with_context(|| format!("connecting to {}:{}", self.host, self.port))?;
+
+        let mut session = ssh2::Session::new()?;
+        session.set_timeout(TIMEOUT_MS);
+        session.set_tcp_stream(stream);
+        session.handshake()?;
+
+        self.verify_host(&session)?;
+        self.authenticate(&session)?;
+
+        let sftp = session.sftp()?;
+        trace_debug!("connected to SFTP server");
+
+        Ok((session, sftp))
+    }
+
+    fn verify_host(&self, session: &ssh2::Session) -> Result<()> {
+        let (key, _) = session.host_key()
+            .ok_or_else(|| Error::from_message_with_extra(UNKNOWN_HOST_KEY, &self.host)
+                .with_kind(ErrorKind::Network))?;
+
+        let mut known_hosts = session.known_hosts()?;
+        if let Some(path) = self.known_hosts.as_deref().filter(|path| path.exists()) {
+            known_hosts.read_file(path, ssh2::KnownHostFileKind::OpenSSH)
+                .with_context(|| format!("reading {}", path.display()))?;
+        }
+
+        //
+        // Unknown hosts are never trusted implicitly: user adds them
+        // to known hosts, e.g. by connecting with ssh once
+        //
+
+        match known_hosts.check_port(&self.host, self.port, key) {
+            ssh2::CheckResult::Match => Ok(()),
+            ssh2::CheckResult::Mismatch => Err(Error::from_message_with_extra(HOST_KEY_MISMATCH, &self.host)
+                .with_kind(ErrorKind::Network)),
+            ssh2::CheckResult::NotFound | ssh2::CheckResult::Failure => Err(Error::from_message_with_extra(UNKNOWN_HOST_KEY, &self.host)
+                .with_kind(ErrorKind::Network)),
+        }
+    }
+
+    fn authenticate(&self, session: &ssh2::Session) -> Result<()> {
+        let result = match &self.private_key {
+            Some((path, passphrase)) => session.userauth_pubkey_file(&self.username, None, path, passphrase.as_deref()),
+            None => session.userauth_agent(&self.username)
+        };
+
+        match result {
+            Ok(_) if session.authenticated() => Ok(()),
+            Ok(_) => Err(Error::from_message_with_extra(SFTP_AUTH_FAILED, &self.username)
+                .with_kind(ErrorKind::Network)),
+            Err(error) => Err(Error::from_message_with_extra(SFTP_AUTH_FAILED, &self.username)
+                .with_kind(ErrorKind::Network)
+                .with_source(error))
+        }
+    }
+
+    fn remote_path(&self, path: &str) -> std::path::PathBuf {
+        path.split('/')
+            .fold(self.root.clone(), |remote_path, component| remote_path.join(component))
+    }
+}
+
+
+fn list_files(sftp: &ssh2::Sftp, folder: &std::path::Path, prefix: &str, files: &mut Vec<String>) -> Result<()> {
+    for (path, stat) in sftp.readdir(folder)? {
+        let name = path.file_name()
+            .unwrap_or_default()
+            .to_string_lossy()
+            .into_owned();
+
+        //
+        // Hidden files are lock and partially written ones
+        //
+
+        if name.starts_with('.') {
+            continue;
+        }
+
+        let relative = match prefix.is_empty() {
+            true => name,
+            false => format!("{}/{}", prefix, name)
+        };
+
+        match stat.is_dir() {
+            true => list_files(sftp, &path, &relative, files)?,
+            false => files.push(relative)
+        }
+    }
+
+    Ok(())
+}
+
+
+fn create_folders(sftp: &ssh2::Sftp, folder: &std::path::Path) -> Result<()> {
+    if folder.as_os_str().is_empty() || sftp.stat(folder).is_ok() {
+        return Ok(());
+    }
+
+    if let Some(parent) = folder.parent() {
+        create_folders(sftp, parent)?;
+    }
+
+    sftp.mkdir(folder, FOLDER_MODE)
+        .map_err(Error::from)
+}
+
+
+fn is_not_found(error: &ssh2::Error) -> bool {
+    error.code() == ssh2::ErrorCode::SFTP(SFTP_NO_SUCH_FILE)
+}
+
+
+fn local_username() -> Option<String> {
+    std::env::var("USER")
+        .or_else(|_| std::env::var("USERNAME"))
+        .ok()
+}
+
+
+fn unix_now() -> u64 {
+    std::time::SystemTime::now()
+        .duration_since(std::time::UNIX_EPOCH)
+        .map_or(0, |elapsed| elapsed.as_secs())
+}
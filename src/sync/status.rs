@@ -0,0 +1,31 @@
+use crate::datetime::Timestamp;
+
+
+/// State of synchronization, that is known without contacting a remote.
+#[derive(Clone, PartialEq, Eq, Debug, Default)]
+pub struct SyncStatus {
+    /// URL of associated remote (`None` if there is no remote)
+    pub remote: Option<String>,
+
+    /// Number of local commits, that remote does not have yet
+    /// (as of the latest exchange with it)
+    pub ahead: usize,
+
+    /// Number of remote commits, that are not merged locally yet
+    /// (as of the latest exchange with it)
+    pub behind: usize,
+
+    /// Number of local changes, that are not exported yet
+    pub pending_changes: usize,
+
+    /// Time of the latest successful synchronization (if any)
+    pub last_sync: Option<Timestamp>,
+}
+
+
+impl SyncStatus {
+    /// Checks if a remote is associated.
+    pub fn has_remote(&self) -> bool {
+        self.remote.is_some()
+    }
+}
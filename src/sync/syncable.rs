@@ -10,6 +10,18 @@ pub trait Syncable {
     /// Type of instance identifier representation.
     type InstanceId : std::fmt::Display;
 
+    /// Type of a kind of synchronized entities.
+    type Scope : Copy + std::fmt::Display;
+
+    /// Returns kinds of entities, that participate in synchronization.
+    /// 
+    /// Local changes are collected for each listed kind separately.
+    /// Merging of remote changes is not driven by this list.
+    fn scopes(&self) -> Vec<Self::Scope>;
+
+    /// Returns number of local changes, that are not exported yet.
+    fn pending_changes(&self) -> Result<usize>;
+
     /// Merges remote changelog and exports the local one.
     ///
     /// * `timestamp_rw` - last synchronization time (the function overwrites
@@ -18,12 +30,48 @@ pub trait Syncable {
     ///                        overwrites this value after preforming synchronization)
     /// * `changelog_rw` - full changelog to merge (the function appends local changelog
     ///                    to this value after preforming synchronization)
-    /// * `last_sync` - last synchronization timestamp
+    /// * `state_snapshot_rw` - snapshot of full state for instances, that have never been synchronized
+    /// * `last_sync` - last synchronization timestamp, [`None`] if remote changes are merged from diffs (see [`Syncable::merge_diff`]) and the changelog is only exported
     /// * `context` - user-provided context
-    fn merge_and_export_changes<Ts, Li, Cl>(&self, timestamp_rw: &mut Ts, last_instance_rw: &mut Li,
-        changelog_rw: &mut Cl, last_sync: &Timestamp, context: &Self::Context) -> Result<()>
+    fn merge_and_export_changes<Ts, Li, Cl, Ss>(&self, timestamp_rw: &mut Ts, last_instance_rw: &mut Li,
+        changelog_rw: &mut Cl, state_snapshot_rw: &mut Ss, last_sync: Option<&Timestamp>, context: &Self::Context) -> Result<()>
     where
         Ts: std::io::Read + std::io::Write + std::io::Seek,
         Li: std::io::Read + std::io::Write + std::io::Seek,
+        Cl: std::io::Read + std::io::Write + std::io::Seek,
+        Ss: std::io::Read + std::io::Write + std::io::Seek;
+
+    /// Exports local changes, that are not exported yet, as a diff. Unlike
+    /// changelog, a diff holds changes of a single synchronization only.
+    /// Returns `false` and writes nothing if there are no such changes.
+    /// Changes remain unexported until the changelog is exported.
+    ///
+    /// * `diff_w` - diff to write
+    /// * `context` - user-provided context
+    fn export_diff<W: std::io::Write>(&self, diff_w: &mut W, context: &Self::Context) -> Result<bool>;
+
+    /// Merges a diff of another instance (see [`Syncable::export_diff`]).
+    /// Diffs are tracked by acknowledgements, hence changes are merged
+    /// regardless of their timestamps. Local changes must be exported
+    /// before, since changes made by merging are never exported.
+    ///
+    /// * `diff_r` - diff to merge
+    /// * `context` - user-provided context
+    fn merge_diff<R: std::io::Read>(&self, diff_r: &mut R, context: &Self::Context) -> Result<()>;
+
+    /// Joins a changelog of a diverged history (e.g. recovered from 
+    /// a damaged repository) into the current one, so that changes
+    /// exported only there are not lost. Local data is not changed.
+    ///
+    /// * `timestamp_r` - last synchronization time of the current changelog
+    /// * `last_instance_r` - last synchronized instance of the current changelog
+    /// * `changelog_rw` - current changelog (the function overwrites it with the union)
+    /// * `joined` - last synchronization time, last synchronized instance and changelog of the diverged history
+    /// * `context` - user-provided context
+    fn join_changelog<Ts, Li, Cl>(&self, timestamp_r: &mut Ts, last_instance_r: &mut Li, changelog_rw: &mut Cl,
+        joined: (&[u8], &[u8], &[u8]), context: &Self::Context) -> Result<()>
+    where
+        Ts: std::io::Read + std::io::Seek,
+        Li: std::io::Read + std::io::Seek,
         Cl: std::io::Read + std::io::Write + std::io::Seek;
 }
@@ -0,0 +1,44 @@
+use crate::error::Result;
+
+
+/// Storage of synchronization files on a remote without version
+/// control, e.g. a folder on an SSH server.
+///
+/// Files are addressed by relative paths with `/` separators. Transport
+/// does not interpret contents of files: they are encrypted by instances,
+/// that write them. See [`super::TransportSyncEngine`] for an engine, that
+/// synchronizes through a transport.
+pub trait SyncTransport {
+    /// Returns identifier of remote (e.g. URL), that is shown to a user.
+    fn remote(&self) -> String;
+
+    /// Lists paths of all synchronization files on remote.
+    fn list(&self) -> Result<Vec<String>>;
+
+    /// Reads a file.
+    ///
+    /// * `path` - path of the file
+    fn read(&self, path: &str) -> Result<Vec<u8>>;
+
+    /// Writes a file, an existing one is replaced. Missing
+    /// folders are created.
+    ///
+    /// * `path` - path of the file
+    /// * `content` - content of the file
+    fn write(&self, path: &str, content: &[u8]) -> Result<()>;
+
+    /// Removes a file.
+    ///
+    /// * `path` - path of the file
+    fn remove(&self, path: &str) -> Result<()>;
+
+    /// Acquires exclusive lock of remote, so that instances do not
+    /// exchange files simultaneously. Fails with [`crate::error::ErrorKind::Locked`]
+    /// if another instance holds the lock.
+    ///
+    /// * `owner` - identifier of the instance, that acquires the lock
+    fn lock(&self, owner: &str) -> Result<()>;
+
+    /// Releases lock acquired with [`SyncTransport::lock`].
+    fn unlock(&self) -> Result<()>;
+}
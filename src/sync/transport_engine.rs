@@ -0,0 +1,339 @@
+use std::collections::HashMap;
+
+use crate::location::{Location, LocationLock, create_private_dir};
+use crate::error::{Result, Error, ErrorKind, Context};
+use crate::cancel::CancellationToken;
+use crate::trace::trace_debug;
+use crate::progress::{Progress, Phase};
+use crate::redact::redact_url;
+use super::engine::SyncEngine;
+use super::syncable::Syncable;
+use super::status::SyncStatus;
+use super::transport::SyncTransport;
+use super::exchange::{self, TIMESTAMP_FILE, LAST_INSTANCE_FILE, CHANGELOG_FILE};
+use super::{REMOTE_IS_FIXED, MALFORMED_REMOTE_PATH, MALFORMED_REMOTE_BUNDLE};
+
+
+/// Folder with local copy of remote files.
+const MIRROR_FOLDER: &str = "mirror";
+
+/// Remote file, that bundles files of changelog.
+const BUNDLE_FILE: &str = "bundle";
+
+/// Files of changelog, that are encrypted together and hence must
+/// be replaced at once.
+const BUNDLED_FILES: [&str; 3] = [TIMESTAMP_FILE, LAST_INSTANCE_FILE, CHANGELOG_FILE];
+
+
+/// Synchronization engine, that exchanges files through a [`SyncTransport`].
+///
+/// Files of remote are downloaded into a local mirror, changes are
+/// exchanged the same way as with git, then changed files are uploaded
+/// back. There is no version control to detect conflicting updates,
+/// hence remote is locked during synchronization. Files of changelog
+/// depend on each other, so they are stored remotely as a single file.
+/// If uploading fails, local changelog is kept aside and joined on the
+/// next synchronization.
+pub struct TransportSyncEngine<T> {
+    /// Transport to remote
+    transport: T,
+
+    /// Path to local copy of remote files
+    mirror_path: std::path::PathBuf,
+
+    /// Path to last sync timestamp file
+    last_sync_path: std::path::PathBuf,
+
+    /// Path to folder with changelog to join (see [`exchange::keep_aside`])
+    pending_path: std::path::PathBuf,
+
+    /// Lock of location, that prevents concurrent synchronization from other processes
+    _lock: LocationLock,
+}
+
+
+impl<T: SyncTransport> TransportSyncEngine<T> {
+    /// Creates an engine. Local synchronization files are created
+    /// if they are absent.
+    ///
+    /// * `loc` - storage location provider
+    /// * `transport` - transport to remote
+    pub fn open<L: Location>(loc: &L, transport: T) -> Result<Self> {
+        let lock = LocationLock::acquire(loc)?;
+
+        loc.create_if_absent()?;
+        create_private_dir(exchange::sync_folder(loc))?;
+
+        let last_sync_path = exchange::last_sync_path(loc);
+        exchange::init_last_sync(&last_sync_path)?;
+
+        Ok(TransportSyncEngine {
+            transport,
+            mirror_path: exchange::sync_folder(loc).join(MIRROR_FOLDER),
+            last_sync_path,
+            pending_path: exchange::pending_path(loc),
+            _lock: lock
+        })
+    }
+
+    /// Obtain transport to remote.
+    pub fn transport(&self) -> &T {
+        &self.transport
+    }
+}
+
+
+impl<T: SyncTransport> SyncEngine for TransportSyncEngine<T> {
+    #[cfg_attr(feature = "tracing", tracing::instrument(level = "debug", skip_all, fields(instance = %current_instance)))]
+    fn perform_sync<S: Syncable>(&self, current_instance: &S::InstanceId, syncable: &S, context: &S::Context,
+        cancel: &CancellationToken, progress: &dyn Progress) -> Result<()>
+    {
+        cancel.check()?;
+        self.transport
+            .lock(&current_instance.to_string())?;
+
+        let result = self.exchange(current_instance, syncable, context, cancel, progress);
+
+        //
+        // Lock is released even if synchronization failed,
+        // but its error is more important
+        //
+
+        let unlocked = self.transport.unlock();
+        result.and(unlocked)
+    }
+
+    fn status<S: Syncable>(&self, syncable: &S) -> Result<SyncStatus> {
+        Ok(SyncStatus {
+            remote: Some(self.transport.remote()),
+            pending_changes: syncable.pending_changes()?,
+            last_sync: exchange::last_sync(&self.last_sync_path)?,
+            ..Default::default()
+        })
+    }
+
+    fn add_remote(&self, remote: &str) -> Result<()> {
+        Err(Error::from_message_with_extra(REMOTE_IS_FIXED, redact_url(remote)).with_kind(ErrorKind::InvalidInput))
+    }
+
+    fn remove_remote(&self) -> Result<()> {
+        Err(Error::from_message(REMOTE_IS_FIXED).with_kind(ErrorKind::InvalidInput))
+    }
+
+    fn change_remote(&self, remote: &str) -> Result<()> {
+        Err(Error::from_message_with_extra(REMOTE_IS_FIXED, redact_url(remote)).with_kind(ErrorKind::InvalidInput))
+    }
+}
+
+
+impl<T: SyncTransport> TransportSyncEngine<T> {
+    fn exchange<S: Syncable>(&self, current_instance: &S::InstanceId, syncable: &S, context: &S::Context,
+        cancel: &CancellationToken, progress: &dyn Progress) -> Result<()>
+    {
+        //
+        // Cancellation is impossible after download: merged
+        // changes must be uploaded
+        //
+
+        let remote_files = self.download(cancel, progress)?;
+        cancel.check()?;
+
+        let result = exchange::exchange_changes(&self.mirror_path, &self.last_sync_path, 
+            Some(&self.pending_path), current_instance, syncable, context, progress)
+            .and_then(|_| self.upload(&remote_files, progress));
+
+        //
+        // Local changes may be exported already, so they are kept
+        // aside until they reach remote, otherwise the next download
+        // would overwrite them
+        //
+
+        if let Err(error) = result {
+            trace_debug!("synchronization failed, keeping changelog aside");
+            exchange::keep_aside(&self.mirror_path, &self.pending_path)?;
+
+            return Err(error);
+        }
+
+        if self.pending_path.exists() {
+            std::fs::remove_dir_all(&self.pending_path)?;
+        }
+
+        Ok(())
+    }
+
+    #[cfg_attr(feature = "tracing", tracing::instrument(level = "debug", skip_all))]
+    fn download(&self, cancel: &CancellationToken, progress: &dyn Progress) -> Result<HashMap<String, Vec<u8>>> {
+        let paths = self.transport.list()
+            .with_context(|| format!("listing {}", self.transport.remote()))?;
+
+        //
+        // Mirror is replaced with remote's version completely
+        //
+
+        if self.mirror_path.exists() {
+            std::fs::remove_dir_all(&self.mirror_path)?;
+        }
+
+        create_private_dir(&self.mirror_path)?;
+
+        let mut remote_files = HashMap::new();
+        for (done, path) in paths.iter().enumerate() {
+            progress.report(Phase::Fetch, done, paths.len());
+            cancel.check()?;
+
+            let content = self.transport.read(path)
+                .with_context(|| format!("reading {}", path))?;
+
+            match path.as_str() {
+                BUNDLE_FILE => self.unbundle(&content)?,
+                path => {
+                    let local_path = self.local_path(path)?;
+                    if let Some(parent) = local_path.parent() {
+                        std::fs::create_dir_all(parent)?;
+                    }
+
+                    std::fs::write(&local_path, &content)?;
+                }
+            }
+
+            remote_files.insert(path.clone(), content);
+        }
+
+        progress.report(Phase::Fetch, paths.len(), paths.len());
+        trace_debug!(files = paths.len(), "remote files downloaded");
+
+        Ok(remote_files)
+    }
+
+    #[cfg_attr(feature = "tracing", tracing::instrument(level = "debug", skip_all))]
+    fn upload(&self, remote_files: &HashMap<String, Vec<u8>>, progress: &dyn Progress) -> Result<()> {
+        let mut local_files = Vec::new();
+        list_files(&self.mirror_path, "", &mut local_files)?;
+
+        local_files.retain(|path| !BUNDLED_FILES.contains(&path.as_str()));
+
+        let mut local_contents = Vec::new();
+        for path in local_files {
+            let content = std::fs::read(self.local_path(&path)?)?;
+            local_contents.push((path, content));
+        }
+
+        //
+        // Bundle is written last, so that it never refers to diffs,
+        // that are missing on remote
+        //
+
+        local_contents.push((BUNDLE_FILE.to_owned(), self.bundle()?));
+
+        let removed: Vec<&String> = remote_files
+            .keys()
+            .filter(|path| !local_contents.iter().any(|(local_path, _)| local_path == *path))
+            .collect();
+
+        let changed: Vec<_> = local_contents
+            .iter()
+            .filter(|(path, content)| remote_files.get(path) != Some(content))
+            .collect();
+
+        //
+        // Files are written before stale ones are removed,
+        // so remote never misses changes of an instance
+        //
+
+        let total = changed.len() + removed.len();
+        for (done, (path, content)) in changed.iter().enumerate() {
+            progress.report(Phase::Push, done, total);
+            self.transport.write(path, content)
+                .with_context(|| format!("writing {}", path))?;
+        }
+
+        for (done, path) in removed.iter().enumerate() {
+            progress.report(Phase::Push, changed.len() + done, total);
+            self.transport.remove(path)
+                .with_context(|| format!("removing {}", path))?;
+        }
+
+        progress.report(Phase::Push, total, total);
+        trace_debug!(changed = changed.len(), removed = removed.len(), "local files uploaded");
+
+        Ok(())
+    }
+
+    fn bundle(&self) -> Result<Vec<u8>> {
+        let mut bundle = Vec::new();
+
+        for file in BUNDLED_FILES {
+            let content = std::fs::read(self.mirror_path.join(file))?;
+
+            bundle.extend_from_slice(&(content.len() as u64).to_le_bytes());
+            bundle.extend_from_slice(&content);
+        }
+
+        Ok(bundle)
+    }
+
+    fn unbundle(&self, mut bundle: &[u8]) -> Result<()> {
+        let malformed = || Error::from_message_with_extra(MALFORMED_REMOTE_BUNDLE, BUNDLE_FILE)
+            .with_kind(ErrorKind::Corruption);
+
+        for file in BUNDLED_FILES {
+            let (length, rest) = bundle.split_first_chunk::<8>()
+                .ok_or_else(malformed)?;
+
+            let length = usize::try_from(u64::from_le_bytes(*length))
+                .map_err(|_| malformed())?;
+
+            if rest.len() < length {
+                return Err(malformed());
+            }
+
+            let (content, rest) = rest.split_at(length);
+            std::fs::write(self.mirror_path.join(file), content)?;
+
+            bundle = rest;
+        }
+
+        Ok(())
+    }
+
+    fn local_path(&self, path: &str) -> Result<std::path::PathBuf> {
+        //
+        // Paths come from remote, so they must not lead out of mirror
+        //
+
+        let is_valid = |component: &str| !component.is_empty() && component != "." &&
+            component != ".." && !component.contains('\\');
+
+        if !path.split('/').all(is_valid) {
+            return Err(Error::from_message_with_extra(MALFORMED_REMOTE_PATH, path)
+                .with_kind(ErrorKind::Corruption));
+        }
+
+        Ok(path
+            .split('/')
+            .fold(self.mirror_path.clone(), |local_path, component| local_path.join(component)))
+    }
+}
+
+
+fn list_files(folder: &std::path::Path, prefix: &str, files: &mut Vec<String>) -> Result<()> {
+    for entry in std::fs::read_dir(folder)? {
+        let entry = entry?;
+        let name = entry.file_name()
+            .to_string_lossy()
+            .into_owned();
+
+        let path = match prefix.is_empty() {
+            true => name,
+            false => format!("{}/{}", prefix, name)
+        };
+
+        match entry.file_type()?.is_dir() {
+            true => list_files(&entry.path(), &path, files)?,
+            false => files.push(path)
+        }
+    }
+
+    Ok(())
+}
@@ -0,0 +1,340 @@
+use chrono::{Datelike, Months};
+use rand::{Rng, SeedableRng};
+use rand::rngs::StdRng;
+
+use crate::core::{Budget, ConfigLoader, ConfigOverrides, InstanceId};
+use crate::crypto::{CryptoEngine, PassphraseCryptoEngine, KeyId};
+use crate::datetime::Timestamp;
+use crate::error::Result;
+use crate::storage::{DataStorage, MemoryStorage, Account, Category, Transaction, CategoryType, TransactionKind};
+use crate::storage::{Id, MetaInfo, CustomFields};
+use crate::sync::{SyncEngine, OfflineSyncEngine};
+
+
+/// Key identifier of fixture budgets.
+const FIXTURE_KEY_ID: &str = "fixture";
+
+/// Passphrase of fixture budgets.
+const FIXTURE_PASSPHRASE: &[u8] = b"fixture";
+
+/// Names of generated accounts.
+const ACCOUNT_NAMES: &[&str] = &["Checking", "Savings", "Cash", "Credit card", "Brokerage", "Travel fund"];
+
+/// Generated income categories (see [`CategoryTemplate`]).
+const INCOME_CATEGORIES: &[CategoryTemplate] = &[
+    ("Salary", 350_000, 1, 0.0, 1.0, &["Employer"]),
+    ("Bonus", 200_000, 12, 0.0, 0.15, &["Employer"]),
+    ("Interest", 1_500, 1, 0.0, 1.0, &["Bank"]),
+];
+
+/// Generated outcome categories (see [`CategoryTemplate`]).
+const OUTCOME_CATEGORIES: &[CategoryTemplate] = &[
+    ("Groceries", 6_000, 12, 0.15, 1.0, &["Supermarket", "Farmers market", "Bakery"]),
+    ("Utilities", 12_000, 1, 0.4, 1.0, &["Electricity", "Heating", "Water"]),
+    ("Transport", 3_000, 3, 0.1, 1.0, &["Fuel", "Metro", "Taxi"]),
+    ("Dining out", 4_500, 7, 0.25, 1.0, &["Cafe", "Restaurant", "Pizzeria"]),
+    ("Travel", 60_000, 7, 0.9, 0.3, &["Airline", "Hotel", "Car rental"]),
+    ("Gifts", 15_000, 12, 0.9, 0.4, &["Gift shop", "Bookstore"]),
+];
+
+
+/// Template of a generated category: name, base amount of a
+/// transaction, month of the peak (1-based), seasonal amplitude,
+/// probability of a transaction and descriptions of transactions.
+type CategoryTemplate = (&'static str, isize, u32, f64, f64, &'static [&'static str]);
+
+
+/// Budget, that lives in memory entirely.
+pub type MemoryBudget = Budget<PassphraseCryptoEngine, OfflineSyncEngine, MemoryStorage>;
+
+
+/// Creates an initialized in-memory budget.
+///
+/// Instance identifier and key are derived from the seed, hence
+/// budgets created with the same seed are identical.
+///
+/// * `seed` - seed to derive instance identifier and key from
+pub fn memory_budget(seed: u64) -> Result<MemoryBudget> {
+    let mut rng = StdRng::seed_from_u64(seed);
+
+    let key_id: <PassphraseCryptoEngine as CryptoEngine>::KeyId = KeyId::new(FIXTURE_KEY_ID);
+    let salt: [u8; 16] = rng.gen();
+    let crypto_engine = PassphraseCryptoEngine::new(&key_id, FIXTURE_PASSPHRASE, &salt)?;
+
+    let config = ConfigLoader::new()
+        .overrides(ConfigOverrides {
+            key_id: Some(FIXTURE_KEY_ID.to_owned()),
+            instance_id: Some(InstanceId::from_bytes(rng.gen())),
+            ..Default::default()
+        })
+        .load()?;
+
+    let budget = Budget::new(crypto_engine, OfflineSyncEngine::new(), MemoryStorage::new(), config)?;
+    budget.initialize()?;
+
+    Ok(budget)
+}
+
+
+/// Items created by [`BudgetGenerator`].
+#[derive(Clone, Debug)]
+pub struct GeneratedBudget {
+    /// Identifiers of generated accounts
+    pub accounts: Vec<Id>,
+
+    /// Identifiers of generated income categories
+    pub income_categories: Vec<Id>,
+
+    /// Identifiers of generated outcome categories
+    pub outcome_categories: Vec<Id>,
+
+    /// Number of generated transactions (without opening balances)
+    pub transactions: usize,
+}
+
+
+/// Generator of realistic budgets for tests, demos and benchmarks.
+///
+/// Generates accounts with opening balances, income and outcome
+/// categories and several months of transactions. Spendings have
+/// seasonality, e.g. utilities peak in winter and travel in summer.
+/// All values (including identifiers) are produced by a seeded
+/// random generator, so the same seed and parameters always give
+/// the same data.
+#[derive(Clone, Debug)]
+pub struct BudgetGenerator {
+    /// Seed of random generator
+    seed: u64,
+
+    /// Number of accounts to generate
+    accounts: usize,
+
+    /// Number of months to generate transactions for
+    months: u32,
+
+    /// Number of spendings per month
+    spendings_per_month: usize,
+
+    /// Beginning of the first month
+    start: Timestamp,
+}
+
+
+impl BudgetGenerator {
+    /// Creates a generator with default parameters: 2 accounts and
+    /// 12 months of transactions starting in January 2023.
+    ///
+    /// * `seed` - seed of random generator
+    pub fn new(seed: u64) -> Self {
+        BudgetGenerator {
+            seed,
+            accounts: 2,
+            months: 12,
+            spendings_per_month: 30,
+            start: Timestamp::from_timestamp(1_672_531_200, 0)
+                .expect("January 2023 is a valid timestamp")
+        }
+    }
+
+    /// Sets number of accounts to generate.
+    ///
+    /// * `accounts` - number of accounts (at least one is generated)
+    pub fn accounts(mut self, accounts: usize) -> Self {
+        self.accounts = accounts.max(1);
+        self
+    }
+
+    /// Sets number of months to generate transactions for.
+    ///
+    /// * `months` - number of months
+    pub fn months(mut self, months: u32) -> Self {
+        self.months = months;
+        self
+    }
+
+    /// Sets average number of spendings per month.
+    ///
+    /// * `spendings` - number of spendings
+    pub fn spendings_per_month(mut self, spendings: usize) -> Self {
+        self.spendings_per_month = spendings;
+        self
+    }
+
+    /// Sets beginning of the first month.
+    ///
+    /// * `start` - first month's beginning
+    pub fn start(mut self, start: Timestamp) -> Self {
+        self.start = start;
+        self
+    }
+
+    /// Generates items and adds them into a budget.
+    ///
+    /// Budget is expected to be initialized, but it may contain
+    /// other items.
+    ///
+    /// * `budget` - budget to generate items in
+    pub fn generate<Ce, Se, St>(&self, budget: &Budget<Ce, Se, St>) -> Result<GeneratedBudget>
+    where
+        Ce: CryptoEngine,
+        Se: SyncEngine,
+        St: DataStorage
+    {
+        let mut rng = StdRng::seed_from_u64(self.seed);
+
+        let accounts = (0..self.accounts)
+            .map(|index| self.generate_account(budget, &mut rng, index))
+            .collect::<Result<Vec<_>>>()?;
+
+        let income_categories = INCOME_CATEGORIES.iter()
+            .map(|template| self.generate_category(budget, &mut rng, template.0, CategoryType::Income))
+            .collect::<Result<Vec<_>>>()?;
+
+        let outcome_categories = OUTCOME_CATEGORIES.iter()
+            .map(|template| self.generate_category(budget, &mut rng, template.0, CategoryType::Outcome))
+            .collect::<Result<Vec<_>>>()?;
+
+        //
+        // Incomes always go to the first account, spendings
+        // are made from any of them
+        //
+
+        let mut transactions = 0;
+
+        for month in 0..self.months {
+            let month_start = self.month_start(month);
+
+            for (template, category) in INCOME_CATEGORIES.iter().zip(&income_categories) {
+                if rng.gen_bool(template.4) {
+                    let amount = Self::seasonal_amount(&mut rng, template, month_start.month());
+                    self.generate_transaction(budget, &mut rng, month_start, accounts[0], *category, amount, template)?;
+                    transactions += 1;
+                }
+            }
+
+            for _ in 0..self.spendings_per_month {
+                let index = rng.gen_range(0..OUTCOME_CATEGORIES.len());
+                let template = &OUTCOME_CATEGORIES[index];
+
+                if !rng.gen_bool(template.4) {
+                    continue;
+                }
+
+                let account = accounts[rng.gen_range(0..accounts.len())];
+                let amount = -Self::seasonal_amount(&mut rng, template, month_start.month());
+
+                self.generate_transaction(budget, &mut rng, month_start, account, outcome_categories[index], amount, template)?;
+                transactions += 1;
+            }
+        }
+
+        Ok(GeneratedBudget {
+            accounts,
+            income_categories,
+            outcome_categories,
+            transactions
+        })
+    }
+}
+
+
+impl BudgetGenerator {
+    fn generate_account<Ce, Se, St>(&self, budget: &Budget<Ce, Se, St>, rng: &mut StdRng, index: usize) -> Result<Id>
+    where
+        Ce: CryptoEngine,
+        Se: SyncEngine,
+        St: DataStorage
+    {
+        let id = Self::generate_id(rng);
+        let name = match ACCOUNT_NAMES.get(index) {
+            Some(name) => name.to_string(),
+            None => format!("Account #{}", index + 1)
+        };
+
+        budget.add_account(&Account {
+            id: Some(id),
+            name,
+            balance: 0,
+            initial_balance: 0,
+            note: String::new(),
+            custom_fields: CustomFields::new(),
+            meta_info: MetaInfo::new(Some(self.start), None, None)
+        })?;
+
+        budget.set_opening_balance(id, rng.gen_range(0..1_000_000), self.start)?;
+        Ok(id)
+    }
+
+    fn generate_category<Ce, Se, St>(&self, budget: &Budget<Ce, Se, St>, rng: &mut StdRng, name: &str,
+        category_type: CategoryType) -> Result<Id>
+    where
+        Ce: CryptoEngine,
+        Se: SyncEngine,
+        St: DataStorage
+    {
+        let id = Self::generate_id(rng);
+
+        budget.add_category(&Category {
+            id: Some(id),
+            name: name.to_owned(),
+            category_type,
+            note: String::new(),
+            custom_fields: CustomFields::new(),
+            meta_info: MetaInfo::new(Some(self.start), None, None)
+        })?;
+
+        Ok(id)
+    }
+
+    #[allow(clippy::too_many_arguments)]
+    fn generate_transaction<Ce, Se, St>(&self, budget: &Budget<Ce, Se, St>, rng: &mut StdRng, month_start: Timestamp,
+        account: Id, category: Id, amount: isize, template: &CategoryTemplate) -> Result<()>
+    where
+        Ce: CryptoEngine,
+        Se: SyncEngine,
+        St: DataStorage
+    {
+        let timestamp = month_start + chrono::Duration::seconds(rng.gen_range(0..28 * 24 * 60 * 60));
+        let descriptions = template.5;
+
+        budget.add_transaction(&Transaction {
+            id: Some(Self::generate_id(rng)),
+            timestamp,
+            description: descriptions[rng.gen_range(0..descriptions.len())].to_owned(),
+            account_id: account,
+            category_id: category,
+            amount,
+            kind: TransactionKind::Regular,
+            note: String::new(),
+            custom_fields: CustomFields::new(),
+            spender_id: None,
+            meta_info: MetaInfo::new(Some(timestamp), None, None)
+        })
+    }
+
+    fn month_start(&self, month: u32) -> Timestamp {
+        self.start
+            .checked_add_months(Months::new(month))
+            .expect("Generated dates MUST be representable")
+    }
+
+    fn seasonal_amount(rng: &mut StdRng, template: &CategoryTemplate, month: u32) -> isize {
+        //
+        // Amount follows a cosine wave with the maximum in the peak
+        // month, plus some noise
+        //
+
+        let (_, base, peak, amplitude, _, _) = *template;
+        let phase = 2.0 * std::f64::consts::PI * (month as f64 - peak as f64) / 12.0;
+        let season = 1.0 + amplitude * phase.cos();
+        let noise = rng.gen_range(0.7..1.3);
+
+        (base as f64 * season * noise).round() as isize
+    }
+
+    fn generate_id(rng: &mut StdRng) -> Id {
+        uuid::Builder::from_random_bytes(rng.gen())
+            .into_uuid()
+            .into_bytes()
+    }
+}
@@ -0,0 +1,20 @@
+//
+// Diagnostic events, that are emitted only if `tracing` feature is on.
+//
+// Events MUST NOT contain sensitive values: amounts, descriptions,
+// names, keys, passphrases and plaintexts. Identifiers, counts,
+// sizes and kinds of operations are fine. Types marked with
+// `Redacted` print no sensitive values in debug form.
+//
+
+
+/// Emits a debug-level diagnostic event.
+macro_rules! trace_debug {
+    ($($arg:tt)*) => {
+        #[cfg(feature = "tracing")]
+        tracing::debug!($($arg)*);
+    };
+}
+
+
+pub(crate) use trace_debug;